@@ -6,8 +6,12 @@ use criterion::criterion_group;
 use criterion::criterion_main;
 use criterion::BenchmarkId;
 use criterion::Criterion;
+use ministark::hash::Blake3HashFn;
+use ministark::hash::CanonicalLeafEncoder;
 use ministark::hash::ElementHashFn;
 use ministark::hash::Sha256HashFn;
+use ministark::matrix::MatrixLayout;
+use ministark::merkle::hash_rows_with_layout;
 use ministark::merkle::MatrixMerkleTree;
 use ministark::merkle::MatrixMerkleTreeImpl;
 use ministark::utils::GpuAllocator;
@@ -36,11 +40,19 @@ fn build_merkle_tree_bench<F: GpuField + Field, H: ElementHashFn<F>>(
         group.bench_with_input(BenchmarkId::new("from_matrix", n), &n, |b, _| {
             b.iter(|| MatrixMerkleTreeImpl::<H>::from_matrix(&matrix))
         });
+
+        for layout in [MatrixLayout::ColumnMajor, MatrixLayout::RowMajor] {
+            let id = BenchmarkId::new(format!("hash_rows/{layout:?}"), n);
+            group.bench_with_input(id, &n, |b, _| {
+                b.iter(|| hash_rows_with_layout::<F, H, CanonicalLeafEncoder>(&matrix, layout))
+            });
+        }
     }
 }
 
 fn build_merkle_tree_benches(c: &mut Criterion) {
     build_merkle_tree_bench::<Fp, Sha256HashFn>(c, "Sha256");
+    build_merkle_tree_bench::<Fp, Blake3HashFn>(c, "Blake3");
 }
 
 criterion_group!(benches, build_merkle_tree_benches);
@@ -1,33 +1,33 @@
+use crate::fields::BrainfuckField;
 use crate::tables;
 use crate::tables::Challenge;
 use crate::tables::EvaluationArgumentHint;
+use crate::tables::FinalStateHint;
 use crate::vm::compile;
 use crate::BrainfuckClaim;
+use ark_ff::FftField;
 use ark_ff::Field;
-use ark_ff::One;
 use ark_ff::Zero;
-use ark_poly::EvaluationDomain;
-use ark_poly::Radix2EvaluationDomain;
+use core::marker::PhantomData;
 use ministark::air::AirConfig;
 use ministark::challenges::Challenges;
-use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Assertion;
 use ministark::constraints::Constraint;
 use ministark::constraints::Hint;
 use ministark::constraints::VerifierChallenge;
 use ministark::hints::Hints;
 use ministark::utils::FieldVariant;
-use ministark_gpu::fields::p18446744069414584321::ark::Fp;
-use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
-use num_traits::Pow;
+use ministark::StarkExtensionOf;
+use ministark_gpu::GpuFftField;
 
-pub struct BrainfuckAirConfig;
+pub struct BrainfuckAirConfig<F>(PhantomData<F>);
 
-impl AirConfig for BrainfuckAirConfig {
+impl<F: BrainfuckField> AirConfig for BrainfuckAirConfig<F> {
     const NUM_BASE_COLUMNS: usize = 17;
     const NUM_EXTENSION_COLUMNS: usize = 9;
 
-    type Fp = Fp;
-    type Fq = Fq3;
+    type Fp = F::Fp;
+    type Fq = F::Fq;
     type PublicInputs = BrainfuckClaim;
 
     fn gen_hints(
@@ -41,30 +41,50 @@ impl AirConfig for BrainfuckAirConfig {
             source_code,
             input,
             output,
+            final_state,
         } = execution_info;
 
         let (input_eval_arg, input_eval_offset) =
             io_terminal_helper(input, challenges[Gamma.index()], trace_len);
         let (output_eval_arg, output_eval_offset) =
             io_terminal_helper(output, challenges[Delta.index()], trace_len);
-        let instruction_eval_arg = compute_instruction_evaluation_argument(source_code, challenges);
+        let instruction_eval_arg =
+            compute_instruction_evaluation_argument::<Self::Fp, Self::Fq>(source_code, challenges);
 
+        let is_bounded = Self::Fq::from(final_state.is_some());
+        let final_state = final_state.unwrap_or_default();
         Hints::new(vec![
             (Instruction.index(), instruction_eval_arg),
             (Input.index(), input_eval_arg),
             (InputOffset.index(), input_eval_offset),
             (Output.index(), output_eval_arg),
             (OutputOffset.index(), output_eval_offset),
+            (FinalStateHint::IsBounded.index(), is_bounded),
+            (
+                FinalStateHint::Cycle.index(),
+                Self::Fq::from(final_state.cycle),
+            ),
+            (FinalStateHint::Ip.index(), Self::Fq::from(final_state.ip)),
+            (FinalStateHint::Mp.index(), Self::Fq::from(final_state.mp)),
+            (
+                FinalStateHint::MemVal.index(),
+                Self::Fq::from(final_state.mem_val),
+            ),
         ])
     }
 
-    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Self::Fp, Self::Fq>>> {
-        use AlgebraicItem::*;
-        let one = Constant(FieldVariant::<Fp, Fq3>::Fp(Fp::one()));
-        let trace_xs = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
-        let first_trace_x = Constant(FieldVariant::<Fp, Fq3>::Fp(trace_xs.element(0)));
-        let last_trace_x = Constant(FieldVariant::Fp(trace_xs.element(trace_len - 1)));
+    fn assertions(_trace_len: usize) -> Vec<Assertion<FieldVariant<Self::Fp, Self::Fq>>> {
+        [
+            tables::InstructionExtensionColumn::assertions(),
+            tables::InputExtensionColumn::assertions(),
+            tables::OutputExtensionColumn::assertions(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
 
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Self::Fp, Self::Fq>>> {
         let transition_constraints = [
             tables::ProcessorBaseColumn::transition_constraints(),
             tables::ProcessorExtensionColumn::transition_constraints(),
@@ -77,13 +97,7 @@ impl AirConfig for BrainfuckAirConfig {
         ]
         .into_iter()
         .flatten()
-        .map(|constraint| {
-            // ensure constraints hold in all rows except the last
-            // multiply by `(x - t_(n-1))` to remove the last term
-            // NOTE: `x^trace_len - 1 = (x - t_0)(x - t_1)...(x - t_(n-1))`
-            // NOTE: `t^(n-1) = t^(-1)`
-            constraint * ((X - last_trace_x) / (X.pow(trace_len) - one))
-        });
+        .map(|constraint| Constraint::when_transition(trace_len, constraint));
 
         let boundary_constraints = [
             tables::ProcessorBaseColumn::boundary_constraints(),
@@ -96,37 +110,24 @@ impl AirConfig for BrainfuckAirConfig {
         ]
         .into_iter()
         .flatten()
-        .map(|constraint| {
-            // ensure constraint holds in the first row
-            // symbolically divide `(x - t_0)`
-            constraint / (X - first_trace_x)
-        });
+        .map(Constraint::when_first_row);
 
-        let terminal_constraints = [
-            tables::ProcessorExtensionColumn::terminal_constraints(),
-            tables::InstructionExtensionColumn::terminal_constraints(),
-            tables::InputExtensionColumn::terminal_constraints(),
-            tables::OutputExtensionColumn::terminal_constraints(),
-        ]
-        .into_iter()
-        .flatten()
-        .map(|constraint| {
-            // ensure constraint holds in the last row
-            // symbolically divide `(x - t_(n-1))`
-            // NOTE: `t^(n-1) = t^(-1)`
-            constraint / (X - last_trace_x)
-        });
+        let terminal_constraints = tables::ProcessorExtensionColumn::terminal_constraints()
+            .into_iter()
+            .map(|constraint| Constraint::when_last_row(trace_len, constraint));
 
         transition_constraints
             .chain(boundary_constraints)
             .chain(terminal_constraints)
-            .map(Constraint::from)
             .collect()
     }
 }
 
 // Computes the evaluation terminal for the instruction table
-fn compute_instruction_evaluation_argument(source_code: &str, challenges: &Challenges<Fq3>) -> Fq3 {
+fn compute_instruction_evaluation_argument<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    source_code: &str,
+    challenges: &Challenges<Fq>,
+) -> Fq {
     use Challenge::Eta;
     use Challenge::A;
     use Challenge::B;
@@ -135,7 +136,7 @@ fn compute_instruction_evaluation_argument(source_code: &str, challenges: &Chall
     // add padding
     program.push(0);
     // let prev_ip = None;
-    let mut acc = Fq3::zero();
+    let mut acc = Fq::zero();
     for (ip, curr_instr) in program.iter().copied().enumerate() {
         let next_instr = program.get(ip + 1).copied().unwrap_or(0);
         acc = acc * challenges[Eta.index()]
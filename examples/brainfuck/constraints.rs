@@ -1,5 +1,6 @@
 use crate::tables::Challenge;
 use crate::tables::EvaluationArgumentHint;
+use crate::tables::FinalStateHint;
 use crate::tables::InputBaseColumn;
 use crate::tables::InputExtensionColumn;
 use crate::tables::InstructionBaseColumn;
@@ -13,6 +14,7 @@ use crate::tables::ProcessorExtensionColumn;
 use crate::vm::OpCode;
 use ark_ff::FftField;
 use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Assertion;
 use ministark::constraints::ExecutionTraceColumn;
 use ministark::constraints::Hint;
 use ministark::constraints::VerifierChallenge;
@@ -42,6 +44,12 @@ impl ProcessorBaseColumn {
         let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
         let two = one + one;
         let mem_val_is_zero = MemVal.curr() * MemValInv.curr() - one;
+        let is_bounded = FinalStateHint::IsBounded.hint();
+        // transitions into padding are only unconstrained when proving a
+        // prefix of the execution. The state before the padding is then
+        // checked against the claimed final state instead.
+        let is_not_final_transition = -(&is_bounded * Dummy.next()) + one;
+        let is_final_transition = &is_bounded * (Dummy.curr() - one) * Dummy.next();
         let mut constraints = (None, None, None);
 
         use OpCode::*;
@@ -110,9 +118,16 @@ impl ProcessorBaseColumn {
         }
 
         vec![
-            constraints.0.unwrap(),
-            constraints.1.unwrap(),
-            constraints.2.unwrap(),
+            &is_not_final_transition * constraints.0.unwrap(),
+            &is_not_final_transition * constraints.1.unwrap(),
+            &is_not_final_transition * constraints.2.unwrap(),
+            // padding can't be followed by execution in a proven prefix
+            &is_bounded * Dummy.curr() * (Dummy.next() - one),
+            // state before the padding matches the claimed final state
+            &is_final_transition * (Cycle.curr() - FinalStateHint::Cycle.hint()),
+            &is_final_transition * (Ip.curr() - FinalStateHint::Ip.hint()),
+            &is_final_transition * (Mp.curr() - FinalStateHint::Mp.hint()),
+            &is_final_transition * (MemVal.curr() - FinalStateHint::MemVal.hint()),
             // cycle independent constraints
             Cycle.next() - Cycle.curr() - one,
             MemVal.curr() * &mem_val_is_zero,
@@ -378,10 +393,13 @@ impl InstructionExtensionColumn {
         ]
     }
 
-    pub fn terminal_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
-    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+    pub fn assertions<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    ) -> Vec<Assertion<FieldVariant<Fp, Fq>>> {
         use InstructionExtensionColumn::*;
-        vec![ProgramEvaluation.curr() - EvaluationArgumentHint::Instruction.hint()]
+        vec![Assertion::last_row(
+            ProgramEvaluation,
+            EvaluationArgumentHint::Instruction.hint(),
+        )]
     }
 
     pub fn transition_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
@@ -431,13 +449,13 @@ impl InputExtensionColumn {
         vec![Evaluation.curr() - Value.curr()]
     }
 
-    pub fn terminal_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
-    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+    pub fn assertions<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    ) -> Vec<Assertion<FieldVariant<Fp, Fq>>> {
         use InputExtensionColumn::*;
-        vec![
-            Evaluation.curr()
-                - EvaluationArgumentHint::Input.hint() * EvaluationArgumentHint::InputOffset.hint(),
-        ]
+        vec![Assertion::last_row(
+            Evaluation,
+            EvaluationArgumentHint::Input.hint() * EvaluationArgumentHint::InputOffset.hint(),
+        )]
     }
 
     pub fn transition_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
@@ -457,14 +475,13 @@ impl OutputExtensionColumn {
         vec![Evaluation.curr() - Value.curr()]
     }
 
-    pub fn terminal_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
-    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+    pub fn assertions<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    ) -> Vec<Assertion<FieldVariant<Fp, Fq>>> {
         use OutputExtensionColumn::*;
-        vec![
-            Evaluation.curr()
-                - EvaluationArgumentHint::Output.hint()
-                    * EvaluationArgumentHint::OutputOffset.hint(),
-        ]
+        vec![Assertion::last_row(
+            Evaluation,
+            EvaluationArgumentHint::Output.hint() * EvaluationArgumentHint::OutputOffset.hint(),
+        )]
     }
 
     pub fn transition_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
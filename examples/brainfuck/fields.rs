@@ -0,0 +1,30 @@
+use ark_ff::PrimeField;
+use ministark::StarkExtensionOf;
+use ministark_gpu::fields::p18446744069414584321;
+use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481 as p252;
+use ministark_gpu::GpuFftField;
+
+/// Fields the brainfuck VM is proven over
+pub trait BrainfuckField: Send + Sync + 'static {
+    /// Field of the base trace
+    type Fp: GpuFftField<FftField = Self::Fp> + PrimeField;
+    /// Field challenges are drawn from and extension columns live in
+    type Fq: StarkExtensionOf<Self::Fp>;
+}
+
+/// 64-bit field `2^64 - 2^32 + 1`. Challenges are drawn from a cubic
+/// extension to get enough bits of security.
+pub struct Goldilocks;
+
+impl BrainfuckField for Goldilocks {
+    type Fp = p18446744069414584321::ark::Fp;
+    type Fq = p18446744069414584321::ark::Fq3;
+}
+
+/// 252-bit field used by Cairo. Large enough that no extension is needed.
+pub struct Stark252;
+
+impl BrainfuckField for Stark252 {
+    type Fp = p252::ark::Fp;
+    type Fq = p252::ark::Fp;
+}
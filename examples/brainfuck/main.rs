@@ -3,30 +3,38 @@
 use air::BrainfuckAirConfig;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use core::marker::PhantomData;
+use fields::BrainfuckField;
+use fields::Goldilocks;
+use fields::Stark252;
 use ministark::hash::HashFn;
 use ministark::hash::Sha256HashFn;
+use ministark::impl_public_inputs;
 use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::public_inputs::public_coin_seed;
 use ministark::random::PublicCoin;
 use ministark::random::PublicCoinImpl;
 use ministark::stark::Stark;
+use ministark::trace::GeneratedTrace;
+use ministark::trace::TraceGenerator;
 use ministark::utils::SerdeOutput;
 use ministark::Proof;
 use ministark::ProofOptions;
-use ministark::Trace;
-use ministark_gpu::fields::p18446744069414584321::ark::Fp;
-use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
 use sha2::Sha256;
 use std::fs;
 use std::fs::File;
+use std::io;
 use std::io::Write;
 use std::path::PathBuf;
 use std::time::Instant;
 use structopt::StructOpt;
-use trace::BrainfuckTrace;
+use trace::BrainfuckExecution;
+use trace::BrainfuckTraceGenerator;
 use vm::simulate;
 
 mod air;
 mod constraints;
+mod fields;
 mod tables;
 mod trace;
 mod vm;
@@ -40,6 +48,16 @@ enum BrainfuckOptions {
         dst: PathBuf,
         #[structopt(long, default_value = "")]
         input: String,
+        /// Field to prove over: "goldilocks" or "stark252"
+        #[structopt(long, default_value = "goldilocks")]
+        field: String,
+        /// Only prove the first `max_cycles` cycles of the execution
+        #[structopt(long)]
+        max_cycles: Option<usize>,
+        /// Optimize the program before proving it. Only rewrites that keep
+        /// cycles the same are applied when `max_cycles` is set.
+        #[structopt(long)]
+        optimize: bool,
     },
     Verify {
         src: PathBuf,
@@ -49,6 +67,11 @@ enum BrainfuckOptions {
         input: String,
         #[structopt(long)]
         output: String,
+        #[structopt(long, default_value = "goldilocks")]
+        field: String,
+        /// Verify a proof of the optimized program
+        #[structopt(long)]
+        optimize: bool,
     },
 }
 
@@ -57,32 +80,71 @@ pub struct BrainfuckClaim {
     pub source_code: String,
     pub input: Vec<u8>,
     pub output: Vec<u8>,
+    /// State after the last proven cycle when only a prefix of the execution
+    /// is proven. `input` and `output` are the symbols read and written by
+    /// the prefix.
+    pub final_state: Option<ProcessorState>,
+}
+
+impl_public_inputs!(BrainfuckClaim {
+    source_code,
+    input,
+    output,
+    final_state
+});
+
+/// Registers of the brainfuck VM at a particular cycle
+#[derive(CanonicalSerialize, CanonicalDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProcessorState {
+    pub cycle: u64,
+    pub ip: u64,
+    pub mp: u64,
+    pub mem_val: u64,
 }
 
-impl Stark for BrainfuckClaim {
-    type Fp = Fp;
-    type Fq = Fq3;
-    type AirConfig = BrainfuckAirConfig;
+impl_public_inputs!(ProcessorState {
+    cycle,
+    ip,
+    mp,
+    mem_val
+});
+
+/// Proves brainfuck programs over the field `F`
+pub struct BrainfuckStark<F> {
+    claim: BrainfuckClaim,
+    _field: PhantomData<F>,
+}
+
+impl<F> BrainfuckStark<F> {
+    pub const fn new(claim: BrainfuckClaim) -> Self {
+        Self {
+            claim,
+            _field: PhantomData,
+        }
+    }
+}
+
+impl<F: BrainfuckField> Stark for BrainfuckStark<F> {
+    type Fp = F::Fp;
+    type Fq = F::Fq;
+    type AirConfig = BrainfuckAirConfig<F>;
     type Digest = SerdeOutput<Sha256>;
-    type PublicCoin = PublicCoinImpl<Fq3, Sha256HashFn>;
+    type PublicCoin = PublicCoinImpl<F::Fq, Sha256HashFn>;
     type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
-    type Witness = BrainfuckTrace;
-    type Trace = BrainfuckTrace;
+    type Witness = BrainfuckExecution<F::Fp>;
+    type Trace = GeneratedTrace<BrainfuckExecution<F::Fp>, BrainfuckTraceGenerator<F>>;
 
     fn gen_public_coin(&self, air: &ministark::Air<Self::AirConfig>) -> Self::PublicCoin {
-        let mut seed = Vec::new();
-        air.public_inputs().serialize_compressed(&mut seed).unwrap();
-        air.trace_len().serialize_compressed(&mut seed).unwrap();
-        air.options().serialize_compressed(&mut seed).unwrap();
+        let seed = public_coin_seed(air);
         PublicCoinImpl::new(Sha256HashFn::hash_chunks([&*seed]))
     }
 
-    fn get_public_inputs(&self) -> Self {
-        self.clone()
+    fn get_public_inputs(&self) -> BrainfuckClaim {
+        self.claim.clone()
     }
 
-    fn generate_trace(&self, witness: BrainfuckTrace) -> BrainfuckTrace {
-        witness
+    fn generate_trace(&self, execution: BrainfuckExecution<F::Fp>) -> Self::Trace {
+        BrainfuckTraceGenerator::new().generate(execution)
     }
 }
 
@@ -107,41 +169,70 @@ const OPTIONS: ProofOptions = {
 fn main() {
     // read command-line args
     match BrainfuckOptions::from_args() {
-        BrainfuckOptions::Prove { src, dst, input } => prove(src, input, dst),
+        BrainfuckOptions::Prove {
+            src,
+            dst,
+            input,
+            field,
+            max_cycles,
+            optimize,
+        } => match field.as_str() {
+            "goldilocks" => prove::<Goldilocks>(src, input, dst, max_cycles, optimize),
+            "stark252" => prove::<Stark252>(src, input, dst, max_cycles, optimize),
+            _ => panic!("unsupported field \"{field}\""),
+        },
         BrainfuckOptions::Verify {
             src,
             proof,
             input,
             output,
-        } => verify(src, input, output, proof),
+            field,
+            optimize,
+        } => match field.as_str() {
+            "goldilocks" => verify::<Goldilocks>(src, input, output, proof, optimize),
+            "stark252" => verify::<Stark252>(src, input, output, proof, optimize),
+            _ => panic!("unsupported field \"{field}\""),
+        },
     }
 }
 
-fn prove(source_code_path: PathBuf, input: String, output_path: PathBuf) {
-    let source_code = fs::read_to_string(source_code_path).unwrap();
-    let mut output = Vec::new();
+fn prove<F: BrainfuckField>(
+    source_code_path: PathBuf,
+    input: String,
+    output_path: PathBuf,
+    max_cycles: Option<usize>,
+    optimize: bool,
+) {
+    let mut source_code = fs::read_to_string(source_code_path).unwrap();
+    if optimize {
+        source_code = vm::optimize(&source_code, max_cycles.is_some());
+    }
 
     let now = Instant::now();
-    let trace = simulate(&source_code, &mut input.as_bytes(), &mut output);
+    let execution = simulate::<F::Fp>(
+        &source_code,
+        &mut input.as_bytes(),
+        &mut io::sink(),
+        max_cycles,
+    );
     println!(
-        "Generated execution trace (cols={}, rows={}) in {:.0?}",
-        trace.base_columns().num_cols(),
-        trace.base_columns().num_rows(),
+        "Simulated program (rows={}) in {:.0?}",
+        execution.processor_base_trace.num_rows(),
         now.elapsed(),
     );
+
+    let claim = BrainfuckTraceGenerator::<F>::new().public_inputs(&execution);
     println!(
         "Program output: \"{}\"",
-        String::from_utf8(output.clone()).unwrap()
+        String::from_utf8(claim.output.clone()).unwrap()
     );
-
-    let claim = BrainfuckClaim {
-        source_code,
-        input: input.into_bytes(),
-        output,
-    };
+    if let Some(state) = claim.final_state {
+        println!("Proving a prefix of the execution ending in {state:?}");
+    }
 
     let now = Instant::now();
-    let proof = pollster::block_on(claim.prove(OPTIONS, trace)).unwrap();
+    let stark = BrainfuckStark::<F>::new(claim.clone());
+    let proof = pollster::block_on(stark.prove(OPTIONS, execution)).unwrap();
     println!("Proof generated in: {:.0?}", now.elapsed());
     let security_level = proof.security_level_bits();
     println!("Proof security (conjectured): {security_level}bit",);
@@ -157,17 +248,29 @@ fn prove(source_code_path: PathBuf, input: String, output_path: PathBuf) {
     println!("Proof written to {}", output_path.as_path().display());
 }
 
-fn verify(source_code_path: PathBuf, input: String, output: String, proof_path: PathBuf) {
-    let source_code = fs::read_to_string(source_code_path).unwrap();
+fn verify<F: BrainfuckField>(
+    source_code_path: PathBuf,
+    input: String,
+    output: String,
+    proof_path: PathBuf,
+    optimize: bool,
+) {
+    let mut source_code = fs::read_to_string(source_code_path).unwrap();
     let proof_bytes = fs::read(proof_path).unwrap();
-    let (execution_info, proof): (BrainfuckClaim, Proof<BrainfuckClaim>) =
+    let (execution_info, proof): (BrainfuckClaim, Proof<BrainfuckStark<F>>) =
         <_>::deserialize_compressed(proof_bytes.as_slice()).unwrap();
     assert_eq!(input.as_bytes(), execution_info.input);
     assert_eq!(output.as_bytes(), execution_info.output);
+    if optimize {
+        source_code = vm::optimize(&source_code, execution_info.final_state.is_some());
+    }
     assert_eq!(source_code, execution_info.source_code);
+    if let Some(state) = execution_info.final_state {
+        println!("Proof is for a prefix of the execution ending in {state:?}");
+    }
 
     let now = Instant::now();
-    execution_info
+    BrainfuckStark::<F>::new(execution_info)
         .verify(proof, SECURITY_LEVEL)
         .expect("verification failed");
     println!("Proof verified in: {:?}", now.elapsed());
@@ -40,6 +40,24 @@ impl ministark::constraints::Hint for EvaluationArgumentHint {
     }
 }
 
+/// Claimed processor state after the last proven cycle. Only enforced when
+/// `IsBounded` is one i.e. when a prefix of the execution is proven.
+#[derive(Clone, Copy)]
+pub enum FinalStateHint {
+    // follows the evaluation argument hints
+    IsBounded = 5,
+    Cycle,
+    Ip,
+    Mp,
+    MemVal,
+}
+
+impl ministark::constraints::Hint for FinalStateHint {
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum ProcessorBaseColumn {
     Cycle,
@@ -1,3 +1,4 @@
+use crate::fields::BrainfuckField;
 use crate::tables::BrainfuckColumn;
 use crate::tables::Challenge;
 use crate::tables::InputBaseColumn;
@@ -11,77 +12,80 @@ use crate::tables::OutputExtensionColumn;
 use crate::tables::ProcessorBaseColumn;
 use crate::tables::ProcessorExtensionColumn;
 use crate::vm::OpCode;
+use crate::BrainfuckClaim;
+use crate::ProcessorState;
 use ark_ff::Field;
 use ark_ff::One;
 use ark_ff::PrimeField;
 use ark_ff::UniformRand;
 use ark_ff::Zero;
+use core::marker::PhantomData;
 use ministark::challenges::Challenges;
 use ministark::constraints::VerifierChallenge;
+use ministark::trace::TraceGenerator;
 use ministark::utils::GpuAllocator;
 use ministark::utils::GpuVec;
 // use ministark::constraint::Challenge as _;
 use ministark::Matrix;
-use ministark::Trace;
-use ministark_gpu::fields::p18446744069414584321::ark::Fp;
-use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
-
-pub struct BrainfuckTrace {
-    processor_base_trace: Matrix<Fp>,
-    memory_base_trace: Matrix<Fp>,
-    instruction_base_trace: Matrix<Fp>,
-    input_base_trace: Matrix<Fp>,
-    output_base_trace: Matrix<Fp>,
-    base_trace: Matrix<Fp>,
+use ministark::StarkExtensionOf;
+use ministark_gpu::GpuFftField;
+
+/// Execution tables produced by running a brainfuck program
+pub struct BrainfuckExecution<Fp> {
+    pub source_code: String,
+    pub input: Vec<u8>,
+    pub output: Vec<u8>,
+    /// Set if the execution stopped before the program finished
+    pub final_state: Option<ProcessorState>,
+    pub processor_base_trace: Matrix<Fp>,
+    pub memory_base_trace: Matrix<Fp>,
+    pub instruction_base_trace: Matrix<Fp>,
+    pub input_base_trace: Matrix<Fp>,
+    pub output_base_trace: Matrix<Fp>,
 }
 
-impl BrainfuckTrace {
-    pub fn new(
-        processor_base_trace: Matrix<Fp>,
-        memory_base_trace: Matrix<Fp>,
-        instruction_base_trace: Matrix<Fp>,
-        input_base_trace: Matrix<Fp>,
-        output_base_trace: Matrix<Fp>,
-    ) -> Self {
-        let base_trace = Matrix::join(vec![
-            processor_base_trace.clone(),
-            memory_base_trace.clone(),
-            instruction_base_trace.clone(),
-            input_base_trace.clone(),
-            output_base_trace.clone(),
-        ]);
-        Self {
-            processor_base_trace,
-            memory_base_trace,
-            instruction_base_trace,
-            input_base_trace,
-            output_base_trace,
-            base_trace,
-        }
+pub struct BrainfuckTraceGenerator<F>(PhantomData<F>);
+
+impl<F> BrainfuckTraceGenerator<F> {
+    pub const fn new() -> Self {
+        Self(PhantomData)
     }
 }
 
-impl Trace for BrainfuckTrace {
-    type Fp = Fp;
-    type Fq = Fq3;
+impl<F: BrainfuckField> TraceGenerator<BrainfuckExecution<F::Fp>> for BrainfuckTraceGenerator<F> {
+    type Fp = F::Fp;
+    type Fq = F::Fq;
+    type PublicInputs = BrainfuckClaim;
+
+    fn gen_base_columns(&self, execution: &BrainfuckExecution<F::Fp>) -> Matrix<F::Fp> {
+        Matrix::join(vec![
+            execution.processor_base_trace.clone(),
+            execution.memory_base_trace.clone(),
+            execution.instruction_base_trace.clone(),
+            execution.input_base_trace.clone(),
+            execution.output_base_trace.clone(),
+        ])
+    }
 
-    fn build_extension_columns(
+    fn gen_extension_columns(
         &self,
-        challenges: &Challenges<Self::Fq>,
-    ) -> Option<Matrix<Self::Fq>> {
-        let Self {
+        execution: &BrainfuckExecution<F::Fp>,
+        _base_columns: &Matrix<F::Fp>,
+        challenges: &Challenges<F::Fq>,
+    ) -> Option<Matrix<F::Fq>> {
+        let BrainfuckExecution {
             processor_base_trace,
             memory_base_trace,
             instruction_base_trace,
             input_base_trace,
             output_base_trace,
             ..
-        } = self;
+        } = execution;
 
         // let mut rng = rand::thread_rng();
         let mut rng = ark_std::test_rng(); // rand::thread_rng();
-        let instr_initial = Fq3::rand(&mut rng);
-        let mem_initial = Fq3::rand(&mut rng);
+        let instr_initial = F::Fq::rand(&mut rng);
+        let mem_initial = F::Fq::rand(&mut rng);
 
         let processor_matrix =
             gen_processor_ext_matrix(instr_initial, mem_initial, challenges, processor_base_trace);
@@ -100,17 +104,22 @@ impl Trace for BrainfuckTrace {
         ]))
     }
 
-    fn base_columns(&self) -> &Matrix<Self::Fp> {
-        &self.base_trace
+    fn public_inputs(&self, execution: &BrainfuckExecution<F::Fp>) -> BrainfuckClaim {
+        BrainfuckClaim {
+            source_code: execution.source_code.clone(),
+            input: execution.input.clone(),
+            output: execution.output.clone(),
+            final_state: execution.final_state,
+        }
     }
 }
 
-fn gen_processor_ext_matrix(
-    instruction_permutation_initial: Fq3,
-    memory_permutation_initial: Fq3,
-    challenges: &Challenges<Fq3>,
+fn gen_processor_ext_matrix<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>>(
+    instruction_permutation_initial: Fq,
+    memory_permutation_initial: Fq,
+    challenges: &Challenges<Fq>,
     base_matrix: &Matrix<Fp>,
-) -> Matrix<Fq3> {
+) -> Matrix<Fq> {
     use Challenge::*;
     use ProcessorBaseColumn::*;
     use ProcessorExtensionColumn::*;
@@ -118,15 +127,15 @@ fn gen_processor_ext_matrix(
     // prepare
     let mut instr_permutation_running_product = instruction_permutation_initial;
     let mut mem_permutation_running_product = memory_permutation_initial;
-    let mut input_running_evaluation = Fq3::zero();
-    let mut output_running_evaluation = Fq3::zero();
+    let mut input_running_evaluation = Fq::zero();
+    let mut output_running_evaluation = Fq::zero();
 
     // loop over all rows
     let mut extension_rows = Vec::new();
     for row in 0..base_matrix.num_rows() {
         let curr_base_row = base_matrix.get_row(row).unwrap();
         let next_base_row = base_matrix.get_row(row + 1);
-        let mut extension_row = [Fq3::zero(); ProcessorExtensionColumn::NUM_TRACE_COLUMNS];
+        let mut extension_row = [Fq::zero(); ProcessorExtensionColumn::NUM_TRACE_COLUMNS];
 
         // Permutations columns
         extension_row[InstructionPermutation as usize] = instr_permutation_running_product;
@@ -163,11 +172,11 @@ fn gen_processor_ext_matrix(
     Matrix::new(into_columns(extension_rows))
 }
 
-fn gen_memory_ext_matrix(
-    memory_permutation_initial: Fq3,
-    challenges: &Challenges<Fq3>,
+fn gen_memory_ext_matrix<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>>(
+    memory_permutation_initial: Fq,
+    challenges: &Challenges<Fq>,
     base_matrix: &Matrix<Fp>,
-) -> Matrix<Fq3> {
+) -> Matrix<Fq> {
     use Challenge::*;
     use MemoryBaseColumn::*;
     use MemoryExtensionColumn::*;
@@ -179,7 +188,7 @@ fn gen_memory_ext_matrix(
     let mut extension_rows = Vec::new();
     for row in 0..base_matrix.num_rows() {
         let base_row: Vec<Fp> = base_matrix.iter().map(|column| column[row]).collect();
-        let mut extension_row = [Fq3::zero(); MemoryExtensionColumn::NUM_TRACE_COLUMNS];
+        let mut extension_row = [Fq::zero(); MemoryExtensionColumn::NUM_TRACE_COLUMNS];
         extension_row[Permutation as usize] = mem_permutation_running_product;
         if base_row[Dummy as usize].is_zero() {
             mem_permutation_running_product *= challenges[Beta.index()]
@@ -193,25 +202,25 @@ fn gen_memory_ext_matrix(
     Matrix::new(into_columns(extension_rows))
 }
 
-fn gen_instruction_ext_matrix(
-    instruction_permutation_initial: Fq3,
-    challenges: &Challenges<Fq3>,
+fn gen_instruction_ext_matrix<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>>(
+    instruction_permutation_initial: Fq,
+    challenges: &Challenges<Fq>,
     base_matrix: &Matrix<Fp>,
-) -> Matrix<Fq3> {
+) -> Matrix<Fq> {
     use Challenge::*;
     use InstructionBaseColumn::*;
     use InstructionExtensionColumn::*;
 
     // prepare
     let mut permutation_running_product = instruction_permutation_initial;
-    let mut evaluation_running_sum = Fq3::zero();
+    let mut evaluation_running_sum = Fq::zero();
     let mut previous_address = -Fp::one();
 
     let mut extension_rows = Vec::new();
     for row in 0..base_matrix.num_rows() {
         let curr_base_row = base_matrix.get_row(row).unwrap();
         let prev_base_row = base_matrix.get_row(row.wrapping_sub(1));
-        let mut extension_row = [Fq3::zero(); InstructionExtensionColumn::NUM_TRACE_COLUMNS];
+        let mut extension_row = [Fq::zero(); InstructionExtensionColumn::NUM_TRACE_COLUMNS];
 
         if !curr_base_row[CurrInstr as usize].is_zero()
             && row > 0
@@ -244,19 +253,22 @@ fn gen_instruction_ext_matrix(
     Matrix::new(into_columns(extension_rows))
 }
 
-fn gen_input_ext_matrix(challenges: &Challenges<Fq3>, base_matrix: &Matrix<Fp>) -> Matrix<Fq3> {
+fn gen_input_ext_matrix<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>>(
+    challenges: &Challenges<Fq>,
+    base_matrix: &Matrix<Fp>,
+) -> Matrix<Fq> {
     use Challenge::*;
     use InputBaseColumn::*;
     use InputExtensionColumn::*;
 
     // prepare
-    let mut running_evaluation = Fq3::zero();
+    let mut running_evaluation = Fq::zero();
 
     // loop over all rows
     let mut extension_rows = Vec::new();
     for row in 0..base_matrix.num_rows() {
         let base_row = base_matrix.get_row(row).unwrap();
-        let mut extension_row = [Fq3::zero(); InputExtensionColumn::NUM_TRACE_COLUMNS];
+        let mut extension_row = [Fq::zero(); InputExtensionColumn::NUM_TRACE_COLUMNS];
         running_evaluation =
             running_evaluation * challenges[Gamma.index()] + base_row[Value as usize];
         extension_row[Evaluation as usize] = running_evaluation;
@@ -266,19 +278,22 @@ fn gen_input_ext_matrix(challenges: &Challenges<Fq3>, base_matrix: &Matrix<Fp>)
     Matrix::new(into_columns(extension_rows))
 }
 
-fn gen_output_ext_matrix(challenges: &Challenges<Fq3>, base_matrix: &Matrix<Fp>) -> Matrix<Fq3> {
+fn gen_output_ext_matrix<Fp: GpuFftField + PrimeField, Fq: StarkExtensionOf<Fp>>(
+    challenges: &Challenges<Fq>,
+    base_matrix: &Matrix<Fp>,
+) -> Matrix<Fq> {
     use Challenge::*;
     use OutputBaseColumn::*;
     use OutputExtensionColumn::*;
 
     // prepare
-    let mut running_evaluation = Fq3::zero();
+    let mut running_evaluation = Fq::zero();
 
     // loop over all rows
     let mut extension_rows = Vec::new();
     for row in 0..base_matrix.num_rows() {
         let base_row = base_matrix.get_row(row).unwrap();
-        let mut extension_row = [Fq3::zero(); OutputExtensionColumn::NUM_TRACE_COLUMNS];
+        let mut extension_row = [Fq::zero(); OutputExtensionColumn::NUM_TRACE_COLUMNS];
         running_evaluation =
             running_evaluation * challenges[Delta.index()] + base_row[Value as usize];
         extension_row[Evaluation as usize] = running_evaluation;
@@ -5,14 +5,15 @@ use crate::tables::MemoryBaseColumn;
 use crate::tables::OutputBaseColumn;
 use crate::tables::ProcessorBaseColumn;
 use crate::trace::into_columns;
-use crate::BrainfuckTrace;
+use crate::trace::BrainfuckExecution;
+use crate::ProcessorState;
 use ark_ff::Field;
 use ark_ff::One;
 use ark_ff::Zero;
+use ministark::table::pad_rows;
+use ministark::table::ColumnPadding;
 use ministark::Matrix;
 
-type Fp = <BrainfuckTrace as ministark::Trace>::Fp;
-
 /// Opcodes determined by the lexer
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OpCode {
@@ -65,6 +66,57 @@ fn lex(source: &str) -> Vec<OpCode> {
     operations
 }
 
+/// Rewrites a program into a shorter program with the same output. The AIR
+/// only supports the eight brainfuck instructions so only rewrites that are
+/// themselves brainfuck are applied:
+/// - adjacent `+`/`-` and `>`/`<` that cancel out are removed
+/// - loops that can't run (at the start of the program or straight after
+///   another loop) are removed
+///
+/// Comments are always removed. With `cycle_exact` set nothing else changes
+/// so cycle `i` of the optimized program is cycle `i` of the original. This is
+/// needed when proving a prefix of an execution.
+pub fn optimize(source: &str, cycle_exact: bool) -> String {
+    let opcodes = lex(source);
+    let mut optimized = Vec::new();
+    if cycle_exact {
+        optimized = opcodes;
+    } else {
+        let mut opcodes = opcodes.into_iter();
+        while let Some(opcode) = opcodes.next() {
+            let last = optimized.last().copied();
+            match (last, opcode) {
+                (Some(OpCode::Increment), OpCode::Decrement)
+                | (Some(OpCode::Decrement), OpCode::Increment)
+                | (Some(OpCode::IncrementPointer), OpCode::DecrementPointer)
+                | (Some(OpCode::DecrementPointer), OpCode::IncrementPointer) => {
+                    optimized.pop();
+                }
+                // the current cell is zero at the start of the program and
+                // after a loop so the loop is skipped
+                (None | Some(OpCode::LoopEnd), OpCode::LoopBegin) => {
+                    let mut depth = 1;
+                    for opcode in opcodes.by_ref() {
+                        match opcode {
+                            OpCode::LoopBegin => depth += 1,
+                            OpCode::LoopEnd => depth -= 1,
+                            _ => {}
+                        }
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                }
+                _ => optimized.push(opcode),
+            }
+        }
+    }
+    optimized
+        .into_iter()
+        .map(|opcode| char::from(opcode as u8))
+        .collect()
+}
+
 pub fn compile(source: &str) -> Vec<usize> {
     let opcodes = lex(source);
     let mut program = Vec::new();
@@ -105,12 +157,14 @@ struct Register {
     mem_val: usize,
 }
 
-// Outputs base execution trace
-pub fn simulate(
+// Outputs base execution trace. Execution stops after `max_cycles` cycles if
+// set in which case the state the VM stopped in is recorded.
+pub fn simulate<Fp: Field>(
     source_code: &str,
     input: &mut impl std::io::Read,
     output: &mut impl std::io::Write,
-) -> BrainfuckTrace {
+    max_cycles: Option<usize>,
+) -> BrainfuckExecution<Fp> {
     let program = compile(source_code);
 
     let mut tape = [0u8; 1024];
@@ -140,7 +194,7 @@ pub fn simulate(
     }
 
     // main loop
-    while register.ip < program.len() {
+    while register.ip < program.len() && !max_cycles.is_some_and(|max| register.cycle >= max) {
         let mem_val = Fp::from(register.mem_val as u64);
 
         processor_rows.push({
@@ -214,6 +268,13 @@ pub fn simulate(
         register.mem_val = tape[register.mp].into();
     }
 
+    let final_state = (register.ip < program.len()).then(|| ProcessorState {
+        cycle: register.cycle as u64,
+        ip: register.ip as u64,
+        mp: register.mp as u64,
+        mem_val: register.mem_val as u64,
+    });
+
     // Collect final state into execution tables
     let mem_val = Fp::from(register.mem_val as u64);
     processor_rows.push({
@@ -270,72 +331,62 @@ pub fn simulate(
     let input_base_trace = Matrix::new(into_columns(input_rows));
     let output_base_trace = Matrix::new(into_columns(output_rows));
 
-    BrainfuckTrace::new(
+    BrainfuckExecution {
+        source_code: source_code.to_owned(),
+        input: input_symbols,
+        output: output_symbols,
+        final_state,
         processor_base_trace,
         memory_base_trace,
         instruction_base_trace,
         input_base_trace,
         output_base_trace,
-    )
+    }
 }
 
-fn pad_processor_rows(rows: &mut Vec<[Fp; ProcessorBaseColumn::NUM_TRACE_COLUMNS]>, n: usize) {
-    use ProcessorBaseColumn::*;
-    while rows.len() < n {
-        let last_row = rows.last().unwrap();
-        let mut new_row = [Fp::zero(); ProcessorBaseColumn::NUM_TRACE_COLUMNS];
-        new_row[Cycle as usize] = last_row[Cycle as usize] + Fp::one();
-        new_row[Ip as usize] = last_row[Ip as usize];
-        new_row[CurrInstr as usize] = Fp::zero();
-        new_row[NextInstr as usize] = Fp::zero();
-        new_row[Mp as usize] = last_row[Mp as usize];
-        new_row[MemVal as usize] = last_row[MemVal as usize];
-        new_row[MemValInv as usize] = last_row[MemValInv as usize];
-        new_row[Dummy as usize] = Fp::one();
-        rows.push(new_row);
-    }
+fn pad_processor_rows<Fp: Field>(
+    rows: &mut Vec<[Fp; ProcessorBaseColumn::NUM_TRACE_COLUMNS]>,
+    n: usize,
+) {
+    use ColumnPadding::*;
+    // columns: cycle, ip, curr_instr, next_instr, mp, mem_val, mem_val_inv, dummy
+    pad_rows(
+        rows,
+        n,
+        &[Increment, Repeat, Zero, Zero, Repeat, Repeat, Repeat, One],
+    );
 }
 
-fn pad_memory_rows(rows: &mut Vec<[Fp; MemoryBaseColumn::NUM_TRACE_COLUMNS]>, n: usize) {
-    use MemoryBaseColumn::*;
-    while rows.len() < n {
-        let last_row = rows.last().unwrap();
-        let mut new_row = [Fp::zero(); MemoryBaseColumn::NUM_TRACE_COLUMNS];
-        new_row[Cycle as usize] = last_row[Cycle as usize] + Fp::one();
-        new_row[Mp as usize] = last_row[Mp as usize];
-        new_row[MemVal as usize] = last_row[MemVal as usize];
-        new_row[Dummy as usize] = Fp::one();
-        rows.push(new_row);
-    }
+fn pad_memory_rows<Fp: Field>(rows: &mut Vec<[Fp; MemoryBaseColumn::NUM_TRACE_COLUMNS]>, n: usize) {
+    use ColumnPadding::*;
+    // columns: cycle, mp, mem_val, dummy
+    pad_rows(rows, n, &[Increment, Repeat, Repeat, One]);
 }
 
-fn pad_instruction_rows(rows: &mut Vec<[Fp; InstructionBaseColumn::NUM_TRACE_COLUMNS]>, n: usize) {
-    use InstructionBaseColumn::*;
-    let last_ip = rows.last().unwrap()[Ip as usize];
-    while rows.len() < n {
-        let mut new_row = [Fp::zero(); InstructionBaseColumn::NUM_TRACE_COLUMNS];
-        new_row[Ip as usize] = last_ip;
-        new_row[CurrInstr as usize] = Fp::zero();
-        new_row[NextInstr as usize] = Fp::zero();
-        rows.push(new_row);
-    }
+fn pad_instruction_rows<Fp: Field>(
+    rows: &mut Vec<[Fp; InstructionBaseColumn::NUM_TRACE_COLUMNS]>,
+    n: usize,
+) {
+    use ColumnPadding::*;
+    // columns: ip, curr_instr, next_instr
+    pad_rows(rows, n, &[Repeat, Zero, Zero]);
 }
 
-fn pad_input_rows(rows: &mut Vec<[Fp; InputBaseColumn::NUM_TRACE_COLUMNS]>, n: usize) {
+fn pad_input_rows<Fp: Field>(rows: &mut Vec<[Fp; InputBaseColumn::NUM_TRACE_COLUMNS]>, n: usize) {
     while rows.len() < n {
         let new_row = [Fp::zero(); InputBaseColumn::NUM_TRACE_COLUMNS];
         rows.push(new_row);
     }
 }
 
-fn pad_output_rows(rows: &mut Vec<[Fp; OutputBaseColumn::NUM_TRACE_COLUMNS]>, n: usize) {
+fn pad_output_rows<Fp: Field>(rows: &mut Vec<[Fp; OutputBaseColumn::NUM_TRACE_COLUMNS]>, n: usize) {
     while rows.len() < n {
         let new_row = [Fp::zero(); OutputBaseColumn::NUM_TRACE_COLUMNS];
         rows.push(new_row);
     }
 }
 
-fn derive_memory_rows(
+fn derive_memory_rows<Fp: Field>(
     processor_rows: &[[Fp; ProcessorBaseColumn::NUM_TRACE_COLUMNS]],
 ) -> Vec<[Fp; MemoryBaseColumn::NUM_TRACE_COLUMNS]> {
     use MemoryBaseColumn::*;
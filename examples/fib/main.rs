@@ -1,8 +1,6 @@
 #![feature(allocator_api)]
 
 use ark_ff::One;
-use ark_poly::EvaluationDomain;
-use ark_poly::Radix2EvaluationDomain;
 use ark_serialize::CanonicalSerialize;
 use ministark::air::AirConfig;
 use ministark::constraints::AlgebraicItem;
@@ -22,7 +20,6 @@ use ministark::Matrix;
 use ministark::ProofOptions;
 use ministark::Trace;
 use ministark_gpu::fields::p18446744069414584321::ark::Fp;
-use num_traits::Pow;
 use sha2::Sha256;
 use std::time::Instant;
 
@@ -42,8 +39,8 @@ impl Trace for FibTrace {
         self.0.num_rows()
     }
 
-    fn base_columns(&self) -> &Matrix<Self::Fp> {
-        &self.0
+    fn base_columns(&self) -> Option<&Matrix<Self::Fp>> {
+        Some(&self.0)
     }
 }
 
@@ -71,14 +68,6 @@ impl AirConfig for FibAirConfig {
     }
 
     fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Self::Fp, Self::Fq>>> {
-        use AlgebraicItem::*;
-        let trace_xs = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
-        // NOTE: =1
-        let first_trace_x = Constant(FieldVariant::Fp(trace_xs.element(0)));
-        // NOTE: =trace_xs.group_gen_inv()
-        let last_trace_x = Constant(FieldVariant::Fp(trace_xs.element(trace_len - 1)));
-        let one = Constant(FieldVariant::Fp(Fp::one()));
-
         let boundary_constraints = {
             let v0 = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
             let v1 = v0 + v0;
@@ -101,11 +90,7 @@ impl AirConfig for FibAirConfig {
             ]
         }
         .into_iter()
-        .map(|constraint| {
-            // ensure constraint holds in the first row
-            // symbolically divide `(x - t_0)`
-            constraint / (X - first_trace_x)
-        });
+        .map(Constraint::when_first_row);
 
         let transition_constraints = vec![
             0.next() - 6.curr() * 7.curr(),
@@ -118,27 +103,16 @@ impl AirConfig for FibAirConfig {
             7.next() - 5.next() * 6.next(),
         ]
         .into_iter()
-        .map(|constraint| {
-            // ensure constraints hold in all rows except the last
-            // multiply by `(x - t_(n-1))` to remove the last term
-            // NOTE: `x^trace_len - 1 = (x - t_0)(x - t_1)...(x - t_(n-1))`
-            // NOTE: `t^(n-1) = t^(-1)`
-            constraint * ((X - last_trace_x) / (X.pow(trace_len) - one))
-        });
+        .map(|constraint| Constraint::when_transition(trace_len, constraint));
 
         let terminal_constraints =
             vec![7.curr() - AlgebraicItem::Hint(FibHint::ClaimedNthFibNum as usize)]
                 .into_iter()
-                .map(|constraint| {
-                    // ensure constraint holds in the last row
-                    // symbolically divide `(x - t_0)`
-                    constraint / (X - last_trace_x)
-                });
+                .map(|constraint| Constraint::when_last_row(trace_len, constraint));
 
         boundary_constraints
             .chain(terminal_constraints)
             .chain(transition_constraints)
-            .map(Constraint::new)
             .collect()
     }
 }
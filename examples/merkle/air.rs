@@ -0,0 +1,193 @@
+//! AIR for Merkle membership proofs
+//!
+//! Each node on the path from the leaf to the root is hashed with its sibling
+//! over `HASH_ROWS` rows. Row `t` of a hash holds the permutation state before
+//! round `t` and the output of the inverse S-box of round `t`. The first row
+//! also holds the node, its sibling and whether the node is the right child.
+//! The first element of the final state is the parent which becomes the node
+//! of the next hash. Round constants and selectors for the rows of a hash are
+//! periodic columns.
+//!
+//! Paths are padded to a power of two number of hashes so the root is checked
+//! on the last row of hash `depth - 1` with a public selector polynomial.
+
+use crate::hash::params;
+use crate::hash::ALPHA;
+use crate::hash::ROUNDS;
+use crate::hash::WIDTH;
+use crate::MerkleClaim;
+use ark_ff::One;
+use ark_ff::Zero;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use core::array;
+use ministark::air::public_sequence_polynomial;
+use ministark::air::AirConfig;
+use ministark::air::ColumnType;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Assertion;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::constraints::Hint;
+use ministark::constraints::PeriodicColumn;
+use ministark::constraints::PublicPolynomial;
+use ministark::expression::Expr;
+use ministark::gadgets::select;
+use ministark::hints::Hints;
+use ministark::utils::FieldVariant;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+/// Rows per hash. The last row holds the output of the permutation.
+pub const HASH_ROWS: usize = ROUNDS + 1;
+
+/// Permutation state before the round
+pub const STATE: usize = 0;
+/// Output of the inverse S-box of the round
+pub const MID: usize = STATE + WIDTH;
+pub const NODE: usize = MID + WIDTH;
+pub const SIBLING: usize = NODE + 1;
+/// One if the node is the right child of its parent
+pub const IS_RIGHT: usize = SIBLING + 1;
+pub const NUM_BASE_COLUMNS: usize = IS_RIGHT + 1;
+
+/// Public polynomial that is one on the row holding the root
+const ROOT_SELECTOR: usize = 0;
+
+#[derive(Clone, Copy)]
+enum MerkleHint {
+    Leaf = 0,
+    Root = 1,
+}
+
+impl Hint for MerkleHint {
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+type Expression = Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>;
+
+/// Returns the number of hashes in the trace of a path of length `depth`
+pub const fn num_hashes(depth: usize) -> usize {
+    depth.next_power_of_two()
+}
+
+pub struct MerkleAirConfig;
+
+impl AirConfig for MerkleAirConfig {
+    const NUM_BASE_COLUMNS: usize = NUM_BASE_COLUMNS;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = MerkleClaim;
+
+    fn column_types() -> Vec<ColumnType> {
+        let mut column_types = vec![ColumnType::Felt; NUM_BASE_COLUMNS];
+        column_types[IS_RIGHT] = ColumnType::Bit;
+        column_types
+    }
+
+    fn expected_trace_len(claim: &MerkleClaim) -> Option<usize> {
+        Some(num_hashes(claim.depth) * HASH_ROWS)
+    }
+
+    fn gen_hints(
+        _trace_len: usize,
+        claim: &MerkleClaim,
+        _: &Challenges<Self::Fq>,
+    ) -> Hints<Self::Fq> {
+        Hints::new(vec![
+            (MerkleHint::Leaf as usize, claim.leaf),
+            (MerkleHint::Root as usize, claim.root),
+        ])
+    }
+
+    /// # Panics
+    /// Panics if the depth is zero or doesn't fit in the trace
+    fn gen_public_polynomials(trace_len: usize, claim: &MerkleClaim) -> Vec<Vec<Fp>> {
+        let mut root_selector = vec![Fp::zero(); trace_len];
+        root_selector[claim.depth * HASH_ROWS - 1] = Fp::one();
+        vec![public_sequence_polynomial(&root_selector)]
+    }
+
+    fn assertions(_trace_len: usize) -> Vec<Assertion<FieldVariant<Self::Fp, Self::Fq>>> {
+        vec![Assertion::first_row(NODE, MerkleHint::Leaf.hint())]
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Self::Fp, Self::Fq>>> {
+        let params = params();
+        let is_round = periodic(array::from_fn(|t| Fp::from(u64::from(t < ROUNDS))));
+        let is_first = periodic(array::from_fn(|t| Fp::from(u64::from(t == 0))));
+        let is_last = periodic(array::from_fn(|t| Fp::from(u64::from(t == ROUNDS))));
+        let [c1, c2]: [[Expression; WIDTH]; 2] = array::from_fn(|half| {
+            array::from_fn(|j| {
+                periodic(array::from_fn(|t| {
+                    params
+                        .round_constants
+                        .get(t)
+                        .map_or(Fp::zero(), |c| c[half][j])
+                }))
+            })
+        });
+
+        let mut constraints = Vec::new();
+        let state: [Expression; WIDTH] = array::from_fn(|j| (STATE + j).curr());
+        let mid: [Expression; WIDTH] = array::from_fn(|j| (MID + j).curr());
+        let sbox = state.clone().map(|s| s.pow(ALPHA as usize));
+        for j in 0..WIDTH {
+            // `mid^7` is checked rather than computing `x^(1/7)`
+            let first_half = mid[j].pow(ALPHA as usize) - mds_row(j, &sbox) - &c1[j];
+            let second_half = (STATE + j).next() - mds_row(j, &mid) - &c2[j];
+            for expr in [first_half, second_half] {
+                constraints.push(Constraint::when_every_row(trace_len, &is_round * expr));
+            }
+        }
+
+        let (node, sibling, is_right) = (NODE.curr(), SIBLING.curr(), IS_RIGHT.curr());
+        let zero = Expression::from(constant(Fp::zero()));
+        let inputs = [
+            select(is_right.clone(), sibling.clone(), node.clone()),
+            select(is_right, node, sibling),
+            zero.clone(),
+            zero,
+        ];
+        for (state, input) in state.iter().zip(inputs) {
+            let expr = &is_first * (state - input);
+            constraints.push(Constraint::when_every_row(trace_len, expr));
+        }
+
+        // the parent is the node of the next hash
+        let parent = is_last * (NODE.next() - &state[0]);
+        constraints.push(Constraint::when_transition(trace_len, parent));
+
+        let root = &state[0] - MerkleHint::Root.hint();
+        let root_selector: Expression = ROOT_SELECTOR.public_polynomial();
+        constraints.push(Constraint::when_every_row(trace_len, root_selector * root));
+
+        constraints
+    }
+}
+
+fn constant(value: Fp) -> AlgebraicItem<FieldVariant<Fp, Fp>> {
+    AlgebraicItem::Constant(FieldVariant::Fp(value))
+}
+
+/// Returns row `j` of the MDS matrix multiplied by `values`
+fn mds_row(j: usize, values: &[Expression; WIDTH]) -> Expression {
+    let row = params().mds[j];
+    values
+        .iter()
+        .zip(row)
+        .map(|(value, m)| value * &constant(m))
+        .sum()
+}
+
+/// Returns a column equal to `values[t]` on row `t` of every hash
+fn periodic(values: [Fp; HASH_ROWS]) -> Expression {
+    let domain = Radix2EvaluationDomain::<Fp>::new(HASH_ROWS).unwrap();
+    let coeffs = domain.ifft(&values);
+    let coeffs = coeffs.into_iter().map(FieldVariant::Fp).collect::<Vec<_>>();
+    // periodic columns borrow their coefficients for the life of the program
+    let coeffs = Box::leak(coeffs.into_boxed_slice());
+    AlgebraicItem::Periodic(PeriodicColumn::new(coeffs, HASH_ROWS)).into()
+}
@@ -0,0 +1,75 @@
+//! Rescue-Prime style permutation used to hash the nodes of the tree
+//!
+//! Each round applies the S-box `x^7`, an MDS matrix and round constants
+//! followed by the inverse S-box `x^(1/7)`, the MDS matrix and round constants
+//! again. Parameters are picked for illustration and haven't been analysed.
+
+use ark_ff::Field;
+use ark_ff::Zero;
+use core::array;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use sha2::Digest;
+use sha2::Sha256;
+use std::sync::OnceLock;
+
+pub const WIDTH: usize = 4;
+pub const ROUNDS: usize = 7;
+pub const ALPHA: u64 = 7;
+/// Inverse of `ALPHA` mod `p - 1`
+pub const ALPHA_INV: u64 = 10540996611094048183;
+
+pub struct HashParams {
+    /// Cauchy matrix `1 / (i + j + WIDTH)`. Cauchy matrices are MDS.
+    pub mds: [[Fp; WIDTH]; WIDTH],
+    /// Constants added after the first and second half of each round
+    pub round_constants: [[[Fp; WIDTH]; 2]; ROUNDS],
+}
+
+pub fn params() -> &'static HashParams {
+    static PARAMS: OnceLock<HashParams> = OnceLock::new();
+    PARAMS.get_or_init(|| HashParams {
+        mds: array::from_fn(|i| {
+            array::from_fn(|j| Fp::from((i + j + WIDTH) as u64).inverse().unwrap())
+        }),
+        round_constants: array::from_fn(|round| {
+            array::from_fn(|half| array::from_fn(|j| round_constant(round, half, j)))
+        }),
+    })
+}
+
+/// Derives a round constant from the SHA-256 hash of its position
+fn round_constant(round: usize, half: usize, j: usize) -> Fp {
+    let seed = format!("ministark-merkle-rescue-{round}-{half}-{j}");
+    let digest = Sha256::digest(seed.as_bytes());
+    Fp::from(u64::from_le_bytes(digest[..8].try_into().unwrap()))
+}
+
+fn mds_mul(state: &[Fp; WIDTH]) -> [Fp; WIDTH] {
+    params()
+        .mds
+        .map(|row| row.iter().zip(state).map(|(&m, &s)| m * s).sum())
+}
+
+/// Applies round `round` to `state`. Returns the output of the inverse S-box
+/// and the next state.
+pub fn round(state: &[Fp; WIDTH], round: usize) -> ([Fp; WIDTH], [Fp; WIDTH]) {
+    let [c1, c2] = params().round_constants[round];
+    let mut mid = mds_mul(&state.map(|s| s.pow([ALPHA])));
+    for (mid, c) in mid.iter_mut().zip(c1) {
+        *mid = (*mid + c).pow([ALPHA_INV]);
+    }
+    let mut next = mds_mul(&mid);
+    for (next, c) in next.iter_mut().zip(c2) {
+        *next += c;
+    }
+    (mid, next)
+}
+
+/// Hashes the children of a node
+pub fn merge(left: Fp, right: Fp) -> Fp {
+    let mut state = [left, right, Fp::zero(), Fp::zero()];
+    for i in 0..ROUNDS {
+        state = round(&state, i).1;
+    }
+    state[0]
+}
@@ -0,0 +1,123 @@
+#![feature(allocator_api)]
+
+use air::MerkleAirConfig;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use hash::merge;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::impl_public_inputs;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::public_inputs::public_coin_seed;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::utils::SerdeOutput;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use sha2::Sha256;
+use std::time::Instant;
+use structopt::StructOpt;
+use trace::gen_trace;
+use trace::MerklePath;
+use trace::MerkleTrace;
+
+mod air;
+mod hash;
+mod trace;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "merkle", about = "miniSTARK Merkle membership prover")]
+struct MerkleOptions {
+    /// Depth of the tree
+    #[structopt(long, default_value = "16")]
+    depth: usize,
+    /// Position of the leaf to prove membership of
+    #[structopt(long, default_value = "0")]
+    index: usize,
+}
+
+/// Claim that `leaf` is a leaf of the tree of depth `depth` with root `root`
+#[derive(CanonicalSerialize, CanonicalDeserialize, Clone, Debug)]
+pub struct MerkleClaim {
+    pub root: Fp,
+    pub leaf: Fp,
+    pub depth: usize,
+}
+
+impl_public_inputs!(MerkleClaim { root, leaf, depth });
+
+impl Stark for MerkleClaim {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = MerkleAirConfig;
+    type Digest = SerdeOutput<Sha256>;
+    type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+    type Witness = MerklePath;
+    type Trace = MerkleTrace;
+
+    fn get_public_inputs(&self) -> MerkleClaim {
+        self.clone()
+    }
+
+    fn generate_trace(&self, path: MerklePath) -> MerkleTrace {
+        gen_trace(self.leaf, &path)
+    }
+
+    fn gen_public_coin(&self, air: &ministark::Air<Self::AirConfig>) -> Self::PublicCoin {
+        let seed = public_coin_seed(air);
+        PublicCoinImpl::new(Sha256HashFn::hash_chunks([&*seed]))
+    }
+}
+
+/// Returns the layers of the tree with leaves `leaves` ordered from the leaves
+/// to the root
+fn build_tree(leaves: Vec<Fp>) -> Vec<Vec<Fp>> {
+    let mut layers = vec![leaves];
+    while layers.last().unwrap().len() > 1 {
+        let layer = layers.last().unwrap();
+        let parents = layer.chunks(2).map(|pair| merge(pair[0], pair[1]));
+        layers.push(parents.collect());
+    }
+    layers
+}
+
+const SECURITY_LEVEL: u32 = 30;
+const OPTIONS: ProofOptions = ProofOptions::new(32, 8, 8, 8, 64);
+
+fn main() {
+    let MerkleOptions { depth, index } = MerkleOptions::from_args();
+    assert!(depth > 0, "depth must be non-zero");
+    assert!(index < 1 << depth, "index {index} is out of range");
+
+    let now = Instant::now();
+    let leaves = (0..1u64 << depth).map(Fp::from).collect();
+    let layers = build_tree(leaves);
+    println!("Tree built in: {:?}", now.elapsed());
+
+    let claim = MerkleClaim {
+        root: layers[depth][0],
+        leaf: layers[0][index],
+        depth,
+    };
+    let path = MerklePath {
+        index,
+        siblings: (0..depth)
+            .map(|level| layers[level][(index >> level) ^ 1])
+            .collect(),
+    };
+    println!("Root: {}", claim.root);
+
+    let now = Instant::now();
+    let proof = pollster::block_on(claim.prove(OPTIONS, path)).expect("prover failed");
+    println!("Proof generated in: {:?}", now.elapsed());
+    let security_level = proof.security_level_bits();
+    println!("Proof security (conjectured): {security_level}bit");
+
+    let now = Instant::now();
+    claim
+        .verify(proof, SECURITY_LEVEL)
+        .expect("verification failed");
+    println!("Proof verified in: {:?}", now.elapsed());
+}
@@ -0,0 +1,83 @@
+use crate::air::num_hashes;
+use crate::air::HASH_ROWS;
+use crate::air::IS_RIGHT;
+use crate::air::MID;
+use crate::air::NODE;
+use crate::air::NUM_BASE_COLUMNS;
+use crate::air::SIBLING;
+use crate::air::STATE;
+use crate::hash::round;
+use crate::hash::ROUNDS;
+use ark_ff::Zero;
+use ministark::utils::GpuAllocator;
+use ministark::Matrix;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+/// Authentication path of a leaf
+pub struct MerklePath {
+    /// Position of the leaf in the tree
+    pub index: usize,
+    /// Siblings of the nodes on the path ordered from the leaf to the root
+    pub siblings: Vec<Fp>,
+}
+
+pub struct MerkleTrace(Matrix<Fp>);
+
+impl Trace for MerkleTrace {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn len(&self) -> usize {
+        self.0.num_rows()
+    }
+
+    fn base_columns(&self) -> Option<&Matrix<Self::Fp>> {
+        Some(&self.0)
+    }
+}
+
+/// Generates the trace of hashing `leaf` up to the root along `path`. See
+/// [`crate::air`] for the layout.
+pub fn gen_trace(leaf: Fp, path: &MerklePath) -> MerkleTrace {
+    let depth = path.siblings.len();
+    let num_hashes = num_hashes(depth);
+    let mut columns = vec![vec![Fp::zero(); num_hashes * HASH_ROWS]; NUM_BASE_COLUMNS];
+
+    let mut node = leaf;
+    for i in 0..num_hashes {
+        // padding hashes after the root hash it with a zero sibling
+        let sibling = path.siblings.get(i).copied().unwrap_or_default();
+        let is_right = i < depth && (path.index >> i) & 1 == 1;
+        let first_row = i * HASH_ROWS;
+        columns[NODE][first_row] = node;
+        columns[SIBLING][first_row] = sibling;
+        columns[IS_RIGHT][first_row] = Fp::from(u64::from(is_right));
+
+        let (left, right) = if is_right {
+            (sibling, node)
+        } else {
+            (node, sibling)
+        };
+        let mut state = [left, right, Fp::zero(), Fp::zero()];
+        for t in 0..ROUNDS {
+            let (mid, next) = round(&state, t);
+            for (j, (&s, m)) in state.iter().zip(mid).enumerate() {
+                columns[STATE + j][first_row + t] = s;
+                columns[MID + j][first_row + t] = m;
+            }
+            state = next;
+        }
+        for (j, s) in state.into_iter().enumerate() {
+            columns[STATE + j][first_row + ROUNDS] = s;
+        }
+        node = state[0];
+    }
+
+    MerkleTrace(Matrix::new(
+        columns
+            .into_iter()
+            .map(|column| column.to_vec_in(GpuAllocator))
+            .collect(),
+    ))
+}
@@ -0,0 +1,273 @@
+//! AIR for the SHA-256 compression function
+//!
+//! Each 512-bit message block takes 64 rows. Row `t` of a block holds the
+//! working variables `a..h` before round `t` and the message schedule word
+//! `W_t`. Words are decomposed into bits (least significant first) so the
+//! bitwise operations of SHA-256 are low degree polynomials and the bit
+//! columns double as range checks. Additions mod `2^32` are checked over the
+//! field with a few carry bits.
+//!
+//! The first row of a block holds the chaining value i.e. the first row of
+//! the previous block plus its working variables after round 63. Blocks with
+//! `ACTIVE` set to zero leave the chaining value unchanged so the number of
+//! blocks can be padded to a power of two. The last block is always inactive
+//! and its first row holds the digest. Message padding isn't checked so a
+//! proof shows knowledge of blocks that compress to the digest.
+
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use core::array;
+use core::ops::Range;
+use ministark::air::AirConfig;
+use ministark::air::ColumnType;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Assertion;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::constraints::PeriodicColumn;
+use ministark::expression::Expr;
+use ministark::hints::Hints;
+use ministark::utils::FieldVariant;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+pub const WORD_BITS: usize = 32;
+/// Rows (and rounds) per message block
+pub const ROUNDS: usize = 64;
+/// Number of message words in a block. Schedule words after these are
+/// derived from the previous words.
+pub const MESSAGE_WORDS: usize = 16;
+
+/// Bits of the working variables `a..h`. Variable `j` starts at column
+/// `j * WORD_BITS`.
+pub const VARIABLES: Range<usize> = 0..8 * WORD_BITS;
+/// Bits of the message schedule word `W_t`
+pub const W: usize = VARIABLES.end;
+pub const CARRY_A: usize = W + WORD_BITS;
+pub const CARRY_E: usize = CARRY_A + 3;
+/// Carry of the message schedule word
+pub const CARRY_W: usize = CARRY_E + 3;
+/// Carries of `b`, `c`, `d`, `f`, `g` and `h` when the chaining value is
+/// updated. These are zero within a block.
+pub const CARRY_COPY: usize = CARRY_W + 2;
+/// One in blocks that update the chaining value
+pub const ACTIVE: usize = CARRY_COPY + 6;
+pub const NUM_BASE_COLUMNS: usize = ACTIVE + 1;
+
+/// Carry bits of each working variable as `(first column, number of bits)`
+pub const CARRY_COLUMNS: [(usize, usize); 8] = [
+    (CARRY_A, 3),
+    (CARRY_COPY, 1),
+    (CARRY_COPY + 1, 1),
+    (CARRY_COPY + 2, 1),
+    (CARRY_E, 3),
+    (CARRY_COPY + 3, 1),
+    (CARRY_COPY + 4, 1),
+    (CARRY_COPY + 5, 1),
+];
+
+pub const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+pub const K: [u32; ROUNDS] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+type Expression = Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>;
+type Bits = [Expression; WORD_BITS];
+
+pub struct Sha256AirConfig;
+
+impl AirConfig for Sha256AirConfig {
+    const NUM_BASE_COLUMNS: usize = NUM_BASE_COLUMNS;
+    type Fp = Fp;
+    type Fq = Fp;
+    /// SHA-256 digest
+    type PublicInputs = [u8; 32];
+
+    fn gen_hints(
+        _trace_len: usize,
+        digest: &[u8; 32],
+        _: &Challenges<Self::Fq>,
+    ) -> Hints<Self::Fq> {
+        Hints::new(
+            digest
+                .chunks(4)
+                .map(|word| u32::from_be_bytes(word.try_into().unwrap()))
+                .map(Fp::from)
+                .enumerate()
+                .collect(),
+        )
+    }
+
+    fn column_types() -> Vec<ColumnType> {
+        vec![ColumnType::Bit; NUM_BASE_COLUMNS]
+    }
+
+    fn assertions(_trace_len: usize) -> Vec<Assertion<FieldVariant<Self::Fp, Self::Fq>>> {
+        vec![
+            Assertion::first_row(ACTIVE, constant(1).into()),
+            Assertion::last_row(ACTIVE, constant(0).into()),
+        ]
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Self::Fp, Self::Fq>>> {
+        let is_round = periodic(array::from_fn(|t| u64::from(t != ROUNDS - 1)));
+        let is_boundary = Expression::from(constant(1)) - &is_round;
+        let is_schedule = periodic(array::from_fn(|t| u64::from(t >= MESSAGE_WORDS)));
+        let k = periodic(K.map(u64::from));
+        let two_pow_32 = constant(1 << 32);
+        let active: Expression = ACTIVE.curr();
+
+        let [a, b, c, d, e, f, g, h] = &array::from_fn(|j| bits(j * WORD_BITS, 0));
+        let t1 = word(h) + big_sigma1(e) + ch(e, f, g) + k + word(&bits(W, 0));
+        let t2 = big_sigma0(a) + maj(a, b, c);
+        // working variables after the round
+        let new_vars = [
+            &t1 + &t2,
+            word(a),
+            word(b),
+            word(c),
+            word(d) + &t1,
+            word(e),
+            word(f),
+            word(g),
+        ];
+
+        let mut constraints = Vec::new();
+        for (j, new_var) in new_vars.iter().enumerate() {
+            let (carry_column, carry_bits) = CARRY_COLUMNS[j];
+            let next = word(&bits(j * WORD_BITS, 1));
+            let next = next + value(carry_column..carry_column + carry_bits, 0) * two_pow_32;
+            // the chaining value is on the first row of the block
+            let chaining_value = word(&bits(j * WORD_BITS, 1 - ROUNDS as isize));
+            let round = &is_round * (&next - new_var);
+            let update = &is_boundary * (next - &active * new_var - chaining_value);
+            constraints.push(Constraint::when_transition(trace_len, round));
+            constraints.push(Constraint::when_transition(trace_len, update));
+        }
+
+        let schedule = {
+            let w = word(&bits(W, 0)) + value(CARRY_W..CARRY_W + 2, 0) * two_pow_32;
+            let w2 = bits(W, -2);
+            let w15 = bits(W, -15);
+            let derived =
+                small_sigma1(&w2) + word(&bits(W, -7)) + small_sigma0(&w15) + word(&bits(W, -16));
+            is_schedule * (w - derived)
+        };
+        constraints.push(Constraint::when_every_row(trace_len, schedule));
+
+        // blocks are active until the first inactive block
+        let stays_active = (ACTIVE.next() - &active) * &is_round;
+        let deactivates = ACTIVE.next() * (Expression::from(constant(1)) - &active) * is_boundary;
+        constraints.push(Constraint::when_transition(trace_len, stays_active));
+        constraints.push(Constraint::when_transition(trace_len, deactivates));
+
+        for (j, iv) in IV.into_iter().enumerate() {
+            let expr = word(&bits(j * WORD_BITS, 0)) - constant(iv.into());
+            constraints.push(Constraint::when_first_row(expr));
+        }
+        for j in 0..8 {
+            let expr = word(&bits(j * WORD_BITS, 0)) - AlgebraicItem::Hint(j);
+            let last_block = -(ROUNDS as isize);
+            constraints.push(Constraint::when_row(trace_len, last_block, expr));
+        }
+
+        constraints
+    }
+}
+
+fn constant(value: u64) -> AlgebraicItem<FieldVariant<Fp, Fp>> {
+    AlgebraicItem::Constant(FieldVariant::Fp(Fp::from(value)))
+}
+
+/// Returns a column equal to `values[t]` on row `t` of every block
+fn periodic(values: [u64; ROUNDS]) -> Expression {
+    let domain = Radix2EvaluationDomain::<Fp>::new(ROUNDS).unwrap();
+    let coeffs = domain.ifft(&values.map(Fp::from));
+    let coeffs = coeffs.into_iter().map(FieldVariant::Fp).collect::<Vec<_>>();
+    // periodic columns borrow their coefficients for the life of the program
+    let coeffs = Box::leak(coeffs.into_boxed_slice());
+    AlgebraicItem::Periodic(PeriodicColumn::new(coeffs, ROUNDS)).into()
+}
+
+/// Bits of the word starting at `column` on the row at `offset`
+fn bits(column: usize, offset: isize) -> Bits {
+    array::from_fn(|i| (column + i).offset(offset))
+}
+
+/// Value of the little endian bits in `columns` on the row at `offset`
+fn value(columns: Range<usize>, offset: isize) -> Expression {
+    let bits = columns
+        .map(|column| column.offset(offset))
+        .collect::<Vec<_>>();
+    word(&bits)
+}
+
+fn word(bits: &[Expression]) -> Expression {
+    bits.iter()
+        .enumerate()
+        .map(|(i, bit)| bit * &constant(1 << i))
+        .sum()
+}
+
+fn xor3(x: &Expression, y: &Expression, z: &Expression) -> Expression {
+    let xy = x * y;
+    x + y + z - (&xy + x * z + y * z) * constant(2) + xy * z * constant(4)
+}
+
+/// Bit `i` of `x` rotated right by `n`
+fn rotr(x: &Bits, i: usize, n: usize) -> Expression {
+    x[(i + n) % WORD_BITS].clone()
+}
+
+/// Bit `i` of `x` shifted right by `n`
+fn shr(x: &Bits, i: usize, n: usize) -> Expression {
+    x.get(i + n).cloned().unwrap_or_else(|| constant(0).into())
+}
+
+fn big_sigma0(x: &Bits) -> Expression {
+    word(&array::from_fn::<_, WORD_BITS, _>(|i| {
+        xor3(&rotr(x, i, 2), &rotr(x, i, 13), &rotr(x, i, 22))
+    }))
+}
+
+fn big_sigma1(x: &Bits) -> Expression {
+    word(&array::from_fn::<_, WORD_BITS, _>(|i| {
+        xor3(&rotr(x, i, 6), &rotr(x, i, 11), &rotr(x, i, 25))
+    }))
+}
+
+fn small_sigma0(x: &Bits) -> Expression {
+    word(&array::from_fn::<_, WORD_BITS, _>(|i| {
+        xor3(&rotr(x, i, 7), &rotr(x, i, 18), &shr(x, i, 3))
+    }))
+}
+
+fn small_sigma1(x: &Bits) -> Expression {
+    word(&array::from_fn::<_, WORD_BITS, _>(|i| {
+        xor3(&rotr(x, i, 17), &rotr(x, i, 19), &shr(x, i, 10))
+    }))
+}
+
+fn ch(e: &Bits, f: &Bits, g: &Bits) -> Expression {
+    word(&array::from_fn::<_, WORD_BITS, _>(|i| {
+        &e[i] * (&f[i] - &g[i]) + &g[i]
+    }))
+}
+
+fn maj(a: &Bits, b: &Bits, c: &Bits) -> Expression {
+    word(&array::from_fn::<_, WORD_BITS, _>(|i| {
+        let (a, b, c) = (&a[i], &b[i], &c[i]);
+        let ab = a * b;
+        &ab + a * c + b * c - ab * c * constant(2)
+    }))
+}
@@ -0,0 +1,87 @@
+#![feature(allocator_api)]
+
+use air::Sha256AirConfig;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::public_inputs::public_coin_seed;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::utils::SerdeOutput;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use sha2::Digest;
+use sha2::Sha256;
+use std::time::Instant;
+use structopt::StructOpt;
+use trace::gen_trace;
+use trace::Sha256Trace;
+
+mod air;
+mod trace;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "sha256", about = "miniSTARK SHA-256 preimage prover")]
+struct Sha256Options {
+    /// Message to prove knowledge of
+    #[structopt(default_value = "abc")]
+    message: String,
+}
+
+/// Claim of knowing a message with SHA-256 digest `digest`
+struct Sha256Claim {
+    digest: [u8; 32],
+}
+
+impl Stark for Sha256Claim {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = Sha256AirConfig;
+    type Digest = SerdeOutput<Sha256>;
+    type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+    /// The preimage
+    type Witness = Vec<u8>;
+    type Trace = Sha256Trace;
+
+    fn get_public_inputs(&self) -> [u8; 32] {
+        self.digest
+    }
+
+    fn generate_trace(&self, message: Vec<u8>) -> Sha256Trace {
+        gen_trace(&message)
+    }
+
+    fn gen_public_coin(&self, air: &ministark::Air<Self::AirConfig>) -> Self::PublicCoin {
+        let seed = public_coin_seed(air);
+        PublicCoinImpl::new(Sha256HashFn::hash_chunks([&*seed]))
+    }
+}
+
+const SECURITY_LEVEL: u32 = 30;
+const OPTIONS: ProofOptions = ProofOptions::new(32, 8, 8, 8, 64);
+
+fn main() {
+    let Sha256Options { message } = Sha256Options::from_args();
+    let message = message.into_bytes();
+    let digest = Sha256::digest(&message).into();
+    let claim = Sha256Claim { digest };
+    println!("Digest: {}", hex(&digest));
+
+    let now = Instant::now();
+    let proof = pollster::block_on(claim.prove(OPTIONS, message)).expect("prover failed");
+    println!("Proof generated in: {:?}", now.elapsed());
+    let security_level = proof.security_level_bits();
+    println!("Proof security (conjectured): {security_level}bit");
+
+    let now = Instant::now();
+    claim
+        .verify(proof, SECURITY_LEVEL)
+        .expect("verification failed");
+    println!("Proof verified in: {:?}", now.elapsed());
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -0,0 +1,159 @@
+use crate::air::ACTIVE;
+use crate::air::CARRY_COLUMNS;
+use crate::air::CARRY_W;
+use crate::air::IV;
+use crate::air::K;
+use crate::air::MESSAGE_WORDS;
+use crate::air::NUM_BASE_COLUMNS;
+use crate::air::ROUNDS;
+use crate::air::W;
+use crate::air::WORD_BITS;
+use ark_ff::Zero;
+use ministark::utils::GpuAllocator;
+use ministark::Matrix;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+pub struct Sha256Trace(Matrix<Fp>);
+
+impl Trace for Sha256Trace {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn len(&self) -> usize {
+        self.0.num_rows()
+    }
+
+    fn base_columns(&self) -> Option<&Matrix<Self::Fp>> {
+        Some(&self.0)
+    }
+}
+
+/// Pads `message` and splits it into blocks of big endian words
+pub fn pad(message: &[u8]) -> Vec<[u32; MESSAGE_WORDS]> {
+    let mut bytes = message.to_vec();
+    bytes.push(0x80);
+    while bytes.len() % 64 != 56 {
+        bytes.push(0);
+    }
+    bytes.extend((message.len() as u64 * 8).to_be_bytes());
+    bytes
+        .chunks(64)
+        .map(|block| {
+            let mut words = [0; MESSAGE_WORDS];
+            for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+                *word = u32::from_be_bytes(bytes.try_into().unwrap());
+            }
+            words
+        })
+        .collect()
+}
+
+/// Generates the trace of hashing `message`. See [`crate::air`] for the
+/// layout.
+pub fn gen_trace(message: &[u8]) -> Sha256Trace {
+    let blocks = pad(message);
+    // the last block is inactive and holds the digest
+    let num_blocks = (blocks.len() + 1).next_power_of_two();
+    let trace_len = num_blocks * ROUNDS;
+    let mut columns = vec![vec![Fp::zero(); trace_len]; NUM_BASE_COLUMNS];
+    let mut set_bits = |first_column: usize, num_bits: usize, row: usize, value: u64| {
+        for (i, column) in columns[first_column..first_column + num_bits]
+            .iter_mut()
+            .enumerate()
+        {
+            column[row] = Fp::from((value >> i) & 1);
+        }
+    };
+
+    let mut chaining_value = IV;
+    for block in 0..num_blocks {
+        let is_active = block < blocks.len();
+        let w = schedule(blocks.get(block).unwrap_or(&[0; MESSAGE_WORDS]));
+        let mut vars = chaining_value;
+        for t in 0..ROUNDS {
+            let row = block * ROUNDS + t;
+            for (j, &var) in vars.iter().enumerate() {
+                set_bits(j * WORD_BITS, WORD_BITS, row, var.into());
+            }
+            set_bits(W, WORD_BITS, row, w[t].into());
+            set_bits(ACTIVE, 1, row, is_active.into());
+            if t >= MESSAGE_WORDS {
+                let carry = schedule_sum(&w, t) >> 32;
+                set_bits(CARRY_W, 2, row, carry);
+            }
+
+            let [a, b, c, d, e, f, g, h] = vars.map(u64::from);
+            let t1 = h + big_sigma1(e) + ch(e, f, g) + u64::from(K[t]) + u64::from(w[t]);
+            let t2 = big_sigma0(a) + maj(a, b, c);
+            let mut new_vars = [t1 + t2, a, b, c, d + t1, e, f, g];
+            if t == ROUNDS - 1 {
+                // the next row holds the updated chaining value
+                if !is_active {
+                    new_vars = [0; 8];
+                }
+                for (j, new_var) in new_vars.iter_mut().enumerate() {
+                    *new_var += u64::from(chaining_value[j]);
+                    chaining_value[j] = *new_var as u32;
+                }
+            } else {
+                vars = new_vars.map(|var| var as u32);
+            }
+            for (&(carry_column, carry_bits), &new_var) in CARRY_COLUMNS.iter().zip(&new_vars) {
+                set_bits(carry_column, carry_bits, row, new_var >> 32);
+            }
+        }
+    }
+
+    Sha256Trace(Matrix::new(
+        columns
+            .into_iter()
+            .map(|column| column.to_vec_in(GpuAllocator))
+            .collect(),
+    ))
+}
+
+/// Expands the message words of a block into the message schedule
+fn schedule(block: &[u32; MESSAGE_WORDS]) -> [u32; ROUNDS] {
+    let mut w = [0; ROUNDS];
+    w[..MESSAGE_WORDS].copy_from_slice(block);
+    for t in MESSAGE_WORDS..ROUNDS {
+        w[t] = schedule_sum(&w, t) as u32;
+    }
+    w
+}
+
+/// Sum of the words that schedule word `t` is derived from before it's
+/// reduced mod `2^32`
+fn schedule_sum(w: &[u32; ROUNDS], t: usize) -> u64 {
+    let word = |i: usize| u64::from(w[t - i]);
+    small_sigma1(word(2)) + word(7) + small_sigma0(word(15)) + word(16)
+}
+
+fn rotr(x: u64, n: u32) -> u64 {
+    (x as u32).rotate_right(n).into()
+}
+
+fn big_sigma0(x: u64) -> u64 {
+    rotr(x, 2) ^ rotr(x, 13) ^ rotr(x, 22)
+}
+
+fn big_sigma1(x: u64) -> u64 {
+    rotr(x, 6) ^ rotr(x, 11) ^ rotr(x, 25)
+}
+
+fn small_sigma0(x: u64) -> u64 {
+    rotr(x, 7) ^ rotr(x, 18) ^ (x >> 3)
+}
+
+fn small_sigma1(x: u64) -> u64 {
+    rotr(x, 17) ^ rotr(x, 19) ^ (x >> 10)
+}
+
+fn ch(e: u64, f: u64, g: u64) -> u64 {
+    (e & f) ^ (!e & g & 0xffffffff)
+}
+
+fn maj(a: u64, b: u64, c: u64) -> u64 {
+    (a & b) ^ (a & c) ^ (b & c)
+}
@@ -0,0 +1,76 @@
+#ifndef MINISTARK_H
+#define MINISTARK_H
+
+/* Generated with cbindgen:0.26.0 */
+
+/* Generated by cbindgen. Don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The proof is valid
+ */
+#define MINISTARK_VALID 0
+
+/**
+ * The proof is well formed but invalid
+ */
+#define MINISTARK_INVALID 1
+
+/**
+ * A pointer was null but its length wasn't zero
+ */
+#define MINISTARK_ERR_NULL_POINTER -1
+
+/**
+ * The AIR description couldn't be parsed or loaded
+ */
+#define MINISTARK_ERR_INVALID_DESCRIPTION -2
+
+/**
+ * The proof couldn't be deserialized
+ */
+#define MINISTARK_ERR_MALFORMED_PROOF -3
+
+/**
+ * The verifier panicked. This is a bug.
+ */
+#define MINISTARK_ERR_INTERNAL -4
+
+/**
+ * A public input wasn't a canonical field element i.e. it wasn't less than
+ * the modulus
+ */
+#define MINISTARK_ERR_NON_CANONICAL_INPUT -5
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Verifies a proof of the AIR described by the JSON `air_description` with
+ * the given public inputs. Returns `MINISTARK_VALID`, `MINISTARK_INVALID` or
+ * a negative `MINISTARK_ERR_*` code.
+ *
+ * # Safety
+ *
+ * Each pointer must be valid for reads of its length or may be null if the
+ * length is 0. The buffers are only read during the call.
+ */
+int ministark_verify(const uint8_t *proof_ptr,
+                     size_t proof_len,
+                     const uint64_t *public_inputs_ptr,
+                     size_t public_inputs_len,
+                     const uint8_t *air_description_ptr,
+                     size_t air_description_len,
+                     uint32_t security_bits);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* MINISTARK_H */
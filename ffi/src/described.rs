@@ -0,0 +1,168 @@
+//! STARK for AIRs loaded from the portable description format
+//!
+//! Constraints come from an [`AirDescription`] at runtime but the number of
+//! base columns must be known at compile time. [`with_num_base_columns`]
+//! dispatches to an implementation for each supported column count.
+
+use ark_serialize::CanonicalSerialize;
+use ministark::air::AirConfig;
+use ministark::air::AirConfigError;
+use ministark::challenges::Challenges;
+use ministark::constraints::Constraint;
+use ministark::description::AirDescription;
+use ministark::description::DescriptionError;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::hints::Hints;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::random::PublicCoinImpl;
+use ministark::serialize::from_bytes;
+use ministark::serialize::ByteOrder;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::utils::SerdeOutput;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::ProofOptions;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use sha2::Sha256;
+
+/// Largest number of base columns supported by [`with_num_base_columns`]
+pub const MAX_BASE_COLUMNS: usize = 16;
+
+/// Evaluates `$f::<N>(args..)` where `N` is the number of base columns or
+/// `$unsupported` if there are more than [`MAX_BASE_COLUMNS`]
+#[macro_export]
+macro_rules! with_num_base_columns {
+    ($num_columns:expr, $f:ident($($arg:expr),*), $unsupported:expr) => {
+        match $num_columns {
+            1 => $f::<1>($($arg),*),
+            2 => $f::<2>($($arg),*),
+            3 => $f::<3>($($arg),*),
+            4 => $f::<4>($($arg),*),
+            5 => $f::<5>($($arg),*),
+            6 => $f::<6>($($arg),*),
+            7 => $f::<7>($($arg),*),
+            8 => $f::<8>($($arg),*),
+            9 => $f::<9>($($arg),*),
+            10 => $f::<10>($($arg),*),
+            11 => $f::<11>($($arg),*),
+            12 => $f::<12>($($arg),*),
+            13 => $f::<13>($($arg),*),
+            14 => $f::<14>($($arg),*),
+            15 => $f::<15>($($arg),*),
+            16 => $f::<16>($($arg),*),
+            _ => $unsupported,
+        }
+    };
+}
+
+/// Trace with base columns only
+pub struct BaseTrace(pub Matrix<Fp>);
+
+impl Trace for BaseTrace {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn base_columns(&self) -> Option<&Matrix<Fp>> {
+        Some(&self.0)
+    }
+}
+
+/// Returns `value` as a field element or `None` if it's not less than the
+/// modulus. Inputs are never silently reduced so each field element has a
+/// single encoding.
+pub fn canonical_element(value: u64) -> Option<Fp> {
+    from_bytes(&value.to_le_bytes(), ByteOrder::Little).ok()
+}
+
+/// Seeds the public coin with the public inputs, trace length, options and
+/// [`Air::id`]
+pub fn seed_public_coin<C: AirConfig>(air: &Air<C>) -> PublicCoinImpl<C::Fp, Sha256HashFn> {
+    let mut seed = Vec::new();
+    air.public_inputs().serialize_compressed(&mut seed).unwrap();
+    air.trace_len().serialize_compressed(&mut seed).unwrap();
+    air.options().serialize_compressed(&mut seed).unwrap();
+    seed.extend_from_slice(&air.id());
+    PublicCoinImpl::new(Sha256HashFn::hash_chunks([&*seed]))
+}
+
+/// AIR with `N` base columns whose constraints are loaded from a description.
+/// Public inputs are the AIR's hints.
+pub struct DescribedAirConfig<const N: usize>;
+
+impl<const N: usize> AirConfig for DescribedAirConfig<N> {
+    const NUM_BASE_COLUMNS: usize = N;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = Vec<Fp>;
+
+    fn gen_hints(_trace_len: usize, public_inputs: &Vec<Fp>, _: &Challenges<Fp>) -> Hints<Fp> {
+        Hints::new(public_inputs.iter().copied().enumerate().collect())
+    }
+
+    fn constraints(_trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        unreachable!("described AIRs are built with `Air::from_description`")
+    }
+}
+
+pub struct DescribedClaim<const N: usize> {
+    description: AirDescription,
+    public_inputs: Vec<Fp>,
+}
+
+impl<const N: usize> DescribedClaim<N> {
+    /// Checks the description can be loaded before it's used
+    pub fn new(
+        description: AirDescription,
+        public_inputs: Vec<Fp>,
+        options: ProofOptions,
+    ) -> Result<Self, DescriptionError> {
+        Air::<DescribedAirConfig<N>>::from_description(
+            &description,
+            public_inputs.clone(),
+            options,
+        )?;
+        Ok(Self {
+            description,
+            public_inputs,
+        })
+    }
+}
+
+impl<const N: usize> Stark for DescribedClaim<N> {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = DescribedAirConfig<N>;
+    type Digest = SerdeOutput<Sha256>;
+    type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+    type Witness = BaseTrace;
+    type Trace = BaseTrace;
+
+    fn get_public_inputs(&self) -> Vec<Fp> {
+        self.public_inputs.clone()
+    }
+
+    fn generate_trace(&self, witness: BaseTrace) -> BaseTrace {
+        witness
+    }
+
+    fn gen_public_coin(&self, air: &Air<Self::AirConfig>) -> Self::PublicCoin {
+        seed_public_coin(air)
+    }
+
+    fn air(
+        &self,
+        _trace_len: usize,
+        options: ProofOptions,
+    ) -> Result<Air<Self::AirConfig>, AirConfigError> {
+        Air::from_description(&self.description, self.public_inputs.clone(), options).map_err(
+            |error| match error {
+                DescriptionError::InvalidAir { source } => source,
+                error => unreachable!("description was checked when loaded: {error}"),
+            },
+        )
+    }
+}
@@ -0,0 +1,117 @@
+//! C bindings for the miniSTARK verifier
+//!
+//! Lets C, C++, Go (via cgo) and mobile apps verify proofs of AIRs loaded from
+//! the portable description format (see `ministark::description`). The header
+//! `include/ministark.h` is generated by cbindgen when the crate is built.
+//! Proofs are in the canonical compressed serialization and field elements are
+//! Goldilocks elements passed as `uint64_t`. Field elements must be less than
+//! the modulus.
+
+pub mod described;
+
+use ark_serialize::CanonicalDeserialize;
+use described::canonical_element;
+use described::DescribedClaim;
+use ministark::description::AirDescription;
+use ministark::stark::Stark;
+use ministark::Proof;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use std::ffi::c_int;
+use std::panic::catch_unwind;
+use std::slice;
+
+/// The proof is valid
+pub const MINISTARK_VALID: c_int = 0;
+/// The proof is well formed but invalid
+pub const MINISTARK_INVALID: c_int = 1;
+/// A pointer was null but its length wasn't zero
+pub const MINISTARK_ERR_NULL_POINTER: c_int = -1;
+/// The AIR description couldn't be parsed or loaded
+pub const MINISTARK_ERR_INVALID_DESCRIPTION: c_int = -2;
+/// The proof couldn't be deserialized
+pub const MINISTARK_ERR_MALFORMED_PROOF: c_int = -3;
+/// The verifier panicked. This is a bug.
+pub const MINISTARK_ERR_INTERNAL: c_int = -4;
+/// A public input wasn't a canonical field element i.e. it wasn't less than
+/// the modulus
+pub const MINISTARK_ERR_NON_CANONICAL_INPUT: c_int = -5;
+
+/// Returns the slice or `None` if the pointer is null and the length isn't 0
+unsafe fn as_slice<'a, T>(ptr: *const T, len: usize) -> Option<&'a [T]> {
+    if len == 0 {
+        Some(&[])
+    } else if ptr.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(ptr, len))
+    }
+}
+
+fn verify_described<const N: usize>(
+    description: AirDescription,
+    proof: &[u8],
+    public_inputs: Vec<Fp>,
+    security_bits: u32,
+) -> c_int {
+    let Ok(proof) = Proof::<DescribedClaim<N>>::deserialize_compressed(proof) else {
+        return MINISTARK_ERR_MALFORMED_PROOF;
+    };
+    let options = proof.header.options;
+    let Ok(claim) = DescribedClaim::<N>::new(description, public_inputs, options) else {
+        return MINISTARK_ERR_INVALID_DESCRIPTION;
+    };
+    match claim.verify(proof, security_bits) {
+        Ok(()) => MINISTARK_VALID,
+        Err(_) => MINISTARK_INVALID,
+    }
+}
+
+/// Verifies a proof of the AIR described by the JSON `air_description` with
+/// the given public inputs. Returns `MINISTARK_VALID`, `MINISTARK_INVALID` or
+/// a negative `MINISTARK_ERR_*` code.
+///
+/// # Safety
+///
+/// Each pointer must be valid for reads of its length or may be null if the
+/// length is 0. The buffers are only read during the call.
+#[no_mangle]
+pub unsafe extern "C" fn ministark_verify(
+    proof_ptr: *const u8,
+    proof_len: usize,
+    public_inputs_ptr: *const u64,
+    public_inputs_len: usize,
+    air_description_ptr: *const u8,
+    air_description_len: usize,
+    security_bits: u32,
+) -> c_int {
+    let (Some(proof), Some(public_inputs), Some(air_description)) = (
+        as_slice(proof_ptr, proof_len),
+        as_slice(public_inputs_ptr, public_inputs_len),
+        as_slice(air_description_ptr, air_description_len),
+    ) else {
+        return MINISTARK_ERR_NULL_POINTER;
+    };
+    // unwinding into C is undefined behaviour
+    catch_unwind(|| {
+        let Ok(description) = serde_json::from_slice::<AirDescription>(air_description) else {
+            return MINISTARK_ERR_INVALID_DESCRIPTION;
+        };
+        if description.num_extension_columns != 0 {
+            return MINISTARK_ERR_INVALID_DESCRIPTION;
+        }
+        let Some(public_inputs) = public_inputs
+            .iter()
+            .copied()
+            .map(canonical_element)
+            .collect::<Option<Vec<Fp>>>()
+        else {
+            return MINISTARK_ERR_NON_CANONICAL_INPUT;
+        };
+        with_num_base_columns!(
+            description.num_base_columns,
+            verify_described(description, proof, public_inputs, security_bits),
+            MINISTARK_ERR_INVALID_DESCRIPTION
+        )
+    })
+    .unwrap_or(MINISTARK_ERR_INTERNAL)
+}
@@ -0,0 +1,150 @@
+use ark_serialize::CanonicalSerialize;
+use ministark::air::AirConfig;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::hints::Hints;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::ProofOptions;
+use ministark_ffi::described::BaseTrace;
+use ministark_ffi::described::DescribedClaim;
+use ministark_ffi::ministark_verify;
+use ministark_ffi::MINISTARK_ERR_INVALID_DESCRIPTION;
+use ministark_ffi::MINISTARK_ERR_NON_CANONICAL_INPUT;
+use ministark_ffi::MINISTARK_ERR_NULL_POINTER;
+use ministark_ffi::MINISTARK_INVALID;
+use ministark_ffi::MINISTARK_VALID;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use std::ffi::c_int;
+
+const TRACE_LEN: usize = 64;
+const OPTIONS: ProofOptions = ProofOptions::new(16, 4, 0, 2, 4);
+/// Goldilocks modulus
+const MODULUS: u64 = 0xFFFF_FFFF_0000_0001;
+
+/// Fibonacci AIR whose description is loaded by the bindings
+struct FibAirConfig;
+
+impl AirConfig for FibAirConfig {
+    const NUM_BASE_COLUMNS: usize = 2;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = Fp;
+
+    fn gen_hints(_trace_len: usize, claim: &Fp, _: &Challenges<Fp>) -> Hints<Fp> {
+        Hints::new(vec![(0, *claim)])
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::from(1u8)));
+        let boundary = [0.curr() - one, 1.curr() - one]
+            .into_iter()
+            .map(Constraint::when_first_row);
+        let transition = [0.next() - 1.curr(), 1.next() - 0.curr() - 1.curr()]
+            .into_iter()
+            .map(|expr| Constraint::when_transition(trace_len, expr));
+        let terminal = [1.curr() - AlgebraicItem::Hint(0)]
+            .into_iter()
+            .map(|expr| Constraint::when_last_row(trace_len, expr));
+        boundary.chain(transition).chain(terminal).collect()
+    }
+}
+
+/// Returns the JSON description, the claimed last value and the proof
+fn gen_proof() -> (Vec<u8>, u64, Vec<u8>) {
+    // values stay below the modulus for this many rows
+    let (mut a, mut b) = (1u64, 1u64);
+    let mut rows = Vec::new();
+    for _ in 0..TRACE_LEN {
+        rows.push(vec![Fp::from(a), Fp::from(b)]);
+        (a, b) = (b, a + b);
+    }
+    let claim = rows.last().unwrap()[1];
+    let description = Air::<FibAirConfig>::new(TRACE_LEN, claim, OPTIONS).export_description();
+    let json = serde_json::to_vec(&description).unwrap();
+    let stark = DescribedClaim::<2>::new(description, vec![claim], OPTIONS).unwrap();
+    let trace = BaseTrace(Matrix::from_rows(rows));
+    let proof = pollster::block_on(stark.prove(OPTIONS, trace)).unwrap();
+    let mut proof_bytes = Vec::new();
+    proof.serialize_compressed(&mut proof_bytes).unwrap();
+    // `a` holds the second column of the last row
+    (json, a, proof_bytes)
+}
+
+fn verify(proof: &[u8], public_inputs: &[u64], description: &[u8]) -> c_int {
+    unsafe {
+        ministark_verify(
+            proof.as_ptr(),
+            proof.len(),
+            public_inputs.as_ptr(),
+            public_inputs.len(),
+            description.as_ptr(),
+            description.len(),
+            0,
+        )
+    }
+}
+
+#[test]
+fn verifies_through_the_c_abi() {
+    let (description, claim, proof) = gen_proof();
+
+    assert_eq!(MINISTARK_VALID, verify(&proof, &[claim], &description));
+    assert_eq!(
+        MINISTARK_INVALID,
+        verify(&proof, &[claim + 1], &description)
+    );
+}
+
+#[test]
+fn rejects_non_canonical_public_inputs() {
+    let (description, claim, proof) = gen_proof();
+
+    // both would be reduced to a valid field element
+    for input in [MODULUS, u64::MAX] {
+        assert_eq!(
+            MINISTARK_ERR_NON_CANONICAL_INPUT,
+            verify(&proof, &[claim, input], &description)
+        );
+    }
+}
+
+#[test]
+fn rejects_null_pointers() {
+    let (description, claim, proof) = gen_proof();
+
+    let result = unsafe {
+        ministark_verify(
+            std::ptr::null(),
+            proof.len(),
+            [claim].as_ptr(),
+            1,
+            description.as_ptr(),
+            description.len(),
+            0,
+        )
+    };
+
+    assert_eq!(MINISTARK_ERR_NULL_POINTER, result);
+}
+
+#[test]
+fn rejects_invalid_descriptions() {
+    let (description, claim, proof) = gen_proof();
+    let mut wrong_id: serde_json::Value = serde_json::from_slice(&description).unwrap();
+    wrong_id["air_id"] = "00".repeat(32).into();
+    let wrong_id = serde_json::to_vec(&wrong_id).unwrap();
+
+    assert_eq!(
+        MINISTARK_ERR_INVALID_DESCRIPTION,
+        verify(&proof, &[claim], b"not json")
+    );
+    assert_eq!(
+        MINISTARK_ERR_INVALID_DESCRIPTION,
+        verify(&proof, &[claim], &wrong_id)
+    );
+}
@@ -249,6 +249,8 @@ pub mod p36185027886661312136973227830950701056231072153315966999730920561358720
         impl GpuField for Fp {
             type FftField = Self;
 
+            const GPU_SUPPORTED: bool = false;
+
             fn field_name() -> String {
                 "p3618502788666131213697322783095070105623107215331596699973092056135872020481_fp"
                     .to_string()
@@ -13,10 +13,20 @@ pub mod prelude;
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 pub mod stage;
 pub mod utils;
+#[cfg(feature = "webgpu")]
+pub mod webgpu;
 
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 pub use metal;
 
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::string::String;
+    pub use alloc::string::ToString;
+}
+#[cfg(feature = "webgpu")]
+pub use wgpu;
+
 /// A trait to be implemented if the field can be used for FFTs on the GPU.
 pub trait GpuFftField: GpuField<FftField = Self> {}
 
@@ -33,6 +43,10 @@ pub trait GpuFrom<Rhs> {}
 pub trait GpuField: GpuMul<Self> + GpuAdd<Self> + GpuMul<Self::FftField> + Sized {
     type FftField: GpuFftField;
 
+    /// Whether GPU kernels exist for this field. Fields implemented with
+    /// [`impl_cpu_field`] always use the CPU.
+    const GPU_SUPPORTED: bool = true;
+
     // Used to select which GPU kernel to call.
     fn field_name() -> alloc::string::String;
 }
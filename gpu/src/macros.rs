@@ -434,3 +434,35 @@ macro_rules! wrap_field {
         }
     };
 }
+
+/// Implements the GPU marker traits for a field that doesn't have GPU kernels.
+/// Any arkworks FFT field can then be used by a prover that runs entirely on
+/// the CPU. The field's two-adic subgroup must be large enough for the
+/// evaluation domains of the proof.
+#[macro_export]
+macro_rules! impl_cpu_field {
+    ($field:ty) => {
+        impl $crate::GpuField for $field {
+            type FftField = Self;
+
+            const GPU_SUPPORTED: bool = false;
+
+            fn field_name() -> $crate::__private::String {
+                use $crate::__private::ToString;
+                core::any::type_name::<$field>().to_string()
+            }
+        }
+
+        impl $crate::GpuFftField for $field {}
+
+        impl $crate::GpuMul<$field> for $field {}
+
+        impl $crate::GpuMul<&$field> for $field {}
+
+        impl $crate::GpuAdd<$field> for $field {}
+
+        impl $crate::GpuAdd<&$field> for $field {}
+
+        impl $crate::GpuFrom<$field> for $field {}
+    };
+}
@@ -330,6 +330,23 @@ pub fn get_planner() -> &'static Planner {
     &PLANNER
 }
 
+static DEVICE_PLANNERS: Lazy<Vec<Planner>> = Lazy::new(|| {
+    metal::Device::all()
+        .iter()
+        .map(|device| Planner::new(device))
+        .collect()
+});
+
+/// Returns the planner for the device at `index` in [`metal::Device::all`]
+pub fn get_device_planner(index: usize) -> Option<&'static Planner> {
+    DEVICE_PLANNERS.get(index)
+}
+
+/// Returns the number of GPUs on the host
+pub fn num_devices() -> usize {
+    DEVICE_PLANNERS.len()
+}
+
 pub struct Planner {
     pub library: metal::Library,
     pub command_queue: Rc<metal::CommandQueue>,
@@ -349,6 +366,14 @@ impl Planner {
         }
     }
 
+    /// Returns the number of bytes of memory the device can use without
+    /// degrading performance
+    pub fn max_working_set_size(&self) -> usize {
+        self.command_queue
+            .device()
+            .recommended_max_working_set_size() as usize
+    }
+
     #[cfg(feature = "arkworks")]
     pub fn plan_fft<F: GpuField + ark_ff::Field>(
         &self,
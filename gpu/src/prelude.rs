@@ -1,5 +1,9 @@
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+pub use crate::plan::get_device_planner;
+#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 pub use crate::plan::get_planner;
+#[cfg(all(target_arch = "aarch64", target_os = "macos"))]
+pub use crate::plan::num_devices;
 #[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "arkworks"))]
 pub use crate::plan::GpuFft;
 #[cfg(all(target_arch = "aarch64", target_os = "macos", feature = "arkworks"))]
@@ -0,0 +1,502 @@
+//! WebGPU (wgpu) compute backend. Unlike the Metal backend this runs in
+//! browsers and on Windows/Linux machines. Currently supports FFTs over
+//! subgroups and pointwise addition/multiplication for the 64-bit field
+//! `p = 2^64 - 2^32 + 1` and building SHA-256 Merkle trees.
+use crate::GpuField;
+use alloc::vec::Vec;
+use core::mem::size_of;
+use core::mem::size_of_val;
+use wgpu::util::DeviceExt;
+
+const SHADER: &str = include_str!("webgpu/goldilocks.wgsl");
+
+const SHA256_SHADER: &str = include_str!("webgpu/sha256.wgsl");
+
+/// Name of the only field currently supported by the WebGPU backend
+const SUPPORTED_FIELD: &str = "p18446744069414584321_fp";
+
+/// Threads per workgroup. Must match `@workgroup_size` in the shaders.
+const WORKGROUP_SIZE: u32 = 64;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct Params {
+    n: u32,
+    num_boxes: u32,
+    log_n: u32,
+    _padding: u32,
+}
+
+/// Number of columns in flight in [`WebGpuContext::fft_columns`]
+const NUM_IN_FLIGHT: usize = 2;
+
+type MapReceiver = futures_channel::oneshot::Receiver<Result<(), wgpu::BufferAsyncError>>;
+
+/// Submission of a transform and the pending map of its read back buffer
+type InFlight = (wgpu::SubmissionIndex, MapReceiver);
+
+/// Device buffer for one column with mapped staging buffers for uploading it
+/// and reading it back
+struct ColumnSlot {
+    upload: wgpu::Buffer,
+    values: wgpu::Buffer,
+    download: wgpu::Buffer,
+    /// Pending map of the upload buffer. `None` once it's mapped.
+    upload_mapped: Option<MapReceiver>,
+    /// Index of the column being transformed
+    in_flight: Option<(usize, InFlight)>,
+}
+
+impl ColumnSlot {
+    fn new(device: &wgpu::Device, size: u64) -> Self {
+        let buffer = |usage, mapped_at_creation| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size,
+                usage,
+                mapped_at_creation,
+            })
+        };
+        Self {
+            upload: buffer(
+                wgpu::BufferUsages::MAP_WRITE | wgpu::BufferUsages::COPY_SRC,
+                true,
+            ),
+            values: buffer(
+                wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+                false,
+            ),
+            download: buffer(
+                wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                false,
+            ),
+            upload_mapped: None,
+            in_flight: None,
+        }
+    }
+}
+
+/// Starts mapping the whole buffer
+fn map(buffer: &wgpu::Buffer, mode: wgpu::MapMode) -> MapReceiver {
+    let (sender, receiver) = futures_channel::oneshot::channel();
+    buffer.slice(..).map_async(mode, move |result| {
+        let _ = sender.send(result);
+    });
+    receiver
+}
+
+/// Context used by the prover (see [`WebGpuContext::install`])
+#[cfg(not(target_arch = "wasm32"))]
+static INSTALLED: once_cell::sync::OnceCell<WebGpuContext> = once_cell::sync::OnceCell::new();
+
+pub struct WebGpuContext {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    fft_butterfly: wgpu::ComputePipeline,
+    bit_reverse: wgpu::ComputePipeline,
+    mul_assign: wgpu::ComputePipeline,
+    add_assign: wgpu::ComputePipeline,
+    sha256_merge_leaves: wgpu::ComputePipeline,
+    sha256_merge_nodes: wgpu::ComputePipeline,
+}
+
+impl WebGpuContext {
+    /// Requests a device from the default adapter.
+    /// Returns `None` if no suitable adapter is available.
+    pub async fn new() -> Option<Self> {
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await?;
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor::default(), None)
+            .await
+            .ok()?;
+        let module = |label, source: &str| {
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            })
+        };
+        let goldilocks = module("goldilocks", SHADER);
+        let sha256 = module("sha256", SHA256_SHADER);
+        let pipeline = |module, entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: None,
+                module,
+                entry_point,
+            })
+        };
+        let fft_butterfly = pipeline(&goldilocks, "fft_butterfly");
+        let bit_reverse = pipeline(&goldilocks, "bit_reverse");
+        let mul_assign = pipeline(&goldilocks, "mul_assign");
+        let add_assign = pipeline(&goldilocks, "add_assign");
+        let sha256_merge_leaves = pipeline(&sha256, "merge_leaves");
+        let sha256_merge_nodes = pipeline(&sha256, "merge_nodes");
+        Some(Self {
+            device,
+            queue,
+            fft_butterfly,
+            bit_reverse,
+            mul_assign,
+            add_assign,
+            sha256_merge_leaves,
+            sha256_merge_nodes,
+        })
+    }
+
+    /// Makes this the context the prover offloads work to e.g. building
+    /// SHA-256 Merkle trees. Returns the context back if one is already
+    /// installed.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn install(self) -> Result<(), Self> {
+        INSTALLED.set(self)
+    }
+
+    /// Returns the context installed with [`WebGpuContext::install`]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn installed() -> Option<&'static Self> {
+        INSTALLED.get()
+    }
+
+    /// Evaluates the polynomial with coefficients `values` over the domain
+    /// (in natural order). Only subgroups are supported (no coset offset).
+    #[cfg(feature = "arkworks")]
+    pub async fn fft<F: GpuField + ark_ff::FftField>(
+        &self,
+        values: &mut [F],
+        domain: ark_poly::Radix2EvaluationDomain<F>,
+    ) {
+        self.fft_columns(&mut [values], domain).await;
+    }
+
+    /// Evaluates the polynomials with coefficients `columns` over the domain
+    /// (in natural order). Transfers overlap with kernel execution: while one
+    /// column is transformed the previous column is read back and the next
+    /// one is uploaded. Staging buffers are allocated once per column in
+    /// flight and re-mapped between columns. Only subgroups are supported.
+    #[cfg(feature = "arkworks")]
+    pub async fn fft_columns<F: GpuField + ark_ff::FftField>(
+        &self,
+        columns: &mut [&mut [F]],
+        domain: ark_poly::Radix2EvaluationDomain<F>,
+    ) {
+        use ark_ff::One;
+        use ark_ff::Zero;
+        use ark_poly::EvaluationDomain;
+        assert_supported_field::<F>();
+        let n = domain.size();
+        assert!(columns.iter().all(|column| column.len() == n));
+        assert!(n >= 2);
+        assert!(domain.offset.is_one(), "coset FFTs are not supported");
+
+        let mut twiddles = alloc::vec![F::zero(); n / 2];
+        crate::utils::fill_twiddles(&mut twiddles, domain.group_gen);
+        crate::utils::bit_reverse(&mut twiddles);
+        let twiddles_buffer = self.storage_buffer(as_bytes(&twiddles));
+
+        let size = (n * size_of::<F>()) as u64;
+        let mut slots = (0..NUM_IN_FLIGHT.min(columns.len()))
+            .map(|_| ColumnSlot::new(&self.device, size))
+            .collect::<Vec<ColumnSlot>>();
+        for i in 0..columns.len() {
+            let slot = &mut slots[i % NUM_IN_FLIGHT];
+            if let Some((j, transform)) = slot.in_flight.take() {
+                self.download(slot, transform, columns[j]).await;
+            }
+            self.upload(slot, as_bytes(columns[i])).await;
+
+            let mut encoder = self.device.create_command_encoder(&Default::default());
+            encoder.copy_buffer_to_buffer(&slot.upload, 0, &slot.values, 0, size);
+            self.encode_fft(&mut encoder, &slot.values, &twiddles_buffer, n);
+            encoder.copy_buffer_to_buffer(&slot.values, 0, &slot.download, 0, size);
+            let submission = self.queue.submit(Some(encoder.finish()));
+            slot.upload_mapped = Some(map(&slot.upload, wgpu::MapMode::Write));
+            let download = map(&slot.download, wgpu::MapMode::Read);
+            slot.in_flight = Some((i, (submission, download)));
+        }
+        for slot in &mut slots {
+            if let Some((j, transform)) = slot.in_flight.take() {
+                self.download(slot, transform, columns[j]).await;
+            }
+        }
+    }
+
+    #[cfg(feature = "arkworks")]
+    fn encode_fft(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        values: &wgpu::Buffer,
+        twiddles: &wgpu::Buffer,
+        n: usize,
+    ) {
+        let log_n = n.ilog2();
+        for stage in 0..log_n {
+            let params = Params {
+                n: n.try_into().unwrap(),
+                num_boxes: 1 << stage,
+                log_n,
+                ..Default::default()
+            };
+            self.dispatch(
+                encoder,
+                &self.fft_butterfly,
+                values,
+                Some(twiddles),
+                params,
+                n / 2,
+            );
+        }
+        let params = Params {
+            n: n.try_into().unwrap(),
+            log_n,
+            ..Default::default()
+        };
+        self.dispatch(encoder, &self.bit_reverse, values, None, params, n);
+    }
+
+    /// Copies `bytes` into the slot's upload buffer once the previous upload
+    /// has been consumed
+    async fn upload(&self, slot: &mut ColumnSlot, bytes: &[u8]) {
+        if let Some(mapped) = slot.upload_mapped.take() {
+            // no-op on the web where the browser drives the device
+            self.device.poll(wgpu::Maintain::Poll);
+            mapped.await.unwrap().expect("failed to map staging buffer");
+        }
+        slot.upload
+            .slice(..)
+            .get_mapped_range_mut()
+            .copy_from_slice(bytes);
+        slot.upload.unmap();
+    }
+
+    /// Waits for a transform to finish without waiting on later submissions
+    /// and copies the result into `dst`
+    async fn download<F>(&self, slot: &ColumnSlot, transform: InFlight, dst: &mut [F]) {
+        let (submission, mapped) = transform;
+        // no-op on the web where the browser drives the device
+        self.device
+            .poll(wgpu::Maintain::WaitForSubmissionIndex(submission));
+        mapped.await.unwrap().expect("failed to map staging buffer");
+        as_bytes_mut(dst).copy_from_slice(&slot.download.slice(..).get_mapped_range());
+        slot.download.unmap();
+    }
+
+    /// Computes `lhs[i] *= rhs[i]`
+    pub async fn mul_assign<F: GpuField>(&self, lhs: &mut [F], rhs: &[F]) {
+        self.pointwise(&self.mul_assign, lhs, rhs).await;
+    }
+
+    /// Computes `lhs[i] += rhs[i]`
+    pub async fn add_assign<F: GpuField>(&self, lhs: &mut [F], rhs: &[F]) {
+        self.pointwise(&self.add_assign, lhs, rhs).await;
+    }
+
+    /// Returns the nodes of a SHA-256 Merkle tree over `leaves`. Nodes are
+    /// laid out like the CPU implementation: node `1` is the root, the children
+    /// of node `i` are nodes `2 * i` and `2 * i + 1` and node `0` is unused.
+    /// Each node is the digest of its children's digests concatenated.
+    ///
+    /// # Panics
+    /// Panics if the number of leaves isn't a power of two greater than one
+    pub async fn sha256_merkle_nodes(&self, leaves: &[[u8; 32]]) -> Vec<[u8; 32]> {
+        let (encoder, nodes_buffer) = self.encode_sha256_merkle_nodes(leaves);
+        let mut nodes = alloc::vec![[0; 32]; leaves.len()];
+        self.read_back(encoder, &nodes_buffer, &mut nodes).await;
+        nodes
+    }
+
+    /// Same as [`WebGpuContext::sha256_merkle_nodes`] but blocks until the
+    /// tree is built. Not available on the web where the browser drives the
+    /// device.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn sha256_merkle_nodes_blocking(&self, leaves: &[[u8; 32]]) -> Vec<[u8; 32]> {
+        let (encoder, nodes_buffer) = self.encode_sha256_merkle_nodes(leaves);
+        let mut nodes = alloc::vec![[0; 32]; leaves.len()];
+        let (staging_buffer, mut mapped) = self.start_read_back(encoder, &nodes_buffer, &nodes);
+        self.device.poll(wgpu::Maintain::Wait);
+        mapped
+            .try_recv()
+            .unwrap()
+            .expect("map completes once the device is idle")
+            .expect("failed to map staging buffer");
+        finish_read_back(&staging_buffer, &mut nodes);
+        nodes
+    }
+
+    fn encode_sha256_merkle_nodes(
+        &self,
+        leaves: &[[u8; 32]],
+    ) -> (wgpu::CommandEncoder, wgpu::Buffer) {
+        let n = leaves.len();
+        assert!(n >= 2 && n.is_power_of_two());
+        let leaves_buffer = self.storage_buffer(as_bytes(leaves));
+        let nodes_buffer = self.storage_buffer(&alloc::vec![0; size_of_val(leaves)]);
+        let params = |layer_size: usize| Params {
+            n: layer_size.try_into().unwrap(),
+            ..Default::default()
+        };
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        self.dispatch(
+            &mut encoder,
+            &self.sha256_merge_leaves,
+            &nodes_buffer,
+            Some(&leaves_buffer),
+            params(n / 2),
+            n / 2,
+        );
+        let mut layer_size = n / 4;
+        while layer_size > 0 {
+            self.dispatch(
+                &mut encoder,
+                &self.sha256_merge_nodes,
+                &nodes_buffer,
+                None,
+                params(layer_size),
+                layer_size,
+            );
+            layer_size /= 2;
+        }
+        (encoder, nodes_buffer)
+    }
+
+    async fn pointwise<F: GpuField>(
+        &self,
+        pipeline: &wgpu::ComputePipeline,
+        lhs: &mut [F],
+        rhs: &[F],
+    ) {
+        assert_supported_field::<F>();
+        assert_eq!(lhs.len(), rhs.len());
+        if lhs.is_empty() {
+            return;
+        }
+        let n = lhs.len();
+        let lhs_buffer = self.storage_buffer(as_bytes(lhs));
+        let rhs_buffer = self.storage_buffer(as_bytes(rhs));
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        let params = Params {
+            n: n.try_into().unwrap(),
+            ..Default::default()
+        };
+        self.dispatch(
+            &mut encoder,
+            pipeline,
+            &lhs_buffer,
+            Some(&rhs_buffer),
+            params,
+            n,
+        );
+        self.read_back(encoder, &lhs_buffer, lhs).await;
+    }
+
+    fn storage_buffer(&self, contents: &[u8]) -> wgpu::Buffer {
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+            })
+    }
+
+    fn dispatch(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::ComputePipeline,
+        values: &wgpu::Buffer,
+        operands: Option<&wgpu::Buffer>,
+        params: Params,
+        num_threads: usize,
+    ) {
+        let params_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: as_bytes(core::slice::from_ref(&params)),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let mut entries = alloc::vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: values.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: params_buffer.as_entire_binding(),
+            },
+        ];
+        if let Some(operands) = operands {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 1,
+                resource: operands.as_entire_binding(),
+            });
+        }
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+
+        let num_threads = u32::try_from(num_threads).unwrap();
+        let num_workgroups = (num_threads + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+        let mut pass = encoder.begin_compute_pass(&Default::default());
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(num_workgroups, 1, 1);
+    }
+
+    async fn read_back<F>(&self, encoder: wgpu::CommandEncoder, src: &wgpu::Buffer, dst: &mut [F]) {
+        let (staging_buffer, mapped) = self.start_read_back(encoder, src, dst);
+        // no-op on the web where the browser drives the device
+        self.device.poll(wgpu::Maintain::Wait);
+        mapped.await.unwrap().expect("failed to map staging buffer");
+        finish_read_back(&staging_buffer, dst);
+    }
+
+    /// Submits `encoder` with a copy of `src` into a new staging buffer and
+    /// starts mapping the staging buffer
+    fn start_read_back<F>(
+        &self,
+        mut encoder: wgpu::CommandEncoder,
+        src: &wgpu::Buffer,
+        dst: &[F],
+    ) -> (wgpu::Buffer, MapReceiver) {
+        let size = size_of_val(dst) as u64;
+        let staging_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        encoder.copy_buffer_to_buffer(src, 0, &staging_buffer, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+        let mapped = map(&staging_buffer, wgpu::MapMode::Read);
+        (staging_buffer, mapped)
+    }
+}
+
+/// Copies a mapped staging buffer into `dst`
+fn finish_read_back<F>(staging_buffer: &wgpu::Buffer, dst: &mut [F]) {
+    as_bytes_mut(dst).copy_from_slice(&staging_buffer.slice(..).get_mapped_range());
+    staging_buffer.unmap();
+}
+
+fn assert_supported_field<F: GpuField>() {
+    assert_eq!(SUPPORTED_FIELD, F::field_name(), "field is not supported");
+    assert_eq!(size_of::<F>(), size_of::<u64>());
+}
+
+fn as_bytes<T>(values: &[T]) -> &[u8] {
+    // SAFETY: only used for field elements, digests and plain data structs
+    unsafe { core::slice::from_raw_parts(values.as_ptr().cast(), size_of_val(values)) }
+}
+
+fn as_bytes_mut<T>(values: &mut [T]) -> &mut [u8] {
+    // SAFETY: only used for field elements and digests
+    unsafe { core::slice::from_raw_parts_mut(values.as_mut_ptr().cast(), size_of_val(values)) }
+}
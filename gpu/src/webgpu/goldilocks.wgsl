@@ -0,0 +1,150 @@
+// Kernels for the field `p = 2^64 - 2^32 + 1` (elements in Montgomery form).
+// WGSL has no 64-bit integers so elements are stored as `vec2<u32>(lo, hi)`.
+// Field arithmetic is a port of the Metal implementation in felt_u64.h.metal
+
+struct Params {
+    n: u32,
+    num_boxes: u32,
+    log_n: u32,
+    _padding: u32,
+}
+
+struct U128 {
+    lo: vec2<u32>,
+    hi: vec2<u32>,
+}
+
+@group(0) @binding(0) var<storage, read_write> vals: array<vec2<u32>>;
+@group(0) @binding(1) var<storage, read> operands: array<vec2<u32>>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+// Field modulus `p = 2^64 - 2^32 + 1`
+const N: vec2<u32> = vec2<u32>(1u, 0xffffffffu);
+const EPSILON: vec2<u32> = vec2<u32>(0xffffffffu, 0u);
+
+fn u64_lt(a: vec2<u32>, b: vec2<u32>) -> bool {
+    return a.y < b.y || (a.y == b.y && a.x < b.x);
+}
+
+// wrapping addition
+fn u64_add(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    let lo = a.x + b.x;
+    let carry = select(0u, 1u, lo < a.x);
+    return vec2<u32>(lo, a.y + b.y + carry);
+}
+
+// wrapping subtraction
+fn u64_sub(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    let borrow = select(0u, 1u, a.x < b.x);
+    return vec2<u32>(a.x - b.x, a.y - b.y - borrow);
+}
+
+// full 32x32 bit multiplication
+fn mul32(a: u32, b: u32) -> vec2<u32> {
+    let a0 = a & 0xffffu;
+    let a1 = a >> 16u;
+    let b0 = b & 0xffffu;
+    let b1 = b >> 16u;
+    let p00 = a0 * b0;
+    let p01 = a0 * b1;
+    let p10 = a1 * b0;
+    let p11 = a1 * b1;
+    let mid = p01 + (p00 >> 16u) + (p10 & 0xffffu);
+    let lo = (mid << 16u) | (p00 & 0xffffu);
+    let hi = p11 + (mid >> 16u) + (p10 >> 16u);
+    return vec2<u32>(lo, hi);
+}
+
+// full 64x64 bit multiplication
+fn mul64(a: vec2<u32>, b: vec2<u32>) -> U128 {
+    let ll = mul32(a.x, b.x);
+    let lh = mul32(a.x, b.y);
+    let hl = mul32(a.y, b.x);
+    let hh = mul32(a.y, b.y);
+    let t1 = u64_add(u64_add(vec2<u32>(ll.y, 0u), vec2<u32>(lh.x, 0u)), vec2<u32>(hl.x, 0u));
+    var t2 = u64_add(vec2<u32>(lh.y, 0u), vec2<u32>(hl.y, 0u));
+    t2 = u64_add(t2, vec2<u32>(hh.x, 0u));
+    t2 = u64_add(t2, vec2<u32>(t1.y, 0u));
+    return U128(vec2<u32>(ll.x, t1.x), vec2<u32>(t2.x, hh.y + t2.y));
+}
+
+fn fp_add(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    // We compute a + b = a - (p - b).
+    let tmp = u64_sub(N, b);
+    let x1 = u64_sub(a, tmp);
+    if u64_lt(a, tmp) {
+        return u64_sub(x1, EPSILON);
+    }
+    return x1;
+}
+
+fn fp_sub(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    let x1 = u64_sub(a, b);
+    if u64_lt(a, b) {
+        return u64_sub(x1, EPSILON);
+    }
+    return x1;
+}
+
+// Montgomery multiplication
+fn fp_mul(lhs: vec2<u32>, rhs: vec2<u32>) -> vec2<u32> {
+    let x = mul64(lhs, rhs);
+    let xl = x.lo;
+    let xh = x.hi;
+    let a = u64_add(xl, vec2<u32>(0u, xl.x));
+    var b = u64_sub(a, vec2<u32>(a.y, 0u));
+    if u64_lt(a, xl) {
+        b = u64_sub(b, vec2<u32>(1u, 0u));
+    }
+    let r = u64_sub(xh, b);
+    if u64_lt(xh, b) {
+        return u64_sub(r, EPSILON);
+    }
+    return r;
+}
+
+// Performs a single iteration of Cooley-Tukey FFT
+@compute @workgroup_size(64)
+fn fft_butterfly(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let tid = gid.x;
+    if tid >= params.n / 2u {
+        return;
+    }
+    let input_step = (params.n / params.num_boxes) / 2u;
+    let box_id = tid / input_step;
+    let target_index = box_id * input_step * 2u + (tid % input_step);
+    let p = vals[target_index];
+    let q = fp_mul(vals[target_index + input_step], operands[box_id]);
+    vals[target_index] = fp_add(p, q);
+    vals[target_index + input_step] = fp_sub(p, q);
+}
+
+@compute @workgroup_size(64)
+fn bit_reverse(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let i = gid.x;
+    if i >= params.n {
+        return;
+    }
+    let ri = reverseBits(i) >> (32u - params.log_n);
+    if i < ri {
+        let tmp = vals[i];
+        vals[i] = vals[ri];
+        vals[ri] = tmp;
+    }
+}
+
+@compute @workgroup_size(64)
+fn mul_assign(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let i = gid.x;
+    if i < params.n {
+        vals[i] = fp_mul(vals[i], operands[i]);
+    }
+}
+
+@compute @workgroup_size(64)
+fn add_assign(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let i = gid.x;
+    if i < params.n {
+        vals[i] = fp_add(vals[i], operands[i]);
+    }
+}
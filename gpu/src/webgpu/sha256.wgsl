@@ -0,0 +1,127 @@
+// SHA-256 kernels for building Merkle trees. Node `i` has children `2 * i`
+// and `2 * i + 1` and the nodes of the layer of size `n` start at index `n`.
+// Digests are stored as 8 words in the byte order of the digest so words are
+// byte swapped when they're loaded and stored.
+
+struct Params {
+    n: u32,
+    num_boxes: u32,
+    log_n: u32,
+    _padding: u32,
+}
+
+@group(0) @binding(0) var<storage, read_write> nodes: array<u32>;
+@group(0) @binding(1) var<storage, read> leaves: array<u32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+var<private> K: array<u32, 64> = array<u32, 64>(
+    0x428a2f98u, 0x71374491u, 0xb5c0fbcfu, 0xe9b5dba5u, 0x3956c25bu, 0x59f111f1u, 0x923f82a4u, 0xab1c5ed5u,
+    0xd807aa98u, 0x12835b01u, 0x243185beu, 0x550c7dc3u, 0x72be5d74u, 0x80deb1feu, 0x9bdc06a7u, 0xc19bf174u,
+    0xe49b69c1u, 0xefbe4786u, 0x0fc19dc6u, 0x240ca1ccu, 0x2de92c6fu, 0x4a7484aau, 0x5cb0a9dcu, 0x76f988dau,
+    0x983e5152u, 0xa831c66du, 0xb00327c8u, 0xbf597fc7u, 0xc6e00bf3u, 0xd5a79147u, 0x06ca6351u, 0x14292967u,
+    0x27b70a85u, 0x2e1b2138u, 0x4d2c6dfcu, 0x53380d13u, 0x650a7354u, 0x766a0abbu, 0x81c2c92eu, 0x92722c85u,
+    0xa2bfe8a1u, 0xa81a664bu, 0xc24b8b70u, 0xc76c51a3u, 0xd192e819u, 0xd6990624u, 0xf40e3585u, 0x106aa070u,
+    0x19a4c116u, 0x1e376c08u, 0x2748774cu, 0x34b0bcb5u, 0x391c0cb3u, 0x4ed8aa4au, 0x5b9cca4fu, 0x682e6ff3u,
+    0x748f82eeu, 0x78a5636fu, 0x84c87814u, 0x8cc70208u, 0x90befffau, 0xa4506cebu, 0xbef9a3f7u, 0xc67178f2u,
+);
+
+fn bswap(x: u32) -> u32 {
+    return (x << 24u) | ((x << 8u) & 0xff0000u) | ((x >> 8u) & 0xff00u) | (x >> 24u);
+}
+
+fn rotr(x: u32, n: u32) -> u32 {
+    return (x >> n) | (x << (32u - n));
+}
+
+// Applies the compression function to the block in `w[0..16]`
+fn compress(state: ptr<function, array<u32, 8>>, w: ptr<function, array<u32, 64>>) {
+    for (var i = 16u; i < 64u; i++) {
+        let w15 = (*w)[i - 15u];
+        let w2 = (*w)[i - 2u];
+        let s0 = rotr(w15, 7u) ^ rotr(w15, 18u) ^ (w15 >> 3u);
+        let s1 = rotr(w2, 17u) ^ rotr(w2, 19u) ^ (w2 >> 10u);
+        (*w)[i] = (*w)[i - 16u] + s0 + (*w)[i - 7u] + s1;
+    }
+    var a = (*state)[0];
+    var b = (*state)[1];
+    var c = (*state)[2];
+    var d = (*state)[3];
+    var e = (*state)[4];
+    var f = (*state)[5];
+    var g = (*state)[6];
+    var h = (*state)[7];
+    for (var i = 0u; i < 64u; i++) {
+        let s1 = rotr(e, 6u) ^ rotr(e, 11u) ^ rotr(e, 25u);
+        let ch = (e & f) ^ (~e & g);
+        let t1 = h + s1 + ch + K[i] + (*w)[i];
+        let s0 = rotr(a, 2u) ^ rotr(a, 13u) ^ rotr(a, 22u);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        h = g;
+        g = f;
+        f = e;
+        e = d + t1;
+        d = c;
+        c = b;
+        b = a;
+        a = t1 + s0 + maj;
+    }
+    (*state)[0] += a;
+    (*state)[1] += b;
+    (*state)[2] += c;
+    (*state)[3] += d;
+    (*state)[4] += e;
+    (*state)[5] += f;
+    (*state)[6] += g;
+    (*state)[7] += h;
+}
+
+// Returns the digest of the 64 byte message in `w[0..16]`
+fn sha256_64(w: ptr<function, array<u32, 64>>) -> array<u32, 8> {
+    var state = array<u32, 8>(
+        0x6a09e667u, 0xbb67ae85u, 0x3c6ef372u, 0xa54ff53au,
+        0x510e527fu, 0x9b05688cu, 0x1f83d9abu, 0x5be0cd19u,
+    );
+    compress(&state, w);
+    // the message fills the first block so the padding is a block of its own
+    var padding: array<u32, 64>;
+    padding[0] = 0x80000000u;
+    padding[15] = 512u;
+    compress(&state, &padding);
+    return state;
+}
+
+fn store_node(node: u32, value: array<u32, 8>) {
+    var digest = value;
+    for (var j = 0u; j < 8u; j++) {
+        nodes[node * 8u + j] = bswap(digest[j]);
+    }
+}
+
+// Hashes pairs of leaves into the layer of nodes of size `params.n`
+@compute @workgroup_size(64)
+fn merge_leaves(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let i = gid.x;
+    if i >= params.n {
+        return;
+    }
+    var w: array<u32, 64>;
+    for (var j = 0u; j < 16u; j++) {
+        w[j] = bswap(leaves[i * 16u + j]);
+    }
+    store_node(params.n + i, sha256_64(&w));
+}
+
+// Hashes pairs of nodes of the previous layer into the layer of size
+// `params.n`
+@compute @workgroup_size(64)
+fn merge_nodes(@builtin(global_invocation_id) gid: vec3<u32>) {
+    if gid.x >= params.n {
+        return;
+    }
+    let i = params.n + gid.x;
+    var w: array<u32, 64>;
+    for (var j = 0u; j < 16u; j++) {
+        w[j] = bswap(nodes[i * 16u + j]);
+    }
+    store_node(i, sha256_64(&w));
+}
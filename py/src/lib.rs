@@ -0,0 +1,293 @@
+//! Python bindings for proving and verifying with miniSTARK
+//!
+//! Two kinds of AIR are supported:
+//! * built-in AIRs compiled into the module (currently a Fibonacci AIR)
+//! * AIRs loaded from the portable description format (see
+//!   `ministark::description`). The base trace is passed in from Python and
+//!   public inputs are given to the constraints as hints in order.
+//!
+//! AIRs are over the 64-bit Goldilocks field. Field elements are passed as
+//! integers that must be less than the modulus. Build and install the module
+//! with `maturin develop`.
+
+#![feature(allocator_api)]
+
+use ark_ff::One;
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ministark::air::AirConfig;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::description::AirDescription;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::hints::Hints;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::utils::GpuAllocator;
+use ministark::utils::SerdeOutput;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::Proof;
+use ministark::ProofOptions;
+use ministark_ffi::described::canonical_element;
+use ministark_ffi::described::seed_public_coin;
+use ministark_ffi::described::BaseTrace;
+use ministark_ffi::described::DescribedClaim;
+use ministark_ffi::described::MAX_BASE_COLUMNS;
+use ministark_ffi::with_num_base_columns;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use sha2::Sha256;
+use std::borrow::Cow;
+use std::fmt::Display;
+
+fn value_error(error: impl Display) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// Proof options. Defaults give ~100 bits of security.
+#[pyclass(name = "ProofOptions")]
+#[derive(Clone, Copy)]
+struct PyProofOptions(ProofOptions);
+
+#[pymethods]
+impl PyProofOptions {
+    #[new]
+    #[pyo3(signature = (
+        num_queries = 32,
+        lde_blowup_factor = 4,
+        grinding_factor = 16,
+        fri_folding_factor = 8,
+        fri_max_remainder_coeffs = 16
+    ))]
+    fn new(
+        num_queries: u8,
+        lde_blowup_factor: u8,
+        grinding_factor: u8,
+        fri_folding_factor: u8,
+        fri_max_remainder_coeffs: u8,
+    ) -> Self {
+        Self(ProofOptions::new(
+            num_queries,
+            lde_blowup_factor,
+            grinding_factor,
+            fri_folding_factor,
+            fri_max_remainder_coeffs,
+        ))
+    }
+}
+
+fn serialize_proof<S: Stark>(proof: &Proof<S>) -> Cow<'static, [u8]> {
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    Cow::Owned(bytes)
+}
+
+fn verify_proof<S: Stark>(claim: &S, proof: &[u8], security_bits: u32) -> PyResult<bool> {
+    let proof = Proof::<S>::deserialize_compressed(proof).map_err(value_error)?;
+    Ok(claim.verify(proof, security_bits).is_ok())
+}
+
+fn to_u64(value: Fp) -> u64 {
+    value.into_bigint().0[0]
+}
+
+/// Values that aren't less than the modulus are rejected rather than reduced
+fn to_fp(value: u64) -> PyResult<Fp> {
+    canonical_element(value)
+        .ok_or_else(|| value_error(format!("{value} is not less than the field modulus")))
+}
+
+fn to_fp_vec(values: Vec<u64>) -> PyResult<Vec<Fp>> {
+    values.into_iter().map(to_fp).collect()
+}
+
+/// Fibonacci sequence starting `1, 1` in two columns. The public input is the
+/// last value of the second column.
+struct FibAirConfig;
+
+impl AirConfig for FibAirConfig {
+    const NUM_BASE_COLUMNS: usize = 2;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = Fp;
+
+    fn gen_hints(_trace_len: usize, claim: &Fp, _: &Challenges<Fp>) -> Hints<Fp> {
+        Hints::new(vec![(0, *claim)])
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let boundary = [0.curr() - one, 1.curr() - one]
+            .into_iter()
+            .map(Constraint::when_first_row);
+        let transition = [0.next() - 1.curr(), 1.next() - 0.curr() - 1.curr()]
+            .into_iter()
+            .map(|expr| Constraint::when_transition(trace_len, expr));
+        let terminal = [1.curr() - AlgebraicItem::Hint(0)]
+            .into_iter()
+            .map(|expr| Constraint::when_last_row(trace_len, expr));
+        boundary.chain(transition).chain(terminal).collect()
+    }
+}
+
+struct FibClaim(Fp);
+
+impl Stark for FibClaim {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = FibAirConfig;
+    type Digest = SerdeOutput<Sha256>;
+    type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+    type Witness = BaseTrace;
+    type Trace = BaseTrace;
+
+    fn get_public_inputs(&self) -> Fp {
+        self.0
+    }
+
+    fn generate_trace(&self, witness: BaseTrace) -> BaseTrace {
+        witness
+    }
+
+    fn gen_public_coin(&self, air: &Air<FibAirConfig>) -> Self::PublicCoin {
+        seed_public_coin(air)
+    }
+}
+
+/// Proves the Fibonacci AIR for a trace of `n` rows. Returns the claimed
+/// last value and the proof.
+#[pyfunction]
+fn prove_fibonacci(n: usize, options: PyProofOptions) -> PyResult<(u64, Cow<'static, [u8]>)> {
+    if !n.is_power_of_two() {
+        return Err(value_error(format!("{n} is not a power of two")));
+    }
+    let mut a = Vec::with_capacity_in(n, GpuAllocator);
+    let mut b = Vec::with_capacity_in(n, GpuAllocator);
+    let (mut x, mut y) = (Fp::one(), Fp::one());
+    for _ in 0..n {
+        a.push(x);
+        b.push(y);
+        (x, y) = (y, x + y);
+    }
+    let claim = FibClaim(*b.last().unwrap());
+    let trace = BaseTrace(Matrix::new(vec![a, b]));
+    let proof = pollster::block_on(claim.prove(options.0, trace)).map_err(value_error)?;
+    Ok((to_u64(claim.0), serialize_proof(&proof)))
+}
+
+/// Verifies a proof generated with `prove_fibonacci`
+#[pyfunction]
+#[pyo3(signature = (claim, proof, security_bits = 80))]
+fn verify_fibonacci(claim: u64, proof: &[u8], security_bits: u32) -> PyResult<bool> {
+    verify_proof(&FibClaim(to_fp(claim)?), proof, security_bits)
+}
+
+fn parse_description(description: &str) -> PyResult<AirDescription> {
+    let description: AirDescription = serde_json::from_str(description).map_err(value_error)?;
+    if description.num_extension_columns != 0 {
+        return Err(value_error("AIRs with extension columns aren't supported"));
+    }
+    Ok(description)
+}
+
+fn prove_described_impl<const N: usize>(
+    description: AirDescription,
+    trace: Vec<Vec<Fp>>,
+    public_inputs: Vec<Fp>,
+    options: ProofOptions,
+) -> PyResult<Cow<'static, [u8]>> {
+    let trace_len = description.trace_len;
+    if trace.len() != N || trace.iter().any(|column| column.len() != trace_len) {
+        return Err(value_error(format!(
+            "trace must have {N} columns of {trace_len} rows"
+        )));
+    }
+    let columns = trace
+        .into_iter()
+        .map(|column| column.to_vec_in(GpuAllocator))
+        .collect();
+    let claim =
+        DescribedClaim::<N>::new(description, public_inputs, options).map_err(value_error)?;
+    let trace = BaseTrace(Matrix::new(columns));
+    let proof = pollster::block_on(claim.prove(options, trace)).map_err(value_error)?;
+    Ok(serialize_proof(&proof))
+}
+
+fn verify_described_impl<const N: usize>(
+    description: AirDescription,
+    proof: &[u8],
+    public_inputs: Vec<Fp>,
+    security_bits: u32,
+) -> PyResult<bool> {
+    let options = Proof::<DescribedClaim<N>>::deserialize_compressed(proof)
+        .map_err(value_error)?
+        .header
+        .options;
+    let claim =
+        DescribedClaim::<N>::new(description, public_inputs, options).map_err(value_error)?;
+    verify_proof(&claim, proof, security_bits)
+}
+
+/// Proves an AIR loaded from a JSON description. `trace` holds the values of
+/// each base column.
+#[pyfunction]
+fn prove_described(
+    description: &str,
+    trace: Vec<Vec<u64>>,
+    public_inputs: Vec<u64>,
+    options: PyProofOptions,
+) -> PyResult<Cow<'static, [u8]>> {
+    // inputs are checked before the description so errors don't depend on it
+    let trace = trace
+        .into_iter()
+        .map(to_fp_vec)
+        .collect::<PyResult<Vec<Vec<Fp>>>>()?;
+    let public_inputs = to_fp_vec(public_inputs)?;
+    let description = parse_description(description)?;
+    with_num_base_columns!(
+        description.num_base_columns,
+        prove_described_impl(description, trace, public_inputs, options.0),
+        Err(value_error(format!(
+            "AIRs with more than {MAX_BASE_COLUMNS} base columns aren't supported"
+        )))
+    )
+}
+
+/// Verifies a proof generated with `prove_described`
+#[pyfunction]
+#[pyo3(signature = (description, proof, public_inputs, security_bits = 80))]
+fn verify_described(
+    description: &str,
+    proof: &[u8],
+    public_inputs: Vec<u64>,
+    security_bits: u32,
+) -> PyResult<bool> {
+    let public_inputs = to_fp_vec(public_inputs)?;
+    let description = parse_description(description)?;
+    with_num_base_columns!(
+        description.num_base_columns,
+        verify_described_impl(description, proof, public_inputs, security_bits),
+        Err(value_error(format!(
+            "AIRs with more than {MAX_BASE_COLUMNS} base columns aren't supported"
+        )))
+    )
+}
+
+#[pymodule]
+fn ministark(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyProofOptions>()?;
+    m.add_function(wrap_pyfunction!(prove_fibonacci, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_fibonacci, m)?)?;
+    m.add_function(wrap_pyfunction!(prove_described, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_described, m)?)?;
+    Ok(())
+}
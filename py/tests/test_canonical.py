@@ -0,0 +1,32 @@
+import pytest
+
+import ministark
+
+MODULUS = 0xFFFF_FFFF_0000_0001
+
+
+def test_fibonacci_round_trip():
+    options = ministark.ProofOptions(num_queries=16, grinding_factor=0)
+    claim, proof = ministark.prove_fibonacci(64, options)
+
+    assert ministark.verify_fibonacci(claim, proof)
+    assert not ministark.verify_fibonacci(claim + 1, proof)
+
+
+@pytest.mark.parametrize("value", [MODULUS, 2**64 - 1])
+def test_non_canonical_claims_are_rejected(value):
+    options = ministark.ProofOptions(num_queries=16, grinding_factor=0)
+    _, proof = ministark.prove_fibonacci(64, options)
+
+    with pytest.raises(ValueError, match="not less than the field modulus"):
+        ministark.verify_fibonacci(value, proof)
+
+
+@pytest.mark.parametrize("value", [MODULUS, 2**64 - 1])
+def test_non_canonical_described_inputs_are_rejected(value):
+    options = ministark.ProofOptions()
+    # inputs are checked before the description is used
+    with pytest.raises(ValueError, match="not less than the field modulus"):
+        ministark.verify_described("{}", b"", [value])
+    with pytest.raises(ValueError, match="not less than the field modulus"):
+        ministark.prove_described("{}", [[value]], [], options)
@@ -1,29 +1,47 @@
 use crate::challenges::Challenges;
 use crate::constraints::AlgebraicItem;
+use crate::constraints::Assertion;
 use crate::constraints::CompositionConstraint;
 use crate::constraints::CompositionItem;
 use crate::constraints::Constraint;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::RowIndependentOps;
+use crate::context::CancellationToken;
 use crate::expression::Expr;
 use crate::hints::Hints;
+use crate::trace::TraceInfo;
 use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
 use crate::utils::GpuVec;
 use crate::Matrix;
+use crate::OptionsError;
 use crate::ProofOptions;
 use crate::StarkExtensionOf;
 use alloc::collections::BTreeSet;
 use alloc::vec::Vec;
+use ark_ff::BigInteger;
 use ark_ff::FftField;
+use ark_ff::Field;
+use ark_ff::PrimeField;
 use ark_poly::EvaluationDomain;
 use ark_poly::Radix2EvaluationDomain;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use core::fmt::Display;
+use core::ops::Range;
+use digest::Digest as _;
 use ministark_gpu::GpuFftField;
 use num_traits::Pow;
+use sha2::Sha256;
+use snafu::Snafu;
 use std::time::Instant;
 
 pub trait AirConfig: Send + Sync + Sized + 'static {
     const NUM_BASE_COLUMNS: usize;
     const NUM_EXTENSION_COLUMNS: usize = 0;
+    /// Version of the AIR definition recorded in proof headers. Bump the major
+    /// version (or the minor version before 1.0) when the constraints change.
+    const VERSION: AirVersion = AirVersion::new(0, 0, 0);
 
     type Fp: GpuFftField<FftField = Self::Fp> + FftField;
     type Fq: StarkExtensionOf<Self::Fp>;
@@ -31,6 +49,20 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
 
     fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Self::Fp, Self::Fq>>>;
 
+    /// Types of the base columns in order. Columns without a type are
+    /// [`ColumnType::Felt`]. Typed columns are constrained automatically. Byte
+    /// and u32 columns are range checked with auxiliary columns which must be
+    /// included in [`AirConfig::NUM_BASE_COLUMNS`] (see [`ColumnTypeChecks`]).
+    fn column_types() -> Vec<ColumnType> {
+        Vec::new()
+    }
+
+    /// Assertions on single cells of the trace e.g. boundary and terminal
+    /// values of extension columns. See [`Assertion`].
+    fn assertions(_trace_len: usize) -> Vec<Assertion<FieldVariant<Self::Fp, Self::Fq>>> {
+        Vec::new()
+    }
+
     fn gen_hints(
         _trace_len: usize,
         _public_inputs: &Self::PublicInputs,
@@ -39,6 +71,31 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
         Hints::default()
     }
 
+    /// Generates polynomials from the public inputs that constraints can
+    /// reference with [`AlgebraicItem::PublicPolynomial`]. Polynomials are
+    /// given by their coefficients and must have degree less than `trace_len`.
+    /// Rather than the prover committing to these the verifier evaluates them
+    /// at the OOD point itself.
+    fn gen_public_polynomials(
+        _trace_len: usize,
+        _public_inputs: &Self::PublicInputs,
+    ) -> Vec<Vec<Self::Fp>> {
+        Vec::new()
+    }
+
+    /// Application defined bytes describing the trace e.g. the hash of a
+    /// program. Bound into the transcript along with the shape of the trace
+    /// (see [`TraceInfo`]).
+    fn trace_meta(_trace_len: usize, _public_inputs: &Self::PublicInputs) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Returns the trace length implied by the public inputs if there is one.
+    /// AIRs (and therefore proofs) for traces of any other length are invalid.
+    fn expected_trace_len(_public_inputs: &Self::PublicInputs) -> Option<usize> {
+        None
+    }
+
     fn domain_offset() -> Self::Fp {
         Self::Fp::GENERATOR
     }
@@ -51,27 +108,54 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
         trace_len: usize,
         constraints: &[Constraint<FieldVariant<Self::Fp, Self::Fq>>],
     ) -> CompositionConstraint<FieldVariant<Self::Fp, Self::Fq>> {
-        let ce_blowup_factor = constraints
+        let trace_degree = trace_len - 1;
+        // constraints are adjusted to the degree of the highest degree constraint
+        let composition_degree = constraints
             .iter()
-            .map(|c| c.blowup_factor(trace_len))
+            .map(|constraint| {
+                let (numerator_degree, denominator_degree) = constraint.degree(trace_degree);
+                numerator_degree - denominator_degree
+            })
             .max()
             .unwrap();
-        let composition_degree = trace_len * ce_blowup_factor - 1;
-        let trace_degree = trace_len - 1;
         let x = Expr::Leaf(CompositionItem::Item(AlgebraicItem::X));
         let mut composition_coeff = (0..).map(|i| Expr::Leaf(CompositionItem::CompositionCoeff(i)));
-        let expr = constraints
-            .iter()
-            .map(|constraint| {
-                let (numerator_degree, denominator_degree) = constraint.degree(trace_degree);
-                let evaluation_degree = numerator_degree - denominator_degree;
-                assert!(evaluation_degree <= composition_degree);
-                let degree_adjustment = composition_degree - evaluation_degree;
-                // TODO: if degree_adjustment is 0 then we only need one challenge
-                let constraint = constraint.map_leaves(&mut |&leaf| CompositionItem::Item(leaf));
-                let alpha = composition_coeff.next().unwrap();
-                let beta = composition_coeff.next().unwrap();
-                &constraint * (x.clone().pow(degree_adjustment) * alpha + beta)
+        let to_composition = |expr: &Expr<AlgebraicItem<FieldVariant<Self::Fp, Self::Fq>>>| {
+            expr.map_leaves(&mut |&leaf| CompositionItem::Item(leaf))
+        };
+        // numerators of constraints that share a divisor are combined before dividing
+        // so each vanishing polynomial is inverted once per chunk of the LDE rather
+        // than once per constraint
+        let mut quotients = Vec::<(Option<Expr<_>>, Vec<Expr<_>>)>::new();
+        for constraint in constraints {
+            let (numerator_degree, denominator_degree) = constraint.degree(trace_degree);
+            let evaluation_degree = numerator_degree - denominator_degree;
+            assert!(evaluation_degree <= composition_degree);
+            let degree_adjustment = composition_degree - evaluation_degree;
+            // TODO: if degree_adjustment is 0 then we only need one challenge
+            let (numerator, divisor) = match &**constraint {
+                Expr::Div(numerator, divisor) => (
+                    to_composition(&numerator.read().unwrap()),
+                    Some(to_composition(&divisor.read().unwrap())),
+                ),
+                expr => (to_composition(expr), None),
+            };
+            let alpha = composition_coeff.next().unwrap();
+            let beta = composition_coeff.next().unwrap();
+            let term = numerator * (x.clone().pow(degree_adjustment) * alpha + beta);
+            match quotients.iter_mut().find(|(d, _)| *d == divisor) {
+                Some((_, terms)) => terms.push(term),
+                None => quotients.push((divisor, vec![term])),
+            }
+        }
+        let expr = quotients
+            .into_iter()
+            .map(|(divisor, terms)| {
+                let numerator = terms.into_iter().sum::<Expr<_>>();
+                match divisor {
+                    Some(divisor) => numerator / divisor,
+                    None => numerator,
+                }
             })
             .sum::<Expr<CompositionItem<FieldVariant<Self::Fp, Self::Fq>>>>();
         // TODO: remove log and timing
@@ -82,16 +166,21 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
     }
 
     // TODO: maybe move this into a constraint evaluator
+    /// Evaluates the composition constraint over the constraint evaluation
+    /// domain. Rows are skipped once `cancellation_token` is cancelled so the
+    /// result should be discarded if the token is cancelled.
     #[allow(clippy::too_many_arguments)]
     fn eval_constraint(
         composition_constraint: &CompositionConstraint<FieldVariant<Self::Fp, Self::Fq>>,
         challenges: &[Self::Fq],
         hints: &[Self::Fq],
         composition_constraint_coeffs: &[Self::Fq],
+        public_polynomials: &[Vec<Self::Fp>],
         lde_step: usize,
         x_lde: GpuVec<Self::Fp>,
         base_trace_lde_cols: &[&[Self::Fp]],
         extension_trace_lde_cols: Option<&[&[Self::Fq]]>,
+        cancellation_token: &CancellationToken,
     ) -> Matrix<Self::Fq> {
         let eval_expr = composition_constraint.map_leaves(&mut |leaf| match leaf {
             CompositionItem::Item(item) => *item,
@@ -107,6 +196,7 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
         //     &eval_expr,
         //     challenges,
         //     hints,
+        //     public_polynomials,
         //     lde_step,
         //     Self::domain_offset(),
         //     x_lde,
@@ -119,43 +209,429 @@ pub trait AirConfig: Send + Sync + Sized + 'static {
             &eval_expr,
             challenges,
             hints,
+            public_polynomials,
             lde_step,
             Self::domain_offset(),
             &x_lde,
             base_trace_lde_cols,
             extension_trace_lde_cols,
+            cancellation_token,
         )
     }
 }
 
+/// Type of the values in a trace column (see [`AirConfig::column_types`])
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColumnType {
+    /// Any field element
+    #[default]
+    Felt,
+    /// `0` or `1`
+    Bit,
+    /// Value in `0..2^8`
+    Byte,
+    /// Value in `0..2^32`
+    U32,
+}
+
+impl ColumnType {
+    /// Returns the number of bits values fit in or `None` for
+    /// [`ColumnType::Felt`]
+    pub const fn num_bits(self) -> Option<u32> {
+        match self {
+            Self::Felt => None,
+            Self::Bit => Some(1),
+            Self::Byte => Some(8),
+            Self::U32 => Some(32),
+        }
+    }
+
+    /// Returns the number of auxiliary columns needed to range check a column
+    /// of this type. Bit columns are checked directly.
+    pub const fn num_auxiliary_columns(self) -> usize {
+        match self {
+            Self::Felt | Self::Bit => 0,
+            Self::Byte => 8,
+            Self::U32 => 32,
+        }
+    }
+
+    /// Returns true if `value` is a value of this type
+    pub fn contains<F: Field>(self, value: &F) -> bool {
+        let Some(num_bits) = self.num_bits() else {
+            return true;
+        };
+        let mut elements = value.to_base_prime_field_elements();
+        let element = elements.next().unwrap();
+        elements.all(|element| element.is_zero()) && element.into_bigint().num_bits() <= num_bits
+    }
+}
+
+/// Constraints for the typed base columns of an AIR (see
+/// [`AirConfig::column_types`]). Bit columns are constrained to be boolean.
+/// Byte and u32 columns are decomposed into auxiliary bit columns which are
+/// each constrained to be boolean and must recompose to the column.
+///
+/// The auxiliary columns are the last base columns, after any auxiliary
+/// columns of [`crate::degree_lowering::lower_degree`]. Like those they're
+/// filled in from the rest of the base trace (see
+/// [`ColumnTypeChecks::fill_auxiliary_columns`]).
+pub struct ColumnTypeChecks {
+    column_types: Vec<ColumnType>,
+    first_auxiliary_column: usize,
+}
+
+impl ColumnTypeChecks {
+    /// Creates the checks for a trace with `num_base_columns` base columns
+    /// including the auxiliary columns
+    pub fn new<F: Field>(
+        column_types: Vec<ColumnType>,
+        num_base_columns: usize,
+    ) -> Result<Self, AirConfigError> {
+        let modulus_bits = F::BasePrimeField::MODULUS_BIT_SIZE;
+        for (column, ty) in column_types.iter().enumerate() {
+            if let Some(num_bits) = ty.num_bits() {
+                if num_bits >= modulus_bits {
+                    return Err(AirConfigError::ColumnTypeTooWide { column, num_bits });
+                }
+            }
+        }
+        let num_auxiliary_columns = column_types
+            .iter()
+            .map(|ty| ty.num_auxiliary_columns())
+            .sum::<usize>();
+        let num_columns = column_types.len() + num_auxiliary_columns;
+        if num_columns > num_base_columns {
+            return Err(AirConfigError::InvalidColumn {
+                column: num_columns - 1,
+                num_columns: num_base_columns,
+            });
+        }
+        Ok(Self {
+            column_types,
+            first_auxiliary_column: num_base_columns - num_auxiliary_columns,
+        })
+    }
+
+    /// Returns the index of the first auxiliary column
+    pub const fn first_auxiliary_column(&self) -> usize {
+        self.first_auxiliary_column
+    }
+
+    /// Returns the constraints of every typed column
+    pub fn constraints<Fp: FftField, Fq: Field>(
+        &self,
+        trace_len: usize,
+    ) -> Vec<Constraint<FieldVariant<Fp, Fq>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let is_bit = |column: usize| {
+            Constraint::when_every_row(trace_len, column.curr() * (column.curr() - one))
+        };
+        let mut constraints = Vec::new();
+        for (column, ty, auxiliary_columns) in self.columns() {
+            if ty == ColumnType::Bit {
+                constraints.push(is_bit(column));
+            }
+            if auxiliary_columns.is_empty() {
+                continue;
+            }
+            // the auxiliary columns hold the bits of the value in little endian
+            let mut shift = Fp::one();
+            let recomposition = auxiliary_columns
+                .clone()
+                .map(|bit_column| {
+                    let bit = bit_column.curr() * AlgebraicItem::Constant(FieldVariant::Fp(shift));
+                    shift.double_in_place();
+                    bit
+                })
+                .sum::<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>();
+            constraints.push(Constraint::when_every_row(
+                trace_len,
+                column.curr() - recomposition,
+            ));
+            constraints.extend(auxiliary_columns.map(is_bit));
+        }
+        constraints
+    }
+
+    /// Appends the auxiliary columns to the base trace
+    ///
+    /// # Panics
+    /// Panics if the auxiliary columns aren't the next columns of the base
+    /// trace or a typed column holds a value outside of its type
+    pub fn fill_auxiliary_columns<F: PrimeField>(&self, base_trace: &mut Matrix<F>) {
+        assert_eq!(self.first_auxiliary_column, base_trace.num_cols());
+        let n = base_trace.num_rows();
+        for (column, ty, auxiliary_columns) in self.columns() {
+            let mut bit_columns = auxiliary_columns
+                .map(|_| Vec::with_capacity_in(n, GpuAllocator))
+                .collect::<Vec<GpuVec<F>>>();
+            for (row, value) in base_trace.0[column].iter().enumerate() {
+                assert!(
+                    ty.contains(value),
+                    "column {column} holds {value} on row {row} which isn't a {ty:?}"
+                );
+                let bits = value.into_bigint();
+                for (i, bit_column) in bit_columns.iter_mut().enumerate() {
+                    bit_column.push(F::from(bits.get_bit(i)));
+                }
+            }
+            base_trace.0.extend(bit_columns);
+        }
+    }
+
+    /// Returns each typed column with its type and auxiliary columns
+    fn columns(&self) -> impl Iterator<Item = (usize, ColumnType, Range<usize>)> + '_ {
+        let mut next_auxiliary_column = self.first_auxiliary_column;
+        self.column_types
+            .iter()
+            .enumerate()
+            .map(move |(column, &ty)| {
+                let start = next_auxiliary_column;
+                next_auxiliary_column += ty.num_auxiliary_columns();
+                (column, ty, start..next_auxiliary_column)
+            })
+    }
+}
+
+/// Semantic version of an AIR definition (see [`AirConfig::VERSION`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AirVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl AirVersion {
+    pub const fn new(major: u16, minor: u16, patch: u16) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Returns true if proofs created with either version can be verified
+    /// with the other. Follows semver: versions before 1.0 are compatible if
+    /// their minor versions match.
+    pub const fn is_compatible_with(&self, other: &Self) -> bool {
+        self.major == other.major && (self.major != 0 || self.minor == other.minor)
+    }
+}
+
+impl Display for AirVersion {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
 pub fn trace_domain<A: AirConfig>(trace_len: usize) -> Radix2EvaluationDomain<A::Fp> {
     Radix2EvaluationDomain::new(trace_len).unwrap()
 }
 
+/// Interpolates a public sequence of values over the subgroup of size
+/// `values.len()`. Use with [`crate::constraints::public_sequence_constraint`]
+/// to constrain a column to the sequence.
+pub fn public_sequence_polynomial<F: FftField>(values: &[F]) -> Vec<F> {
+    assert!(values.len().is_power_of_two());
+    Radix2EvaluationDomain::new(values.len())
+        .unwrap()
+        .ifft(values)
+}
+
 pub struct Air<AC: AirConfig> {
     constraints: Vec<Constraint<FieldVariant<AC::Fp, AC::Fq>>>,
     composition_constraint: CompositionConstraint<FieldVariant<AC::Fp, AC::Fq>>,
+    row_independent_ops: RowIndependentOps,
+    composition_degree: usize,
     ce_blowup_factor: usize,
     trace_len: usize,
     options: ProofOptions,
     public_inputs: AC::PublicInputs,
+    public_polynomials: Vec<Vec<AC::Fp>>,
+}
+
+/// Errors for an AIR that can't be used with a trace length or proof options
+#[derive(Debug, Snafu)]
+pub enum AirConfigError {
+    #[snafu(display("invalid proof options: {source}"))]
+    InvalidOptions { source: OptionsError },
+    #[snafu(display(
+        "constraints require a blowup factor of {ce_blowup_factor} but the LDE blowup factor is \
+         {lde_blowup_factor}"
+    ))]
+    ConstraintDegreeTooHigh {
+        ce_blowup_factor: usize,
+        lde_blowup_factor: usize,
+    },
+    #[snafu(display("constraint references column {column} but there are {num_columns} columns"))]
+    InvalidColumn { column: usize, num_columns: usize },
+    #[snafu(display("column {column} holds {num_bits} bit values which don't fit in the field"))]
+    ColumnTypeTooWide { column: usize, num_bits: u32 },
+    #[snafu(display(
+        "public polynomial {index} has {len} coefficients but the trace length is {trace_len}"
+    ))]
+    PublicPolynomialDegreeTooHigh {
+        index: usize,
+        len: usize,
+        trace_len: usize,
+    },
+    #[snafu(display(
+        "constraint references public polynomial {index} but there are {num} public polynomials"
+    ))]
+    MissingPublicPolynomial { index: usize, num: usize },
+    #[snafu(display(
+        "{num_extension_columns} extension columns but constraints don't use any challenges"
+    ))]
+    MissingChallenges { num_extension_columns: usize },
+    #[snafu(display("public inputs imply a trace length of {expected} but got {actual}"))]
+    TraceLenMismatch { expected: usize, actual: usize },
+    #[snafu(display(
+        "assertion {index} has a value that depends on the row. Values can only reference \
+         constants, challenges and hints"
+    ))]
+    RowDependentAssertion { index: usize },
 }
 
 impl<C: AirConfig> Air<C> {
+    /// # Panics
+    /// Panics if the AIR is invalid. See [`Air::try_new`].
     pub fn new(trace_len: usize, public_inputs: C::PublicInputs, options: ProofOptions) -> Self {
-        let constraints = C::constraints(trace_len);
+        Self::try_new(trace_len, public_inputs, options).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    pub fn try_new(
+        trace_len: usize,
+        public_inputs: C::PublicInputs,
+        options: ProofOptions,
+    ) -> Result<Self, AirConfigError> {
+        options
+            .validate::<C::Fp>(trace_len)
+            .map_err(|source| AirConfigError::InvalidOptions { source })?;
+        let mut constraints = C::constraints(trace_len);
+        let column_type_checks =
+            ColumnTypeChecks::new::<C::Fp>(C::column_types(), C::NUM_BASE_COLUMNS)?;
+        constraints.extend(column_type_checks.constraints(trace_len));
+        for (index, assertion) in C::assertions(trace_len).into_iter().enumerate() {
+            if !assertion.is_row_independent() {
+                return Err(AirConfigError::RowDependentAssertion { index });
+            }
+            constraints.push(assertion.constraint(trace_len));
+        }
+        let public_polynomials = C::gen_public_polynomials(trace_len, &public_inputs);
+        Self::from_constraints(
+            trace_len,
+            public_inputs,
+            options,
+            constraints,
+            public_polynomials,
+        )
+    }
+
+    /// Creates an AIR from constraints and public polynomials that don't come
+    /// from the config e.g. constraints loaded at runtime with
+    /// [`Air::from_description`].
+    pub fn from_constraints(
+        trace_len: usize,
+        public_inputs: C::PublicInputs,
+        options: ProofOptions,
+        constraints: Vec<Constraint<FieldVariant<C::Fp, C::Fq>>>,
+        public_polynomials: Vec<Vec<C::Fp>>,
+    ) -> Result<Self, AirConfigError> {
+        options
+            .validate::<C::Fp>(trace_len)
+            .map_err(|source| AirConfigError::InvalidOptions { source })?;
         let composition_constraint = C::composition_constraint(trace_len, &constraints);
-        let ce_blowup_factor = composition_constraint.blowup_factor(trace_len);
-        assert!(ce_blowup_factor <= options.lde_blowup_factor.into());
+        let (numerator_degree, denominator_degree) = composition_constraint.degree(trace_len - 1);
+        let composition_degree = numerator_degree - denominator_degree;
+        // the constraint evaluation domain must fit every coefficient of the
+        // composition polynomial
+        let ce_blowup_factor = composition_constraint
+            .blowup_factor(trace_len)
+            .max((composition_degree / trace_len + 1).next_power_of_two());
+        let row_independent_ops = composition_constraint.row_independent_ops();
 
-        Self {
+        let air = Self {
             constraints,
             composition_constraint,
+            row_independent_ops,
+            composition_degree,
             ce_blowup_factor,
             trace_len,
             options,
             public_inputs,
+            public_polynomials,
+        };
+        air.validate()?;
+        Ok(air)
+    }
+
+    /// Checks the constraints can be proven with the trace length and options
+    pub fn validate(&self) -> Result<(), AirConfigError> {
+        use AirConfigError::*;
+        self.options
+            .validate::<C::Fp>(self.trace_len)
+            .map_err(|source| InvalidOptions { source })?;
+
+        if let Some(expected) = C::expected_trace_len(&self.public_inputs)
+            && expected != self.trace_len
+        {
+            return Err(TraceLenMismatch {
+                expected,
+                actual: self.trace_len,
+            });
+        }
+
+        let lde_blowup_factor = self.lde_blowup_factor();
+        if self.ce_blowup_factor > lde_blowup_factor {
+            return Err(ConstraintDegreeTooHigh {
+                ce_blowup_factor: self.ce_blowup_factor,
+                lde_blowup_factor,
+            });
+        }
+
+        for (index, polynomial) in self.public_polynomials.iter().enumerate() {
+            if polynomial.len() > self.trace_len {
+                return Err(PublicPolynomialDegreeTooHigh {
+                    index,
+                    len: polynomial.len(),
+                    trace_len: self.trace_len,
+                });
+            }
+        }
+
+        let num_columns = C::NUM_BASE_COLUMNS + C::NUM_EXTENSION_COLUMNS;
+        let num_public_polynomials = self.public_polynomials.len();
+        let mut result = Ok(());
+        for constraint in &self.constraints {
+            constraint.traverse(&mut |node| match *node {
+                Expr::Leaf(AlgebraicItem::Trace(column, _)) if column >= num_columns => {
+                    result = Err(InvalidColumn {
+                        column,
+                        num_columns,
+                    });
+                }
+                Expr::Leaf(AlgebraicItem::PublicPolynomial(index))
+                    if index >= num_public_polynomials =>
+                {
+                    result = Err(MissingPublicPolynomial {
+                        index,
+                        num: num_public_polynomials,
+                    });
+                }
+                _ => {}
+            });
+            result?;
         }
+
+        // extension columns are built from verifier challenges
+        if C::NUM_EXTENSION_COLUMNS != 0 && self.num_challenges() == 0 {
+            return Err(MissingChallenges {
+                num_extension_columns: C::NUM_EXTENSION_COLUMNS,
+            });
+        }
+
+        Ok(())
     }
 
     pub const fn trace_len(&self) -> usize {
@@ -170,14 +646,39 @@ impl<C: AirConfig> Air<C> {
         &self.public_inputs
     }
 
+    pub fn public_polynomials(&self) -> &[Vec<C::Fp>] {
+        &self.public_polynomials
+    }
+
+    pub fn constraints(&self) -> &[Constraint<FieldVariant<C::Fp, C::Fq>>] {
+        &self.constraints
+    }
+
     pub const fn ce_blowup_factor(&self) -> usize {
         self.ce_blowup_factor
     }
 
     /// Returns a degree that all constraint polynomials must be normalized to.
+    /// This is the degree of the highest degree constraint rather than the
+    /// size of the constraint evaluation domain.
     pub const fn composition_degree(&self) -> usize {
-        let ce_domain_size = self.trace_len * self.ce_blowup_factor();
-        ce_domain_size - 1
+        self.composition_degree
+    }
+
+    /// Returns the number of columns the composition polynomial is split into.
+    ///
+    /// The composition polynomial `H(x)` of degree `d` is split into the
+    /// smallest number of columns `k` such that each column `H_i(x)` has degree
+    /// less than `trace_len` and
+    /// `H(x) = H_0(x^k) + x * H_1(x^k) + ... + x^(k-1) * H_(k-1)(x^k)`. `k`
+    /// doesn't need to be a power of two so `H_i` holds the coefficients
+    /// `i, i + k, i + 2k, ...` of `H` and the last columns have a lower degree
+    /// if `k` doesn't divide `d + 1`. Every column is checked to have degree
+    /// less than `trace_len` so the verifier only knows `H` has degree less
+    /// than `k * trace_len`. This is enough for the OOD check since `H(z)`
+    /// must also match the composition constraint evaluated at `z`.
+    pub const fn num_composition_trace_columns(&self) -> usize {
+        self.composition_degree / self.trace_len + 1
     }
 
     pub fn num_challenges(&self) -> usize {
@@ -192,6 +693,15 @@ impl<C: AirConfig> Air<C> {
         num_challenges
     }
 
+    pub fn trace_info(&self) -> TraceInfo {
+        TraceInfo {
+            trace_len: self.trace_len,
+            num_base_columns: C::NUM_BASE_COLUMNS,
+            num_extension_columns: C::NUM_EXTENSION_COLUMNS,
+            meta: C::trace_meta(self.trace_len, &self.public_inputs),
+        }
+    }
+
     pub fn gen_hints(&self, challenges: &Challenges<C::Fq>) -> Hints<C::Fq> {
         C::gen_hints(self.trace_len(), self.public_inputs(), challenges)
     }
@@ -232,16 +742,108 @@ impl<C: AirConfig> Air<C> {
         self.options.lde_blowup_factor as usize
     }
 
+    /// Operations of the composition constraint that evaluators hoist out of
+    /// the per-row loop
+    pub const fn row_independent_ops(&self) -> RowIndependentOps {
+        self.row_independent_ops
+    }
+
     pub const fn composition_constraint(
         &self,
     ) -> &CompositionConstraint<FieldVariant<C::Fp, C::Fq>> {
         &self.composition_constraint
     }
 
+    /// Returns the `(column, offset)` pairs used by the constraints. Proofs
+    /// contain an OOD evaluation for each pair rather than for a window of
+    /// rows so a long-range constraint only adds evaluations for the columns
+    /// it uses. Query openings are single rows whatever the offsets.
     pub fn trace_arguments(&self) -> BTreeSet<(usize, isize)> {
         self.constraints
             .iter()
             .map(Constraint::trace_arguments)
             .fold(BTreeSet::new(), |a, b| &a | &b)
     }
+
+    /// Returns an identifier for the AIR. The identifier is the SHA-256 hash of
+    /// the trace layout and a postfix encoding of every constraint.
+    pub fn id(&self) -> [u8; 32] {
+        let mut description = Vec::new();
+        for n in [
+            C::NUM_BASE_COLUMNS,
+            C::NUM_EXTENSION_COLUMNS,
+            self.trace_len,
+        ] {
+            description.extend_from_slice(&(n as u64).to_le_bytes());
+        }
+        for constraint in &self.constraints {
+            constraint.traverse(&mut |node| describe_node(node, &mut description));
+            description.push(u8::MAX);
+        }
+        Sha256::digest(description).into()
+    }
+}
+
+/// Appends an unambiguous encoding of a constraint node to `description`.
+/// Children are visited first so the encoding of a constraint is postfix.
+fn describe_node<Fp: CanonicalSerialize, Fq: CanonicalSerialize>(
+    node: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    description: &mut Vec<u8>,
+) {
+    let describe_constant =
+        |constant: &FieldVariant<Fp, Fq>, description: &mut Vec<u8>| match constant {
+            FieldVariant::Fp(v) => {
+                description.push(0);
+                v.serialize_compressed(&mut *description).unwrap();
+            }
+            FieldVariant::Fq(v) => {
+                description.push(1);
+                v.serialize_compressed(&mut *description).unwrap();
+            }
+        };
+    let describe_int = |n: u64, description: &mut Vec<u8>| {
+        description.extend_from_slice(&n.to_le_bytes());
+    };
+    match node {
+        Expr::Leaf(item) => match item {
+            AlgebraicItem::X => description.push(0),
+            AlgebraicItem::Constant(constant) => {
+                description.push(1);
+                describe_constant(constant, description);
+            }
+            AlgebraicItem::Challenge(i) => {
+                description.push(2);
+                describe_int(*i as u64, description);
+            }
+            AlgebraicItem::Periodic(column) => {
+                description.push(3);
+                describe_int(column.interval_size() as u64, description);
+                describe_int(column.coeffs().len() as u64, description);
+                for coeff in column.coeffs() {
+                    describe_constant(coeff, description);
+                }
+            }
+            AlgebraicItem::Hint(i) => {
+                description.push(4);
+                describe_int(*i as u64, description);
+            }
+            AlgebraicItem::Trace(column, offset) => {
+                description.push(5);
+                describe_int(*column as u64, description);
+                description.extend_from_slice(&(*offset as i64).to_le_bytes());
+            }
+            AlgebraicItem::PublicPolynomial(i) => {
+                description.push(11);
+                describe_int(*i as u64, description);
+            }
+        },
+        Expr::Neg(_) => description.push(6),
+        Expr::Add(_, _) => description.push(7),
+        Expr::Mul(_, _) => description.push(8),
+        Expr::Div(_, _) => description.push(9),
+        Expr::Pow(_, exponent) => {
+            description.push(10);
+            describe_int(*exponent as u64, description);
+        }
+    }
 }
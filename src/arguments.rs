@@ -0,0 +1,348 @@
+use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::VerifierChallenge;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::One;
+use ark_ff::Zero;
+use ministark_gpu::GpuFftField;
+
+/// Verifier challenges used by arguments. Rows of columns are compressed into
+/// a single value with the linear combination `w_0 * c_0 + ... + w_(k-1) *
+/// c_(k-1)` and `alpha` is the argument's random point.
+#[derive(Clone, Debug)]
+pub struct ArgumentChallenges {
+    /// Challenge index of `alpha`
+    pub alpha: usize,
+    /// Challenge indices of the weights `w_i`. One for each column.
+    pub weights: Vec<usize>,
+}
+
+impl ArgumentChallenges {
+    fn combination_expr<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        cols: &[usize],
+        offset: isize,
+    ) -> Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> {
+        cols.iter()
+            .zip(&self.weights)
+            .map(|(col, weight)| weight.challenge() * col.offset(offset))
+            .sum()
+    }
+
+    fn compress_expr<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        cols: &[usize],
+    ) -> Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> {
+        self.alpha.challenge() - self.combination_expr(cols, 0)
+    }
+
+    fn combination<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        challenges: &Challenges<Fq>,
+        row: impl Fn(usize) -> Fp,
+    ) -> Fq {
+        self.weights
+            .iter()
+            .enumerate()
+            .fold(Fq::zero(), |acc, (i, &weight)| {
+                acc + challenges[weight] * row(i)
+            })
+    }
+
+    fn compress<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        challenges: &Challenges<Fq>,
+        base: &Matrix<Fp>,
+        cols: &[usize],
+        row: usize,
+    ) -> Fq {
+        challenges[self.alpha] - self.combination(challenges, |i| base.0[cols[i]][row])
+    }
+}
+
+/// Running product argument that the rows of the `lhs` columns are a
+/// permutation of the rows of the `rhs` columns.
+///
+/// Each side has an extension column holding the product of `alpha - v_i`
+/// for the compressed rows `v_i` before it. Both products start at one and
+/// the terminal constraint checks the products over all rows are equal.
+/// Padding rows can be left out of a side with a dummy column.
+#[derive(Clone, Debug)]
+pub struct PermutationArgument {
+    lhs_cols: Vec<usize>,
+    rhs_cols: Vec<usize>,
+    challenges: ArgumentChallenges,
+    lhs_product_col: usize,
+    rhs_product_col: usize,
+    lhs_dummy_col: Option<usize>,
+    rhs_dummy_col: Option<usize>,
+}
+
+impl PermutationArgument {
+    /// Creates a permutation argument between base columns. The running
+    /// products are stored in the execution trace columns `product_cols`.
+    ///
+    /// # Panics
+    /// Panics if the sides don't have one column per challenge weight
+    pub fn new(
+        lhs_cols: Vec<usize>,
+        rhs_cols: Vec<usize>,
+        challenges: ArgumentChallenges,
+        product_cols: [usize; 2],
+    ) -> Self {
+        assert_eq!(lhs_cols.len(), challenges.weights.len());
+        assert_eq!(rhs_cols.len(), challenges.weights.len());
+        let [lhs_product_col, rhs_product_col] = product_cols;
+        Self {
+            lhs_cols,
+            rhs_cols,
+            challenges,
+            lhs_product_col,
+            rhs_product_col,
+            lhs_dummy_col: None,
+            rhs_dummy_col: None,
+        }
+    }
+
+    /// Leaves rows out of the `lhs` product where `dummy_col` is one.
+    /// The table must constrain `dummy_col` to be binary.
+    pub const fn skip_lhs_when(mut self, dummy_col: usize) -> Self {
+        self.lhs_dummy_col = Some(dummy_col);
+        self
+    }
+
+    /// Leaves rows out of the `rhs` product where `dummy_col` is one.
+    /// The table must constrain `dummy_col` to be binary.
+    pub const fn skip_rhs_when(mut self, dummy_col: usize) -> Self {
+        self.rhs_dummy_col = Some(dummy_col);
+        self
+    }
+
+    /// Returns the factor a row contributes to the running product. This is
+    /// `alpha - v` for regular rows and `1` for dummy rows.
+    fn factor_expr<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        cols: &[usize],
+        dummy_col: Option<usize>,
+    ) -> Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let factor = self.challenges.compress_expr(cols);
+        dummy_col.map_or_else(
+            || factor.clone(),
+            |dummy_col| &factor - &(dummy_col.curr() * (&factor - &one)),
+        )
+    }
+
+    /// Constraints that apply to the first row
+    pub fn boundary_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        vec![
+            self.lhs_product_col.curr() - one,
+            self.rhs_product_col.curr() - one,
+        ]
+    }
+
+    /// Constraints that apply to every row except the last
+    pub fn transition_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let lhs = self.lhs_product_col;
+        let rhs = self.rhs_product_col;
+        let lhs_factor = self.factor_expr(&self.lhs_cols, self.lhs_dummy_col);
+        let rhs_factor = self.factor_expr(&self.rhs_cols, self.rhs_dummy_col);
+        vec![
+            lhs.next() - lhs.curr() * lhs_factor,
+            rhs.next() - rhs.curr() * rhs_factor,
+        ]
+    }
+
+    /// Constraints that apply to the last row
+    pub fn terminal_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let lhs = self.lhs_product_col;
+        let rhs = self.rhs_product_col;
+        let lhs_factor = self.factor_expr(&self.lhs_cols, self.lhs_dummy_col);
+        let rhs_factor = self.factor_expr(&self.rhs_cols, self.rhs_dummy_col);
+        vec![lhs.curr() * lhs_factor - rhs.curr() * rhs_factor]
+    }
+
+    /// Generates the running product columns in the order `[lhs, rhs]`
+    pub fn gen_extension_columns<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        base: &Matrix<Fp>,
+        challenges: &Challenges<Fq>,
+    ) -> Matrix<Fq> {
+        Matrix::new(vec![
+            self.running_product(base, challenges, &self.lhs_cols, self.lhs_dummy_col),
+            self.running_product(base, challenges, &self.rhs_cols, self.rhs_dummy_col),
+        ])
+    }
+
+    fn running_product<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        base: &Matrix<Fp>,
+        challenges: &Challenges<Fq>,
+        cols: &[usize],
+        dummy_col: Option<usize>,
+    ) -> GpuVec<Fq> {
+        let num_rows = base.num_rows();
+        let mut product = Fq::one();
+        let mut column = Vec::with_capacity_in(num_rows, GpuAllocator);
+        for row in 0..num_rows {
+            column.push(product);
+            let is_dummy = dummy_col.map_or(false, |col| base.0[col][row].is_one());
+            if !is_dummy {
+                product *= self.challenges.compress(challenges, base, cols, row);
+            }
+        }
+        column
+    }
+}
+
+/// Running evaluation argument that absorbs rows of columns into a single
+/// terminal value. Used to show a table contains a public sequence e.g. a
+/// program or its inputs and outputs.
+///
+/// The compressed rows `v_i = w_0 * c_0 + ... + w_(k-1) * c_(k-1)` are
+/// treated as coefficients of a polynomial evaluated at `alpha` using
+/// Horner's method. With a selector column only rows where the selector is
+/// one are absorbed.
+#[derive(Clone, Debug)]
+pub struct EvaluationArgument {
+    cols: Vec<usize>,
+    challenges: ArgumentChallenges,
+    evaluation_col: usize,
+    selector_col: Option<usize>,
+    dummy_col: Option<usize>,
+}
+
+impl EvaluationArgument {
+    /// Creates an evaluation argument over base columns. The running
+    /// evaluation is stored in the execution trace column `evaluation_col`.
+    ///
+    /// # Panics
+    /// Panics if there isn't one column per challenge weight
+    pub fn new(cols: Vec<usize>, challenges: ArgumentChallenges, evaluation_col: usize) -> Self {
+        assert_eq!(cols.len(), challenges.weights.len());
+        Self {
+            cols,
+            challenges,
+            evaluation_col,
+            selector_col: None,
+            dummy_col: None,
+        }
+    }
+
+    /// Only absorb rows where the binary `selector_col` is one
+    pub const fn with_selector(mut self, selector_col: usize) -> Self {
+        self.selector_col = Some(selector_col);
+        self
+    }
+
+    /// Never absorb rows where the binary `dummy_col` is one e.g. padding
+    pub const fn skip_when(mut self, dummy_col: usize) -> Self {
+        self.dummy_col = Some(dummy_col);
+        self
+    }
+
+    /// Returns an expression that is one on absorbed rows and zero otherwise
+    fn absorb_expr<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        offset: isize,
+    ) -> Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let selector = self
+            .selector_col
+            .map_or_else(|| one.into(), |col| col.offset(offset));
+        self.dummy_col.map_or_else(
+            || selector.clone(),
+            |dummy_col| &selector - &(&selector * &dummy_col.offset(offset)),
+        )
+    }
+
+    fn is_absorbed<Fp: GpuFftField + FftField>(&self, base: &Matrix<Fp>, row: usize) -> bool {
+        let is_selected = self
+            .selector_col
+            .map_or(true, |col| base.0[col][row].is_one());
+        let is_dummy = self
+            .dummy_col
+            .map_or(false, |col| base.0[col][row].is_one());
+        is_selected && !is_dummy
+    }
+
+    /// Constraints that apply to the first row
+    pub fn boundary_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let absorb = self.absorb_expr(0);
+        let value = self.challenges.combination_expr(&self.cols, 0);
+        vec![self.evaluation_col.curr() - absorb * value]
+    }
+
+    /// Constraints that apply to every row except the last
+    pub fn transition_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let evaluation = self.evaluation_col;
+        let absorb = self.absorb_expr(1);
+        let next_value = self.challenges.combination_expr(&self.cols, 1);
+        let absorbed = evaluation.curr() * self.challenges.alpha.challenge() + next_value;
+        // the evaluation is unchanged on rows that aren't absorbed
+        vec![evaluation.next() - evaluation.curr() - absorb * (absorbed - evaluation.curr())]
+    }
+
+    /// Constraints that apply to the last row. `terminal` is the expected
+    /// evaluation, typically a hint computed with
+    /// [`EvaluationArgument::compute_terminal`].
+    pub fn terminal_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        terminal: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        vec![self.evaluation_col.curr() - terminal]
+    }
+
+    /// Computes the terminal value for a sequence of absorbed rows
+    pub fn compute_terminal<'a, Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        challenges: &Challenges<Fq>,
+        rows: impl IntoIterator<Item = &'a [Fp]>,
+    ) -> Fq {
+        let alpha = challenges[self.challenges.alpha];
+        rows.into_iter().fold(Fq::zero(), |acc, row| {
+            acc * alpha + self.challenges.combination(challenges, |i| row[i])
+        })
+    }
+
+    /// Generates the running evaluation column
+    pub fn gen_extension_columns<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        base: &Matrix<Fp>,
+        challenges: &Challenges<Fq>,
+    ) -> Matrix<Fq> {
+        let alpha = challenges[self.challenges.alpha];
+        let num_rows = base.num_rows();
+        let mut evaluation = Fq::zero();
+        let mut column = Vec::with_capacity_in(num_rows, GpuAllocator);
+        for row in 0..num_rows {
+            if self.is_absorbed(base, row) {
+                let value = self
+                    .challenges
+                    .combination(challenges, |i| base.0[self.cols[i]][row]);
+                evaluation = evaluation * alpha + value;
+            }
+            column.push(evaluation);
+        }
+        Matrix::new(vec![column])
+    }
+}
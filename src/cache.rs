@@ -0,0 +1,163 @@
+//! Caches for data that is reused between proofs
+//!
+//! Servers proving the same program over and over recompute commitments to
+//! preprocessed columns and tables of domain elements that only depend on the
+//! field, the domain size and the program. A [`ProverCache`] stores these as
+//! bytes under a [`CacheKey`] so they can be loaded instead of recomputed.
+//! [`MemoryCache`] and [`FileSystemCache`] are provided. Other key-value stores
+//! (e.g. sled) can be plugged in by implementing [`ProverCache`].
+
+use crate::hash::HashFn;
+use crate::hash::LeafEncoder;
+use crate::merkle::MatrixMerkleTree;
+use crate::merkle::MatrixMerkleTreeImpl;
+use crate::Matrix;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use digest::Digest as _;
+use sha2::Sha256;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+/// Identifies a cache entry by its name, field, domain size and program
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CacheKey([u8; 32]);
+
+impl CacheKey {
+    /// Creates the key of the entry `name` for a program over the field `F`.
+    /// `program_hash` identifies the program e.g. [`crate::Air::id`] or a hash
+    /// of the preprocessed columns.
+    pub fn new<F: Field>(name: &str, domain_size: usize, program_hash: [u8; 32]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update((name.len() as u64).to_le_bytes());
+        hasher.update(name.as_bytes());
+        let characteristic = F::characteristic();
+        hasher.update((characteristic.len() as u64).to_le_bytes());
+        for limb in characteristic {
+            hasher.update(limb.to_le_bytes());
+        }
+        hasher.update(F::extension_degree().to_le_bytes());
+        hasher.update((domain_size as u64).to_le_bytes());
+        hasher.update(program_hash);
+        Self(hasher.finalize().into())
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+/// Key-value store for data reused between proofs. Caching is best effort so
+/// implementations can drop entries or ignore failed writes.
+pub trait ProverCache: Send + Sync {
+    fn get(&self, key: &CacheKey) -> Option<Vec<u8>>;
+
+    fn insert(&self, key: &CacheKey, value: Vec<u8>);
+}
+
+/// Cache that keeps entries in memory for the life of the process
+#[derive(Debug, Default)]
+pub struct MemoryCache(RwLock<BTreeMap<CacheKey, Vec<u8>>>);
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ProverCache for MemoryCache {
+    fn get(&self, key: &CacheKey) -> Option<Vec<u8>> {
+        self.0.read().unwrap().get(key).cloned()
+    }
+
+    fn insert(&self, key: &CacheKey, value: Vec<u8>) {
+        self.0.write().unwrap().insert(*key, value);
+    }
+}
+
+/// Cache that stores each entry as a file in a directory
+#[derive(Clone, Debug)]
+pub struct FileSystemCache {
+    dir: PathBuf,
+}
+
+impl FileSystemCache {
+    /// Creates a cache in `dir`. The directory is created on the first insert.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl ProverCache for FileSystemCache {
+    fn get(&self, key: &CacheKey) -> Option<Vec<u8>> {
+        fs::read(self.dir.join(key.to_hex())).ok()
+    }
+
+    fn insert(&self, key: &CacheKey, value: Vec<u8>) {
+        // write to a temporary file first so readers never see a partial entry
+        let path = self.dir.join(key.to_hex());
+        let tmp_path = path.with_extension("tmp");
+        let _ = fs::create_dir_all(&self.dir)
+            .and_then(|()| fs::write(&tmp_path, value))
+            .and_then(|()| fs::rename(&tmp_path, path));
+    }
+}
+
+/// Returns the cached value or computes and caches it. Entries that fail to
+/// deserialize are recomputed.
+pub fn get_or_insert_with<T: CanonicalSerialize + CanonicalDeserialize>(
+    cache: &(impl ProverCache + ?Sized),
+    key: &CacheKey,
+    f: impl FnOnce() -> T,
+) -> T {
+    let cached = cache
+        .get(key)
+        .and_then(|bytes| T::deserialize_uncompressed(&*bytes).ok());
+    if let Some(value) = cached {
+        return value;
+    }
+    let value = f();
+    let mut bytes = Vec::new();
+    value.serialize_uncompressed(&mut bytes).unwrap();
+    cache.insert(key, bytes);
+    value
+}
+
+/// Returns the Merkle tree of a matrix of preprocessed columns. The matrix is
+/// only generated and committed to if the tree isn't cached.
+pub fn preprocessed_merkle_tree<F: Field, H: HashFn, E: LeafEncoder<F>>(
+    cache: &(impl ProverCache + ?Sized),
+    key: &CacheKey,
+    gen_matrix: impl FnOnce() -> Matrix<F>,
+) -> MatrixMerkleTreeImpl<H, E> {
+    get_or_insert_with(cache, key, || {
+        MatrixMerkleTreeImpl::from_matrix(&gen_matrix())
+    })
+}
+
+/// Returns the elements of a domain in their natural order. Elements only
+/// depend on the domain so entries are shared by all programs.
+pub fn domain_elements<F: FftField>(
+    cache: &(impl ProverCache + ?Sized),
+    domain: Radix2EvaluationDomain<F>,
+) -> Vec<F> {
+    let mut offset = Vec::new();
+    domain
+        .coset_offset()
+        .serialize_compressed(&mut offset)
+        .unwrap();
+    let key = CacheKey::new::<F>(
+        "domain elements",
+        domain.size(),
+        Sha256::digest(offset).into(),
+    );
+    get_or_insert_with(cache, &key, || domain.elements().collect())
+}
@@ -2,7 +2,13 @@ use crate::challenges::Challenges;
 use crate::fri;
 use crate::fri::FriProof;
 use crate::hints::Hints;
+use crate::proof::ProofHeader;
+use crate::proof::ProofMetadata;
+use crate::proof::SampledChallenge;
+use crate::random::derive_query_positions;
+use crate::random::draw_from_sub_coin;
 use crate::random::PublicCoin;
+use crate::random::SubCoin;
 use crate::stark::Stark;
 use crate::trace::Queries;
 use crate::Air;
@@ -11,7 +17,6 @@ use alloc::vec::Vec;
 use ark_ff::Field;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
-use std::collections::BTreeSet;
 
 pub struct ProverChannel<'a, S: Stark> {
     air: &'a Air<S::AirConfig>,
@@ -24,11 +29,13 @@ pub struct ProverChannel<'a, S: Stark> {
     execution_trace_ood_evals: Vec<S::Fq>,
     composition_trace_ood_evals: Vec<S::Fq>,
     pow_nonce: u64,
+    challenges: Vec<SampledChallenge<S::Fq>>,
 }
 
 // impl<'a, A: Air, D: Digest> ProverChannel<'a, A, D> {
 impl<'a, S: Stark> ProverChannel<'a, S> {
-    pub fn new(air: &'a Air<S::AirConfig>, public_coin: S::PublicCoin) -> Self {
+    pub fn new(air: &'a Air<S::AirConfig>, mut public_coin: S::PublicCoin) -> Self {
+        air.trace_info().bind(&mut public_coin);
         ProverChannel {
             air,
             public_coin,
@@ -40,6 +47,7 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
             fri_layer_commitments: Vec::new(),
             fri_remainder_coeffs: Vec::new(),
             pow_nonce: 0,
+            challenges: Vec::new(),
         }
     }
 
@@ -58,8 +66,29 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
         self.composition_trace_commitment = commitment;
     }
 
+    /// Records challenges drawn from a fork of [`Self::public_coin`]
+    pub fn record_challenges(&mut self, sub_coin: SubCoin, label: &'static str, values: &[S::Fq]) {
+        self.challenges.push(SampledChallenge {
+            sub_coin,
+            label,
+            values: values.to_vec(),
+        });
+    }
+
+    /// Draws `n` challenges from `sub_coin` and records them
+    pub fn draw_challenges(
+        &mut self,
+        sub_coin: SubCoin,
+        label: &'static str,
+        n: usize,
+    ) -> Vec<S::Fq> {
+        let values = draw_from_sub_coin(&self.public_coin, sub_coin, n);
+        self.record_challenges(sub_coin, label, &values);
+        values
+    }
+
     pub fn get_ood_point(&mut self) -> S::Fq {
-        self.public_coin.draw()
+        self.draw_challenges(SubCoin::Deep, "ood point", 1)[0]
     }
 
     pub fn send_ood_evals(
@@ -89,14 +118,29 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
             .verify_proof_of_work(grinding_factor, nonce));
 
         self.pow_nonce = nonce;
-        self.public_coin.reseed_with_int(self.pow_nonce);
     }
 
-    pub fn get_fri_query_positions(&mut self) -> BTreeSet<usize> {
+    /// Draws the query positions. See [`derive_query_positions`]
+    pub fn get_fri_query_positions(&mut self) -> Vec<usize> {
         // TODO: voulnerability if multiple positions are the same
         let lde_domain_size = self.air.trace_len() * self.air.lde_blowup_factor();
         let num_queries = self.air.options().num_queries as usize;
-        self.public_coin.draw_queries(num_queries, lde_domain_size)
+        derive_query_positions(
+            &mut self.public_coin,
+            self.pow_nonce,
+            num_queries,
+            lde_domain_size,
+        )
+    }
+
+    /// Returns the challenges recorded so far along with the proof of work
+    /// nonce and query positions
+    pub fn metadata(&self, query_positions: &[usize]) -> ProofMetadata<S::Fq> {
+        ProofMetadata {
+            challenges: self.challenges.clone(),
+            pow_nonce: self.pow_nonce,
+            query_positions: query_positions.to_vec(),
+        }
     }
 
     pub fn build_proof(
@@ -105,7 +149,7 @@ impl<'a, S: Stark> ProverChannel<'a, S> {
         fri_proof: FriProof<S::Fq, S::Digest, S::MerkleTree>,
     ) -> Proof<S> {
         Proof {
-            options: self.air.options(),
+            header: ProofHeader::new::<S>(self.air),
             trace_len: self.air.trace_len(),
             base_trace_commitment: self.base_trace_commitment,
             extension_trace_commitment: self.extension_trace_commitment,
@@ -137,7 +181,7 @@ impl<'a, S: Stark> fri::ProverChannel for ProverChannel<'a, S> {
     }
 
     fn draw_fri_alpha(&mut self) -> S::Fq {
-        self.public_coin.draw()
+        self.draw_challenges(SubCoin::Fri, "fri alpha", 1)[0]
     }
 }
 
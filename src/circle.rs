@@ -0,0 +1,343 @@
+//! Experimental circle STARK domains
+//!
+//! Mersenne-31 has a multiplicative group of order `2 * (2^30 - 1)` so it has
+//! no large power of two subgroups for a radix-2 FFT. The circle `x^2 + y^2 =
+//! 1` over M31 has `2^31` points though and the circle FFT of Haböck, Levit
+//! and Papini uses cosets of it as evaluation domains. [`FftDomain`] abstracts
+//! over both kinds of domain so circle STARK prototypes can reuse the parts of
+//! the pipeline written against it.
+//!
+//! Circle domains have no `X` so only constraints that are polynomials in
+//! the trace cells, constants and challenges are supported (see
+//! [`check_circle_constraint`]). The prover divides by the circle domain's
+//! vanishing polynomial itself (see [`CircleDomain::vanishing_eval`]).
+
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::expression::Expr;
+use alloc::vec::Vec;
+use ark_ff::batch_inversion;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_ff::Fp64;
+use ark_ff::MontBackend;
+use ark_ff::MontConfig;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use core::ops::Add;
+use snafu::Snafu;
+
+#[derive(MontConfig)]
+#[modulus = "2147483647"]
+#[generator = "7"]
+pub struct M31Config;
+
+/// Mersenne-31 prime field `2^31 - 1`
+pub type M31 = Fp64<MontBackend<M31Config, 1>>;
+
+/// Domain polynomials can be interpolated over and evaluated on
+pub trait FftDomain<F> {
+    fn size(&self) -> usize;
+
+    /// Returns the coefficients of the polynomial with evaluations `evals`
+    fn interpolate(&self, evals: Vec<F>) -> Vec<F>;
+
+    /// Evaluates the polynomial with coefficients `coeffs` over the domain.
+    /// There can be fewer coefficients than the size of the domain.
+    fn evaluate(&self, coeffs: Vec<F>) -> Vec<F>;
+}
+
+impl<F: FftField> FftDomain<F> for Radix2EvaluationDomain<F> {
+    fn size(&self) -> usize {
+        EvaluationDomain::size(self)
+    }
+
+    fn interpolate(&self, evals: Vec<F>) -> Vec<F> {
+        self.ifft(&evals)
+    }
+
+    fn evaluate(&self, coeffs: Vec<F>) -> Vec<F> {
+        self.fft(&coeffs)
+    }
+}
+
+/// Point on the circle `x^2 + y^2 = 1`. Points form a group with the identity
+/// `(1, 0)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CirclePoint<F> {
+    pub x: F,
+    pub y: F,
+}
+
+impl<F: Field> CirclePoint<F> {
+    pub fn identity() -> Self {
+        Self {
+            x: F::one(),
+            y: F::zero(),
+        }
+    }
+
+    /// Returns the inverse of the point. Inverses share an x-coordinate.
+    pub fn conjugate(self) -> Self {
+        Self {
+            x: self.x,
+            y: -self.y,
+        }
+    }
+
+    pub fn double(self) -> Self {
+        self + self
+    }
+
+    pub fn repeated_double(mut self, n: u32) -> Self {
+        for _ in 0..n {
+            self = self.double();
+        }
+        self
+    }
+
+    /// Returns `self + ... + self` (`n` times)
+    pub fn repeated_add(self, mut n: usize) -> Self {
+        let mut result = Self::identity();
+        let mut base = self;
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result + base;
+            }
+            base = base.double();
+            n >>= 1;
+        }
+        result
+    }
+}
+
+impl<F: Field> Add for CirclePoint<F> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            x: self.x * other.x - self.y * other.y,
+            y: self.x * other.y + self.y * other.x,
+        }
+    }
+}
+
+/// Field with a circle group of order `2^LOG_CIRCLE_ORDER`
+pub trait CircleField: Field {
+    const LOG_CIRCLE_ORDER: u32;
+
+    /// Generator of the circle group
+    fn circle_generator() -> CirclePoint<Self>;
+
+    /// Returns the generator of the circle subgroup of order `2^log_size`
+    fn circle_subgroup_generator(log_size: u32) -> CirclePoint<Self> {
+        assert!(log_size <= Self::LOG_CIRCLE_ORDER);
+        Self::circle_generator().repeated_double(Self::LOG_CIRCLE_ORDER - log_size)
+    }
+}
+
+impl CircleField for M31 {
+    const LOG_CIRCLE_ORDER: u32 = 31;
+
+    fn circle_generator() -> CirclePoint<Self> {
+        CirclePoint {
+            x: Self::from(2u32),
+            y: Self::from(1_268_011_823u32),
+        }
+    }
+}
+
+/// Standard position coset of size `2^log_size` i.e. the points `q + i * g`
+/// where `g` generates the subgroup of order `2^log_size` and `q` generates
+/// the subgroup of order `2^(log_size + 1)`. The coset is closed under
+/// conjugation (point `i` is the conjugate of point `n - 1 - i`) and doubling
+/// maps it to the standard position coset of half the size.
+#[derive(Debug, Clone, Copy)]
+pub struct CircleDomain<F> {
+    log_size: u32,
+    offset: CirclePoint<F>,
+    step: CirclePoint<F>,
+}
+
+impl<F: CircleField> CircleDomain<F> {
+    /// # Panics
+    /// Panics if the domain is empty or larger than the circle group
+    pub fn new(log_size: u32) -> Self {
+        assert!(log_size >= 1 && log_size < F::LOG_CIRCLE_ORDER);
+        Self {
+            log_size,
+            offset: F::circle_subgroup_generator(log_size + 1),
+            step: F::circle_subgroup_generator(log_size),
+        }
+    }
+
+    pub const fn log_size(&self) -> u32 {
+        self.log_size
+    }
+
+    pub fn element(&self, i: usize) -> CirclePoint<F> {
+        self.offset + self.step.repeated_add(i)
+    }
+
+    pub fn elements(&self) -> Vec<CirclePoint<F>> {
+        let mut point = self.offset;
+        (0..1 << self.log_size)
+            .map(|_| {
+                let curr = point;
+                point = point + self.step;
+                curr
+            })
+            .collect()
+    }
+
+    /// Evaluates the vanishing polynomial of the domain at `point`. This is
+    /// the x-coordinate of `point` doubled `log_size - 1` times.
+    pub fn vanishing_eval(&self, point: CirclePoint<F>) -> F {
+        point.repeated_double(self.log_size - 1).x
+    }
+
+    /// Returns the x-coordinates of the first half of the domain. The
+    /// negation of `xs[i]` is `xs[len - 1 - i]`.
+    fn half_xs(&self) -> Vec<F> {
+        let mut elements = self.elements();
+        elements.truncate(elements.len() / 2);
+        elements.into_iter().map(|p| p.x).collect()
+    }
+}
+
+impl<F: CircleField> FftDomain<F> for CircleDomain<F> {
+    fn size(&self) -> usize {
+        1 << self.log_size
+    }
+
+    /// Returns coefficients in the circle FFT basis. Bit 0 of a coefficient's
+    /// index is the power of `y`, bit 1 the power of `x` and bit `i + 1` the
+    /// power of `x` doubled `i` times.
+    fn interpolate(&self, evals: Vec<F>) -> Vec<F> {
+        let n = self.size();
+        assert_eq!(evals.len(), n);
+        let points = self.elements();
+        let mut y_invs = points[0..n / 2]
+            .iter()
+            .map(|p| p.y.double())
+            .collect::<Vec<F>>();
+        batch_inversion(&mut y_invs);
+        let two_inv = F::from(2u8).inverse().unwrap();
+        let (f0, f1) = (0..n / 2)
+            .map(|i| {
+                let (a, b) = (evals[i], evals[n - 1 - i]);
+                ((a + b) * two_inv, (a - b) * y_invs[i])
+            })
+            .unzip();
+        let xs = self.half_xs();
+        interleave(line_interpolate(f0, &xs), line_interpolate(f1, &xs))
+    }
+
+    fn evaluate(&self, mut coeffs: Vec<F>) -> Vec<F> {
+        let n = self.size();
+        assert!(coeffs.len() <= n);
+        coeffs.resize(n, F::zero());
+        let (c0, c1) = deinterleave(coeffs);
+        let xs = self.half_xs();
+        let f0 = line_evaluate(c0, &xs);
+        let f1 = line_evaluate(c1, &xs);
+        let points = self.elements();
+        let mut evals = vec![F::zero(); n];
+        for i in 0..n / 2 {
+            let y_f1 = points[i].y * f1[i];
+            evals[i] = f0[i] + y_f1;
+            evals[n - 1 - i] = f0[i] - y_f1;
+        }
+        evals
+    }
+}
+
+/// Interpolates over x-coordinates closed under negation where the negation
+/// of `xs[i]` is `xs[len - 1 - i]`
+fn line_interpolate<F: Field>(evals: Vec<F>, xs: &[F]) -> Vec<F> {
+    let m = evals.len();
+    if m == 1 {
+        return evals;
+    }
+    let half = &xs[0..m / 2];
+    let mut x_invs = half.iter().map(|x| x.double()).collect::<Vec<F>>();
+    batch_inversion(&mut x_invs);
+    let two_inv = F::from(2u8).inverse().unwrap();
+    let (g0, g1) = (0..m / 2)
+        .map(|i| {
+            let (a, b) = (evals[i], evals[m - 1 - i]);
+            ((a + b) * two_inv, (a - b) * x_invs[i])
+        })
+        .unzip();
+    let next_xs = half.iter().map(|&x| double_x(x)).collect::<Vec<F>>();
+    interleave(
+        line_interpolate(g0, &next_xs),
+        line_interpolate(g1, &next_xs),
+    )
+}
+
+fn line_evaluate<F: Field>(coeffs: Vec<F>, xs: &[F]) -> Vec<F> {
+    let m = coeffs.len();
+    if m == 1 {
+        return coeffs;
+    }
+    let half = &xs[0..m / 2];
+    let next_xs = half.iter().map(|&x| double_x(x)).collect::<Vec<F>>();
+    let (c0, c1) = deinterleave(coeffs);
+    let g0 = line_evaluate(c0, &next_xs);
+    let g1 = line_evaluate(c1, &next_xs);
+    let mut evals = vec![F::zero(); m];
+    for (i, &x) in half.iter().enumerate() {
+        evals[i] = g0[i] + x * g1[i];
+        evals[m - 1 - i] = g0[i] - x * g1[i];
+    }
+    evals
+}
+
+/// Returns the x-coordinate of a point with x-coordinate `x` doubled
+fn double_x<F: Field>(x: F) -> F {
+    x.square().double() - F::one()
+}
+
+fn interleave<F: Copy>(even: Vec<F>, odd: Vec<F>) -> Vec<F> {
+    even.into_iter()
+        .zip(odd)
+        .flat_map(|(even, odd)| [even, odd])
+        .collect()
+}
+
+fn deinterleave<F: Copy>(values: Vec<F>) -> (Vec<F>, Vec<F>) {
+    values.chunks(2).map(|pair| (pair[0], pair[1])).unzip()
+}
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum CircleError {
+    #[snafu(display("circle constraints can't reference the domain point X"))]
+    DomainPoint,
+    #[snafu(display("circle constraints can't use periodic columns"))]
+    PeriodicColumn,
+    #[snafu(display("circle constraints can't use public polynomials"))]
+    PublicPolynomial,
+    #[snafu(display("circle constraints can't divide"))]
+    Division,
+}
+
+/// Checks a constraint only uses AIR features supported over circle domains.
+/// Periodic columns, public polynomials and divisors are all defined in terms
+/// of the multiplicative domain point `X` which circle domains don't have.
+pub fn check_circle_constraint<T>(constraint: &Constraint<T>) -> Result<(), CircleError> {
+    let mut result = Ok(());
+    constraint.traverse(&mut |node| {
+        let error = match node {
+            Expr::Div(_, _) => Some(CircleError::Division),
+            Expr::Leaf(AlgebraicItem::X) => Some(CircleError::DomainPoint),
+            Expr::Leaf(AlgebraicItem::Periodic(_)) => Some(CircleError::PeriodicColumn),
+            Expr::Leaf(AlgebraicItem::PublicPolynomial(_)) => Some(CircleError::PublicPolynomial),
+            _ => None,
+        };
+        if result.is_ok() {
+            result = error.map_or(Ok(()), Err);
+        }
+    });
+    result
+}
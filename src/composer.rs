@@ -1,15 +1,40 @@
 use crate::air::AirConfig;
+#[cfg(feature = "gpu")]
+use crate::utils::batch_inverse;
 use crate::utils::divide_out_point_into;
 use crate::utils::divide_out_points_into;
 use crate::utils::horner_evaluate;
+#[cfg(feature = "gpu")]
+use crate::utils::use_gpu;
 use crate::utils::GpuAllocator;
 use crate::utils::GpuVec;
 use crate::Air;
 use crate::Matrix;
+#[cfg(feature = "gpu")]
+use crate::StarkExtensionOf;
+#[cfg(feature = "gpu")]
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
+#[cfg(feature = "gpu")]
+use ark_ff::FftField;
 use ark_ff::Field;
 use ark_ff::Zero;
 use ark_poly::EvaluationDomain;
+#[cfg(feature = "gpu")]
+use ark_poly::Radix2EvaluationDomain;
+use ministark_gpu::prelude::*;
+#[cfg(feature = "gpu")]
+use ministark_gpu::stage::AddAssignConstStage;
+#[cfg(feature = "gpu")]
+use ministark_gpu::stage::ConvertIntoStage;
+#[cfg(feature = "gpu")]
+use ministark_gpu::stage::MulAssignConstStage;
+#[cfg(feature = "gpu")]
+use ministark_gpu::stage::MulAssignStage;
+#[cfg(feature = "gpu")]
+use ministark_gpu::stage::MulIntoConstStage;
+#[cfg(feature = "gpu")]
+use ministark_gpu::GpuFftField;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use std::iter::zip;
@@ -20,6 +45,8 @@ pub struct DeepPolyComposer<'a, A: AirConfig> {
     base_trace_polys: Matrix<A::Fp>,
     extension_trace_polys: Option<Matrix<A::Fq>>,
     composition_trace_polys: Matrix<A::Fq>,
+    /// Evaluations from [`Self::get_ood_evals`]
+    ood_evals: Option<(Vec<A::Fq>, Vec<A::Fq>)>,
 }
 
 impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
@@ -36,6 +63,7 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
             base_trace_polys,
             extension_trace_polys,
             composition_trace_polys,
+            ood_evals: None,
         }
     }
 
@@ -47,6 +75,7 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
             base_trace_polys,
             extension_trace_polys,
             composition_trace_polys,
+            ood_evals: _,
         } = self;
 
         let trace_domain = air.trace_domain();
@@ -74,25 +103,38 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
                     panic!("column is {col_idx} but there are only {num_columns} columns")
                 }
             })
-            .collect();
+            .collect::<Vec<A::Fq>>();
 
         // generate ood evaluations for the composition trace polynomials
         let z_n = self.z.pow([composition_trace_polys.num_cols() as u64]);
         let composition_trace_evals = ark_std::cfg_iter!(composition_trace_polys)
             .map(|column| horner_evaluate(column, &z_n))
-            .collect();
+            .collect::<Vec<A::Fq>>();
 
+        self.ood_evals = Some((
+            execution_trace_evals.clone(),
+            composition_trace_evals.clone(),
+        ));
         (execution_trace_evals, composition_trace_evals)
     }
 
     // <https://medium.com/starkware/starkdex-deep-dive-the-stark-core-engine-497942d0f0ab>
     pub fn into_deep_poly(self, composition_coeffs: DeepCompositionCoeffs<A::Fq>) -> Matrix<A::Fq> {
+        #[cfg(feature = "gpu")]
+        if use_gpu::<A::Fq>(self.composition_trace_polys.num_rows()) {
+            return self.into_deep_poly_gpu(composition_coeffs);
+        }
+        self.into_deep_poly_cpu(composition_coeffs)
+    }
+
+    fn into_deep_poly_cpu(self, composition_coeffs: DeepCompositionCoeffs<A::Fq>) -> Matrix<A::Fq> {
         let Self {
             z,
             air,
             base_trace_polys,
             extension_trace_polys,
             composition_trace_polys,
+            ood_evals: _,
         } = self;
 
         let DeepCompositionCoeffs {
@@ -163,29 +205,226 @@ impl<'a, A: AirConfig> DeepPolyComposer<'a, A> {
                 .collect(),
         );
         let mut combined_coeffs = GpuVec::try_from(quotients.sum_columns()).unwrap();
+        adjust_degree(&mut combined_coeffs, degree_alpha, degree_beta);
+        Matrix::new(vec![combined_coeffs])
+    }
 
-        let chunk_size = 1 << 16;
-        if degree_beta.is_zero() {
-            // P(x) * alpha
-            ark_std::cfg_chunks_mut!(combined_coeffs, chunk_size).for_each(|coeff_chunk| {
-                for coeff in coeff_chunk {
-                    *coeff *= degree_alpha;
+    /// Computes the DEEP quotients in evaluation form over a coset of the
+    /// trace domain with [`deep_quotients_gpu`] and interpolates their sum
+    #[cfg(feature = "gpu")]
+    fn into_deep_poly_gpu(
+        mut self,
+        composition_coeffs: DeepCompositionCoeffs<A::Fq>,
+    ) -> Matrix<A::Fq> {
+        let (execution_trace_evals, composition_trace_evals) = match self.ood_evals.take() {
+            Some(ood_evals) => ood_evals,
+            None => self.get_ood_evals(),
+        };
+        let Self {
+            z,
+            air,
+            base_trace_polys,
+            extension_trace_polys,
+            composition_trace_polys,
+            ood_evals: _,
+        } = self;
+
+        let DeepCompositionCoeffs {
+            execution_trace: execution_trace_alphas,
+            composition_trace: composition_trace_alphas,
+            degree: (degree_alpha, degree_beta),
+        } = composition_coeffs;
+
+        let trace_domain = air.trace_domain();
+        let g = trace_domain.group_gen();
+        let g_inv = trace_domain.group_gen_inv();
+
+        // composition trace columns come first followed by the extension columns
+        let num_composition_columns = composition_trace_polys.num_cols();
+        let z_n = z.pow([num_composition_columns as u64]);
+        let mut quotients = vec![DeepQuotient {
+            z: z_n,
+            base_columns: Vec::new(),
+            extension_columns: zip(composition_trace_evals, composition_trace_alphas)
+                .enumerate()
+                .map(|(i, (eval, alpha))| (i, eval, alpha))
+                .collect(),
+        }];
+        let mut execution_trace_quotients = BTreeMap::new();
+        for (((col_idx, offset), eval), alpha) in zip(
+            zip(air.trace_arguments(), execution_trace_evals),
+            execution_trace_alphas,
+        ) {
+            let quotient = execution_trace_quotients.entry(offset).or_insert_with(|| {
+                let generator = if offset >= 0 { g } else { g_inv };
+                DeepQuotient {
+                    z: z * generator.pow([offset.unsigned_abs() as u64]),
+                    base_columns: Vec::new(),
+                    extension_columns: Vec::new(),
                 }
             });
-        } else {
-            // Adjust the degree
-            // P(x) * (alpha + x * beta)
-            let mut last = A::Fq::zero();
-            for coeff in &mut combined_coeffs {
-                let tmp = *coeff;
-                *coeff *= degree_alpha;
-                *coeff += last * degree_beta;
-                last = tmp;
+            if col_idx < A::NUM_BASE_COLUMNS {
+                quotient.base_columns.push((col_idx, eval, alpha));
+            } else {
+                let column = num_composition_columns + col_idx - A::NUM_BASE_COLUMNS;
+                quotient.extension_columns.push((column, eval, alpha));
             }
         }
+        quotients.extend(execution_trace_quotients.into_values());
 
-        Matrix::new(vec![combined_coeffs])
+        let n = composition_trace_polys.num_rows();
+        let domain = Radix2EvaluationDomain::new_coset(n, A::domain_offset()).unwrap();
+        let base_evaluations = base_trace_polys.into_evaluations(domain);
+        let mut extension_columns = composition_trace_polys;
+        if let Some(extension_trace_polys) = extension_trace_polys {
+            extension_columns.append(extension_trace_polys);
+        }
+        let extension_evaluations = extension_columns.into_evaluations(domain);
+        let xs = domain.elements().collect::<Vec<A::Fp>>();
+
+        let combined_evals =
+            deep_quotients_gpu(&xs, &base_evaluations, &extension_evaluations, &quotients);
+        let mut combined_coeffs = Matrix::new(vec![combined_evals]).into_polynomials(domain);
+        adjust_degree(&mut combined_coeffs[0], degree_alpha, degree_beta);
+        combined_coeffs
+    }
+}
+
+/// Columns divided by the same point `z` in the DEEP composition polynomial.
+/// Each column is listed as `(column, f(z), alpha)`.
+#[cfg(feature = "gpu")]
+struct DeepQuotient<F> {
+    z: F,
+    base_columns: Vec<(usize, F, F)>,
+    extension_columns: Vec<(usize, F, F)>,
+}
+
+/// Evaluates `sum_i alpha_i * (f_i(x) - f_i(z_i)) / (x - z_i)` at each `x` in
+/// `xs`. The inverses of `x - z_i` are computed once per point with a batch
+/// inversion on the CPU (there is no extension field inverse kernel) and the
+/// quotients and their sum are computed with fused GPU kernels.
+#[cfg(feature = "gpu")]
+fn deep_quotients_gpu<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+    xs: &[Fp],
+    base_evaluations: &Matrix<Fp>,
+    extension_evaluations: &Matrix<Fq>,
+    quotients: &[DeepQuotient<Fq>],
+) -> GpuVec<Fq> {
+    let n = xs.len();
+    let library = &get_planner().library;
+    let command_queue = &get_planner().command_queue;
+    let device = command_queue.device();
+    let command_buffer = command_queue.new_command_buffer();
+    let mut accumulator = Vec::with_capacity_in(n, GpuAllocator);
+    accumulator.resize(n, Fq::zero());
+    let mut scratch = accumulator.clone();
+    let accumulator_buffer = buffer_mut_no_copy(device, &mut accumulator);
+    let scratch_buffer = buffer_mut_no_copy(device, &mut scratch);
+    let base_buffers = base_evaluations
+        .iter()
+        .map(|column| buffer_no_copy(device, column))
+        .collect::<Vec<_>>();
+    let extension_buffers = extension_evaluations
+        .iter()
+        .map(|column| buffer_no_copy(device, column))
+        .collect::<Vec<_>>();
+
+    let convert = ConvertIntoStage::<Fq, Fp>::new(library, n);
+    let mul_into_const = MulIntoConstStage::<Fq>::new(library, n);
+    let mul_assign_const = MulAssignConstStage::<Fq>::new(library, n);
+    let add_assign_const = AddAssignConstStage::<Fq>::new(library, n);
+    let mul_assign = MulAssignStage::<Fq>::new(library, n);
+    let add_assign = AddAssignStage::<Fq>::new(library, n);
+
+    // the inverses must outlive the command buffer
+    let mut all_inverses = Vec::new();
+    for quotient in quotients {
+        let mut inverses = ark_std::cfg_iter!(xs)
+            .map(|&x| Fq::from(x) - quotient.z)
+            .collect::<Vec<Fq>>()
+            .to_vec_in(GpuAllocator);
+        batch_inverse(&mut inverses);
+        let inverses_buffer = buffer_no_copy(device, &inverses);
+        // scratch holds alpha * f(x) and becomes alpha * (f(x) - f(z)) / (x - z)
+        let accumulate = |eval: Fq, alpha: Fq| {
+            add_assign_const.encode(command_buffer, &scratch_buffer, &(-alpha * eval));
+            mul_assign.encode(command_buffer, &scratch_buffer, &inverses_buffer, 0);
+            add_assign.encode(command_buffer, &accumulator_buffer, &scratch_buffer, 0);
+        };
+        for &(column, eval, alpha) in &quotient.base_columns {
+            convert.encode(command_buffer, &scratch_buffer, &base_buffers[column]);
+            mul_assign_const.encode(command_buffer, &scratch_buffer, alpha);
+            accumulate(eval, alpha);
+        }
+        for &(column, eval, alpha) in &quotient.extension_columns {
+            let column_buffer = &extension_buffers[column];
+            mul_into_const.encode(command_buffer, &scratch_buffer, column_buffer, &alpha);
+            accumulate(eval, alpha);
+        }
+        all_inverses.push(inverses);
+    }
+    command_buffer.commit();
+    command_buffer.wait_until_completed();
+    drop(all_inverses);
+    accumulator
+}
+
+/// Multiplies the polynomial `P(x)` by `alpha + x * beta` in place. The
+/// leading coefficient of `P(x)` must be zero. Quotients from synthetic
+/// division always have a zero leading coefficient.
+fn adjust_degree<F: GpuField + Field>(coeffs: &mut GpuVec<F>, alpha: F, beta: F) {
+    debug_assert!(coeffs.last().map_or(true, Zero::is_zero));
+    #[cfg(feature = "gpu")]
+    if use_gpu::<F>(coeffs.len()) {
+        return adjust_degree_gpu(coeffs, alpha, beta);
+    }
+    adjust_degree_cpu(coeffs, alpha, beta);
+}
+
+fn adjust_degree_cpu<F: Field>(coeffs: &mut GpuVec<F>, alpha: F, beta: F) {
+    let chunk_size = 1 << 16;
+    if beta.is_zero() {
+        // P(x) * alpha
+        ark_std::cfg_chunks_mut!(coeffs, chunk_size).for_each(|coeff_chunk| {
+            for coeff in coeff_chunk {
+                *coeff *= alpha;
+            }
+        });
+    } else {
+        // Adjust the degree
+        // P(x) * (alpha + x * beta)
+        let mut last = F::zero();
+        for coeff in coeffs {
+            let tmp = *coeff;
+            *coeff *= alpha;
+            *coeff += last * beta;
+            last = tmp;
+        }
+    }
+}
+
+/// Computes `alpha * P(x) + beta * x * P(x)` with fused multiply and add
+/// kernels. Multiplying by `x` is a shift of the coefficients by one which
+/// wraps the zero leading coefficient around to the constant term.
+#[cfg(feature = "gpu")]
+fn adjust_degree_gpu<F: GpuField + Field>(coeffs: &mut GpuVec<F>, alpha: F, beta: F) {
+    let n = coeffs.len();
+    let library = &get_planner().library;
+    let command_queue = &get_planner().command_queue;
+    let device = command_queue.device();
+    let command_buffer = command_queue.new_command_buffer();
+    let mut shifted = coeffs.clone();
+    let coeffs_buffer = buffer_mut_no_copy(device, coeffs);
+    let shifted_buffer = buffer_mut_no_copy(device, &mut shifted);
+    let multiplier = MulAssignConstStage::<F>::new(library, n);
+    multiplier.encode(command_buffer, &coeffs_buffer, alpha);
+    if !beta.is_zero() {
+        multiplier.encode(command_buffer, &shifted_buffer, beta);
+        let adder = AddAssignStage::<F>::new(library, n);
+        adder.encode(command_buffer, &coeffs_buffer, &shifted_buffer, -1);
     }
+    command_buffer.commit();
+    command_buffer.wait_until_completed();
 }
 
 pub struct DeepCompositionCoeffs<F> {
@@ -1,8 +1,14 @@
 use crate::expression::Expr;
 use crate::utils;
+use crate::utils::FieldVariant;
+use alloc::collections::BTreeMap;
 use alloc::collections::BTreeSet;
+use ark_ff::FftField;
+use ark_ff::Field;
 use ark_ff::One;
 use ark_ff::Zero;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
 use core::iter::Product;
 use core::iter::Sum;
 use core::ops::Add;
@@ -15,6 +21,7 @@ use core::ops::Sub;
 use num_traits::Pow;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::ptr::addr_of;
 
 // TODO: should really remove copy as this type might change in the future
 #[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
@@ -25,6 +32,9 @@ pub enum AlgebraicItem<T: 'static> {
     Periodic(PeriodicColumn<'static, T>),
     Hint(usize),
     Trace(/* =column */ usize, /* =offset */ isize),
+    /// Polynomial derived from the public inputs.
+    /// See [`crate::air::AirConfig::gen_public_polynomials`]
+    PublicPolynomial(usize),
 }
 
 impl<T> AlgebraicItem<T> {
@@ -34,7 +44,7 @@ impl<T> AlgebraicItem<T> {
         match &self {
             // TODO: handle implications of a zero?
             Constant(_) | Challenge(_) | Hint(_) => Degree(0, 0),
-            Trace(_, _) => Degree(trace_degree, 0),
+            Trace(_, _) | PublicPolynomial(_) => Degree(trace_degree, 0),
             Periodic(col) => col.degree(trace_degree),
             X => Degree(1, 0),
         }
@@ -260,6 +270,108 @@ impl<T> Constraint<T> {
     }
 }
 
+impl<Fp: FftField, Fq: Field> Constraint<FieldVariant<Fp, Fq>> {
+    /// Returns a constraint that `expr` is zero on rows where `selector` is
+    /// non-zero. The selector is typically a binary column or an expression
+    /// that is non-zero for a single VM instruction.
+    pub fn when(
+        selector: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+        expr: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    ) -> Self {
+        Self::new(selector * expr)
+    }
+
+    /// Returns a constraint that `expr` is zero on every row
+    pub fn when_every_row(
+        trace_len: usize,
+        expr: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    ) -> Self {
+        use AlgebraicItem::*;
+        let one = Constant(FieldVariant::Fp(Fp::one()));
+        Self::new(expr / (X.pow(trace_len) - one))
+    }
+
+    /// Returns a constraint that `expr` is zero on every row except the last
+    pub fn when_transition(
+        trace_len: usize,
+        expr: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    ) -> Self {
+        use AlgebraicItem::*;
+        let one = Constant(FieldVariant::Fp(Fp::one()));
+        let last_x = Constant(FieldVariant::Fp(last_trace_x::<Fp>(trace_len)));
+        // `x^n - 1 = (x - t_0)(x - t_1)...(x - t_(n-1))` so multiplying by
+        // `x - t_(n-1)` removes the last row
+        Self::new(expr * ((X - last_x) / (X.pow(trace_len) - one)))
+    }
+
+    /// Returns a constraint that `expr` is zero on every row except the first.
+    /// Used for constraints that reference the previous row.
+    pub fn when_not_first_row(
+        trace_len: usize,
+        expr: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    ) -> Self {
+        use AlgebraicItem::*;
+        let one = Constant(FieldVariant::Fp(Fp::one()));
+        // the first row of the trace domain is `t_0 = 1`
+        Self::new(expr * ((X - one) / (X.pow(trace_len) - one)))
+    }
+
+    /// Returns a constraint that `expr` is zero on the first row
+    pub fn when_first_row(expr: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>) -> Self {
+        use AlgebraicItem::*;
+        // the first row of the trace domain is `t_0 = 1`
+        let first_x = Constant(FieldVariant::Fp(Fp::one()));
+        Self::new(expr / (X - first_x))
+    }
+
+    /// Returns a constraint that `expr` is zero on the last row
+    pub fn when_last_row(
+        trace_len: usize,
+        expr: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    ) -> Self {
+        Self::when_row(trace_len, -1, expr)
+    }
+
+    /// Returns a constraint that `expr` is zero on a single row. Negative
+    /// rows are relative to the end of the trace i.e. `-1` is the last row.
+    pub fn when_row(
+        trace_len: usize,
+        row: isize,
+        expr: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    ) -> Self {
+        Self::when_rows(trace_len, [row], expr)
+    }
+
+    /// Returns a constraint that `expr` is zero on each of the given rows.
+    /// Rows are interpreted the same way as in [`Constraint::when_row`].
+    pub fn when_rows(
+        trace_len: usize,
+        rows: impl IntoIterator<Item = isize>,
+        expr: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    ) -> Self {
+        use AlgebraicItem::*;
+        let trace_len_signed = isize::try_from(trace_len).unwrap();
+        // `-1` and `n-1` are the same row so dedup after resolving
+        let rows = rows
+            .into_iter()
+            .map(|row| row.rem_euclid(trace_len_signed).unsigned_abs())
+            .collect::<BTreeSet<usize>>();
+        let domain = Radix2EvaluationDomain::<Fp>::new(trace_len).unwrap();
+        let divisor = rows
+            .into_iter()
+            .map(|row| X - Constant(FieldVariant::Fp(domain.element(row))))
+            .product::<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>();
+        Self::new(expr / divisor)
+    }
+}
+
+/// Returns the last element of the trace domain `t_(n-1) = t^(-1)`
+fn last_trace_x<F: FftField>(trace_len: usize) -> F {
+    Radix2EvaluationDomain::<F>::new(trace_len)
+        .unwrap()
+        .group_gen_inv()
+}
+
 impl<T> From<Expr<AlgebraicItem<T>>> for Constraint<T> {
     fn from(value: Expr<AlgebraicItem<T>>) -> Self {
         Self::new(value)
@@ -325,6 +437,68 @@ impl<T: Clone + Copy + Zero + Ord + Hash> CompositionConstraint<T> {
         let (numerator_degree, denominator_degree) = self.degree(trace_degree);
         blowup_factor(numerator_degree, denominator_degree, trace_degree)
     }
+
+    /// Counts the operations that only combine constants, challenges, hints
+    /// and composition coefficients. These have the same value on every row
+    /// so evaluators compute them once (see
+    /// [`crate::eval_cpu::hoist_row_independent`]). Shared nodes are counted
+    /// once.
+    pub fn row_independent_ops(&self) -> RowIndependentOps {
+        let mut ops = RowIndependentOps::default();
+        count_row_independent(&self.0, &mut BTreeMap::new(), &mut ops);
+        ops
+    }
+}
+
+/// Operations of an expression that have the same value on every row
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RowIndependentOps {
+    pub num_ops: usize,
+    /// Field multiplications including those of divisions and powers
+    pub num_muls: usize,
+}
+
+impl RowIndependentOps {
+    /// Returns the multiplications saved by computing the operations once
+    /// rather than once for each of `num_chunks` chunks of rows
+    pub const fn saved_muls(&self, num_chunks: usize) -> usize {
+        self.num_muls * num_chunks.saturating_sub(1)
+    }
+}
+
+/// Returns true if `expr` is row independent. Row independent operations are
+/// added to `ops`.
+fn count_row_independent<T>(
+    expr: &Expr<CompositionItem<T>>,
+    seen: &mut BTreeMap<*const Expr<CompositionItem<T>>, bool>,
+    ops: &mut RowIndependentOps,
+) -> bool {
+    use AlgebraicItem::*;
+    if let Some(&is_row_independent) = seen.get(&addr_of!(*expr)) {
+        return is_row_independent;
+    }
+    let is_row_independent = match expr {
+        Expr::Leaf(CompositionItem::CompositionCoeff(_)) => true,
+        Expr::Leaf(CompositionItem::Item(item)) => {
+            matches!(item, Constant(_) | Challenge(_) | Hint(_))
+        }
+        Expr::Neg(a) | Expr::Pow(a, _) => count_row_independent(&a.read().unwrap(), seen, ops),
+        Expr::Add(a, b) | Expr::Mul(a, b) | Expr::Div(a, b) => {
+            let a = count_row_independent(&a.read().unwrap(), seen, ops);
+            let b = count_row_independent(&b.read().unwrap(), seen, ops);
+            a && b
+        }
+    };
+    if is_row_independent && !matches!(expr, Expr::Leaf(_)) {
+        ops.num_ops += 1;
+        ops.num_muls += match expr {
+            Expr::Mul(_, _) | Expr::Div(_, _) => 1,
+            Expr::Pow(_, e) if *e > 1 => (e.ilog2() + e.count_ones() - 1) as usize,
+            _ => 0,
+        };
+    }
+    seen.insert(addr_of!(*expr), is_row_independent);
+    is_row_independent
 }
 
 impl<T> Deref for CompositionConstraint<T> {
@@ -360,6 +534,101 @@ impl Hint for usize {
     }
 }
 
+pub trait PublicPolynomial {
+    fn index(&self) -> usize;
+
+    fn public_polynomial<T>(&self) -> Expr<AlgebraicItem<T>> {
+        AlgebraicItem::PublicPolynomial(self.index()).into()
+    }
+}
+
+impl PublicPolynomial for usize {
+    fn index(&self) -> usize {
+        *self
+    }
+}
+
+/// Returns a constraint that `column` matches a public sequence of
+/// `num_values` values on rows `0, k, 2k, ...` where `k = trace_len /
+/// num_values`. These rows form a subgroup of the trace domain so the
+/// constraint is divided by `x^num_values - 1`. The sequence is given by
+/// `polynomial` which should be generated with
+/// [`crate::air::public_sequence_polynomial`].
+pub fn public_sequence_constraint<T: One>(
+    column: impl ExecutionTraceColumn,
+    polynomial: impl PublicPolynomial,
+    num_values: usize,
+) -> Constraint<T> {
+    assert!(num_values.is_power_of_two());
+    let x = Expr::from(AlgebraicItem::X);
+    let one = Expr::from(AlgebraicItem::Constant(T::one()));
+    Constraint::new((column.curr() - polynomial.public_polynomial()) / (x.pow(num_values) - one))
+}
+
+/// Assertion that a single cell of the trace equals `value`. Values can only
+/// reference constants, challenges and hints so assertions on extension
+/// columns can check terminals the verifier computes from the public inputs
+/// (see [`crate::air::AirConfig::gen_hints`]) e.g. that a running product
+/// starts at one and ends at a terminal `T`.
+#[derive(Clone)]
+pub struct Assertion<T: 'static> {
+    pub column: usize,
+    /// Row of the cell. Negative rows are relative to the end of the trace
+    /// i.e. `-1` is the last row.
+    pub row: isize,
+    pub value: Expr<AlgebraicItem<T>>,
+}
+
+impl<T> Assertion<T> {
+    pub fn new(
+        column: impl ExecutionTraceColumn,
+        row: isize,
+        value: Expr<AlgebraicItem<T>>,
+    ) -> Self {
+        Self {
+            column: column.index(),
+            row,
+            value,
+        }
+    }
+
+    /// Asserts the first cell of `column` equals `value`
+    pub fn first_row(column: impl ExecutionTraceColumn, value: Expr<AlgebraicItem<T>>) -> Self {
+        Self::new(column, 0, value)
+    }
+
+    /// Asserts the last cell of `column` equals `value`
+    pub fn last_row(column: impl ExecutionTraceColumn, value: Expr<AlgebraicItem<T>>) -> Self {
+        Self::new(column, -1, value)
+    }
+
+    /// Returns true if the value is the same on every row i.e. it only
+    /// references constants, challenges and hints
+    pub fn is_row_independent(&self) -> bool {
+        let mut is_row_independent = true;
+        self.value.traverse(&mut |node| {
+            if let Expr::Leaf(
+                AlgebraicItem::X
+                | AlgebraicItem::Trace(..)
+                | AlgebraicItem::Periodic(_)
+                | AlgebraicItem::PublicPolynomial(_),
+            ) = node
+            {
+                is_row_independent = false;
+            }
+        });
+        is_row_independent
+    }
+}
+
+impl<Fp: FftField, Fq: Field> Assertion<FieldVariant<Fp, Fq>> {
+    /// Returns the constraint that enforces the assertion
+    pub fn constraint(&self, trace_len: usize) -> Constraint<FieldVariant<Fp, Fq>> {
+        let cell = self.column.curr() - self.value.clone();
+        Constraint::when_row(trace_len, self.row, cell)
+    }
+}
+
 pub trait VerifierChallenge {
     /// Get the challenge index
     fn index(&self) -> usize;
@@ -393,6 +662,12 @@ pub trait ExecutionTraceColumn {
         self.offset(1)
     }
 
+    /// Create a constraint element for the previous cycle. Evaluated at
+    /// `z/g` out of domain.
+    fn prev<T>(&self) -> Expr<AlgebraicItem<T>> {
+        self.offset(-1)
+    }
+
     fn offset<T>(&self, offset: isize) -> Expr<AlgebraicItem<T>> {
         AlgebraicItem::Trace(self.index(), offset).into()
     }
@@ -404,6 +679,51 @@ impl ExecutionTraceColumn for usize {
     }
 }
 
+/// A named column defined by an expression over the current row of other
+/// columns e.g. `is_dummy = 1 - dummy`. Virtual columns aren't part of the
+/// trace. Instead their expression is inlined wherever they are used.
+#[derive(Clone)]
+pub struct VirtualColumn<T: 'static> {
+    name: &'static str,
+    expr: Expr<AlgebraicItem<T>>,
+}
+
+impl<T: Clone + Ord> VirtualColumn<T> {
+    pub const fn new(name: &'static str, expr: Expr<AlgebraicItem<T>>) -> Self {
+        Self { name, expr }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    // Create a constraint element for the current cycle
+    pub fn curr(&self) -> Expr<AlgebraicItem<T>> {
+        self.offset(0)
+    }
+
+    // Create a constraint element for the next cycle
+    pub fn next(&self) -> Expr<AlgebraicItem<T>> {
+        self.offset(1)
+    }
+
+    /// Create a constraint element for the previous cycle
+    pub fn prev(&self) -> Expr<AlgebraicItem<T>> {
+        self.offset(-1)
+    }
+
+    /// Returns the column's expression with all trace accesses shifted by
+    /// `offset` rows
+    pub fn offset(&self, offset: isize) -> Expr<AlgebraicItem<T>> {
+        self.expr.map_leaves(&mut |leaf| match leaf {
+            &AlgebraicItem::Trace(column, row_offset) => {
+                AlgebraicItem::Trace(column, row_offset + offset)
+            }
+            leaf => leaf.clone(),
+        })
+    }
+}
+
 /// Degree of the form `(numerator_degree, denominator_degree)`
 struct Degree(pub usize, pub usize);
 
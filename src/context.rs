@@ -0,0 +1,416 @@
+use crate::prover::ProvingError;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::Matrix;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalSerialize;
+use core::any::Any;
+use core::any::TypeId;
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+use core::task::Context;
+use core::task::Poll;
+#[cfg(feature = "gpu")]
+use ministark_gpu::plan::get_device_planner;
+#[cfg(feature = "gpu")]
+use ministark_gpu::plan::Planner;
+use std::sync::Mutex;
+
+std::thread_local! {
+    static CURRENT_CONTEXT: RefCell<Option<ProverContext>> = const { RefCell::new(None) };
+}
+
+/// Resources used while generating a proof. Embedding applications can create
+/// a context once and pass it to [`crate::stark::Stark::prove_with_context`]
+/// to control resource usage. Proofs generated with their own context don't
+/// compete for rayon's global thread pool.
+///
+/// The resources don't change the proof. Parallel reductions only add and
+/// multiply field elements which is exact so the order is irrelevant, GPU
+/// shards are whole columns and grinding returns the smallest valid nonce.
+/// Proofs are bit-identical for any number of threads or devices so there is
+/// no separate deterministic mode.
+///
+/// Clones share the thread pool, memory budget, twiddle cache and scratch
+/// arena so proofs generated with a long lived context reuse the tables and
+/// buffers of earlier proofs.
+#[derive(Clone, Default)]
+pub struct ProverContext {
+    #[cfg(feature = "parallel")]
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+    memory_budget: Option<Arc<MemoryBudget>>,
+    devices: Vec<Device>,
+    twiddle_cache: Arc<TwiddleCache>,
+    scratch_arena: Arc<ScratchArena>,
+    scan_trace: bool,
+    cancellation_token: CancellationToken,
+}
+
+impl ProverContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a context for small proofs (see [`crate::ProofOptions::small`]).
+    /// Work runs on a single thread since splitting tiny traces between
+    /// threads costs more than it saves. Create the context once and reuse it
+    /// so the thread is only spawned once.
+    ///
+    /// # Panics
+    /// Panics if the thread can't be spawned
+    pub fn low_latency() -> Self {
+        #[cfg(feature = "parallel")]
+        return Self::new()
+            .with_num_threads(1)
+            .expect("failed to spawn prover thread");
+        #[cfg(not(feature = "parallel"))]
+        return Self::new();
+    }
+
+    /// Runs parallel work on the provided thread pool
+    #[cfg(feature = "parallel")]
+    pub fn with_thread_pool(mut self, thread_pool: Arc<rayon::ThreadPool>) -> Self {
+        self.thread_pool = Some(thread_pool);
+        self
+    }
+
+    /// Runs parallel work on a new thread pool with `num_threads` threads
+    #[cfg(feature = "parallel")]
+    pub fn with_num_threads(self, num_threads: usize) -> Result<Self, rayon::ThreadPoolBuildError> {
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()?;
+        Ok(self.with_thread_pool(Arc::new(thread_pool)))
+    }
+
+    /// Limits the bytes held by the prover's major buffers to `limit`. Proofs
+    /// generated with clones of this context share the budget.
+    pub fn with_memory_budget(mut self, limit: usize) -> Self {
+        self.memory_budget = Some(Arc::new(MemoryBudget::new(limit)));
+        self
+    }
+
+    pub fn memory_budget(&self) -> Option<&MemoryBudget> {
+        self.memory_budget.as_deref()
+    }
+
+    /// Shards the columns of the trace and composition trace LDEs and the
+    /// evaluations of each FRI layer across the GPUs at these indices (see
+    /// `ministark_gpu::plan::get_device_planner`). Shards are joined on the
+    /// host where the Merkle trees are built.
+    ///
+    /// # Panics
+    /// Panics if the `gpu` feature is enabled and there is no GPU at one of
+    /// the indices
+    pub fn with_devices(mut self, devices: impl IntoIterator<Item = usize>) -> Self {
+        self.devices = devices.into_iter().map(Device::new).collect();
+        self
+    }
+
+    pub fn devices(&self) -> &[Device] {
+        &self.devices
+    }
+
+    /// Domain elements computed by proofs generated with this context
+    pub fn twiddle_cache(&self) -> &TwiddleCache {
+        &self.twiddle_cache
+    }
+
+    /// Buffers recycled by proofs generated with this context
+    pub fn scratch_arena(&self) -> &ScratchArena {
+        &self.scratch_arena
+    }
+
+    /// Scans the execution trace with [`crate::debug::scan_trace`] before
+    /// proving. Non-canonical values fail with
+    /// [`ProvingError::NonCanonicalTraceValue`] and suspicious columns are
+    /// logged. Off by default since it reads the whole trace.
+    pub const fn with_trace_scan(mut self, scan_trace: bool) -> Self {
+        self.scan_trace = scan_trace;
+        self
+    }
+
+    pub const fn scans_trace(&self) -> bool {
+        self.scan_trace
+    }
+
+    /// Proofs generated with this context fail with
+    /// [`ProvingError::Cancelled`] once `cancellation_token` is cancelled
+    pub fn with_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    pub const fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation_token
+    }
+
+    /// Returns the number of threads available for parallel work
+    pub fn num_threads(&self) -> usize {
+        #[cfg(feature = "parallel")]
+        return self.thread_pool.as_ref().map_or_else(
+            rayon::current_num_threads,
+            rayon::ThreadPool::current_num_threads,
+        );
+        #[cfg(not(feature = "parallel"))]
+        return 1;
+    }
+
+    /// Executes `op` using the resources of this context
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        #[cfg(feature = "parallel")]
+        if let Some(thread_pool) = &self.thread_pool {
+            return thread_pool.install(op);
+        }
+        op()
+    }
+
+    /// Returns the context [`Self::scope`] is running on this thread or a new
+    /// context if there is none. Used by [`crate::prover::default_prove`].
+    pub fn current() -> Self {
+        CURRENT_CONTEXT.with(|current| current.borrow().clone().unwrap_or_default())
+    }
+
+    /// Executes `op` with this context as the [`Self::current`] context
+    pub fn scope<R>(&self, op: impl FnOnce() -> R) -> R {
+        let previous = CURRENT_CONTEXT.with(|current| current.replace(Some(self.clone())));
+        let _restore = RestoreContext(previous);
+        op()
+    }
+
+    /// Returns a future that polls `future` with this context as the
+    /// [`Self::current`] context. Lets [`crate::stark::Stark::prove_with_context`]
+    /// call [`crate::stark::Stark::prove`] so overrides of it are respected.
+    pub fn bind<F: Future>(&self, future: F) -> WithContext<F> {
+        WithContext {
+            context: self.clone(),
+            future: Box::pin(future),
+        }
+    }
+}
+
+/// Restores the previous [`ProverContext::current`] context when dropped
+struct RestoreContext(Option<ProverContext>);
+
+impl Drop for RestoreContext {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT_CONTEXT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Future returned by [`ProverContext::bind`]
+pub struct WithContext<F> {
+    context: ProverContext,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for WithContext<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = &mut *self;
+        this.context.scope(|| this.future.as_mut().poll(cx))
+    }
+}
+
+/// Handle to a GPU the prover's work can be sharded across. Without the `gpu`
+/// feature the shards assigned to a device run on the host.
+#[derive(Clone, Copy)]
+pub struct Device {
+    index: usize,
+    #[cfg(feature = "gpu")]
+    planner: &'static Planner,
+}
+
+impl Device {
+    /// # Panics
+    /// Panics if the `gpu` feature is enabled and there is no GPU at `index`
+    #[cfg_attr(not(feature = "gpu"), allow(clippy::missing_const_for_fn))]
+    pub fn new(index: usize) -> Self {
+        Self {
+            index,
+            #[cfg(feature = "gpu")]
+            planner: get_device_planner(index)
+                .unwrap_or_else(|| panic!("no GPU with index {index}")),
+        }
+    }
+
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    #[cfg(feature = "gpu")]
+    pub const fn planner(&self) -> &'static Planner {
+        self.planner
+    }
+}
+
+/// Elements of the domains used by the prover. Tables only depend on the
+/// field, size and offset of a domain so they're computed once and shared by
+/// every proof generated with a context.
+#[derive(Default)]
+pub struct TwiddleCache(Mutex<BTreeMap<TwiddleKey, Arc<dyn Any + Send + Sync>>>);
+
+/// Field, size and serialized offset of a domain
+type TwiddleKey = (TypeId, usize, Vec<u8>);
+
+impl TwiddleCache {
+    /// Returns the elements of `domain` in their natural order
+    pub fn domain_elements<F: FftField>(&self, domain: Radix2EvaluationDomain<F>) -> Arc<Vec<F>> {
+        let mut offset = Vec::new();
+        domain
+            .coset_offset()
+            .serialize_compressed(&mut offset)
+            .unwrap();
+        let key = (TypeId::of::<F>(), domain.size(), offset);
+        let elements = Arc::clone(
+            self.0
+                .lock()
+                .unwrap()
+                .entry(key)
+                .or_insert_with(|| Arc::new(domain.elements().collect::<Vec<F>>())),
+        );
+        elements.downcast().unwrap()
+    }
+}
+
+/// Buffers recycled between proofs. The LDEs are the prover's largest
+/// allocations so reusing them saves the allocator from mapping and zeroing
+/// fresh pages for every proof.
+#[derive(Default)]
+pub struct ScratchArena(Mutex<Vec<Box<dyn Any + Send>>>);
+
+impl ScratchArena {
+    /// Returns a zeroed buffer of length `len`. Reuses a recycled buffer if
+    /// there is one large enough.
+    pub fn take<F: Field>(&self, len: usize) -> GpuVec<F> {
+        let recycled = {
+            let mut buffers = self.0.lock().unwrap();
+            buffers
+                .iter()
+                .position(|buffer| {
+                    buffer
+                        .downcast_ref::<GpuVec<F>>()
+                        .is_some_and(|buffer| buffer.capacity() >= len)
+                })
+                .map(|i| buffers.swap_remove(i))
+        };
+        let mut buffer = match recycled {
+            Some(buffer) => *buffer.downcast::<GpuVec<F>>().unwrap(),
+            None => Vec::with_capacity_in(len, GpuAllocator),
+        };
+        buffer.clear();
+        buffer.resize(len, F::zero());
+        buffer
+    }
+
+    /// Keeps `buffer` for a later call to [`Self::take`]
+    pub fn recycle<F: Field>(&self, buffer: GpuVec<F>) {
+        self.0.lock().unwrap().push(Box::new(buffer));
+    }
+
+    /// Keeps the columns of `matrix` for later calls to [`Self::take`]
+    pub fn recycle_matrix<F: Field>(&self, matrix: Matrix<F>) {
+        for column in matrix.0 {
+            self.recycle(column);
+        }
+    }
+}
+
+/// Accounts for the bytes held by the prover's major buffers (trace, LDEs,
+/// composition trace and FRI layers). Buffers are reserved before they're
+/// allocated so a proof that doesn't fit fails early with
+/// [`ProvingError::OutOfMemoryBudget`] rather than being killed by the OS
+/// midway through.
+#[derive(Debug)]
+pub struct MemoryBudget {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl MemoryBudget {
+    pub const fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    pub const fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns the number of reserved bytes
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Reserves `bytes` bytes for `buffer`. The bytes are released once the
+    /// reservation is dropped.
+    pub fn reserve(
+        &self,
+        buffer: &'static str,
+        bytes: usize,
+    ) -> Result<MemoryReservation<'_>, ProvingError> {
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|&total| total <= self.limit)
+            })
+            .map_err(|used| ProvingError::OutOfMemoryBudget {
+                buffer,
+                required: used.saturating_add(bytes),
+                limit: self.limit,
+            })?;
+        Ok(MemoryReservation {
+            budget: self,
+            bytes,
+        })
+    }
+}
+
+/// Bytes reserved with [`MemoryBudget::reserve`]
+#[derive(Debug)]
+pub struct MemoryReservation<'a> {
+    budget: &'a MemoryBudget,
+    bytes: usize,
+}
+
+impl Drop for MemoryReservation<'_> {
+    fn drop(&mut self) {
+        self.budget.used.fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
+
+/// Token for cancelling a proof that is being generated. The prover checks the
+/// token between stages of the protocol and between chunks of work while
+/// extending traces, hashing rows and evaluating constraints. It returns
+/// [`crate::prover::ProvingError::Cancelled`] once the token is cancelled.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the proof. Clones of this token are cancelled as well.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
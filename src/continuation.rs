@@ -0,0 +1,83 @@
+//! Proving a long execution as a chain of proofs over consecutive chunks
+
+use crate::air::AirConfig;
+use crate::channel::VerifierChannelArtifacts;
+use crate::hash::HashFn;
+use crate::public_inputs::PublicInputs;
+use crate::stark::Stark;
+use crate::verifier::verify_batch;
+use crate::verifier::BatchVerificationError;
+use crate::verifier::VerificationError;
+use crate::Proof;
+use alloc::vec::Vec;
+use snafu::Snafu;
+
+/// Public inputs of one chunk of an execution. The chunk's AIR must assert the
+/// initial state on the first row and the final state on the last row (e.g.
+/// with hints derived from the public inputs). Since public inputs are bound
+/// to the transcript the final state is committed to by the proof and chunks
+/// are linked by requiring each chunk to start in the state the previous one
+/// ended in.
+pub trait Continuation {
+    type State: PublicInputs;
+
+    /// State the chunk starts in
+    fn initial_state(&self) -> Self::State;
+
+    /// State the chunk ends in. This is the initial state of the next chunk.
+    fn final_state(&self) -> Self::State;
+}
+
+/// Returns a commitment to a state of a chunked execution
+pub fn commit_state<H: HashFn>(state: &impl PublicInputs) -> H::Digest {
+    state.hash::<H>()
+}
+
+/// Verifies a chain of proofs as one logical execution. Checks the final state
+/// of each chunk is the initial state of the next chunk and that every proof
+/// is valid. Returns the artifacts of each proof.
+pub fn verify_chain<S: Stark>(
+    chain: Vec<(&S, Proof<S>)>,
+    required_security_bits: u32,
+) -> Result<Vec<VerifierChannelArtifacts<S::Fq>>, ChainVerificationError>
+where
+    <S::AirConfig as AirConfig>::PublicInputs: Continuation,
+{
+    if chain.is_empty() {
+        return Err(ChainVerificationError::EmptyChain);
+    }
+
+    let public_inputs = chain
+        .iter()
+        .map(|(this, _)| this.get_public_inputs())
+        .collect::<Vec<_>>();
+    for (index, [chunk, next]) in public_inputs.array_windows().enumerate() {
+        let final_state = chunk.final_state().to_canonical_bytes();
+        let initial_state = next.initial_state().to_canonical_bytes();
+        if final_state != initial_state {
+            return Err(ChainVerificationError::BrokenLink { index });
+        }
+    }
+
+    verify_batch(chain, required_security_bits).map_err(|err| match err {
+        BatchVerificationError::InvalidProof { index, source } => {
+            ChainVerificationError::InvalidChunk { index, source }
+        }
+    })
+}
+
+/// Errors that can occur when verifying a chain of proofs
+#[derive(Debug, Snafu)]
+pub enum ChainVerificationError {
+    #[snafu(display("a chain must contain at least one proof"))]
+    EmptyChain,
+    #[snafu(display(
+        "final state of chunk {index} doesn't match the initial state of the next chunk"
+    ))]
+    BrokenLink { index: usize },
+    #[snafu(display("proof of chunk {index} is invalid: {source}"))]
+    InvalidChunk {
+        index: usize,
+        source: VerificationError,
+    },
+}
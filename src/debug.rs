@@ -1,17 +1,44 @@
 //! Tools for debugging issues that may arrive with AIR or STARK
 //! TODO:
 
+use crate::air::AirConfig;
 use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::expression::Expr;
+use crate::hash::Digest;
 use crate::hints::Hints;
+use crate::proof::Proof;
+use crate::reference::reference_verify_with_report;
 use crate::stark::Stark;
+use crate::utils::horner_evaluate;
+use crate::utils::FieldVariant;
+use crate::verifier::VerificationError;
+use crate::Air;
 use crate::Matrix;
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_ff::PrimeField;
+use ark_ff::UniformRand;
+use ark_ff::Zero;
+use ark_poly::EvaluationDomain;
+use core::fmt::Debug;
+use core::fmt::Display;
+use core::iter::zip;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
 
 /// Checks AIR constraints are valid
 pub const fn default_validate_constraints<S: Stark>(
     _this: &S,
     _challenges: &Challenges<S::Fq>,
     _hints: &Hints<S::Fq>,
-    _base_trace: &Matrix<S::Fp>,
+    _trace: &S::Trace,
     _extension_trace: Option<&Matrix<S::Fq>>,
 ) {
     // ```text
@@ -126,3 +153,604 @@ pub const fn default_validate_constraints<S: Stark>(
     // }
     // ```
 }
+
+/// Number of rows a constraint is enforced on for a particular trace
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConstraintCoverage {
+    /// Index of the constraint in [`Air::constraints`]
+    pub constraint: usize,
+    /// Rows where changing any of the trace values the constraint references
+    /// would violate the constraint
+    pub num_active_rows: usize,
+    /// Rows where the constraint is already violated
+    pub num_failing_rows: usize,
+}
+
+impl ConstraintCoverage {
+    /// Returns true if no trace values could violate the constraint. These
+    /// constraints are dead and silently break soundness.
+    pub const fn is_vacuous(&self) -> bool {
+        self.num_active_rows == 0 && self.num_failing_rows == 0
+    }
+}
+
+/// Reports how many rows of a trace each constraint is enforced on.
+///
+/// A constraint is active on a row if perturbing any trace value it
+/// references makes it fail on that row. Rows where a selector is zero or
+/// the constraint's divisor doesn't vanish aren't active.
+pub fn constraint_coverage<C: AirConfig>(
+    air: &Air<C>,
+    challenges: &Challenges<C::Fq>,
+    hints: &Hints<C::Fq>,
+    base_trace: &Matrix<C::Fp>,
+    extension_trace: Option<&Matrix<C::Fq>>,
+) -> Vec<ConstraintCoverage> {
+    let trace_len = air.trace_len();
+    let trace_domain = air.trace_domain();
+    let num_base_columns = base_trace.num_cols();
+    let mut rng = ark_std::test_rng();
+    let trace_value = |col: usize, row: usize| -> C::Fq {
+        if col < num_base_columns {
+            base_trace.0[col][row].into()
+        } else {
+            extension_trace.unwrap().0[col - num_base_columns][row]
+        }
+    };
+
+    air.constraints()
+        .iter()
+        .enumerate()
+        .map(|(constraint_index, constraint)| {
+            let arguments = constraint.trace_arguments();
+            let mut coverage = ConstraintCoverage {
+                constraint: constraint_index,
+                num_active_rows: 0,
+                num_failing_rows: 0,
+            };
+            for row in 0..trace_len {
+                let x = trace_domain.element(row);
+                // checks the constraint with `delta` added to one trace value
+                let is_satisfied = |perturbation: Option<((usize, isize), C::Fq)>| {
+                    constraint
+                        .check(&mut |leaf| {
+                            eval_leaf(air, challenges, hints, x, leaf, |col, offset| {
+                                let shifted_row =
+                                    (row as isize + offset).rem_euclid(trace_len as isize) as usize;
+                                let mut value = trace_value(col, shifted_row);
+                                if let Some((argument, delta)) = perturbation
+                                    && argument == (col, offset)
+                                {
+                                    value += delta;
+                                }
+                                value
+                            })
+                        })
+                        .is_some()
+                };
+
+                if !is_satisfied(None) {
+                    coverage.num_failing_rows += 1;
+                } else if arguments
+                    .iter()
+                    .any(|&argument| !is_satisfied(Some((argument, C::Fq::rand(&mut rng)))))
+                {
+                    coverage.num_active_rows += 1;
+                }
+            }
+            coverage
+        })
+        .collect()
+}
+
+/// Evaluates a constraint leaf at a point `x` of the trace domain
+fn eval_leaf<C: AirConfig>(
+    air: &Air<C>,
+    challenges: &[C::Fq],
+    hints: &[C::Fq],
+    x: C::Fp,
+    leaf: &AlgebraicItem<FieldVariant<C::Fp, C::Fq>>,
+    trace_value: impl FnOnce(usize, isize) -> C::Fq,
+) -> FieldVariant<C::Fp, C::Fq> {
+    use AlgebraicItem::*;
+    match *leaf {
+        X => FieldVariant::Fp(x),
+        Constant(v) => v,
+        Challenge(i) => FieldVariant::Fq(challenges[i]),
+        Hint(i) => FieldVariant::Fq(hints[i]),
+        Periodic(col) => {
+            let point = x.pow([(air.trace_len() / col.interval_size()) as u64]);
+            let coeffs = col
+                .coeffs()
+                .iter()
+                .map(FieldVariant::as_fq)
+                .collect::<Vec<_>>();
+            FieldVariant::Fq(horner_evaluate(&coeffs, &C::Fq::from(point)))
+        }
+        PublicPolynomial(i) => FieldVariant::Fp(horner_evaluate(&air.public_polynomials()[i], &x)),
+        Trace(col, offset) => FieldVariant::Fq(trace_value(col, offset)),
+    }
+}
+
+/// Common mistakes in an AIR definition found by [`lint_air`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LintWarning {
+    /// An execution trace column that no constraint references
+    UnconstrainedColumn { column: usize },
+    /// An extension column with no constraint enforced on the first row
+    MissingBoundaryConstraint { column: usize },
+    /// An extension column with no constraint enforced on the last row
+    MissingTerminalConstraint { column: usize },
+    /// A constraint that doesn't reference the execution trace
+    ConstantConstraint { constraint: usize },
+    /// A challenge that is drawn but no constraint uses
+    UnusedChallenge { challenge: usize },
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnconstrainedColumn { column } => {
+                write!(f, "column {column} is not referenced by any constraint")
+            }
+            Self::MissingBoundaryConstraint { column } => {
+                write!(f, "extension column {column} has no boundary constraint")
+            }
+            Self::MissingTerminalConstraint { column } => {
+                write!(f, "extension column {column} has no terminal constraint")
+            }
+            Self::ConstantConstraint { constraint } => {
+                write!(f, "constraint {constraint} does not reference the trace")
+            }
+            Self::UnusedChallenge { challenge } => {
+                write!(f, "challenge {challenge} is not used by any constraint")
+            }
+        }
+    }
+}
+
+/// Statically checks the constraints of an AIR for common soundness mistakes.
+///
+/// The rows a constraint is enforced on are found by evaluating it with
+/// random trace values, challenges and hints. A constraint is enforced on a
+/// row if it fails there.
+pub fn lint_air<C: AirConfig>(air: &Air<C>) -> Vec<LintWarning> {
+    use AlgebraicItem::*;
+    let mut rng = ark_std::test_rng();
+    let trace_len = air.trace_len();
+    let trace_domain = air.trace_domain();
+    let num_columns = C::NUM_BASE_COLUMNS + C::NUM_EXTENSION_COLUMNS;
+    let constraints = air.constraints();
+
+    let mut num_hints = 0;
+    let mut used_challenges = BTreeSet::new();
+    for constraint in constraints {
+        constraint.traverse(&mut |node| match *node {
+            Expr::Leaf(Challenge(i)) => {
+                used_challenges.insert(i);
+            }
+            Expr::Leaf(Hint(i)) => num_hints = num_hints.max(i + 1),
+            _ => {}
+        });
+    }
+    let challenges = (0..air.num_challenges())
+        .map(|_| C::Fq::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let hints = (0..num_hints)
+        .map(|_| C::Fq::rand(&mut rng))
+        .collect::<Vec<_>>();
+    let trace_values = air
+        .trace_arguments()
+        .into_iter()
+        .map(|argument| (argument, C::Fq::rand(&mut rng)))
+        .collect::<BTreeMap<_, _>>();
+    let is_enforced = |constraint: &Constraint<FieldVariant<C::Fp, C::Fq>>, row: usize| {
+        let x = trace_domain.element(row % trace_len);
+        constraint
+            .check(&mut |leaf| {
+                eval_leaf(air, &challenges, &hints, x, leaf, |col, offset| {
+                    trace_values[&(col, offset)]
+                })
+            })
+            .is_none()
+    };
+
+    let mut warnings = Vec::new();
+    let mut referenced_columns = BTreeSet::new();
+    let mut has_boundary = BTreeSet::new();
+    let mut has_terminal = BTreeSet::new();
+    for (i, constraint) in constraints.iter().enumerate() {
+        let columns = constraint
+            .trace_arguments()
+            .into_iter()
+            .map(|(column, _)| column)
+            .collect::<BTreeSet<usize>>();
+        if columns.is_empty() {
+            warnings.push(LintWarning::ConstantConstraint { constraint: i });
+        }
+
+        // transition constraints are enforced on the first and second row but
+        // not the last. Constraints enforced on every row count as both.
+        let first = is_enforced(constraint, 0);
+        let second = is_enforced(constraint, 1);
+        let penultimate = is_enforced(constraint, trace_len - 2);
+        let last = is_enforced(constraint, trace_len - 1);
+        if first && (!second || last) {
+            has_boundary.extend(&columns);
+        }
+        if last && (!penultimate || first) {
+            has_terminal.extend(&columns);
+        }
+        referenced_columns.extend(columns);
+    }
+
+    for column in 0..num_columns {
+        if !referenced_columns.contains(&column) {
+            warnings.push(LintWarning::UnconstrainedColumn { column });
+        } else if column >= C::NUM_BASE_COLUMNS {
+            if !has_boundary.contains(&column) {
+                warnings.push(LintWarning::MissingBoundaryConstraint { column });
+            }
+            if !has_terminal.contains(&column) {
+                warnings.push(LintWarning::MissingTerminalConstraint { column });
+            }
+        }
+    }
+
+    for challenge in 0..air.num_challenges() {
+        if !used_challenges.contains(&challenge) {
+            warnings.push(LintWarning::UnusedChallenge { challenge });
+        }
+    }
+
+    warnings
+}
+
+/// Suspicious values and columns of a trace found by [`scan_trace`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceWarning {
+    /// A value whose internal representation isn't reduced. Usually written to
+    /// memory without going through the field's constructors e.g. by unsafe
+    /// code or a GPU kernel.
+    NonCanonicalValue { column: usize, row: usize },
+    /// A column where every value is zero
+    ZeroColumn { column: usize },
+    /// A column where every value is the same non-zero value
+    ConstantColumn { column: usize },
+}
+
+impl Display for TraceWarning {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NonCanonicalValue { column, row } => {
+                write!(f, "column {column} has a non-canonical value on row {row}")
+            }
+            Self::ZeroColumn { column } => write!(f, "column {column} is all zeros"),
+            Self::ConstantColumn { column } => write!(f, "column {column} is constant"),
+        }
+    }
+}
+
+/// Scans the values of a trace for witness generation bugs that would
+/// otherwise surface as constraint failures. Only the first non-canonical
+/// value of a column is reported. Zero and constant columns may be intended
+/// e.g. unused columns of a short execution.
+pub fn scan_trace<F: Field>(trace: &Matrix<F>) -> Vec<TraceWarning> {
+    scan_columns(trace.iter())
+}
+
+/// Same as [`scan_trace`] for columns that aren't stored in a [`Matrix`] e.g.
+/// the expanded columns of a compressed trace (see [`crate::Trace::base_column`])
+pub fn scan_columns<F: Field>(
+    columns: impl IntoIterator<Item = impl AsRef<[F]>>,
+) -> Vec<TraceWarning> {
+    let mut warnings = Vec::new();
+    for (column, values) in columns.into_iter().enumerate() {
+        let values = values.as_ref();
+        if let Some(row) = values.iter().position(|value| !is_canonical(value)) {
+            warnings.push(TraceWarning::NonCanonicalValue { column, row });
+        }
+        let Some(first) = values.first() else {
+            continue;
+        };
+        if values.iter().all(|value| value == first) {
+            warnings.push(if first.is_zero() {
+                TraceWarning::ZeroColumn { column }
+            } else {
+                TraceWarning::ConstantColumn { column }
+            });
+        }
+    }
+    warnings
+}
+
+/// Returns true if the representation of each coefficient is reduced i.e.
+/// unchanged by a round trip through its integer representation
+fn is_canonical<F: Field>(value: &F) -> bool {
+    value
+        .to_base_prime_field_elements()
+        .all(|coeff| F::BasePrimeField::from_bigint(coeff.into_bigint()) == Some(coeff))
+}
+
+/// Writes the columns of a trace as CSV with a row per trace row. Handy for
+/// plotting long executions to spot mis-filled columns.
+pub fn write_trace_csv<F: Field>(trace: &Matrix<F>, mut writer: impl Write) -> io::Result<()> {
+    let header = (0..trace.num_cols())
+        .map(|col| format!("col_{col}"))
+        .collect::<Vec<String>>();
+    writeln!(writer, "row,{}", header.join(","))?;
+    for row in 0..trace.num_rows() {
+        let values = trace
+            .0
+            .iter()
+            .map(|column| column[row].to_string())
+            .collect::<Vec<String>>();
+        writeln!(writer, "{row},{}", values.join(","))?;
+    }
+    Ok(())
+}
+
+/// Returns the rows of a trace each constraint is violated on
+pub fn constraint_violations<C: AirConfig>(
+    air: &Air<C>,
+    challenges: &Challenges<C::Fq>,
+    hints: &Hints<C::Fq>,
+    base_trace: &Matrix<C::Fp>,
+    extension_trace: Option<&Matrix<C::Fq>>,
+) -> Vec<Vec<usize>> {
+    let trace_len = air.trace_len();
+    let trace_domain = air.trace_domain();
+    let num_base_columns = base_trace.num_cols();
+    air.constraints()
+        .iter()
+        .map(|constraint| {
+            (0..trace_len)
+                .filter(|&row| {
+                    let x = trace_domain.element(row);
+                    constraint
+                        .check(&mut |leaf| {
+                            eval_leaf(air, challenges, hints, x, leaf, |col, offset| {
+                                let row =
+                                    (row as isize + offset).rem_euclid(trace_len as isize) as usize;
+                                if col < num_base_columns {
+                                    base_trace.0[col][row].into()
+                                } else {
+                                    extension_trace.unwrap().0[col - num_base_columns][row]
+                                }
+                            })
+                        })
+                        .is_none()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the rows of a trace where each typed column (see
+/// [`AirConfig::column_types`]) holds a value outside of its type
+pub fn column_type_violations<C: AirConfig>(base_trace: &Matrix<C::Fp>) -> Vec<Vec<usize>> {
+    zip(C::column_types(), &base_trace.0)
+        .map(|(ty, column)| {
+            (0..column.len())
+                .filter(|&row| !ty.contains(&column[row]))
+                .collect()
+        })
+        .collect()
+}
+
+/// Renders [`constraint_violations`] as an SVG heatmap with a band per
+/// constraint. Rows are grouped into at most `width` cells and a cell is red
+/// if the constraint is violated on any of its rows.
+pub fn write_violation_heatmap_svg(
+    violations: &[Vec<usize>],
+    trace_len: usize,
+    width: usize,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    const CELL_HEIGHT: usize = 8;
+    let num_cells = width.clamp(1, trace_len.max(1));
+    let rows_per_cell = trace_len.div_ceil(num_cells).max(1);
+    let height = violations.len() * CELL_HEIGHT;
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{num_cells}" height="{height}" shape-rendering="crispEdges">"#
+    )?;
+    writeln!(
+        writer,
+        r##"<rect width="{num_cells}" height="{height}" fill="#eeeeee"/>"##
+    )?;
+    for (constraint, rows) in violations.iter().enumerate() {
+        let y = constraint * CELL_HEIGHT;
+        let cells = rows
+            .iter()
+            .map(|row| row / rows_per_cell)
+            .collect::<BTreeSet<usize>>();
+        for cell in cells {
+            writeln!(
+                writer,
+                r##"<rect x="{cell}" y="{y}" width="1" height="{CELL_HEIGHT}" fill="#d62728"><title>constraint {constraint}</title></rect>"##
+            )?;
+        }
+    }
+    writeln!(writer, "</svg>")
+}
+
+/// Environment variable that makes [`assert_golden`] overwrite golden files
+pub const UPDATE_GOLDEN_ENV_VAR: &str = "MINISTARK_UPDATE_GOLDEN";
+
+/// Asserts `actual` matches the contents of the golden file at `path`. The
+/// file is written if it doesn't exist or if [`UPDATE_GOLDEN_ENV_VAR`] is set
+/// so intended changes (e.g. to the Fiat-Shamir transcript recorded in
+/// [`crate::proof::ProofMetadata`]) can be accepted by re-running the test.
+///
+/// # Panics
+/// Panics if `actual` doesn't match the golden file
+pub fn assert_golden(path: impl AsRef<Path>, actual: &str) {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_GOLDEN_ENV_VAR).is_some() || !path.exists() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(path).unwrap();
+    if expected != actual {
+        let first_difference = zip(expected.lines(), actual.lines())
+            .position(|(expected, actual)| expected != actual)
+            .unwrap_or_else(|| expected.lines().count().min(actual.lines().count()));
+        panic!(
+            "output doesn't match golden file {} (first difference on line {}). Run with \
+             {UPDATE_GOLDEN_ENV_VAR}=1 to update it.\nexpected:\n{expected}\nactual:\n{actual}",
+            path.display(),
+            first_difference + 1,
+        );
+    }
+}
+
+/// Detailed account of a verification produced by [`explain_verification`]
+#[derive(Debug)]
+pub struct VerificationReport<F, D> {
+    /// Values drawn from the public coin and its state after each step of
+    /// the transcript
+    pub transcript: Vec<TranscriptStep<F>>,
+    pub query_positions: Vec<usize>,
+    /// Merkle proofs checked before verification stopped
+    pub merkle_roots: Vec<MerkleRootCheck<D>>,
+    /// Expected and actual evaluation of the composition polynomial at the
+    /// OOD point
+    pub ood_evaluation: Option<(F, F)>,
+    /// First query where FRI folding doesn't match the next layer or the
+    /// remainder
+    pub failing_fold: Option<FriFoldFailure<F>>,
+    pub error: Option<VerificationError>,
+}
+
+impl<F: Clone, D> VerificationReport<F, D> {
+    pub const fn new() -> Self {
+        Self {
+            transcript: Vec::new(),
+            query_positions: Vec::new(),
+            merkle_roots: Vec::new(),
+            ood_evaluation: None,
+            failing_fold: None,
+            error: None,
+        }
+    }
+
+    pub fn record_step(&mut self, label: &'static str, drawn: &[F], public_coin: &impl Debug) {
+        self.transcript.push(TranscriptStep {
+            label,
+            drawn: drawn.to_vec(),
+            public_coin: format!("{public_coin:?}"),
+        });
+    }
+}
+
+impl<F: Clone, D> Default for VerificationReport<F, D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Display, D: Digest> Display for VerificationReport<F, D> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.error {
+            Some(error) => writeln!(f, "verification failed: {error}")?,
+            None => writeln!(f, "verification succeeded")?,
+        }
+        writeln!(f, "transcript:")?;
+        for step in &self.transcript {
+            write!(f, "  {}: [", step.label)?;
+            for (i, value) in step.drawn.iter().enumerate() {
+                let separator = if i == 0 { "" } else { ", " };
+                write!(f, "{separator}{value}")?;
+            }
+            writeln!(f, "] {}", step.public_coin)?;
+        }
+        writeln!(f, "query positions: {:?}", self.query_positions)?;
+        if let Some((expected, actual)) = &self.ood_evaluation {
+            writeln!(f, "ood evaluation: expected {expected}, actual {actual}")?;
+        }
+        writeln!(f, "merkle roots:")?;
+        for check in &self.merkle_roots {
+            let status = if check.is_valid() { "ok" } else { "MISMATCH" };
+            write!(
+                f,
+                "  {}: {status} expected {}",
+                check.label,
+                hex(&check.expected)
+            )?;
+            match &check.actual {
+                Some(actual) => writeln!(f, ", actual {}", hex(actual))?,
+                None => writeln!(f, ", actual unknown")?,
+            }
+        }
+        if let Some(fold) = &self.failing_fold {
+            writeln!(
+                f,
+                "fri fold failed in layer {} at position {}: expected {}, actual {}",
+                fold.layer, fold.position, fold.expected, fold.actual
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn hex(digest: &impl Digest) -> String {
+    digest
+        .as_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct TranscriptStep<F> {
+    pub label: &'static str,
+    pub drawn: Vec<F>,
+    /// Debug representation of the public coin after this step
+    pub public_coin: String,
+}
+
+/// Root a Merkle proof was checked against and the root it resolves to
+#[derive(Debug, Clone)]
+pub struct MerkleRootCheck<D> {
+    pub label: String,
+    pub expected: D,
+    /// `None` if the Merkle tree can't recover roots
+    pub actual: Option<D>,
+}
+
+impl<D: PartialEq> MerkleRootCheck<D> {
+    pub fn is_valid(&self) -> bool {
+        self.actual.as_ref() == Some(&self.expected)
+    }
+}
+
+/// A FRI query whose folded evaluation doesn't match the value opened in the
+/// next layer (or the evaluation of the remainder)
+#[derive(Debug, Clone, Copy)]
+pub struct FriFoldFailure<F> {
+    pub layer: usize,
+    pub position: usize,
+    pub expected: F,
+    pub actual: F,
+}
+
+/// Verifies a proof and reports the transcript, Merkle roots and FRI folds
+/// checked along the way. Intended for debugging incompatible prover and
+/// verifier pairs once a proof has failed verification.
+///
+/// Uses the [reference verifier](crate::reference) so the report is slow to
+/// produce.
+pub fn explain_verification<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    required_security_bits: u32,
+) -> VerificationReport<S::Fq, S::Digest> {
+    let mut report = VerificationReport::new();
+    let result = reference_verify_with_report(this, proof, required_security_bits, &mut report);
+    report.error = result.err();
+    report
+}
@@ -0,0 +1,233 @@
+//! Degree lowering for AIRs with a few high degree constraints.
+//!
+//! The size of the constraint evaluation domain is determined by the constraint
+//! with the highest degree. [`lower_degree`] splits constraints that exceed a
+//! target blowup factor by moving sub-expressions into auxiliary base columns
+//! and adding constraints that each auxiliary column equals its expression.
+#![allow(clippy::arc_with_non_send_sync)]
+
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::expression::Expr;
+use crate::expression::P;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::One;
+use ministark_gpu::GpuFftField;
+use num_traits::Pow;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+/// Constraints with auxiliary columns introduced by [`lower_degree`]
+pub struct DegreeLowering<Fp: 'static, Fq: 'static> {
+    constraints: Vec<Constraint<FieldVariant<Fp, Fq>>>,
+    auxiliary_columns: Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>,
+    num_base_columns: usize,
+}
+
+impl<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>> DegreeLowering<Fp, Fq> {
+    pub fn constraints(&self) -> &[Constraint<FieldVariant<Fp, Fq>>] {
+        &self.constraints
+    }
+
+    pub fn into_constraints(self) -> Vec<Constraint<FieldVariant<Fp, Fq>>> {
+        self.constraints
+    }
+
+    /// Returns the number of auxiliary columns. These are appended to the base
+    /// columns so they must be included in `AirConfig::NUM_BASE_COLUMNS`.
+    pub fn num_auxiliary_columns(&self) -> usize {
+        self.auxiliary_columns.len()
+    }
+
+    /// Appends the auxiliary columns to the base trace
+    pub fn fill_auxiliary_columns(&self, base_trace: &mut Matrix<Fp>) {
+        assert_eq!(self.num_base_columns, base_trace.num_cols());
+        let n = base_trace.num_rows();
+        for expr in &self.auxiliary_columns {
+            let mut column = Vec::with_capacity_in(n, GpuAllocator);
+            for row in 0..n {
+                let value = expr.eval(&mut |leaf| match leaf {
+                    &AlgebraicItem::Trace(i, offset) => {
+                        #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+                        let position = (row as isize + offset).rem_euclid(n as isize) as usize;
+                        FieldVariant::Fp(base_trace.0[i][position])
+                    }
+                    &AlgebraicItem::Constant(v) => v,
+                    _ => unreachable!(),
+                });
+                match value {
+                    FieldVariant::Fp(value) => column.push(value),
+                    FieldVariant::Fq(_) => unreachable!(),
+                }
+            }
+            base_trace.0.push(column);
+        }
+    }
+}
+
+/// Splits constraints until each has a blowup factor of at most
+/// `max_blowup_factor`. Repeatedly replaces the highest degree sub-expression
+/// that only involves base columns and base field constants with a new
+/// auxiliary column. Extension column indices in the returned constraints are
+/// shifted to make room for the auxiliary columns.
+///
+/// # Panics
+/// Panics if a constraint can't be lowered e.g. if its degree comes from
+/// challenges, periodic columns or extension columns.
+pub fn lower_degree<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>(
+    constraints: Vec<Constraint<FieldVariant<Fp, Fq>>>,
+    num_base_columns: usize,
+    trace_len: usize,
+    max_blowup_factor: usize,
+) -> DegreeLowering<Fp, Fq> {
+    assert!(max_blowup_factor >= 2);
+    // auxiliary columns are placed after every column referenced by the
+    // constraints while lowering so they can't collide with extension columns
+    let auxiliary_start = constraints
+        .iter()
+        .flat_map(|constraint| constraint.trace_arguments())
+        .map(|(i, _)| i + 1)
+        .max()
+        .unwrap_or(0)
+        .max(num_base_columns);
+    let columns = LowerableColumns {
+        num_base_columns,
+        auxiliary_start,
+    };
+    let mut auxiliary_columns = Vec::new();
+    let mut lowered_constraints = Vec::new();
+    for mut constraint in constraints {
+        while constraint.blowup_factor(trace_len) > max_blowup_factor {
+            let (target, _) = find_candidate(&*constraint, columns, max_blowup_factor)
+                .expect("unable to lower the degree of constraint");
+            let column = AlgebraicItem::Trace(auxiliary_start + auxiliary_columns.len(), 0);
+            constraint = Constraint::new(replace(&*constraint, &target, &Expr::Leaf(column)));
+            auxiliary_columns.push(target.read().unwrap().clone());
+        }
+        lowered_constraints.push(constraint);
+    }
+
+    // ensure each auxiliary column equals its expression on every row
+    let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+    let vanishing_poly = AlgebraicItem::X.pow(trace_len) - one;
+    for (i, expr) in auxiliary_columns.iter().enumerate() {
+        let column = AlgebraicItem::Trace(auxiliary_start + i, 0);
+        let constraint = (Expr::Leaf(column) - expr.clone()) / vanishing_poly.clone();
+        lowered_constraints.push(Constraint::new(constraint));
+    }
+
+    // move the auxiliary columns after the base columns and shift the
+    // extension columns to make room for them
+    let num_auxiliary_columns = auxiliary_columns.len();
+    let mut shift_columns = |leaf: &AlgebraicItem<FieldVariant<Fp, Fq>>| match leaf {
+        &AlgebraicItem::Trace(i, offset) if i >= auxiliary_start => {
+            AlgebraicItem::Trace(num_base_columns + i - auxiliary_start, offset)
+        }
+        &AlgebraicItem::Trace(i, offset) if i >= num_base_columns => {
+            AlgebraicItem::Trace(i + num_auxiliary_columns, offset)
+        }
+        leaf => *leaf,
+    };
+    let lowered_constraints = lowered_constraints
+        .into_iter()
+        .map(|constraint| Constraint::new(constraint.map_leaves(&mut shift_columns)))
+        .collect::<Vec<_>>();
+    let auxiliary_columns = auxiliary_columns
+        .iter()
+        .map(|expr| expr.map_leaves(&mut shift_columns))
+        .collect::<Vec<_>>();
+
+    DegreeLowering {
+        constraints: lowered_constraints,
+        auxiliary_columns,
+        num_base_columns,
+    }
+}
+
+/// Columns that sub-expressions moved into auxiliary columns can involve
+#[derive(Clone, Copy)]
+struct LowerableColumns {
+    num_base_columns: usize,
+    auxiliary_start: usize,
+}
+
+impl LowerableColumns {
+    /// Returns true for base columns and auxiliary columns
+    const fn contains(self, column: usize) -> bool {
+        column < self.num_base_columns || column >= self.auxiliary_start
+    }
+}
+
+/// Returns the degree of an expression in the trace columns. Returns `None`
+/// if the expression contains anything other than base columns, auxiliary
+/// columns and base field constants.
+fn trace_degree<Fp, Fq>(
+    expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    columns: LowerableColumns,
+) -> Option<usize> {
+    use Expr::*;
+    match expr {
+        Leaf(AlgebraicItem::Trace(i, _)) if columns.contains(*i) => Some(1),
+        Leaf(AlgebraicItem::Constant(FieldVariant::Fp(_))) => Some(0),
+        Leaf(_) | Div(_, _) => None,
+        Neg(a) => trace_degree(&a.read().unwrap(), columns),
+        Pow(a, e) => trace_degree(&a.read().unwrap(), columns).map(|d| d * e),
+        Add(a, b) => Some(
+            trace_degree(&a.read().unwrap(), columns)?
+                .max(trace_degree(&b.read().unwrap(), columns)?),
+        ),
+        Mul(a, b) => Some(
+            trace_degree(&a.read().unwrap(), columns)? + trace_degree(&b.read().unwrap(), columns)?,
+        ),
+    }
+}
+
+/// Finds the highest degree sub-expression that can be moved into an auxiliary
+/// column. Returns the sub-expression and its degree.
+fn find_candidate<Fp, Fq>(
+    expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    columns: LowerableColumns,
+    max_degree: usize,
+) -> Option<(P<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>, usize)> {
+    use Expr::*;
+    let children = match expr {
+        Leaf(_) => vec![],
+        Neg(a) | Pow(a, _) => vec![a],
+        Add(a, b) | Mul(a, b) | Div(a, b) => vec![a, b],
+    };
+    children
+        .into_iter()
+        .filter_map(
+            |child| match trace_degree(&child.read().unwrap(), columns) {
+                Some(degree) if (2..=max_degree).contains(&degree) => Some((child.clone(), degree)),
+                _ => find_candidate(&child.read().unwrap(), columns, max_degree),
+            },
+        )
+        .max_by_key(|(_, degree)| *degree)
+}
+
+/// Replaces all occurrences of `target` with `replacement`
+fn replace<T: Clone>(expr: &Expr<T>, target: &P<Expr<T>>, replacement: &Expr<T>) -> Expr<T> {
+    use Expr::*;
+    let replace_child = |child: &P<Expr<T>>| {
+        let expr = if Arc::ptr_eq(child, target) {
+            replacement.clone()
+        } else {
+            replace(&child.read().unwrap(), target, replacement)
+        };
+        Arc::new(RwLock::new(expr))
+    };
+    match expr {
+        Leaf(_) => expr.clone(),
+        Neg(a) => Neg(replace_child(a)),
+        Pow(a, e) => Pow(replace_child(a), *e),
+        Add(a, b) => Add(replace_child(a), replace_child(b)),
+        Mul(a, b) => Mul(replace_child(a), replace_child(b)),
+        Div(a, b) => Div(replace_child(a), replace_child(b)),
+    }
+}
@@ -0,0 +1,392 @@
+//! Portable description of an AIR
+//!
+//! [`Air::export_description`] lists the trace layout and the expression tree
+//! of every constraint so tools outside of Rust (verifier code generators,
+//! formal analysis, documentation) can work with the arithmetization. With the
+//! `serde` feature enabled the description serializes to JSON (or any other
+//! serde format). Field elements are written as decimal integers in `[0, p)`.
+//!
+//! [`Air::from_description`] goes the other way so a verifier can check proofs
+//! for constraints loaded at runtime instead of compiled in. The trace layout,
+//! hints and composition still come from the [`AirConfig`] the description is
+//! loaded into.
+
+use crate::air::AirConfig;
+use crate::air::AirConfigError;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::constraints::PeriodicColumn;
+use crate::expression::Expr;
+use crate::expression::P;
+use crate::utils::FieldVariant;
+use crate::Air;
+use crate::ProofOptions;
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_ff::PrimeField;
+use snafu::Snafu;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+/// Description of an AIR instance. See [`Air::export_description`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AirDescription {
+    /// Hex encoding of [`Air::id`]
+    pub air_id: String,
+    pub trace_len: usize,
+    pub num_base_columns: usize,
+    pub num_extension_columns: usize,
+    /// Number of challenges drawn after committing to the base trace
+    pub num_challenges: usize,
+    /// Power-of-two degree blowup of the composition polynomial
+    pub ce_blowup_factor: usize,
+    pub constraints: Vec<ConstraintDescription>,
+    /// Coefficients of each public polynomial. See
+    /// [`AirConfig::gen_public_polynomials`].
+    pub public_polynomials: Vec<Vec<String>>,
+}
+
+/// Description of a single constraint. Boundary, transition and terminal
+/// constraints differ only in the divisor at the root of their expression.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintDescription {
+    /// Upper bound on the degree of the numerator and denominator in `x`
+    pub degree: (usize, usize),
+    /// Indices of the challenges the constraint uses
+    pub challenges: Vec<usize>,
+    pub expression: ExprDescription,
+}
+
+/// Field constant. Extension field elements are given by their coefficients
+/// over the base field.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstantDescription {
+    Fp(String),
+    Fq(Vec<String>),
+}
+
+/// Node of a constraint expression tree
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprDescription {
+    X,
+    Constant {
+        value: ConstantDescription,
+    },
+    Challenge {
+        index: usize,
+    },
+    Periodic {
+        interval_size: usize,
+        coeffs: Vec<ConstantDescription>,
+    },
+    Hint {
+        index: usize,
+    },
+    Trace {
+        column: usize,
+        offset: isize,
+    },
+    PublicPolynomial {
+        index: usize,
+    },
+    Neg {
+        operand: Box<ExprDescription>,
+    },
+    Add {
+        lhs: Box<ExprDescription>,
+        rhs: Box<ExprDescription>,
+    },
+    Mul {
+        lhs: Box<ExprDescription>,
+        rhs: Box<ExprDescription>,
+    },
+    Div {
+        lhs: Box<ExprDescription>,
+        rhs: Box<ExprDescription>,
+    },
+    Pow {
+        base: Box<ExprDescription>,
+        exponent: usize,
+    },
+}
+
+/// Errors for a description that can't be imported
+#[derive(Debug, Snafu)]
+pub enum DescriptionError {
+    #[snafu(display("{value} is not a field element in [0, p)"))]
+    InvalidFieldElement { value: String },
+    #[snafu(display(
+        "extension field element has {actual} coefficients but the extension degree is {expected}"
+    ))]
+    InvalidExtensionElement { expected: usize, actual: usize },
+    #[snafu(display(
+        "periodic column with {num_coeffs} coefficients and interval size {interval_size} is invalid"
+    ))]
+    InvalidPeriodicColumn {
+        num_coeffs: usize,
+        interval_size: usize,
+    },
+    #[snafu(display("description has {actual} {kind} columns but the AIR has {expected}"))]
+    ColumnMismatch {
+        kind: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    #[snafu(display("invalid AIR: {source}"))]
+    InvalidAir { source: AirConfigError },
+    #[snafu(display("imported AIR has id {actual} but the description has id {expected}"))]
+    AirIdMismatch { expected: String, actual: String },
+}
+
+fn describe_prime<F: PrimeField>(value: F) -> String {
+    value.into_bigint().to_string()
+}
+
+fn describe_constant<Fp: PrimeField, Fq: Field<BasePrimeField = Fp>>(
+    constant: &FieldVariant<Fp, Fq>,
+) -> ConstantDescription {
+    match constant {
+        FieldVariant::Fp(v) => ConstantDescription::Fp(describe_prime(*v)),
+        FieldVariant::Fq(v) => ConstantDescription::Fq(
+            v.to_base_prime_field_elements()
+                .map(describe_prime)
+                .collect(),
+        ),
+    }
+}
+
+fn describe_expr<Fp: PrimeField, Fq: Field<BasePrimeField = Fp>>(
+    expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+) -> ExprDescription {
+    let describe = |child: &P<_>| Box::new(describe_expr(&child.read().unwrap()));
+    match expr {
+        Expr::Leaf(item) => match item {
+            AlgebraicItem::X => ExprDescription::X,
+            AlgebraicItem::Constant(v) => ExprDescription::Constant {
+                value: describe_constant(v),
+            },
+            &AlgebraicItem::Challenge(index) => ExprDescription::Challenge { index },
+            AlgebraicItem::Periodic(column) => ExprDescription::Periodic {
+                interval_size: column.interval_size(),
+                coeffs: column.coeffs().iter().map(describe_constant).collect(),
+            },
+            &AlgebraicItem::Hint(index) => ExprDescription::Hint { index },
+            &AlgebraicItem::Trace(column, offset) => ExprDescription::Trace { column, offset },
+            &AlgebraicItem::PublicPolynomial(index) => ExprDescription::PublicPolynomial { index },
+        },
+        Expr::Neg(a) => ExprDescription::Neg {
+            operand: describe(a),
+        },
+        Expr::Add(a, b) => ExprDescription::Add {
+            lhs: describe(a),
+            rhs: describe(b),
+        },
+        Expr::Mul(a, b) => ExprDescription::Mul {
+            lhs: describe(a),
+            rhs: describe(b),
+        },
+        Expr::Div(a, b) => ExprDescription::Div {
+            lhs: describe(a),
+            rhs: describe(b),
+        },
+        Expr::Pow(a, exponent) => ExprDescription::Pow {
+            base: describe(a),
+            exponent: *exponent,
+        },
+    }
+}
+
+fn import_prime<F: PrimeField>(value: &str) -> Result<F, DescriptionError> {
+    // parsing reduces modulo p so check the value was already reduced
+    match F::from_str(value) {
+        Ok(v) if describe_prime(v) == value => Ok(v),
+        _ => Err(DescriptionError::InvalidFieldElement {
+            value: value.to_string(),
+        }),
+    }
+}
+
+fn import_constant<Fp: PrimeField, Fq: Field<BasePrimeField = Fp>>(
+    constant: &ConstantDescription,
+) -> Result<FieldVariant<Fp, Fq>, DescriptionError> {
+    match constant {
+        ConstantDescription::Fp(v) => Ok(FieldVariant::Fp(import_prime(v)?)),
+        ConstantDescription::Fq(coeffs) => {
+            let coeffs = coeffs
+                .iter()
+                .map(|v| import_prime(v))
+                .collect::<Result<Vec<Fp>, DescriptionError>>()?;
+            Fq::from_base_prime_field_elems(&coeffs)
+                .map(FieldVariant::Fq)
+                .ok_or_else(|| DescriptionError::InvalidExtensionElement {
+                    expected: usize::try_from(Fq::extension_degree()).unwrap(),
+                    actual: coeffs.len(),
+                })
+        }
+    }
+}
+
+fn import_expr<Fp: PrimeField, Fq: Field<BasePrimeField = Fp>>(
+    expr: &ExprDescription,
+) -> Result<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>, DescriptionError> {
+    let import = |child: &ExprDescription| import_expr(child).map(|e| Arc::new(RwLock::new(e)));
+    Ok(match expr {
+        ExprDescription::X => Expr::Leaf(AlgebraicItem::X),
+        ExprDescription::Constant { value } => {
+            Expr::Leaf(AlgebraicItem::Constant(import_constant(value)?))
+        }
+        &ExprDescription::Challenge { index } => Expr::Leaf(AlgebraicItem::Challenge(index)),
+        ExprDescription::Periodic {
+            interval_size,
+            coeffs,
+        } => {
+            let interval_size = *interval_size;
+            let num_coeffs = coeffs.len();
+            if !num_coeffs.is_power_of_two()
+                || !interval_size.is_power_of_two()
+                || num_coeffs > interval_size
+            {
+                return Err(DescriptionError::InvalidPeriodicColumn {
+                    num_coeffs,
+                    interval_size,
+                });
+            }
+            let coeffs = coeffs
+                .iter()
+                .map(import_constant)
+                .collect::<Result<Vec<_>, DescriptionError>>()?;
+            // periodic columns borrow their coefficients for the life of the program
+            let coeffs = Box::leak(coeffs.into_boxed_slice());
+            Expr::Leaf(AlgebraicItem::Periodic(PeriodicColumn::new(
+                coeffs,
+                interval_size,
+            )))
+        }
+        &ExprDescription::Hint { index } => Expr::Leaf(AlgebraicItem::Hint(index)),
+        &ExprDescription::Trace { column, offset } => {
+            Expr::Leaf(AlgebraicItem::Trace(column, offset))
+        }
+        &ExprDescription::PublicPolynomial { index } => {
+            Expr::Leaf(AlgebraicItem::PublicPolynomial(index))
+        }
+        ExprDescription::Neg { operand } => Expr::Neg(import(operand)?),
+        ExprDescription::Add { lhs, rhs } => Expr::Add(import(lhs)?, import(rhs)?),
+        ExprDescription::Mul { lhs, rhs } => Expr::Mul(import(lhs)?, import(rhs)?),
+        ExprDescription::Div { lhs, rhs } => Expr::Div(import(lhs)?, import(rhs)?),
+        ExprDescription::Pow { base, exponent } => Expr::Pow(import(base)?, *exponent),
+    })
+}
+
+fn encode_id(id: [u8; 32]) -> String {
+    id.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+impl<C: AirConfig> Air<C>
+where
+    C::Fp: PrimeField,
+{
+    /// Returns a machine-readable description of the trace layout and
+    /// constraints
+    pub fn export_description(&self) -> AirDescription {
+        let trace_degree = self.trace_len() - 1;
+        let constraints = self
+            .constraints()
+            .iter()
+            .map(|constraint| {
+                let mut challenges = BTreeSet::new();
+                constraint.traverse(&mut |node| {
+                    if let &Expr::Leaf(AlgebraicItem::Challenge(i)) = node {
+                        challenges.insert(i);
+                    }
+                });
+                ConstraintDescription {
+                    degree: constraint.degree(trace_degree),
+                    challenges: challenges.into_iter().collect(),
+                    expression: describe_expr(constraint),
+                }
+            })
+            .collect();
+        AirDescription {
+            air_id: encode_id(self.id()),
+            trace_len: self.trace_len(),
+            num_base_columns: C::NUM_BASE_COLUMNS,
+            num_extension_columns: C::NUM_EXTENSION_COLUMNS,
+            num_challenges: self.num_challenges(),
+            ce_blowup_factor: self.ce_blowup_factor(),
+            constraints,
+            public_polynomials: self
+                .public_polynomials()
+                .iter()
+                .map(|coeffs| coeffs.iter().copied().map(describe_prime).collect())
+                .collect(),
+        }
+    }
+
+    /// Creates an AIR from the constraints of a description, typically one
+    /// loaded at runtime. Public polynomials are generated for the given public
+    /// inputs with [`AirConfig::gen_public_polynomials`] and hints with
+    /// [`AirConfig::gen_hints`] so only the constraints need to be described.
+    /// Fails if the description doesn't fit the config or the imported
+    /// constraints don't hash to the description's AIR id.
+    pub fn from_description(
+        description: &AirDescription,
+        public_inputs: C::PublicInputs,
+        options: ProofOptions,
+    ) -> Result<Self, DescriptionError> {
+        for (kind, expected, actual) in [
+            ("base", C::NUM_BASE_COLUMNS, description.num_base_columns),
+            (
+                "extension",
+                C::NUM_EXTENSION_COLUMNS,
+                description.num_extension_columns,
+            ),
+        ] {
+            if expected != actual {
+                return Err(DescriptionError::ColumnMismatch {
+                    kind,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        let trace_len = description.trace_len;
+        let constraints = description
+            .constraints
+            .iter()
+            .map(|constraint| import_expr(&constraint.expression).map(Constraint::new))
+            .collect::<Result<Vec<_>, DescriptionError>>()?;
+        let public_polynomials = C::gen_public_polynomials(trace_len, &public_inputs);
+        let air = Self::from_constraints(
+            trace_len,
+            public_inputs,
+            options,
+            constraints,
+            public_polynomials,
+        )
+        .map_err(|source| DescriptionError::InvalidAir { source })?;
+        let air_id = encode_id(air.id());
+        if air_id != description.air_id {
+            return Err(DescriptionError::AirIdMismatch {
+                expected: description.air_id.clone(),
+                actual: air_id,
+            });
+        }
+        Ok(air)
+    }
+}
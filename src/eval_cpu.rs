@@ -1,6 +1,9 @@
 use crate::constraints::AlgebraicItem;
 use crate::constraints::PeriodicColumn;
+use crate::context::CancellationToken;
 use crate::expression::Expr;
+use crate::expression::P;
+use crate::utils::batch_inverse;
 use crate::utils::FieldVariant;
 use crate::utils::GpuAllocator;
 use crate::Matrix;
@@ -8,7 +11,6 @@ use crate::StarkExtensionOf;
 use alloc::borrow::Cow;
 use alloc::boxed::Box;
 use alloc::vec::Vec;
-use ark_ff::batch_inversion;
 use ark_ff::FftField;
 use ark_ff::Field;
 use ark_poly::domain::DomainCoeff;
@@ -29,41 +31,62 @@ use num_traits::Pow;
 use rayon::prelude::*;
 use std::collections::BTreeMap;
 
+/// Rows are evaluated in chunks of up to this many rows
+const MAX_CHUNK_SIZE: usize = 512;
+
+/// Returns the number of chunks of rows [`eval`] splits `n` rows into
+pub const fn num_chunks(n: usize) -> usize {
+    if n < MAX_CHUNK_SIZE {
+        n
+    } else {
+        n / MAX_CHUNK_SIZE
+    }
+}
+
+/// Evaluates `expr` over the constraint evaluation domain. Chunks of rows are
+/// skipped once `cancellation_token` is cancelled.
 #[allow(clippy::too_many_arguments)]
 pub fn eval<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>(
     expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
     challenges: &[Fq],
     hints: &[Fq],
+    public_polynomials: &[Vec<Fp>],
     lde_step: usize,
     domain_offset: Fp,
     x_lde: &[Fp],
     base_trace_lde_cols: &[&[Fp]],
     extension_trace_lde_cols: Option<&[&[Fq]]>,
+    cancellation_token: &CancellationToken,
 ) -> Matrix<Fq> {
+    let expr = &hoist_row_independent(expr, challenges, hints);
     let n = x_lde.len();
     let mut result = Vec::with_capacity_in(n, GpuAllocator);
     result.resize(n, Fq::zero());
     match n {
-        1..512 => eval_impl::<Fp, Fq, 1>(
+        1..MAX_CHUNK_SIZE => eval_impl::<Fp, Fq, 1>(
             expr,
             challenges,
             hints,
+            public_polynomials,
             lde_step,
             domain_offset,
             x_lde,
             base_trace_lde_cols,
             extension_trace_lde_cols,
+            cancellation_token,
             &mut result,
         ),
-        512.. => eval_impl::<Fp, Fq, 512>(
+        MAX_CHUNK_SIZE.. => eval_impl::<Fp, Fq, MAX_CHUNK_SIZE>(
             expr,
             challenges,
             hints,
+            public_polynomials,
             lde_step,
             domain_offset,
             x_lde,
             base_trace_lde_cols,
             extension_trace_lde_cols,
+            cancellation_token,
             &mut result,
         ),
         0 => {}
@@ -81,11 +104,13 @@ fn eval_impl<
     expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
     challenges: &[Fq],
     hints: &[Fq],
+    public_polynomials: &[Vec<Fp>],
     lde_step: usize,
     domain_offset: Fp,
     x_lde: &[Fp],
     base_trace_lde_cols: &[&[Fp]],
     extension_trace_lde_cols: Option<&[&[Fq]]>,
+    cancellation_token: &CancellationToken,
     result: &mut [Fq],
 ) {
     use AlgebraicItem::*;
@@ -99,9 +124,18 @@ fn eval_impl<
     let extension_column_range = num_base_columns..num_base_columns + num_extension_columns;
     let periodic_column_evals_map =
         build_periodic_column_evals_map(expr, domain_offset, trace_len, lde_step, CHUNK_SIZE);
+    let public_polynomial_evals = public_polynomials
+        .iter()
+        .map(|coeffs| {
+            eval_public_polynomial(domain_offset, trace_len, lde_step, coeffs, CHUNK_SIZE)
+        })
+        .collect::<Vec<Vec<Fp>>>();
     cfg_chunks_mut!(result, CHUNK_SIZE)
         .enumerate()
         .for_each(|(i, chunk)| {
+            if cancellation_token.is_cancelled() {
+                return;
+            }
             let chunk_offset = CHUNK_SIZE * i;
             let chunk_res: [Fq; CHUNK_SIZE] = expr
                 .graph_eval(&mut |leaf| match *leaf {
@@ -143,12 +177,57 @@ fn eval_impl<
                             ))),
                         }
                     }
+                    PublicPolynomial(i) => EvalItem::Evals(Box::new(FieldVariant::Fp(
+                        extract_lde_chunk(&public_polynomial_evals[i], chunk_offset),
+                    ))),
                 })
                 .into_fq_array();
             chunk.copy_from_slice(&chunk_res);
         });
 }
 
+/// Replaces subexpressions that only combine constants, challenges and hints
+/// with the constant they evaluate to. These would otherwise be evaluated
+/// again for every chunk of rows. See
+/// [`crate::constraints::CompositionConstraint::row_independent_ops`].
+pub fn hoist_row_independent<
+    Fp: GpuFftField<FftField = Fp> + FftField,
+    Fq: StarkExtensionOf<Fp>,
+>(
+    expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    challenges: &[Fq],
+    hints: &[Fq],
+) -> Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> {
+    use AlgebraicItem::*;
+    let mut expr = expr.map_leaves(&mut |&leaf| match leaf {
+        Challenge(i) => Constant(FieldVariant::Fq(challenges[i])),
+        Hint(i) => Constant(FieldVariant::Fq(hints[i])),
+        leaf => leaf,
+    });
+    expr.traverse_mut(&mut |node| {
+        let constant =
+            |child: &P<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>| match *child.read().unwrap() {
+                Expr::Leaf(Constant(v)) => Some(v),
+                _ => None,
+            };
+        let value = match node {
+            Expr::Leaf(_) => None,
+            Expr::Neg(a) => constant(a).map(|a| -a),
+            Expr::Pow(a, e) => constant(a).map(|a| Pow::pow(a, *e)),
+            Expr::Add(a, b) => constant(a).zip(constant(b)).map(|(a, b)| a + b),
+            Expr::Mul(a, b) => constant(a).zip(constant(b)).map(|(a, b)| a * b),
+            // division by zero is left for the evaluator to report
+            Expr::Div(a, b) => constant(a)
+                .zip(constant(b).and_then(|b| b.inverse()))
+                .map(|(a, b_inv)| a * b_inv),
+        };
+        if let Some(value) = value {
+            *node = Expr::Leaf(Constant(value));
+        }
+    });
+    expr
+}
+
 /// Extracts a chunk of evaluations from a low-degree-extension
 #[inline]
 pub fn extract_lde_chunk<F: Field, const CHUNK_SIZE: usize>(
@@ -231,6 +310,22 @@ pub fn build_periodic_column_evals_map<
     res
 }
 
+/// Evaluates a public polynomial over the constraint evaluation domain. A
+/// polynomial of degree less than `trace_len` is the same as a periodic column
+/// with an interval of `trace_len`.
+pub fn eval_public_polynomial<F: GpuFftField<FftField = F> + FftField>(
+    domain_offset: F,
+    trace_len: usize,
+    blowup_factor: usize,
+    coeffs: &[F],
+    min_len: usize,
+) -> Vec<F> {
+    let mut coeffs = coeffs.to_vec();
+    coeffs.resize(coeffs.len().next_power_of_two(), F::zero());
+    let column = PeriodicColumn::new(&coeffs, trace_len);
+    eval_periodic_column(domain_offset, trace_len, blowup_factor, column, min_len)
+}
+
 /// Generates a preiodic low degree extension of a periodic column of values
 pub fn eval_periodic_column<F: GpuField + Field + DomainCoeff<F::FftField>>(
     domain_offset: F::FftField,
@@ -282,11 +377,11 @@ impl<'a, Fp: Field, Fq: Field + From<Fp>, const N: usize> EvalItem<'a, Fp, Fq, N
             Self::Constant(v) => Self::Constant(v.inverse().unwrap()),
             Self::Evals(evals) => match *evals {
                 FieldVariant::Fp(mut evals) => {
-                    batch_inversion(evals.to_mut());
+                    batch_inverse(evals.to_mut());
                     Self::Evals(Box::new(FieldVariant::Fp(evals)))
                 }
                 FieldVariant::Fq(mut evals) => {
-                    batch_inversion(evals.to_mut());
+                    batch_inverse(evals.to_mut());
                     Self::Evals(Box::new(FieldVariant::Fq(evals)))
                 }
             },
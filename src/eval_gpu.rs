@@ -1,6 +1,7 @@
 #![cfg(feature = "gpu")]
 
 use crate::constraints::AlgebraicItem;
+use crate::eval_cpu::eval_public_polynomial;
 use crate::expression::Expr;
 use crate::utils::FieldType;
 use crate::utils::FieldVariant;
@@ -47,7 +48,9 @@ pub fn eval<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>
     expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
     challenges: &[Fq],
     hints: &[Fq],
+    public_polynomials: &[Vec<Fp>],
     lde_step: usize,
+    domain_offset: Fp,
     x_lde: GpuVec<Fp>,
     base_trace_lde: &Matrix<Fp>,
     extension_trace_lde: Option<&Matrix<Fq>>,
@@ -65,6 +68,7 @@ pub fn eval<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>
 
     let mut trace_ldes = Vec::new();
     let mut trace_ldes_map = BTreeMap::new();
+    let mut public_polynomial_ldes_map = BTreeMap::new();
 
     // TODO: this is really bad but can be changed when GPU constraint
     // evaluation is refactored.
@@ -112,6 +116,26 @@ pub fn eval<Fp: GpuFftField<FftField = Fp> + FftField, Fq: StarkExtensionOf<Fp>>
         &Periodic(_col) => {
             todo!()
         }
+        &PublicPolynomial(i) => {
+            // public polynomials are small so their LDEs are generated on the CPU
+            #[allow(clippy::option_if_let_else)]
+            let lde = if let Some(lde) = public_polynomial_ldes_map.get(&i) {
+                Weak::upgrade(lde).unwrap()
+            } else {
+                let trace_len = lde_size / lde_step;
+                let coeffs = &public_polynomials[i];
+                let evals =
+                    eval_public_polynomial(domain_offset, trace_len, lde_step, coeffs, lde_size);
+                let evals = evals.to_vec_in(GpuAllocator);
+                let buffer = buffer_no_copy(device, &evals);
+                let lde = lde_cache
+                    .borrow_mut()
+                    .add(FieldVariant::Fp(Lde(evals, buffer)));
+                public_polynomial_ldes_map.insert(i, Rc::downgrade(&lde));
+                lde
+            };
+            EvaluationItem::new_lde(&lde_calculator, &lde_cache, command_buffer, lde, 0)
+        }
         X => {
             // generate an LDE for the only X (we called reuse_shared_nodes)
             let mut x_lde = Option::take(&mut x_lde).unwrap();
@@ -1,8 +1,22 @@
+//! FRI low degree test
+//!
+//! The prover ([`FriProver`]) and verifier ([`FriVerifier`]) only depend on
+//! [`FriOptions`], a [`PublicCoin`] and a [`MatrixMerkleTree`] so they can be
+//! used outside of a STARK e.g. by polynomial commitment schemes. [`verify`]
+//! runs the whole verifier in one call.
+
+use crate::context::Device;
 use crate::hash::Digest;
 use crate::merkle;
+use crate::merkle::DeferredRowCheck;
 use crate::merkle::MatrixMerkleTree;
 use crate::merkle::MerkleTree;
+use crate::merkle::TreeTag;
 use crate::random::PublicCoin;
+use crate::random::SubCoin;
+use crate::utils::horner_evaluate_many;
+#[cfg(feature = "gpu")]
+use crate::utils::use_gpu;
 use crate::utils::GpuAllocator;
 use crate::utils::GpuVec;
 use crate::Matrix;
@@ -23,14 +37,14 @@ use ministark_gpu::utils::bit_reverse_index;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use snafu::Snafu;
-use std::iter::zip;
 use std::marker::PhantomData;
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FriOptions {
     folding_factor: usize,
     max_remainder_coeffs: usize,
     blowup_factor: usize,
+    compact: bool,
 }
 
 impl FriOptions {
@@ -43,9 +57,29 @@ impl FriOptions {
             folding_factor,
             max_remainder_coeffs,
             blowup_factor,
+            compact: false,
         }
     }
 
+    /// Omits the queried values of each layer from proofs. The verifier
+    /// already knows these from the DEEP composition evaluations or folding
+    /// the previous layer so they're reinserted during verification.
+    pub const fn with_compact(self, compact: bool) -> Self {
+        Self { compact, ..self }
+    }
+
+    pub const fn folding_factor(&self) -> usize {
+        self.folding_factor
+    }
+
+    pub const fn max_remainder_coeffs(&self) -> usize {
+        self.max_remainder_coeffs
+    }
+
+    pub const fn blowup_factor(&self) -> usize {
+        self.blowup_factor
+    }
+
     pub const fn num_layers(&self, mut domain_size: usize) -> usize {
         let mut num_layers = 0;
         while domain_size > self.max_remainder_coeffs * self.blowup_factor {
@@ -88,6 +122,106 @@ where
             remainder_coeffs,
         }
     }
+
+    /// Splits the proof into its commitments and the openings of each layer
+    pub fn split(self) -> (FriCommitments<F, D>, Vec<LayerProof<F, D, M>>) {
+        let layer_commitments = self
+            .layers
+            .iter()
+            .map(|layer| layer.commitment.clone())
+            .collect();
+        let commitments = FriCommitments {
+            layer_commitments,
+            remainder_coeffs: self.remainder_coeffs,
+        };
+        (commitments, self.layers)
+    }
+
+    /// Serializes the commitments of the proof in the format of
+    /// [`FriCommitments`]. Proofs that are verified as they're read serialize
+    /// these before the query positions are drawn and the openings of each
+    /// layer (see [`Self::serialize_layer_openings`]) after.
+    pub fn serialize_commitments<W: ark_serialize::Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), ark_serialize::SerializationError> {
+        self.layers
+            .len()
+            .serialize_with_mode(&mut writer, compress)?;
+        for layer in &self.layers {
+            layer
+                .commitment
+                .serialize_with_mode(&mut writer, compress)?;
+        }
+        self.remainder_coeffs
+            .serialize_with_mode(&mut writer, compress)
+    }
+
+    pub fn commitments_serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        self.layers.len().serialized_size(compress)
+            + self
+                .layers
+                .iter()
+                .map(|layer| layer.commitment.serialized_size(compress))
+                .sum::<usize>()
+            + self.remainder_coeffs.serialized_size(compress)
+    }
+
+    /// Serializes the queried rows and Merkle proof of each layer
+    pub fn serialize_layer_openings<W: ark_serialize::Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), ark_serialize::SerializationError> {
+        for layer in &self.layers {
+            layer
+                .flattenend_rows
+                .serialize_with_mode(&mut writer, compress)?;
+            layer
+                .merkle_proof
+                .serialize_with_mode(&mut writer, compress)?;
+        }
+        Ok(())
+    }
+
+    pub fn layer_openings_serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        self.layers
+            .iter()
+            .map(|layer| {
+                layer.flattenend_rows.serialized_size(compress)
+                    + layer.merkle_proof.serialized_size(compress)
+            })
+            .sum()
+    }
+
+    /// Reads the layer openings written by [`Self::serialize_layer_openings`]
+    /// for layers with the given commitments
+    pub fn deserialize_layer_openings<R: ark_serialize::Read>(
+        mut reader: R,
+        layer_commitments: &[D],
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Vec<LayerProof<F, D, M>>, ark_serialize::SerializationError> {
+        layer_commitments
+            .iter()
+            .map(|commitment| {
+                Ok(LayerProof {
+                    flattenend_rows: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+                    merkle_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+                    commitment: commitment.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Layer commitments and remainder of a [`FriProof`]. The verifier reseeds
+/// the public coin with these before the query positions are drawn.
+#[derive(CanonicalSerialize, CanonicalDeserialize, Clone)]
+pub struct FriCommitments<F: Field, D: Digest> {
+    pub layer_commitments: Vec<D>,
+    pub remainder_coeffs: Vec<F>,
 }
 
 struct FriLayer<F: GpuField, M: MerkleTree> {
@@ -114,10 +248,25 @@ where
         }
     }
 
-    pub fn verify<const N: usize>(self, positions: &[usize]) -> Result<(), merkle::Error> {
+    /// Verifies the rows of the `layer`th layer against its commitment. The
+    /// layer's tree has `num_rows` rows.
+    pub fn verify<const N: usize>(
+        self,
+        layer: usize,
+        num_rows: usize,
+        positions: &[usize],
+    ) -> Result<(), merkle::Error> {
         let commitment = &self.commitment;
         let (rows, _remainder) = &self.flattenend_rows.as_chunks::<N>();
-        M::verify_rows(commitment, positions, rows, self.merkle_proof)
+        let tag = TreeTag::FriLayer(layer);
+        M::verify_tagged_rows(
+            commitment,
+            num_rows,
+            tag,
+            positions,
+            rows,
+            self.merkle_proof,
+        )
     }
 }
 
@@ -125,6 +274,7 @@ pub struct FriProver<F: GpuField, D: Digest, M: MerkleTree> {
     options: FriOptions,
     layers: Vec<FriLayer<F, M>>,
     remainder_coeffs: Vec<F>,
+    devices: Vec<Device>,
     _phantom: PhantomData<D>,
 }
 
@@ -141,25 +291,44 @@ where
             options,
             layers: Vec::new(),
             remainder_coeffs: Vec::new(),
+            devices: Vec::new(),
             _phantom: PhantomData,
         }
     }
 
+    /// Shards the evaluations of each layer across `devices`. Each device
+    /// interpolates and folds a contiguous chunk of the layer's bit-reversed
+    /// evaluations (see [`drp_shard_coeffs`]).
+    pub fn with_devices(mut self, devices: &[Device]) -> Self {
+        self.devices = devices.to_vec();
+        self
+    }
+
     pub fn into_proof(self, positions: &[usize]) -> FriProof<F, D, M> {
         let folding_factor = self.options.folding_factor;
         // let (last_layer, initial_layers) = self.layers.split_last().unwrap();
         let mut proof_layers = Vec::new();
         let mut positions = positions.to_vec();
         for layer in &self.layers {
-            positions = fold_positions(&positions, folding_factor);
-            proof_layers.push(match folding_factor {
-                2 => query_layer::<F, D, M, 2>(layer, &positions),
-                4 => query_layer::<F, D, M, 4>(layer, &positions),
-                6 => query_layer::<F, D, M, 6>(layer, &positions),
-                8 => query_layer::<F, D, M, 8>(layer, &positions),
-                16 => query_layer::<F, D, M, 16>(layer, &positions),
+            let folded_positions = fold_positions(&positions, folding_factor);
+            let mut layer_proof = match folding_factor {
+                2 => query_layer::<F, D, M, 2>(layer, &folded_positions),
+                4 => query_layer::<F, D, M, 4>(layer, &folded_positions),
+                6 => query_layer::<F, D, M, 6>(layer, &folded_positions),
+                8 => query_layer::<F, D, M, 8>(layer, &folded_positions),
+                16 => query_layer::<F, D, M, 16>(layer, &folded_positions),
                 _ => unimplemented!("folding factor {folding_factor} is not supported"),
-            });
+            };
+            if self.options.compact {
+                layer_proof.flattenend_rows = compact_rows(
+                    &layer_proof.flattenend_rows,
+                    &positions,
+                    &folded_positions,
+                    folding_factor,
+                );
+            }
+            proof_layers.push(layer_proof);
+            positions = folded_positions;
         }
 
         // // layers store interlaved evaluations so they need to be un-interleaved
@@ -176,6 +345,18 @@ where
         FriProof::new(proof_layers, self.remainder_coeffs)
     }
 
+    /// Returns the committed evaluations of each layer. Each row holds the
+    /// `folding_factor` evaluations that are folded into a single evaluation of
+    /// the next layer.
+    pub fn layer_evaluations(&self) -> Vec<&Matrix<F>> {
+        self.layers.iter().map(|layer| &layer.evaluations).collect()
+    }
+
+    /// Returns the coefficients of the remainder polynomial
+    pub fn remainder_coeffs(&self) -> &[F] {
+        &self.remainder_coeffs
+    }
+
     pub fn build_layers(
         &mut self,
         channel: &mut impl ProverChannel<Field = F, Digest = D>,
@@ -194,6 +375,20 @@ where
         self.set_remainder(channel, evaluations);
     }
 
+    /// Builds the FRI layers for a random linear combination of several DEEP
+    /// composition polynomials. All evaluations must be over the same domain.
+    /// `batching_coeff` must be drawn from the public coin before the first
+    /// layer is committed. The verifier checks these layers with
+    /// [`FriVerifier::verify_batched`].
+    pub fn build_batched_layers(
+        &mut self,
+        channel: &mut impl ProverChannel<Field = F, Digest = D>,
+        evaluations: &[GpuVec<F>],
+        batching_coeff: F,
+    ) {
+        self.build_layers(channel, batch_evaluations(evaluations, batching_coeff));
+    }
+
     /// Builds a single layer of the FRI protocol
     /// Returns the evaluations for the next layer.
     fn build_layer<const N: usize>(
@@ -213,21 +408,30 @@ where
         assert!(remainder.is_empty());
 
         let matrix = Matrix::from_arrays(cosets);
-        let merkle_tree = M::from_matrix(&matrix);
+
+        // interpolating the evaluations doesn't depend on the layer's alpha so it
+        // can happen while the layer is being committed to
+        let folding_factor = self.options.folding_factor;
+        let devices = shard_devices(&self.devices, evaluations.len(), folding_factor);
+        let tag = TreeTag::FriLayer(self.layers.len());
+        let commit = || M::from_tagged_matrix(&matrix, tag);
+        let interpolate = || drp_shard_coeffs(evaluations, folding_factor, devices);
+        #[cfg(feature = "parallel")]
+        let (merkle_tree, shards) = rayon::join(commit, interpolate);
+        #[cfg(not(feature = "parallel"))]
+        let (merkle_tree, shards) = (commit(), interpolate());
         channel.commit_fri_layer(merkle_tree.root());
 
+        // the next evaluations
+        let alpha = channel.draw_fri_alpha();
+        let next_evaluations = fold_drp_shards(&shards, alpha, folding_factor, devices);
+
         self.layers.push(FriLayer {
             merkle_tree,
             evaluations: matrix,
         });
 
-        // return the next evaluations
-        apply_drp(
-            evaluations,
-            F::FftField::ONE,
-            channel.draw_fri_alpha(),
-            self.options.folding_factor,
-        )
+        next_evaluations
     }
 
     fn set_remainder(
@@ -249,7 +453,7 @@ where
     }
 }
 
-#[derive(Debug, Snafu)]
+#[derive(Debug, Snafu, PartialEq, Eq)]
 pub enum VerificationError {
     #[snafu(display("queries do not resolve to their commitment in layer {layer}"))]
     LayerCommitmentInvalid { layer: usize },
@@ -267,6 +471,10 @@ pub enum VerificationError {
         folding_factor: usize,
         layer: usize,
     },
+    #[snafu(display("expected {expected} layers but the proof has {actual}"))]
+    LayerCountMismatch { expected: usize, actual: usize },
+    #[snafu(display("folding factor {folding_factor} is not supported"))]
+    UnsupportedFoldingFactor { folding_factor: usize },
 }
 
 /// Fri verifier adapted from Winterfell to match Starkware's verifier
@@ -278,7 +486,8 @@ where
     options: FriOptions,
     layer_commitments: Vec<D>,
     pub layer_alphas: Vec<F>,
-    proof: FriProof<F, D, M>,
+    layers: Vec<LayerProof<F, D, M>>,
+    remainder_coeffs: Vec<F>,
     domain: Radix2EvaluationDomain<F::FftField>,
 }
 
@@ -296,23 +505,38 @@ where
         proof: FriProof<F, D, M>,
         max_poly_degree: usize,
     ) -> Result<Self, VerificationError> {
+        let (commitments, layers) = proof.split();
+        Self::from_commitments(public_coin, options, commitments, max_poly_degree)?
+            .with_layers(layers)
+    }
+
+    /// Reseeds the public coin with the commitments of a proof. The openings
+    /// of each layer are added with [`Self::with_layers`] which allows them to
+    /// be read after the query positions are drawn.
+    pub fn from_commitments(
+        public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+        options: FriOptions,
+        commitments: FriCommitments<F, D>,
+        max_poly_degree: usize,
+    ) -> Result<Self, VerificationError> {
+        let FriCommitments {
+            layer_commitments,
+            remainder_coeffs,
+        } = commitments;
         let folding_factor = options.folding_factor;
         let domain_offset = options.domain_offset::<F>();
         let domain_size = max_poly_degree.next_power_of_two() * options.blowup_factor;
         let domain = Radix2EvaluationDomain::new_coset(domain_size, domain_offset).unwrap();
 
         let mut layer_alphas = Vec::new();
-        let mut layer_commitments = Vec::new();
         let mut layer_codeword_len = domain_size;
-        for (i, layer) in proof.layers.iter().enumerate() {
+        for (i, commitment) in layer_commitments.iter().enumerate() {
             // TODO: batch merkle tree proofs
-            // get the merkle root from the first merkle path
-            public_coin.reseed_with_digest(&layer.commitment);
-            let alpha = public_coin.draw();
+            public_coin.reseed_with_digest(commitment);
+            let alpha = public_coin.fork(SubCoin::Fri).draw();
             layer_alphas.push(alpha);
-            layer_commitments.push(layer.commitment.clone());
 
-            if i != proof.layers.len() - 1 && layer_codeword_len % folding_factor != 0 {
+            if i != layer_commitments.len() - 1 && layer_codeword_len % folding_factor != 0 {
                 return Err(VerificationError::CodewordTruncation {
                     size: layer_codeword_len,
                     folding_factor,
@@ -323,7 +547,15 @@ where
             layer_codeword_len /= folding_factor;
         }
 
-        public_coin.reseed_with_field_element_vector(&proof.remainder_coeffs);
+        let num_layers = options.num_layers(domain_size);
+        if layer_commitments.len() != num_layers {
+            return Err(VerificationError::LayerCountMismatch {
+                expected: num_layers,
+                actual: layer_commitments.len(),
+            });
+        }
+
+        public_coin.reseed_with_field_element_vector(&remainder_coeffs);
 
         // TODO: add back in
         // let remainder_root =
@@ -339,18 +571,45 @@ where
             options,
             layer_commitments,
             layer_alphas,
-            proof,
+            layers: Vec::new(),
+            remainder_coeffs,
             domain,
         })
     }
 
+    /// Adds the queried rows and Merkle proof of each layer to a verifier
+    /// created with [`Self::from_commitments`]
+    pub fn with_layers(self, layers: Vec<LayerProof<F, D, M>>) -> Result<Self, VerificationError> {
+        if layers.len() != self.layer_commitments.len() {
+            return Err(VerificationError::LayerCountMismatch {
+                expected: self.layer_commitments.len(),
+                actual: layers.len(),
+            });
+        }
+        Ok(Self { layers, ..self })
+    }
+
     pub fn verify_generic<const N: usize>(
         self,
         positions: &[usize],
         evaluations: &[F],
+    ) -> Result<(), VerificationError> {
+        self.verify_layers::<N>(positions, evaluations, DeferredRowCheck::verify)
+    }
+
+    /// Verifies each layer and the remainder. The Merkle check of each layer
+    /// is passed to `check_rows` which can either run it straight away or
+    /// defer it (see [`Self::verify_deferred`]).
+    fn verify_layers<const N: usize>(
+        self,
+        positions: &[usize],
+        evaluations: &[F],
+        mut check_rows: impl FnMut(
+            DeferredRowCheck<M, VerificationError>,
+        ) -> Result<(), VerificationError>,
     ) -> Result<(), VerificationError> {
         let folding_domain = Radix2EvaluationDomain::new(N).unwrap();
-        let mut layers = self.proof.layers.into_iter();
+        let mut layers = self.layers.into_iter();
         let mut layer_alphas = self.layer_alphas.into_iter();
         let mut layer_commitments = self.layer_commitments.into_iter();
         let mut positions = positions.to_vec();
@@ -364,28 +623,42 @@ where
             let layer_alpha = layer_alphas.next().unwrap();
             let layer_commitment = layer_commitments.next().unwrap();
 
-            // TODO: change assert to error. Check remainder
-            let layer = layers.next().unwrap();
-            let (rows, _) = &layer.flattenend_rows.as_chunks::<N>();
-            assert_eq!(rows.len(), folded_positions.len());
+            // the number of layers is checked in `FriVerifier::with_layers`
+            let layer = layers
+                .next()
+                .ok_or(VerificationError::LayerCommitmentInvalid { layer: i })?;
+            let flattenend_rows = if self.options.compact {
+                expand_rows(
+                    &layer.flattenend_rows,
+                    &positions,
+                    &evaluations,
+                    &folded_positions,
+                    N,
+                )
+                .ok_or(VerificationError::LayerCommitmentInvalid { layer: i })?
+            } else {
+                layer.flattenend_rows
+            };
+            let (rows, remainder) = &flattenend_rows.as_chunks::<N>();
+            if rows.len() != folded_positions.len() || !remainder.is_empty() {
+                return Err(VerificationError::LayerCommitmentInvalid { layer: i });
+            }
 
             // verify the layer values against the layer's commitment
-            M::verify_rows(
-                &layer_commitment,
-                &folded_positions,
-                rows,
+            let row_hashes = rows
+                .iter()
+                .map(|row| M::hash_tagged_row(TreeTag::FriLayer(i), row))
+                .collect();
+            check_rows(DeferredRowCheck::new(
+                layer_commitment,
+                domain_size / N,
+                folded_positions.clone(),
+                row_hashes,
                 layer.merkle_proof,
-            )
-            .map_err(|_| VerificationError::LayerCommitmentInvalid { layer: i })?;
+                VerificationError::LayerCommitmentInvalid { layer: i },
+            ))?;
 
             let query_values = get_query_values(rows, &positions, &folded_positions);
-            // println!("evaluatinos: {:?}", evaluations);
-            // println!("query vals: {:?}", query_values);
-            assert_eq!(evaluations.len(), query_values.len());
-            for (i, (eval, query_val)) in zip(&evaluations, &query_values).enumerate() {
-                assert_eq!(eval, query_val, "mismatch at {i}");
-            }
-
             if evaluations != query_values {
                 return Err(VerificationError::InvalidDegreeRespectingProjection { layer: i });
             }
@@ -415,7 +688,7 @@ where
             domain_size /= N;
         }
         verify_remainder::<F>(
-            self.proof.remainder_coeffs,
+            self.remainder_coeffs,
             &positions,
             &evaluations,
             domain_generator,
@@ -425,21 +698,89 @@ where
     }
 
     pub fn verify(self, positions: &[usize], evaluations: &[F]) -> Result<(), VerificationError> {
+        self.verify_with(positions, evaluations, DeferredRowCheck::verify)
+    }
+
+    /// Same as [`Self::verify`] but returns the Merkle check of each layer
+    /// rather than running it. The checks must be run for the proof to be
+    /// verified (see [`merkle::verify_deferred`]).
+    pub fn verify_deferred(
+        self,
+        positions: &[usize],
+        evaluations: &[F],
+    ) -> Result<Vec<DeferredRowCheck<M, VerificationError>>, VerificationError> {
+        let mut checks = Vec::new();
+        self.verify_with(positions, evaluations, |check| {
+            checks.push(check);
+            Ok(())
+        })?;
+        Ok(checks)
+    }
+
+    fn verify_with(
+        self,
+        positions: &[usize],
+        evaluations: &[F],
+        check_rows: impl FnMut(DeferredRowCheck<M, VerificationError>) -> Result<(), VerificationError>,
+    ) -> Result<(), VerificationError> {
         if positions.len() != evaluations.len() {
             return Err(VerificationError::NumPositionEvaluationMismatch);
         }
 
         match self.options.folding_factor {
-            2 => self.verify_generic::<2>(positions, evaluations),
-            4 => self.verify_generic::<4>(positions, evaluations),
-            8 => self.verify_generic::<8>(positions, evaluations),
-            16 => self.verify_generic::<16>(positions, evaluations),
-            // TODO: move this to options
-            folding_factor => unreachable!("folding factor {folding_factor} not supported"),
+            2 => self.verify_layers::<2>(positions, evaluations, check_rows),
+            4 => self.verify_layers::<4>(positions, evaluations, check_rows),
+            8 => self.verify_layers::<8>(positions, evaluations, check_rows),
+            16 => self.verify_layers::<16>(positions, evaluations, check_rows),
+            folding_factor => Err(VerificationError::UnsupportedFoldingFactor { folding_factor }),
+        }
+    }
+
+    /// Verifies FRI layers built with [`FriProver::build_batched_layers`].
+    /// `evaluations` contains the query evaluations of each DEEP composition
+    /// polynomial and `batching_coeff` must be drawn from the public coin
+    /// before creating the verifier.
+    pub fn verify_batched(
+        self,
+        positions: &[usize],
+        evaluations: &[Vec<F>],
+        batching_coeff: F,
+    ) -> Result<(), VerificationError> {
+        if evaluations
+            .iter()
+            .any(|evals| evals.len() != positions.len())
+        {
+            return Err(VerificationError::NumPositionEvaluationMismatch);
         }
+        let evaluations = (0..positions.len())
+            .map(|i| horner(evaluations.iter().map(|evals| evals[i]), batching_coeff))
+            .collect::<Vec<F>>();
+        self.verify(positions, &evaluations)
     }
 }
 
+/// Verifies a FRI proof that `evaluations` at the bit-reversed `positions`
+/// of the LDE domain are of a polynomial of degree at most `max_poly_degree`.
+/// `public_coin` must be in the state the prover's was in when the first layer
+/// was committed and the positions must be drawn from it after this returns.
+/// Use [`FriVerifier`] directly to draw the positions in between.
+pub fn verify<F, D, M>(
+    options: FriOptions,
+    public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+    proof: FriProof<F, D, M>,
+    max_poly_degree: usize,
+    positions: &[usize],
+    evaluations: &[F],
+) -> Result<(), VerificationError>
+where
+    F: GpuField + Field + DomainCoeff<F::FftField>,
+    F::FftField: FftField,
+    D: Digest,
+    M: MatrixMerkleTree<F, Root = D>,
+{
+    FriVerifier::new(public_coin, options, proof, max_poly_degree)?.verify(positions, evaluations)
+}
+
 fn verify_remainder<F: GpuField + Field + DomainCoeff<F::FftField>>(
     remainder_coeffs: Vec<F>,
     positions: &[usize],
@@ -460,18 +801,15 @@ where
     }
     let xs = positions
         .iter()
-        .map(|p| domain_generator.pow([bit_reverse_index(domain_size, *p) as u64]));
-    for (i, x) in xs.enumerate() {
-        // TODO: fix types and just call horner_evaluate
-        let y = remainder_poly
-            .iter()
-            .rfold(F::zero(), move |mut result, coeff| {
-                result *= x;
-                result + coeff
-            });
-        if expected_evaluations[i] != y {
-            return Err(VerificationError::RemainderCommitmentInvalid);
-        }
+        .map(|p| {
+            let mut x = F::one();
+            x *= domain_generator.pow([bit_reverse_index(domain_size, *p) as u64]);
+            x
+        })
+        .collect::<Vec<F>>();
+    let evaluations = horner_evaluate_many(remainder_poly.coeffs(), &xs);
+    if evaluations != expected_evaluations {
+        return Err(VerificationError::RemainderCommitmentInvalid);
     }
     Ok(())
 }
@@ -487,6 +825,39 @@ pub trait ProverChannel {
     fn draw_fri_alpha(&mut self) -> Self::Field;
 }
 
+/// [`ProverChannel`] that commits to layers by reseeding a [`PublicCoin`].
+/// Matches the transcript [`FriVerifier`] expects.
+pub struct PublicCoinChannel<'a, P: PublicCoin> {
+    pub public_coin: &'a mut P,
+}
+
+impl<'a, P: PublicCoin> PublicCoinChannel<'a, P> {
+    pub fn new(public_coin: &'a mut P) -> Self {
+        Self { public_coin }
+    }
+}
+
+impl<'a, P: PublicCoin> ProverChannel for PublicCoinChannel<'a, P>
+where
+    P::Field: GpuField,
+{
+    type Digest = P::Digest;
+    type Field = P::Field;
+
+    fn commit_fri_layer(&mut self, layer_root: P::Digest) {
+        self.public_coin.reseed_with_digest(&layer_root);
+    }
+
+    fn commit_remainder(&mut self, remainder_coeffs: &[P::Field]) {
+        self.public_coin
+            .reseed_with_field_element_vector(remainder_coeffs);
+    }
+
+    fn draw_fri_alpha(&mut self) -> P::Field {
+        self.public_coin.fork(SubCoin::Fri).draw()
+    }
+}
+
 /// Performs a degree respecting projection (drp) on polynomial evaluations.
 // Example for `folding_factor = 2`:
 // ```text
@@ -524,11 +895,123 @@ pub trait ProverChannel {
 //    └────────┴────┴────┴────┴────┘
 // ```
 pub fn apply_drp<F: GpuField + Field + DomainCoeff<F::FftField>>(
-    mut evals: GpuVec<F>,
+    evals: GpuVec<F>,
     domain_offset: F::FftField,
     alpha: F,
     folding_factor: usize,
 ) -> GpuVec<F>
+where
+    F::FftField: FftField,
+{
+    let coeffs = drp_coeffs(evals, domain_offset, folding_factor, None);
+    fold_drp_coeffs(&coeffs, domain_offset, alpha, folding_factor, None)
+}
+
+/// Returns the devices a layer of `n` evaluations is sharded across. Shards
+/// are contiguous chunks of the bit-reversed evaluations so there must be a
+/// power of two of them and each must fold into at least one evaluation.
+fn shard_devices(devices: &[Device], n: usize, folding_factor: usize) -> &[Device] {
+    if devices.len() < 2 {
+        return devices;
+    }
+    let num_shards = core::cmp::min(1 << devices.len().ilog2(), n / folding_factor);
+    &devices[..num_shards]
+}
+
+/// Interpolates the bit-reversed evaluations of a layer for a degree respecting
+/// projection with one shard per device. Shard `i` holds the bit-reversed
+/// evaluations over the coset `ω^bit_reverse(i)` of a smaller subgroup. The
+/// projection at a point only depends on the evaluations over its coset so
+/// shards are interpolated and folded independently. Returns the coefficients
+/// and coset offset of each shard.
+fn drp_shard_coeffs<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    evals: GpuVec<F>,
+    folding_factor: usize,
+    devices: &[Device],
+) -> Vec<(GpuVec<F>, F::FftField)>
+where
+    F::FftField: FftField,
+{
+    if devices.len() < 2 {
+        let offset = F::FftField::ONE;
+        let coeffs = drp_coeffs(evals, offset, folding_factor, devices.first());
+        return vec![(coeffs, offset)];
+    }
+    let n = evals.len();
+    let num_shards = devices.len();
+    let generator = Radix2EvaluationDomain::<F::FftField>::new(n)
+        .unwrap()
+        .group_gen();
+    let shards = evals
+        .chunks(n / num_shards)
+        .enumerate()
+        .map(|(i, shard)| {
+            let exponent = bit_reverse_index(num_shards, i) as u64;
+            (shard.to_vec_in(GpuAllocator), generator.pow([exponent]))
+        })
+        .collect::<Vec<_>>();
+    on_devices(shards, devices, |(shard, offset), device| {
+        let coeffs = drp_coeffs(shard, offset, folding_factor, Some(device));
+        (coeffs, offset)
+    })
+}
+
+/// Folds the shards returned by [`drp_shard_coeffs`] with `alpha` and joins
+/// the bit-reversed evaluations of the projection
+fn fold_drp_shards<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    shards: &[(GpuVec<F>, F::FftField)],
+    alpha: F,
+    folding_factor: usize,
+    devices: &[Device],
+) -> GpuVec<F>
+where
+    F::FftField: FftField,
+{
+    if let [(coeffs, offset)] = shards {
+        return fold_drp_coeffs(coeffs, *offset, alpha, folding_factor, devices.first());
+    }
+    let folded_shards = on_devices(shards, devices, |(coeffs, offset), device| {
+        fold_drp_coeffs(coeffs, *offset, alpha, folding_factor, Some(device))
+    });
+    let n = folded_shards.iter().map(Vec::len).sum();
+    let mut evals = Vec::with_capacity_in(n, GpuAllocator);
+    for shard in folded_shards {
+        evals.extend_from_slice(&shard);
+    }
+    evals
+}
+
+/// Runs `op` on each item and the device at the same index in parallel
+fn on_devices<T: Send, R: Send>(
+    items: impl IntoIterator<Item = T>,
+    devices: &[Device],
+    op: impl Fn(T, &Device) -> R + Sync,
+) -> Vec<R> {
+    std::thread::scope(|s| {
+        let handles = items
+            .into_iter()
+            .zip(devices)
+            .map(|(item, device)| {
+                let op = &op;
+                s.spawn(move || op(item, device))
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+/// Interpolates bit-reversed evaluations for a degree respecting projection.
+/// Coefficients are scaled by the folding factor. The FFT runs on `device` if
+/// there is one.
+fn drp_coeffs<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    mut evals: GpuVec<F>,
+    domain_offset: F::FftField,
+    folding_factor: usize,
+    device: Option<&Device>,
+) -> GpuVec<F>
 where
     F::FftField: FftField,
 {
@@ -536,12 +1019,25 @@ where
     let domain = Radix2EvaluationDomain::new_coset(n, domain_offset).unwrap();
     // TODO: integrate bit reverse into fft
     bit_reverse(&mut evals);
-    let mut coeffs = ifft(evals, domain);
+    let mut coeffs = ifft(evals, domain, device);
     let fold_fact = F::from(folding_factor as u64);
-    for coeff in &mut coeffs {
-        *coeff *= fold_fact;
-    }
+    ark_std::cfg_iter_mut!(coeffs).for_each(|coeff| *coeff *= fold_fact);
+    coeffs
+}
 
+/// Folds the coefficients returned by [`drp_coeffs`] with `alpha` and returns
+/// the bit-reversed evaluations of the projection
+fn fold_drp_coeffs<F: GpuField + Field + DomainCoeff<F::FftField>>(
+    coeffs: &[F],
+    domain_offset: F::FftField,
+    alpha: F,
+    folding_factor: usize,
+    device: Option<&Device>,
+) -> GpuVec<F>
+where
+    F::FftField: FftField,
+{
+    let n = coeffs.len();
     let alpha_powers = (0..folding_factor)
         .map(|i| alpha.pow([i as u64]))
         .collect::<Vec<F>>();
@@ -561,24 +1057,29 @@ where
     let drp_domain = Radix2EvaluationDomain::new_coset(n / folding_factor, drp_offset).unwrap();
 
     // return the drp evals
-    let mut evals = fft(drp_coeffs, drp_domain);
+    let mut evals = fft(drp_coeffs, drp_domain, device);
     bit_reverse(&mut evals);
     evals
 }
 
 // requires ownership when the gpu feature is enabled
 #[allow(clippy::needless_pass_by_value)]
+#[cfg_attr(not(feature = "gpu"), allow(unused_variables))]
 fn ifft<F: GpuField + Field + DomainCoeff<F::FftField>>(
     evals: GpuVec<F>,
     domain: Radix2EvaluationDomain<F::FftField>,
+    device: Option<&Device>,
 ) -> GpuVec<F>
 where
     F::FftField: FftField,
 {
     #[cfg(feature = "gpu")]
-    if domain.size() >= GpuFft::<F>::MIN_SIZE {
+    if use_gpu::<F>(domain.size()) {
         let mut coeffs = evals;
-        let mut ifft = GpuIfft::from(domain);
+        let mut ifft = match device {
+            Some(device) => device.planner().plan_ifft(domain),
+            None => GpuIfft::from(domain),
+        };
         ifft.encode(&mut coeffs);
         ifft.execute();
         return coeffs;
@@ -590,17 +1091,22 @@ where
 
 // requires ownership when the gpu feature is enabled
 #[allow(clippy::needless_pass_by_value)]
+#[cfg_attr(not(feature = "gpu"), allow(unused_variables))]
 fn fft<F: GpuField + Field + DomainCoeff<F::FftField>>(
     coeffs: GpuVec<F>,
     domain: Radix2EvaluationDomain<F::FftField>,
+    device: Option<&Device>,
 ) -> GpuVec<F>
 where
     F::FftField: FftField,
 {
     #[cfg(feature = "gpu")]
-    if domain.size() >= GpuFft::<F>::MIN_SIZE {
+    if use_gpu::<F>(domain.size()) {
         let mut evals = coeffs;
-        let mut fft = GpuFft::from(domain);
+        let mut fft = match device {
+            Some(device) => device.planner().plan_fft(domain),
+            None => GpuFft::from(domain),
+        };
         fft.encode(&mut evals);
         fft.execute();
         return evals;
@@ -610,6 +1116,27 @@ where
     evals.to_vec_in(GpuAllocator)
 }
 
+/// Combines evaluations of several polynomials over the same domain into
+/// `Σ α^i * evaluations[i]`
+///
+/// # Panics
+/// Panics if the evaluations have different lengths
+pub fn batch_evaluations<F: Field>(evaluations: &[GpuVec<F>], batching_coeff: F) -> GpuVec<F> {
+    let n = evaluations.first().map_or(0, Vec::len);
+    assert!(evaluations.iter().all(|evals| evals.len() == n));
+    let mut res = Vec::with_capacity_in(n, GpuAllocator);
+    res.resize(n, F::zero());
+    ark_std::cfg_iter_mut!(res)
+        .enumerate()
+        .for_each(|(i, v)| *v = horner(evaluations.iter().map(|evals| evals[i]), batching_coeff));
+    res
+}
+
+/// Evaluates `Σ α^i * values[i]`
+fn horner<F: Field>(values: impl DoubleEndedIterator<Item = F>, alpha: F) -> F {
+    values.rfold(F::zero(), |acc, v| acc * alpha + v)
+}
+
 /// # Panics
 /// Panics is positions are not all unique and sorted
 pub fn fold_positions(positions: &[usize], folding_factor: usize) -> Vec<usize> {
@@ -629,6 +1156,55 @@ pub fn fold_positions(positions: &[usize], folding_factor: usize) -> Vec<usize>
     // res
 }
 
+/// Removes the values at `positions` from the queried rows of a layer. Rows
+/// are flattened and `folded_positions` are the rows' positions.
+pub fn compact_rows<F: Copy>(
+    flattenend_rows: &[F],
+    positions: &[usize],
+    folded_positions: &[usize],
+    folding_factor: usize,
+) -> Vec<F> {
+    flattenend_rows
+        .chunks(folding_factor)
+        .zip(folded_positions)
+        .flat_map(|(row, &folded_position)| {
+            row.iter().enumerate().filter_map(move |(i, value)| {
+                let position = folded_position * folding_factor + i;
+                positions
+                    .binary_search(&position)
+                    .is_err()
+                    .then_some(*value)
+            })
+        })
+        .collect()
+}
+
+/// Inverse of [`compact_rows`]. Reinserts `evaluations` at their `positions`.
+/// Returns `None` if the number of values doesn't match the positions.
+pub fn expand_rows<F: Copy>(
+    values: &[F],
+    positions: &[usize],
+    evaluations: &[F],
+    folded_positions: &[usize],
+    folding_factor: usize,
+) -> Option<Vec<F>> {
+    if positions.len() != evaluations.len() {
+        return None;
+    }
+    let mut values = values.iter();
+    let mut rows = Vec::with_capacity(folded_positions.len() * folding_factor);
+    for &folded_position in folded_positions {
+        for i in 0..folding_factor {
+            let position = folded_position * folding_factor + i;
+            rows.push(match positions.binary_search(&position) {
+                Ok(j) => evaluations[j],
+                Err(_) => *values.next()?,
+            });
+        }
+    }
+    values.next().is_none().then_some(rows)
+}
+
 // from winterfell
 pub fn get_query_values<F: Field, const N: usize>(
     chunks: &[[F; N]],
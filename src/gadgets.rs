@@ -0,0 +1,330 @@
+//! Constraint gadgets for patterns that come up in most VM AIRs
+
+use crate::constraints::AlgebraicItem;
+use crate::constraints::ExecutionTraceColumn;
+use crate::expression::Expr;
+use crate::utils::batch_inverse;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use ark_ff::BigInteger;
+use ark_ff::Field;
+use ark_ff::One;
+use ark_ff::PrimeField;
+use core::iter::repeat;
+use core::iter::zip;
+
+/// Checks if a value is zero with the nondeterministic inverse trick. The
+/// prover supplies a column holding the inverse of the value (or zero if the
+/// value is zero) which is generated with [`is_zero_witness`].
+pub struct IsZero<T: 'static> {
+    /// `1` if the value is zero and `0` otherwise
+    pub is_zero: Expr<AlgebraicItem<T>>,
+    /// Constraints that force the inverse column to be the inverse of the
+    /// value or zero. Apply to every row the gadget is used on.
+    pub constraints: [Expr<AlgebraicItem<T>>; 2],
+}
+
+impl<T: One + Clone> IsZero<T> {
+    pub fn new(value: Expr<AlgebraicItem<T>>, inverse_column: impl ExecutionTraceColumn) -> Self {
+        let one = Expr::from(AlgebraicItem::Constant(T::one()));
+        let inverse = inverse_column.curr();
+        let is_zero = one - &value * &inverse;
+        // `value * is_zero = 0` forces `is_zero` to be `0` for nonzero values
+        // and `inverse * is_zero = 0` forces `inverse` to be `0` for zero
+        let constraints = [&value * &is_zero, &inverse * &is_zero];
+        Self {
+            is_zero,
+            constraints,
+        }
+    }
+
+    /// `1` if the value is nonzero and `0` otherwise
+    pub fn is_nonzero(&self) -> Expr<AlgebraicItem<T>> {
+        let one = Expr::from(AlgebraicItem::Constant(T::one()));
+        one - self.is_zero.clone()
+    }
+}
+
+/// Returns the inverse column of an [`IsZero`] gadget given the values of the
+/// checked expression on each row
+pub fn is_zero_witness<F: Field>(values: &[F]) -> GpuVec<F> {
+    let mut inverses = Vec::with_capacity_in(values.len(), GpuAllocator);
+    inverses.extend_from_slice(values);
+    batch_inverse(&mut inverses);
+    inverses
+}
+
+/// Returns `a` if `condition` is one and `b` if `condition` is zero. The
+/// condition must be constrained to be binary.
+pub fn select<T: Clone>(
+    condition: Expr<AlgebraicItem<T>>,
+    a: Expr<AlgebraicItem<T>>,
+    b: Expr<AlgebraicItem<T>>,
+) -> Expr<AlgebraicItem<T>> {
+    condition * (a - b.clone()) + b
+}
+
+/// Returns a constraint that `a` equals `b` when `condition` is one
+pub fn conditional_equality<T>(
+    condition: Expr<AlgebraicItem<T>>,
+    a: Expr<AlgebraicItem<T>>,
+    b: Expr<AlgebraicItem<T>>,
+) -> Expr<AlgebraicItem<T>> {
+    condition * (a - b)
+}
+
+/// Order of the byte columns of a [`ByteDecomposition`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    /// The first byte column holds the most significant byte
+    Big,
+    /// The first byte column holds the least significant byte
+    Little,
+}
+
+/// Decomposes a value column into `width` consecutive byte columns. Each byte
+/// column is range checked and the bytes must recompose to the value.
+///
+/// The range check of a byte `b` is the degree 256 polynomial `Π (b - i)` for
+/// `0 <= i < 256` so the constraints should be passed through
+/// [`crate::degree_lowering::lower_degree`] before they're used in an AIR.
+#[derive(Clone, Copy, Debug)]
+pub struct ByteDecomposition {
+    value_column: usize,
+    first_byte_column: usize,
+    width: usize,
+    endianness: Endianness,
+}
+
+impl ByteDecomposition {
+    pub fn new(
+        value_column: usize,
+        first_byte_column: usize,
+        width: usize,
+        endianness: Endianness,
+    ) -> Self {
+        assert!(width != 0, "must decompose into at least one byte");
+        Self {
+            value_column,
+            first_byte_column,
+            width,
+            endianness,
+        }
+    }
+
+    pub const fn value_column(&self) -> usize {
+        self.value_column
+    }
+
+    /// Returns the byte columns ordered from least to most significant
+    pub fn byte_columns(&self) -> Vec<usize> {
+        let columns = self.first_byte_column..self.first_byte_column + self.width;
+        match self.endianness {
+            Endianness::Big => columns.rev().collect(),
+            Endianness::Little => columns.collect(),
+        }
+    }
+
+    /// Returns the constraint that the value equals `Σ b_i * 256^i` where
+    /// `b_i` is the `i`-th least significant byte
+    pub fn recomposition_constraint<Fp: Field, Fq: Field>(
+        &self,
+    ) -> Expr<AlgebraicItem<FieldVariant<Fp, Fq>>> {
+        let mut shift = Fp::one();
+        let recomposition = self
+            .byte_columns()
+            .into_iter()
+            .map(|column| {
+                let byte = column.curr() * AlgebraicItem::Constant(FieldVariant::Fp(shift));
+                shift *= Fp::from(256u32);
+                byte
+            })
+            .sum::<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>();
+        self.value_column.curr() - recomposition
+    }
+
+    /// Returns a constraint for each byte column that it holds a value in
+    /// `0..256`
+    pub fn range_constraints<Fp: Field, Fq: Field>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        self.byte_columns()
+            .into_iter()
+            .map(|column| {
+                (0..256u32)
+                    .map(|v| column.curr() - AlgebraicItem::Constant(FieldVariant::Fp(Fp::from(v))))
+                    .product()
+            })
+            .collect()
+    }
+
+    /// Returns all constraints of the decomposition
+    pub fn constraints<Fp: Field, Fq: Field>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let mut constraints = self.range_constraints();
+        constraints.push(self.recomposition_constraint());
+        constraints
+    }
+
+    /// Returns the byte columns (in the order of the column indices) for the
+    /// values of the value column
+    ///
+    /// # Panics
+    /// Panics if a value doesn't fit in `width` bytes
+    pub fn witness<F: PrimeField>(&self, values: &[F]) -> Vec<GpuVec<F>> {
+        let mut columns = (0..self.width)
+            .map(|_| Vec::with_capacity_in(values.len(), GpuAllocator))
+            .collect::<Vec<GpuVec<F>>>();
+        for value in values {
+            let bytes = value.into_bigint().to_bytes_le();
+            assert!(
+                bytes.iter().skip(self.width).all(|&byte| byte == 0),
+                "{value} doesn't fit in {} bytes",
+                self.width
+            );
+            let mut bytes = bytes
+                .into_iter()
+                .chain(repeat(0))
+                .take(self.width)
+                .collect::<Vec<u8>>();
+            if self.endianness == Endianness::Big {
+                bytes.reverse();
+            }
+            for (column, byte) in zip(&mut columns, bytes) {
+                column.push(F::from(byte));
+            }
+        }
+        columns
+    }
+}
+
+/// Operation of a [`U32Arithmetic`] gadget
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum U32Operation {
+    /// `c = a + b mod 2^32` with `carry` set on overflow
+    Add,
+    /// `c = a - b mod 2^32` with `carry` set on underflow
+    Sub,
+    /// `c = a * b mod 2^32` with the high 32 bits of the product in `carry`
+    Mul,
+}
+
+/// 32-bit modular arithmetic `c = a op b mod 2^32` over columns `a`, `b`, `c`
+/// and a carry column. The result (and the carry of a multiplication) is
+/// range checked with a [`ByteDecomposition`] into byte columns starting at
+/// `first_byte_column`. The inputs `a` and `b` are assumed to be range checked
+/// elsewhere e.g. by the gadget that produced them.
+///
+/// Multiplication requires a field with a modulus larger than `2^64` so the
+/// product can't wrap around the modulus.
+#[derive(Clone, Copy, Debug)]
+pub struct U32Arithmetic {
+    pub operation: U32Operation,
+    pub a: usize,
+    pub b: usize,
+    pub c: usize,
+    pub carry: usize,
+    pub first_byte_column: usize,
+}
+
+/// Columns of a [`U32Arithmetic`] gadget generated by the prover
+pub struct U32Witness<F> {
+    pub c: GpuVec<F>,
+    pub carry: GpuVec<F>,
+    /// Byte columns in the order of their column indices
+    pub bytes: Vec<GpuVec<F>>,
+}
+
+impl U32Arithmetic {
+    /// Returns the number of byte columns used for range checks
+    pub const fn num_byte_columns(&self) -> usize {
+        match self.operation {
+            U32Operation::Add | U32Operation::Sub => 4,
+            U32Operation::Mul => 8,
+        }
+    }
+
+    fn result_decomposition(&self) -> ByteDecomposition {
+        ByteDecomposition::new(self.c, self.first_byte_column, 4, Endianness::Little)
+    }
+
+    fn carry_decomposition(&self) -> ByteDecomposition {
+        ByteDecomposition::new(
+            self.carry,
+            self.first_byte_column + 4,
+            4,
+            Endianness::Little,
+        )
+    }
+
+    /// Returns the constraints of the operation followed by the range checks
+    pub fn constraints<Fp: PrimeField, Fq: Field>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let two_pow_32 = AlgebraicItem::Constant(FieldVariant::Fp(Fp::from(1u64 << 32)));
+        let (a, b, c, carry) = (
+            self.a.curr(),
+            self.b.curr(),
+            self.c.curr(),
+            self.carry.curr(),
+        );
+        let mut constraints = match self.operation {
+            U32Operation::Add => vec![
+                a + b - c - carry.clone() * two_pow_32,
+                &carry * &(carry.clone() - one),
+            ],
+            U32Operation::Sub => vec![
+                a + carry.clone() * two_pow_32 - b - c,
+                &carry * &(carry.clone() - one),
+            ],
+            U32Operation::Mul => {
+                assert!(
+                    Fp::MODULUS_BIT_SIZE > 64,
+                    "u32 multiplication requires a field larger than 64 bits"
+                );
+                vec![a * b - c - carry * two_pow_32]
+            }
+        };
+        constraints.extend(self.result_decomposition().constraints());
+        if self.operation == U32Operation::Mul {
+            constraints.extend(self.carry_decomposition().constraints());
+        }
+        constraints
+    }
+
+    /// Returns the result, carry and byte columns for the inputs
+    pub fn witness<F: PrimeField>(&self, a: &[u32], b: &[u32]) -> U32Witness<F> {
+        assert_eq!(a.len(), b.len());
+        let (results, carries): (Vec<u64>, Vec<u64>) = zip(a, b)
+            .map(|(&a, &b)| match self.operation {
+                U32Operation::Add => {
+                    let (c, overflow) = a.overflowing_add(b);
+                    (c.into(), overflow.into())
+                }
+                U32Operation::Sub => {
+                    let (c, underflow) = a.overflowing_sub(b);
+                    (c.into(), underflow.into())
+                }
+                U32Operation::Mul => {
+                    let product = u64::from(a) * u64::from(b);
+                    (product & u64::from(u32::MAX), product >> 32)
+                }
+            })
+            .unzip();
+        let to_column = |values: Vec<u64>| {
+            let mut column = Vec::with_capacity_in(values.len(), GpuAllocator);
+            column.extend(values.into_iter().map(F::from));
+            column
+        };
+        let c = to_column(results);
+        let carry = to_column(carries);
+        let mut bytes = self.result_decomposition().witness(&c);
+        if self.operation == U32Operation::Mul {
+            bytes.extend(self.carry_decomposition().witness(&carry));
+        }
+        U32Witness { c, carry, bytes }
+    }
+}
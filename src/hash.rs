@@ -1,8 +1,22 @@
+use crate::merkle::build_merkle_nodes;
+use crate::merkle::HashedLeafConfig;
+use crate::serialize::write_bytes;
+use crate::serialize::ByteOrder;
 use crate::utils::SerdeOutput;
+use alloc::vec::Vec;
+use ark_ff::BigInteger;
 use ark_ff::Field;
+use ark_ff::PrimeField;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use ark_serialize::Compress;
+use ark_serialize::Read;
+use ark_serialize::SerializationError;
+use ark_serialize::Valid;
+use ark_serialize::Validate;
+use ark_serialize::Write;
 use core::fmt::Debug;
+use core::marker::PhantomData;
 use digest::Digest as _;
 use sha2::Sha256;
 
@@ -14,6 +28,9 @@ pub trait HashFn: Send + Sync + 'static {
     /// Collision resistance of the hash function measured in bits.
     const COLLISION_RESISTANCE: u32;
 
+    /// Short identifier of the hash function e.g. "sha256".
+    const NAME: &'static str;
+
     /// Returns a hash of the provided sequence of bytes.
     fn hash(bytes: impl IntoIterator<Item = u8>) -> Self::Digest;
 
@@ -27,8 +44,20 @@ pub trait HashFn: Send + Sync + 'static {
     /// Returns hash(`seed` || `value`). This method is intended for use in PRNG
     /// and proof-of-work contexts.
     fn merge_with_int(seed: &Self::Digest, value: u64) -> Self::Digest;
+
+    /// Returns the nodes of a Merkle tree with hashed leaves `leaves` (see
+    /// [`build_merkle_nodes`]). Hash functions with a GPU implementation can
+    /// override this to build the tree on the GPU.
+    fn build_merkle_nodes(leaves: &[Self::Digest]) -> Vec<Self::Digest> {
+        build_merkle_nodes::<HashedLeafConfig<Self>>(leaves)
+    }
 }
 
+/// Trees with fewer leaves are built on the CPU rather than being copied to
+/// the GPU
+#[cfg(all(feature = "webgpu", not(target_arch = "wasm32")))]
+const WEBGPU_MIN_LEAVES: usize = 1 << 12;
+
 /// Defines a cryptographic hash function for hashing field elements.
 ///
 /// This trait defines a hash procedure for a sequence of field elements. The
@@ -36,8 +65,107 @@ pub trait HashFn: Send + Sync + 'static {
 /// extension of the base field.
 /// Trait inspired by Winterfell: <https://github.com/facebook/winterfell/blob/main/crypto/src/hash/mod.rs#L55>
 pub trait ElementHashFn<F: Field>: HashFn {
+    /// Returns a hash of the provided field elements with each element written
+    /// in `byte_order` (see [`write_bytes`]).
+    fn hash_elements_in_order(
+        elements: impl IntoIterator<Item = F>,
+        byte_order: ByteOrder,
+    ) -> Self::Digest;
+
     /// Returns a hash of the provided field elements.
-    fn hash_elements(elements: impl IntoIterator<Item = F>) -> Self::Digest;
+    fn hash_elements(elements: impl IntoIterator<Item = F>) -> Self::Digest {
+        Self::hash_elements_in_order(elements, ByteOrder::Little)
+    }
+}
+
+/// Byte order of the elements written by a [`LeafEncoder`] or absorbed by a
+/// [`crate::random::PublicCoinImpl`]. Recorded in the proof header so verifiers
+/// know how elements were encoded.
+pub trait LeafByteOrder: Send + Sync + 'static {
+    const BYTE_ORDER: ByteOrder;
+}
+
+/// Defines how a row of field elements is encoded into bytes before it is
+/// hashed into a Merkle tree leaf. External verifiers need to match this exact
+/// format to check commitments.
+pub trait LeafEncoder<F: Field>: LeafByteOrder {
+    /// Appends the encoding of the elements to the buffer.
+    fn encode(elements: &[F], buffer: &mut Vec<u8>);
+}
+
+/// Encodes elements with their canonical arkworks serialization i.e.
+/// little-endian integers in the range `[0, p)`.
+pub struct CanonicalLeafEncoder;
+
+impl LeafByteOrder for CanonicalLeafEncoder {
+    const BYTE_ORDER: ByteOrder = ByteOrder::Little;
+}
+
+impl<F: Field> LeafEncoder<F> for CanonicalLeafEncoder {
+    fn encode(elements: &[F], buffer: &mut Vec<u8>) {
+        for element in elements {
+            write_bytes(element, ByteOrder::Little, buffer);
+        }
+    }
+}
+
+/// Encodes elements as big-endian integers in the range `[0, p)` for
+/// verifiers that expect network byte order.
+pub struct BigEndianLeafEncoder;
+
+impl LeafByteOrder for BigEndianLeafEncoder {
+    const BYTE_ORDER: ByteOrder = ByteOrder::Big;
+}
+
+impl<F: Field> LeafEncoder<F> for BigEndianLeafEncoder {
+    fn encode(elements: &[F], buffer: &mut Vec<u8>) {
+        for element in elements {
+            write_bytes(element, ByteOrder::Big, buffer);
+        }
+    }
+}
+
+/// Encodes elements with their in-memory representation. For arkworks fields
+/// this is the Montgomery form of the element (little-endian limbs on
+/// little-endian targets) which saves converting out of Montgomery form.
+pub struct MontgomeryLeafEncoder;
+
+impl LeafByteOrder for MontgomeryLeafEncoder {
+    const BYTE_ORDER: ByteOrder = ByteOrder::Little;
+}
+
+impl<F: Field> LeafEncoder<F> for MontgomeryLeafEncoder {
+    fn encode(elements: &[F], buffer: &mut Vec<u8>) {
+        // SAFETY: field elements are plain old data without any padding
+        let bytes = unsafe {
+            core::slice::from_raw_parts(
+                elements.as_ptr().cast::<u8>(),
+                core::mem::size_of_val(elements),
+            )
+        };
+        buffer.extend_from_slice(bytes);
+    }
+}
+
+/// Packs each base field element into four little-endian bytes. This halves
+/// the amount of data hashed for 31-bit fields (compared to 64-bit limbs).
+/// Only supports fields with a modulus that fits in 32 bits.
+pub struct PackedU32LeafEncoder;
+
+impl LeafByteOrder for PackedU32LeafEncoder {
+    const BYTE_ORDER: ByteOrder = ByteOrder::Little;
+}
+
+impl<F: Field> LeafEncoder<F> for PackedU32LeafEncoder {
+    fn encode(elements: &[F], buffer: &mut Vec<u8>) {
+        assert!(F::BasePrimeField::MODULUS_BIT_SIZE <= 32);
+        for element in elements {
+            for coeff in element.to_base_prime_field_elements() {
+                let value = coeff.into_bigint().as_ref()[0] as u32;
+                buffer.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
 }
 
 /// Defines output type for a cryptographic hash function.
@@ -53,6 +181,13 @@ pub trait Digest:
     /// on the possible digest size. For digests which are smaller than 32
     /// bytes, the unused bytes should be set to 0.
     fn as_bytes(&self) -> [u8; 32];
+
+    /// Returns the digest held by the first [`Digest::LEN`] bytes of `bytes`.
+    /// Inverse of [`Digest::as_bytes`].
+    fn from_bytes(bytes: &[u8; 32]) -> Self;
+
+    /// Number of leading bytes of [`Digest::as_bytes`] that hold the digest
+    const LEN: usize = 32;
 }
 
 pub struct Sha256HashFn;
@@ -62,6 +197,8 @@ impl HashFn for Sha256HashFn {
 
     const COLLISION_RESISTANCE: u32 = 128;
 
+    const NAME: &'static str = "sha256";
+
     fn hash(bytes: impl IntoIterator<Item = u8>) -> SerdeOutput<Sha256> {
         let mut hasher = Sha256::new();
         bytes.into_iter().for_each(|b| hasher.update([b]));
@@ -87,14 +224,225 @@ impl HashFn for Sha256HashFn {
         hasher.update(value.to_be_bytes());
         SerdeOutput::new(hasher.finalize())
     }
+
+    /// Builds large trees with the installed WebGPU context if there is one
+    /// (see [`ministark_gpu::webgpu::WebGpuContext::install`])
+    #[cfg(all(feature = "webgpu", not(target_arch = "wasm32")))]
+    fn build_merkle_nodes(leaves: &[SerdeOutput<Sha256>]) -> Vec<SerdeOutput<Sha256>> {
+        use ministark_gpu::webgpu::WebGpuContext;
+        match WebGpuContext::installed() {
+            Some(context) if leaves.len() >= WEBGPU_MIN_LEAVES => {
+                let leaves = leaves
+                    .iter()
+                    .map(Digest::as_bytes)
+                    .collect::<Vec<[u8; 32]>>();
+                let nodes = context.sha256_merkle_nodes_blocking(&leaves);
+                let nodes = nodes.into_iter().map(|node| SerdeOutput::new(node.into()));
+                nodes.collect()
+            }
+            _ => build_merkle_nodes::<HashedLeafConfig<Self>>(leaves),
+        }
+    }
 }
 
 impl<F: Field> ElementHashFn<F> for Sha256HashFn {
-    fn hash_elements(elements: impl IntoIterator<Item = F>) -> Self::Digest {
+    fn hash_elements_in_order(
+        elements: impl IntoIterator<Item = F>,
+        byte_order: ByteOrder,
+    ) -> Self::Digest {
+        let mut byte_buffer = Vec::new();
+        for element in elements {
+            write_bytes(&element, byte_order, &mut byte_buffer);
+        }
+        Self::hash_chunks([&*byte_buffer])
+    }
+}
+
+/// Blake3 hash function. Inputs larger than [`Blake3HashFn::PARALLEL_THRESHOLD`]
+/// are hashed with Blake3's multithreaded tree mode when the `parallel`
+/// feature is enabled. This is much faster than SHA256 for wide rows which is
+/// common when committing to LDEs over small fields.
+pub struct Blake3HashFn;
+
+impl Blake3HashFn {
+    /// Number of bytes at which point Blake3's multithreaded tree mode is used.
+    /// Blake3 recommends 128KiB.
+    pub const PARALLEL_THRESHOLD: usize = 1 << 17;
+
+    fn update(hasher: &mut blake3::Hasher, bytes: &[u8]) {
+        #[cfg(feature = "parallel")]
+        if bytes.len() >= Self::PARALLEL_THRESHOLD {
+            hasher.update_rayon(bytes);
+            return;
+        }
+        hasher.update(bytes);
+    }
+}
+
+impl HashFn for Blake3HashFn {
+    type Digest = SerdeOutput<blake3::Hasher>;
+
+    const COLLISION_RESISTANCE: u32 = 128;
+
+    const NAME: &'static str = "blake3";
+
+    fn hash(bytes: impl IntoIterator<Item = u8>) -> SerdeOutput<blake3::Hasher> {
+        let bytes = bytes.into_iter().collect::<Vec<u8>>();
+        Self::hash_chunks([&*bytes])
+    }
+
+    fn hash_chunks<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> SerdeOutput<blake3::Hasher> {
+        let mut hasher = blake3::Hasher::new();
+        chunks
+            .into_iter()
+            .for_each(|chunk| Self::update(&mut hasher, chunk));
+        SerdeOutput::new((*hasher.finalize().as_bytes()).into())
+    }
+
+    fn merge(
+        v0: &SerdeOutput<blake3::Hasher>,
+        v1: &SerdeOutput<blake3::Hasher>,
+    ) -> SerdeOutput<blake3::Hasher> {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&**v0);
+        hasher.update(&**v1);
+        SerdeOutput::new((*hasher.finalize().as_bytes()).into())
+    }
+
+    fn merge_with_int(
+        seed: &SerdeOutput<blake3::Hasher>,
+        value: u64,
+    ) -> SerdeOutput<blake3::Hasher> {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&**seed);
+        hasher.update(&value.to_be_bytes());
+        SerdeOutput::new((*hasher.finalize().as_bytes()).into())
+    }
+}
+
+impl<F: Field> ElementHashFn<F> for Blake3HashFn {
+    fn hash_elements_in_order(
+        elements: impl IntoIterator<Item = F>,
+        byte_order: ByteOrder,
+    ) -> Self::Digest {
         let mut byte_buffer = Vec::new();
         for element in elements {
-            element.serialize_uncompressed(&mut byte_buffer).unwrap();
+            write_bytes(&element, byte_order, &mut byte_buffer);
         }
         Self::hash_chunks([&*byte_buffer])
     }
 }
+
+/// Digest made of the first `N` bytes of another digest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TruncatedDigest<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> Default for TruncatedDigest<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> Digest for TruncatedDigest<N> {
+    fn as_bytes(&self) -> [u8; 32] {
+        let mut res = [0; 32];
+        res[..N].copy_from_slice(&self.0);
+        res
+    }
+
+    fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let mut res = [0; N];
+        res.copy_from_slice(&bytes[..N]);
+        Self(res)
+    }
+
+    const LEN: usize = N;
+}
+
+// serialized without a length prefix since the length is part of the type
+impl<const N: usize> CanonicalSerialize for TruncatedDigest<N> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        _compress: Compress,
+    ) -> Result<(), SerializationError> {
+        Ok(writer.write_all(&self.0)?)
+    }
+
+    fn serialized_size(&self, _compress: Compress) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> Valid for TruncatedDigest<N> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<const N: usize> CanonicalDeserialize for TruncatedDigest<N> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        _compress: Compress,
+        _validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let mut bytes = [0; N];
+        reader.read_exact(&mut bytes)?;
+        Ok(Self(bytes))
+    }
+}
+
+/// Hash function with outputs truncated to `N` bytes. Shrinks Merkle paths and
+/// commitments at the cost of collision resistance which drops to `4 * N`
+/// bits. Proofs record `N` as [`crate::ProofOptions::digest_len`].
+pub struct TruncatedHashFn<H, const N: usize>(PhantomData<H>);
+
+impl<H: HashFn, const N: usize> TruncatedHashFn<H, N> {
+    fn truncate(digest: &H::Digest) -> TruncatedDigest<N> {
+        assert!(
+            N <= H::Digest::LEN,
+            "can't truncate to more than the digest"
+        );
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&digest.as_bytes()[..N]);
+        TruncatedDigest(bytes)
+    }
+}
+
+impl<H: HashFn, const N: usize> HashFn for TruncatedHashFn<H, N> {
+    type Digest = TruncatedDigest<N>;
+
+    #[allow(clippy::cast_possible_truncation)]
+    const COLLISION_RESISTANCE: u32 = if H::COLLISION_RESISTANCE < 4 * N as u32 {
+        H::COLLISION_RESISTANCE
+    } else {
+        4 * N as u32
+    };
+
+    const NAME: &'static str = H::NAME;
+
+    fn hash(bytes: impl IntoIterator<Item = u8>) -> TruncatedDigest<N> {
+        Self::truncate(&H::hash(bytes))
+    }
+
+    fn hash_chunks<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> TruncatedDigest<N> {
+        Self::truncate(&H::hash_chunks(chunks))
+    }
+
+    fn merge(v0: &TruncatedDigest<N>, v1: &TruncatedDigest<N>) -> TruncatedDigest<N> {
+        Self::hash_chunks([&v0.0[..], &v1.0[..]])
+    }
+
+    fn merge_with_int(seed: &TruncatedDigest<N>, value: u64) -> TruncatedDigest<N> {
+        Self::hash_chunks([&seed.0[..], &value.to_be_bytes()[..]])
+    }
+}
+
+impl<F: Field, H: ElementHashFn<F>, const N: usize> ElementHashFn<F> for TruncatedHashFn<H, N> {
+    fn hash_elements_in_order(
+        elements: impl IntoIterator<Item = F>,
+        byte_order: ByteOrder,
+    ) -> Self::Digest {
+        Self::truncate(&H::hash_elements_in_order(elements, byte_order))
+    }
+}
@@ -0,0 +1,309 @@
+//! Interactive (non Fiat-Shamir) version of the STARK prover.
+//!
+//! Rather than drawing verifier randomness from a public coin the prover is
+//! exposed as a state machine and each round's randomness is supplied by the
+//! caller. This is useful for differential testing of the transcript,
+//! MPC-in-the-head experiments and for teaching the protocol step by step.
+//! Since the verifier samples the query positions directly there is no proof
+//! of work in this mode.
+use crate::air::AirConfig;
+use crate::challenges::Challenges;
+use crate::composer::DeepCompositionCoeffs;
+use crate::composer::DeepPolyComposer;
+use crate::context::CancellationToken;
+use crate::context::ProverContext;
+use crate::fri;
+use crate::fri::FriProof;
+use crate::fri::FriProver;
+use crate::hash::Digest;
+use crate::hints::Hints;
+use crate::ldt::Fri;
+use crate::ldt::LowDegreeTest;
+use crate::merkle::MerkleTree;
+use crate::prover::commit_base_trace;
+use crate::prover::commit_composition_trace;
+use crate::prover::commit_extension_trace;
+use crate::prover::commit_low_degree_test;
+use crate::prover::gen_composition_trace_polys;
+use crate::prover::open_traces;
+use crate::prover::TraceCommitment;
+use crate::stark::Stark;
+use crate::trace::Queries;
+use crate::Air;
+use crate::Matrix;
+use crate::Trace;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_poly::EvaluationDomain;
+use ministark_gpu::GpuField;
+use snafu::Snafu;
+
+/// The prover's cancellation token is never cancelled
+const NOT_CANCELLED: &str = "interactive runs can't be cancelled";
+
+/// Rounds of the interactive protocol in the order they must be executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Round {
+    BaseTrace,
+    ExtensionTrace,
+    CompositionTrace,
+    OodEvaluations,
+    Fri,
+    Queries,
+    Done,
+}
+
+pub struct InteractiveProver<'a, S: Stark> {
+    air: &'a Air<S::AirConfig>,
+    round: Round,
+    trace: Option<S::Trace>,
+    challenges: Option<Challenges<S::Fq>>,
+    hints: Option<Hints<S::Fq>>,
+    context: ProverContext,
+    base_trace_polys: Option<Matrix<S::Fp>>,
+    base_trace: Option<TraceCommitment<S::Fp, S::MerkleTree>>,
+    extension_trace_polys: Option<Matrix<S::Fq>>,
+    extension_trace: Option<TraceCommitment<S::Fq, S::MerkleTree>>,
+    composition_trace_polys: Option<Matrix<S::Fq>>,
+    composition_trace: Option<TraceCommitment<S::Fq, S::MerkleTree>>,
+    deep_poly_composer: Option<DeepPolyComposer<'a, S::AirConfig>>,
+    fri_prover: Option<FriProver<S::Fq, S::Digest, S::MerkleTree>>,
+}
+
+impl<'a, S: Stark> InteractiveProver<'a, S> {
+    /// Creates a prover that uses the resources of the
+    /// [`ProverContext::current`] context. Interactive runs can't be
+    /// cancelled so the context's cancellation token is replaced.
+    pub fn new(air: &'a Air<S::AirConfig>, trace: S::Trace) -> Self {
+        assert_eq!(air.trace_len(), trace.len());
+        Self {
+            air,
+            round: Round::BaseTrace,
+            trace: Some(trace),
+            challenges: None,
+            hints: None,
+            context: ProverContext::current().with_cancellation_token(CancellationToken::new()),
+            base_trace_polys: None,
+            base_trace: None,
+            extension_trace_polys: None,
+            extension_trace: None,
+            composition_trace_polys: None,
+            composition_trace: None,
+            deep_poly_composer: None,
+            fri_prover: None,
+        }
+    }
+
+    /// Returns the round the prover is waiting on
+    pub const fn round(&self) -> Round {
+        self.round
+    }
+
+    /// Returns the challenges supplied by the verifier (if supplied yet)
+    pub const fn challenges(&self) -> Option<&Challenges<S::Fq>> {
+        self.challenges.as_ref()
+    }
+
+    /// Returns the hints generated from the verifier's challenges
+    pub const fn hints(&self) -> Option<&Hints<S::Fq>> {
+        self.hints.as_ref()
+    }
+
+    fn advance(&mut self, expected: Round, next: Round) {
+        assert_eq!(self.round, expected, "expected round {expected:?}");
+        self.round = next;
+    }
+
+    /// Commits to the base trace and returns the commitment
+    pub fn commit_base_trace(&mut self) -> S::Digest {
+        self.advance(Round::BaseTrace, Round::ExtensionTrace);
+        let trace = self.trace.as_ref().unwrap();
+        let (base_trace_polys, base_trace) =
+            commit_base_trace::<S>(&self.context, self.air, trace).expect(NOT_CANCELLED);
+        let commitment = base_trace.tree.root();
+        self.base_trace_polys = Some(base_trace_polys);
+        self.base_trace = Some(base_trace);
+        commitment
+    }
+
+    /// Receives the verifier's AIR challenges and commits to the extension
+    /// trace. Returns `None` if the AIR has no extension columns.
+    pub fn commit_extension_trace(&mut self, challenges: Vec<S::Fq>) -> Option<S::Digest> {
+        self.advance(Round::ExtensionTrace, Round::CompositionTrace);
+        assert_eq!(self.air.num_challenges(), challenges.len());
+        let challenges = Challenges::new(challenges);
+        let hints = self.air.gen_hints(&challenges);
+
+        let trace = self.trace.take().unwrap();
+        let extension_trace = trace.build_extension_columns(&challenges);
+        let (extension_trace_polys, extension_trace) =
+            commit_extension_trace::<S>(&self.context, self.air, extension_trace.as_ref())
+                .expect(NOT_CANCELLED)
+                .unzip();
+        let commitment = extension_trace.as_ref().map(|t| t.tree.root());
+
+        self.challenges = Some(challenges);
+        self.hints = Some(hints);
+        self.extension_trace_polys = extension_trace_polys;
+        self.extension_trace = extension_trace;
+        commitment
+    }
+
+    /// Receives the verifier's composition constraint coefficients and commits
+    /// to the composition trace
+    pub fn commit_composition_trace(&mut self, composition_coeffs: Vec<S::Fq>) -> S::Digest {
+        self.advance(Round::CompositionTrace, Round::OodEvaluations);
+        let air = self.air;
+        assert_eq!(
+            air.num_composition_constraint_coeffs(),
+            composition_coeffs.len()
+        );
+
+        let composition_trace_polys = gen_composition_trace_polys(
+            &self.context,
+            air,
+            self.challenges.as_ref().unwrap(),
+            self.hints.as_ref().unwrap(),
+            &composition_coeffs,
+            &mut self.base_trace.as_mut().unwrap().lde,
+            self.extension_trace.as_mut().map(|t| &mut t.lde),
+        )
+        .expect(NOT_CANCELLED);
+        let composition_trace =
+            commit_composition_trace::<S>(&self.context, air, &composition_trace_polys)
+                .expect(NOT_CANCELLED);
+        let commitment = composition_trace.tree.root();
+
+        self.composition_trace_polys = Some(composition_trace_polys);
+        self.composition_trace = Some(composition_trace);
+        commitment
+    }
+
+    /// Receives the OOD point `z` from the verifier and returns the OOD
+    /// evaluations in the form `(execution_trace_evals,
+    /// composition_trace_evals)`
+    pub fn evaluate_ood(&mut self, z: S::Fq) -> (Vec<S::Fq>, Vec<S::Fq>) {
+        self.advance(Round::OodEvaluations, Round::Fri);
+        let mut deep_poly_composer = DeepPolyComposer::new(
+            self.air,
+            z,
+            self.base_trace_polys.take().unwrap(),
+            self.extension_trace_polys.take(),
+            self.composition_trace_polys.take().unwrap(),
+        );
+        let ood_evals = deep_poly_composer.get_ood_evals();
+        self.deep_poly_composer = Some(deep_poly_composer);
+        ood_evals
+    }
+
+    /// Receives the DEEP composition coefficients and builds the FRI layers.
+    /// `draw_alpha` is called with each FRI layer commitment and must return
+    /// the verifier's folding challenge for that layer. Returns the FRI layer
+    /// commitments and the coefficients of the remainder polynomial. The
+    /// prover can't be resumed after an error.
+    pub fn commit_fri_layers(
+        &mut self,
+        deep_coeffs: DeepCompositionCoeffs<S::Fq>,
+        draw_alpha: impl FnMut(&S::Digest) -> S::Fq,
+    ) -> Result<(Vec<S::Digest>, Vec<S::Fq>), InteractiveError> {
+        self.advance(Round::Fri, Round::Queries);
+        assert_eq!(
+            self.air.trace_arguments().len(),
+            deep_coeffs.execution_trace.len()
+        );
+        assert_eq!(
+            self.air.num_composition_trace_columns(),
+            deep_coeffs.composition_trace.len()
+        );
+        let deep_poly_composer = self.deep_poly_composer.take().unwrap();
+        let deep_composition_poly = deep_poly_composer.into_deep_poly(deep_coeffs);
+        let deep_composition_lde =
+            deep_composition_poly.into_bit_reversed_evaluations(self.air.lde_domain());
+
+        let mut channel = InteractiveFriChannel {
+            draw_alpha,
+            layer_commitments: Vec::new(),
+            remainder_coeffs: Vec::new(),
+            error: None,
+        };
+        let fri_prover = commit_low_degree_test::<S>(
+            self.air.options(),
+            &mut channel,
+            deep_composition_lde.try_into().unwrap(),
+            self.context.devices(),
+        );
+        if let Some(error) = channel.error {
+            return Err(error);
+        }
+        self.fri_prover = Some(fri_prover);
+        Ok((channel.layer_commitments, channel.remainder_coeffs))
+    }
+
+    /// Opens the trace and FRI commitments at the verifier's query positions.
+    /// A verifier using the crate's transcript should derive these with
+    /// [`crate::random::derive_query_positions`]
+    pub fn open(
+        &mut self,
+        query_positions: &[usize],
+    ) -> (Queries<S>, FriProof<S::Fq, S::Digest, S::MerkleTree>) {
+        self.advance(Round::Queries, Round::Done);
+        let lde_domain_size = self.air.lde_domain().size();
+        assert!(query_positions.iter().all(|&p| p < lde_domain_size));
+        let queries = open_traces(
+            self.base_trace.as_ref().unwrap(),
+            self.extension_trace.as_ref(),
+            self.composition_trace.as_ref().unwrap(),
+            query_positions,
+        );
+        let fri_prover = self.fri_prover.take().unwrap();
+        let fri_proof = <Fri as LowDegreeTest<S::Fq, S::Digest, S::MerkleTree>>::open(
+            fri_prover,
+            query_positions,
+        );
+        (queries, fri_proof)
+    }
+}
+
+/// Errors returned by the [`InteractiveProver`]
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum InteractiveError {
+    #[snafu(display("a FRI folding challenge was requested before any layer was committed"))]
+    MissingFriLayerCommitment,
+}
+
+/// FRI prover channel where the folding challenges are supplied externally
+struct InteractiveFriChannel<D, F, A: FnMut(&D) -> F> {
+    draw_alpha: A,
+    layer_commitments: Vec<D>,
+    remainder_coeffs: Vec<F>,
+    error: Option<InteractiveError>,
+}
+
+impl<D, F, A> fri::ProverChannel for InteractiveFriChannel<D, F, A>
+where
+    D: Digest,
+    F: GpuField + Field,
+    A: FnMut(&D) -> F,
+{
+    type Digest = D;
+    type Field = F;
+
+    fn commit_fri_layer(&mut self, layer_root: D) {
+        self.layer_commitments.push(layer_root);
+    }
+
+    fn commit_remainder(&mut self, remainder_coeffs: &[F]) {
+        self.remainder_coeffs = remainder_coeffs.to_vec();
+    }
+
+    fn draw_fri_alpha(&mut self) -> F {
+        // the folding challenge is drawn after its layer is committed. The
+        // error is returned once FRI finishes since the channel can't fail
+        let Some(commitment) = self.layer_commitments.last() else {
+            self.error
+                .get_or_insert(InteractiveError::MissingFriLayerCommitment);
+            return F::zero();
+        };
+        (self.draw_alpha)(commitment)
+    }
+}
@@ -0,0 +1,154 @@
+//! Low degree tests run on the DEEP composition polynomial
+//!
+//! FRI is the only low degree test at the moment. The prover and verifier
+//! only go through [`LowDegreeTest`] so other protocols can be added without
+//! changing how they're driven.
+
+use crate::fri::FriCommitments;
+use crate::fri::FriProof;
+use crate::fri::FriProver;
+use crate::fri::FriVerifier;
+use crate::fri::LayerProof;
+use crate::fri::ProverChannel;
+use crate::fri::VerificationError;
+use crate::hash::Digest;
+use crate::merkle::DeferredRowCheck;
+use crate::merkle::MatrixMerkleTree;
+use crate::merkle::MerkleTree;
+use crate::random::PublicCoin;
+use crate::utils::GpuVec;
+use crate::ProofOptions;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::domain::DomainCoeff;
+use ministark_gpu::GpuField;
+
+/// Proves evaluations over the LDE domain are of a low degree polynomial.
+/// The prover commits before the query positions are drawn and opens the
+/// commitments after. The verifier mirrors this by reading the commitments
+/// into the public coin before the positions are drawn.
+pub trait LowDegreeTest<F, D, M: MerkleTree> {
+    /// Committed state of the prover
+    type Prover;
+    /// Commitments the verifier read from a proof
+    type Verifier;
+    type Proof;
+    /// Part of a proof the verifier reads before the query positions are drawn
+    type Commitments;
+    /// Part of a proof the verifier reads after the query positions are drawn
+    type Openings;
+    type Error;
+
+    /// Commits to bit-reversed evaluations of a polynomial over the LDE
+    /// domain
+    fn commit(
+        options: ProofOptions,
+        channel: &mut impl ProverChannel<Field = F, Digest = D>,
+        evaluations: GpuVec<F>,
+    ) -> Self::Prover;
+
+    /// Opens the prover's commitments at the query positions
+    fn open(prover: Self::Prover, positions: &[usize]) -> Self::Proof;
+
+    /// Splits a proof into its commitments and openings
+    fn split_proof(proof: Self::Proof) -> (Self::Commitments, Self::Openings);
+
+    /// Reseeds the public coin with the commitments of a proof
+    fn read_commitments(
+        options: ProofOptions,
+        public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+        commitments: Self::Commitments,
+        max_poly_degree: usize,
+    ) -> Result<Self::Verifier, Self::Error>;
+
+    /// Checks the openings are consistent with the evaluations of a low
+    /// degree polynomial at the query positions
+    fn verify(
+        verifier: Self::Verifier,
+        openings: Self::Openings,
+        positions: &[usize],
+        evaluations: &[F],
+    ) -> Result<(), Self::Error>;
+
+    /// Same as [`Self::verify`] but returns the Merkle checks of the openings
+    /// rather than running them (see [`crate::merkle::verify_deferred`])
+    fn verify_deferred(
+        verifier: Self::Verifier,
+        openings: Self::Openings,
+        positions: &[usize],
+        evaluations: &[F],
+    ) -> Result<Vec<DeferredRowCheck<M, Self::Error>>, Self::Error>;
+}
+
+/// FRI as a [`LowDegreeTest`]
+pub struct Fri;
+
+impl<F, D, M> LowDegreeTest<F, D, M> for Fri
+where
+    F: GpuField + Field + DomainCoeff<F::FftField>,
+    F::FftField: FftField,
+    D: Digest,
+    M: MatrixMerkleTree<F, Root = D>,
+{
+    type Prover = FriProver<F, D, M>;
+    type Verifier = FriVerifier<F, D, M>;
+    type Proof = FriProof<F, D, M>;
+    type Commitments = FriCommitments<F, D>;
+    type Openings = Vec<LayerProof<F, D, M>>;
+    type Error = VerificationError;
+
+    fn commit(
+        options: ProofOptions,
+        channel: &mut impl ProverChannel<Field = F, Digest = D>,
+        evaluations: GpuVec<F>,
+    ) -> Self::Prover {
+        let mut prover = FriProver::new(options.into_fri_options());
+        prover.build_layers(channel, evaluations);
+        prover
+    }
+
+    fn open(prover: Self::Prover, positions: &[usize]) -> Self::Proof {
+        prover.into_proof(positions)
+    }
+
+    fn split_proof(proof: Self::Proof) -> (Self::Commitments, Self::Openings) {
+        proof.split()
+    }
+
+    fn read_commitments(
+        options: ProofOptions,
+        public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+        commitments: Self::Commitments,
+        max_poly_degree: usize,
+    ) -> Result<Self::Verifier, Self::Error> {
+        FriVerifier::from_commitments(
+            public_coin,
+            options.into_fri_options(),
+            commitments,
+            max_poly_degree,
+        )
+    }
+
+    fn verify(
+        verifier: Self::Verifier,
+        openings: Self::Openings,
+        positions: &[usize],
+        evaluations: &[F],
+    ) -> Result<(), Self::Error> {
+        verifier
+            .with_layers(openings)?
+            .verify(positions, evaluations)
+    }
+
+    fn verify_deferred(
+        verifier: Self::Verifier,
+        openings: Self::Openings,
+        positions: &[usize],
+        evaluations: &[F],
+    ) -> Result<Vec<DeferredRowCheck<M, Self::Error>>, Self::Error> {
+        verifier
+            .with_layers(openings)?
+            .verify_deferred(positions, evaluations)
+    }
+}
@@ -33,23 +33,46 @@
 #[macro_use]
 pub mod macros;
 pub mod air;
+pub mod arguments;
+pub mod cache;
 pub mod challenges;
 pub mod channel;
+#[cfg(feature = "experimental")]
+pub mod circle;
 pub mod composer;
 pub mod constraints;
+pub mod context;
+pub mod continuation;
 pub mod debug;
+pub mod degree_lowering;
+pub mod description;
 pub mod eval_cpu;
 pub mod eval_gpu;
 pub mod expression;
 pub mod fri;
+pub mod gadgets;
 pub mod hash;
 pub mod hints;
+pub mod interactive;
+pub mod ldt;
 pub mod matrix;
+pub mod memory;
 pub mod merkle;
+pub mod pcs;
 pub mod proof;
+pub mod profile;
 pub mod prover;
+pub mod public_inputs;
 pub mod random;
+pub mod reference;
+pub mod rom;
+pub mod serialize;
+pub mod sparse;
+pub mod stack;
 pub mod stark;
+#[cfg(feature = "experimental")]
+pub mod sumcheck;
+pub mod table;
 pub mod trace;
 pub mod utils;
 pub mod verifier;
@@ -77,19 +100,37 @@ use ministark_gpu::GpuField;
 use ministark_gpu::GpuFrom;
 use ministark_gpu::GpuMul;
 pub use proof::Proof;
+use snafu::Snafu;
+use stark::Stark;
+use verifier::VerifierCost;
 pub use trace::Trace;
 
 // TODO: include ability to specify:
 // - base field
 // - extension field
 // - hashing function
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Copy, CanonicalSerialize, CanonicalDeserialize, PartialEq, Eq)]
 pub struct ProofOptions {
+    /// Number of FRI queries. The trace and composition trace are opened at
+    /// the same positions since the verifier needs the DEEP composition
+    /// polynomial at each FRI query. Fewer trace queries would leave FRI
+    /// queries unchecked and more wouldn't add security so the two can't be
+    /// configured separately.
     pub num_queries: u8,
     pub lde_blowup_factor: u8,
     pub grinding_factor: u8,
     pub fri_folding_factor: u8,
     pub fri_max_remainder_coeffs: u8,
+    /// Number of bytes in the digests of Merkle trees and the public coin.
+    /// Must match the digest of the hash function e.g. 20 for
+    /// [`hash::TruncatedHashFn`] with `N = 20`.
+    #[cfg_attr(feature = "serde", serde(default = "ProofOptions::full_digest_len"))]
+    pub digest_len: u8,
+    /// Omits FRI layer values the verifier can recompute from proofs. See
+    /// [`FriOptions::with_compact`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub compact: bool,
 }
 
 impl ProofOptions {
@@ -98,6 +139,8 @@ impl ProofOptions {
     pub const MIN_BLOWUP_FACTOR: u8 = 1;
     pub const MAX_BLOWUP_FACTOR: u8 = 128;
     pub const MAX_GRINDING_FACTOR: u8 = 50;
+    pub const MIN_DIGEST_LEN: u8 = 16;
+    pub const MAX_DIGEST_LEN: u8 = 32;
 
     pub const fn new(
         num_queries: u8,
@@ -118,9 +161,160 @@ impl ProofOptions {
             grinding_factor,
             fri_folding_factor,
             fri_max_remainder_coeffs,
+            digest_len: Self::MAX_DIGEST_LEN,
+            compact: false,
         }
     }
 
+    /// Largest trace length [`ProofOptions::small`] is intended for
+    pub const SMALL_MAX_TRACE_LEN: usize = 1 << 12;
+
+    /// Returns options that minimize latency for traces with at most
+    /// [`ProofOptions::SMALL_MAX_TRACE_LEN`] rows e.g. for interactive demos
+    /// or per-request proofs in web services. A large folding factor keeps
+    /// the number of FRI layers small and the LDE domain stays below
+    /// [`utils::GPU_MIN_DOMAIN_SIZE`] so nothing is dispatched to the GPU.
+    /// Pair with [`context::ProverContext::low_latency`].
+    pub const fn small() -> Self {
+        Self::new(32, 8, 8, 8, 64)
+    }
+
+    /// Returns the options for digests truncated to `digest_len` bytes
+    pub const fn with_digest_len(self, digest_len: u8) -> Self {
+        assert!(digest_len >= Self::MIN_DIGEST_LEN);
+        assert!(digest_len <= Self::MAX_DIGEST_LEN);
+        Self { digest_len, ..self }
+    }
+
+    /// Returns the options for smaller proofs that omit values the verifier
+    /// can recompute
+    pub const fn with_compact(self, compact: bool) -> Self {
+        Self { compact, ..self }
+    }
+
+    #[cfg(feature = "serde")]
+    const fn full_digest_len() -> u8 {
+        Self::MAX_DIGEST_LEN
+    }
+
+    /// Returns the largest trace length supported by these options. The LDE
+    /// domain (of size `trace_len * lde_blowup_factor`) must fit in the
+    /// two-adic subgroup of `F`.
+    pub fn max_trace_len<F: FftField>(&self) -> usize {
+        let max_domain_size = if F::TWO_ADICITY >= usize::BITS {
+            1 << (usize::BITS - 1)
+        } else {
+            1 << F::TWO_ADICITY
+        };
+        max_domain_size / usize::from(self.lde_blowup_factor)
+    }
+
+    /// Checks these options can be used to prove a trace of length
+    /// `trace_len` over the field `F`
+    pub fn validate<F: FftField>(&self, trace_len: usize) -> Result<(), OptionsError> {
+        if !trace_len.is_power_of_two() {
+            return Err(OptionsError::TraceLenNotPowerOfTwo { trace_len });
+        }
+        let max_trace_len = self.max_trace_len::<F>();
+        if trace_len > max_trace_len {
+            return Err(OptionsError::DomainTooLarge {
+                trace_len,
+                blowup_factor: self.lde_blowup_factor,
+                max_trace_len,
+            });
+        }
+        if !matches!(self.fri_folding_factor, 2 | 4 | 8 | 16) {
+            return Err(OptionsError::UnsupportedFoldingFactor {
+                folding_factor: self.fri_folding_factor,
+            });
+        }
+        Ok(())
+    }
+
+    /// Largest grinding factor picked by
+    /// [`ProofOptions::target_max_proof_size`]. Grinding takes
+    /// `2^grinding_factor` hashes on average.
+    pub const TARGET_MAX_GRINDING_FACTOR: u8 = 20;
+
+    /// Returns options for proofs of `air` with at most `max_proof_size` bytes
+    /// and at least `security_level_bits` bits of conjectured security e.g. to
+    /// fit proofs in the calldata of an on-chain verifier. The number of
+    /// queries, grinding factor and FRI folding factor are tuned and the
+    /// remaining options are kept. The lowest grinding factor that meets the
+    /// target is picked since grinding slows down the prover. Proof sizes are
+    /// the upper bounds of [`verifier::VerifierCost::estimate`].
+    pub fn target_max_proof_size<S: Stark>(
+        self,
+        air: &Air<S::AirConfig>,
+        max_proof_size: usize,
+        security_level_bits: u32,
+    ) -> Result<Self, ProofSizeError> {
+        let trace_len = air.trace_len();
+        let options = Self {
+            fri_folding_factor: 2,
+            ..self
+        };
+        options
+            .validate::<S::Fp>(trace_len)
+            .map_err(|source| ProofSizeError::InvalidOptions { source })?;
+
+        let max_security_level_bits = proof::options_security_level_bits::<S>(
+            Self {
+                num_queries: Self::MAX_NUM_QUERIES,
+                grinding_factor: Self::TARGET_MAX_GRINDING_FACTOR,
+                ..self
+            },
+            trace_len,
+        );
+        if max_security_level_bits < security_level_bits {
+            return Err(ProofSizeError::InsufficientSecurity {
+                security_level_bits,
+                max_security_level_bits,
+            });
+        }
+
+        let security_per_query = self.lde_blowup_factor.ilog2();
+        let min_num_queries = |grinding_factor: u8| match security_level_bits
+            .saturating_sub(grinding_factor.into())
+        {
+            0 => Some(Self::MIN_NUM_QUERIES),
+            _ if security_per_query == 0 => None,
+            bits => u8::try_from(bits.div_ceil(security_per_query))
+                .ok()
+                .filter(|&num_queries| num_queries <= Self::MAX_NUM_QUERIES),
+        };
+
+        let mut smallest: Option<(usize, Self)> = None;
+        for grinding_factor in 0..=Self::TARGET_MAX_GRINDING_FACTOR {
+            let Some(num_queries) = min_num_queries(grinding_factor) else {
+                continue;
+            };
+            for fri_folding_factor in [2, 4, 8, 16] {
+                let options = Self {
+                    num_queries,
+                    grinding_factor,
+                    fri_folding_factor,
+                    ..self
+                };
+                let proof_size = VerifierCost::estimate::<S>(air, options).proof_bytes;
+                if smallest.map_or(true, |(smallest_size, _)| proof_size < smallest_size) {
+                    smallest = Some((proof_size, options));
+                }
+            }
+            if let Some((proof_size, options)) = smallest
+                && proof_size <= max_proof_size
+            {
+                return Ok(options);
+            }
+        }
+
+        Err(ProofSizeError::ProofTooLarge {
+            security_level_bits,
+            max_proof_size,
+            min_proof_size: smallest.map_or(usize::MAX, |(proof_size, _)| proof_size),
+        })
+    }
+
     pub fn into_fri_options(self) -> FriOptions {
         // TODO: move fri params into struct
         FriOptions::new(
@@ -128,9 +322,55 @@ impl ProofOptions {
             self.fri_folding_factor.into(),
             self.fri_max_remainder_coeffs.into(),
         )
+        .with_compact(self.compact)
     }
 }
 
+/// Errors for proof options that can't be used with a trace
+#[derive(Debug, Snafu)]
+pub enum OptionsError {
+    #[snafu(display("trace length must be a power of two, but `{trace_len}` was provided"))]
+    TraceLenNotPowerOfTwo { trace_len: usize },
+    #[snafu(display(
+        "trace length `{trace_len}` with blowup factor `{blowup_factor}` exceeds the field's \
+         two-adic subgroup. The maximum supported trace length is `{max_trace_len}`"
+    ))]
+    DomainTooLarge {
+        trace_len: usize,
+        blowup_factor: u8,
+        max_trace_len: usize,
+    },
+    #[snafu(display("FRI folding factor `{folding_factor}` is not supported"))]
+    UnsupportedFoldingFactor { folding_factor: u8 },
+}
+
+/// Errors for proof size targets that can't be met. See
+/// [`ProofOptions::target_max_proof_size`].
+#[derive(Debug, Snafu)]
+pub enum ProofSizeError {
+    #[snafu(display("{source}"))]
+    InvalidOptions { source: OptionsError },
+    #[snafu(display(
+        "`{security_level_bits}` bits of security were required but at most \
+         `{max_security_level_bits}` bits are possible with this field, hash function, digest \
+         length, blowup factor and trace length"
+    ))]
+    InsufficientSecurity {
+        security_level_bits: u32,
+        max_security_level_bits: u32,
+    },
+    #[snafu(display(
+        "the smallest proof with `{security_level_bits}` bits of security is around \
+         `{min_proof_size}` bytes which exceeds the target of `{max_proof_size}` bytes. Try a \
+         larger blowup factor, shorter digests or compact proofs"
+    ))]
+    ProofTooLarge {
+        security_level_bits: u32,
+        max_proof_size: usize,
+        min_proof_size: usize,
+    },
+}
+
 pub trait StarkExtensionOf<Fp: GpuFftField + FftField>:
     GpuField<FftField = Fp>
     + Field<BasePrimeField = Fp>
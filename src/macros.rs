@@ -51,3 +51,20 @@ macro_rules! forward_ref_op_assign {
         }
     };
 }
+
+/// Implements [`PublicInputs`](crate::public_inputs::PublicInputs) for a
+/// struct by encoding the listed fields in order
+///
+/// ```ignore
+/// impl_public_inputs!(Claim { program, input, output });
+/// ```
+#[macro_export]
+macro_rules! impl_public_inputs {
+    ($t:ty { $($field:ident),* $(,)? }) => {
+        impl $crate::public_inputs::PublicInputs for $t {
+            fn encode(&self, buffer: &mut ::std::vec::Vec<u8>) {
+                $($crate::public_inputs::PublicInputs::encode(&self.$field, buffer);)*
+            }
+        }
+    };
+}
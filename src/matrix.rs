@@ -1,6 +1,9 @@
 use crate::constraints::ExecutionTraceColumn;
+use crate::context::Device;
 use crate::hash::ElementHashFn;
 use crate::utils::horner_evaluate;
+#[cfg(feature = "gpu")]
+use crate::utils::use_gpu;
 use crate::utils::GpuAllocator;
 use crate::utils::GpuVec;
 use alloc::string::String;
@@ -17,8 +20,10 @@ use core::ops::Deref;
 use core::ops::DerefMut;
 use core::ops::Index;
 use core::ops::IndexMut;
+use core::ops::Range;
 use ministark_gpu::prelude::*;
 use ministark_gpu::utils::bit_reverse;
+use ministark_gpu::utils::bit_reverse_index;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
@@ -49,16 +54,13 @@ impl<F: Field> Matrix<F> {
     /// Creates a matrix from row-major list of arrays
     pub fn from_arrays<const NUM_COLS: usize>(rows: &[[F; NUM_COLS]]) -> Self {
         let num_rows = rows.len();
-        let num_cols = NUM_COLS;
-        let mut cols = (0..num_cols)
-            .map(|_| Vec::with_capacity_in(num_rows, GpuAllocator))
+        let cols = ark_std::cfg_into_iter!(0..NUM_COLS)
+            .map(|i| {
+                let mut col = Vec::with_capacity_in(num_rows, GpuAllocator);
+                col.extend(rows.iter().map(|row| row[i]));
+                col
+            })
             .collect::<Vec<GpuVec<F>>>();
-        // TODO: parallelise
-        for row in rows {
-            for (col, value) in cols.iter_mut().zip(row) {
-                col.push(*value);
-            }
-        }
         Self::new(cols)
     }
 
@@ -115,7 +117,6 @@ impl<F: Field> Matrix<F> {
         self
     }
 
-    #[cfg(not(feature = "gpu"))]
     fn into_polynomials_cpu(self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
         F: GpuField + DomainCoeff<F::FftField>,
@@ -147,10 +148,11 @@ impl<F: Field> Matrix<F> {
         // TODO: using the newtype pattern for type safety would be cool
         // i.e. take as input Matrix<Evaluations> and return Matrix<Polynomials>
         // https://doc.rust-lang.org/book/ch19-04-advanced-types.html
-        #[cfg(not(feature = "gpu"))]
-        return self.into_polynomials_cpu(domain);
         #[cfg(feature = "gpu")]
-        return self.into_polynomials_gpu(domain);
+        if use_gpu::<F>(domain.size()) {
+            return self.into_polynomials_gpu(domain);
+        }
+        self.into_polynomials_cpu(domain)
     }
 
     /// Interpolates the columns of the matrix over the domain
@@ -162,7 +164,6 @@ impl<F: Field> Matrix<F> {
         self.clone().into_polynomials(domain)
     }
 
-    #[cfg(not(feature = "gpu"))]
     fn into_evaluations_cpu(self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
     where
         F: GpuField + DomainCoeff<F::FftField>,
@@ -216,10 +217,11 @@ impl<F: Field> Matrix<F> {
         // TODO: using the newtype pattern for type safety would be cool
         // i.e. take as input Matrix<Polynomials> and return Matrix<Evaluations>
         // https://doc.rust-lang.org/book/ch19-04-advanced-types.html
-        #[cfg(not(feature = "gpu"))]
-        return self.into_evaluations_cpu(domain);
         #[cfg(feature = "gpu")]
-        return self.into_evaluations_gpu(domain);
+        if use_gpu::<F>(domain.size()) {
+            return self.into_evaluations_gpu(domain);
+        }
+        self.into_evaluations_cpu(domain)
     }
 
     pub fn into_bit_reversed_evaluations(self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
@@ -227,6 +229,13 @@ impl<F: Field> Matrix<F> {
         F: GpuField + DomainCoeff<F::FftField>,
         F::FftField: FftField,
     {
+        #[cfg(feature = "gpu")]
+        if use_gpu::<F>(domain.size()) {
+            let lde_bytes = self.num_cols() * domain.size() * core::mem::size_of::<F>();
+            if lde_bytes > get_planner().max_working_set_size() {
+                return self.bit_reversed_coset_evaluate(domain);
+            }
+        }
         let mut evaluations = self.into_evaluations(domain);
         // TODO: remove this and just do regular in-order->out-of-order CT FFT
         evaluations.bit_reverse_rows();
@@ -250,6 +259,136 @@ impl<F: Field> Matrix<F> {
         self.clone().into_bit_reversed_evaluations(domain)
     }
 
+    /// Returns the bit-reversed evaluations of the columns over `domain`. The
+    /// columns are split evenly between `devices` and each shard is evaluated
+    /// on its own GPU in parallel. Shards are joined in column order on the
+    /// host.
+    pub fn bit_reversed_evaluate_on(
+        &self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+        devices: &[Device],
+    ) -> Self
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        self.clone()
+            .into_bit_reversed_evaluations_on(domain, devices)
+    }
+
+    /// Like [`Self::bit_reversed_evaluate_on`] but evaluates the columns in
+    /// place. Columns that already have `domain.size()` values (e.g. zero
+    /// padded buffers from a [`crate::context::ScratchArena`]) aren't
+    /// reallocated.
+    pub fn into_bit_reversed_evaluations_on(
+        self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+        devices: &[Device],
+    ) -> Self
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        if devices.len() > 1 && self.num_cols() > 1 {
+            let shard_size = self.num_cols().div_ceil(devices.len());
+            let mut columns = self.0.into_iter();
+            let shards = core::iter::from_fn(|| {
+                let shard = columns.by_ref().take(shard_size).collect::<Vec<_>>();
+                (!shard.is_empty()).then(|| Self::new(shard))
+            })
+            .collect::<Vec<_>>();
+            let shards = std::thread::scope(|s| {
+                let handles = shards
+                    .into_iter()
+                    .zip(devices)
+                    .map(|(shard, device)| {
+                        s.spawn(move || {
+                            shard.into_bit_reversed_evaluations_on_device(domain, device)
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap())
+                    .collect()
+            });
+            return Self::join(shards);
+        }
+        self.into_bit_reversed_evaluations(domain)
+    }
+
+    /// Evaluates the columns with the FFT planner of `device`. Small domains
+    /// and builds without the `gpu` feature evaluate the columns on the host.
+    #[cfg_attr(not(feature = "gpu"), allow(unused_variables))]
+    fn into_bit_reversed_evaluations_on_device(
+        self,
+        domain: Radix2EvaluationDomain<F::FftField>,
+        device: &Device,
+    ) -> Self
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        #[cfg(feature = "gpu")]
+        if use_gpu::<F>(domain.size()) {
+            let mut evaluations = self;
+            let mut fft = device.planner().plan_fft(domain);
+            for column in &mut evaluations.0 {
+                column.resize(domain.size(), F::zero());
+                fft.encode(column);
+            }
+            fft.execute();
+            evaluations.bit_reverse_rows();
+            return evaluations;
+        }
+        self.into_bit_reversed_evaluations(domain)
+    }
+
+    /// Returns the bit-reversed evaluations of the columns over `domain`.
+    ///
+    /// The domain is split into cosets of the trace domain and each coset is
+    /// evaluated with an FFT of the trace length. The GPU only ever holds one
+    /// trace-sized buffer per column so LDEs larger than GPU memory can be
+    /// computed. In bit-reversed order the evaluations over coset `j` are the
+    /// contiguous chunk `bit_reverse(j)` of the LDE.
+    pub fn bit_reversed_coset_evaluate(&self, domain: Radix2EvaluationDomain<F::FftField>) -> Self
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        let n = self.num_rows();
+        let blowup_factor = domain.size() / n;
+        let trace_domain = Radix2EvaluationDomain::new(n).unwrap();
+        let mut lde = Self::new(
+            (0..self.num_cols())
+                .map(|_| {
+                    let mut col = Vec::with_capacity_in(domain.size(), GpuAllocator);
+                    col.resize(domain.size(), F::zero());
+                    col
+                })
+                .collect(),
+        );
+        let mut scratch = self.clone();
+        let mut coset_offset = domain.coset_offset();
+        for j in 0..blowup_factor {
+            for (scratch_col, col) in scratch.iter_mut().zip(&self.0) {
+                scratch_col.copy_from_slice(col);
+            }
+            let coset = trace_domain.get_coset(coset_offset).unwrap();
+            scratch = scratch.into_evaluations(coset);
+            scratch.bit_reverse_rows();
+            let chunk = match blowup_factor {
+                1 => 0,
+                _ => bit_reverse_index(blowup_factor, j) * n,
+            };
+            for (lde_col, scratch_col) in lde.iter_mut().zip(&scratch.0) {
+                lde_col[chunk..chunk + n].copy_from_slice(scratch_col);
+            }
+            coset_offset *= domain.group_gen();
+        }
+        lde
+    }
+
     // TODO: remove
     pub fn hash_rows<H: ElementHashFn<F>>(&self) -> Vec<H::Digest> {
         let num_rows = self.num_rows();
@@ -299,6 +438,11 @@ impl<F: Field> Matrix<F> {
         }
     }
 
+    /// Transposes the matrix into a row-major matrix
+    pub fn to_row_major(&self) -> RowMajorMatrix<F> {
+        RowMajorMatrix::from_rows_of(self, 0..self.num_rows())
+    }
+
     pub fn rows(&self) -> Vec<Vec<F>> {
         (0..self.num_rows())
             .map(|row| self.get_row(row).unwrap())
@@ -319,7 +463,6 @@ impl<F: Field> Matrix<F> {
             .collect()
     }
 
-    #[cfg(not(feature = "gpu"))]
     pub fn sum_columns_cpu(&self) -> Self {
         let n = self.num_rows();
         let mut accumulator = Vec::with_capacity_in(n, GpuAllocator);
@@ -387,10 +530,86 @@ impl<F: Field> Matrix<F> {
     where
         F: GpuField,
     {
-        #[cfg(not(feature = "gpu"))]
-        return self.sum_columns_cpu();
         #[cfg(feature = "gpu")]
-        return self.sum_columns_gpu();
+        if use_gpu::<F>(self.num_rows()) {
+            return self.sum_columns_gpu();
+        }
+        self.sum_columns_cpu()
+    }
+}
+
+/// Layout used when reading a [`Matrix`] row by row. FFTs want columns to be
+/// contiguous while hashing rows wants rows to be contiguous.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatrixLayout {
+    /// Read each row directly from the columns. Every value in a row comes from
+    /// a different column so reads are strided.
+    ColumnMajor,
+    /// Transpose blocks of rows into a row-major buffer before reading them
+    #[default]
+    RowMajor,
+}
+
+/// Number of rows transposed at a time by [`RowMajorMatrix::from_rows_of`]
+pub const TRANSPOSE_BLOCK_SIZE: usize = 64;
+
+/// Matrix with each row stored contiguously
+#[derive(Debug, Clone)]
+pub struct RowMajorMatrix<F> {
+    values: Vec<F>,
+    num_cols: usize,
+}
+
+impl<F: Field> RowMajorMatrix<F> {
+    /// Transposes a range of rows of a column-major matrix. Rows are copied in
+    /// blocks so reads from each column and writes to the buffer stay in
+    /// cache.
+    pub fn from_rows_of(matrix: &Matrix<F>, rows: Range<usize>) -> Self {
+        let num_cols = matrix.num_cols();
+        let mut values = vec![F::zero(); rows.len() * num_cols];
+        for block_start in rows.clone().step_by(TRANSPOSE_BLOCK_SIZE) {
+            let block_end = core::cmp::min(block_start + TRANSPOSE_BLOCK_SIZE, rows.end);
+            for (j, column) in matrix.0.iter().enumerate() {
+                for (i, value) in column[block_start..block_end].iter().enumerate() {
+                    values[(block_start - rows.start + i) * num_cols + j] = *value;
+                }
+            }
+        }
+        Self { values, num_cols }
+    }
+
+    pub const fn num_cols(&self) -> usize {
+        self.num_cols
+    }
+
+    pub fn num_rows(&self) -> usize {
+        if self.num_cols == 0 {
+            0
+        } else {
+            self.values.len() / self.num_cols
+        }
+    }
+
+    pub fn row(&self, i: usize) -> &[F] {
+        &self.values[i * self.num_cols..(i + 1) * self.num_cols]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[F]> {
+        (0..self.num_rows()).map(|i| self.row(i))
+    }
+
+    /// Transposes back into a column-major matrix
+    pub fn to_column_major(&self) -> Matrix<F> {
+        let num_rows = self.num_rows();
+        Matrix::new(
+            (0..self.num_cols)
+                .map(|j| {
+                    let mut column = Vec::with_capacity_in(num_rows, GpuAllocator);
+                    column.extend(self.rows().map(|row| row[j]));
+                    column
+                })
+                .collect(),
+        )
     }
 }
 
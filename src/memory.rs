@@ -0,0 +1,244 @@
+//! Read-write memory component for VM AIRs
+//!
+//! A VM records each memory access as a row `(address, clk, value, is_write)`
+//! in execution order. The same accesses are copied into a memory table sorted
+//! by address and then by clock where it's cheap to check that each read
+//! returns the value last written to its address. A permutation argument links
+//! the two.
+
+use crate::arguments::ArgumentChallenges;
+use crate::arguments::PermutationArgument;
+use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::ExecutionTraceColumn;
+use crate::expression::Expr;
+use crate::gadgets::ByteDecomposition;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::One;
+use ark_ff::PrimeField;
+use ministark_gpu::GpuFftField;
+
+/// Columns of a table of memory accesses
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryColumns {
+    pub address: usize,
+    pub clk: usize,
+    pub value: usize,
+    /// Binary column that is one for writes and zero for reads
+    pub is_write: usize,
+}
+
+impl MemoryColumns {
+    fn columns(self) -> Vec<usize> {
+        vec![self.address, self.clk, self.value, self.is_write]
+    }
+}
+
+/// A single memory access
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub address: u64,
+    pub clk: u64,
+    pub value: u64,
+    pub is_write: bool,
+}
+
+/// Read-write memory. Memory starts out zeroed and addresses must be accessed
+/// contiguously from the first address i.e. the sorted addresses increase by
+/// zero or one between rows.
+///
+/// Accesses to the same address must have strictly increasing clocks. The
+/// difference `clk' - clk - 1` is held in a clock jump column that's range
+/// checked with a [`ByteDecomposition`]. The range check is high degree so
+/// constraints should be passed through
+/// [`crate::degree_lowering::lower_degree`].
+#[derive(Clone, Debug)]
+pub struct RwMemory {
+    accesses: MemoryColumns,
+    sorted: MemoryColumns,
+    sorted_dummy_col: usize,
+    clock_jump: ByteDecomposition,
+    permutation: PermutationArgument,
+}
+
+/// Sorted memory table of a [`RwMemory`] generated by the prover
+pub struct RwMemoryWitness<F> {
+    /// Sorted columns in the order address, clk, value, is_write
+    pub sorted: [GpuVec<F>; 4],
+    /// One on padding rows at the end of the sorted table
+    pub dummy: GpuVec<F>,
+    pub clock_jump: GpuVec<F>,
+    /// Byte columns of the clock jump in the order of their column indices
+    pub clock_jump_bytes: Vec<GpuVec<F>>,
+}
+
+impl RwMemory {
+    /// Creates a memory component. `challenges` must have four weights and
+    /// the running products of the permutation argument are stored in the
+    /// execution trace columns `product_cols`. The value column of
+    /// `clock_jump` holds the clock jumps. Its width bounds the number of
+    /// cycles between two accesses to the same address.
+    pub fn new(
+        accesses: MemoryColumns,
+        sorted: MemoryColumns,
+        sorted_dummy_col: usize,
+        challenges: ArgumentChallenges,
+        product_cols: [usize; 2],
+        clock_jump: ByteDecomposition,
+    ) -> Self {
+        let permutation = PermutationArgument::new(
+            accesses.columns(),
+            sorted.columns(),
+            challenges,
+            product_cols,
+        )
+        .skip_rhs_when(sorted_dummy_col);
+        Self {
+            accesses,
+            sorted,
+            sorted_dummy_col,
+            clock_jump,
+            permutation,
+        }
+    }
+
+    /// Leaves rows of the VM where the binary `dummy_col` is one out of the
+    /// memory e.g. cycles that don't access memory
+    pub fn skip_accesses_when(mut self, dummy_col: usize) -> Self {
+        self.permutation = self.permutation.skip_lhs_when(dummy_col);
+        self
+    }
+
+    /// Columns of the accesses in execution order
+    pub const fn accesses(&self) -> MemoryColumns {
+        self.accesses
+    }
+
+    /// Constraints that apply to the first row
+    pub fn boundary_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let MemoryColumns {
+            value, is_write, ..
+        } = self.sorted;
+        let mut constraints = self.permutation.boundary_constraints();
+        // the first access reads zeroed memory unless it's a write
+        constraints.push(-(is_write.curr() - one) * value.curr());
+        constraints
+    }
+
+    /// Constraints that apply to every row
+    pub fn row_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let is_write = self.sorted.is_write;
+        let dummy = self.sorted_dummy_col;
+        let mut constraints = vec![
+            is_write.curr() * (is_write.curr() - one),
+            dummy.curr() * (dummy.curr() - one),
+        ];
+        constraints.extend(self.clock_jump.constraints());
+        constraints
+    }
+
+    /// Constraints that apply to every row except the last
+    pub fn transition_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let MemoryColumns {
+            address,
+            clk,
+            value,
+            is_write,
+        } = self.sorted;
+        let dummy = self.sorted_dummy_col;
+        let jump = self.clock_jump.value_column();
+        // constraints between two rows of the sorted table only apply if the
+        // next row isn't padding
+        let is_real = -(dummy.next() - one);
+        let address_step = address.next() - address.curr();
+        let is_same_address = -(address_step.clone() - one);
+        let is_next_read = -(is_write.next() - one);
+        let mut constraints = vec![
+            // addresses increase by zero or one
+            &is_real * &(&address_step * &(address_step.clone() - one)),
+            // padding rows are at the end
+            dummy.curr() * (dummy.next() - one),
+            // reads of a new address return zero
+            &is_real * &(&address_step * &(&is_next_read * &value.next())),
+            // reads of the same address return the last value
+            &is_real * &(&is_same_address * &(&is_next_read * &(value.next() - value.curr()))),
+            // accesses to the same address have increasing clocks
+            &is_real * &(&is_same_address * &(clk.next() - clk.curr() - one - jump.curr())),
+        ];
+        constraints.extend(self.permutation.transition_constraints());
+        constraints
+    }
+
+    /// Constraints that apply to the last row
+    pub fn terminal_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        self.permutation.terminal_constraints()
+    }
+
+    /// Generates the sorted memory table for accesses padded to `num_rows`
+    /// rows. Padding repeats the last access.
+    ///
+    /// # Panics
+    /// Panics if there are no accesses or more accesses than rows
+    pub fn gen_sorted_table<F: PrimeField>(
+        &self,
+        accesses: &[MemoryAccess],
+        num_rows: usize,
+    ) -> RwMemoryWitness<F> {
+        assert!(!accesses.is_empty());
+        assert!(accesses.len() <= num_rows);
+        let mut sorted_accesses = accesses.to_vec();
+        sorted_accesses.sort_by_key(|access| (access.address, access.clk));
+        let last_access = *sorted_accesses.last().unwrap();
+
+        let new_column = || Vec::with_capacity_in(num_rows, GpuAllocator);
+        let mut sorted = [new_column(), new_column(), new_column(), new_column()];
+        let mut dummy = new_column();
+        let mut clock_jump = new_column();
+        for row in 0..num_rows {
+            let access = sorted_accesses.get(row).unwrap_or(&last_access);
+            sorted[0].push(F::from(access.address));
+            sorted[1].push(F::from(access.clk));
+            sorted[2].push(F::from(access.value));
+            sorted[3].push(F::from(access.is_write));
+            dummy.push(F::from(row >= sorted_accesses.len()));
+            let jump = match sorted_accesses.get(row + 1) {
+                Some(next) if next.address == access.address => next.clk - access.clk - 1,
+                _ => 0,
+            };
+            clock_jump.push(F::from(jump));
+        }
+        let clock_jump_bytes = self.clock_jump.witness(&clock_jump);
+        RwMemoryWitness {
+            sorted,
+            dummy,
+            clock_jump,
+            clock_jump_bytes,
+        }
+    }
+
+    /// Generates the running product columns of the permutation argument
+    pub fn gen_extension_columns<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        base: &Matrix<Fp>,
+        challenges: &Challenges<Fq>,
+    ) -> Matrix<Fq> {
+        self.permutation.gen_extension_columns(base, challenges)
+    }
+}
@@ -1,11 +1,24 @@
+use crate::context::CancellationToken;
+use crate::hash::CanonicalLeafEncoder;
 use crate::hash::Digest;
-use crate::hash::ElementHashFn;
 use crate::hash::HashFn;
+use crate::hash::LeafByteOrder;
+use crate::hash::LeafEncoder;
+use crate::matrix::MatrixLayout;
+use crate::matrix::RowMajorMatrix;
+use crate::matrix::TRANSPOSE_BLOCK_SIZE;
+use crate::serialize::ByteOrder;
 use crate::Matrix;
 use alloc::vec::Vec;
 use ark_ff::Field;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use ark_serialize::Compress;
+use ark_serialize::Read;
+use ark_serialize::SerializationError;
+use ark_serialize::Valid;
+use ark_serialize::Validate;
+use ark_serialize::Write;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use snafu::Snafu;
@@ -13,6 +26,9 @@ use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::iter::zip;
 use std::marker::PhantomData;
+use subtle::Choice;
+use subtle::ConditionallySelectable;
+use subtle::ConstantTimeEq;
 
 /// Merkle tree error
 #[derive(Debug, Snafu)]
@@ -21,6 +37,8 @@ pub enum Error {
     TooFewLeaves { min: usize, actual: usize },
     #[snafu(display("number of leaves must be a power of two, but `{n}` were provided"))]
     NumberOfLeavesNotPowerOfTwo { n: usize },
+    #[snafu(display("expected `{expected}` leaves, but only `{actual}` were provided"))]
+    MissingLeaves { expected: usize, actual: usize },
     #[snafu(display("leaf index `{i}` cannot exceed the number of leaves (`{n}`)"))]
     LeafIndexOutOfBounds { i: usize, n: usize },
     #[snafu(display("proof is invalid"))]
@@ -41,15 +59,28 @@ pub trait MerkleTree: Sized + Send + Sync + Clone {
     /// Returns an error if the leaf index is out of bounds.
     fn prove(&self, indices: &[usize]) -> Result<Self::Proof, Error>;
 
-    /// Verifies a merkle proof
+    /// Verifies a merkle proof against a tree with `num_leaves` leaves
     ///
     /// # Errors
     ///
     /// This function returns an error if the proof fails verification.
-    fn verify(root: &Self::Root, proof: Self::Proof, indices: &[usize]) -> Result<(), Error>;
+    fn verify(
+        root: &Self::Root,
+        num_leaves: usize,
+        proof: Self::Proof,
+        indices: &[usize],
+    ) -> Result<(), Error>;
 
     /// Returns the number of security bits
     fn security_level_bits() -> u32;
+
+    /// Returns the name of the hash function used by the tree
+    fn hash_fn_name() -> &'static str;
+
+    /// Returns the byte order of field elements hashed into the leaves
+    fn byte_order() -> ByteOrder {
+        ByteOrder::Little
+    }
 }
 
 // TODO: all these merkle tree abstractions are way out of control. need to
@@ -62,7 +93,14 @@ pub trait MerkleTreeConfig: Send + Sync + Sized + 'static {
 
     fn hash_nodes(depth: u32, n0: &Self::Digest, n1: &Self::Digest) -> Self::Digest;
 
+    /// Returns the nodes of a tree over `leaves` (see [`build_merkle_nodes`])
+    fn build_nodes(leaves: &[Self::Leaf]) -> Vec<Self::Digest> {
+        build_merkle_nodes::<Self>(leaves)
+    }
+
     fn security_level_bits() -> u32;
+
+    fn hash_fn_name() -> &'static str;
 }
 
 /// Merkle View contains information needed to verify multiple Merkle paths.
@@ -103,6 +141,48 @@ impl<C: MerkleTreeConfig> Clone for MerkleTreeImpl<C> {
     }
 }
 
+// trees are serialized so they can be cached. See [`crate::cache`].
+impl<C: MerkleTreeConfig> CanonicalSerialize for MerkleTreeImpl<C> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.nodes.serialize_with_mode(&mut writer, compress)?;
+        self.leaves.serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.nodes.serialized_size(compress) + self.leaves.serialized_size(compress)
+    }
+}
+
+impl<C: MerkleTreeConfig> Valid for MerkleTreeImpl<C> {
+    fn check(&self) -> Result<(), SerializationError> {
+        let n = self.leaves.len();
+        if n < 2 || !n.is_power_of_two() || self.nodes.len() != n {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(())
+    }
+}
+
+impl<C: MerkleTreeConfig> CanonicalDeserialize for MerkleTreeImpl<C> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let nodes = Vec::deserialize_with_mode(&mut reader, compress, validate)?;
+        let leaves = Vec::deserialize_with_mode(reader, compress, validate)?;
+        let tree = Self { nodes, leaves };
+        if validate == Validate::Yes {
+            tree.check()?;
+        }
+        Ok(tree)
+    }
+}
+
 impl<C: MerkleTreeConfig> MerkleTreeImpl<C> {
     /// # Errors
     ///
@@ -122,7 +202,7 @@ impl<C: MerkleTreeConfig> MerkleTreeImpl<C> {
             return Err(Error::NumberOfLeavesNotPowerOfTwo { n });
         }
 
-        let nodes = build_merkle_nodes::<C>(&leaves);
+        let nodes = C::build_nodes(&leaves);
         Ok(Self { nodes, leaves })
     }
 
@@ -136,6 +216,97 @@ impl<C: MerkleTreeConfig> MerkleTreeImpl<C> {
     fn height(&self) -> u32 {
         self.leaves.len().ilog2()
     }
+
+    /// Returns the root the leaves and nodes of a proof hash to. Returns
+    /// `None` if there are no leaves.
+    pub fn recover_root(
+        proof: MerkleView<C::Digest, C::Leaf>,
+        indices: &[usize],
+    ) -> Result<Option<C::Digest>, Error> {
+        // the height comes from the proof so it can't be trusted
+        let height = proof.height;
+        if height == 0 || height >= usize::BITS {
+            return Err(Error::InvalidProof);
+        }
+        let num_leaves = 1 << height;
+        for &i in indices {
+            if i >= num_leaves {
+                return Err(Error::LeafIndexOutOfBounds { i, n: num_leaves });
+            }
+        }
+
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if indices.len() != proof.initial_leaves.len() {
+            return Err(Error::InvalidProof);
+        }
+
+        // handle leaves and specify the internal node indices
+        let mut node_queue = VecDeque::new();
+        let mut siblings = VecDeque::from_iter(proof.sibling_leaves);
+        let mut leaf_queue = zip(indices, proof.initial_leaves).collect::<VecDeque<_>>();
+        while let Some((index, leaf)) = leaf_queue.pop_front() {
+            let node_index = (num_leaves + index) >> 1;
+
+            if let Some((next_index, next_leaf)) = leaf_queue.front() {
+                let are_siblings = index ^ 1 == *next_index;
+                if are_siblings {
+                    let running_hash = C::hash_leaves(height - 1, &leaf, next_leaf);
+                    node_queue.push_back((node_index, running_hash));
+                    leaf_queue.pop_front();
+                    continue;
+                }
+            }
+
+            let sibling = siblings.pop_front().ok_or(Error::InvalidProof)?;
+            let running_hash = if index % 2 == 0 {
+                C::hash_leaves(height - 1, &leaf, &sibling)
+            } else {
+                C::hash_leaves(height - 1, &sibling, &leaf)
+            };
+            node_queue.push_back((node_index, running_hash));
+        }
+        if !siblings.is_empty() {
+            return Err(Error::InvalidProof);
+        }
+
+        // handle internal nodes
+        let mut nodes = VecDeque::from_iter(proof.nodes);
+        while let Some((index, hash)) = node_queue.pop_front() {
+            let depth = index.ilog2();
+
+            if depth == 0 {
+                assert!(node_queue.is_empty());
+                // unused nodes would make proofs malleable
+                return if nodes.is_empty() {
+                    Ok(Some(hash))
+                } else {
+                    Err(Error::InvalidProof)
+                };
+            }
+
+            if let Some((next_index, next_hash)) = node_queue.front() {
+                let are_siblings = index ^ 1 == *next_index;
+                if are_siblings {
+                    let running_hash = C::hash_nodes(depth - 1, &hash, next_hash);
+                    node_queue.push_back((index >> 1, running_hash));
+                    node_queue.pop_front();
+                    continue;
+                }
+            }
+
+            let sibling = nodes.pop_front().ok_or(Error::InvalidProof)?;
+            let running_hash = if index % 2 == 0 {
+                C::hash_nodes(depth - 1, &hash, &sibling)
+            } else {
+                C::hash_nodes(depth - 1, &sibling, &hash)
+            };
+            node_queue.push_back((index >> 1, running_hash));
+        }
+
+        Ok(None)
+    }
 }
 
 impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
@@ -208,132 +379,696 @@ impl<C: MerkleTreeConfig> MerkleTree for MerkleTreeImpl<C> {
 
     fn verify(
         root: &C::Digest,
+        num_leaves: usize,
         proof: MerkleView<C::Digest, C::Leaf>,
         indices: &[usize],
     ) -> Result<(), Error> {
-        let height = proof.height;
-        let num_leaves = 1 << height;
-        for &i in indices {
-            if i >= num_leaves {
-                return Err(Error::LeafIndexOutOfBounds { i, n: num_leaves });
-            }
+        if !num_leaves.is_power_of_two() || proof.height != num_leaves.ilog2() {
+            return Err(Error::InvalidProof);
+        }
+        match Self::recover_root(proof, indices)? {
+            Some(hash) if hash != *root => Err(Error::InvalidProof),
+            _ => Ok(()),
         }
+    }
 
-        let mut indices = indices.to_vec();
-        indices.sort_unstable();
-        indices.dedup();
+    fn security_level_bits() -> u32 {
+        C::security_level_bits()
+    }
 
-        // handle leaves and specify the internal node indices
-        let mut node_queue = VecDeque::new();
-        let mut siblings = VecDeque::from_iter(proof.sibling_leaves);
-        let mut leaf_queue = zip(indices, proof.initial_leaves).collect::<VecDeque<_>>();
-        while let Some((index, leaf)) = leaf_queue.pop_front() {
-            let node_index = (num_leaves + index) >> 1;
+    fn hash_fn_name() -> &'static str {
+        C::hash_fn_name()
+    }
+}
 
-            if let Some((next_index, next_leaf)) = leaf_queue.front() {
-                let are_siblings = index ^ 1 == *next_index;
-                if are_siblings {
-                    let running_hash = C::hash_leaves(height - 1, &leaf, next_leaf);
-                    node_queue.push_back((node_index, running_hash));
-                    leaf_queue.pop_front();
-                    continue;
-                }
+/// Merkle tree that supports proving/verifying rows of a matrix
+///
+/// Inspired by plonky3's MMCS
+/// <https://github.com/Plonky3/Plonky3/blob/main/commit/src/mmcs.rs>
+pub trait MatrixMerkleTree<T>: MerkleTree + Sized {
+    fn from_matrix(m: &Matrix<T>) -> Self {
+        Self::from_tagged_matrix(m, TreeTag::Untagged)
+    }
+
+    /// Commits to the rows of `m` with each row hashed together with `tag`
+    fn from_tagged_matrix(m: &Matrix<T>, tag: TreeTag) -> Self;
+
+    /// Same as [`Self::from_tagged_matrix`] but returns `None` if
+    /// `cancellation_token` is cancelled before the tree is built
+    fn from_tagged_matrix_cancellable(
+        m: &Matrix<T>,
+        tag: TreeTag,
+        cancellation_token: &CancellationToken,
+    ) -> Option<Self> {
+        (!cancellation_token.is_cancelled()).then(|| Self::from_tagged_matrix(m, tag))
+    }
+
+    fn prove_rows(&self, row_ids: &[usize]) -> Result<Self::Proof, Error> {
+        self.prove(row_ids)
+    }
+
+    /// Verifies rows of a tree over a matrix with `num_rows` rows
+    fn verify_rows(
+        root: &Self::Root,
+        num_rows: usize,
+        row_ids: &[usize],
+        rows: &[impl AsRef<[T]>],
+        proof: Self::Proof,
+    ) -> Result<(), Error> {
+        Self::verify_tagged_rows(root, num_rows, TreeTag::Untagged, row_ids, rows, proof)
+    }
+
+    /// Verifies rows of a tree built with [`Self::from_tagged_matrix`] over a
+    /// matrix with `num_rows` rows
+    fn verify_tagged_rows(
+        root: &Self::Root,
+        num_rows: usize,
+        tag: TreeTag,
+        row_ids: &[usize],
+        rows: &[impl AsRef<[T]>],
+        proof: Self::Proof,
+    ) -> Result<(), Error> {
+        let row_hashes = rows
+            .iter()
+            .map(|row| Self::hash_tagged_row(tag, row.as_ref()))
+            .collect();
+        Self::verify_row_hashes(root, num_rows, row_ids, row_hashes, proof)
+    }
+
+    /// Returns the hash of a row committed to by [`Self::from_tagged_matrix`]
+    fn hash_tagged_row(tag: TreeTag, row: &[T]) -> Self::Root;
+
+    /// Returns the hashes of the rows of `m` (see [`Self::hash_tagged_row`])
+    fn hash_tagged_rows(m: &Matrix<T>, tag: TreeTag) -> Vec<Self::Root>;
+
+    /// Builds a tree from the hashes of its rows. See
+    /// [`MatrixMerkleTreeBuilder`].
+    fn from_row_hashes(row_hashes: Vec<Self::Root>) -> Result<Self, Error>;
+
+    /// Same as [`Self::verify_tagged_rows`] but takes the hashes of the rows
+    /// (see [`Self::hash_tagged_row`]) so rows can be dropped once hashed
+    fn verify_row_hashes(
+        root: &Self::Root,
+        num_rows: usize,
+        row_ids: &[usize],
+        row_hashes: Vec<Self::Root>,
+        proof: Self::Proof,
+    ) -> Result<(), Error>;
+
+    /// Returns the root that `rows` and `proof` resolve to. Used to report
+    /// the actual root of proofs that fail verification. Returns `None` if
+    /// the tree doesn't support recovering roots.
+    fn recover_root(
+        _tag: TreeTag,
+        _row_ids: &[usize],
+        _rows: &[impl AsRef<[T]>],
+        _proof: Self::Proof,
+    ) -> Option<Self::Root> {
+        None
+    }
+}
+
+/// Merkle check of hashed rows that's run later. Lets a verifier collect the
+/// checks of many proofs and run them in a single pass (see
+/// [`verify_deferred`]).
+pub struct DeferredRowCheck<M: MerkleTree, E> {
+    root: M::Root,
+    num_rows: usize,
+    row_ids: Vec<usize>,
+    row_hashes: Vec<M::Root>,
+    proof: M::Proof,
+    verify: fn(&M::Root, usize, &[usize], Vec<M::Root>, M::Proof) -> Result<(), Error>,
+    error: E,
+}
+
+impl<M: MerkleTree, E> DeferredRowCheck<M, E> {
+    /// Defers [`MatrixMerkleTree::verify_row_hashes`]. Running the check
+    /// returns `error` if it fails.
+    pub fn new<T>(
+        root: M::Root,
+        num_rows: usize,
+        row_ids: Vec<usize>,
+        row_hashes: Vec<M::Root>,
+        proof: M::Proof,
+        error: E,
+    ) -> Self
+    where
+        M: MatrixMerkleTree<T>,
+    {
+        Self {
+            root,
+            num_rows,
+            row_ids,
+            row_hashes,
+            proof,
+            verify: <M as MatrixMerkleTree<T>>::verify_row_hashes,
+            error,
+        }
+    }
+
+    /// Runs the check
+    pub fn verify(self) -> Result<(), E> {
+        let Self {
+            root,
+            num_rows,
+            row_ids,
+            row_hashes,
+            proof,
+            verify,
+            error,
+        } = self;
+        verify(&root, num_rows, &row_ids, row_hashes, proof).map_err(|_| error)
+    }
+
+    /// Changes the error returned if the check fails
+    pub fn map_error<T>(self, f: impl FnOnce(E) -> T) -> DeferredRowCheck<M, T> {
+        DeferredRowCheck {
+            root: self.root,
+            num_rows: self.num_rows,
+            row_ids: self.row_ids,
+            row_hashes: self.row_hashes,
+            proof: self.proof,
+            verify: self.verify,
+            error: f(self.error),
+        }
+    }
+}
+
+/// Runs deferred checks, in parallel if the `parallel` feature is enabled.
+/// Returns the error of the first check that fails.
+pub fn verify_deferred<M: MerkleTree, E: Send>(
+    checks: Vec<DeferredRowCheck<M, E>>,
+) -> Result<(), E> {
+    let results: Vec<Result<(), E>> = ark_std::cfg_into_iter!(checks)
+        .map(DeferredRowCheck::verify)
+        .collect();
+    results.into_iter().collect()
+}
+
+/// Domain tag hashed with every row of a [`MatrixMerkleTree`] so rows opened
+/// against one of a proof's trees can't be opened against another
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TreeTag {
+    /// Rows are hashed on their own
+    #[default]
+    Untagged,
+    BaseTrace,
+    ExtensionTrace,
+    CompositionTrace,
+    FriLayer(usize),
+}
+
+impl TreeTag {
+    /// Appends the bytes that prefix the encoding of each row
+    pub fn encode(self, buffer: &mut Vec<u8>) {
+        match self {
+            Self::Untagged => {}
+            Self::BaseTrace => buffer.push(1),
+            Self::ExtensionTrace => buffer.push(2),
+            Self::CompositionTrace => buffer.push(3),
+            Self::FriLayer(layer) => {
+                buffer.push(4);
+                buffer.extend_from_slice(&(layer as u32).to_le_bytes());
             }
+        }
+    }
+}
 
-            let sibling = siblings.pop_front().unwrap();
-            let running_hash = if index % 2 == 0 {
-                C::hash_leaves(height - 1, &leaf, &sibling)
-            } else {
-                C::hash_leaves(height - 1, &sibling, &leaf)
-            };
-            node_queue.push_back((node_index, running_hash));
+/// Hashes the encoding of a row prefixed by `tag`. `buffer` is scratch space.
+fn hash_row<F: Field, H: HashFn, E: LeafEncoder<F>>(
+    tag: TreeTag,
+    row: &[F],
+    buffer: &mut Vec<u8>,
+) -> H::Digest {
+    buffer.clear();
+    tag.encode(buffer);
+    E::encode(row, buffer);
+    H::hash_chunks([&**buffer])
+}
+
+/// Merkle tree over the rows of a matrix. Rows are encoded into bytes with `E`
+/// and the leaves of the tree are the hashes of these encodings.
+pub struct MatrixMerkleTreeImpl<H: HashFn, E = CanonicalLeafEncoder> {
+    merkle_tree: MerkleTreeImpl<HashedLeafConfig<H>>,
+    _encoder: PhantomData<E>,
+}
+
+impl<H: HashFn, E> Clone for MatrixMerkleTreeImpl<H, E> {
+    fn clone(&self) -> Self {
+        Self {
+            merkle_tree: self.merkle_tree.clone(),
+            _encoder: PhantomData,
+        }
+    }
+}
+
+impl<H: HashFn, E: LeafByteOrder> CanonicalSerialize for MatrixMerkleTreeImpl<H, E> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.merkle_tree.serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.merkle_tree.serialized_size(compress)
+    }
+}
+
+impl<H: HashFn, E: LeafByteOrder> Valid for MatrixMerkleTreeImpl<H, E> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.merkle_tree.check()
+    }
+}
+
+impl<H: HashFn, E: LeafByteOrder> CanonicalDeserialize for MatrixMerkleTreeImpl<H, E> {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        Ok(Self {
+            merkle_tree: MerkleTreeImpl::deserialize_with_mode(reader, compress, validate)?,
+            _encoder: PhantomData,
+        })
+    }
+}
+
+impl<H: HashFn, E> MatrixMerkleTreeImpl<H, E> {
+    fn new(leaves: Vec<H::Digest>) -> Result<Self, Error> {
+        assert!(leaves.len().is_power_of_two());
+        Ok(Self {
+            merkle_tree: MerkleTreeImpl::new(leaves)?,
+            _encoder: PhantomData,
+        })
+    }
+}
+
+impl<H: HashFn, E: LeafByteOrder> MerkleTree for MatrixMerkleTreeImpl<H, E> {
+    type Proof = MerkleView<H::Digest, H::Digest>;
+    type Root = H::Digest;
+
+    fn root(&self) -> Self::Root {
+        self.merkle_tree.root()
+    }
+
+    fn prove(&self, indices: &[usize]) -> Result<Self::Proof, Error> {
+        self.merkle_tree.prove(indices)
+    }
+
+    fn verify(
+        root: &Self::Root,
+        num_leaves: usize,
+        proof: Self::Proof,
+        indices: &[usize],
+    ) -> Result<(), Error> {
+        MerkleTreeImpl::<HashedLeafConfig<H>>::verify(root, num_leaves, proof, indices)
+    }
+
+    fn security_level_bits() -> u32 {
+        H::COLLISION_RESISTANCE
+    }
+
+    fn hash_fn_name() -> &'static str {
+        H::NAME
+    }
+
+    fn byte_order() -> ByteOrder {
+        E::BYTE_ORDER
+    }
+}
+
+impl<F: Field, H: HashFn, E: LeafEncoder<F>> MatrixMerkleTree<F> for MatrixMerkleTreeImpl<H, E> {
+    fn from_tagged_matrix(m: &Matrix<F>, tag: TreeTag) -> Self {
+        Self::new(hash_tagged_rows::<F, H, E>(m, tag)).unwrap()
+    }
+
+    fn from_tagged_matrix_cancellable(
+        m: &Matrix<F>,
+        tag: TreeTag,
+        cancellation_token: &CancellationToken,
+    ) -> Option<Self> {
+        let row_hashes = hash_tagged_rows_cancellable::<F, H, E>(m, tag, cancellation_token)?;
+        Some(Self::new(row_hashes).unwrap())
+    }
+
+    fn hash_tagged_row(tag: TreeTag, row: &[F]) -> Self::Root {
+        hash_row::<F, H, E>(tag, row, &mut Vec::new())
+    }
+
+    fn hash_tagged_rows(m: &Matrix<F>, tag: TreeTag) -> Vec<Self::Root> {
+        hash_tagged_rows::<F, H, E>(m, tag)
+    }
+
+    fn from_row_hashes(row_hashes: Vec<Self::Root>) -> Result<Self, Error> {
+        Self::new(row_hashes)
+    }
+
+    fn verify_row_hashes(
+        root: &Self::Root,
+        num_rows: usize,
+        row_ids: &[usize],
+        row_hashes: Vec<Self::Root>,
+        proof: Self::Proof,
+    ) -> Result<(), Error> {
+        if row_ids.len() != row_hashes.len() {
+            return Err(Error::InvalidProof);
+        }
+        let (indices, initial_leaves) = sort_row_hashes(row_ids, row_hashes);
+        if proof.initial_leaves == initial_leaves {
+            Self::verify(root, num_rows, proof, &indices)
+        } else {
+            Err(Error::InvalidProof)
+        }
+    }
+
+    fn recover_root(
+        tag: TreeTag,
+        row_ids: &[usize],
+        rows: &[impl AsRef<[F]>],
+        mut proof: Self::Proof,
+    ) -> Option<Self::Root> {
+        let mut buffer = Vec::new();
+        let row_hashes = rows
+            .iter()
+            .map(|row| hash_row::<F, H, E>(tag, row.as_ref(), &mut buffer))
+            .collect();
+        let (indices, initial_leaves) = sort_row_hashes(row_ids, row_hashes);
+        proof.initial_leaves = initial_leaves;
+        MerkleTreeImpl::<HashedLeafConfig<H>>::recover_root(proof, &indices)
+            .ok()
+            .flatten()
+    }
+}
+
+/// Sorts and dedups the hashes of opened rows and returns their indices and
+/// hashes
+fn sort_row_hashes<D>(row_ids: &[usize], row_hashes: Vec<D>) -> (Vec<usize>, Vec<D>) {
+    // remove duplicates and sort
+    let mut instances = zip(row_ids.iter().copied(), row_hashes).collect::<Vec<_>>();
+    instances.sort_unstable_by_key(|&(i, _)| i);
+    instances.dedup_by_key(|&mut (i, _)| i);
+    instances.into_iter().unzip()
+}
+
+/// Builds a [`MatrixMerkleTree`] from chunks of rows. This allows committing
+/// to a matrix as its rows are produced (e.g. by the prover's LDE which is
+/// evaluated one coset at a time) rather than hashing the matrix once it's
+/// complete. Only row hashes are kept around. Rows must be appended in the
+/// order they are committed to.
+pub struct MatrixMerkleTreeBuilder<M: MerkleTree> {
+    num_rows: usize,
+    tag: TreeTag,
+    leaves: Vec<M::Root>,
+}
+
+impl<M: MerkleTree> MatrixMerkleTreeBuilder<M> {
+    pub fn new(num_rows: usize) -> Self {
+        assert!(num_rows.is_power_of_two());
+        Self {
+            num_rows,
+            tag: TreeTag::Untagged,
+            leaves: Vec::with_capacity(num_rows),
+        }
+    }
+
+    /// Hashes rows with `tag`. See [`MatrixMerkleTree::from_tagged_matrix`].
+    pub fn with_tag(mut self, tag: TreeTag) -> Self {
+        self.tag = tag;
+        self
+    }
+
+    /// Returns the number of rows that have been appended so far
+    pub fn num_appended_rows(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Hashes and appends a chunk of rows
+    pub fn append_rows<F: Field>(&mut self, rows: &Matrix<F>)
+    where
+        M: MatrixMerkleTree<F>,
+    {
+        let n = rows.num_rows();
+        if n == 0 {
+            return;
+        }
+        assert!(self.leaves.len() + n <= self.num_rows, "too many rows");
+        self.leaves.extend(M::hash_tagged_rows(rows, self.tag));
+    }
+
+    /// Hashes and appends a single row
+    pub fn append_row<F: Field>(&mut self, row: &[F])
+    where
+        M: MatrixMerkleTree<F>,
+    {
+        assert!(self.leaves.len() < self.num_rows, "too many rows");
+        self.leaves.push(M::hash_tagged_row(self.tag, row));
+    }
+
+    /// Builds the Merkle tree once all rows have been appended
+    pub fn finalize<F>(self) -> Result<M, Error>
+    where
+        M: MatrixMerkleTree<F>,
+    {
+        if self.leaves.len() != self.num_rows {
+            return Err(Error::MissingLeaves {
+                expected: self.num_rows,
+                actual: self.leaves.len(),
+            });
         }
-        assert!(siblings.is_empty());
+        M::from_row_hashes(self.leaves)
+    }
+}
+
+/// Merkle tree over the rows of a matrix where each leaf commits to `R`
+/// consecutive rows. Row `i` belongs to leaf `i / R` and a leaf is the hash of
+/// the row hashes it contains. For tall matrices this reduces the number of
+/// node hashes and shortens authentication paths at the cost of including the
+/// hashes of unqueried rows that share a leaf with a queried row in proofs.
+pub struct PackedMatrixMerkleTree<H: HashFn, E = CanonicalLeafEncoder, const R: usize = 4> {
+    row_hashes: Vec<H::Digest>,
+    merkle_tree: MerkleTreeImpl<HashedLeafConfig<H>>,
+    _encoder: PhantomData<E>,
+}
+
+/// Proof for a [`PackedMatrixMerkleTree`]
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalDeserialize, CanonicalSerialize)]
+pub struct PackedMerkleProof<D: Digest> {
+    pub view: MerkleView<D, D>,
+    /// Hashes of the rows in opened leaves that weren't queried
+    pub sibling_row_hashes: Vec<D>,
+}
+
+impl<H: HashFn, E, const R: usize> Clone for PackedMatrixMerkleTree<H, E, R> {
+    fn clone(&self) -> Self {
+        Self {
+            row_hashes: self.row_hashes.clone(),
+            merkle_tree: self.merkle_tree.clone(),
+            _encoder: PhantomData,
+        }
+    }
+}
+
+impl<H: HashFn, E, const R: usize> PackedMatrixMerkleTree<H, E, R> {
+    fn new(row_hashes: Vec<H::Digest>) -> Result<Self, Error> {
+        assert!(R.is_power_of_two());
+        if row_hashes.len() < R * 2 {
+            return Err(Error::TooFewLeaves {
+                min: R * 2,
+                actual: row_hashes.len(),
+            });
+        }
+        let leaves = row_hashes.chunks(R).map(hash_row_hashes::<H>).collect();
+        Ok(Self {
+            row_hashes,
+            merkle_tree: MerkleTreeImpl::new(leaves)?,
+            _encoder: PhantomData,
+        })
+    }
 
-        // handle internal nodes
-        let mut nodes = VecDeque::from_iter(proof.nodes);
-        while let Some((index, hash)) = node_queue.pop_front() {
-            let depth = index.ilog2();
+    fn leaf_row_hashes(&self, leaf_ids: &[usize]) -> Vec<H::Digest> {
+        leaf_ids
+            .iter()
+            .flat_map(|leaf| &self.row_hashes[leaf * R..(leaf + 1) * R])
+            .cloned()
+            .collect()
+    }
+}
 
-            if depth == 0 {
-                assert!(node_queue.is_empty());
-                // compare against the root
-                return if *root == hash {
-                    Ok(())
-                } else {
-                    Err(Error::InvalidProof)
-                };
-            }
+impl<H: HashFn, E: LeafByteOrder, const R: usize> MerkleTree for PackedMatrixMerkleTree<H, E, R> {
+    type Proof = PackedMerkleProof<H::Digest>;
+    type Root = H::Digest;
 
-            if let Some((next_index, next_hash)) = node_queue.front() {
-                let are_siblings = index ^ 1 == *next_index;
-                if are_siblings {
-                    let running_hash = C::hash_nodes(depth - 1, &hash, next_hash);
-                    node_queue.push_back((index >> 1, running_hash));
-                    node_queue.pop_front();
-                    continue;
-                }
-            }
+    fn root(&self) -> Self::Root {
+        self.merkle_tree.root()
+    }
 
-            let sibling = nodes.pop_front().unwrap();
-            let running_hash = if index % 2 == 0 {
-                C::hash_nodes(depth - 1, &hash, &sibling)
-            } else {
-                C::hash_nodes(depth - 1, &sibling, &hash)
-            };
-            node_queue.push_back((index >> 1, running_hash));
-        }
+    /// Proves the leaves at `indices`. Note these are leaf indices rather than
+    /// row indices.
+    fn prove(&self, indices: &[usize]) -> Result<Self::Proof, Error> {
+        let view = self.merkle_tree.prove(indices)?;
+        let mut leaf_ids = indices.to_vec();
+        leaf_ids.sort_unstable();
+        leaf_ids.dedup();
+        Ok(PackedMerkleProof {
+            view,
+            sibling_row_hashes: self.leaf_row_hashes(&leaf_ids),
+        })
+    }
 
-        Ok(())
+    fn verify(
+        root: &Self::Root,
+        num_leaves: usize,
+        proof: Self::Proof,
+        indices: &[usize],
+    ) -> Result<(), Error> {
+        let leaves = proof
+            .sibling_row_hashes
+            .chunks(R)
+            .map(hash_row_hashes::<H>)
+            .collect::<Vec<_>>();
+        if proof.view.initial_leaves != leaves {
+            return Err(Error::InvalidProof);
+        }
+        MerkleTreeImpl::<HashedLeafConfig<H>>::verify(root, num_leaves, proof.view, indices)
     }
 
     fn security_level_bits() -> u32 {
-        C::security_level_bits()
+        H::COLLISION_RESISTANCE
+    }
+
+    fn hash_fn_name() -> &'static str {
+        H::NAME
+    }
+
+    fn byte_order() -> ByteOrder {
+        E::BYTE_ORDER
     }
 }
 
-/// Merkle tree that supports proving/verifying rows of a matrix
-///
-/// Inspired by plonky3's MMCS
-/// <https://github.com/Plonky3/Plonky3/blob/main/commit/src/mmcs.rs>
-pub trait MatrixMerkleTree<T>: MerkleTree + Sized {
-    fn from_matrix(m: &Matrix<T>) -> Self;
+impl<F: Field, H: HashFn, E: LeafEncoder<F>, const R: usize> MatrixMerkleTree<F>
+    for PackedMatrixMerkleTree<H, E, R>
+{
+    fn from_tagged_matrix(m: &Matrix<F>, tag: TreeTag) -> Self {
+        Self::new(hash_tagged_rows::<F, H, E>(m, tag)).unwrap()
+    }
+
+    fn from_tagged_matrix_cancellable(
+        m: &Matrix<F>,
+        tag: TreeTag,
+        cancellation_token: &CancellationToken,
+    ) -> Option<Self> {
+        let row_hashes = hash_tagged_rows_cancellable::<F, H, E>(m, tag, cancellation_token)?;
+        Some(Self::new(row_hashes).unwrap())
+    }
 
     fn prove_rows(&self, row_ids: &[usize]) -> Result<Self::Proof, Error> {
-        self.prove(row_ids)
+        let mut row_ids = row_ids.to_vec();
+        row_ids.sort_unstable();
+        row_ids.dedup();
+        let mut leaf_ids = row_ids.iter().map(|i| i / R).collect::<Vec<_>>();
+        leaf_ids.dedup();
+        let view = self.merkle_tree.prove(&leaf_ids)?;
+        let sibling_row_hashes = leaf_ids
+            .iter()
+            .flat_map(|leaf| leaf * R..(leaf + 1) * R)
+            .filter(|i| row_ids.binary_search(i).is_err())
+            .map(|i| self.row_hashes[i].clone())
+            .collect();
+        Ok(PackedMerkleProof {
+            view,
+            sibling_row_hashes,
+        })
     }
 
-    fn verify_rows(
+    fn hash_tagged_row(tag: TreeTag, row: &[F]) -> Self::Root {
+        hash_row::<F, H, E>(tag, row, &mut Vec::new())
+    }
+
+    fn hash_tagged_rows(m: &Matrix<F>, tag: TreeTag) -> Vec<Self::Root> {
+        hash_tagged_rows::<F, H, E>(m, tag)
+    }
+
+    fn from_row_hashes(row_hashes: Vec<Self::Root>) -> Result<Self, Error> {
+        Self::new(row_hashes)
+    }
+
+    fn verify_row_hashes(
         root: &Self::Root,
+        num_rows: usize,
         row_ids: &[usize],
-        rows: &[impl AsRef<[T]>],
+        row_hashes: Vec<Self::Root>,
         proof: Self::Proof,
-    ) -> Result<(), Error>;
+    ) -> Result<(), Error> {
+        if row_ids.len() != row_hashes.len() || num_rows % R != 0 {
+            return Err(Error::InvalidProof);
+        }
+        let (row_ids, row_hashes) = sort_row_hashes(row_ids, row_hashes);
+
+        let mut leaf_ids = row_ids.iter().map(|i| i / R).collect::<Vec<_>>();
+        leaf_ids.dedup();
+
+        // reconstruct the row hashes of each opened leaf
+        let mut instances = zip(row_ids, row_hashes).peekable();
+        let mut sibling_row_hashes = proof.sibling_row_hashes.into_iter();
+        let mut leaves = Vec::new();
+        for &leaf in &leaf_ids {
+            let mut leaf_row_hashes = Vec::with_capacity(R);
+            for i in leaf * R..(leaf + 1) * R {
+                let row_hash = match instances.next_if(|&(row_id, _)| row_id == i) {
+                    Some((_, row_hash)) => row_hash,
+                    None => sibling_row_hashes.next().ok_or(Error::InvalidProof)?,
+                };
+                leaf_row_hashes.push(row_hash);
+            }
+            leaves.push(hash_row_hashes::<H>(&leaf_row_hashes));
+        }
+
+        if sibling_row_hashes.next().is_some() || proof.view.initial_leaves != leaves {
+            return Err(Error::InvalidProof);
+        }
+        MerkleTreeImpl::<HashedLeafConfig<H>>::verify(root, num_rows / R, proof.view, &leaf_ids)
+    }
+}
+
+fn hash_row_hashes<H: HashFn>(row_hashes: &[H::Digest]) -> H::Digest {
+    let bytes = row_hashes.iter().map(Digest::as_bytes).collect::<Vec<_>>();
+    H::hash_chunks(bytes.iter().map(|b| &b[..]))
 }
 
-pub struct MatrixMerkleTreeImpl<H: HashFn> {
+/// Merkle tree over the rows of a matrix with proofs that are verified in
+/// constant time with respect to the queried rows. Every query opens its full
+/// authentication path so the shape of a proof doesn't depend on which rows
+/// are queried, paths are hashed without branching on the row index and
+/// digests are compared in constant time. Proofs are larger than those of
+/// [`MatrixMerkleTreeImpl`] since nodes shared between paths are repeated.
+pub struct ConstantTimeMatrixMerkleTree<H: HashFn, E = CanonicalLeafEncoder> {
     merkle_tree: MerkleTreeImpl<HashedLeafConfig<H>>,
+    _encoder: PhantomData<E>,
+}
+
+/// Proof for a [`ConstantTimeMatrixMerkleTree`]
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalDeserialize, CanonicalSerialize)]
+pub struct ConstantTimeMerkleProof<D: Digest> {
+    /// Leaf of each query in the order of the queries
+    pub leaves: Vec<D>,
+    /// Authentication path of each query from the leaf's sibling to the
+    /// root's child
+    pub paths: Vec<Vec<D>>,
 }
 
-impl<H: HashFn> Clone for MatrixMerkleTreeImpl<H> {
+impl<H: HashFn, E> Clone for ConstantTimeMatrixMerkleTree<H, E> {
     fn clone(&self) -> Self {
         Self {
             merkle_tree: self.merkle_tree.clone(),
+            _encoder: PhantomData,
         }
     }
 }
 
-impl<H: HashFn> MatrixMerkleTreeImpl<H> {
-    fn new(leaves: Vec<H::Digest>) -> Result<Self, Error> {
-        assert!(leaves.len().is_power_of_two());
-        Ok(Self {
-            merkle_tree: MerkleTreeImpl::new(leaves)?,
-        })
-    }
-}
-
-impl<H: HashFn> MerkleTree for MatrixMerkleTreeImpl<H> {
-    type Proof = MerkleView<H::Digest, H::Digest>;
+impl<H: HashFn, E: LeafByteOrder> MerkleTree for ConstantTimeMatrixMerkleTree<H, E> {
+    type Proof = ConstantTimeMerkleProof<H::Digest>;
     type Root = H::Digest;
 
     fn root(&self) -> Self::Root {
@@ -341,49 +1076,157 @@ impl<H: HashFn> MerkleTree for MatrixMerkleTreeImpl<H> {
     }
 
     fn prove(&self, indices: &[usize]) -> Result<Self::Proof, Error> {
-        self.merkle_tree.prove(indices)
+        let MerkleTreeImpl { nodes, leaves } = &self.merkle_tree;
+        let num_leaves = leaves.len();
+        let mut proof = ConstantTimeMerkleProof {
+            leaves: Vec::new(),
+            paths: Vec::new(),
+        };
+        for &i in indices {
+            if i >= num_leaves {
+                return Err(Error::LeafIndexOutOfBounds { i, n: num_leaves });
+            }
+            let mut path = vec![leaves[i ^ 1].clone()];
+            let mut node = (num_leaves + i) >> 1;
+            while node > 1 {
+                path.push(nodes[node ^ 1].clone());
+                node >>= 1;
+            }
+            proof.leaves.push(leaves[i].clone());
+            proof.paths.push(path);
+        }
+        Ok(proof)
     }
 
-    fn verify(root: &Self::Root, proof: Self::Proof, indices: &[usize]) -> Result<(), Error> {
-        MerkleTreeImpl::<HashedLeafConfig<H>>::verify(root, proof, indices)
+    fn verify(
+        root: &Self::Root,
+        num_leaves: usize,
+        proof: Self::Proof,
+        indices: &[usize],
+    ) -> Result<(), Error> {
+        let ConstantTimeMerkleProof { leaves, paths } = proof;
+        // the number of queries and height of the tree are public
+        if !num_leaves.is_power_of_two() {
+            return Err(Error::InvalidProof);
+        }
+        let height = num_leaves.ilog2() as usize;
+        if leaves.len() != indices.len()
+            || paths.len() != indices.len()
+            || paths.iter().any(|path| path.len() != height)
+        {
+            return Err(Error::InvalidProof);
+        }
+        let root = root.as_bytes();
+        let mut is_valid = Choice::from(1);
+        for ((&index, leaf), path) in zip(indices, leaves).zip(paths) {
+            // branching here only reveals that a query is malformed
+            if index >= num_leaves {
+                return Err(Error::LeafIndexOutOfBounds {
+                    i: index,
+                    n: num_leaves,
+                });
+            }
+            let mut hash = leaf;
+            for (depth, sibling) in path.iter().enumerate() {
+                #[allow(clippy::cast_possible_truncation)]
+                let is_right = Choice::from(((index >> depth) & 1) as u8);
+                let (left, right) = conditional_swap(&hash, sibling, is_right);
+                hash = H::merge(&left, &right);
+            }
+            is_valid &= hash.as_bytes().ct_eq(&root);
+        }
+        if bool::from(is_valid) {
+            Ok(())
+        } else {
+            Err(Error::InvalidProof)
+        }
     }
 
     fn security_level_bits() -> u32 {
         H::COLLISION_RESISTANCE
     }
+
+    fn hash_fn_name() -> &'static str {
+        H::NAME
+    }
+
+    fn byte_order() -> ByteOrder {
+        E::BYTE_ORDER
+    }
 }
 
-impl<F: Field, H: ElementHashFn<F> + Send + Sync + 'static> MatrixMerkleTree<F>
-    for MatrixMerkleTreeImpl<H>
+impl<F: Field, H: HashFn, E: LeafEncoder<F>> MatrixMerkleTree<F>
+    for ConstantTimeMatrixMerkleTree<H, E>
 {
-    fn from_matrix(m: &Matrix<F>) -> Self {
-        Self::new(hash_rows::<F, H>(m)).unwrap()
+    fn from_tagged_matrix(m: &Matrix<F>, tag: TreeTag) -> Self {
+        Self {
+            merkle_tree: MerkleTreeImpl::new(hash_tagged_rows::<F, H, E>(m, tag)).unwrap(),
+            _encoder: PhantomData,
+        }
     }
 
-    fn verify_rows(
+    fn from_tagged_matrix_cancellable(
+        m: &Matrix<F>,
+        tag: TreeTag,
+        cancellation_token: &CancellationToken,
+    ) -> Option<Self> {
+        let row_hashes = hash_tagged_rows_cancellable::<F, H, E>(m, tag, cancellation_token)?;
+        Some(Self {
+            merkle_tree: MerkleTreeImpl::new(row_hashes).unwrap(),
+            _encoder: PhantomData,
+        })
+    }
+
+    fn hash_tagged_row(tag: TreeTag, row: &[F]) -> Self::Root {
+        hash_row::<F, H, E>(tag, row, &mut Vec::new())
+    }
+
+    fn hash_tagged_rows(m: &Matrix<F>, tag: TreeTag) -> Vec<Self::Root> {
+        hash_tagged_rows::<F, H, E>(m, tag)
+    }
+
+    fn from_row_hashes(row_hashes: Vec<Self::Root>) -> Result<Self, Error> {
+        Ok(Self {
+            merkle_tree: MerkleTreeImpl::new(row_hashes)?,
+            _encoder: PhantomData,
+        })
+    }
+
+    fn verify_row_hashes(
         root: &Self::Root,
+        num_rows: usize,
         row_ids: &[usize],
-        rows: &[impl AsRef<[F]>],
+        row_hashes: Vec<Self::Root>,
         proof: Self::Proof,
     ) -> Result<(), Error> {
-        // remove duplicates and sort
-        let mut instances = zip(row_ids, rows).collect::<Vec<_>>();
-        instances.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
-        instances.dedup_by(|(a, _), (b, _)| a == b);
-
-        let (indices, rows): (Vec<_>, Vec<_>) = instances.into_iter().unzip();
-        let initial_leaves = rows
-            .iter()
-            .map(|r| H::hash_elements(r.as_ref().iter().copied()))
-            .collect::<Vec<_>>();
-        if proof.initial_leaves == initial_leaves {
-            Self::verify(root, proof, &indices)
+        if row_hashes.len() != proof.leaves.len() {
+            return Err(Error::InvalidProof);
+        }
+        let mut leaves_match = Choice::from(1);
+        for (row_hash, leaf) in zip(&row_hashes, &proof.leaves) {
+            leaves_match &= row_hash.as_bytes().ct_eq(&leaf.as_bytes());
+        }
+        // verify the paths even if the leaves don't match to keep timing uniform
+        let paths_valid = Self::verify(root, num_rows, proof, row_ids);
+        if bool::from(leaves_match) {
+            paths_valid
         } else {
             Err(Error::InvalidProof)
         }
     }
 }
 
+/// Returns `(b, a)` if `swap` is set and `(a, b)` otherwise without branching
+/// on `swap`
+fn conditional_swap<D: Digest>(a: &D, b: &D, swap: Choice) -> (D, D) {
+    let mut a = a.as_bytes();
+    let mut b = b.as_bytes();
+    for (a, b) in zip(&mut a, &mut b) {
+        u8::conditional_swap(a, b, swap);
+    }
+    (D::from_bytes(&a), D::from_bytes(&b))
+}
+
 pub struct HashedLeafConfig<H: HashFn>(PhantomData<H>);
 
 impl<H: HashFn> Clone for HashedLeafConfig<H> {
@@ -404,12 +1247,71 @@ impl<H: HashFn> MerkleTreeConfig for HashedLeafConfig<H> {
         H::merge(n0, n1)
     }
 
+    fn build_nodes(leaves: &[H::Digest]) -> Vec<H::Digest> {
+        H::build_merkle_nodes(leaves)
+    }
+
     fn security_level_bits() -> u32 {
         H::COLLISION_RESISTANCE
     }
+
+    fn hash_fn_name() -> &'static str {
+        H::NAME
+    }
+}
+
+pub fn hash_rows<F: Field, H: HashFn, E: LeafEncoder<F>>(matrix: &Matrix<F>) -> Vec<H::Digest> {
+    hash_tagged_rows::<F, H, E>(matrix, TreeTag::Untagged)
+}
+
+/// Hashes the rows of a matrix with each row prefixed by `tag`
+pub fn hash_tagged_rows<F: Field, H: HashFn, E: LeafEncoder<F>>(
+    matrix: &Matrix<F>,
+    tag: TreeTag,
+) -> Vec<H::Digest> {
+    hash_tagged_rows_with_layout::<F, H, E>(matrix, tag, MatrixLayout::default())
+}
+
+/// Hashes the rows of a matrix. `layout` controls how rows are read from the
+/// matrix and doesn't change the result.
+pub fn hash_rows_with_layout<F: Field, H: HashFn, E: LeafEncoder<F>>(
+    matrix: &Matrix<F>,
+    layout: MatrixLayout,
+) -> Vec<H::Digest> {
+    hash_tagged_rows_with_layout::<F, H, E>(matrix, TreeTag::Untagged, layout)
+}
+
+/// See [`hash_tagged_rows`] and [`hash_rows_with_layout`]
+pub fn hash_tagged_rows_with_layout<F: Field, H: HashFn, E: LeafEncoder<F>>(
+    matrix: &Matrix<F>,
+    tag: TreeTag,
+    layout: MatrixLayout,
+) -> Vec<H::Digest> {
+    hash_rows_impl::<F, H, E>(matrix, tag, layout, None).unwrap()
+}
+
+/// Same as [`hash_tagged_rows`] but returns `None` if `cancellation_token` is
+/// cancelled. The token is checked before each block of rows is hashed.
+pub fn hash_tagged_rows_cancellable<F: Field, H: HashFn, E: LeafEncoder<F>>(
+    matrix: &Matrix<F>,
+    tag: TreeTag,
+    cancellation_token: &CancellationToken,
+) -> Option<Vec<H::Digest>> {
+    hash_rows_impl::<F, H, E>(
+        matrix,
+        tag,
+        MatrixLayout::default(),
+        Some(cancellation_token),
+    )
 }
 
-pub fn hash_rows<F: Field, H: ElementHashFn<F>>(matrix: &Matrix<F>) -> Vec<H::Digest> {
+fn hash_rows_impl<F: Field, H: HashFn, E: LeafEncoder<F>>(
+    matrix: &Matrix<F>,
+    tag: TreeTag,
+    layout: MatrixLayout,
+    cancellation_token: Option<&CancellationToken>,
+) -> Option<Vec<H::Digest>> {
+    let is_cancelled = || cancellation_token.is_some_and(CancellationToken::is_cancelled);
     let num_rows = matrix.num_rows();
     let mut row_hashes = vec![H::Digest::default(); num_rows];
 
@@ -425,14 +1327,33 @@ pub fn hash_rows<F: Field, H: ElementHashFn<F>>(matrix: &Matrix<F>) -> Vec<H::Di
         .enumerate()
         .for_each(|(chunk_offset, chunk)| {
             let offset = chunk_size * chunk_offset;
+            let mut byte_buffer = Vec::new();
+            let mut hash_row = |row: &[F]| hash_row::<F, H, E>(tag, row, &mut byte_buffer);
             let mut row_buffer = vec![F::zero(); matrix.num_cols()];
-            for (i, row_hash) in chunk.iter_mut().enumerate() {
-                matrix.read_row(offset + i, &mut row_buffer);
-                *row_hash = H::hash_elements(row_buffer.iter().copied());
+            for (i, row_hashes) in chunk.chunks_mut(TRANSPOSE_BLOCK_SIZE).enumerate() {
+                if is_cancelled() {
+                    return;
+                }
+                let start = offset + i * TRANSPOSE_BLOCK_SIZE;
+                match layout {
+                    MatrixLayout::ColumnMajor => {
+                        for (j, row_hash) in row_hashes.iter_mut().enumerate() {
+                            matrix.read_row(start + j, &mut row_buffer);
+                            *row_hash = hash_row(&row_buffer);
+                        }
+                    }
+                    MatrixLayout::RowMajor => {
+                        let rows =
+                            RowMajorMatrix::from_rows_of(matrix, start..start + row_hashes.len());
+                        for (row_hash, row) in zip(row_hashes, rows.rows()) {
+                            *row_hash = hash_row(row);
+                        }
+                    }
+                }
             }
         });
 
-    row_hashes
+    (!is_cancelled()).then_some(row_hashes)
 }
 
 #[cfg(feature = "parallel")]
@@ -509,18 +1430,29 @@ pub fn build_merkle_nodes<C: MerkleTreeConfig>(leaves: &[C::Leaf]) -> Vec<C::Dig
 
 #[cfg(test)]
 mod tests {
+    use super::ConstantTimeMatrixMerkleTree;
     use super::Error;
     use super::MatrixMerkleTree;
+    use super::MatrixMerkleTreeBuilder;
     use super::MatrixMerkleTreeImpl;
     use super::MerkleTree;
     use super::MerkleTreeConfig;
     use super::MerkleTreeImpl;
+    use super::MerkleView;
+    use super::PackedMatrixMerkleTree;
+    use super::TreeTag;
+    use crate::context::CancellationToken;
+    use crate::hash::CanonicalLeafEncoder;
     use crate::hash::HashFn;
+    use crate::hash::MontgomeryLeafEncoder;
     use crate::hash::Sha256HashFn;
+    use crate::hash::TruncatedHashFn;
     use crate::utils::GpuAllocator;
     use crate::utils::SerdeOutput;
     use crate::Matrix;
     use ark_ff::MontFp as Fp;
+    use ark_serialize::CanonicalDeserialize;
+    use ark_serialize::CanonicalSerialize;
     use digest::Digest;
     use ministark_gpu::fields::p3618502788666131213697322783095070105623107215331596699973092056135872020481::ark::Fp;
     use sha2::Sha256;
@@ -534,7 +1466,78 @@ mod tests {
 
         let proof = tree.prove(&[i])?;
 
-        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, proof, &[i])
+        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, 8, proof, &[i])
+    }
+
+    #[test]
+    fn adjacent_leaves_share_authentication_paths() -> Result<(), Error> {
+        let tree = MerkleTreeImpl::<UnhashedLeafConfig>::new((0..16).collect())?;
+
+        let adjacent = tree.prove(&[4, 5, 6, 7])?;
+        let scattered = tree.prove(&[0, 5, 10, 15])?;
+
+        assert!(adjacent.sibling_leaves.is_empty());
+        assert_eq!(2, adjacent.nodes.len());
+        assert!(adjacent.compressed_size() < scattered.compressed_size());
+        Ok(())
+    }
+
+    #[test]
+    fn proofs_round_trip_for_adversarial_positions() -> Result<(), Error> {
+        type Tree = MerkleTreeImpl<UnhashedLeafConfig>;
+        let tree = Tree::new((0..16).collect())?;
+        let commitment = tree.root();
+        let position_sets: [&[usize]; 8] = [
+            &[0],
+            &[15],
+            &[15, 0],
+            &[3, 3, 3],
+            &[7, 6, 5, 4],
+            &[1, 2],
+            &[0, 1, 14, 15],
+            &[12, 0, 5, 1, 9, 13, 2, 14, 3, 10, 6, 11, 4, 15, 8, 7],
+        ];
+
+        for positions in position_sets {
+            let mut bytes = Vec::new();
+            tree.prove(positions)?
+                .serialize_compressed(&mut bytes)
+                .unwrap();
+            let proof = MerkleView::deserialize_compressed(&*bytes).unwrap();
+
+            Tree::verify(&commitment, 16, proof, positions)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_proofs_are_rejected() -> Result<(), Error> {
+        type Tree = MerkleTreeImpl<UnhashedLeafConfig>;
+        let tree = Tree::new((0..16).collect())?;
+        let commitment = tree.root();
+        let positions = [1, 2];
+        let proof = tree.prove(&positions)?;
+        let mut missing_node = proof.clone();
+        missing_node.nodes.pop();
+        let mut extra_node = proof.clone();
+        extra_node.nodes.push(commitment.clone());
+        let mut extra_sibling = proof.clone();
+        extra_sibling.sibling_leaves.push(0);
+        let mut missing_leaf = proof.clone();
+        missing_leaf.initial_leaves.pop();
+        let mut bad_height = proof;
+        bad_height.height = u32::MAX;
+
+        for proof in [
+            missing_node,
+            extra_node,
+            extra_sibling,
+            missing_leaf,
+            bad_height,
+        ] {
+            assert!(Tree::verify(&commitment, 16, proof, &positions).is_err());
+        }
+        Ok(())
     }
 
     #[test]
@@ -548,7 +1551,142 @@ mod tests {
 
         let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
 
-        MatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(&commitment, &row_ids, &rows, proof)
+        MatrixMerkleTreeImpl::<Sha256HashFn>::verify_rows(&commitment, 4, &row_ids, &rows, proof)
+    }
+
+    #[test]
+    fn rows_only_verify_with_their_tree_tag() -> Result<(), Error> {
+        type Tree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        let column: &[Fp] = &[Fp!("1"), Fp!("2"), Fp!("3"), Fp!("4")];
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = Tree::from_tagged_matrix(&matrix, TreeTag::BaseTrace);
+        let commitment = tree.root();
+        let row_ids = [1, 2];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+
+        let tag = TreeTag::CompositionTrace;
+        assert!(
+            Tree::verify_tagged_rows(&commitment, 4, tag, &row_ids, &rows, proof.clone()).is_err()
+        );
+        Tree::verify_tagged_rows(&commitment, 4, TreeTag::BaseTrace, &row_ids, &rows, proof)
+    }
+
+    #[test]
+    fn prove_rows_with_montgomery_leaf_encoding() -> Result<(), Error> {
+        type Tree = MatrixMerkleTreeImpl<Sha256HashFn, MontgomeryLeafEncoder>;
+        let column: &[Fp] = &[Fp!("1"), Fp!("2"), Fp!("3"), Fp!("4")];
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = Tree::from_matrix(&matrix);
+        let commitment = tree.root();
+        let row_ids = [1, 3];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+
+        Tree::verify_rows(&commitment, 4, &row_ids, &rows, proof)
+    }
+
+    #[test]
+    fn cancelled_trees_are_not_built() {
+        type Tree = MatrixMerkleTreeImpl<Sha256HashFn>;
+        let column: &[Fp] = &[Fp!("1"), Fp!("2"), Fp!("3"), Fp!("4")];
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let cancellation_token = CancellationToken::new();
+
+        let tree =
+            Tree::from_tagged_matrix_cancellable(&matrix, TreeTag::BaseTrace, &cancellation_token);
+        cancellation_token.cancel();
+        let cancelled_tree =
+            Tree::from_tagged_matrix_cancellable(&matrix, TreeTag::BaseTrace, &cancellation_token);
+
+        let expected_root = Tree::from_tagged_matrix(&matrix, TreeTag::BaseTrace).root();
+        assert_eq!(expected_root, tree.unwrap().root());
+        assert!(cancelled_tree.is_none());
+    }
+
+    #[test]
+    fn builder_matches_from_matrix() -> Result<(), Error> {
+        let column: &[Fp] = &[Fp!("1"), Fp!("2"), Fp!("3"), Fp!("4")];
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
+        let mut builder = MatrixMerkleTreeBuilder::<MatrixMerkleTreeImpl<Sha256HashFn>>::new(4);
+
+        builder.append_rows(&Matrix::new(vec![column[..3].to_vec_in(GpuAllocator)]));
+        builder.append_row(&[column[3]]);
+
+        assert_eq!(tree.root(), builder.finalize::<Fp>()?.root());
+        Ok(())
+    }
+
+    #[test]
+    fn prove_rows_with_packed_leaves() -> Result<(), Error> {
+        type Tree = PackedMatrixMerkleTree<Sha256HashFn, CanonicalLeafEncoder, 2>;
+        let column = (0..8).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = Tree::from_matrix(&matrix);
+        let commitment = tree.root();
+        let row_ids = [1, 2, 3, 6];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+
+        assert_eq!(2, proof.sibling_row_hashes.len());
+        Tree::verify_rows(&commitment, 8, &row_ids, &rows, proof)
+    }
+
+    #[test]
+    fn constant_time_tree_rejects_wrong_row() -> Result<(), Error> {
+        type Tree = ConstantTimeMatrixMerkleTree<Sha256HashFn>;
+        let column = (0..8).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = Tree::from_matrix(&matrix);
+        let commitment = tree.root();
+        let row_ids = [5, 2, 5];
+        let rows = row_ids.map(|i| [column[i]]);
+        let wrong_rows = row_ids.map(|i| [column[i ^ 1]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+
+        assert!(Tree::verify_rows(&commitment, 8, &row_ids, &wrong_rows, proof.clone()).is_err());
+        Tree::verify_rows(&commitment, 8, &row_ids, &rows, proof)
+    }
+
+    #[test]
+    fn constant_time_tree_rejects_proofs_for_another_height() -> Result<(), Error> {
+        type Tree = ConstantTimeMatrixMerkleTree<Sha256HashFn>;
+        let column = (0..8).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = Tree::from_matrix(&matrix);
+        let commitment = tree.root();
+        let row_ids = [3];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+        let mut short_proof = proof.clone();
+        short_proof.paths[0].pop();
+
+        assert!(Tree::verify_rows(&commitment, 4, &row_ids, &rows, short_proof).is_err());
+        assert!(Tree::verify_rows(&commitment, 16, &row_ids, &rows, proof.clone()).is_err());
+        Tree::verify_rows(&commitment, 8, &row_ids, &rows, proof)
+    }
+
+    #[test]
+    fn truncated_digests_shrink_proofs() -> Result<(), Error> {
+        type Tree = MatrixMerkleTreeImpl<TruncatedHashFn<Sha256HashFn, 20>>;
+        let column = (0..8).map(Fp::from).collect::<Vec<Fp>>();
+        let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+        let tree = Tree::from_matrix(&matrix);
+        let full_tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
+        let row_ids = [1, 6];
+        let rows = row_ids.map(|i| [column[i]]);
+
+        let proof = MatrixMerkleTree::<Fp>::prove_rows(&tree, &row_ids)?;
+        let full_proof = MatrixMerkleTree::<Fp>::prove_rows(&full_tree, &row_ids)?;
+
+        assert!(proof.compressed_size() < full_proof.compressed_size());
+        Tree::verify_rows(&tree.root(), 8, &row_ids, &rows, proof)
     }
 
     #[test]
@@ -565,7 +1703,7 @@ mod tests {
 
         let proof = tree.prove(&[i])?;
 
-        MerkleTreeImpl::<HashedLeafConfig>::verify(&commitment, proof, &[i])
+        MerkleTreeImpl::<HashedLeafConfig>::verify(&commitment, 8, proof, &[i])
     }
 
     #[test]
@@ -577,7 +1715,7 @@ mod tests {
 
         let proof = tree.prove(&[i])?;
 
-        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, proof, &[i])
+        MerkleTreeImpl::<UnhashedLeafConfig>::verify(&commitment, 1 << 10, proof, &[i])
     }
 
     struct HashedLeafConfig;
@@ -601,6 +1739,10 @@ mod tests {
         fn security_level_bits() -> u32 {
             Sha256HashFn::COLLISION_RESISTANCE
         }
+
+        fn hash_fn_name() -> &'static str {
+            Sha256HashFn::NAME
+        }
     }
 
     struct UnhashedLeafConfig;
@@ -622,5 +1764,9 @@ mod tests {
         fn security_level_bits() -> u32 {
             Sha256HashFn::COLLISION_RESISTANCE
         }
+
+        fn hash_fn_name() -> &'static str {
+            Sha256HashFn::NAME
+        }
     }
 }
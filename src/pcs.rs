@@ -0,0 +1,277 @@
+//! Polynomial commitment scheme built on FRI
+//!
+//! Polynomials are committed to with a Merkle tree over the rows of their
+//! bit-reversed low degree extension. Evaluations at arbitrary points are
+//! opened DEEP style: the quotients `(p(x) - p(z)) / (x - z)` of each point
+//! are combined and the prover shows a random linear combination of the
+//! combined quotients is of low degree with a single batched FRI proof. Values
+//! of the quotients at the FRI query positions are computed by the verifier
+//! from the opened rows of the commitment.
+
+use crate::fri;
+use crate::fri::FriOptions;
+use crate::fri::FriProof;
+use crate::fri::FriProver;
+use crate::fri::FriVerifier;
+use crate::fri::PublicCoinChannel;
+use crate::hash::Digest;
+use crate::merkle::MatrixMerkleTree;
+use crate::random::PublicCoin;
+use crate::utils::GpuAllocator;
+use crate::Matrix;
+use alloc::vec::Vec;
+use ark_ff::batch_inversion;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::domain::DomainCoeff;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use core::marker::PhantomData;
+use ministark_gpu::prelude::*;
+use ministark_gpu::utils::bit_reverse;
+use ministark_gpu::utils::bit_reverse_index;
+use snafu::Snafu;
+
+/// Polynomial commitment scheme that opens polynomials with FRI
+pub struct FriPcs<F, D, M> {
+    options: FriOptions,
+    num_queries: usize,
+    _phantom: PhantomData<(F, D, M)>,
+}
+
+/// Prover state for polynomials committed to with [`FriPcs::commit`]
+pub struct PcsProverData<F, M> {
+    /// Coefficients of each polynomial
+    pub polynomials: Matrix<F>,
+    /// Bit-reversed evaluations of each polynomial over the LDE domain
+    pub lde: Matrix<F>,
+    pub tree: M,
+}
+
+/// Proof of the evaluations of committed polynomials at a set of points
+#[derive(CanonicalSerialize, CanonicalDeserialize, Clone)]
+pub struct PcsOpening<F: Field, D: Digest, M: MatrixMerkleTree<F>> {
+    /// `values[i][j]` is the evaluation of polynomial `j` at point `i`
+    pub values: Vec<Vec<F>>,
+    /// Rows of the LDE at the FRI query positions
+    pub rows: Vec<Vec<F>>,
+    pub merkle_proof: M::Proof,
+    pub fri_proof: FriProof<F, D, M>,
+}
+
+#[derive(Debug, Snafu)]
+pub enum PcsError {
+    #[snafu(display("expected {expected} opened values but got {actual}"))]
+    InvalidNumValues { expected: usize, actual: usize },
+    #[snafu(display("expected {expected} opened rows but got {actual}"))]
+    InvalidNumRows { expected: usize, actual: usize },
+    #[snafu(display("opened rows do not resolve to the commitment"))]
+    RowsDoNotMatchCommitment,
+    #[snafu(display("opening points can't be in the LDE domain"))]
+    PointInDomain,
+    #[snafu(display("{num_rows} rows isn't a power of two that fits the LDE domain"))]
+    InvalidNumPolyRows { num_rows: usize },
+    #[snafu(context(false))]
+    #[snafu(display("fri verification failed: {source}"))]
+    FriVerification { source: fri::VerificationError },
+}
+
+impl<F, D, M> FriPcs<F, D, M>
+where
+    F: GpuField + Field + DomainCoeff<F::FftField>,
+    F::FftField: FftField,
+    D: Digest,
+    M: MatrixMerkleTree<F, Root = D>,
+{
+    pub const fn new(options: FriOptions, num_queries: usize) -> Self {
+        Self {
+            options,
+            num_queries,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Commits to the polynomials with evaluations `evaluations` over the
+    /// subgroup of size `evaluations.num_rows()`
+    ///
+    /// # Panics
+    /// Panics if the number of rows isn't a power of two
+    pub fn commit(&self, evaluations: Matrix<F>) -> (D, PcsProverData<F, M>) {
+        let n = evaluations.num_rows();
+        let domain = Radix2EvaluationDomain::new(n).unwrap();
+        let polynomials = evaluations.into_polynomials(domain);
+        let lde = polynomials.bit_reversed_evaluate(self.lde_domain(n).unwrap());
+        let tree = M::from_matrix(&lde);
+        (
+            tree.root(),
+            PcsProverData {
+                polynomials,
+                lde,
+                tree,
+            },
+        )
+    }
+
+    /// Opens the committed polynomials at `points`
+    ///
+    /// # Panics
+    /// Panics if a point is in the LDE domain
+    pub fn open(
+        &self,
+        data: &PcsProverData<F, M>,
+        points: &[F],
+        public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+    ) -> PcsOpening<F, D, M> {
+        let values = points
+            .iter()
+            .map(|&z| data.polynomials.evaluate_at(z))
+            .collect::<Vec<Vec<F>>>();
+        public_coin.reseed_with_field_elements(&values.concat());
+        let alpha = public_coin.draw();
+        let batching_coeff = public_coin.draw();
+
+        let lde_size = data.lde.num_rows();
+        let mut xs = self
+            .lde_domain(data.polynomials.num_rows())
+            .unwrap()
+            .elements()
+            .collect::<Vec<_>>();
+        bit_reverse(&mut xs);
+        let mut quotients = Vec::new();
+        for (&z, values) in points.iter().zip(&values) {
+            let mut denominators = xs.iter().map(|&x| lift::<F>(x) - z).collect::<Vec<F>>();
+            assert!(
+                denominators.iter().all(|d| !d.is_zero()),
+                "point is in the domain"
+            );
+            batch_inversion(&mut denominators);
+            let mut quotient = Vec::with_capacity_in(lde_size, GpuAllocator);
+            quotient.resize(lde_size, F::zero());
+            let mut coeff = F::one();
+            for (column, &value) in data.lde.iter().zip(values) {
+                for ((quotient, &eval), &denominator) in
+                    quotient.iter_mut().zip(column.iter()).zip(&denominators)
+                {
+                    *quotient += coeff * (eval - value) * denominator;
+                }
+                coeff *= alpha;
+            }
+            quotients.push(quotient);
+        }
+
+        let mut fri_prover = FriProver::new(self.options);
+        let mut channel = PublicCoinChannel::new(public_coin);
+        fri_prover.build_batched_layers(&mut channel, &quotients, batching_coeff);
+        let positions = public_coin
+            .draw_queries(self.num_queries, lde_size)
+            .into_iter()
+            .collect::<Vec<usize>>();
+        let rows = positions
+            .iter()
+            .map(|&position| data.lde.get_row(position).unwrap())
+            .collect();
+        let merkle_proof = data.tree.prove_rows(&positions).unwrap();
+        PcsOpening {
+            values,
+            rows,
+            merkle_proof,
+            fri_proof: fri_prover.into_proof(&positions),
+        }
+    }
+
+    /// Verifies the evaluations in `opening` are of the polynomials committed
+    /// to in `commitment`. Polynomials must have been committed to with
+    /// `num_rows` evaluations.
+    pub fn verify(
+        &self,
+        commitment: &D,
+        num_rows: usize,
+        points: &[F],
+        opening: PcsOpening<F, D, M>,
+        public_coin: &mut impl PublicCoin<Field = F, Digest = D>,
+    ) -> Result<(), PcsError> {
+        let PcsOpening {
+            values,
+            rows,
+            merkle_proof,
+            fri_proof,
+        } = opening;
+        let lde_domain = self
+            .lde_domain(num_rows)
+            .ok_or(PcsError::InvalidNumPolyRows { num_rows })?;
+        if values.len() != points.len() {
+            return Err(PcsError::InvalidNumValues {
+                expected: points.len(),
+                actual: values.len(),
+            });
+        }
+        let num_polys = values.first().map_or(0, Vec::len);
+        if let Some(invalid) = values.iter().find(|values| values.len() != num_polys) {
+            return Err(PcsError::InvalidNumValues {
+                expected: num_polys,
+                actual: invalid.len(),
+            });
+        }
+        public_coin.reseed_with_field_elements(&values.concat());
+        let alpha = public_coin.draw();
+        let batching_coeff = public_coin.draw();
+
+        let fri_verifier = FriVerifier::new(public_coin, self.options, fri_proof, num_rows - 1)?;
+        let lde_size = lde_domain.size();
+        let positions = public_coin
+            .draw_queries(self.num_queries, lde_size)
+            .into_iter()
+            .collect::<Vec<usize>>();
+        if rows.len() != positions.len() {
+            return Err(PcsError::InvalidNumRows {
+                expected: positions.len(),
+                actual: rows.len(),
+            });
+        }
+        if rows.iter().any(|row| row.len() != num_polys) {
+            return Err(PcsError::RowsDoNotMatchCommitment);
+        }
+        M::verify_rows(commitment, lde_size, &positions, &rows, merkle_proof)
+            .map_err(|_| PcsError::RowsDoNotMatchCommitment)?;
+
+        // `quotients[i][j]` is the combined quotient of point `i` at query `j`
+        let mut quotients = vec![Vec::with_capacity(positions.len()); points.len()];
+        for (&position, row) in positions.iter().zip(&rows) {
+            let x = lift::<F>(lde_domain.element(bit_reverse_index(lde_size, position)));
+            for ((&z, values), quotients) in points.iter().zip(&values).zip(&mut quotients) {
+                let denominator_inv = (x - z).inverse().ok_or(PcsError::PointInDomain)?;
+                let mut quotient = F::zero();
+                let mut coeff = F::one();
+                for (&eval, &value) in row.iter().zip(values) {
+                    quotient += coeff * (eval - value) * denominator_inv;
+                    coeff *= alpha;
+                }
+                quotients.push(quotient);
+            }
+        }
+        Ok(fri_verifier.verify_batched(&positions, &quotients, batching_coeff)?)
+    }
+
+    /// Returns `None` if `num_rows` isn't a power of two or the LDE domain is
+    /// too large for the field
+    fn lde_domain(&self, num_rows: usize) -> Option<Radix2EvaluationDomain<F::FftField>> {
+        if !num_rows.is_power_of_two() {
+            return None;
+        }
+        let lde_size = num_rows.checked_mul(self.options.blowup_factor())?;
+        let offset = self.options.domain_offset::<F>();
+        Radix2EvaluationDomain::new_coset(lde_size, offset)
+    }
+}
+
+/// Maps an element of the FFT field into `F`
+fn lift<F: GpuField + Field + DomainCoeff<F::FftField>>(x: F::FftField) -> F
+where
+    F::FftField: FftField,
+{
+    let mut res = F::one();
+    res *= x;
+    res
+}
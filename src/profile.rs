@@ -0,0 +1,72 @@
+//! Proof parameters that can be pinned in deployment config files
+
+use crate::merkle::MerkleTree;
+use crate::proof::ProofHeader;
+use crate::random::PublicCoin;
+use crate::stark::Stark;
+use crate::verifier::VerificationError;
+use crate::ProofOptions;
+use alloc::string::String;
+use alloc::string::ToString;
+use ministark_gpu::GpuField;
+
+/// The proof options, fields and hash functions a deployment accepts. With
+/// the `serde` feature enabled profiles can be loaded from any serde format
+/// (e.g. TOML or JSON) so verifiers don't need parameters baked into the
+/// binary.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofProfile {
+    pub options: ProofOptions,
+    pub base_field: String,
+    pub extension_field: String,
+    pub merkle_tree_hash: String,
+    pub public_coin_hash: String,
+}
+
+impl ProofProfile {
+    /// Returns the profile of proofs generated by `S` with `options`
+    pub fn new<S: Stark>(options: ProofOptions) -> Self {
+        Self {
+            options,
+            base_field: S::Fp::field_name(),
+            extension_field: S::Fq::field_name(),
+            merkle_tree_hash: S::MerkleTree::hash_fn_name().to_string(),
+            public_coin_hash: S::PublicCoin::hash_fn_name().to_string(),
+        }
+    }
+
+    /// Checks a proof with header `header` was generated with this profile
+    pub fn check_header(&self, header: &ProofHeader) -> Result<(), VerificationError> {
+        use VerificationError::*;
+        if self.options != header.options {
+            return Err(OptionsMismatch {
+                expected: self.options,
+                actual: header.options,
+            });
+        }
+        for (expected, actual) in [
+            (&self.base_field, &header.base_field),
+            (&self.extension_field, &header.extension_field),
+        ] {
+            if expected != actual {
+                return Err(FieldMismatch {
+                    expected: expected.clone(),
+                    actual: actual.clone(),
+                });
+            }
+        }
+        for (expected, actual) in [
+            (&self.merkle_tree_hash, &header.merkle_tree_hash),
+            (&self.public_coin_hash, &header.public_coin_hash),
+        ] {
+            if expected != actual {
+                return Err(HashFnMismatch {
+                    expected: expected.clone(),
+                    actual: actual.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
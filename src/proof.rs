@@ -1,17 +1,121 @@
+use crate::air::AirConfig;
+use crate::air::AirVersion;
+use crate::fri::FriCommitments;
 use crate::fri::FriProof;
 use crate::merkle::MerkleTree;
 use crate::random::PublicCoin;
+use crate::random::SubCoin;
+use crate::serialize::ByteOrder;
 use crate::stark::Stark;
 use crate::trace::Queries;
 use crate::utils::field_bits;
+use crate::Air;
 use crate::ProofOptions;
+use alloc::string::String;
+use alloc::string::ToString;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
 use ark_serialize::Valid;
+use core::fmt::Display;
+use ministark_gpu::GpuField;
 
-/// A proof generated by a mini-stark prover
-pub struct Proof<C: Stark> {
+/// Current version of the proof format
+pub const PROOF_VERSION: u32 = 11;
+
+/// Describes the statement and parameters a proof was generated for. The
+/// verifier checks the header before anything else so mismatched parameters
+/// are reported precisely rather than as a failure deep inside FRI.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ProofHeader {
+    pub version: u32,
+    /// See [`crate::air::AirConfig::VERSION`]
+    pub air_version: AirVersion,
+    /// See [`Air::id`]
+    pub air_id: [u8; 32],
     pub options: ProofOptions,
+    pub base_field: String,
+    pub extension_field: String,
+    pub merkle_tree_hash: String,
+    pub public_coin_hash: String,
+    /// Byte order of field elements in Merkle leaves
+    pub byte_order: ByteOrder,
+    /// Byte order of field elements absorbed by the public coin. Field
+    /// elements in the proof itself always use the canonical arkworks
+    /// (little-endian) serialization.
+    pub transcript_byte_order: ByteOrder,
+}
+
+impl ProofHeader {
+    pub fn new<C: Stark>(air: &Air<C::AirConfig>) -> Self {
+        Self {
+            version: PROOF_VERSION,
+            air_version: C::AirConfig::VERSION,
+            air_id: air.id(),
+            options: air.options(),
+            base_field: C::Fp::field_name(),
+            extension_field: C::Fq::field_name(),
+            merkle_tree_hash: C::MerkleTree::hash_fn_name().to_string(),
+            public_coin_hash: C::PublicCoin::hash_fn_name().to_string(),
+            byte_order: C::MerkleTree::byte_order(),
+            transcript_byte_order: C::PublicCoin::byte_order(),
+        }
+    }
+}
+
+/// Challenge sampled from the public coin while proving
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampledChallenge<F> {
+    /// Sub-coin the challenge was drawn from
+    pub sub_coin: SubCoin,
+    pub label: &'static str,
+    pub values: Vec<F>,
+}
+
+/// Details of proof generation that aren't part of the proof. Every challenge
+/// is recorded in the order it was sampled so tests can catch changes to the
+/// Fiat-Shamir transcript (see [`crate::debug::assert_golden`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofMetadata<F> {
+    pub challenges: Vec<SampledChallenge<F>>,
+    pub pow_nonce: u64,
+    pub query_positions: Vec<usize>,
+}
+
+impl<F> ProofMetadata<F> {
+    /// Returns the challenges drawn from `sub_coin` in the order they were
+    /// sampled. Handy for auditing the Fiat-Shamir transcript a stage at a
+    /// time.
+    pub fn sub_coin_challenges(
+        &self,
+        sub_coin: SubCoin,
+    ) -> impl Iterator<Item = &SampledChallenge<F>> {
+        self.challenges
+            .iter()
+            .filter(move |challenge| challenge.sub_coin == sub_coin)
+    }
+}
+
+impl<F: Display> Display for ProofMetadata<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for SampledChallenge {
+            sub_coin,
+            label,
+            values,
+        } in &self.challenges
+        {
+            let values = values.iter().map(ToString::to_string).collect::<Vec<_>>();
+            writeln!(f, "[{sub_coin}] {label}: [{}]", values.join(", "))?;
+        }
+        writeln!(f, "pow nonce: {}", self.pow_nonce)?;
+        writeln!(f, "query positions: {:?}", self.query_positions)
+    }
+}
+
+/// A proof generated by a mini-stark prover. Sections are serialized in the
+/// order they're added to the transcript so a verifier can check each section
+/// as it's read (see [`crate::verifier::default_verify_from_reader`]).
+pub struct Proof<C: Stark> {
+    pub header: ProofHeader,
     pub trace_len: usize,
     pub base_trace_commitment: C::Digest,
     pub extension_trace_commitment: Option<C::Digest>,
@@ -26,7 +130,7 @@ pub struct Proof<C: Stark> {
 impl<C: Stark> Clone for Proof<C> {
     fn clone(&self) -> Self {
         Self {
-            options: self.options,
+            header: self.header.clone(),
             trace_len: self.trace_len,
             base_trace_commitment: self.base_trace_commitment.clone(),
             extension_trace_commitment: self.extension_trace_commitment.clone(),
@@ -46,7 +150,7 @@ impl<C: Stark> CanonicalSerialize for Proof<C> {
         mut writer: W,
         compress: ark_serialize::Compress,
     ) -> Result<(), ark_serialize::SerializationError> {
-        self.options.serialize_with_mode(&mut writer, compress)?;
+        self.header.serialize_with_mode(&mut writer, compress)?;
         self.trace_len.serialize_with_mode(&mut writer, compress)?;
         self.base_trace_commitment
             .serialize_with_mode(&mut writer, compress)?;
@@ -54,28 +158,32 @@ impl<C: Stark> CanonicalSerialize for Proof<C> {
             .serialize_with_mode(&mut writer, compress)?;
         self.composition_trace_commitment
             .serialize_with_mode(&mut writer, compress)?;
-        self.fri_proof.serialize_with_mode(&mut writer, compress)?;
-        self.pow_nonce.serialize_with_mode(&mut writer, compress)?;
-        self.trace_queries
-            .serialize_with_mode(&mut writer, compress)?;
         self.execution_trace_ood_evals
             .serialize_with_mode(&mut writer, compress)?;
         self.composition_trace_ood_evals
             .serialize_with_mode(&mut writer, compress)?;
+        self.fri_proof
+            .serialize_commitments(&mut writer, compress)?;
+        self.pow_nonce.serialize_with_mode(&mut writer, compress)?;
+        self.trace_queries
+            .serialize_with_mode(&mut writer, compress)?;
+        self.fri_proof
+            .serialize_layer_openings(&mut writer, compress)?;
         Ok(())
     }
 
     fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
-        self.options.serialized_size(compress)
+        self.header.serialized_size(compress)
             + self.trace_len.serialized_size(compress)
             + self.base_trace_commitment.serialized_size(compress)
             + self.extension_trace_commitment.serialized_size(compress)
             + self.composition_trace_commitment.serialized_size(compress)
-            + self.fri_proof.serialized_size(compress)
-            + self.pow_nonce.serialized_size(compress)
-            + self.trace_queries.serialized_size(compress)
             + self.execution_trace_ood_evals.serialized_size(compress)
             + self.composition_trace_ood_evals.serialized_size(compress)
+            + self.fri_proof.commitments_serialized_size(compress)
+            + self.pow_nonce.serialized_size(compress)
+            + self.trace_queries.serialized_size(compress)
+            + self.fri_proof.layer_openings_serialized_size(compress)
     }
 }
 
@@ -92,57 +200,83 @@ impl<C: Stark> CanonicalDeserialize for Proof<C> {
         compress: ark_serialize::Compress,
         validate: ark_serialize::Validate,
     ) -> Result<Self, ark_serialize::SerializationError> {
+        let header = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let trace_len = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let base_trace_commitment = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let extension_trace_commitment =
+            <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let composition_trace_commitment =
+            <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let execution_trace_ood_evals =
+            <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let composition_trace_ood_evals =
+            <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let fri_commitments: FriCommitments<C::Fq, C::Digest> =
+            <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let pow_nonce = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let trace_queries = <_>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let fri_layers = FriProof::deserialize_layer_openings(
+            &mut reader,
+            &fri_commitments.layer_commitments,
+            compress,
+            validate,
+        )?;
         Ok(Self {
-            options: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
-            trace_len: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
-            base_trace_commitment: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
-            extension_trace_commitment: <_>::deserialize_with_mode(
-                &mut reader,
-                compress,
-                validate,
-            )?,
-            composition_trace_commitment: <_>::deserialize_with_mode(
-                &mut reader,
-                compress,
-                validate,
-            )?,
-            fri_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
-            pow_nonce: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
-            trace_queries: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
-            execution_trace_ood_evals: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
-            composition_trace_ood_evals: <_>::deserialize_with_mode(
-                &mut reader,
-                compress,
-                validate,
-            )?,
+            header,
+            trace_len,
+            base_trace_commitment,
+            extension_trace_commitment,
+            composition_trace_commitment,
+            fri_proof: FriProof::new(fri_layers, fri_commitments.remainder_coeffs),
+            pow_nonce,
+            trace_queries,
+            execution_trace_ood_evals,
+            composition_trace_ood_evals,
         })
     }
 }
 
 impl<C: Stark> Proof<C> {
-    // adapted from Winterfell
-    // also https://github.com/starkware-libs/ethSTARK/blob/master/README.md#7-Measuring-Security
-    // https://eprint.iacr.org/2020/654.pdf section 7.2 for proven security
+    /// See [`security_level_bits`]
     pub fn security_level_bits(&self) -> u32 {
-        let field_security = {
-            let lde_domain_size = self.trace_len * self.options.lde_blowup_factor as usize;
-            let extension_field_bits = field_bits::<C::Fq>();
-            extension_field_bits - lde_domain_size.ilog2()
-        };
-
-        let fri_query_security = {
-            let grinding_factor = u32::from(self.options.grinding_factor);
-            let security_per_query = self.options.lde_blowup_factor.ilog2();
-            let num_fri_quiries = u32::from(self.options.num_queries);
-            security_per_query * num_fri_quiries + grinding_factor
-        };
-
-        let merkle_tree_security = C::MerkleTree::security_level_bits();
-        let public_coin_security = C::PublicCoin::security_level_bits();
-
-        field_security
-            .min(fri_query_security)
-            .min(merkle_tree_security)
-            .min(public_coin_security)
+        security_level_bits::<C>(&self.header, self.trace_len)
     }
 }
+
+// adapted from Winterfell
+// also https://github.com/starkware-libs/ethSTARK/blob/master/README.md#7-Measuring-Security
+// https://eprint.iacr.org/2020/654.pdf section 7.2 for proven security
+/// Returns the conjectured security of a proof with this header and trace
+/// length. Only needs the start of a proof so streamed proofs can be rejected
+/// before the rest is read.
+pub fn security_level_bits<C: Stark>(header: &ProofHeader, trace_len: usize) -> u32 {
+    options_security_level_bits::<C>(header.options, trace_len)
+}
+
+/// Returns the conjectured security of proofs generated with `options` for a
+/// trace of length `trace_len`. See [`security_level_bits`].
+pub fn options_security_level_bits<C: Stark>(options: ProofOptions, trace_len: usize) -> u32 {
+    let field_security = {
+        let lde_domain_size = trace_len * options.lde_blowup_factor as usize;
+        let extension_field_bits = field_bits::<C::Fq>();
+        extension_field_bits - lde_domain_size.ilog2()
+    };
+
+    let fri_query_security = {
+        let grinding_factor = u32::from(options.grinding_factor);
+        let security_per_query = options.lde_blowup_factor.ilog2();
+        let num_fri_quiries = u32::from(options.num_queries);
+        security_per_query * num_fri_quiries + grinding_factor
+    };
+
+    let merkle_tree_security = C::MerkleTree::security_level_bits();
+    let public_coin_security = C::PublicCoin::security_level_bits();
+    // birthday bound on collisions of truncated digests
+    let digest_security = 4 * u32::from(options.digest_len);
+
+    field_security
+        .min(fri_query_security)
+        .min(merkle_tree_security)
+        .min(public_coin_security)
+        .min(digest_security)
+}
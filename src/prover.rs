@@ -1,11 +1,26 @@
 use crate::air::AirConfig;
+use crate::air::AirConfigError;
 use crate::challenges::Challenges;
 use crate::channel::ProverChannel;
 use crate::composer::DeepPolyComposer;
+use crate::context::CancellationToken;
+use crate::context::Device;
+use crate::context::ProverContext;
+use crate::debug;
+use crate::debug::TraceWarning;
+use crate::eval_cpu;
+use crate::fri;
 use crate::fri::FriProver;
+use crate::hints::Hints;
+use crate::ldt::Fri;
+use crate::ldt::LowDegreeTest;
 use crate::merkle::MatrixMerkleTree;
+use crate::merkle::MatrixMerkleTreeBuilder;
 use crate::merkle::MerkleTree;
-use crate::random::draw_multiple;
+use crate::merkle::TreeTag;
+use crate::proof::ProofMetadata;
+use crate::random::PublicCoin;
+use crate::random::SubCoin;
 use crate::stark::Stark;
 use crate::trace::Queries;
 use crate::utils::GpuAllocator;
@@ -16,114 +31,241 @@ use crate::Proof;
 use crate::ProofOptions;
 use crate::Trace;
 use alloc::vec::Vec;
+use ark_ff::FftField;
 use ark_ff::Field;
+use ark_ff::Zero;
+use ark_poly::domain::DomainCoeff;
 use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use core::fmt::Display;
+use core::iter::successors;
+use core::iter::zip;
+use core::mem::size_of;
+use core::time::Duration;
 use ministark_gpu::utils::bit_reverse;
+use ministark_gpu::utils::bit_reverse_index;
+use ministark_gpu::GpuField;
 use wasm_timer::Instant;
 
-#[allow(clippy::too_many_lines)]
+/// Generates a proof using the resources of the [`ProverContext::current`]
+/// context
 pub fn default_prove<S: Stark>(
     this: &S,
     options: ProofOptions,
     witness: S::Witness,
 ) -> Result<Proof<S>, ProvingError> {
+    default_prove_with_context(this, &ProverContext::current(), options, witness)
+}
+
+/// Generates a proof along with metadata recording every sampled challenge
+pub fn default_prove_with_metadata<S: Stark>(
+    this: &S,
+    options: ProofOptions,
+    witness: S::Witness,
+) -> Result<(Proof<S>, ProofMetadata<S::Fq>), ProvingError> {
+    let context = ProverContext::current();
+    context
+        .install(|| prove_impl(this, &context, options, witness, false))
+        .map(|(proof, metadata, _)| (proof, metadata))
+}
+
+/// Generates a proof along with the intermediate polynomials and FRI layers.
+/// Capturing the artifacts keeps copies of the polynomials in memory so this
+/// is intended for research and tests rather than production proving.
+pub fn default_prove_with_artifacts<S: Stark>(
+    this: &S,
+    options: ProofOptions,
+    witness: S::Witness,
+) -> Result<(Proof<S>, ProverArtifacts<S::Fp, S::Fq>), ProvingError> {
+    let context = ProverContext::current();
+    context
+        .install(|| prove_impl(this, &context, options, witness, true))
+        .map(|(proof, _, artifacts)| (proof, artifacts.unwrap()))
+}
+
+/// Generates a proof. Returns [`ProvingError::Cancelled`] if the cancellation
+/// token is cancelled. See [`CancellationToken`] for where the token is
+/// checked.
+pub fn default_prove_cancellable<S: Stark>(
+    this: &S,
+    options: ProofOptions,
+    witness: S::Witness,
+    cancellation_token: &CancellationToken,
+) -> Result<Proof<S>, ProvingError> {
+    let context = ProverContext::current().with_cancellation_token(cancellation_token.clone());
+    default_prove_with_context(this, &context, options, witness)
+}
+
+/// Generates a proof using the resources of `context`. Fails with
+/// [`ProvingError::OutOfMemoryBudget`] if the proof needs more memory than the
+/// context's [`crate::context::MemoryBudget`] allows.
+pub fn default_prove_with_context<S: Stark>(
+    this: &S,
+    context: &ProverContext,
+    options: ProofOptions,
+    witness: S::Witness,
+) -> Result<Proof<S>, ProvingError> {
+    context
+        .install(|| prove_impl(this, context, options, witness, false))
+        .map(|(proof, ..)| proof)
+}
+
+/// Generates a proof with the memory budget, devices, trace scan and
+/// cancellation token of `context`. The intermediate polynomials and FRI
+/// layers are only kept if `capture_artifacts` is set.
+#[allow(clippy::too_many_lines, clippy::type_complexity)]
+fn prove_impl<S: Stark>(
+    this: &S,
+    context: &ProverContext,
+    options: ProofOptions,
+    witness: S::Witness,
+    capture_artifacts: bool,
+) -> Result<
+    (
+        Proof<S>,
+        ProofMetadata<S::Fq>,
+        Option<ProverArtifacts<S::Fp, S::Fq>>,
+    ),
+    ProvingError,
+> {
+    let check_cancelled = || {
+        if context.cancellation_token().is_cancelled() {
+            Err(ProvingError::Cancelled)
+        } else {
+            Ok(())
+        }
+    };
+
+    let reserve = |buffer, bytes| {
+        context
+            .memory_budget()
+            .map(|budget| budget.reserve(buffer, bytes))
+            .transpose()
+    };
+
+    if usize::from(options.digest_len) != S::Digest::LEN {
+        return Err(ProvingError::DigestLenMismatch {
+            expected: S::Digest::LEN,
+            actual: options.digest_len,
+        });
+    }
+
     let now = Instant::now();
     let trace = this.generate_trace(witness);
-    web_sys::console::log_1(&format!("Generated execution trace (cols={}, rows={}) in {:.0?}", trace.base_columns().num_cols(), trace.base_columns().num_rows(), now.elapsed()).into());
+    check_cancelled()?;
+    web_sys::console::log_1(&format!("Generated execution trace (cols={}, rows={}) in {:.0?}", trace.num_base_columns(), trace.len(), now.elapsed()).into());
+    if context.scans_trace() {
+        let columns = (0..trace.num_base_columns()).map(|i| trace.base_column(i));
+        for warning in debug::scan_columns(columns) {
+            if let TraceWarning::NonCanonicalValue { column, row } = warning {
+                return Err(ProvingError::NonCanonicalTraceValue { column, row });
+            }
+            web_sys::console::log_1(&format!("Trace scan: {warning}").into());
+        }
+    }
 
     let now = Instant::now();
-    let air = Air::new(trace.len(), this.get_public_inputs(), options);
+    let air = this
+        .air(trace.len(), options)
+        .map_err(ProvingError::InvalidAir)?;
     let public_coin = this.gen_public_coin(&air);
     let mut channel = ProverChannel::<S>::new(&air, public_coin);
     web_sys::console::log_1(&format!("Init air {:.0?}", now.elapsed()).into());
 
+    // sizes of the major buffers. Merkle trees store two digests per leaf.
+    let trace_len = air.trace_len();
+    let lde_size = air.lde_domain().size();
+    let fq_size = size_of::<S::Fq>();
+    let tree_size = 2 * size_of::<S::Digest>();
+    let trace_row_size = S::AirConfig::NUM_BASE_COLUMNS * size_of::<S::Fp>()
+        + S::AirConfig::NUM_EXTENSION_COLUMNS * fq_size;
+    let trace_reservation = reserve("execution trace", trace_len * trace_row_size)?;
+    let _trace_polys_reservation =
+        reserve("execution trace polynomials", trace_len * trace_row_size)?;
+    let _trace_lde_reservation = reserve(
+        "execution trace LDE",
+        lde_size * (trace_row_size + 2 * tree_size),
+    )?;
+
     let now = Instant::now();
-    let trace_xs = air.trace_domain();
     let lde_xs = air.lde_domain();
-    let base_trace = trace.base_columns();
-    assert_eq!(S::AirConfig::NUM_BASE_COLUMNS, base_trace.num_cols());
-    let base_trace_polys = base_trace.interpolate(trace_xs);
-    let mut base_trace_lde = base_trace_polys.bit_reversed_evaluate(lde_xs);
-    let base_trace_tree = S::MerkleTree::from_matrix(&base_trace_lde);
+    let (base_trace_polys, mut base_trace) = commit_base_trace::<S>(context, &air, &trace)?;
     web_sys::console::log_1(&format!("Base trace commitment {:.0?}", now.elapsed()).into());
 
-    channel.commit_base_trace(base_trace_tree.root());
+    channel.commit_base_trace(base_trace.tree.root());
+    check_cancelled()?;
     let num_challenges = air.num_challenges();
-    let challenges = Challenges::new(draw_multiple(&mut channel.public_coin, num_challenges));
+    let challenges = Challenges::new(channel.draw_challenges(
+        SubCoin::TraceExtension,
+        "air challenges",
+        num_challenges,
+    ));
     let hints = air.gen_hints(&challenges);
 
     let now = Instant::now();
     let extension_trace = trace.build_extension_columns(&challenges);
-    let num_extension_cols = extension_trace.as_ref().map_or(0, Matrix::num_cols);
-    assert_eq!(S::AirConfig::NUM_EXTENSION_COLUMNS, num_extension_cols);
-    let extension_trace_polys = extension_trace.as_ref().map(|t| t.interpolate(trace_xs));
-    let mut extension_trace_lde = extension_trace_polys
-        .as_ref()
-        .map(|p| p.bit_reversed_evaluate(lde_xs));
-    let extension_trace_tree = extension_trace_lde.as_ref().map(S::MerkleTree::from_matrix);
-    if let Some(t) = extension_trace_tree.as_ref() {
-        channel.commit_extension_trace(t.root());
+    let (extension_trace_polys, mut extension_trace_commitment) =
+        commit_extension_trace::<S>(context, &air, extension_trace.as_ref())?.unzip();
+    if let Some(t) = extension_trace_commitment.as_ref() {
+        channel.commit_extension_trace(t.tree.root());
     }
     web_sys::console::log_1(&format!("Extension trace commitment {:.0?}", now.elapsed()).into());
 
     #[cfg(debug_assertions)]
-    this.validate_constraints(&challenges, &hints, base_trace, extension_trace.as_ref());
-    drop((trace, extension_trace));
-
-    let composition_trace_polys: Matrix<S::Fq>;
-    let composition_trace_lde: Matrix<S::Fq>;
-    let composition_trace_tree: S::MerkleTree;
-    {
-        // To prevent allocating more memory, just re-order the values in the trace to
-        // be in natural order. Note that for the remainder of the protocol the trace
-        // should entirely be in bit-reversed order hence why this function is
-        // called again at the end of the block.
-        let ce_lde_xs = air.ce_domain();
-        let ce_domain_size = ce_lde_xs.size();
-        let base_trace_ce_cols = bit_reverse_ce_trace(ce_domain_size, &mut base_trace_lde);
-        let extension_trace_ce_cols = extension_trace_lde
-            .as_mut()
-            .map(|t| bit_reverse_ce_trace(ce_domain_size, t));
-
-        let num_composition_coeffs = air.num_composition_constraint_coeffs();
-        let composition_coeffs = draw_multiple(&mut channel.public_coin, num_composition_coeffs);
-        let x_lde = ce_lde_xs.elements().collect::<Vec<_>>();
+    this.validate_constraints(&challenges, &hints, &trace, extension_trace.as_ref());
+    drop((trace, extension_trace, trace_reservation));
+    check_cancelled()?;
 
-        let now = Instant::now();
-        let composition_evals = S::AirConfig::eval_constraint(
-            air.composition_constraint(),
-            &challenges,
-            &hints,
-            &composition_coeffs,
-            air.ce_blowup_factor(),
-            x_lde.to_vec_in(GpuAllocator),
-            &base_trace_ce_cols,
-            extension_trace_ce_cols.as_deref(),
-        );
-        web_sys::console::log_1(&format!("Constraint eval {:.0?}", now.elapsed()).into());
+    let num_composition_coeffs = air.num_composition_constraint_coeffs();
+    let composition_coeffs = channel.draw_challenges(
+        SubCoin::Composition,
+        "composition coefficients",
+        num_composition_coeffs,
+    );
 
-        let now = Instant::now();
-        let composition_poly =
-            GpuVec::try_from(composition_evals.into_polynomials(air.ce_domain())).unwrap();
-        let mut composition_trace_cols = (0..air.ce_blowup_factor())
-            .map(|_| Vec::with_capacity_in(air.trace_len(), GpuAllocator))
-            .collect::<Vec<_>>();
-        for chunk in composition_poly.chunks(composition_trace_cols.len()) {
-            for i in 0..composition_trace_cols.len() {
-                composition_trace_cols[i].push(chunk[i]);
-            }
-        }
-        composition_trace_polys = Matrix::new(composition_trace_cols);
-        composition_trace_lde = composition_trace_polys.bit_reversed_evaluate(air.lde_domain());
-        composition_trace_tree = S::MerkleTree::from_matrix(&composition_trace_lde);
-        channel.commit_composition_trace(composition_trace_tree.root());
-        web_sys::console::log_1(&format!("Composition trace commitment {:.0?}", now.elapsed()).into());
-
-        bit_reverse_ce_trace(ce_domain_size, &mut base_trace_lde);
-        extension_trace_lde
-            .as_mut()
-            .map(|t| bit_reverse_ce_trace(ce_domain_size, t));
-    }
+    let num_composition_cols = air.num_composition_trace_columns();
+    let composition_size = (air.ce_domain().size() + trace_len * num_composition_cols) * fq_size
+        + lde_size * (num_composition_cols * fq_size + tree_size);
+    let _composition_reservation = reserve("composition trace", composition_size)?;
+
+    let now = Instant::now();
+    let composition_trace_polys = gen_composition_trace_polys(
+        context,
+        &air,
+        &challenges,
+        &hints,
+        &composition_coeffs,
+        &mut base_trace.lde,
+        extension_trace_commitment.as_mut().map(|t| &mut t.lde),
+    )?;
+    web_sys::console::log_1(&format!("Constraint eval {:.0?}", now.elapsed()).into());
+    let hoisted_ops = air.row_independent_ops();
+    let saved_muls = hoisted_ops.saved_muls(eval_cpu::num_chunks(air.ce_domain().size()));
+    web_sys::console::log_1(
+        &format!(
+            "Hoisted {} row independent multiplications (saved {saved_muls})",
+            hoisted_ops.num_muls
+        )
+        .into(),
+    );
+    check_cancelled()?;
+
+    let now = Instant::now();
+    let composition_trace = commit_composition_trace::<S>(context, &air, &composition_trace_polys)?;
+    channel.commit_composition_trace(composition_trace.tree.root());
+    web_sys::console::log_1(&format!("Composition trace commitment {:.0?}", now.elapsed()).into());
+    check_cancelled()?;
+
+    let mut artifacts = capture_artifacts.then(|| ProverArtifacts {
+        base_trace_polys: base_trace_polys.clone(),
+        extension_trace_polys: extension_trace_polys.clone(),
+        composition_trace_polys: composition_trace_polys.clone(),
+        deep_composition_poly: Vec::new(),
+        fri_layers: Vec::new(),
+        fri_remainder_coeffs: Vec::new(),
+    });
+
+    let _deep_reservation = reserve("DEEP composition polynomial", 2 * lde_size * fq_size)?;
 
     let now = Instant::now();
     let z = channel.get_ood_point();
@@ -137,44 +279,525 @@ pub fn default_prove<S: Stark>(
     let (execution_trace_oods, composition_trace_oods) = deep_poly_composer.get_ood_evals();
     channel.send_ood_evals(execution_trace_oods, composition_trace_oods);
 
-    let deep_coeffs = this.gen_deep_coeffs(&mut channel.public_coin, &air);
+    let deep_coeffs = this.gen_deep_coeffs(&mut channel.public_coin.fork(SubCoin::Deep), &air);
+    let (degree_alpha, degree_beta) = deep_coeffs.degree;
+    channel.record_challenges(
+        SubCoin::Deep,
+        "deep trace coefficients",
+        &deep_coeffs.execution_trace,
+    );
+    channel.record_challenges(
+        SubCoin::Deep,
+        "deep composition coefficients",
+        &deep_coeffs.composition_trace,
+    );
+    channel.record_challenges(
+        SubCoin::Deep,
+        "deep degree coefficients",
+        &[degree_alpha, degree_beta],
+    );
     let deep_composition_poly = deep_poly_composer.into_deep_poly(deep_coeffs);
+    if let Some(artifacts) = artifacts.as_mut() {
+        artifacts.deep_composition_poly = deep_composition_poly[0].to_vec();
+    }
     // let deep_xs = Radix2EvaluationDomain::new(lde_xs.size());
     let deep_composition_lde = deep_composition_poly.into_bit_reversed_evaluations(lde_xs);
     web_sys::console::log_1(&format!("Deep composition {:.0?}", now.elapsed()).into());
+    check_cancelled()?;
 
-    let now = Instant::now();
     let fri_options = options.into_fri_options();
-    let mut fri_prover = FriProver::<S::Fq, S::Digest, S::MerkleTree>::new(fri_options);
-    fri_prover.build_layers(&mut channel, deep_composition_lde.try_into().unwrap());
+    let folding_factor = usize::from(options.fri_folding_factor);
+    let fri_layers_size = successors(Some(lde_size), |n| Some(n / folding_factor))
+        .take(fri_options.num_layers(lde_size))
+        .map(|n| n * (fq_size + tree_size))
+        .sum();
+    let _fri_reservation = reserve("FRI layers", fri_layers_size)?;
+
+    let now = Instant::now();
+    let fri_prover = commit_low_degree_test::<S>(
+        options,
+        &mut channel,
+        deep_composition_lde.try_into().unwrap(),
+        context.devices(),
+    );
     web_sys::console::log_1(&format!("FRI {:.0?}", now.elapsed()).into());
+    check_cancelled()?;
+    if let Some(artifacts) = artifacts.as_mut() {
+        let fri_layers = fri_prover.layer_evaluations();
+        artifacts.fri_layers = fri_layers.into_iter().cloned().collect();
+        artifacts.fri_remainder_coeffs = fri_prover.remainder_coeffs().to_vec();
+    }
 
     let now = Instant::now();
     channel.grind_fri_commitments();
     web_sys::console::log_1(&format!("Proof of work {:.0?}", now.elapsed()).into());
+    check_cancelled()?;
 
-    let query_positions = Vec::from_iter(channel.get_fri_query_positions());
-    let fri_proof = fri_prover.into_proof(&query_positions);
+    let query_positions = channel.get_fri_query_positions();
+    let fri_proof =
+        <Fri as LowDegreeTest<S::Fq, S::Digest, S::MerkleTree>>::open(fri_prover, &query_positions);
 
-    let queries = Queries::new(
-        &base_trace_lde,
-        extension_trace_lde.as_ref(),
-        &composition_trace_lde,
-        &base_trace_tree,
-        extension_trace_tree.as_ref(),
-        &composition_trace_tree,
+    let queries = open_traces(
+        &base_trace,
+        extension_trace_commitment.as_ref(),
+        &composition_trace,
         &query_positions,
     );
-    Ok(channel.build_proof(queries, fri_proof))
+    let scratch_arena = context.scratch_arena();
+    scratch_arena.recycle_matrix(base_trace.lde);
+    if let Some(extension_trace) = extension_trace_commitment {
+        scratch_arena.recycle_matrix(extension_trace.lde);
+    }
+    scratch_arena.recycle_matrix(composition_trace.lde);
+    let metadata = channel.metadata(&query_positions);
+    Ok((channel.build_proof(queries, fri_proof), metadata, artifacts))
+}
+
+/// LDE of a trace and the Merkle tree committing to its rows
+pub(crate) struct TraceCommitment<F, M> {
+    pub lde: Matrix<F>,
+    pub tree: M,
+}
+
+/// Extends `polys` over the LDE domain and commits to the rows of the LDE.
+/// Rows are hashed chunk by chunk as the LDE is evaluated. Returns
+/// [`ProvingError::Cancelled`] if the context's cancellation token is cancelled
+/// while extending.
+fn commit_trace<F, M>(
+    context: &ProverContext,
+    polys: &Matrix<F>,
+    domain: Radix2EvaluationDomain<F::FftField>,
+    tag: TreeTag,
+) -> Result<TraceCommitment<F, M>, ProvingError>
+where
+    F: Field + GpuField + DomainCoeff<F::FftField>,
+    F::FftField: FftField,
+    M: MatrixMerkleTree<F>,
+{
+    let mut builder = MatrixMerkleTreeBuilder::<M>::new(domain.size()).with_tag(tag);
+    let lde = extend(context, polys, domain, |rows| builder.append_rows(rows))?;
+    let tree = builder
+        .finalize::<F>()
+        .expect("every row of the LDE is appended");
+    Ok(TraceCommitment { lde, tree })
+}
+
+/// Interpolates the base trace and commits to its LDE. Returns the base trace
+/// polynomials along with the commitment.
+pub(crate) fn commit_base_trace<S: Stark>(
+    context: &ProverContext,
+    air: &Air<S::AirConfig>,
+    trace: &S::Trace,
+) -> Result<(Matrix<S::Fp>, TraceCommitment<S::Fp, S::MerkleTree>), ProvingError> {
+    let polys = trace.interpolate_base_columns(air.trace_domain());
+    assert_eq!(S::AirConfig::NUM_BASE_COLUMNS, polys.num_cols());
+    let commitment = commit_trace(context, &polys, air.lde_domain(), TreeTag::BaseTrace)?;
+    Ok((polys, commitment))
+}
+
+/// Interpolates the extension trace and commits to its LDE. Returns `None` if
+/// the AIR has no extension columns.
+pub(crate) fn commit_extension_trace<S: Stark>(
+    context: &ProverContext,
+    air: &Air<S::AirConfig>,
+    extension_trace: Option<&Matrix<S::Fq>>,
+) -> Result<Option<(Matrix<S::Fq>, TraceCommitment<S::Fq, S::MerkleTree>)>, ProvingError> {
+    let num_extension_cols = extension_trace.map_or(0, Matrix::num_cols);
+    assert_eq!(S::AirConfig::NUM_EXTENSION_COLUMNS, num_extension_cols);
+    extension_trace
+        .map(|trace| {
+            let polys = trace.interpolate(air.trace_domain());
+            let tag = TreeTag::ExtensionTrace;
+            let commitment = commit_trace(context, &polys, air.lde_domain(), tag)?;
+            Ok((polys, commitment))
+        })
+        .transpose()
+}
+
+/// Commits to the LDE of the composition trace polynomials
+pub(crate) fn commit_composition_trace<S: Stark>(
+    context: &ProverContext,
+    air: &Air<S::AirConfig>,
+    composition_trace_polys: &Matrix<S::Fq>,
+) -> Result<TraceCommitment<S::Fq, S::MerkleTree>, ProvingError> {
+    // the columns share a tree. The DEEP composition needs every column at
+    // every query so separate trees would add an authentication path per
+    // column to the proof without opening any fewer values
+    commit_trace(
+        context,
+        composition_trace_polys,
+        air.lde_domain(),
+        TreeTag::CompositionTrace,
+    )
+}
+
+/// Commits to the bit-reversed evaluations of the DEEP composition polynomial
+/// with FRI. The layers are sharded across `devices`.
+pub(crate) fn commit_low_degree_test<S: Stark>(
+    options: ProofOptions,
+    channel: &mut impl fri::ProverChannel<Field = S::Fq, Digest = S::Digest>,
+    deep_composition_lde: GpuVec<S::Fq>,
+    devices: &[Device],
+) -> FriProver<S::Fq, S::Digest, S::MerkleTree> {
+    let mut fri_prover = FriProver::new(options.into_fri_options()).with_devices(devices);
+    fri_prover.build_layers(channel, deep_composition_lde);
+    fri_prover
+}
+
+/// Opens the trace commitments at the query positions
+pub(crate) fn open_traces<S: Stark>(
+    base_trace: &TraceCommitment<S::Fp, S::MerkleTree>,
+    extension_trace: Option<&TraceCommitment<S::Fq, S::MerkleTree>>,
+    composition_trace: &TraceCommitment<S::Fq, S::MerkleTree>,
+    query_positions: &[usize],
+) -> Queries<S> {
+    Queries::new(
+        &base_trace.lde,
+        extension_trace.map(|t| &t.lde),
+        &composition_trace.lde,
+        &base_trace.tree,
+        extension_trace.map(|t| &t.tree),
+        &composition_trace.tree,
+        query_positions,
+    )
+}
+
+/// Intermediate values of the prover captured by
+/// [`default_prove_with_artifacts`]. Polynomials are given by their
+/// coefficients.
+pub struct ProverArtifacts<Fp, Fq> {
+    pub base_trace_polys: Matrix<Fp>,
+    pub extension_trace_polys: Option<Matrix<Fq>>,
+    /// Columns of the composition polynomial. See
+    /// [`Air::num_composition_trace_columns`].
+    pub composition_trace_polys: Matrix<Fq>,
+    pub deep_composition_poly: Vec<Fq>,
+    /// Bit-reversed evaluations committed to in each FRI layer. Each row holds
+    /// the evaluations folded into a single evaluation of the next layer.
+    pub fri_layers: Vec<Matrix<Fq>>,
+    pub fri_remainder_coeffs: Vec<Fq>,
 }
 
 /// Errors that can occur during the proving stage
 #[derive(Debug)]
 pub enum ProvingError {
     Fail,
+    /// Proof generation was cancelled with a [`CancellationToken`]
+    Cancelled,
+    /// The AIR can't be used with the trace or proof options
+    InvalidAir(AirConfigError),
+    /// Allocating `buffer` would exceed the [`crate::context::MemoryBudget`]
+    OutOfMemoryBudget {
+        buffer: &'static str,
+        required: usize,
+        limit: usize,
+    },
+    /// The proof options don't match the digest of the hash function
+    DigestLenMismatch {
+        expected: usize,
+        actual: u8,
+    },
+    /// The execution trace holds a value that isn't a canonical field element
+    NonCanonicalTraceValue {
+        column: usize,
+        row: usize,
+    },
     // TODO
 }
 
+impl Display for ProvingError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Fail => write!(f, "proof generation failed"),
+            Self::Cancelled => write!(f, "proof generation was cancelled"),
+            Self::InvalidAir(source) => write!(f, "invalid AIR: {source}"),
+            Self::OutOfMemoryBudget {
+                buffer,
+                required,
+                limit,
+            } => write!(
+                f,
+                "allocating the {buffer} requires {required} bytes but the memory budget is \
+                 {limit} bytes. Try a lower LDE blowup factor or a run-length encoded trace \
+                 (see `ministark::sparse`)."
+            ),
+            Self::DigestLenMismatch { expected, actual } => write!(
+                f,
+                "the hash function has {expected} byte digests but the proof options use \
+                 {actual} bytes (see `ProofOptions::with_digest_len`)"
+            ),
+            Self::NonCanonicalTraceValue { column, row } => write!(
+                f,
+                "column {column} of the execution trace has a non-canonical value on row {row}. \
+                 Values must be created with the field's constructors."
+            ),
+        }
+    }
+}
+
+/// Predicted cost of generating a proof. See [`estimate_cost`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CostEstimate {
+    /// Rough upper bound on the number of bytes held in memory at once
+    pub memory_bytes: usize,
+    /// Number of FFTs and IFFTs
+    pub num_ffts: usize,
+    /// Total number of butterflies across all FFTs i.e. `Σ n/2 * log2(n)`
+    pub num_butterflies: usize,
+    /// Number of hashes for commitments and the expected number for grinding
+    pub num_hashes: usize,
+    /// See [`Air::num_composition_trace_columns`]
+    pub num_composition_columns: usize,
+    /// Number of committed FRI layers
+    pub num_fri_layers: usize,
+}
+
+impl CostEstimate {
+    /// Returns a rough proving time on a backend with the given throughput.
+    /// Only FFTs and hashes are counted so constraint evaluation of large AIRs
+    /// takes this over.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn wall_clock(&self, throughput: Throughput) -> Duration {
+        Duration::from_secs_f64(
+            self.num_butterflies as f64 / throughput.butterflies_per_second
+                + self.num_hashes as f64 / throughput.hashes_per_second,
+        )
+    }
+}
+
+/// Measured speed of FFTs and hashing. See [`CostEstimate::wall_clock`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Throughput {
+    pub butterflies_per_second: f64,
+    pub hashes_per_second: f64,
+}
+
+impl Throughput {
+    /// Times an FFT and a Merkle tree over `2^log_size` values on the backend
+    /// the prover uses for a domain of that size. GPU backends are only used
+    /// from [`crate::utils::GPU_MIN_DOMAIN_SIZE`] values.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn measure<S: Stark>(log_size: u32) -> Self {
+        let n = 1 << log_size;
+        let mut column = Vec::with_capacity_in(n, GpuAllocator);
+        column.extend((0..n as u64).map(S::Fp::from));
+        let domain = Radix2EvaluationDomain::new(n).unwrap();
+
+        let now = Instant::now();
+        let evaluations = Matrix::new(vec![column]).evaluate(domain);
+        let fft_time = now.elapsed();
+        let now = Instant::now();
+        <S::MerkleTree as MatrixMerkleTree<S::Fp>>::from_matrix(&evaluations);
+        let hash_time = now.elapsed();
+
+        let num_butterflies = n / 2 * log_size as usize;
+        let num_hashes = 2 * n - 1;
+        Self {
+            butterflies_per_second: num_butterflies as f64 / fft_time.as_secs_f64(),
+            hashes_per_second: num_hashes as f64 / hash_time.as_secs_f64(),
+        }
+    }
+}
+
+/// Estimates the cost of proving a trace of `air` without generating the
+/// trace. Useful for choosing proof options or sizing hardware. Wall-clock
+/// time can be derived with [`CostEstimate::wall_clock`].
+pub fn estimate_cost<S: Stark>(air: &Air<S::AirConfig>) -> CostEstimate {
+    let trace_len = air.trace_len();
+    let options = air.options();
+    let lde_size = air.lde_domain().size();
+    let num_base_cols = S::AirConfig::NUM_BASE_COLUMNS;
+    let num_extension_cols = S::AirConfig::NUM_EXTENSION_COLUMNS;
+    let num_composition_cols = air.num_composition_trace_columns();
+    let num_trace_cols = num_base_cols + num_extension_cols;
+
+    let mut num_ffts = 0;
+    let mut num_butterflies = 0;
+    let mut fft = |size: usize, count: usize| {
+        num_ffts += count;
+        num_butterflies += count * size / 2 * size.ilog2() as usize;
+    };
+    // interpolate and extend the execution trace
+    fft(trace_len, num_trace_cols);
+    fft(lde_size, num_trace_cols);
+    // interpolate the composition polynomial and extend its columns
+    fft(air.ce_domain().size(), 1);
+    fft(lde_size, num_composition_cols);
+    // evaluate the DEEP composition polynomial
+    fft(lde_size, 1);
+    // each FRI layer interpolates and evaluates its projection
+    let fri_options = options.into_fri_options();
+    let folding_factor = usize::from(options.fri_folding_factor);
+    let num_fri_layers = fri_options.num_layers(lde_size);
+    let mut fri_layer_size = lde_size;
+    let mut fri_layer_sizes = Vec::new();
+    for _ in 0..num_fri_layers {
+        fft(fri_layer_size, 1);
+        fri_layer_size /= folding_factor;
+        fft(fri_layer_size, 1);
+        fri_layer_sizes.push(fri_layer_size);
+    }
+
+    // a tree with n leaves requires n leaf hashes and n - 1 node hashes
+    let num_trace_trees = if num_extension_cols == 0 { 2 } else { 3 };
+    let num_hashes = num_trace_trees * (2 * lde_size - 1)
+        + fri_layer_sizes.iter().map(|n| 2 * n - 1).sum::<usize>()
+        + (1 << options.grinding_factor);
+
+    let fp_size = core::mem::size_of::<S::Fp>();
+    let fq_size = core::mem::size_of::<S::Fq>();
+    let digest_size = core::mem::size_of::<S::Digest>();
+    let trace_bytes = num_base_cols * fp_size + num_extension_cols * fq_size;
+    let composition_bytes = num_composition_cols * fq_size;
+    // polynomials, LDEs and merkle trees (which store 2n digests) are all kept
+    // until the trace queries are generated
+    let memory_bytes = trace_len * (trace_bytes + composition_bytes)
+        + lde_size * (trace_bytes + composition_bytes + fq_size)
+        + num_trace_trees * 2 * lde_size * digest_size
+        + fri_layer_sizes
+            .iter()
+            .map(|n| n * (fq_size + 2 * digest_size))
+            .sum::<usize>();
+
+    CostEstimate {
+        memory_bytes,
+        num_ffts,
+        num_butterflies,
+        num_hashes,
+        num_composition_columns: num_composition_cols,
+        num_fri_layers,
+    }
+}
+
+/// Evaluates the composition constraint and returns the composition trace
+/// polynomials.
+///
+/// Constraints are only evaluated over the constraint evaluation domain (of
+/// size `trace_len * ce_blowup_factor`) rather than the entire LDE domain. The
+/// first `n` values of a bit-reversed LDE are the evaluations over a coset of
+/// size `n` so when the LDE blowup factor exceeds the degree of the
+/// constraints there is no need to evaluate the constraints over the remaining
+/// values. The composition polynomial is extended to the LDE domain later by
+/// evaluating the returned columns. Returns [`ProvingError::Cancelled`] if
+/// the context's cancellation token is cancelled during the evaluation.
+pub(crate) fn gen_composition_trace_polys<A: AirConfig>(
+    context: &ProverContext,
+    air: &Air<A>,
+    challenges: &Challenges<A::Fq>,
+    hints: &Hints<A::Fq>,
+    composition_coeffs: &[A::Fq],
+    base_trace_lde: &mut Matrix<A::Fp>,
+    mut extension_trace_lde: Option<&mut Matrix<A::Fq>>,
+) -> Result<Matrix<A::Fq>, ProvingError> {
+    // To prevent allocating more memory, just re-order the values in the trace to
+    // be in natural order. Note that for the remainder of the protocol the trace
+    // should entirely be in bit-reversed order hence why this function is
+    // called again at the end.
+    let ce_lde_xs = air.ce_domain();
+    let ce_domain_size = ce_lde_xs.size();
+    assert!(ce_domain_size <= air.lde_domain().size());
+    let base_trace_ce_cols = bit_reverse_ce_trace(ce_domain_size, base_trace_lde);
+    let extension_trace_ce_cols = extension_trace_lde
+        .as_deref_mut()
+        .map(|t| bit_reverse_ce_trace(ce_domain_size, t));
+    let x_lde = context.twiddle_cache().domain_elements(ce_lde_xs);
+
+    let composition_evals = A::eval_constraint(
+        air.composition_constraint(),
+        challenges,
+        hints,
+        composition_coeffs,
+        air.public_polynomials(),
+        air.ce_blowup_factor(),
+        x_lde.to_vec_in(GpuAllocator),
+        &base_trace_ce_cols,
+        extension_trace_ce_cols.as_deref(),
+        context.cancellation_token(),
+    );
+    drop((base_trace_ce_cols, extension_trace_ce_cols));
+
+    bit_reverse_ce_trace(ce_domain_size, base_trace_lde);
+    if let Some(t) = extension_trace_lde {
+        bit_reverse_ce_trace(ce_domain_size, t);
+    }
+    if context.cancellation_token().is_cancelled() {
+        return Err(ProvingError::Cancelled);
+    }
+
+    // split the composition polynomial into columns. See
+    // `Air::num_composition_trace_columns` for details. Coefficients past the
+    // composition degree are zero for a valid trace.
+    let composition_poly =
+        GpuVec::try_from(composition_evals.into_polynomials(ce_lde_xs)).unwrap();
+    let num_composition_cols = air.num_composition_trace_columns();
+    let mut composition_trace_cols = (0..num_composition_cols)
+        .map(|_| {
+            let mut column = Vec::with_capacity_in(air.trace_len(), GpuAllocator);
+            column.resize(air.trace_len(), A::Fq::zero());
+            column
+        })
+        .collect::<Vec<_>>();
+    let composition_len = core::cmp::min(composition_poly.len(), air.composition_degree() + 1);
+    for (i, &coeff) in composition_poly[..composition_len].iter().enumerate() {
+        composition_trace_cols[i % num_composition_cols][i / num_composition_cols] = coeff;
+    }
+    Ok(Matrix::new(composition_trace_cols))
+}
+
+/// Returns the bit-reversed evaluations of `polys` over `domain`. The
+/// evaluations are written to buffers from the context's scratch arena and
+/// sharded across the context's devices. The LDE is evaluated one coset of the
+/// trace domain at a time and each chunk of rows is passed to `on_rows` as soon
+/// as it's evaluated. The cancellation token is checked before each chunk.
+fn extend<F>(
+    context: &ProverContext,
+    polys: &Matrix<F>,
+    domain: Radix2EvaluationDomain<F::FftField>,
+    mut on_rows: impl FnMut(&Matrix<F>),
+) -> Result<Matrix<F>, ProvingError>
+where
+    F: Field + GpuField + DomainCoeff<F::FftField>,
+    F::FftField: FftField,
+{
+    let scratch_arena = context.scratch_arena();
+    let chunk_size = polys.num_rows();
+    assert!(chunk_size.is_power_of_two() && chunk_size <= domain.size());
+    let num_chunks = domain.size() / chunk_size;
+    let take_matrix = |n| {
+        Matrix::new(
+            (0..polys.num_cols())
+                .map(|_| scratch_arena.take(n))
+                .collect(),
+        )
+    };
+    let mut lde = take_matrix(domain.size());
+    let mut chunk = take_matrix(chunk_size);
+    for i in 0..num_chunks {
+        if context.cancellation_token().is_cancelled() {
+            scratch_arena.recycle_matrix(chunk);
+            scratch_arena.recycle_matrix(lde);
+            return Err(ProvingError::Cancelled);
+        }
+        // rows `i * n..(i + 1) * n` of the bit-reversed LDE are the bit-reversed
+        // evaluations over the coset `offset * ω^bit_reverse_index(i) * <ω^b>`
+        let power = bit_reverse_index(num_chunks, i) as u64;
+        let offset = domain.coset_offset() * domain.group_gen().pow([power]);
+        let coset = Radix2EvaluationDomain::new_coset(chunk_size, offset).unwrap();
+        for (column, poly) in zip(&mut chunk.0, &polys.0) {
+            column[..poly.len()].copy_from_slice(poly);
+            column[poly.len()..].fill(F::zero());
+        }
+        chunk = chunk.into_bit_reversed_evaluations_on(coset, context.devices());
+        on_rows(&chunk);
+        let rows = i * chunk_size..(i + 1) * chunk_size;
+        for (column, evaluations) in zip(&mut lde.0, &chunk.0) {
+            column[rows.clone()].copy_from_slice(evaluations);
+        }
+    }
+    scratch_arena.recycle_matrix(chunk);
+    Ok(lde)
+}
+
 /// Bit reverses the first ce_domain_size many values of the matrix columns.
 /// Returns a slice to the portion of the columns that were bit reversed
 fn bit_reverse_ce_trace<F: Field>(ce_domain_size: usize, trace: &mut Matrix<F>) -> Vec<&[F]> {
@@ -0,0 +1,206 @@
+//! Canonical encoding of public inputs for binding them to the transcript
+
+use crate::air::AirConfig;
+use crate::hash::HashFn;
+use crate::Air;
+use alloc::string::String;
+use alloc::vec::Vec;
+use ark_ff::CubicExtConfig;
+use ark_ff::CubicExtField;
+use ark_ff::Fp;
+use ark_ff::FpConfig;
+use ark_ff::QuadExtConfig;
+use ark_ff::QuadExtField;
+use ark_serialize::CanonicalSerialize;
+
+/// Public inputs with a canonical byte encoding. Fixed size values (integers
+/// and field elements) are encoded as is and variable length values (strings,
+/// vectors and options) are prefixed with their length so no two inputs share
+/// an encoding.
+///
+/// Implement this for a struct with [`crate::impl_public_inputs`].
+pub trait PublicInputs {
+    /// Appends the canonical encoding of these inputs to `buffer`
+    fn encode(&self, buffer: &mut Vec<u8>);
+
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.encode(&mut buffer);
+        buffer
+    }
+
+    /// Hashes the canonical encoding of these inputs
+    fn hash<H: HashFn>(&self) -> H::Digest {
+        H::hash_chunks([&*self.to_canonical_bytes()])
+    }
+}
+
+/// Returns the canonical encoding of the public inputs, trace length and
+/// proof options of `air`. Intended as the seed of the public coin.
+pub fn public_coin_seed<C: AirConfig>(air: &Air<C>) -> Vec<u8>
+where
+    C::PublicInputs: PublicInputs,
+{
+    let mut seed = air.public_inputs().to_canonical_bytes();
+    air.trace_len().encode(&mut seed);
+    air.options().serialize_compressed(&mut seed).unwrap();
+    seed
+}
+
+macro_rules! impl_int {
+    ($($t:ty),*) => {
+        $(
+            impl PublicInputs for $t {
+                fn encode(&self, buffer: &mut Vec<u8>) {
+                    buffer.extend(self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl PublicInputs for usize {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        // encoded as a u64 so the encoding doesn't depend on the platform
+        (*self as u64).encode(buffer);
+    }
+}
+
+impl PublicInputs for bool {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        u8::from(*self).encode(buffer);
+    }
+}
+
+impl PublicInputs for str {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        self.as_bytes().encode(buffer);
+    }
+}
+
+impl PublicInputs for String {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        self.as_str().encode(buffer);
+    }
+}
+
+impl<T: PublicInputs> PublicInputs for [T] {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        self.len().encode(buffer);
+        for value in self {
+            value.encode(buffer);
+        }
+    }
+}
+
+impl<T: PublicInputs> PublicInputs for Vec<T> {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        self.as_slice().encode(buffer);
+    }
+}
+
+impl<T: PublicInputs, const N: usize> PublicInputs for [T; N] {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        for value in self {
+            value.encode(buffer);
+        }
+    }
+}
+
+impl<T: PublicInputs> PublicInputs for Option<T> {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        self.is_some().encode(buffer);
+        if let Some(value) = self {
+            value.encode(buffer);
+        }
+    }
+}
+
+impl<T: PublicInputs + ?Sized> PublicInputs for &T {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        (**self).encode(buffer);
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: PublicInputs),+> PublicInputs for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn encode(&self, buffer: &mut Vec<u8>) {
+                let ($($name,)+) = self;
+                $($name.encode(buffer);)+
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+
+impl<P: FpConfig<N>, const N: usize> PublicInputs for Fp<P, N> {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        self.serialize_uncompressed(buffer).unwrap();
+    }
+}
+
+impl<P: QuadExtConfig> PublicInputs for QuadExtField<P> {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        self.serialize_uncompressed(buffer).unwrap();
+    }
+}
+
+impl<P: CubicExtConfig> PublicInputs for CubicExtField<P> {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        self.serialize_uncompressed(buffer).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PublicInputs;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    struct Claim {
+        program: String,
+        input: Vec<u8>,
+        num_steps: usize,
+    }
+
+    crate::impl_public_inputs!(Claim {
+        program,
+        input,
+        num_steps
+    });
+
+    #[test]
+    fn variable_length_inputs_are_unambiguous() {
+        let a = ("ab".to_string(), "c".to_string());
+        let b = ("a".to_string(), "bc".to_string());
+
+        assert_ne!(a.to_canonical_bytes(), b.to_canonical_bytes());
+    }
+
+    #[test]
+    fn struct_fields_are_encoded_in_order() {
+        let claim = Claim {
+            program: "+.".into(),
+            input: vec![7],
+            num_steps: 2,
+        };
+
+        let bytes = claim.to_canonical_bytes();
+
+        let expected = [
+            &[2, 0, 0, 0, 0, 0, 0, 0, b'+', b'.'][..],
+            &[1, 0, 0, 0, 0, 0, 0, 0, 7],
+            &[2, 0, 0, 0, 0, 0, 0, 0],
+        ]
+        .concat();
+        assert_eq!(expected, bytes);
+    }
+}
@@ -1,6 +1,10 @@
+use crate::hash::CanonicalLeafEncoder;
 use crate::hash::Digest;
 use crate::hash::ElementHashFn;
 use crate::hash::HashFn;
+use crate::hash::LeafByteOrder;
+use crate::serialize::ByteOrder;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use ark_ff::Field;
 use rand::Rng;
@@ -9,7 +13,9 @@ use rand::RngCore;
 use rayon::prelude::*;
 use std::collections::BTreeSet;
 use std::fmt::Debug;
+use std::fmt::Display;
 use std::marker::PhantomData;
+use std::sync::Mutex;
 
 // TODO: alternative approach
 // trait Seedable<T>: Sync + Debug {
@@ -23,7 +29,16 @@ use std::marker::PhantomData;
 // Seedable<Self::Fp> + Seedable<Self::Fp> + Seedable<Self::Fq> +
 // Seedable<FriRemainder<Self::Fq>>
 
+/// Number of nonces checked in parallel before looking for a valid nonce
+#[cfg(feature = "parallel")]
+const GRINDING_BATCH_SIZE: usize = 1 << 16;
+
 /// `PublicCoin` trait adapted from Winterfell
+///
+/// This is the Fiat-Shamir transcript shared by the prover and verifier. An
+/// alternative transcript (e.g. one that matches an external verifier) can be
+/// used by implementing this trait and setting it as
+/// [`crate::stark::Stark::PublicCoin`].
 pub trait PublicCoin: Sized + Send + Sync + Debug {
     type Digest: Digest;
     type Field: Field;
@@ -42,40 +57,132 @@ pub trait PublicCoin: Sized + Send + Sync + Debug {
 
     fn draw(&mut self) -> Self::Field;
 
+    /// Returns an independent coin for drawing the challenges of `sub_coin`.
+    /// The sub-coin is seeded with the current state and the domain separator
+    /// of `sub_coin`. The state of this coin doesn't change.
+    fn fork(&self, sub_coin: SubCoin) -> Self;
+
     /// Draws a maximum of n unique queries in the range `[0, domain_size)`
     fn draw_queries(&mut self, max_n: usize, domain_size: usize) -> BTreeSet<usize>;
 
+    /// Returns the smallest valid nonce. Nonces are searched in batches across
+    /// all threads so the result is the same regardless of the number of
+    /// threads.
     fn grind_proof_of_work(&self, proof_of_work_bits: u8) -> Option<u64> {
         #[cfg(not(feature = "parallel"))]
         return (1..u64::MAX).find(|&nonce| self.verify_proof_of_work(proof_of_work_bits, nonce));
         #[cfg(feature = "parallel")]
         return (1..u64::MAX)
-            .into_par_iter()
-            .find_any(|&nonce| self.verify_proof_of_work(proof_of_work_bits, nonce));
+            .step_by(GRINDING_BATCH_SIZE)
+            .find_map(|batch_start| {
+                let batch_end = batch_start.saturating_add(GRINDING_BATCH_SIZE as u64);
+                (batch_start..batch_end)
+                    .into_par_iter()
+                    .find_first(|&nonce| self.verify_proof_of_work(proof_of_work_bits, nonce))
+            });
     }
 
     fn verify_proof_of_work(&self, proof_of_work_bits: u8, nonce: u64) -> bool;
 
     fn security_level_bits() -> u32;
+
+    /// Returns the name of the hash function used to draw randomness
+    fn hash_fn_name() -> &'static str;
+
+    /// Returns the byte order of field elements absorbed by the coin
+    fn byte_order() -> ByteOrder {
+        ByteOrder::Little
+    }
+}
+
+/// Stages of the protocol that draw challenges. Each stage draws from its own
+/// sub-coin forked from the public coin (see [`PublicCoin::fork`]) while the
+/// public coin only absorbs the transcript. Challenges of different stages are
+/// independent even if they're drawn from the same transcript state and every
+/// challenge can be attributed to a stage when auditing a transcript.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SubCoin {
+    /// Challenges used to build the extension trace
+    TraceExtension,
+    /// Coefficients of the composition polynomial
+    Composition,
+    /// Out-of-domain point and DEEP composition coefficients
+    Deep,
+    /// FRI folding and batching coefficients
+    Fri,
 }
 
-pub struct PublicCoinImpl<F: Field, H: HashFn> {
+impl SubCoin {
+    pub const ALL: [Self; 4] = [
+        Self::TraceExtension,
+        Self::Composition,
+        Self::Deep,
+        Self::Fri,
+    ];
+
+    /// Returns the domain separator absorbed by the sub-coin
+    pub const fn domain(self) -> u64 {
+        u64::from_be_bytes(match self {
+            Self::TraceExtension => *b"extend\0\0",
+            Self::Composition => *b"compose\0",
+            Self::Deep => *b"deep\0\0\0\0",
+            Self::Fri => *b"fri\0\0\0\0\0",
+        })
+    }
+}
+
+impl Display for SubCoin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TraceExtension => write!(f, "trace extension"),
+            Self::Composition => write!(f, "composition"),
+            Self::Deep => write!(f, "deep"),
+            Self::Fri => write!(f, "fri"),
+        }
+    }
+}
+
+/// Domain separator absorbed before query positions are drawn
+pub const QUERY_POSITIONS_DOMAIN: u64 = u64::from_be_bytes(*b"queries\0");
+
+/// Derives the query positions in the range `[0, lde_domain_size)`. Must be
+/// called once the trace, composition and FRI commitments have all been
+/// absorbed by `public_coin`. The proof of work nonce (zero without grinding)
+/// and [`QUERY_POSITIONS_DOMAIN`] are absorbed first so positions are bound
+/// to the whole transcript and never drawn from the same state as another
+/// challenge. Shared by the prover and verifier so both always derive the
+/// same positions.
+pub fn derive_query_positions<P: PublicCoin>(
+    public_coin: &mut P,
+    pow_nonce: u64,
+    num_queries: usize,
+    lde_domain_size: usize,
+) -> Vec<usize> {
+    public_coin.reseed_with_int(pow_nonce);
+    public_coin.reseed_with_int(QUERY_POSITIONS_DOMAIN);
+    Vec::from_iter(public_coin.draw_queries(num_queries, lde_domain_size))
+}
+
+/// Public coin that absorbs field elements written in the byte order of `E`
+/// (see [`LeafByteOrder`]). Use the same byte order as the Merkle tree's leaf
+/// encoder so external verifiers only need to match one encoding.
+pub struct PublicCoinImpl<F: Field, H: HashFn, E: LeafByteOrder = CanonicalLeafEncoder> {
     pub seed: H::Digest,
     counter: u64,
     bytes: Vec<u8>,
-    _phantom: PhantomData<F>,
+    _phantom: PhantomData<(F, E)>,
 }
 
-impl<F: Field, H: ElementHashFn<F>> PublicCoinImpl<F, H> {
+impl<F: Field, H: ElementHashFn<F>, E: LeafByteOrder> PublicCoinImpl<F, H, E> {
     fn reseed_with_field_element(&mut self, val: &F) {
-        let val_digest = H::hash_elements([*val]);
+        let val_digest = H::hash_elements_in_order([*val], E::BYTE_ORDER);
         self.seed = H::merge(&self.seed, &val_digest);
         self.counter = 0;
         self.bytes = Vec::new();
     }
 }
 
-impl<F: Field, H: HashFn> Debug for PublicCoinImpl<F, H> {
+impl<F: Field, H: HashFn, E: LeafByteOrder> Debug for PublicCoinImpl<F, H, E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PublicCoinImpl")
             .field("seed", &self.seed)
@@ -85,7 +192,7 @@ impl<F: Field, H: HashFn> Debug for PublicCoinImpl<F, H> {
     }
 }
 
-impl<F: Field, H: HashFn> PublicCoinImpl<F, H> {
+impl<F: Field, H: HashFn, E: LeafByteOrder> PublicCoinImpl<F, H, E> {
     /// Updates the state by incrementing the counter and returns hash(seed ||
     /// counter)
     fn gen_next(&mut self) -> H::Digest {
@@ -95,7 +202,7 @@ impl<F: Field, H: HashFn> PublicCoinImpl<F, H> {
     }
 }
 
-impl<F: Field, H: ElementHashFn<F>> PublicCoin for PublicCoinImpl<F, H> {
+impl<F: Field, H: ElementHashFn<F>, E: LeafByteOrder> PublicCoin for PublicCoinImpl<F, H, E> {
     type Digest = H::Digest;
     type Field = F;
 
@@ -126,9 +233,13 @@ impl<F: Field, H: ElementHashFn<F>> PublicCoin for PublicCoinImpl<F, H> {
         self.bytes = Vec::new();
     }
 
+    fn fork(&self, sub_coin: SubCoin) -> Self {
+        Self::new(H::merge_with_int(&self.seed, sub_coin.domain()))
+    }
+
     fn verify_proof_of_work(&self, proof_of_work_bits: u8, nonce: u64) -> bool {
         let digest = H::merge_with_int(&self.seed, nonce);
-        leading_zeros(&digest.as_bytes()) >= u32::from(proof_of_work_bits)
+        leading_zeros(&digest.as_bytes()[..H::Digest::LEN]) >= u32::from(proof_of_work_bits)
     }
 
     fn draw(&mut self) -> F {
@@ -142,20 +253,28 @@ impl<F: Field, H: ElementHashFn<F>> PublicCoin for PublicCoinImpl<F, H> {
     fn security_level_bits() -> u32 {
         H::COLLISION_RESISTANCE
     }
+
+    fn hash_fn_name() -> &'static str {
+        H::NAME
+    }
+
+    fn byte_order() -> ByteOrder {
+        E::BYTE_ORDER
+    }
 }
 
-impl<F: Field, H: HashFn> Iterator for PublicCoinImpl<F, H> {
+impl<F: Field, H: HashFn, E: LeafByteOrder> Iterator for PublicCoinImpl<F, H, E> {
     type Item = u8;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.bytes.is_empty() {
-            self.bytes = self.gen_next().as_bytes().to_vec();
+            self.bytes = self.gen_next().as_bytes()[..H::Digest::LEN].to_vec();
         }
         self.bytes.pop()
     }
 }
 
-impl<F: Field, H: HashFn> RngCore for PublicCoinImpl<F, H> {
+impl<F: Field, H: HashFn, E: LeafByteOrder> RngCore for PublicCoinImpl<F, H, E> {
     fn next_u32(&mut self) -> u32 {
         let mut bytes = [0; 4];
         self.fill_bytes(&mut bytes);
@@ -178,6 +297,114 @@ impl<F: Field, H: HashFn> RngCore for PublicCoinImpl<F, H> {
     }
 }
 
+/// Interaction with a [`RecordingPublicCoin`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranscriptEvent<D, F> {
+    ReseedWithDigest(D),
+    ReseedWithFieldElements(Vec<F>),
+    ReseedWithInt(u64),
+    /// A sub-coin was forked. Draws that follow are from the sub-coin until
+    /// the next fork or reseed.
+    Fork(SubCoin),
+    Draw(F),
+    DrawQueries(Vec<usize>),
+}
+
+/// Shared log of transcript events
+pub type TranscriptLog<D, F> = Arc<Mutex<Vec<TranscriptEvent<D, F>>>>;
+
+/// Public coin that records every interaction with an inner public coin. Useful
+/// for auditing a transcript or comparing it against an external verifier. The
+/// log is shared so it can be inspected after the coin is dropped.
+#[derive(Debug)]
+pub struct RecordingPublicCoin<P: PublicCoin> {
+    inner: P,
+    log: TranscriptLog<P::Digest, P::Field>,
+}
+
+impl<P: PublicCoin> RecordingPublicCoin<P> {
+    pub fn with_log(inner: P, log: TranscriptLog<P::Digest, P::Field>) -> Self {
+        Self { inner, log }
+    }
+
+    pub fn log(&self) -> TranscriptLog<P::Digest, P::Field> {
+        Arc::clone(&self.log)
+    }
+
+    fn record(&self, event: TranscriptEvent<P::Digest, P::Field>) {
+        self.log.lock().unwrap().push(event);
+    }
+}
+
+impl<P: PublicCoin> PublicCoin for RecordingPublicCoin<P> {
+    type Digest = P::Digest;
+    type Field = P::Field;
+
+    fn new(digest: Self::Digest) -> Self {
+        Self::with_log(P::new(digest), TranscriptLog::default())
+    }
+
+    fn reseed_with_digest(&mut self, val: &Self::Digest) {
+        self.record(TranscriptEvent::ReseedWithDigest(val.clone()));
+        self.inner.reseed_with_digest(val);
+    }
+
+    fn reseed_with_field_elements(&mut self, vals: &[Self::Field]) {
+        self.record(TranscriptEvent::ReseedWithFieldElements(vals.to_vec()));
+        self.inner.reseed_with_field_elements(vals);
+    }
+
+    fn reseed_with_field_element_vector(&mut self, vector: &[Self::Field]) {
+        self.record(TranscriptEvent::ReseedWithFieldElements(vector.to_vec()));
+        self.inner.reseed_with_field_element_vector(vector);
+    }
+
+    fn reseed_with_int(&mut self, val: u64) {
+        self.record(TranscriptEvent::ReseedWithInt(val));
+        self.inner.reseed_with_int(val);
+    }
+
+    fn draw(&mut self) -> Self::Field {
+        let val = self.inner.draw();
+        self.record(TranscriptEvent::Draw(val));
+        val
+    }
+
+    /// The sub-coin records to the same log
+    fn fork(&self, sub_coin: SubCoin) -> Self {
+        self.record(TranscriptEvent::Fork(sub_coin));
+        Self::with_log(self.inner.fork(sub_coin), self.log())
+    }
+
+    fn draw_queries(&mut self, max_n: usize, domain_size: usize) -> BTreeSet<usize> {
+        let queries = self.inner.draw_queries(max_n, domain_size);
+        self.record(TranscriptEvent::DrawQueries(
+            queries.iter().copied().collect(),
+        ));
+        queries
+    }
+
+    fn grind_proof_of_work(&self, proof_of_work_bits: u8) -> Option<u64> {
+        self.inner.grind_proof_of_work(proof_of_work_bits)
+    }
+
+    fn verify_proof_of_work(&self, proof_of_work_bits: u8, nonce: u64) -> bool {
+        self.inner.verify_proof_of_work(proof_of_work_bits, nonce)
+    }
+
+    fn security_level_bits() -> u32 {
+        P::security_level_bits()
+    }
+
+    fn hash_fn_name() -> &'static str {
+        P::hash_fn_name()
+    }
+
+    fn byte_order() -> ByteOrder {
+        P::byte_order()
+    }
+}
+
 pub fn leading_zeros(hash: &[u8]) -> u32 {
     let mut zeros = 0;
     for byte in hash {
@@ -194,3 +421,12 @@ pub fn leading_zeros(hash: &[u8]) -> u32 {
 pub fn draw_multiple<P: PublicCoin>(public_coin: &mut P, n: usize) -> Vec<P::Field> {
     (0..n).map(|_| public_coin.draw()).collect()
 }
+
+/// Draws `n` challenges from a fork of `public_coin` for `sub_coin`
+pub fn draw_from_sub_coin<P: PublicCoin>(
+    public_coin: &P,
+    sub_coin: SubCoin,
+    n: usize,
+) -> Vec<P::Field> {
+    draw_multiple(&mut public_coin.fork(sub_coin), n)
+}
@@ -0,0 +1,592 @@
+//! Slow reference verifier used to cross-check the production verifier.
+//!
+//! Everything is computed the obvious way: expressions are evaluated as
+//! trees, polynomials with explicit powers and FRI layers are folded with
+//! Lagrange interpolation. Only the transcript, Merkle proofs and proof
+//! layout are shared with [`crate::verifier`].
+
+use crate::air::AirConfig;
+use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::CompositionItem;
+use crate::debug::FriFoldFailure;
+use crate::debug::MerkleRootCheck;
+use crate::debug::VerificationReport;
+use crate::fri;
+use crate::hints::Hints;
+use crate::merkle::MatrixMerkleTree;
+use crate::merkle::MerkleTree;
+use crate::merkle::TreeTag;
+use crate::proof::ProofHeader;
+use crate::proof::PROOF_VERSION;
+use crate::random::draw_from_sub_coin;
+use crate::random::PublicCoin;
+use crate::random::SubCoin;
+use crate::random::QUERY_POSITIONS_DOMAIN;
+use crate::stark::Stark;
+use crate::trace::query_openings;
+use crate::utils::FieldVariant;
+use crate::verifier::default_verify;
+use crate::verifier::VerificationError;
+use crate::Air;
+use crate::Proof;
+use crate::ProofOptions;
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_ff::One;
+use ark_ff::Zero;
+
+/// Verifies `proof` with both the production and the reference verifier and
+/// returns the result of the production verifier.
+///
+/// # Panics
+/// Panics if the verifiers disagree on whether the proof is valid
+pub fn differential_verify<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    required_security_bits: u32,
+) -> Result<(), VerificationError> {
+    let reference = reference_verify(this, proof.clone(), required_security_bits);
+    let production = default_verify(this, proof, required_security_bits).map(|_| ());
+    assert_eq!(
+        production.is_ok(),
+        reference.is_ok(),
+        "verifiers disagree: production returned {production:?} but reference returned \
+         {reference:?}"
+    );
+    production
+}
+
+/// Verifies a proof without any of the optimizations of
+/// [`crate::verifier::default_verify`]. Intended for testing only.
+pub fn reference_verify<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    required_security_bits: u32,
+) -> Result<(), VerificationError> {
+    let mut report = VerificationReport::new();
+    reference_verify_with_report(this, proof, required_security_bits, &mut report)
+}
+
+/// Same as [`reference_verify`] but records the steps of verification in
+/// `report`. See [`crate::debug::explain_verification`].
+#[allow(clippy::too_many_lines)]
+pub fn reference_verify_with_report<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    required_security_bits: u32,
+    report: &mut VerificationReport<S::Fq, S::Digest>,
+) -> Result<(), VerificationError> {
+    use VerificationError::*;
+
+    if proof.header.version != PROOF_VERSION {
+        return Err(UnsupportedProofVersion {
+            version: proof.header.version,
+        });
+    }
+
+    if proof.security_level_bits() < required_security_bits {
+        return Err(InvalidProofSecurity);
+    }
+
+    let Proof {
+        header,
+        trace_len,
+        base_trace_commitment,
+        extension_trace_commitment,
+        composition_trace_commitment,
+        fri_proof,
+        pow_nonce,
+        trace_queries,
+        execution_trace_ood_evals,
+        composition_trace_ood_evals,
+    } = proof;
+    let options = header.options;
+
+    let air = this
+        .air(trace_len, options)
+        .map_err(|source| InvalidAir { source })?;
+    let expected = ProofHeader::new::<S>(&air);
+    if !expected.air_version.is_compatible_with(&header.air_version) {
+        return Err(AirVersionMismatch {
+            expected: expected.air_version,
+            actual: header.air_version,
+        });
+    }
+    // compatible versions only differ by their patch or minor version
+    if (ProofHeader {
+        air_version: expected.air_version,
+        ..header
+    }) != expected
+    {
+        return Err(AirMismatch);
+    }
+    if usize::from(options.digest_len) != S::Digest::LEN {
+        return Err(DigestLenMismatch {
+            expected: S::Digest::LEN,
+            actual: options.digest_len,
+        });
+    }
+    let num_extension_columns = S::AirConfig::NUM_EXTENSION_COLUMNS;
+    if extension_trace_commitment.is_some() != (num_extension_columns > 0) {
+        return Err(ExtensionTraceCommitmentMismatch {
+            num_extension_columns,
+        });
+    }
+
+    // replay the transcript
+    let mut public_coin = this.gen_public_coin(&air);
+    air.trace_info().bind(&mut public_coin);
+    report.record_step("initial seed", &[], &public_coin);
+    public_coin.reseed_with_digest(&base_trace_commitment);
+    let challenges = Challenges::new(draw_from_sub_coin(
+        &public_coin,
+        SubCoin::TraceExtension,
+        air.num_challenges(),
+    ));
+    report.record_step("air challenges", &challenges, &public_coin);
+    let hints = air.gen_hints(&challenges);
+    if let Some(commitment) = &extension_trace_commitment {
+        public_coin.reseed_with_digest(commitment);
+    }
+    let composition_coeffs = draw_from_sub_coin(
+        &public_coin,
+        SubCoin::Composition,
+        air.num_composition_constraint_coeffs(),
+    );
+    report.record_step(
+        "composition coefficients",
+        &composition_coeffs,
+        &public_coin,
+    );
+    public_coin.reseed_with_digest(&composition_trace_commitment);
+
+    let trace_arguments = air.trace_arguments().into_iter().collect::<Vec<_>>();
+    if execution_trace_ood_evals.len() != trace_arguments.len() {
+        return Err(InvalidNumExecutionTraceOodEvaluations {
+            expected: trace_arguments.len(),
+            actual: execution_trace_ood_evals.len(),
+        });
+    }
+    let num_composition_columns = air.num_composition_trace_columns();
+    if composition_trace_ood_evals.len() != num_composition_columns {
+        return Err(InvalidNumCompositionTraceOodEvaluations {
+            expected: num_composition_columns,
+            actual: composition_trace_ood_evals.len(),
+        });
+    }
+
+    let z = public_coin.fork(SubCoin::Deep).draw();
+    report.record_step("ood point", &[z], &public_coin);
+    let mut ood_evals = execution_trace_ood_evals.clone();
+    ood_evals.extend(&composition_trace_ood_evals);
+    public_coin.reseed_with_field_elements(&ood_evals);
+
+    // check the composition constraint at the OOD point
+    let trace_ood_evals = trace_arguments
+        .iter()
+        .copied()
+        .zip(execution_trace_ood_evals.iter().copied())
+        .collect::<BTreeMap<(usize, isize), S::Fq>>();
+    let expected_composition_eval = composition_constraint_evaluation(
+        &air,
+        &composition_coeffs,
+        &challenges,
+        &hints,
+        &trace_ood_evals,
+        z,
+    );
+    let z_n = pow(z, num_composition_columns);
+    let composition_eval = composition_trace_ood_evals
+        .iter()
+        .enumerate()
+        .map(|(i, &eval)| pow(z, i) * eval)
+        .sum::<S::Fq>();
+    report.ood_evaluation = Some((expected_composition_eval, composition_eval));
+    if composition_eval != expected_composition_eval {
+        return Err(InconsistentOodConstraintEvaluations);
+    }
+
+    let deep_coeffs = this.gen_deep_coeffs(&mut public_coin.fork(SubCoin::Deep), &air);
+    let mut fri_alphas = Vec::new();
+    for layer in &fri_proof.layers {
+        public_coin.reseed_with_digest(&layer.commitment);
+        fri_alphas.push(public_coin.fork(SubCoin::Fri).draw());
+    }
+    report.record_step("fri alphas", &fri_alphas, &public_coin);
+    public_coin.reseed_with_field_element_vector(&fri_proof.remainder_coeffs);
+
+    let valid_pow = if options.grinding_factor == 0 {
+        pow_nonce == 0
+    } else {
+        public_coin.verify_proof_of_work(options.grinding_factor, pow_nonce)
+    };
+    if !valid_pow {
+        return Err(FriProofOfWork);
+    }
+
+    let lde_domain_size = trace_len * usize::from(options.lde_blowup_factor);
+    public_coin.reseed_with_int(pow_nonce);
+    public_coin.reseed_with_int(QUERY_POSITIONS_DOMAIN);
+    let positions = public_coin
+        .draw_queries(options.num_queries.into(), lde_domain_size)
+        .into_iter()
+        .collect::<Vec<usize>>();
+    report.record_step("query positions", &[], &public_coin);
+    report.query_positions = positions.clone();
+
+    // check the trace queries
+    let openings = query_openings(
+        &positions,
+        [
+            S::AirConfig::NUM_BASE_COLUMNS,
+            S::AirConfig::NUM_EXTENSION_COLUMNS,
+            num_composition_columns,
+        ],
+        &trace_queries.openings,
+    )
+    .ok_or(InvalidNumQueryValues)?;
+    let base_rows = openings
+        .iter()
+        .map(|opening| opening.base_trace_row)
+        .collect::<Vec<_>>();
+    let extension_rows = openings
+        .iter()
+        .map(|opening| opening.extension_trace_row)
+        .collect::<Vec<_>>();
+    let composition_rows = openings
+        .iter()
+        .map(|opening| opening.composition_trace_row)
+        .collect::<Vec<_>>();
+    if !verify_rows::<S, S::Fp>(
+        report,
+        "base trace",
+        TreeTag::BaseTrace,
+        &base_trace_commitment,
+        lde_domain_size,
+        &positions,
+        &base_rows,
+        trace_queries.base_trace_proof,
+    ) {
+        return Err(BaseTraceQueryDoesNotMatchCommitment);
+    }
+    if let Some(commitment) = &extension_trace_commitment {
+        let proof = trace_queries
+            .extension_trace_proof
+            .ok_or(ExtensionTraceQueryDoesNotMatchCommitment)?;
+        if !verify_rows::<S, S::Fq>(
+            report,
+            "extension trace",
+            TreeTag::ExtensionTrace,
+            commitment,
+            lde_domain_size,
+            &positions,
+            &extension_rows,
+            proof,
+        ) {
+            return Err(ExtensionTraceQueryDoesNotMatchCommitment);
+        }
+    }
+    if !verify_rows::<S, S::Fq>(
+        report,
+        "composition trace",
+        TreeTag::CompositionTrace,
+        &composition_trace_commitment,
+        lde_domain_size,
+        &positions,
+        &composition_rows,
+        trace_queries.composition_trace_proof,
+    ) {
+        return Err(CompositionTraceQueryDoesNotMatchCommitment);
+    }
+
+    // evaluate the DEEP composition polynomial at each query
+    let g = root_of_unity::<S::Fp>(trace_len);
+    let lde_offset = S::AirConfig::domain_offset();
+    let lde_generator = root_of_unity::<S::Fp>(lde_domain_size);
+    let deep_evals = positions
+        .iter()
+        .zip(&openings)
+        .map(|(&position, opening)| {
+            let x = S::Fq::from(
+                lde_offset * pow(lde_generator, reverse_bits(lde_domain_size, position)),
+            );
+            let mut eval = S::Fq::zero();
+            for (j, &(column, offset)) in trace_arguments.iter().enumerate() {
+                let value = if column < S::AirConfig::NUM_BASE_COLUMNS {
+                    S::Fq::from(opening.base_trace_row[column])
+                } else {
+                    opening.extension_trace_row[column - S::AirConfig::NUM_BASE_COLUMNS]
+                };
+                let shift = pow(g, offset.rem_euclid(trace_len as isize) as usize);
+                let numerator = deep_coeffs.execution_trace[j] * (value - ood_evals[j]);
+                eval += numerator * (x - z * shift).inverse().unwrap();
+            }
+            for (j, &value) in opening.composition_trace_row.iter().enumerate() {
+                let numerator =
+                    deep_coeffs.composition_trace[j] * (value - composition_trace_ood_evals[j]);
+                eval += numerator * (x - z_n).inverse().unwrap();
+            }
+            let (alpha, beta) = deep_coeffs.degree;
+            eval * (alpha + beta * x)
+        })
+        .collect::<Vec<S::Fq>>();
+
+    verify_fri::<S>(
+        report,
+        options,
+        trace_len,
+        &fri_proof,
+        &fri_alphas,
+        positions,
+        deep_evals,
+    )?;
+    Ok(())
+}
+
+/// Evaluates the composition constraint at `x` as a tree
+fn composition_constraint_evaluation<A: AirConfig>(
+    air: &Air<A>,
+    composition_coeffs: &[A::Fq],
+    challenges: &Challenges<A::Fq>,
+    hints: &Hints<A::Fq>,
+    trace_ood_evals: &BTreeMap<(usize, isize), A::Fq>,
+    x: A::Fq,
+) -> A::Fq {
+    use AlgebraicItem::*;
+    use CompositionItem::*;
+    air.composition_constraint()
+        .eval(&mut |leaf| match *leaf {
+            Item(X) => FieldVariant::Fq(x),
+            Item(Constant(v)) => v,
+            Item(Challenge(i)) => FieldVariant::Fq(challenges[i]),
+            Item(Hint(i)) => FieldVariant::Fq(hints[i]),
+            Item(Periodic(col)) => {
+                let point = pow(x, air.trace_len() / col.interval_size());
+                let value = col
+                    .coeffs()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, coeff)| coeff.as_fq() * pow(point, i))
+                    .sum::<A::Fq>();
+                FieldVariant::Fq(value)
+            }
+            Item(PublicPolynomial(i)) => {
+                let value = air.public_polynomials()[i]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &coeff)| pow(x, i) * coeff)
+                    .sum::<A::Fq>();
+                FieldVariant::Fq(value)
+            }
+            Item(Trace(col, offset)) => FieldVariant::Fq(trace_ood_evals[&(col, offset)]),
+            CompositionCoeff(i) => FieldVariant::Fq(composition_coeffs[i]),
+        })
+        .as_fq()
+}
+
+/// Checks the FRI layers fold `evals` down to the remainder polynomial
+fn verify_fri<S: Stark>(
+    report: &mut VerificationReport<S::Fq, S::Digest>,
+    options: ProofOptions,
+    trace_len: usize,
+    proof: &fri::FriProof<S::Fq, S::Digest, S::MerkleTree>,
+    alphas: &[S::Fq],
+    mut positions: Vec<usize>,
+    mut evals: Vec<S::Fq>,
+) -> Result<(), fri::VerificationError> {
+    use fri::VerificationError::*;
+    let folding_factor = usize::from(options.fri_folding_factor);
+    let blowup_factor = usize::from(options.lde_blowup_factor);
+    let max_remainder_size = usize::from(options.fri_max_remainder_coeffs) * blowup_factor;
+    let folding_generator = root_of_unity::<S::Fp>(folding_factor);
+    let mut domain_size = (trace_len - 1).next_power_of_two() * blowup_factor;
+    let mut generator = root_of_unity::<S::Fp>(domain_size);
+
+    let mut layer = 0;
+    while domain_size > max_remainder_size {
+        let layer_proof = proof
+            .layers
+            .get(layer)
+            .ok_or(LayerCommitmentInvalid { layer })?;
+        if domain_size % folding_factor != 0 {
+            return Err(CodewordTruncation {
+                size: domain_size,
+                folding_factor,
+                layer,
+            });
+        }
+
+        let folded_positions = positions
+            .iter()
+            .map(|position| position / folding_factor)
+            .collect::<BTreeSet<usize>>()
+            .into_iter()
+            .collect::<Vec<usize>>();
+        let flattenend_rows = if options.compact {
+            fri::expand_rows(
+                &layer_proof.flattenend_rows,
+                &positions,
+                &evals,
+                &folded_positions,
+                folding_factor,
+            )
+            .ok_or(LayerCommitmentInvalid { layer })?
+        } else {
+            layer_proof.flattenend_rows.clone()
+        };
+        let rows = flattenend_rows
+            .chunks(folding_factor)
+            .collect::<Vec<&[S::Fq]>>();
+        if rows.len() != folded_positions.len() || rows.iter().any(|r| r.len() != folding_factor) {
+            return Err(LayerCommitmentInvalid { layer });
+        }
+        if !verify_rows::<S, S::Fq>(
+            report,
+            &format!("fri layer {layer}"),
+            TreeTag::FriLayer(layer),
+            &layer_proof.commitment,
+            domain_size / folding_factor,
+            &folded_positions,
+            &rows,
+            layer_proof.merkle_proof.clone(),
+        ) {
+            return Err(LayerCommitmentInvalid { layer });
+        }
+
+        for (&position, &expected) in positions.iter().zip(&evals) {
+            let row = folded_positions
+                .iter()
+                .position(|p| *p == position / folding_factor)
+                .unwrap();
+            let actual = rows[row][position % folding_factor];
+            if actual != expected {
+                report.failing_fold = Some(FriFoldFailure {
+                    layer,
+                    position,
+                    expected,
+                    actual,
+                });
+                return Err(InvalidDegreeRespectingProjection { layer });
+            }
+        }
+
+        // fold each row by interpolating it and evaluating at alpha
+        let alpha = alphas[layer];
+        let folded_domain_size = domain_size / folding_factor;
+        evals = folded_positions
+            .iter()
+            .zip(&rows)
+            .map(|(&position, row)| {
+                let offset = pow(generator, reverse_bits(folded_domain_size, position));
+                let xs = (0..folding_factor)
+                    .map(|i| offset * pow(folding_generator, reverse_bits(folding_factor, i)))
+                    .collect::<Vec<S::Fp>>();
+                let eval = lagrange_evaluate(&xs, row, alpha);
+                eval * S::Fq::from(folding_factor as u64)
+            })
+            .collect();
+        positions = folded_positions;
+        generator = pow(generator, folding_factor);
+        domain_size = folded_domain_size;
+        layer += 1;
+    }
+
+    let remainder = &proof.remainder_coeffs;
+    let max_degree = domain_size / blowup_factor - 1;
+    if let Some(degree) = remainder.iter().rposition(|coeff| !coeff.is_zero())
+        && degree > max_degree
+    {
+        return Err(RemainderDegreeMismatch { degree: max_degree });
+    }
+    for (&position, &expected) in positions.iter().zip(&evals) {
+        let x = S::Fq::from(pow(generator, reverse_bits(domain_size, position)));
+        let actual = remainder
+            .iter()
+            .enumerate()
+            .map(|(i, &coeff)| coeff * pow(x, i))
+            .sum::<S::Fq>();
+        if actual != expected {
+            report.failing_fold = Some(FriFoldFailure {
+                layer,
+                position,
+                expected,
+                actual,
+            });
+            return Err(RemainderCommitmentInvalid);
+        }
+    }
+    Ok(())
+}
+
+/// Checks `rows` resolve to `root` and records the root they resolve to
+fn verify_rows<S: Stark, T>(
+    report: &mut VerificationReport<S::Fq, S::Digest>,
+    label: &str,
+    tag: TreeTag,
+    root: &S::Digest,
+    num_rows: usize,
+    positions: &[usize],
+    rows: &[impl AsRef<[T]>],
+    proof: <S::MerkleTree as MerkleTree>::Proof,
+) -> bool
+where
+    S::MerkleTree: MatrixMerkleTree<T>,
+{
+    let is_valid = <S::MerkleTree as MatrixMerkleTree<T>>::verify_tagged_rows(
+        root,
+        num_rows,
+        tag,
+        positions,
+        rows,
+        proof.clone(),
+    )
+    .is_ok();
+    let actual = if is_valid {
+        Some(root.clone())
+    } else {
+        <S::MerkleTree as MatrixMerkleTree<T>>::recover_root(tag, positions, rows, proof)
+    };
+    report.merkle_roots.push(MerkleRootCheck {
+        label: label.into(),
+        expected: root.clone(),
+        actual,
+    });
+    is_valid
+}
+
+/// Evaluates the polynomial through the points `(xs[i], ys[i])` at `x`
+fn lagrange_evaluate<Fp: Field, Fq: Field + From<Fp>>(xs: &[Fp], ys: &[Fq], x: Fq) -> Fq {
+    let mut res = Fq::zero();
+    for (i, (&xi, &yi)) in xs.iter().zip(ys).enumerate() {
+        let mut basis = Fq::one();
+        for (j, &xj) in xs.iter().enumerate() {
+            if i != j {
+                basis *= (x - Fq::from(xj)) * Fq::from((xi - xj).inverse().unwrap());
+            }
+        }
+        res += yi * basis;
+    }
+    res
+}
+
+fn root_of_unity<F: FftField>(n: usize) -> F {
+    F::get_root_of_unity(n as u64).unwrap()
+}
+
+fn pow<F: Field>(v: F, exp: usize) -> F {
+    v.pow([exp as u64])
+}
+
+/// Reverses the `log2(n)` low bits of `i`
+fn reverse_bits(n: usize, i: usize) -> usize {
+    assert!(n.is_power_of_two());
+    let mut res = 0;
+    for bit in 0..n.ilog2() {
+        res |= ((i >> bit) & 1) << (n.ilog2() - 1 - bit);
+    }
+    res
+}
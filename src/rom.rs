@@ -0,0 +1,253 @@
+//! Program ROM component for VM AIRs
+//!
+//! The ROM table holds each instruction of the program followed by a copy for
+//! every time the processor fetches it, sorted by instruction pointer. An
+//! evaluation argument shows the program rows are the public program and a
+//! permutation argument shows the copies are the processor's fetches.
+
+use crate::arguments::ArgumentChallenges;
+use crate::arguments::EvaluationArgument;
+use crate::arguments::PermutationArgument;
+use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::ExecutionTraceColumn;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_ff::One;
+use ministark_gpu::GpuFftField;
+
+/// Columns of the processor's instruction fetch
+#[derive(Clone, Debug)]
+pub struct FetchColumns {
+    pub ip: usize,
+    /// Columns of the fetched instruction e.g. the opcode and its operands
+    pub instruction: Vec<usize>,
+}
+
+/// Columns of the ROM table
+#[derive(Clone, Debug)]
+pub struct RomColumns {
+    pub ip: usize,
+    pub instruction: Vec<usize>,
+    /// Binary column that is one on the row of each program instruction
+    pub is_program: usize,
+    /// Binary column that is one on program rows and padding i.e. rows that
+    /// aren't a fetch
+    pub no_fetch: usize,
+}
+
+/// Read-only program memory. Instruction pointers start at zero and address
+/// consecutive instructions.
+#[derive(Clone, Debug)]
+pub struct ProgramRom {
+    table: RomColumns,
+    permutation: PermutationArgument,
+    evaluation: EvaluationArgument,
+}
+
+/// ROM table of a [`ProgramRom`] generated by the prover
+pub struct ProgramRomWitness<F> {
+    pub ip: GpuVec<F>,
+    /// Instruction columns in the order of [`RomColumns::instruction`]
+    pub instruction: Vec<GpuVec<F>>,
+    pub is_program: GpuVec<F>,
+    pub no_fetch: GpuVec<F>,
+}
+
+impl ProgramRom {
+    /// Creates a program ROM. Both argument challenges need a weight for the
+    /// instruction pointer followed by a weight for each instruction column.
+    /// The running products of the fetch argument are stored in the execution
+    /// trace columns `product_cols` and the program evaluation in
+    /// `evaluation_col`.
+    pub fn new(
+        fetch: FetchColumns,
+        table: RomColumns,
+        fetch_challenges: ArgumentChallenges,
+        program_challenges: ArgumentChallenges,
+        product_cols: [usize; 2],
+        evaluation_col: usize,
+    ) -> Self {
+        let table_cols = [&[table.ip][..], &table.instruction].concat();
+        let fetch_cols = [&[fetch.ip][..], &fetch.instruction].concat();
+        let permutation = PermutationArgument::new(
+            fetch_cols,
+            table_cols.clone(),
+            fetch_challenges,
+            product_cols,
+        )
+        .skip_rhs_when(table.no_fetch);
+        let evaluation = EvaluationArgument::new(table_cols, program_challenges, evaluation_col)
+            .with_selector(table.is_program);
+        Self {
+            table,
+            permutation,
+            evaluation,
+        }
+    }
+
+    /// Leaves processor rows where the binary `dummy_col` is one out of the
+    /// fetches e.g. padding
+    pub fn skip_fetch_when(mut self, dummy_col: usize) -> Self {
+        self.permutation = self.permutation.skip_lhs_when(dummy_col);
+        self
+    }
+
+    /// Constraints that apply to the first row
+    pub fn boundary_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let mut constraints = vec![self.table.ip.curr(), self.table.is_program.curr() - one];
+        constraints.extend(self.permutation.boundary_constraints());
+        constraints.extend(self.evaluation.boundary_constraints());
+        constraints
+    }
+
+    /// Constraints that apply to every row
+    pub fn row_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let is_program = self.table.is_program;
+        let no_fetch = self.table.no_fetch;
+        vec![
+            is_program.curr() * (is_program.curr() - one),
+            no_fetch.curr() * (no_fetch.curr() - one),
+            // program rows aren't fetches
+            is_program.curr() * (no_fetch.curr() - one),
+        ]
+    }
+
+    /// Constraints that apply to every row except the last
+    pub fn transition_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let RomColumns {
+            ip,
+            instruction,
+            is_program,
+            no_fetch,
+        } = &self.table;
+        let is_padding = |offset| no_fetch.offset(offset) - is_program.offset(offset);
+        // constraints between two rows only apply if the next row isn't padding
+        let is_real = -(is_padding(1) - one);
+        let ip_step = ip.next() - ip.curr();
+        let is_same_ip = -(ip_step.clone() - one);
+        let mut constraints = vec![
+            // instruction pointers increase by zero or one
+            &is_real * &(&ip_step * &(ip_step.clone() - one)),
+            // each instruction starts with its program row
+            &is_real * &(&ip_step * &(is_program.next() - one)),
+            // followed by its fetches
+            &is_real * &(&is_same_ip * &is_program.next()),
+            // padding rows are at the end
+            is_padding(0) * -(is_padding(1) - one),
+        ];
+        // fetches match the program row
+        constraints.extend(
+            instruction
+                .iter()
+                .map(|col| &is_real * &(&is_same_ip * &(col.next() - col.curr()))),
+        );
+        constraints.extend(self.permutation.transition_constraints());
+        constraints.extend(self.evaluation.transition_constraints());
+        constraints
+    }
+
+    /// Constraints that apply to the last row. `program` is the evaluation of
+    /// the public program, typically a hint computed with
+    /// [`ProgramRom::program_evaluation`].
+    pub fn terminal_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        program: Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let mut constraints = self.permutation.terminal_constraints();
+        constraints.extend(self.evaluation.terminal_constraints(program));
+        constraints
+    }
+
+    /// Computes the evaluation of a program where each instruction is given
+    /// by the values of its instruction columns
+    pub fn program_evaluation<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        challenges: &Challenges<Fq>,
+        program: &[Vec<Fp>],
+    ) -> Fq {
+        let rows = program
+            .iter()
+            .enumerate()
+            .map(|(ip, instruction)| [&[Fp::from(ip as u64)][..], instruction].concat())
+            .collect::<Vec<Vec<Fp>>>();
+        self.evaluation
+            .compute_terminal(challenges, rows.iter().map(Vec::as_slice))
+    }
+
+    /// Generates the ROM table for a program and the instruction pointers
+    /// fetched by the processor padded to `num_rows` rows
+    ///
+    /// # Panics
+    /// Panics if there are more rows than `num_rows` or a fetched instruction
+    /// pointer is outside the program
+    pub fn gen_table<F: Field>(
+        &self,
+        program: &[Vec<F>],
+        fetched_ips: &[usize],
+        num_rows: usize,
+    ) -> ProgramRomWitness<F> {
+        assert!(!program.is_empty());
+        let mut num_fetches = vec![0; program.len()];
+        for &ip in fetched_ips {
+            num_fetches[ip] += 1;
+        }
+        let num_instruction_cols = self.table.instruction.len();
+        let new_column = || Vec::with_capacity_in(num_rows, GpuAllocator);
+        let mut witness = ProgramRomWitness {
+            ip: new_column(),
+            instruction: (0..num_instruction_cols).map(|_| new_column()).collect(),
+            is_program: new_column(),
+            no_fetch: new_column(),
+        };
+        let mut push_row = |ip: usize, is_program: bool, is_fetch: bool| {
+            witness.ip.push(F::from(ip as u64));
+            for (column, &value) in witness.instruction.iter_mut().zip(&program[ip]) {
+                column.push(value);
+            }
+            witness.is_program.push(F::from(is_program));
+            witness.no_fetch.push(F::from(!is_fetch));
+        };
+        for (ip, &num_fetches) in num_fetches.iter().enumerate() {
+            push_row(ip, true, false);
+            for _ in 0..num_fetches {
+                push_row(ip, false, true);
+            }
+        }
+        let num_table_rows = program.len() + fetched_ips.len();
+        assert!(num_table_rows <= num_rows);
+        for _ in num_table_rows..num_rows {
+            push_row(program.len() - 1, false, false);
+        }
+        witness
+    }
+
+    /// Generates the running product columns of the fetch argument followed
+    /// by the program evaluation column
+    pub fn gen_extension_columns<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        base: &Matrix<Fp>,
+        challenges: &Challenges<Fq>,
+    ) -> Matrix<Fq> {
+        Matrix::join(vec![
+            self.permutation.gen_extension_columns(base, challenges),
+            self.evaluation.gen_extension_columns(base, challenges),
+        ])
+    }
+}
@@ -0,0 +1,121 @@
+//! Canonical byte encoding of field elements
+//!
+//! An element is encoded as its base prime field coefficients in order. Each
+//! coefficient is the integer in `[0, p)` written with a fixed width in the
+//! chosen byte order. Merkle leaves, the public coin and proofs all go through
+//! these helpers so external verifiers only need to match one encoding.
+
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_ff::Zero;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ark_serialize::SerializationError;
+use ark_serialize::Valid;
+use core::fmt::Display;
+
+/// Byte order of each encoded base prime field coefficient
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    /// Matches the canonical arkworks serialization
+    #[default]
+    Little,
+    Big,
+}
+
+impl Display for ByteOrder {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Little => write!(f, "little-endian"),
+            Self::Big => write!(f, "big-endian"),
+        }
+    }
+}
+
+impl CanonicalSerialize for ByteOrder {
+    fn serialize_with_mode<W: ark_serialize::Write>(
+        &self,
+        writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), SerializationError> {
+        (*self as u8).serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, _compress: ark_serialize::Compress) -> usize {
+        1
+    }
+}
+
+impl Valid for ByteOrder {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for ByteOrder {
+    fn deserialize_with_mode<R: ark_serialize::Read>(
+        reader: R,
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, SerializationError> {
+        match u8::deserialize_with_mode(reader, compress, validate)? {
+            x if x == Self::Little as u8 => Ok(Self::Little),
+            x if x == Self::Big as u8 => Ok(Self::Big),
+            _ => Err(SerializationError::InvalidData),
+        }
+    }
+}
+
+/// Returns the number of bytes in the encoding of an element of `F`
+pub fn encoded_size<F: Field>() -> usize {
+    coefficient_size::<F>() * usize::try_from(F::extension_degree()).unwrap()
+}
+
+fn coefficient_size<F: Field>() -> usize {
+    F::BasePrimeField::zero().uncompressed_size()
+}
+
+/// Appends the encoding of an element to the buffer
+pub fn write_bytes<F: Field>(element: &F, order: ByteOrder, buffer: &mut Vec<u8>) {
+    for coeff in element.to_base_prime_field_elements() {
+        let start = buffer.len();
+        coeff.serialize_uncompressed(&mut *buffer).unwrap();
+        if order == ByteOrder::Big {
+            buffer[start..].reverse();
+        }
+    }
+}
+
+/// Returns the encoding of an element
+pub fn to_bytes<F: Field>(element: &F, order: ByteOrder) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(encoded_size::<F>());
+    write_bytes(element, order, &mut bytes);
+    bytes
+}
+
+pub fn to_bytes_le<F: Field>(element: &F) -> Vec<u8> {
+    to_bytes(element, ByteOrder::Little)
+}
+
+pub fn to_bytes_be<F: Field>(element: &F) -> Vec<u8> {
+    to_bytes(element, ByteOrder::Big)
+}
+
+/// Decodes an element. Fails if the length is wrong or a coefficient isn't in
+/// `[0, p)`.
+pub fn from_bytes<F: Field>(bytes: &[u8], order: ByteOrder) -> Result<F, SerializationError> {
+    if bytes.len() != encoded_size::<F>() {
+        return Err(SerializationError::InvalidData);
+    }
+    let coeffs = bytes
+        .chunks_exact(coefficient_size::<F>())
+        .map(|chunk| {
+            let mut chunk = chunk.to_vec();
+            if order == ByteOrder::Big {
+                chunk.reverse();
+            }
+            F::BasePrimeField::deserialize_uncompressed(&*chunk)
+        })
+        .collect::<Result<Vec<F::BasePrimeField>, SerializationError>>()?;
+    F::from_base_prime_field_elems(&coeffs).ok_or(SerializationError::InvalidData)
+}
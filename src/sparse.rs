@@ -0,0 +1,147 @@
+//! Run-length encoded trace columns
+//!
+//! VM traces are often mostly padding where a column holds the same value for
+//! thousands of rows. Storing those columns as runs of `(value, length)` and
+//! only expanding them when they're interpolated keeps the prover from holding
+//! a dense copy of the whole trace.
+
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::Matrix;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::domain::DomainCoeff;
+use ark_poly::domain::Radix2EvaluationDomain;
+use core::iter::repeat;
+use ministark_gpu::GpuField;
+
+/// Column stored as runs of repeated values
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RleColumn<F> {
+    runs: Vec<(F, usize)>,
+    len: usize,
+}
+
+impl<F: Field> RleColumn<F> {
+    pub const fn new() -> Self {
+        Self {
+            runs: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Appends a single row
+    pub fn push(&mut self, value: F) {
+        self.push_run(value, 1);
+    }
+
+    /// Appends `len` rows holding `value`. Merges with the last run if it
+    /// holds the same value.
+    pub fn push_run(&mut self, value: F, len: usize) {
+        if len == 0 {
+            return;
+        }
+        self.len += len;
+        match self.runs.last_mut() {
+            Some((last_value, last_len)) if *last_value == value => *last_len += len,
+            _ => self.runs.push((value, len)),
+        }
+    }
+
+    /// Pads the column to `len` rows by repeating `value`
+    pub fn resize(&mut self, len: usize, value: F) {
+        assert!(len >= self.len, "can't shrink a run-length encoded column");
+        self.push_run(value, len - self.len);
+    }
+
+    /// Returns the number of rows
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the runs of the column as `(value, length)` pairs
+    pub fn runs(&self) -> &[(F, usize)] {
+        &self.runs
+    }
+
+    /// Returns the value of a row
+    pub fn get(&self, row: usize) -> Option<F> {
+        let mut start = 0;
+        for &(value, len) in &self.runs {
+            start += len;
+            if row < start {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    /// Returns the dense column
+    pub fn expand(&self) -> GpuVec<F> {
+        let mut column = Vec::with_capacity_in(self.len, GpuAllocator);
+        for &(value, len) in &self.runs {
+            column.extend(repeat(value).take(len));
+        }
+        column
+    }
+}
+
+impl<F: Field> FromIterator<F> for RleColumn<F> {
+    fn from_iter<I: IntoIterator<Item = F>>(iter: I) -> Self {
+        let mut column = Self::new();
+        for value in iter {
+            column.push(value);
+        }
+        column
+    }
+}
+
+/// Matrix of run-length encoded columns
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RleMatrix<F>(pub Vec<RleColumn<F>>);
+
+impl<F: Field> RleMatrix<F> {
+    pub const fn new(cols: Vec<RleColumn<F>>) -> Self {
+        Self(cols)
+    }
+
+    pub fn num_rows(&self) -> usize {
+        let Some(first) = self.0.first() else {
+            return 0;
+        };
+        for (i, col) in self.0.iter().enumerate() {
+            assert_eq!(first.len(), col.len(), "length of column {i} is invalid");
+        }
+        first.len()
+    }
+
+    pub fn num_cols(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns the dense matrix
+    pub fn expand(&self) -> Matrix<F> {
+        Matrix::new(self.0.iter().map(RleColumn::expand).collect())
+    }
+
+    /// Interpolates the columns over the domain. Columns are expanded one at a
+    /// time just before they're interpolated so at most one dense column
+    /// exists alongside the polynomials.
+    pub fn interpolate(&self, domain: Radix2EvaluationDomain<F::FftField>) -> Matrix<F>
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        Matrix::join(
+            self.0
+                .iter()
+                .map(|col| Matrix::new(vec![col.expand()]).into_polynomials(domain))
+                .collect(),
+        )
+    }
+}
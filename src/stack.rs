@@ -0,0 +1,224 @@
+//! Stack component for stack-machine VM AIRs
+//!
+//! Pushes and pops are memory accesses at the current depth so the stack is
+//! checked with a [`RwMemory`]. A push writes to address `sp` and a pop reads
+//! from address `sp - 1` where `sp` is the stack pointer i.e. the depth.
+
+use crate::arguments::ArgumentChallenges;
+use crate::challenges::Challenges;
+use crate::constraints::AlgebraicItem;
+use crate::constraints::ExecutionTraceColumn;
+use crate::expression::Expr;
+use crate::gadgets::is_zero_witness;
+use crate::gadgets::ByteDecomposition;
+use crate::gadgets::IsZero;
+use crate::memory::MemoryAccess;
+use crate::memory::MemoryColumns;
+use crate::memory::RwMemory;
+use crate::memory::RwMemoryWitness;
+use crate::utils::FieldVariant;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
+use crate::Matrix;
+use crate::StarkExtensionOf;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::One;
+use ark_ff::PrimeField;
+use ministark_gpu::GpuFftField;
+
+/// Columns of the VM used by a [`Stack`]
+#[derive(Clone, Copy, Debug)]
+pub struct StackColumns {
+    pub clk: usize,
+    /// Stack pointer. Starts at zero and holds the depth of the stack.
+    pub sp: usize,
+    /// Inverse of the stack pointer (or zero) used to reject pops of an
+    /// empty stack
+    pub sp_inverse: usize,
+    /// Binary column that is one if the row pushes `value`
+    pub is_push: usize,
+    /// Binary column that is one if the row pops `value`
+    pub is_pop: usize,
+    pub value: usize,
+    /// Address of the access. `sp` for pushes and `sp - 1` for pops.
+    pub address: usize,
+    /// Binary column that is one if the row neither pushes nor pops
+    pub no_access: usize,
+}
+
+/// Stack operation of a single row
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StackOp {
+    Push(u64),
+    Pop,
+    None,
+}
+
+/// Stack with push and pop operations
+#[derive(Clone, Debug)]
+pub struct Stack {
+    columns: StackColumns,
+    memory: RwMemory,
+}
+
+/// Columns of a [`Stack`] generated by the prover
+pub struct StackWitness<F> {
+    /// VM columns in the order clk, sp, sp_inverse, is_push, is_pop, value,
+    /// address, no_access
+    pub columns: [GpuVec<F>; 8],
+    pub memory: RwMemoryWitness<F>,
+}
+
+impl Stack {
+    /// Creates a stack. The sorted memory table, permutation argument and
+    /// clock jump range check are configured as in [`RwMemory::new`].
+    pub fn new(
+        columns: StackColumns,
+        sorted: MemoryColumns,
+        sorted_dummy_col: usize,
+        challenges: ArgumentChallenges,
+        product_cols: [usize; 2],
+        clock_jump: ByteDecomposition,
+    ) -> Self {
+        let accesses = MemoryColumns {
+            address: columns.address,
+            clk: columns.clk,
+            value: columns.value,
+            is_write: columns.is_push,
+        };
+        let memory = RwMemory::new(
+            accesses,
+            sorted,
+            sorted_dummy_col,
+            challenges,
+            product_cols,
+            clock_jump,
+        )
+        .skip_accesses_when(columns.no_access);
+        Self { columns, memory }
+    }
+
+    /// Constraints that apply to the first row
+    pub fn boundary_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let mut constraints = vec![self.columns.sp.curr()];
+        constraints.extend(self.memory.boundary_constraints());
+        constraints
+    }
+
+    /// Constraints that apply to every row
+    pub fn row_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let StackColumns {
+            sp,
+            sp_inverse,
+            is_push,
+            is_pop,
+            address,
+            no_access,
+            ..
+        } = self.columns;
+        let sp_is_zero = IsZero::new(sp.curr(), sp_inverse);
+        let mut constraints = vec![
+            is_push.curr() * (is_push.curr() - one),
+            is_pop.curr() * (is_pop.curr() - one),
+            is_push.curr() * is_pop.curr(),
+            no_access.curr() + is_push.curr() + is_pop.curr() - one,
+            address.curr() - sp.curr() + is_pop.curr(),
+            // can't pop an empty stack
+            is_pop.curr() * sp_is_zero.is_zero,
+        ];
+        constraints.extend(sp_is_zero.constraints);
+        constraints.extend(self.memory.row_constraints());
+        constraints
+    }
+
+    /// Constraints that apply to every row except the last
+    pub fn transition_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        let StackColumns {
+            sp,
+            is_push,
+            is_pop,
+            ..
+        } = self.columns;
+        let mut constraints = vec![sp.next() - sp.curr() - is_push.curr() + is_pop.curr()];
+        constraints.extend(self.memory.transition_constraints());
+        constraints
+    }
+
+    /// Constraints that apply to the last row
+    pub fn terminal_constraints<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+    ) -> Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>> {
+        self.memory.terminal_constraints()
+    }
+
+    /// Generates the stack columns for one operation per row padded with
+    /// [`StackOp::None`] to `num_rows` rows. Popped values are read from the
+    /// stack.
+    ///
+    /// # Panics
+    /// Panics if an operation pops an empty stack or if no operation pushes or
+    /// pops
+    pub fn gen_columns<F: PrimeField>(&self, ops: &[StackOp], num_rows: usize) -> StackWitness<F> {
+        assert!(ops.len() <= num_rows);
+        let new_column = || Vec::with_capacity_in(num_rows, GpuAllocator);
+        let [mut clk, mut sp, mut is_push, mut is_pop, mut value, mut address, mut no_access] =
+            [(); 7].map(|_| new_column());
+        let mut stack = Vec::new();
+        let mut accesses = Vec::new();
+        for row in 0..num_rows {
+            let depth = stack.len() as u64;
+            let op = ops.get(row).copied().unwrap_or(StackOp::None);
+            let (access_address, access_value) = match op {
+                StackOp::Push(v) => {
+                    stack.push(v);
+                    (depth, v)
+                }
+                StackOp::Pop => {
+                    let v = stack.pop().expect("stack underflow");
+                    (depth - 1, v)
+                }
+                StackOp::None => (depth, 0),
+            };
+            if op != StackOp::None {
+                accesses.push(MemoryAccess {
+                    address: access_address,
+                    clk: row as u64,
+                    value: access_value,
+                    is_write: matches!(op, StackOp::Push(_)),
+                });
+            }
+            clk.push(F::from(row as u64));
+            sp.push(F::from(depth));
+            is_push.push(F::from(matches!(op, StackOp::Push(_))));
+            is_pop.push(F::from(op == StackOp::Pop));
+            value.push(F::from(access_value));
+            address.push(F::from(access_address));
+            no_access.push(F::from(op == StackOp::None));
+        }
+        let sp_inverse = is_zero_witness(&sp);
+        let memory = self.memory.gen_sorted_table(&accesses, num_rows);
+        StackWitness {
+            columns: [
+                clk, sp, sp_inverse, is_push, is_pop, value, address, no_access,
+            ],
+            memory,
+        }
+    }
+
+    /// Generates the running product columns of the memory argument
+    pub fn gen_extension_columns<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
+        &self,
+        base: &Matrix<Fp>,
+        challenges: &Challenges<Fq>,
+    ) -> Matrix<Fq> {
+        self.memory.gen_extension_columns(base, challenges)
+    }
+}
@@ -1,17 +1,25 @@
 use crate::air::AirConfig;
+use crate::air::AirConfigError;
 use crate::challenges::Challenges;
 use crate::channel::VerifierChannelArtifacts;
 use crate::composer::DeepCompositionCoeffs;
+use crate::context::CancellationToken;
+use crate::context::ProverContext;
 use crate::debug::default_validate_constraints;
 use crate::hash::Digest;
 use crate::hints::Hints;
 use crate::merkle::MatrixMerkleTree;
 use crate::merkle::MerkleTree;
 use crate::prover::default_prove;
+use crate::prover::estimate_cost;
+use crate::prover::CostEstimate;
 use crate::prover::ProvingError;
 use crate::random::draw_multiple;
 use crate::random::PublicCoin;
 use crate::verifier::default_verify;
+use crate::verifier::default_verify_from_reader;
+use crate::verifier::default_verify_light;
+use crate::verifier::LightVerification;
 use crate::verifier::VerificationError;
 use crate::Air;
 use crate::Matrix;
@@ -20,6 +28,8 @@ use crate::ProofOptions;
 use crate::StarkExtensionOf;
 use crate::Trace;
 use ark_ff::FftField;
+use ark_serialize::Compress;
+use ark_serialize::Read;
 use ministark_gpu::GpuFftField;
 
 pub trait Stark: Sized + Send + Sync {
@@ -32,19 +42,30 @@ pub trait Stark: Sized + Send + Sync {
         + MatrixMerkleTree<Self::Fq>;
     type Trace: Trace<Fp = Self::Fp, Fq = Self::Fq>;
     type Digest: Digest;
-    type Witness;
+    type Witness: Send;
 
     fn get_public_inputs(&self) -> <Self::AirConfig as AirConfig>::PublicInputs;
 
     fn gen_public_coin(&self, air: &Air<Self::AirConfig>) -> Self::PublicCoin;
 
+    /// Returns the AIR for a trace of length `trace_len`. Override to build
+    /// the AIR some other way e.g. from a description loaded at runtime (see
+    /// [`Air::from_description`]).
+    fn air(
+        &self,
+        trace_len: usize,
+        options: ProofOptions,
+    ) -> Result<Air<Self::AirConfig>, AirConfigError> {
+        Air::try_new(trace_len, self.get_public_inputs(), options)
+    }
+
     fn gen_deep_coeffs(
         &self,
         public_coin: &mut Self::PublicCoin,
         air: &Air<Self::AirConfig>,
     ) -> DeepCompositionCoeffs<Self::Fq> {
         let num_execution_trace = air.trace_arguments().len();
-        let num_composition_trace = air.ce_blowup_factor();
+        let num_composition_trace = air.num_composition_trace_columns();
         DeepCompositionCoeffs {
             execution_trace: draw_multiple(public_coin, num_execution_trace),
             composition_trace: draw_multiple(public_coin, num_composition_trace),
@@ -62,16 +83,49 @@ pub trait Stark: Sized + Send + Sync {
         default_prove(self, options, witness)
     }
 
+    /// Estimates the cost of proving a trace of length `trace_len`
+    fn estimate_cost(
+        &self,
+        trace_len: usize,
+        options: ProofOptions,
+    ) -> Result<CostEstimate, AirConfigError> {
+        Ok(estimate_cost::<Self>(&self.air(trace_len, options)?))
+    }
+
+    /// Generates a proof that can be aborted with `cancellation_token`
+    async fn prove_cancellable(
+        &self,
+        options: ProofOptions,
+        witness: Self::Witness,
+        cancellation_token: &CancellationToken,
+    ) -> Result<Proof<Self>, ProvingError> {
+        let context = ProverContext::current().with_cancellation_token(cancellation_token.clone());
+        self.prove_with_context(&context, options, witness).await
+    }
+
+    /// Generates a proof using the resources (e.g. thread pool) of `context`.
+    /// Calls [`Self::prove`] with `context` as the [`ProverContext::current`]
+    /// context so overrides of [`Self::prove`] that call
+    /// [`crate::prover::default_prove`] use its resources.
+    async fn prove_with_context(
+        &self,
+        context: &ProverContext,
+        options: ProofOptions,
+        witness: Self::Witness,
+    ) -> Result<Proof<Self>, ProvingError> {
+        context.bind(self.prove(options, witness)).await
+    }
+
     /// Check the AIR constraints are valid
     fn validate_constraints(
         &self,
         challenges: &Challenges<Self::Fq>,
         hints: &Hints<Self::Fq>,
-        base_trace: &Matrix<Self::Fp>,
+        trace: &Self::Trace,
         extension_trace: Option<&Matrix<Self::Fq>>,
     ) {
         // TODO: this is unfinished
-        default_validate_constraints(self, challenges, hints, base_trace, extension_trace);
+        default_validate_constraints(self, challenges, hints, trace, extension_trace);
     }
 
     #[allow(clippy::too_many_lines)]
@@ -82,4 +136,25 @@ pub trait Stark: Sized + Send + Sync {
     ) -> Result<VerifierChannelArtifacts<Self::Fq>, VerificationError> {
         default_verify(self, proof, required_security_bits)
     }
+
+    /// Verifies a proof without checking the trace queries against their
+    /// commitments. See [`default_verify_light`] for details.
+    fn verify_light(
+        &self,
+        proof: Proof<Self>,
+        required_security_bits: u32,
+    ) -> Result<LightVerification<Self::Fq>, VerificationError> {
+        default_verify_light(self, proof, required_security_bits)
+    }
+
+    /// Verifies a proof while it's deserialized from `reader`. See
+    /// [`default_verify_from_reader`] for details.
+    fn verify_from_reader(
+        &self,
+        reader: impl Read,
+        compress: Compress,
+        required_security_bits: u32,
+    ) -> Result<VerifierChannelArtifacts<Self::Fq>, VerificationError> {
+        default_verify_from_reader(self, reader, compress, required_security_bits)
+    }
 }
@@ -0,0 +1,198 @@
+//! Experimental sumcheck based constraint batching
+//!
+//! Rather than dividing the constraints by their vanishing polynomials and
+//! committing to the quotients the prover can show a random combination of
+//! the constraints is zero on every row with a zerocheck. Rows of the trace
+//! are the points of the boolean hypercube and the prover runs a sumcheck of
+//! `eq(r, x) * C(x)` for a random `r`. There are no quotients to interpolate
+//! or commit to which can be much cheaper for very wide traces.
+//!
+//! The sumcheck leaves the verifier with claimed evaluations of the
+//! multilinear extensions of the columns at a random point. Checking them
+//! needs a multilinear commitment scheme which miniSTARK doesn't have so this
+//! mode is only for measuring the prover.
+
+use crate::random::PublicCoin;
+use alloc::vec::Vec;
+use ark_ff::Field;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use snafu::Snafu;
+
+/// Proof that a constraint is zero on every row of a set of columns
+#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SumcheckProof<F: Field> {
+    /// Evaluations of each round polynomial at `0, 1, ..., degree + 1`
+    pub round_polys: Vec<Vec<F>>,
+    /// Evaluations of the multilinear extension of each column at the point
+    /// chosen by the verifier
+    pub column_evals: Vec<F>,
+}
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum SumcheckError {
+    #[snafu(display("expected {expected} rounds but the proof has {actual}"))]
+    RoundCountMismatch { expected: usize, actual: usize },
+    #[snafu(display("round {round} polynomial has the wrong degree"))]
+    RoundDegreeMismatch { round: usize },
+    #[snafu(display("round {round} polynomial does not match the previous claim"))]
+    RoundSumMismatch { round: usize },
+    #[snafu(display("column evaluations do not match the final claim"))]
+    FinalEvaluationMismatch,
+}
+
+/// Proves `constraint` is zero on every row of `columns`. `constraint` maps
+/// the values of the columns in a row to a single value and must be a
+/// polynomial of total degree at most `degree` e.g. a random linear
+/// combination of the AIR's constraints over the trace columns and their
+/// shifted copies.
+///
+/// # Panics
+/// Panics if the columns are not all the same power of two length
+pub fn prove_zerocheck<F: Field, P: PublicCoin<Field = F>>(
+    mut columns: Vec<Vec<F>>,
+    degree: usize,
+    constraint: impl Fn(&[F]) -> F + Sync,
+    public_coin: &mut P,
+) -> SumcheckProof<F> {
+    let n = columns[0].len();
+    assert!(n.is_power_of_two());
+    assert!(columns.iter().all(|column| column.len() == n));
+    let num_vars = n.ilog2() as usize;
+    let r = (0..num_vars)
+        .map(|_| public_coin.draw())
+        .collect::<Vec<F>>();
+    // the eq polynomial is multilinear so it's folded like any other column
+    columns.push(eq_evals(&r));
+
+    let mut round_polys = Vec::new();
+    for _ in 0..num_vars {
+        let half = columns[0].len() / 2;
+        let round_poly = (0..=degree + 1)
+            .map(|t| {
+                let t = F::from(t as u64);
+                let eval_row = |i: usize| {
+                    let row = columns
+                        .iter()
+                        .map(|column| column[i] + t * (column[i + half] - column[i]))
+                        .collect::<Vec<F>>();
+                    let (eq, row) = row.split_last().unwrap();
+                    *eq * constraint(row)
+                };
+                #[cfg(not(feature = "parallel"))]
+                return (0..half).map(eval_row).sum::<F>();
+                #[cfg(feature = "parallel")]
+                return (0..half).into_par_iter().map(eval_row).sum::<F>();
+            })
+            .collect::<Vec<F>>();
+        public_coin.reseed_with_field_elements(&round_poly);
+        let challenge = public_coin.draw();
+        for column in &mut columns {
+            let (lo, hi) = column.split_at(half);
+            *column = lo
+                .iter()
+                .zip(hi)
+                .map(|(&lo, &hi)| lo + challenge * (hi - lo))
+                .collect();
+        }
+        round_polys.push(round_poly);
+    }
+
+    columns.pop();
+    let column_evals = columns
+        .into_iter()
+        .map(|column| column[0])
+        .collect::<Vec<F>>();
+    public_coin.reseed_with_field_elements(&column_evals);
+    SumcheckProof {
+        round_polys,
+        column_evals,
+    }
+}
+
+/// Verifies a zerocheck over `2^num_vars` rows. Returns the point the
+/// column evaluations in the proof are claimed at. Variable `i` of the point
+/// is bit `i` of the row index. The caller is responsible for checking the
+/// column evaluations against a commitment to the columns.
+pub fn verify_zerocheck<F: Field, P: PublicCoin<Field = F>>(
+    proof: &SumcheckProof<F>,
+    num_vars: usize,
+    degree: usize,
+    constraint: impl Fn(&[F]) -> F,
+    public_coin: &mut P,
+) -> Result<Vec<F>, SumcheckError> {
+    let num_rounds = proof.round_polys.len();
+    if num_rounds != num_vars {
+        return Err(SumcheckError::RoundCountMismatch {
+            expected: num_vars,
+            actual: num_rounds,
+        });
+    }
+    let r = (0..num_vars)
+        .map(|_| public_coin.draw())
+        .collect::<Vec<F>>();
+
+    let mut claim = F::zero();
+    let mut challenges = Vec::new();
+    for (round, round_poly) in proof.round_polys.iter().enumerate() {
+        if round_poly.len() != degree + 2 {
+            return Err(SumcheckError::RoundDegreeMismatch { round });
+        }
+        if round_poly[0] + round_poly[1] != claim {
+            return Err(SumcheckError::RoundSumMismatch { round });
+        }
+        public_coin.reseed_with_field_elements(round_poly);
+        let challenge = public_coin.draw();
+        claim = interpolate_at(round_poly, challenge);
+        challenges.push(challenge);
+    }
+
+    // rounds bind the most significant bit of the row index first
+    let point = challenges.into_iter().rev().collect::<Vec<F>>();
+    let eq = r
+        .iter()
+        .zip(&point)
+        .map(|(&r, &x)| r * x + (F::one() - r) * (F::one() - x))
+        .product::<F>();
+    if eq * constraint(&proof.column_evals) != claim {
+        return Err(SumcheckError::FinalEvaluationMismatch);
+    }
+    public_coin.reseed_with_field_elements(&proof.column_evals);
+    Ok(point)
+}
+
+/// Returns `eq(r, x)` for every `x` in the boolean hypercube. Bit `i` of the
+/// index of `x` is variable `i`.
+fn eq_evals<F: Field>(r: &[F]) -> Vec<F> {
+    let mut evals = vec![F::one()];
+    for &r in r {
+        let hi = evals.iter().map(|&e| e * r).collect::<Vec<F>>();
+        for e in &mut evals {
+            *e *= F::one() - r;
+        }
+        evals.extend(hi);
+    }
+    evals
+}
+
+/// Evaluates the polynomial with evaluations `evals` at `0, 1, 2, ...` at `x`
+fn interpolate_at<F: Field>(evals: &[F], x: F) -> F {
+    let nodes = (0..evals.len())
+        .map(|i| F::from(i as u64))
+        .collect::<Vec<F>>();
+    let mut result = F::zero();
+    for (i, (&eval, &node)) in evals.iter().zip(&nodes).enumerate() {
+        let mut numerator = F::one();
+        let mut denominator = F::one();
+        for (j, &other) in nodes.iter().enumerate() {
+            if i != j {
+                numerator *= x - other;
+                denominator *= node - other;
+            }
+        }
+        result += eval * numerator * denominator.inverse().unwrap();
+    }
+    result
+}
@@ -0,0 +1,355 @@
+use crate::constraints::AlgebraicItem;
+use crate::constraints::Constraint;
+use crate::expression::Expr;
+use crate::utils::FieldVariant;
+use crate::utils::GpuVec;
+use crate::Matrix;
+use alloc::vec::Vec;
+use ark_ff::FftField;
+use ark_ff::Field;
+use ark_poly::domain::DomainCoeff;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ministark_gpu::GpuFftField;
+use ministark_gpu::GpuField;
+
+/// How rows are appended to a table so its length matches the trace length
+#[derive(Clone, Copy, Debug)]
+pub enum Padding<F> {
+    /// Pad with rows of zeros
+    Zeros,
+    /// Pad with copies of the last row
+    RepeatLastRow,
+    /// Pad with rows derived from the previous row e.g. incrementing a
+    /// cycle counter and setting a dummy flag
+    NextRow(fn(&[F]) -> Vec<F>),
+    /// Pad each column with its own rule
+    Columns(&'static [ColumnPadding<F>]),
+}
+
+/// How a single column is padded (see [`Padding::Columns`])
+#[derive(Clone, Copy, Debug)]
+pub enum ColumnPadding<F> {
+    /// Pad with zeros
+    Zero,
+    /// Pad with ones e.g. a flag for dummy rows
+    One,
+    /// Pad with copies of the column's last value
+    Repeat,
+    /// Pad with the column's previous value plus one e.g. a cycle counter
+    Increment,
+    /// Pad with a value derived from the previous row
+    Custom(fn(&[F]) -> F),
+}
+
+impl<F: Field> ColumnPadding<F> {
+    /// Returns the value of `column` in the padding row after `prev_row`
+    pub fn next(&self, column: usize, prev_row: &[F]) -> F {
+        match self {
+            Self::Zero => F::zero(),
+            Self::One => F::one(),
+            Self::Repeat => prev_row[column],
+            Self::Increment => prev_row[column] + F::one(),
+            Self::Custom(next) => next(prev_row),
+        }
+    }
+}
+
+/// Pads `rows` to `n` rows by applying the padding of each column to the
+/// previous row. Works with any row type e.g. the fixed size rows of a VM.
+///
+/// # Panics
+/// Panics if there are no rows or the number of columns doesn't match
+pub fn pad_rows<F: Field, R: Clone + AsRef<[F]> + AsMut<[F]>>(
+    rows: &mut Vec<R>,
+    n: usize,
+    columns: &[ColumnPadding<F>],
+) {
+    while rows.len() < n {
+        let prev_row = rows.last().unwrap();
+        assert_eq!(prev_row.as_ref().len(), columns.len());
+        let mut row = prev_row.clone();
+        for (column, value) in row.as_mut().iter_mut().enumerate() {
+            *value = columns[column].next(column, prev_row.as_ref());
+        }
+        rows.push(row);
+    }
+}
+
+/// A group of trace columns with their own boundary, transition and
+/// terminal constraints e.g. the processor or memory table of a VM.
+///
+/// Boundary constraints apply to the first row, transition constraints to
+/// every row except the last and terminal constraints to the last row.
+///
+/// A table can have fewer rows than the trace (see
+/// [`AlgebraicTable::with_height`]) in which case its rows live on a subgroup
+/// of the trace domain. Row `i` of a table of height `h` is row `i * n / h` of
+/// a trace of length `n`. A short table is either kept as a segment of its own
+/// (see [`AlgebraicTable::gen_segment`] and [`TraceSegments`]) or extended to
+/// the trace length (see [`AlgebraicTable::gen_columns`]).
+pub struct AlgebraicTable<Fp: 'static, Fq: 'static> {
+    name: &'static str,
+    padding: Padding<Fp>,
+    height: Option<usize>,
+    boundary_constraints: Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>,
+    transition_constraints: Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>,
+    terminal_constraints: Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>,
+}
+
+impl<Fp: FftField, Fq: Field> AlgebraicTable<Fp, Fq> {
+    pub const fn new(name: &'static str, padding: Padding<Fp>) -> Self {
+        Self {
+            name,
+            padding,
+            height: None,
+            boundary_constraints: Vec::new(),
+            transition_constraints: Vec::new(),
+            terminal_constraints: Vec::new(),
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub const fn padding(&self) -> Padding<Fp> {
+        self.padding
+    }
+
+    /// Pads the table to `height` rows rather than the trace length. Columns
+    /// of the table are then polynomials of degree less than `height` which
+    /// are cheaper to interpolate.
+    ///
+    /// # Panics
+    /// Panics if `height` is not a power of two
+    pub const fn with_height(mut self, height: usize) -> Self {
+        assert!(height.is_power_of_two());
+        self.height = Some(height);
+        self
+    }
+
+    /// Returns the number of rows of the table in a trace of length
+    /// `trace_len`
+    ///
+    /// # Panics
+    /// Panics if the table is longer than the trace
+    pub fn height(&self, trace_len: usize) -> usize {
+        let height = self.height.unwrap_or(trace_len);
+        assert!(
+            height <= trace_len,
+            "{} table is longer than the trace",
+            self.name
+        );
+        height
+    }
+
+    pub fn add_boundary_constraints(
+        &mut self,
+        constraints: impl IntoIterator<Item = Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>,
+    ) -> &mut Self {
+        self.boundary_constraints.extend(constraints);
+        self
+    }
+
+    pub fn add_transition_constraints(
+        &mut self,
+        constraints: impl IntoIterator<Item = Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>,
+    ) -> &mut Self {
+        self.transition_constraints.extend(constraints);
+        self
+    }
+
+    pub fn add_terminal_constraints(
+        &mut self,
+        constraints: impl IntoIterator<Item = Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>>,
+    ) -> &mut Self {
+        self.terminal_constraints.extend(constraints);
+        self
+    }
+
+    /// Returns all the table's constraints with the divisors for their rows
+    pub fn constraints(&self, trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fq>>> {
+        let height = self.height(trace_len);
+        // moving to the next row of the table moves `stride` rows in the trace
+        let stride = isize::try_from(trace_len / height).unwrap();
+        let stretch = |expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fq>>>| {
+            expr.map_leaves(&mut |&leaf| match leaf {
+                AlgebraicItem::Trace(col, offset) => AlgebraicItem::Trace(col, offset * stride),
+                leaf => leaf,
+            })
+        };
+        // the table's rows form a subgroup of the trace domain so constraints
+        // use the divisors of a trace with `height` rows
+        let boundary = self
+            .boundary_constraints
+            .iter()
+            .map(stretch)
+            .map(Constraint::when_first_row);
+        let transition = self
+            .transition_constraints
+            .iter()
+            .map(stretch)
+            .map(|expr| Constraint::when_transition(height, expr));
+        let terminal = self
+            .terminal_constraints
+            .iter()
+            .map(stretch)
+            .map(|expr| Constraint::when_last_row(height, expr));
+        boundary.chain(transition).chain(terminal).collect()
+    }
+
+    /// Pads the rows of the table to `n` rows using the table's padding
+    ///
+    /// # Panics
+    /// Panics if there are no rows and the padding needs a previous row
+    pub fn pad(&self, rows: &mut Vec<Vec<Fp>>, n: usize) {
+        if let Padding::Columns(columns) = self.padding {
+            return pad_rows(rows, n, columns);
+        }
+        while rows.len() < n {
+            let row = match self.padding {
+                Padding::Zeros => vec![Fp::zero(); rows.first().map_or(0, Vec::len)],
+                Padding::RepeatLastRow => rows.last().unwrap().clone(),
+                Padding::NextRow(next_row) => next_row(rows.last().unwrap()),
+                Padding::Columns(_) => unreachable!(),
+            };
+            rows.push(row);
+        }
+    }
+
+    /// Pads the rows of the table to its height and returns them as a segment
+    /// of the trace (see [`TraceSegments`]). Unlike [`Self::gen_columns`] the
+    /// columns of a short table aren't extended to the trace length.
+    pub fn gen_segment(&self, mut rows: Vec<Vec<Fp>>, trace_len: usize) -> Matrix<Fp> {
+        self.pad(&mut rows, self.height(trace_len));
+        Matrix::from_rows(rows)
+    }
+
+    /// Pads the rows of the table and returns them as columns over the trace
+    /// domain. Columns of tables shorter than the trace are extended to the
+    /// trace length by evaluating their polynomials over the trace domain.
+    pub fn gen_columns(&self, mut rows: Vec<Vec<Fp>>, trace_len: usize) -> Matrix<Fp>
+    where
+        Fp: GpuFftField<FftField = Fp>,
+    {
+        if self.height(trace_len) < trace_len {
+            let trace_domain = Radix2EvaluationDomain::new(trace_len).unwrap();
+            return self.interpolate(rows, trace_len).evaluate(trace_domain);
+        }
+        self.pad(&mut rows, trace_len);
+        Matrix::from_rows(rows)
+    }
+
+    /// Pads the rows of the table and returns the coefficients of the
+    /// polynomials of each column. Only the table's rows are interpolated so
+    /// this is cheaper for tables shorter than the trace.
+    pub fn interpolate(&self, mut rows: Vec<Vec<Fp>>, trace_len: usize) -> Matrix<Fp>
+    where
+        Fp: GpuFftField<FftField = Fp>,
+    {
+        let height = self.height(trace_len);
+        self.pad(&mut rows, height);
+        let table_domain = Radix2EvaluationDomain::new(height).unwrap();
+        let mut polynomials = Matrix::from_rows(rows).into_polynomials(table_domain);
+        for column in &mut polynomials.0 {
+            column.resize(trace_len, Fp::zero());
+        }
+        polynomials
+    }
+}
+
+/// Base trace columns grouped into segments of different heights e.g. a long
+/// processor table next to a short memory table. Each segment is only padded
+/// to its own height and lives on the subgroup of the trace domain with that
+/// many elements. Segments are interpolated over their own subgroup and share
+/// the LDE domain of the trace so a short segment is never padded to the
+/// length of the trace.
+///
+/// Traces that store segments return `None` from
+/// [`crate::Trace::base_columns`] and forward [`crate::Trace::len`],
+/// [`crate::Trace::base_column`] and [`crate::Trace::interpolate_base_columns`]
+/// to the segments.
+pub struct TraceSegments<F>(pub Vec<Matrix<F>>);
+
+impl<F: Field> TraceSegments<F> {
+    /// # Panics
+    /// Panics if the height of a segment is not a power of two
+    pub fn new(segments: Vec<Matrix<F>>) -> Self {
+        for (i, segment) in segments.iter().enumerate() {
+            assert!(
+                segment.num_rows().is_power_of_two(),
+                "height of segment {i} is not a power of two"
+            );
+        }
+        Self(segments)
+    }
+
+    /// Returns the length of the trace i.e. the height of the tallest segment
+    pub fn num_rows(&self) -> usize {
+        self.0.iter().map(Matrix::num_rows).max().unwrap_or(0)
+    }
+
+    pub fn num_cols(&self) -> usize {
+        self.0.iter().map(Matrix::num_cols).sum()
+    }
+
+    /// Returns the values of a column over the trace domain. Rows in between
+    /// the rows of a short segment hold the values of the column polynomial.
+    ///
+    /// # Panics
+    /// Panics if the column is out of range
+    pub fn column(&self, column: usize) -> GpuVec<F>
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        let trace_len = self.num_rows();
+        let (segment, column) = self.segment_of(column);
+        let values = segment.0[column].clone();
+        if segment.num_rows() == trace_len {
+            return values;
+        }
+        let segment_domain = Radix2EvaluationDomain::new(segment.num_rows()).unwrap();
+        let trace_domain = Radix2EvaluationDomain::new(trace_len).unwrap();
+        let mut evaluations = Matrix::new(vec![values])
+            .into_polynomials(segment_domain)
+            .into_evaluations(trace_domain);
+        evaluations.0.pop().unwrap()
+    }
+
+    /// Interpolates each segment over its own subgroup of the trace domain.
+    /// Polynomials of a segment have degree less than its height. Their
+    /// coefficients are zero padded to the size of `domain`.
+    pub fn interpolate(&self, domain: Radix2EvaluationDomain<F::FftField>) -> Matrix<F>
+    where
+        F: GpuField + DomainCoeff<F::FftField>,
+        F::FftField: FftField,
+    {
+        Matrix::join(
+            self.0
+                .iter()
+                .map(|segment| {
+                    let segment_domain = Radix2EvaluationDomain::new(segment.num_rows()).unwrap();
+                    let mut polynomials = segment.interpolate(segment_domain);
+                    for column in &mut polynomials.0 {
+                        column.resize(domain.size(), F::zero());
+                    }
+                    polynomials
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the segment holding a column and the column's index within it
+    fn segment_of(&self, column: usize) -> (&Matrix<F>, usize) {
+        let mut offset = column;
+        for segment in &self.0 {
+            if offset < segment.num_cols() {
+                return (segment, offset);
+            }
+            offset -= segment.num_cols();
+        }
+        panic!("column {column} is out of range")
+    }
+}
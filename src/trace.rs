@@ -1,14 +1,34 @@
+use crate::air::AirConfig;
 use crate::challenges::Challenges;
+use crate::constraints::ExecutionTraceColumn;
+use crate::constraints::VerifierChallenge;
 use crate::merkle::MatrixMerkleTree;
 use crate::merkle::MerkleTree;
+use crate::random::PublicCoin;
 use crate::stark::Stark;
+use crate::utils::GpuAllocator;
+use crate::utils::GpuVec;
 use crate::Matrix;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
 use alloc::vec::Vec;
 use ark_ff::FftField;
 use ark_ff::Field;
+use ark_poly::domain::DomainCoeff;
+use ark_poly::domain::Radix2EvaluationDomain;
 use ark_serialize::CanonicalDeserialize;
 use ark_serialize::CanonicalSerialize;
+use ark_serialize::Compress;
+use ark_serialize::Read;
+use ark_serialize::SerializationError;
 use ark_serialize::Valid;
+use ark_serialize::Validate;
+use core::iter::zip;
+use core::marker::PhantomData;
+use ministark_gpu::GpuFftField;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use snafu::Snafu;
 
 /// STARK execution trace
 #[allow(clippy::len_without_is_empty)]
@@ -18,27 +38,526 @@ pub trait Trace: Send + Sync {
 
     /// Returns the number of rows in this execution trace.
     fn len(&self) -> usize {
-        self.base_columns().num_rows()
+        self.base_columns().expect(NOT_DENSE).num_rows()
     }
 
-    /// Returns a reference to the base trace columns.
-    fn base_columns(&self) -> &Matrix<Self::Fp>;
+    /// Returns the number of base trace columns
+    fn num_base_columns(&self) -> usize {
+        self.base_columns().expect(NOT_DENSE).num_cols()
+    }
+
+    /// Returns a reference to the dense base trace columns. Traces that store
+    /// their columns compressed e.g. as a [`crate::sparse::RleMatrix`] return
+    /// `None` and override [`Trace::len`], [`Trace::num_base_columns`] and
+    /// [`Trace::base_column`].
+    fn base_columns(&self) -> Option<&Matrix<Self::Fp>>;
+
+    /// Returns the values of a base trace column. Compressed traces return the
+    /// expanded column.
+    fn base_column(&self, column: usize) -> Cow<'_, [Self::Fp]> {
+        Cow::Borrowed(&self.base_columns().expect(NOT_DENSE).0[column])
+    }
+
+    /// Interpolates the base trace columns over the trace domain. Traces
+    /// without dense base columns are expanded one column at a time. Override
+    /// this to interpolate compressed columns more efficiently.
+    fn interpolate_base_columns(&self, domain: Radix2EvaluationDomain<Self::Fp>) -> Matrix<Self::Fp>
+    where
+        Self::Fp: GpuFftField + DomainCoeff<Self::Fp>,
+    {
+        if let Some(base_columns) = self.base_columns() {
+            return base_columns.interpolate(domain);
+        }
+        Matrix::join(
+            (0..self.num_base_columns())
+                .map(|i| {
+                    let column = self.base_column(i).to_vec_in(GpuAllocator);
+                    Matrix::new(vec![column]).into_polynomials(domain)
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the extension columns along with their dependencies. Used by
+    /// the default implementation of [`Trace::build_extension_columns`] to
+    /// build independent columns in parallel.
+    fn extension_column_graph(&self) -> Option<ExtensionColumnGraph<'_, Self::Fp, Self::Fq>> {
+        None
+    }
 
     /// Builds and returns the extension trace columns
     /// These columns require auxiliary random elements to be constructed.
     /// Returns None if there are no columns that require this.
     fn build_extension_columns(
         &self,
+        challenges: &Challenges<Self::Fq>,
+    ) -> Option<Matrix<Self::Fq>> {
+        self.extension_column_graph().map(|graph| {
+            graph.build_from_columns(self.len(), |column| self.base_column(column), challenges)
+        })
+    }
+}
+
+/// Panic message of the [`Trace`] methods that need dense base columns
+const NOT_DENSE: &str = "traces without dense base columns must override this method";
+
+/// Generates an execution trace from a witness e.g. the output of running a
+/// VM. Implementing this rather than [`Trace`] gives every VM the same
+/// structure: base columns, extension columns and public inputs.
+pub trait TraceGenerator<W>: Send + Sync {
+    type Fp: FftField;
+    type Fq: Field<BasePrimeField = Self::Fp>;
+    type PublicInputs;
+
+    /// Generates the base trace columns from the witness
+    fn gen_base_columns(&self, witness: &W) -> Matrix<Self::Fp>;
+
+    /// Generates the extension trace columns from the verifier's challenges.
+    /// Returns None if there are no columns that require challenges.
+    fn gen_extension_columns(
+        &self,
+        _witness: &W,
+        _base_columns: &Matrix<Self::Fp>,
         _challenges: &Challenges<Self::Fq>,
     ) -> Option<Matrix<Self::Fq>> {
         None
     }
+
+    /// Extracts the public inputs from the witness
+    fn public_inputs(&self, witness: &W) -> Self::PublicInputs;
+
+    /// Generates the base trace columns and returns a trace that generates the
+    /// extension columns on demand
+    fn generate(self, witness: W) -> GeneratedTrace<W, Self>
+    where
+        Self: Sized,
+    {
+        let base_columns = self.gen_base_columns(&witness);
+        GeneratedTrace {
+            generator: self,
+            witness,
+            base_columns,
+        }
+    }
+}
+
+/// Execution trace generated by a [`TraceGenerator`]
+pub struct GeneratedTrace<W, G: TraceGenerator<W>> {
+    generator: G,
+    witness: W,
+    base_columns: Matrix<G::Fp>,
+}
+
+impl<W, G: TraceGenerator<W>> GeneratedTrace<W, G> {
+    pub const fn generator(&self) -> &G {
+        &self.generator
+    }
+
+    pub const fn witness(&self) -> &W {
+        &self.witness
+    }
+
+    pub fn public_inputs(&self) -> G::PublicInputs {
+        self.generator.public_inputs(&self.witness)
+    }
+}
+
+impl<W: Send + Sync, G: TraceGenerator<W>> Trace for GeneratedTrace<W, G> {
+    type Fp = G::Fp;
+    type Fq = G::Fq;
+
+    fn base_columns(&self) -> Option<&Matrix<Self::Fp>> {
+        Some(&self.base_columns)
+    }
+
+    fn build_extension_columns(
+        &self,
+        challenges: &Challenges<Self::Fq>,
+    ) -> Option<Matrix<Self::Fq>> {
+        self.generator
+            .gen_extension_columns(&self.witness, &self.base_columns, challenges)
+    }
+}
+
+/// Function that builds the values of an extension column
+type BuildExtensionColumn<'a, Fp, Fq> =
+    dyn Fn(&ExtensionColumnInputs<'_, Fp, Fq>) -> GpuVec<Fq> + Send + Sync + 'a;
+
+/// Extension column that declares the base columns, challenges and other
+/// extension columns it's built from
+pub struct ExtensionColumn<'a, Fp, Fq: Field> {
+    base_columns: Vec<usize>,
+    challenges: Vec<usize>,
+    extension_columns: Vec<usize>,
+    build: Box<BuildExtensionColumn<'a, Fp, Fq>>,
+}
+
+impl<'a, Fp, Fq: Field> ExtensionColumn<'a, Fp, Fq> {
+    /// Creates a column with values returned by `build`. The column can only
+    /// read the dependencies declared with the `with_*` methods.
+    pub fn new(
+        build: impl Fn(&ExtensionColumnInputs<'_, Fp, Fq>) -> GpuVec<Fq> + Send + Sync + 'a,
+    ) -> Self {
+        Self {
+            base_columns: Vec::new(),
+            challenges: Vec::new(),
+            extension_columns: Vec::new(),
+            build: Box::new(build),
+        }
+    }
+
+    pub fn with_base_columns(
+        mut self,
+        columns: impl IntoIterator<Item = impl ExecutionTraceColumn>,
+    ) -> Self {
+        self.base_columns
+            .extend(columns.into_iter().map(|column| column.index()));
+        self
+    }
+
+    pub fn with_challenges(
+        mut self,
+        challenges: impl IntoIterator<Item = impl VerifierChallenge>,
+    ) -> Self {
+        self.challenges
+            .extend(challenges.into_iter().map(|challenge| challenge.index()));
+        self
+    }
+
+    /// Declares dependencies on other extension columns. Columns are indexed
+    /// from the first extension column.
+    pub fn with_extension_columns(mut self, columns: impl IntoIterator<Item = usize>) -> Self {
+        self.extension_columns.extend(columns);
+        self
+    }
+}
+
+/// Values an [`ExtensionColumn`] is built from
+pub struct ExtensionColumnInputs<'a, Fp, Fq: Field> {
+    column: &'a ExtensionColumn<'a, Fp, Fq>,
+    base_columns: &'a [Option<&'a [Fp]>],
+    challenges: &'a Challenges<Fq>,
+    extension_columns: &'a [Option<GpuVec<Fq>>],
+}
+
+impl<'a, Fp, Fq: Field> ExtensionColumnInputs<'a, Fp, Fq> {
+    /// # Panics
+    /// Panics if the column didn't declare a dependency on the base column
+    pub fn base_column(&self, column: impl ExecutionTraceColumn) -> &'a [Fp] {
+        let index = column.index();
+        assert!(
+            self.column.base_columns.contains(&index),
+            "undeclared dependency on base column {index}"
+        );
+        self.base_columns[index].unwrap()
+    }
+
+    /// # Panics
+    /// Panics if the column didn't declare a dependency on the challenge
+    pub fn challenge(&self, challenge: impl VerifierChallenge) -> Fq {
+        let index = challenge.index();
+        assert!(
+            self.column.challenges.contains(&index),
+            "undeclared dependency on challenge {index}"
+        );
+        self.challenges[index]
+    }
+
+    /// # Panics
+    /// Panics if the column didn't declare a dependency on the extension column
+    pub fn extension_column(&self, column: usize) -> &'a [Fq] {
+        assert!(
+            self.column.extension_columns.contains(&column),
+            "undeclared dependency on extension column {column}"
+        );
+        self.extension_columns[column].as_ref().unwrap()
+    }
+}
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum ExtensionColumnError {
+    #[snafu(display(
+        "extension column {column} depends on extension column {dependency} which doesn't exist"
+    ))]
+    UnknownDependency { column: usize, dependency: usize },
+    #[snafu(display("extension columns {columns:?} have cyclic dependencies"))]
+    CyclicDependencies { columns: Vec<usize> },
+}
+
+/// Extension columns scheduled by their dependencies. Columns are split into
+/// levels where each column only depends on columns in earlier levels. Levels
+/// are built one after the other and the columns within a level in parallel.
+pub struct ExtensionColumnGraph<'a, Fp, Fq: Field> {
+    columns: Vec<ExtensionColumn<'a, Fp, Fq>>,
+    levels: Vec<Vec<usize>>,
+}
+
+impl<'a, Fp: Field, Fq: Field> ExtensionColumnGraph<'a, Fp, Fq> {
+    pub fn new(columns: Vec<ExtensionColumn<'a, Fp, Fq>>) -> Result<Self, ExtensionColumnError> {
+        let num_columns = columns.len();
+        for (column, extension_column) in columns.iter().enumerate() {
+            if let Some(&dependency) = extension_column
+                .extension_columns
+                .iter()
+                .find(|&&dependency| dependency >= num_columns)
+            {
+                return Err(ExtensionColumnError::UnknownDependency { column, dependency });
+            }
+        }
+
+        let mut scheduled = vec![false; num_columns];
+        let mut remaining = (0..num_columns).collect::<Vec<usize>>();
+        let mut levels = Vec::new();
+        while !remaining.is_empty() {
+            let (level, blocked): (Vec<usize>, Vec<usize>) =
+                remaining.into_iter().partition(|&column| {
+                    columns[column]
+                        .extension_columns
+                        .iter()
+                        .all(|&dependency| scheduled[dependency])
+                });
+            if level.is_empty() {
+                return Err(ExtensionColumnError::CyclicDependencies { columns: blocked });
+            }
+            for &column in &level {
+                scheduled[column] = true;
+            }
+            levels.push(level);
+            remaining = blocked;
+        }
+        Ok(Self { columns, levels })
+    }
+
+    /// Returns the indices of the columns in each level
+    pub fn levels(&self) -> &[Vec<usize>] {
+        &self.levels
+    }
+
+    /// Builds the extension columns
+    ///
+    /// # Panics
+    /// Panics if a column reads an undeclared dependency or has a different
+    /// number of rows to the base columns
+    pub fn build(&self, base_columns: &Matrix<Fp>, challenges: &Challenges<Fq>) -> Matrix<Fq> {
+        let num_rows = base_columns.num_rows();
+        let base_column = |column: usize| Cow::Borrowed(&base_columns.0[column][..]);
+        self.build_from_columns(num_rows, base_column, challenges)
+    }
+
+    /// Builds the extension columns from base columns read with
+    /// `base_column`. Only the base columns declared as dependencies are read
+    /// so compressed traces only expand the columns that are needed.
+    ///
+    /// # Panics
+    /// Panics if a column reads an undeclared dependency or doesn't have
+    /// `num_rows` rows
+    pub fn build_from_columns<'b>(
+        &self,
+        num_rows: usize,
+        base_column: impl Fn(usize) -> Cow<'b, [Fp]>,
+        challenges: &Challenges<Fq>,
+    ) -> Matrix<Fq> {
+        let dependencies = self
+            .columns
+            .iter()
+            .flat_map(|column| &column.base_columns)
+            .copied()
+            .collect::<Vec<usize>>();
+        let num_base_columns = dependencies.iter().max().map_or(0, |&max| max + 1);
+        let base_columns = (0..num_base_columns)
+            .map(|column| dependencies.contains(&column).then(|| base_column(column)))
+            .collect::<Vec<Option<Cow<'b, [Fp]>>>>();
+        let base_columns = &base_columns
+            .iter()
+            .map(Option::as_deref)
+            .collect::<Vec<Option<&[Fp]>>>();
+        let mut extension_columns = (0..self.columns.len())
+            .map(|_| None)
+            .collect::<Vec<Option<GpuVec<Fq>>>>();
+        for level in &self.levels {
+            let values = ark_std::cfg_iter!(level)
+                .map(|&column| {
+                    let extension_column = &self.columns[column];
+                    let inputs = ExtensionColumnInputs {
+                        column: extension_column,
+                        base_columns,
+                        challenges,
+                        extension_columns: &extension_columns,
+                    };
+                    let values = (extension_column.build)(&inputs);
+                    assert_eq!(
+                        values.len(),
+                        num_rows,
+                        "extension column {column} has the wrong number of rows"
+                    );
+                    values
+                })
+                .collect::<Vec<GpuVec<Fq>>>();
+            for (&column, values) in zip(level, values) {
+                extension_columns[column] = Some(values);
+            }
+        }
+        Matrix::new(extension_columns.into_iter().map(Option::unwrap).collect())
+    }
 }
 
+/// Shape of the trace a proof is for. The prover and verifier bind this into
+/// the transcript before any commitments so a proof can't be passed off as a
+/// proof for a trace of another length.
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct TraceInfo {
+    pub trace_len: usize,
+    pub num_base_columns: usize,
+    pub num_extension_columns: usize,
+    /// See [`AirConfig::trace_meta`]
+    pub meta: Vec<u8>,
+}
+
+impl TraceInfo {
+    /// Reseeds the public coin with this info
+    pub fn bind(&self, public_coin: &mut impl PublicCoin) {
+        for n in [
+            self.trace_len,
+            self.num_base_columns,
+            self.num_extension_columns,
+            self.meta.len(),
+        ] {
+            public_coin.reseed_with_int(n as u64);
+        }
+        for chunk in self.meta.chunks(8) {
+            let mut bytes = [0; 8];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            public_coin.reseed_with_int(u64::from_le_bytes(bytes));
+        }
+    }
+
+    /// Returns the number of times [`Self::bind`] reseeds the public coin
+    pub fn num_reseeds(&self) -> usize {
+        4 + self.meta.len().div_ceil(8)
+    }
+}
+
+/// Rows of the base, extension and composition traces opened at a query
+/// position. The rows are serialized without their lengths since every
+/// opening of a proof has the same shape (see [`Queries`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceOpening<Fp, Fq> {
+    pub base_trace_row: Vec<Fp>,
+    /// Empty if there are no extension columns
+    pub extension_trace_row: Vec<Fq>,
+    pub composition_trace_row: Vec<Fq>,
+}
+
+impl<Fp, Fq> TraceOpening<Fp, Fq> {
+    /// Returns true if the rows have the given number of base, extension and
+    /// composition columns
+    pub fn has_shape(
+        &self,
+        [num_base_columns, num_extension_columns, num_composition_columns]: [usize; 3],
+    ) -> bool {
+        self.base_trace_row.len() == num_base_columns
+            && self.extension_trace_row.len() == num_extension_columns
+            && self.composition_trace_row.len() == num_composition_columns
+    }
+}
+
+impl<Fp: CanonicalSerialize, Fq: CanonicalSerialize> TraceOpening<Fp, Fq> {
+    fn serialize_rows<W: ark_serialize::Write>(
+        &self,
+        mut writer: W,
+        compress: ark_serialize::Compress,
+    ) -> Result<(), ark_serialize::SerializationError> {
+        for value in &self.base_trace_row {
+            value.serialize_with_mode(&mut writer, compress)?;
+        }
+        for value in self
+            .extension_trace_row
+            .iter()
+            .chain(&self.composition_trace_row)
+        {
+            value.serialize_with_mode(&mut writer, compress)?;
+        }
+        Ok(())
+    }
+
+    fn rows_serialized_size(&self, compress: ark_serialize::Compress) -> usize {
+        let base_size = self
+            .base_trace_row
+            .iter()
+            .map(|value| value.serialized_size(compress))
+            .sum::<usize>();
+        let extension_size = self
+            .extension_trace_row
+            .iter()
+            .chain(&self.composition_trace_row)
+            .map(|value| value.serialized_size(compress))
+            .sum::<usize>();
+        base_size + extension_size
+    }
+}
+
+impl<Fp: CanonicalDeserialize, Fq: CanonicalDeserialize> TraceOpening<Fp, Fq> {
+    fn deserialize_rows<R: ark_serialize::Read>(
+        mut reader: R,
+        [num_base_columns, num_extension_columns, num_composition_columns]: [usize; 3],
+        compress: ark_serialize::Compress,
+        validate: ark_serialize::Validate,
+    ) -> Result<Self, ark_serialize::SerializationError> {
+        let base_trace_row = (0..num_base_columns)
+            .map(|_| Fp::deserialize_with_mode(&mut reader, compress, validate))
+            .collect::<Result<Vec<Fp>, _>>()?;
+        let mut row = |num_columns: usize| {
+            (0..num_columns)
+                .map(|_| Fq::deserialize_with_mode(&mut reader, compress, validate))
+                .collect::<Result<Vec<Fq>, _>>()
+        };
+        Ok(Self {
+            base_trace_row,
+            extension_trace_row: row(num_extension_columns)?,
+            composition_trace_row: row(num_composition_columns)?,
+        })
+    }
+}
+
+/// Rows of the base, extension and composition traces at a query position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryOpening<'a, Fp, Fq> {
+    pub position: usize,
+    pub base_trace_row: &'a [Fp],
+    /// Empty if there are no extension columns
+    pub extension_trace_row: &'a [Fq],
+    pub composition_trace_row: &'a [Fq],
+}
+
+/// Pairs each opening with its query position. Returns `None` if the number of
+/// openings doesn't match the number of positions or a row doesn't have the
+/// number of columns of its trace.
+pub fn query_openings<'a, Fp, Fq>(
+    positions: &[usize],
+    shape: [usize; 3],
+    openings: &'a [TraceOpening<Fp, Fq>],
+) -> Option<Vec<QueryOpening<'a, Fp, Fq>>> {
+    if openings.len() != positions.len() {
+        return None;
+    }
+    zip(positions, openings)
+        .map(|(&position, opening)| {
+            opening.has_shape(shape).then_some(QueryOpening {
+                position,
+                base_trace_row: &opening.base_trace_row,
+                extension_trace_row: &opening.extension_trace_row,
+                composition_trace_row: &opening.composition_trace_row,
+            })
+        })
+        .collect()
+}
+
+/// Trace rows and Merkle proofs at the query positions. The openings are
+/// serialized after their count and the number of composition columns, the
+/// only row length that isn't a constant of the AIR.
 pub struct Queries<C: Stark> {
-    pub base_trace_values: Vec<C::Fp>,
-    pub extension_trace_values: Vec<C::Fq>,
-    pub composition_trace_values: Vec<C::Fq>,
+    /// Opening at each query position
+    pub openings: Vec<TraceOpening<C::Fp, C::Fq>>,
     pub base_trace_proof: <C::MerkleTree as MerkleTree>::Proof,
     pub extension_trace_proof: Option<<C::MerkleTree as MerkleTree>::Proof>,
     pub composition_trace_proof: <C::MerkleTree as MerkleTree>::Proof,
@@ -50,12 +569,22 @@ impl<C: Stark> CanonicalSerialize for Queries<C> {
         mut writer: W,
         compress: ark_serialize::Compress,
     ) -> Result<(), ark_serialize::SerializationError> {
-        self.base_trace_values
-            .serialize_with_mode(&mut writer, compress)?;
-        self.extension_trace_values
-            .serialize_with_mode(&mut writer, compress)?;
-        self.composition_trace_values
+        let num_composition_columns = self.num_composition_columns();
+        let shape = [
+            C::AirConfig::NUM_BASE_COLUMNS,
+            C::AirConfig::NUM_EXTENSION_COLUMNS,
+            num_composition_columns,
+        ];
+        if !self.openings.iter().all(|opening| opening.has_shape(shape)) {
+            return Err(ark_serialize::SerializationError::InvalidData);
+        }
+        self.openings
+            .len()
             .serialize_with_mode(&mut writer, compress)?;
+        num_composition_columns.serialize_with_mode(&mut writer, compress)?;
+        for opening in &self.openings {
+            opening.serialize_rows(&mut writer, compress)?;
+        }
         self.base_trace_proof
             .serialize_with_mode(&mut writer, compress)?;
         self.extension_trace_proof
@@ -66,9 +595,13 @@ impl<C: Stark> CanonicalSerialize for Queries<C> {
     }
 
     fn serialized_size(&self, compress: ark_serialize::Compress) -> usize {
-        self.base_trace_values.serialized_size(compress)
-            + self.extension_trace_values.serialized_size(compress)
-            + self.composition_trace_values.serialized_size(compress)
+        self.openings.len().serialized_size(compress)
+            + self.num_composition_columns().serialized_size(compress)
+            + self
+                .openings
+                .iter()
+                .map(|opening| opening.rows_serialized_size(compress))
+                .sum::<usize>()
             + self.base_trace_proof.serialized_size(compress)
             + self.extension_trace_proof.serialized_size(compress)
             + self.composition_trace_proof.serialized_size(compress)
@@ -87,10 +620,19 @@ impl<C: Stark> CanonicalDeserialize for Queries<C> {
         compress: ark_serialize::Compress,
         validate: ark_serialize::Validate,
     ) -> Result<Self, ark_serialize::SerializationError> {
+        let num_openings = usize::deserialize_with_mode(&mut reader, compress, validate)?;
+        let num_composition_columns =
+            usize::deserialize_with_mode(&mut reader, compress, validate)?;
+        let shape = [
+            C::AirConfig::NUM_BASE_COLUMNS,
+            C::AirConfig::NUM_EXTENSION_COLUMNS,
+            num_composition_columns,
+        ];
+        let openings = (0..num_openings)
+            .map(|_| TraceOpening::deserialize_rows(&mut reader, shape, compress, validate))
+            .collect::<Result<Vec<_>, _>>()?;
         Ok(Self {
-            base_trace_values: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
-            extension_trace_values: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
-            composition_trace_values: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            openings,
             base_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             extension_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
             composition_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
@@ -101,9 +643,7 @@ impl<C: Stark> CanonicalDeserialize for Queries<C> {
 impl<C: Stark> Clone for Queries<C> {
     fn clone(&self) -> Self {
         Self {
-            base_trace_values: self.base_trace_values.clone(),
-            extension_trace_values: self.extension_trace_values.clone(),
-            composition_trace_values: self.composition_trace_values.clone(),
+            openings: self.openings.clone(),
             base_trace_proof: self.base_trace_proof.clone(),
             extension_trace_proof: self.extension_trace_proof.clone(),
             composition_trace_proof: self.composition_trace_proof.clone(),
@@ -112,6 +652,30 @@ impl<C: Stark> Clone for Queries<C> {
 }
 
 impl<C: Stark> Queries<C> {
+    /// Returns the rows of every trace at each query position. See
+    /// [`query_openings`].
+    pub fn openings(
+        &self,
+        positions: &[usize],
+        num_composition_columns: usize,
+    ) -> Option<Vec<QueryOpening<'_, C::Fp, C::Fq>>> {
+        query_openings(
+            positions,
+            [
+                C::AirConfig::NUM_BASE_COLUMNS,
+                C::AirConfig::NUM_EXTENSION_COLUMNS,
+                num_composition_columns,
+            ],
+            &self.openings,
+        )
+    }
+
+    fn num_composition_columns(&self) -> usize {
+        self.openings
+            .first()
+            .map_or(0, |opening| opening.composition_trace_row.len())
+    }
+
     pub fn new(
         base_trace_lde: &Matrix<C::Fp>,
         extension_trace_lde: Option<&Matrix<C::Fq>>,
@@ -128,31 +692,139 @@ impl<C: Stark> Queries<C> {
         let composition_trace_proof =
             MatrixMerkleTree::<C::Fq>::prove_rows(composition_tree, positions).unwrap();
 
-        let mut base_trace_values = Vec::new();
-        let mut extension_trace_values = Vec::new();
-        let mut composition_trace_values = Vec::new();
-        for &position in positions {
-            // execution trace
-            let base_trace_row = base_trace_lde.get_row(position).unwrap();
-            base_trace_values.extend(base_trace_row);
-
-            if let Some(extension_trace_lde) = extension_trace_lde {
-                // TODO: suport ark DomainCoeff on evaluate_at
-                let extension_trace_row = extension_trace_lde.get_row(position).unwrap();
-                extension_trace_values.extend(extension_trace_row);
-            }
-
-            // composition trace
-            let composition_trace_row = composition_trace_lde.get_row(position).unwrap();
-            composition_trace_values.extend(composition_trace_row);
-        }
+        let openings = positions
+            .iter()
+            .map(|&position| TraceOpening {
+                base_trace_row: base_trace_lde.get_row(position).unwrap(),
+                extension_trace_row: extension_trace_lde
+                    .map(|lde| lde.get_row(position).unwrap())
+                    .unwrap_or_default(),
+                composition_trace_row: composition_trace_lde.get_row(position).unwrap(),
+            })
+            .collect();
         Self {
-            base_trace_values,
-            extension_trace_values,
-            composition_trace_values,
+            openings,
             base_trace_proof,
             extension_trace_proof,
             composition_trace_proof,
         }
     }
 }
+
+/// Merkle proofs of the trace queries
+pub struct TraceProofs<C: Stark> {
+    pub base_trace_proof: <C::MerkleTree as MerkleTree>::Proof,
+    pub extension_trace_proof: Option<<C::MerkleTree as MerkleTree>::Proof>,
+    pub composition_trace_proof: <C::MerkleTree as MerkleTree>::Proof,
+}
+
+/// Trace queries whose openings are resolved one at a time. The verifier
+/// hashes and evaluates each opening as it's resolved so it never needs every
+/// opening at once. Implemented by [`Queries`] and [`LazyQueries`].
+pub trait TraceOpenings<C: Stark> {
+    /// Returns the number of openings
+    fn num_openings(&self) -> usize;
+
+    /// Passes each opening in order of the query positions to `f` and then
+    /// returns the Merkle proofs
+    fn resolve<E: From<SerializationError>>(
+        self,
+        f: impl FnMut(TraceOpening<C::Fp, C::Fq>) -> Result<(), E>,
+    ) -> Result<TraceProofs<C>, E>;
+}
+
+impl<C: Stark> TraceOpenings<C> for Queries<C> {
+    fn num_openings(&self) -> usize {
+        self.openings.len()
+    }
+
+    fn resolve<E: From<SerializationError>>(
+        self,
+        mut f: impl FnMut(TraceOpening<C::Fp, C::Fq>) -> Result<(), E>,
+    ) -> Result<TraceProofs<C>, E> {
+        for opening in self.openings {
+            f(opening)?;
+        }
+        Ok(TraceProofs {
+            base_trace_proof: self.base_trace_proof,
+            extension_trace_proof: self.extension_trace_proof,
+            composition_trace_proof: self.composition_trace_proof,
+        })
+    }
+}
+
+/// [`Queries`] read from a reader on demand. Only the number of openings and
+/// composition columns are read upfront. Each opening is deserialized when
+/// it's resolved and the Merkle proofs are read after the last opening.
+pub struct LazyQueries<C: Stark, R: Read> {
+    reader: R,
+    compress: Compress,
+    validate: Validate,
+    num_openings: usize,
+    shape: [usize; 3],
+    _phantom: PhantomData<C>,
+}
+
+impl<C: Stark, R: Read> LazyQueries<C, R> {
+    /// Reads the start of queries serialized with
+    /// [`Queries::serialize_with_mode`]
+    pub fn new(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let num_openings = usize::deserialize_with_mode(&mut reader, compress, validate)?;
+        let num_composition_columns =
+            usize::deserialize_with_mode(&mut reader, compress, validate)?;
+        Ok(Self {
+            reader,
+            compress,
+            validate,
+            num_openings,
+            shape: [
+                C::AirConfig::NUM_BASE_COLUMNS,
+                C::AirConfig::NUM_EXTENSION_COLUMNS,
+                num_composition_columns,
+            ],
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Returns the number of composition columns of each opening
+    pub const fn num_composition_columns(&self) -> usize {
+        self.shape[2]
+    }
+}
+
+impl<C: Stark, R: Read> TraceOpenings<C> for LazyQueries<C, R> {
+    fn num_openings(&self) -> usize {
+        self.num_openings
+    }
+
+    fn resolve<E: From<SerializationError>>(
+        self,
+        mut f: impl FnMut(TraceOpening<C::Fp, C::Fq>) -> Result<(), E>,
+    ) -> Result<TraceProofs<C>, E> {
+        let Self {
+            mut reader,
+            compress,
+            validate,
+            num_openings,
+            shape,
+            _phantom,
+        } = self;
+        for _ in 0..num_openings {
+            f(TraceOpening::deserialize_rows(
+                &mut reader,
+                shape,
+                compress,
+                validate,
+            )?)?;
+        }
+        Ok(TraceProofs {
+            base_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            extension_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+            composition_trace_proof: <_>::deserialize_with_mode(&mut reader, compress, validate)?,
+        })
+    }
+}
@@ -130,6 +130,127 @@ pub fn horner_evaluate<F: Field, T: Field + for<'a> Add<&'a F, Output = T>>(
         .rfold(T::zero(), move |result, coeff| result * point + coeff)
 }
 
+/// Evaluates a polynomial at several points with Horner's method. All points
+/// are stepped together for each coefficient so the inner loop is over
+/// independent values which the compiler can vectorize.
+pub fn horner_evaluate_many<F: Field, T: Field + for<'a> Add<&'a F, Output = T>>(
+    poly_coeffs: &[F],
+    points: &[T],
+) -> Vec<T> {
+    let mut results = vec![T::zero(); points.len()];
+    for coeff in poly_coeffs.iter().rev() {
+        for (result, point) in zip(&mut results, points) {
+            *result = *result * point + coeff;
+        }
+    }
+    results
+}
+
+/// Number of independent running products in [`batch_inverse`]. Interleaving
+/// several products keeps multiple multiplications in flight rather than
+/// waiting on a single dependency chain.
+const BATCH_INVERSE_LANES: usize = 4;
+
+/// Replaces each non-zero value with its inverse using Montgomery's trick.
+/// Zeros are left unchanged.
+pub fn batch_inverse<F: Field>(values: &mut [F]) {
+    #[cfg(not(feature = "parallel"))]
+    serial_batch_inverse(values);
+    #[cfg(feature = "parallel")]
+    {
+        let chunk_size = values.len().div_ceil(rayon::current_num_threads()).max(1);
+        values
+            .par_chunks_mut(chunk_size)
+            .for_each(serial_batch_inverse);
+    }
+}
+
+fn serial_batch_inverse<F: Field>(values: &mut [F]) {
+    const LANES: usize = BATCH_INVERSE_LANES;
+
+    // running product of each lane up to and including each value
+    let mut products = Vec::with_capacity(values.len());
+    let mut accumulators = [F::one(); LANES];
+    for chunk in values.chunks(LANES) {
+        for (accumulator, value) in zip(&mut accumulators, chunk) {
+            if !value.is_zero() {
+                *accumulator *= value;
+            }
+            products.push(*accumulator);
+        }
+    }
+
+    // a single inversion per lane
+    for accumulator in &mut accumulators {
+        *accumulator = accumulator.inverse().unwrap();
+    }
+
+    for (i, value) in values.iter_mut().enumerate().rev() {
+        if value.is_zero() {
+            continue;
+        }
+        let accumulator = &mut accumulators[i % LANES];
+        let preceding_product = if i >= LANES {
+            products[i - LANES]
+        } else {
+            F::one()
+        };
+        let inverse = *accumulator * preceding_product;
+        *accumulator *= *value;
+        *value = inverse;
+    }
+}
+
+/// Returns the barycentric weights `w_j = 1 / Π_{m≠j} (x_j - x_m)` of
+/// distinct points `xs`. Weights only depend on the points so they can be
+/// reused to evaluate any number of polynomials opened at the same points.
+///
+/// # Panics
+/// Panics if the points aren't distinct
+pub fn barycentric_weights<F: Field>(xs: &[F]) -> Vec<F> {
+    let mut weights = xs
+        .iter()
+        .enumerate()
+        .map(|(j, &xj)| {
+            xs.iter()
+                .enumerate()
+                .filter(|&(m, _)| m != j)
+                .map(|(_, &xm)| xj - xm)
+                .product::<F>()
+        })
+        .collect::<Vec<F>>();
+    assert!(
+        weights.iter().all(|w| !w.is_zero()),
+        "points must be distinct"
+    );
+    batch_inverse(&mut weights);
+    weights
+}
+
+/// Evaluates the polynomial of degree less than `xs.len()` that takes values
+/// `ys` at points `xs` at the point `z` using the barycentric form of Lagrange
+/// interpolation `p(z) = L(z) * Σ w_j * y_j / (z - x_j)` where
+/// `L(z) = Π (z - x_j)` and `weights` are the [`barycentric_weights`] of `xs`.
+/// Lets a verifier evaluate a polynomial at an out-of-domain point from its
+/// queried LDE values without the polynomial's coefficients.
+///
+/// # Panics
+/// Panics if `xs`, `weights` and `ys` have different lengths
+pub fn barycentric_evaluate<F: Field>(xs: &[F], weights: &[F], ys: &[F], z: F) -> F {
+    assert_eq!(xs.len(), weights.len());
+    assert_eq!(xs.len(), ys.len());
+    if let Some(i) = xs.iter().position(|&x| x == z) {
+        return ys[i];
+    }
+    let mut inverse_differences = xs.iter().map(|&x| z - x).collect::<Vec<F>>();
+    let vanishing_eval = inverse_differences.iter().product::<F>();
+    batch_inverse(&mut inverse_differences);
+    let sum = zip(zip(weights, ys), inverse_differences)
+        .map(|((&w, &y), inverse_difference)| w * y * inverse_difference)
+        .sum::<F>();
+    vanishing_eval * sum
+}
+
 /// Calculates `c * (P(X) - P(z)) / (X - z)` using synthetic division
 /// <https://en.wikipedia.org/wiki/Synthetic_division>
 // adapted from OpenZKP <https://github.com/0xProject/OpenZKP/blob/master/crypto/stark/src/polynomial.rs#L120>
@@ -469,6 +590,18 @@ pub fn vec_to_gpu_vec<T>(v: Vec<T>) -> GpuVec<T> {
     unsafe { Vec::from_raw_parts_in(ptr, length, capacity, GpuAllocator) }
 }
 
+/// Smallest domain worth sending to the GPU. Below this the cost of
+/// dispatching work outweighs the faster kernels so small proofs (see
+/// [`crate::ProofOptions::small`]) run entirely on the CPU.
+pub const GPU_MIN_DOMAIN_SIZE: usize = 1 << 16;
+
+/// Returns true if work over a domain of size `domain_size` should run on the
+/// GPU
+#[cfg(feature = "gpu")]
+pub const fn use_gpu<F: ministark_gpu::GpuField>(domain_size: usize) -> bool {
+    F::GPU_SUPPORTED && domain_size >= GPU_MIN_DOMAIN_SIZE
+}
+
 #[cfg(all(target_arch = "aarch64", target_os = "macos"))]
 mod page_aligned_allocator {
     use alloc::alloc::Global;
@@ -507,6 +640,12 @@ impl<D: digest::Digest> Digest for SerdeOutput<D> {
         res.copy_from_slice(&self.0);
         res
     }
+
+    fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let mut res = digest::Output::<D>::default();
+        res.copy_from_slice(bytes);
+        Self(res)
+    }
 }
 
 impl<D: digest::Digest> Eq for SerdeOutput<D> {}
@@ -1,197 +1,1142 @@
 use crate::air::AirConfig;
+use crate::air::AirConfigError;
+use crate::air::AirVersion;
 use crate::challenges::Challenges;
 use crate::channel::VerifierChannelArtifacts;
 use crate::composer::DeepCompositionCoeffs;
 use crate::constraints::AlgebraicItem;
 use crate::constraints::CompositionItem;
+use crate::expression::Expr;
 use crate::fri;
+use crate::fri::FriCommitments;
+use crate::fri::FriProof;
 use crate::fri::FriVerifier;
 use crate::hints::Hints;
+use crate::ldt::Fri;
+use crate::ldt::LowDegreeTest;
+use crate::merkle;
+use crate::merkle::DeferredRowCheck;
 use crate::merkle::MatrixMerkleTree;
-use crate::random::draw_multiple;
+use crate::merkle::TreeTag;
+use crate::profile::ProofProfile;
+use crate::proof::security_level_bits;
+use crate::proof::ProofHeader;
+use crate::proof::PROOF_VERSION;
+use crate::random::derive_query_positions;
+use crate::random::draw_from_sub_coin;
 use crate::random::PublicCoin;
+use crate::random::SubCoin;
+use crate::serialize::encoded_size;
+use crate::serialize::ByteOrder;
 use crate::stark::Stark;
+use crate::trace::LazyQueries;
+use crate::trace::Queries;
+use crate::trace::TraceOpening;
+use crate::trace::TraceOpenings;
+use crate::trace::TraceProofs;
+use crate::utils::batch_inverse;
 use crate::utils::horner_evaluate;
 use crate::utils::FieldVariant;
 use crate::Air;
 use crate::Proof;
+use crate::ProofOptions;
 use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::vec::Vec;
 use ark_ff::Field;
 use ark_ff::Zero;
 use ark_poly::EvaluationDomain;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ark_serialize::Compress;
+use ark_serialize::Read;
+use ark_serialize::SerializationError;
+use ark_serialize::Validate;
+use core::iter::successors;
+use core::iter::zip;
 use ministark_gpu::utils::bit_reverse_index;
 use snafu::Snafu;
 
-#[allow(clippy::too_many_lines)]
 pub fn default_verify<S: Stark>(
     this: &S,
     proof: Proof<S>,
     required_security_bits: u32,
 ) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
-    use VerificationError::*;
+    verify_impl(this, proof, required_security_bits, true)
+}
 
-    if proof.security_level_bits() < required_security_bits {
-        return Err(InvalidProofSecurity);
+/// Verifies a proof only if it was generated with the options, fields and
+/// hash functions pinned by `profile`
+pub fn default_verify_with_profile<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    profile: &ProofProfile,
+    required_security_bits: u32,
+) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+    profile.check_header(&proof.header)?;
+    verify_impl(this, proof, required_security_bits, true)
+}
+
+/// Verifies the transcript, OOD consistency and FRI but skips checking that
+/// the trace queries resolve to the trace commitments. This gives a much
+/// weaker guarantee than [`default_verify`] but is useful for quick sanity
+/// checks and sampling based audit tooling.
+pub fn default_verify_light<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    required_security_bits: u32,
+) -> Result<LightVerification<S::Fq>, VerificationError> {
+    verify_impl(this, proof, required_security_bits, false).map(LightVerification)
+}
+
+/// Result of a light verification. Kept separate from
+/// [`VerifierChannelArtifacts`] so a lightly verified proof can't be mistaken
+/// for a fully verified one.
+#[derive(Debug, Clone)]
+pub struct LightVerification<F: Field>(VerifierChannelArtifacts<F>);
+
+impl<F: Field> LightVerification<F> {
+    /// Returns the verifier's artifacts. Note the trace queries used to derive
+    /// these were not checked against the trace commitments.
+    pub const fn unchecked_artifacts(&self) -> &VerifierChannelArtifacts<F> {
+        &self.0
     }
+}
 
-    let Proof {
-        options,
+fn verify_impl<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    required_security_bits: u32,
+    check_trace_queries: bool,
+) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+    let (pending, openings) = replay_transcript(this, proof, required_security_bits)?;
+    let mut inverse_denominators = pending.deep_composition_denominators();
+    batch_inverse(&mut inverse_denominators);
+    let merkle_checks = if check_trace_queries {
+        MerkleChecks::Now
+    } else {
+        MerkleChecks::SkipTrace
+    };
+    pending.finish(merkle_checks, &inverse_denominators, openings)
+}
+
+/// Verifies a proof while it's deserialized from `reader`. Proofs are
+/// serialized in transcript order so each section is checked as soon as it's
+/// read: the header before the body, the OOD evaluations and proof of work
+/// before any openings and the trace openings one query at a time. Invalid
+/// proofs are rejected without reading past the section that failed.
+pub fn default_verify_from_reader<S: Stark>(
+    this: &S,
+    mut reader: impl Read,
+    compress: Compress,
+    required_security_bits: u32,
+) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+    let header = read::<ProofHeader>(&mut reader, compress)?;
+    let trace_len = read(&mut reader, compress)?;
+    let air = verify_proof_start(this, &header, trace_len, required_security_bits)?;
+
+    let base_trace_commitment = read(&mut reader, compress)?;
+    let extension_trace_commitment = read(&mut reader, compress)?;
+    let composition_trace_commitment = read(&mut reader, compress)?;
+    let transcript = CommittedTranscript::new(
+        this,
+        air,
         base_trace_commitment,
         extension_trace_commitment,
         composition_trace_commitment,
+    )?;
+
+    let execution_trace_ood_evals = read(&mut reader, compress)?;
+    let composition_trace_ood_evals = read(&mut reader, compress)?;
+    let transcript = transcript.replay_ood_evals(
+        this,
         execution_trace_ood_evals,
         composition_trace_ood_evals,
-        trace_queries,
+    )?;
+
+    let fri_commitments = read::<FriCommitments<S::Fq, S::Digest>>(&mut reader, compress)?;
+    let fri_layer_commitments = fri_commitments.layer_commitments.clone();
+    let pow_nonce = read(&mut reader, compress)?;
+    let pending = transcript.replay_low_degree_test(fri_commitments, pow_nonce)?;
+
+    let mut inverse_denominators = pending.deep_composition_denominators();
+    batch_inverse(&mut inverse_denominators);
+    let trace_queries = LazyQueries::<S, _>::new(&mut reader, compress, Validate::Yes)?;
+    let checked =
+        pending.check_trace_queries(MerkleChecks::Now, &inverse_denominators, trace_queries)?;
+    let fri_layers = FriProof::deserialize_layer_openings(
+        &mut reader,
+        &fri_layer_commitments,
+        compress,
+        Validate::Yes,
+    )?;
+    checked.verify_low_degree_test(fri_layers)
+}
+
+/// Verifies `num_proofs` proofs serialized one after the other in `reader`.
+/// Each proof is verified with [`default_verify_from_reader`] before the next
+/// one is read so only a single proof is held in memory at a time. Returns the
+/// artifacts of each proof or the index of the first invalid proof.
+pub fn verify_batch_from_reader<S: Stark>(
+    this: &S,
+    mut reader: impl Read,
+    compress: Compress,
+    num_proofs: usize,
+    required_security_bits: u32,
+) -> Result<Vec<VerifierChannelArtifacts<S::Fq>>, BatchVerificationError> {
+    (0..num_proofs)
+        .map(|index| {
+            default_verify_from_reader(this, &mut reader, compress, required_security_bits)
+                .map_err(|source| BatchVerificationError::InvalidProof { index, source })
+        })
+        .collect()
+}
+
+fn read<T: CanonicalDeserialize>(
+    reader: impl Read,
+    compress: Compress,
+) -> Result<T, VerificationError> {
+    Ok(T::deserialize_with_mode(reader, compress, Validate::Yes)?)
+}
+
+/// Verifies several proofs of the same AIR. The transcripts of all proofs are
+/// replayed first (rejecting bad OOD evaluations and proof of work before any
+/// rows are hashed) and the denominators of every DEEP composition evaluation
+/// across all proofs are then inverted with a single batch inversion. The
+/// Merkle checks of the trace queries and FRI layers of every proof are
+/// deferred until all other checks pass and then run in a single pass, in
+/// parallel if the `parallel` feature is enabled. Returns the artifacts of
+/// each proof or the index of an invalid proof. Merkle checks run last so an
+/// invalid proof is only reported by its Merkle checks if every proof passes
+/// the other checks.
+pub fn verify_batch<S: Stark>(
+    batch: Vec<(&S, Proof<S>)>,
+    required_security_bits: u32,
+) -> Result<Vec<VerifierChannelArtifacts<S::Fq>>, BatchVerificationError> {
+    let pending = batch
+        .into_iter()
+        .enumerate()
+        .map(|(index, (this, proof))| {
+            replay_transcript(this, proof, required_security_bits)
+                .map_err(|source| BatchVerificationError::InvalidProof { index, source })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let denominators = pending
+        .iter()
+        .map(|(pending, _)| pending.deep_composition_denominators())
+        .collect::<Vec<Vec<S::Fq>>>();
+    let mut inverse_denominators = denominators.concat();
+    batch_inverse(&mut inverse_denominators);
+
+    let mut inverses = inverse_denominators.as_slice();
+    let mut merkle_checks = Vec::new();
+    let artifacts = pending
+        .into_iter()
+        .zip(denominators)
+        .enumerate()
+        .map(|(index, ((pending, openings), denominators))| {
+            let invalid_proof =
+                move |source| BatchVerificationError::InvalidProof { index, source };
+            let (proof_inverses, rest) = inverses.split_at(denominators.len());
+            inverses = rest;
+            let (artifacts, checks) = pending
+                .finish_deferred(proof_inverses, openings)
+                .map_err(invalid_proof)?;
+            merkle_checks.extend(
+                checks
+                    .into_iter()
+                    .map(|check| check.map_error(invalid_proof)),
+            );
+            Ok(artifacts)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    merkle::verify_deferred(merkle_checks)?;
+    Ok(artifacts)
+}
+
+/// When the Merkle checks of a proof's openings are run
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MerkleChecks {
+    /// Run each check as soon as its rows are hashed
+    Now,
+    /// Return the checks so they can be run later (see [`verify_batch`])
+    Defer,
+    /// Skip the checks of the trace queries (see [`default_verify_light`])
+    SkipTrace,
+}
+
+/// A proof whose transcript has been replayed and OOD evaluations checked.
+/// The trace query openings, DEEP composition and FRI queries are left to be
+/// checked by [`PendingVerification::finish`].
+struct PendingVerification<S: Stark> {
+    air: Air<S::AirConfig>,
+    air_challenges: Challenges<S::Fq>,
+    air_hints: Hints<S::Fq>,
+    base_trace_commitment: S::Digest,
+    extension_trace_commitment: Option<S::Digest>,
+    composition_trace_commitment: S::Digest,
+    trace_ood_eval_map: BTreeMap<(usize, isize), S::Fq>,
+    composition_trace_ood_evals: Vec<S::Fq>,
+    deep_coeffs: DeepCompositionCoeffs<S::Fq>,
+    fri_verifier: FriVerifier<S::Fq, S::Digest, S::MerkleTree>,
+    query_positions: Vec<usize>,
+    z: S::Fq,
+}
+
+impl<S: Stark> PendingVerification<S> {
+    /// See [`deep_composition_denominators`]
+    fn deep_composition_denominators(&self) -> Vec<S::Fq> {
+        deep_composition_denominators(
+            &self.air,
+            &self.query_positions,
+            &self.trace_ood_eval_map,
+            self.z,
+        )
+    }
+
+    /// Runs the remaining checks given the inverses of
+    /// [`Self::deep_composition_denominators`]
+    fn finish(
+        self,
+        merkle_checks: MerkleChecks,
+        inverse_denominators: &[S::Fq],
+        openings: ProofOpenings<S>,
+    ) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+        let ProofOpenings {
+            trace_queries,
+            fri_layers,
+        } = openings;
+        self.check_trace_queries(merkle_checks, inverse_denominators, trace_queries)?
+            .verify_low_degree_test(fri_layers)
+    }
+
+    /// Same as [`Self::finish`] but returns the Merkle checks of the trace
+    /// queries and FRI layers rather than running them
+    fn finish_deferred(
+        self,
+        inverse_denominators: &[S::Fq],
+        openings: ProofOpenings<S>,
+    ) -> Result<(VerifierChannelArtifacts<S::Fq>, Vec<MerkleCheck<S>>), VerificationError> {
+        let ProofOpenings {
+            trace_queries,
+            fri_layers,
+        } = openings;
+        self.check_trace_queries(MerkleChecks::Defer, inverse_denominators, trace_queries)?
+            .defer_low_degree_test(fri_layers)
+    }
+
+    /// Checks the trace openings and computes the DEEP composition evaluation
+    /// of each query. Openings are resolved one query at a time and dropped
+    /// once they're hashed for the Merkle checks and their DEEP composition
+    /// evaluation is computed.
+    fn check_trace_queries(
+        self,
+        merkle_checks: MerkleChecks,
+        inverse_denominators: &[S::Fq],
+        trace_queries: impl TraceOpenings<S>,
+    ) -> Result<CheckedTraceQueries<S>, VerificationError> {
+        use VerificationError::*;
+        let Self {
+            air,
+            air_challenges,
+            air_hints,
+            base_trace_commitment,
+            extension_trace_commitment,
+            composition_trace_commitment,
+            trace_ood_eval_map,
+            composition_trace_ood_evals,
+            deep_coeffs,
+            fri_verifier,
+            query_positions,
+            z: _,
+        } = self;
+
+        let num_queries = query_positions.len();
+        if trace_queries.num_openings() != num_queries {
+            return Err(InvalidNumQueryValues);
+        }
+        let shape = [
+            S::AirConfig::NUM_BASE_COLUMNS,
+            S::AirConfig::NUM_EXTENSION_COLUMNS,
+            air.num_composition_trace_columns(),
+        ];
+        let mut queries = zip(
+            query_points(&air, &query_positions),
+            inverse_denominators.chunks(trace_ood_eval_map.len() + 1),
+        );
+        let mut row_hashes = TraceRowHashes::<S>::default();
+        let mut deep_evaluations = Vec::with_capacity(num_queries);
+        let trace_proofs = trace_queries.resolve(|opening| {
+            let (x, inverses) = queries.next().ok_or(InvalidNumQueryValues)?;
+            if !opening.has_shape(shape) {
+                return Err(InvalidNumQueryValues);
+            }
+            if merkle_checks != MerkleChecks::SkipTrace {
+                row_hashes.push(&opening);
+            }
+            deep_evaluations.push(deep_composition_evaluation::<S::AirConfig>(
+                x,
+                &deep_coeffs,
+                &opening.base_trace_row,
+                &opening.extension_trace_row,
+                &opening.composition_trace_row,
+                &trace_ood_eval_map,
+                &composition_trace_ood_evals,
+                inverses,
+            ));
+            Ok(())
+        })?;
+        if deep_evaluations.len() != num_queries {
+            return Err(InvalidNumQueryValues);
+        }
+
+        let mut trace_merkle_checks = Vec::new();
+        if merkle_checks != MerkleChecks::SkipTrace {
+            trace_merkle_checks = trace_query_checks::<S>(
+                air.lde_domain().size(),
+                &query_positions,
+                base_trace_commitment,
+                extension_trace_commitment,
+                composition_trace_commitment,
+                row_hashes,
+                trace_proofs,
+            )?;
+        }
+        if merkle_checks == MerkleChecks::Now {
+            merkle::verify_deferred(core::mem::take(&mut trace_merkle_checks))?;
+        }
+
+        Ok(CheckedTraceQueries {
+            air_challenges,
+            air_hints,
+            fri_verifier,
+            query_positions,
+            deep_evaluations,
+            trace_merkle_checks,
+        })
+    }
+}
+
+/// Openings of a proof that are checked after the query positions are drawn
+struct ProofOpenings<S: Stark> {
+    trace_queries: Queries<S>,
+    fri_layers: <Fri as LowDegreeTest<S::Fq, S::Digest, S::MerkleTree>>::Openings,
+}
+
+/// Merkle check of a proof's openings that's run later (see [`verify_batch`])
+type MerkleCheck<S> = DeferredRowCheck<<S as Stark>::MerkleTree, VerificationError>;
+
+/// A proof whose trace openings have been checked, apart from any deferred
+/// Merkle checks. Only the low degree test of the DEEP composition
+/// evaluations is left.
+struct CheckedTraceQueries<S: Stark> {
+    air_challenges: Challenges<S::Fq>,
+    air_hints: Hints<S::Fq>,
+    fri_verifier: FriVerifier<S::Fq, S::Digest, S::MerkleTree>,
+    query_positions: Vec<usize>,
+    deep_evaluations: Vec<S::Fq>,
+    trace_merkle_checks: Vec<MerkleCheck<S>>,
+}
+
+impl<S: Stark> CheckedTraceQueries<S> {
+    /// Checks the openings of the low degree test and returns the verifier's
+    /// artifacts
+    fn verify_low_degree_test(
+        self,
+        fri_layers: <Fri as LowDegreeTest<S::Fq, S::Digest, S::MerkleTree>>::Openings,
+    ) -> Result<VerifierChannelArtifacts<S::Fq>, VerificationError> {
+        merkle::verify_deferred(self.trace_merkle_checks)?;
+        let fri_alphas = self.fri_verifier.layer_alphas.clone();
+        <Fri as LowDegreeTest<S::Fq, S::Digest, S::MerkleTree>>::verify(
+            self.fri_verifier,
+            fri_layers,
+            &self.query_positions,
+            &self.deep_evaluations,
+        )?;
+
+        Ok(VerifierChannelArtifacts {
+            air_challenges: self.air_challenges,
+            air_hints: self.air_hints,
+            fri_alphas,
+            query_positions: self.query_positions,
+        })
+    }
+
+    /// Same as [`Self::verify_low_degree_test`] but returns the Merkle checks
+    /// of the trace queries and FRI layers rather than running them
+    fn defer_low_degree_test(
+        self,
+        fri_layers: <Fri as LowDegreeTest<S::Fq, S::Digest, S::MerkleTree>>::Openings,
+    ) -> Result<(VerifierChannelArtifacts<S::Fq>, Vec<MerkleCheck<S>>), VerificationError> {
+        let mut merkle_checks = self.trace_merkle_checks;
+        let fri_alphas = self.fri_verifier.layer_alphas.clone();
+        merkle_checks.extend(
+            <Fri as LowDegreeTest<S::Fq, S::Digest, S::MerkleTree>>::verify_deferred(
+                self.fri_verifier,
+                fri_layers,
+                &self.query_positions,
+                &self.deep_evaluations,
+            )?,
+        );
+
+        let artifacts = VerifierChannelArtifacts {
+            air_challenges: self.air_challenges,
+            air_hints: self.air_hints,
+            fri_alphas,
+            query_positions: self.query_positions,
+        };
+        Ok((artifacts, merkle_checks))
+    }
+}
+
+/// Hashes of the opened rows of each trace. See
+/// [`MatrixMerkleTree::hash_tagged_row`].
+struct TraceRowHashes<S: Stark> {
+    base_trace: Vec<S::Digest>,
+    extension_trace: Vec<S::Digest>,
+    composition_trace: Vec<S::Digest>,
+}
+
+impl<S: Stark> Default for TraceRowHashes<S> {
+    fn default() -> Self {
+        Self {
+            base_trace: Vec::new(),
+            extension_trace: Vec::new(),
+            composition_trace: Vec::new(),
+        }
+    }
+}
+
+impl<S: Stark> TraceRowHashes<S> {
+    fn push(&mut self, opening: &TraceOpening<S::Fp, S::Fq>) {
+        self.base_trace
+            .push(<S::MerkleTree as MatrixMerkleTree<S::Fp>>::hash_tagged_row(
+                TreeTag::BaseTrace,
+                &opening.base_trace_row,
+            ));
+        if S::AirConfig::NUM_EXTENSION_COLUMNS != 0 {
+            self.extension_trace
+                .push(<S::MerkleTree as MatrixMerkleTree<S::Fq>>::hash_tagged_row(
+                    TreeTag::ExtensionTrace,
+                    &opening.extension_trace_row,
+                ));
+        }
+        self.composition_trace
+            .push(<S::MerkleTree as MatrixMerkleTree<S::Fq>>::hash_tagged_row(
+                TreeTag::CompositionTrace,
+                &opening.composition_trace_row,
+            ));
+    }
+}
+
+/// Replays the transcript of a proof that's in memory, checking the header,
+/// OOD evaluations and proof of work along the way. Returns the openings left
+/// to check.
+fn replay_transcript<S: Stark>(
+    this: &S,
+    proof: Proof<S>,
+    required_security_bits: u32,
+) -> Result<(PendingVerification<S>, ProofOpenings<S>), VerificationError> {
+    let Proof {
+        header,
         trace_len,
+        base_trace_commitment,
+        extension_trace_commitment,
+        composition_trace_commitment,
         fri_proof,
         pow_nonce,
-        ..
+        trace_queries,
+        execution_trace_ood_evals,
+        composition_trace_ood_evals,
     } = proof;
+    let air = verify_proof_start(this, &header, trace_len, required_security_bits)?;
+    let (fri_commitments, fri_layers) =
+        <Fri as LowDegreeTest<S::Fq, S::Digest, S::MerkleTree>>::split_proof(fri_proof);
+    let pending = CommittedTranscript::new(
+        this,
+        air,
+        base_trace_commitment,
+        extension_trace_commitment,
+        composition_trace_commitment,
+    )?
+    .replay_ood_evals(this, execution_trace_ood_evals, composition_trace_ood_evals)?
+    .replay_low_degree_test(fri_commitments, pow_nonce)?;
+    let openings = ProofOpenings {
+        trace_queries,
+        fri_layers,
+    };
+    Ok((pending, openings))
+}
 
-    let air = Air::new(trace_len, this.get_public_inputs(), options);
-    let mut public_coin = this.gen_public_coin(&air);
-
-    public_coin.reseed_with_digest(&base_trace_commitment);
-    let num_challenges = air.num_challenges();
-    let air_challenges = Challenges::new(draw_multiple(&mut public_coin, num_challenges));
-    let air_hints = air.gen_hints(&air_challenges);
-
-    let extension_trace_commitment = extension_trace_commitment.map(|commitment| {
-        public_coin.reseed_with_digest(&commitment);
-        commitment
-    });
-
-    let num_composition_coeffs = air.num_composition_constraint_coeffs();
-    let composition_coeffs = draw_multiple(&mut public_coin, num_composition_coeffs);
-    public_coin.reseed_with_digest(&composition_trace_commitment);
-
-    let z = public_coin.draw();
-    let ood_evals = [
-        execution_trace_ood_evals.clone(),
-        composition_trace_ood_evals.clone(),
-    ]
-    .concat();
-    public_coin.reseed_with_field_elements(&ood_evals);
-    // execution trace ood evaluation map
-    let trace_ood_eval_map = air
-        .trace_arguments()
-        .into_iter()
-        .zip(execution_trace_ood_evals)
-        .collect::<BTreeMap<(usize, isize), S::Fq>>();
-    let calculated_ood_constraint_evaluation = ood_constraint_evaluation::<S::AirConfig>(
-        &composition_coeffs,
-        &air_challenges,
-        &air_hints,
-        &trace_ood_eval_map,
-        &air,
-        z,
-    );
-
-    let provided_ood_constraint_evaluation = horner_evaluate(&composition_trace_ood_evals, &z);
-
-    if calculated_ood_constraint_evaluation != provided_ood_constraint_evaluation {
-        return Err(InconsistentOodConstraintEvaluations);
+/// Checks the version and security of a proof and that it was generated for
+/// the AIR. Returns the AIR.
+fn verify_proof_start<S: Stark>(
+    this: &S,
+    header: &ProofHeader,
+    trace_len: usize,
+    required_security_bits: u32,
+) -> Result<Air<S::AirConfig>, VerificationError> {
+    use VerificationError::*;
+    if header.version != PROOF_VERSION {
+        return Err(UnsupportedProofVersion {
+            version: header.version,
+        });
     }
+    let air = this
+        .air(trace_len, header.options)
+        .map_err(|source| InvalidAir { source })?;
+    verify_header::<S>(header, &air)?;
+    if security_level_bits::<S>(header, trace_len) < required_security_bits {
+        return Err(InvalidProofSecurity);
+    }
+    Ok(air)
+}
 
-    let deep_coeffs = this.gen_deep_coeffs(&mut public_coin, &air);
-    let fri_verifier = FriVerifier::<S::Fq, S::Digest, S::MerkleTree>::new(
-        &mut public_coin,
-        options.into_fri_options(),
-        fri_proof,
-        trace_len - 1,
-    )?;
+/// Transcript of a proof replayed up to the trace commitments. The sections of
+/// a proof are replayed in the order they're serialized so
+/// [`default_verify_from_reader`] can check each one as soon as it's read.
+struct CommittedTranscript<S: Stark> {
+    air: Air<S::AirConfig>,
+    public_coin: S::PublicCoin,
+    air_challenges: Challenges<S::Fq>,
+    air_hints: Hints<S::Fq>,
+    composition_coeffs: Vec<S::Fq>,
+    base_trace_commitment: S::Digest,
+    extension_trace_commitment: Option<S::Digest>,
+    composition_trace_commitment: S::Digest,
+}
 
-    if options.grinding_factor != 0 {
-        if !public_coin.verify_proof_of_work(options.grinding_factor, pow_nonce) {
-            return Err(FriProofOfWork);
+impl<S: Stark> CommittedTranscript<S> {
+    /// Reseeds the public coin with the trace commitments and draws the
+    /// challenges and composition coefficients they bind
+    fn new(
+        this: &S,
+        air: Air<S::AirConfig>,
+        base_trace_commitment: S::Digest,
+        extension_trace_commitment: Option<S::Digest>,
+        composition_trace_commitment: S::Digest,
+    ) -> Result<Self, VerificationError> {
+        verify_trace_shape::<S>(extension_trace_commitment.as_ref())?;
+        let mut public_coin = this.gen_public_coin(&air);
+        air.trace_info().bind(&mut public_coin);
+
+        public_coin.reseed_with_digest(&base_trace_commitment);
+        let num_challenges = air.num_challenges();
+        let air_challenges = Challenges::new(draw_from_sub_coin(
+            &public_coin,
+            SubCoin::TraceExtension,
+            num_challenges,
+        ));
+        let air_hints = air.gen_hints(&air_challenges);
+
+        if let Some(commitment) = &extension_trace_commitment {
+            public_coin.reseed_with_digest(commitment);
         }
-        public_coin.reseed_with_int(pow_nonce);
+
+        let num_composition_coeffs = air.num_composition_constraint_coeffs();
+        let composition_coeffs =
+            draw_from_sub_coin(&public_coin, SubCoin::Composition, num_composition_coeffs);
+        public_coin.reseed_with_digest(&composition_trace_commitment);
+
+        Ok(Self {
+            air,
+            public_coin,
+            air_challenges,
+            air_hints,
+            composition_coeffs,
+            base_trace_commitment,
+            extension_trace_commitment,
+            composition_trace_commitment,
+        })
     }
 
-    let lde_domain_size = air.trace_len() * air.lde_blowup_factor();
-    let query_positions =
-        Vec::from_iter(public_coin.draw_queries(options.num_queries.into(), lde_domain_size));
+    /// Checks the OOD evaluations are consistent with the AIR's constraints
+    /// and draws the DEEP composition coefficients
+    fn replay_ood_evals(
+        self,
+        this: &S,
+        execution_trace_ood_evals: Vec<S::Fq>,
+        composition_trace_ood_evals: Vec<S::Fq>,
+    ) -> Result<OodTranscript<S>, VerificationError> {
+        use VerificationError::*;
+        let Self {
+            air,
+            mut public_coin,
+            air_challenges,
+            air_hints,
+            composition_coeffs,
+            base_trace_commitment,
+            extension_trace_commitment,
+            composition_trace_commitment,
+        } = self;
 
-    let base_trace_rows = trace_queries
-        .base_trace_values
-        .chunks(S::AirConfig::NUM_BASE_COLUMNS)
-        .collect::<Vec<_>>();
-    let extension_trace_rows = if S::AirConfig::NUM_EXTENSION_COLUMNS == 0 {
-        Vec::new()
-    } else {
-        trace_queries
-            .extension_trace_values
-            .chunks(S::AirConfig::NUM_EXTENSION_COLUMNS)
-            .collect::<Vec<_>>()
-    };
+        let num_trace_arguments = air.trace_arguments().len();
+        if execution_trace_ood_evals.len() != num_trace_arguments {
+            return Err(InvalidNumExecutionTraceOodEvaluations {
+                expected: num_trace_arguments,
+                actual: execution_trace_ood_evals.len(),
+            });
+        }
 
-    let composition_trace_rows = trace_queries
-        .composition_trace_values
-        .chunks(air.ce_blowup_factor())
-        .collect::<Vec<&[S::Fq]>>();
-
-    // base trace positions
-    S::MerkleTree::verify_rows(
-        &base_trace_commitment,
-        &query_positions,
-        &base_trace_rows,
-        trace_queries.base_trace_proof,
-    )
-    .map_err(|_| BaseTraceQueryDoesNotMatchCommitment)?;
+        let num_composition_columns = air.num_composition_trace_columns();
+        if composition_trace_ood_evals.len() != num_composition_columns {
+            return Err(InvalidNumCompositionTraceOodEvaluations {
+                expected: num_composition_columns,
+                actual: composition_trace_ood_evals.len(),
+            });
+        }
+
+        let z = public_coin.fork(SubCoin::Deep).draw();
+        let ood_evals = [
+            execution_trace_ood_evals.clone(),
+            composition_trace_ood_evals.clone(),
+        ]
+        .concat();
+        public_coin.reseed_with_field_elements(&ood_evals);
+        // execution trace ood evaluation map
+        let trace_ood_eval_map = air
+            .trace_arguments()
+            .into_iter()
+            .zip(execution_trace_ood_evals)
+            .collect::<BTreeMap<(usize, isize), S::Fq>>();
+        let calculated_ood_constraint_evaluation = ood_constraint_evaluation::<S::AirConfig>(
+            &composition_coeffs,
+            &air_challenges,
+            &air_hints,
+            &trace_ood_eval_map,
+            &air,
+            z,
+        );
+
+        let provided_ood_constraint_evaluation =
+            composition_ood_evaluation(&composition_trace_ood_evals, z);
+
+        if calculated_ood_constraint_evaluation != provided_ood_constraint_evaluation {
+            return Err(InconsistentOodConstraintEvaluations);
+        }
+
+        let deep_coeffs = this.gen_deep_coeffs(&mut public_coin.fork(SubCoin::Deep), &air);
+        Ok(OodTranscript {
+            air,
+            public_coin,
+            air_challenges,
+            air_hints,
+            base_trace_commitment,
+            extension_trace_commitment,
+            composition_trace_commitment,
+            trace_ood_eval_map,
+            composition_trace_ood_evals,
+            deep_coeffs,
+            z,
+        })
+    }
+}
+
+/// Transcript of a proof replayed up to the DEEP composition coefficients
+struct OodTranscript<S: Stark> {
+    air: Air<S::AirConfig>,
+    public_coin: S::PublicCoin,
+    air_challenges: Challenges<S::Fq>,
+    air_hints: Hints<S::Fq>,
+    base_trace_commitment: S::Digest,
+    extension_trace_commitment: Option<S::Digest>,
+    composition_trace_commitment: S::Digest,
+    trace_ood_eval_map: BTreeMap<(usize, isize), S::Fq>,
+    composition_trace_ood_evals: Vec<S::Fq>,
+    deep_coeffs: DeepCompositionCoeffs<S::Fq>,
+    z: S::Fq,
+}
+
+impl<S: Stark> OodTranscript<S> {
+    /// Reseeds the public coin with the commitments of the low degree test,
+    /// checks the proof of work and draws the query positions
+    fn replay_low_degree_test(
+        self,
+        fri_commitments: <Fri as LowDegreeTest<S::Fq, S::Digest, S::MerkleTree>>::Commitments,
+        pow_nonce: u64,
+    ) -> Result<PendingVerification<S>, VerificationError> {
+        use VerificationError::*;
+        let Self {
+            air,
+            mut public_coin,
+            air_challenges,
+            air_hints,
+            base_trace_commitment,
+            extension_trace_commitment,
+            composition_trace_commitment,
+            trace_ood_eval_map,
+            composition_trace_ood_evals,
+            deep_coeffs,
+            z,
+        } = self;
+        let options = air.options();
+
+        let fri_verifier =
+            <Fri as LowDegreeTest<S::Fq, S::Digest, S::MerkleTree>>::read_commitments(
+                options,
+                &mut public_coin,
+                fri_commitments,
+                air.trace_len() - 1,
+            )?;
+
+        if options.grinding_factor == 0 {
+            // the nonce is bound to the query positions so it must be fixed when
+            // there is no grinding otherwise the prover could choose positions
+            if pow_nonce != 0 {
+                return Err(FriProofOfWork);
+            }
+        } else if !public_coin.verify_proof_of_work(options.grinding_factor, pow_nonce) {
+            return Err(FriProofOfWork);
+        }
+
+        let lde_domain_size = air.trace_len() * air.lde_blowup_factor();
+        let query_positions = derive_query_positions(
+            &mut public_coin,
+            pow_nonce,
+            options.num_queries.into(),
+            lde_domain_size,
+        );
+
+        Ok(PendingVerification {
+            air,
+            air_challenges,
+            air_hints,
+            base_trace_commitment,
+            extension_trace_commitment,
+            composition_trace_commitment,
+            trace_ood_eval_map,
+            composition_trace_ood_evals,
+            deep_coeffs,
+            fri_verifier,
+            query_positions,
+            z,
+        })
+    }
+}
 
+/// Returns the checks that the hashes of the queried trace rows resolve to
+/// their commitments. Each trace LDE has `lde_size` rows.
+fn trace_query_checks<S: Stark>(
+    lde_size: usize,
+    query_positions: &[usize],
+    base_trace_commitment: S::Digest,
+    extension_trace_commitment: Option<S::Digest>,
+    composition_trace_commitment: S::Digest,
+    row_hashes: TraceRowHashes<S>,
+    proofs: TraceProofs<S>,
+) -> Result<Vec<MerkleCheck<S>>, VerificationError> {
+    use VerificationError::*;
+    let mut checks = vec![MerkleCheck::<S>::new::<S::Fp>(
+        base_trace_commitment,
+        lde_size,
+        query_positions.to_vec(),
+        row_hashes.base_trace,
+        proofs.base_trace_proof,
+        BaseTraceQueryDoesNotMatchCommitment,
+    )];
     if let Some(extension_trace_commitment) = extension_trace_commitment {
-        S::MerkleTree::verify_rows(
-            &extension_trace_commitment,
-            &query_positions,
-            &extension_trace_rows,
-            trace_queries.extension_trace_proof.unwrap(),
-        )
-        .map_err(|_| ExtensionTraceQueryDoesNotMatchCommitment)?;
+        checks.push(MerkleCheck::<S>::new::<S::Fq>(
+            extension_trace_commitment,
+            lde_size,
+            query_positions.to_vec(),
+            row_hashes.extension_trace,
+            proofs
+                .extension_trace_proof
+                .ok_or(ExtensionTraceQueryDoesNotMatchCommitment)?,
+            ExtensionTraceQueryDoesNotMatchCommitment,
+        ));
+    }
+    checks.push(MerkleCheck::<S>::new::<S::Fq>(
+        composition_trace_commitment,
+        lde_size,
+        query_positions.to_vec(),
+        row_hashes.composition_trace,
+        proofs.composition_trace_proof,
+        CompositionTraceQueryDoesNotMatchCommitment,
+    ));
+    Ok(checks)
+}
+
+/// Checks the proof commits to an extension trace if and only if the AIR has
+/// extension columns. Otherwise the extension rows of the openings wouldn't be
+/// checked against any commitment.
+fn verify_trace_shape<S: Stark>(
+    extension_trace_commitment: Option<&S::Digest>,
+) -> Result<(), VerificationError> {
+    let num_extension_columns = S::AirConfig::NUM_EXTENSION_COLUMNS;
+    if extension_trace_commitment.is_some() != (num_extension_columns != 0) {
+        return Err(VerificationError::ExtensionTraceCommitmentMismatch {
+            num_extension_columns,
+        });
     }
+    Ok(())
+}
 
-    // composition trace positions
-    S::MerkleTree::verify_rows(
-        &composition_trace_commitment,
-        &query_positions,
-        &composition_trace_rows,
-        trace_queries.composition_trace_proof,
-    )
-    .map_err(|_| CompositionTraceQueryDoesNotMatchCommitment)?;
-
-    let deep_evaluations = deep_composition_evaluations(
-        &air,
-        &query_positions,
-        &deep_coeffs,
-        &base_trace_rows,
-        &extension_trace_rows,
-        &composition_trace_rows,
-        &trace_ood_eval_map,
-        &composition_trace_ood_evals,
-        z,
-    );
-
-    let fri_alphas = fri_verifier.layer_alphas.clone();
-    fri_verifier.verify(&query_positions, &deep_evaluations)?;
-
-    Ok(VerifierChannelArtifacts {
-        air_challenges,
-        air_hints,
-        fri_alphas,
-        query_positions,
-    })
+/// Checks the proof was generated for the same AIR, fields and hash functions
+fn verify_header<S: Stark>(
+    header: &ProofHeader,
+    air: &Air<S::AirConfig>,
+) -> Result<(), VerificationError> {
+    use VerificationError::*;
+    let expected = ProofHeader::new::<S>(air);
+    if !expected.air_version.is_compatible_with(&header.air_version) {
+        return Err(AirVersionMismatch {
+            expected: expected.air_version,
+            actual: header.air_version,
+        });
+    }
+    if header.air_id != expected.air_id {
+        return Err(AirMismatch);
+    }
+    for (expected, actual) in [
+        (expected.base_field, &header.base_field),
+        (expected.extension_field, &header.extension_field),
+    ] {
+        if &expected != actual {
+            return Err(FieldMismatch {
+                expected,
+                actual: actual.clone(),
+            });
+        }
+    }
+    for (expected, actual) in [
+        (expected.merkle_tree_hash, &header.merkle_tree_hash),
+        (expected.public_coin_hash, &header.public_coin_hash),
+    ] {
+        if &expected != actual {
+            return Err(HashFnMismatch {
+                expected,
+                actual: actual.clone(),
+            });
+        }
+    }
+    if usize::from(header.options.digest_len) != S::Digest::LEN {
+        return Err(DigestLenMismatch {
+            expected: S::Digest::LEN,
+            actual: header.options.digest_len,
+        });
+    }
+    if header.byte_order != expected.byte_order {
+        return Err(ByteOrderMismatch {
+            expected: expected.byte_order,
+            actual: header.byte_order,
+        });
+    }
+    if header.transcript_byte_order != expected.transcript_byte_order {
+        return Err(TranscriptByteOrderMismatch {
+            expected: expected.transcript_byte_order,
+            actual: header.transcript_byte_order,
+        });
+    }
+    Ok(())
+}
+
+/// Work done by the verifier and the size of the proof it checks. See
+/// [`VerifierCost::estimate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifierCost {
+    /// Hashes of the public coin i.e. reseeds and hashes drawn for randomness.
+    /// Excludes hashing the public inputs into the initial seed.
+    pub num_transcript_hashes: usize,
+    /// Hashes of queried rows and Merkle authentication paths
+    pub num_merkle_hashes: usize,
+    /// Extension field multiplications
+    pub num_field_muls: usize,
+    /// Extension field inversions
+    pub num_field_inversions: usize,
+    /// Size of the compressed proof
+    pub proof_bytes: usize,
+}
+
+impl VerifierCost {
+    /// Estimates the cost of verifying a proof for `air` generated with
+    /// `options` without generating the proof. Useful for deriving the gas or
+    /// step count of an on-chain verifier from the cost of its primitives.
+    ///
+    /// Transcript hashes and field operations are exact for a given number of
+    /// distinct query positions. Merkle hashes and proof bytes are upper
+    /// bounds since the authentication paths of nearby queries share nodes.
+    /// Duplicate query positions lower every count.
+    #[allow(clippy::too_many_lines)]
+    pub fn estimate<S: Stark>(air: &Air<S::AirConfig>, options: ProofOptions) -> Self {
+        let fq_bytes = encoded_size::<S::Fq>();
+        let fp_bytes = encoded_size::<S::Fp>();
+        let digest_bytes = S::Digest::default().compressed_size();
+        let num_queries = usize::from(options.num_queries);
+        let lde_size = air.trace_len() * usize::from(options.lde_blowup_factor);
+        let num_base_cols = S::AirConfig::NUM_BASE_COLUMNS;
+        let num_extension_cols = S::AirConfig::NUM_EXTENSION_COLUMNS;
+        let num_trace_trees = if num_extension_cols == 0 { 2 } else { 3 };
+        let num_trace_args = air.trace_arguments().len();
+        let num_composition_cols = air.num_composition_trace_columns();
+        let num_ood_evals = num_trace_args + num_composition_cols;
+        let fri_options = options.into_fri_options();
+        let folding_factor = usize::from(options.fri_folding_factor);
+        let num_fri_layers = fri_options.num_layers(lde_size);
+        let num_remainder_coeffs =
+            fri_options.remainder_size(lde_size) / usize::from(options.lde_blowup_factor);
+        // each hash of the public coin produces a digest worth of randomness
+        let draw = |num_bytes: usize| num_bytes.div_ceil(S::Digest::LEN);
+        // challenges are drawn from sub-coins which take a hash to fork
+        let sub_coin_draw = |num_bytes: usize| 1 + draw(num_bytes);
+
+        // in transcript order: trace info, commitments and challenges, OOD evaluations,
+        // DEEP coefficients, FRI layers, the remainder and the query positions
+        let num_transcript_hashes = air.trace_info().num_reseeds()
+            + 1
+            + sub_coin_draw(air.num_challenges() * fq_bytes)
+            + usize::from(num_extension_cols != 0)
+            + sub_coin_draw(air.num_composition_constraint_coeffs() * fq_bytes)
+            + 1
+            + sub_coin_draw(fq_bytes)
+            + 2 * num_ood_evals
+            + sub_coin_draw((num_ood_evals + 2) * fq_bytes)
+            + num_fri_layers * (1 + sub_coin_draw(fq_bytes))
+            + 2 * num_remainder_coeffs
+            + usize::from(options.grinding_factor != 0)
+            + 2
+            + draw(num_queries * core::mem::size_of::<u64>());
+
+        // each query hashes its row and the path to the root
+        let merkle_path_hashes =
+            |num_leaves: usize| num_queries * (1 + num_leaves.ilog2() as usize);
+        let fri_layer_sizes = successors(Some(lde_size / folding_factor), |n| {
+            Some(n / folding_factor)
+        })
+        .take(num_fri_layers)
+        .collect::<Vec<usize>>();
+        let num_merkle_hashes = num_trace_trees * merkle_path_hashes(lde_size)
+            + fri_layer_sizes
+                .iter()
+                .map(|&n| merkle_path_hashes(n))
+                .sum::<usize>();
+
+        let mut num_field_muls = 0;
+        let mut num_field_inversions = 0;
+        air.composition_constraint()
+            .traverse(&mut |node| match node {
+                Expr::Mul(_, _) => num_field_muls += 1,
+                Expr::Div(_, _) => {
+                    num_field_muls += 1;
+                    num_field_inversions += 1;
+                }
+                Expr::Pow(_, e) if *e > 1 => {
+                    num_field_muls += (e.ilog2() + e.count_ones() - 1) as usize
+                }
+                _ => {}
+            });
+        // DEEP composition: two multiplications per quotient, the degree
+        // adjustment and batch inverting the denominators
+        let num_denominators = num_queries * (num_trace_args + 1);
+        num_field_muls += num_queries * (2 * num_ood_evals + 2) + 3 * num_denominators;
+        num_field_inversions += 1;
+        // FRI: an inverse FFT, scaling and evaluating each folded coset
+        let fold_muls = folding_factor / 2 * folding_factor.ilog2() as usize + 2 * folding_factor;
+        num_field_muls += num_queries * (num_fri_layers * fold_muls + num_remainder_coeffs);
+
+        // serialized vectors are prefixed by their length
+        let vec_bytes =
+            |num_elements: usize, element_bytes: usize| 8 + num_elements * element_bytes;
+        // nodes, queried leaves, sibling leaves and the height of the tree
+        let merkle_proof_bytes = |num_leaves: usize| {
+            let depth = num_leaves.ilog2() as usize;
+            vec_bytes(num_queries * depth.saturating_sub(1), digest_bytes)
+                + 2 * vec_bytes(num_queries, digest_bytes)
+                + 4
+        };
+        let header_bytes = ProofHeader::new::<S>(air).compressed_size();
+        let commitment_bytes = (2 + usize::from(num_extension_cols != 0)) * digest_bytes + 1;
+        // the openings are prefixed by their count and the composition row length
+        let opening_bytes =
+            num_base_cols * fp_bytes + (num_extension_cols + num_composition_cols) * fq_bytes;
+        let trace_query_bytes =
+            16 + num_queries * opening_bytes + num_trace_trees * merkle_proof_bytes(lde_size) + 1;
+        let fri_bytes = fri_layer_sizes
+            .iter()
+            .map(|&n| {
+                // compact proofs omit at least one queried value per row
+                let row_len = folding_factor - usize::from(options.compact);
+                vec_bytes(num_queries * row_len, fq_bytes) + merkle_proof_bytes(n) + digest_bytes
+            })
+            .sum::<usize>()
+            + 8
+            + vec_bytes(num_remainder_coeffs, fq_bytes);
+        let ood_bytes =
+            vec_bytes(num_trace_args, fq_bytes) + vec_bytes(num_composition_cols, fq_bytes);
+        // the trace length and proof of work nonce are eight bytes each
+        let proof_bytes =
+            header_bytes + 8 + commitment_bytes + fri_bytes + 8 + trace_query_bytes + ood_bytes;
+
+        Self {
+            num_transcript_hashes,
+            num_merkle_hashes,
+            num_field_muls,
+            num_field_inversions,
+            proof_bytes,
+        }
+    }
+
+    /// Returns the total number of hash invocations
+    pub const fn num_hashes(&self) -> usize {
+        self.num_transcript_hashes + self.num_merkle_hashes
+    }
 }
 
 /// Errors that are returned during verification of a STARK proof
 #[derive(Debug, Snafu)]
 pub enum VerificationError {
+    #[snafu(display("unsupported proof version {version}"))]
+    UnsupportedProofVersion { version: u32 },
+    #[snafu(display("proof was generated for a different AIR"))]
+    AirMismatch,
+    #[snafu(display("expected AIR version {expected} but the proof uses version {actual}"))]
+    AirVersionMismatch {
+        expected: AirVersion,
+        actual: AirVersion,
+    },
+    #[snafu(display("expected proof options {expected:?} but the proof uses {actual:?}"))]
+    OptionsMismatch {
+        expected: ProofOptions,
+        actual: ProofOptions,
+    },
+    #[snafu(display("expected field {expected} but the proof uses {actual}"))]
+    FieldMismatch { expected: String, actual: String },
+    #[snafu(display("expected hash function {expected} but the proof uses {actual}"))]
+    HashFnMismatch { expected: String, actual: String },
+    #[snafu(display("expected {expected} byte digests but the proof uses {actual} bytes"))]
+    DigestLenMismatch { expected: usize, actual: u8 },
+    #[snafu(display("expected {expected} Merkle leaves but the proof uses {actual} leaves"))]
+    ByteOrderMismatch {
+        expected: ByteOrder,
+        actual: ByteOrder,
+    },
+    #[snafu(display("expected a {expected} transcript but the proof uses a {actual} transcript"))]
+    TranscriptByteOrderMismatch {
+        expected: ByteOrder,
+        actual: ByteOrder,
+    },
+    #[snafu(display("invalid AIR: {source}"))]
+    InvalidAir { source: AirConfigError },
+    #[snafu(display(
+        "extension trace commitment doesn't match the AIR's {num_extension_columns} extension \
+         columns"
+    ))]
+    ExtensionTraceCommitmentMismatch { num_extension_columns: usize },
     #[snafu(display("proof params do not satisfy security requirements"))]
     InvalidProofSecurity,
+    #[snafu(display("expected {expected} execution trace OOD evaluations but got {actual}"))]
+    InvalidNumExecutionTraceOodEvaluations { expected: usize, actual: usize },
+    #[snafu(display("expected {expected} composition trace OOD evaluations but got {actual}"))]
+    InvalidNumCompositionTraceOodEvaluations { expected: usize, actual: usize },
     #[snafu(display("constraint evaluations at the out-of-domain point are inconsistent"))]
     InconsistentOodConstraintEvaluations,
     #[snafu(context(false))]
     #[snafu(display("fri verification failed: {source}"))]
     FriVerification { source: fri::VerificationError },
+    #[snafu(display("number of queried values doesn't match the number of queries"))]
+    InvalidNumQueryValues,
     #[snafu(display("query does not resolve to the base trace commitment"))]
     BaseTraceQueryDoesNotMatchCommitment,
     #[snafu(display("query does not resolve to the extension trace commitment"))]
@@ -200,6 +1145,32 @@ pub enum VerificationError {
     CompositionTraceQueryDoesNotMatchCommitment,
     #[snafu(display("insufficient proof of work on fri commitments"))]
     FriProofOfWork,
+    #[snafu(display("malformed proof: {error}"))]
+    MalformedProof { error: SerializationError },
+}
+
+impl From<SerializationError> for VerificationError {
+    fn from(error: SerializationError) -> Self {
+        Self::MalformedProof { error }
+    }
+}
+
+/// Error returned by [`verify_batch`] for the first invalid proof in a batch
+#[derive(Debug, Snafu)]
+pub enum BatchVerificationError {
+    #[snafu(display("proof {index} in the batch is invalid: {source}"))]
+    InvalidProof {
+        index: usize,
+        source: VerificationError,
+    },
+}
+
+/// Reconstructs the evaluation of the composition polynomial `H(z)` from the
+/// OOD evaluations of its columns `H_i(z^k)` where `k` is the number of
+/// columns: `H(z) = H_0(z^k) + z * H_1(z^k) + ... + z^(k-1) * H_(k-1)(z^k)`.
+/// See [`Air::num_composition_trace_columns`] for how `H` is split.
+pub fn composition_ood_evaluation<F: Field>(composition_trace_ood_evals: &[F], z: F) -> F {
+    horner_evaluate(composition_trace_ood_evals, &z)
 }
 
 pub fn ood_constraint_evaluation<A: AirConfig>(
@@ -229,6 +1200,13 @@ pub fn ood_constraint_evaluation<A: AirConfig>(
                 FieldVariant::Fq(horner_evaluate(&coeffs, &point))
             }
             &Item(Trace(i, j)) => FieldVariant::Fq(trace_ood_eval_map[&(i, j)]),
+            &Item(PublicPolynomial(i)) => {
+                let coeffs = air.public_polynomials()[i]
+                    .iter()
+                    .map(|&coeff| A::Fq::from(coeff))
+                    .collect::<Vec<_>>();
+                FieldVariant::Fq(horner_evaluate(&coeffs, &x))
+            }
             &CompositionCoeff(i) => FieldVariant::Fq(composition_coefficients[i]),
         })
         .as_fq()
@@ -245,53 +1223,138 @@ pub fn deep_composition_evaluations<A: AirConfig>(
     execution_trace_ood_evals_map: &BTreeMap<(usize, isize), A::Fq>,
     composition_trace_ood_evals: &[A::Fq],
     z: A::Fq,
+) -> Vec<A::Fq> {
+    let mut inverse_denominators =
+        deep_composition_denominators(air, query_positions, execution_trace_ood_evals_map, z);
+    batch_inverse(&mut inverse_denominators);
+    deep_composition_evaluations_with_inverses(
+        air,
+        query_positions,
+        composition_coeffs,
+        base_trace_rows,
+        extension_trace_rows,
+        composition_trace_rows,
+        execution_trace_ood_evals_map,
+        composition_trace_ood_evals,
+        &inverse_denominators,
+    )
+}
+
+/// Returns the denominators of the DEEP composition quotients. For each query
+/// position `x` there is a denominator `x - z * g^offset` for each execution
+/// trace OOD evaluation followed by `x - z^k` for the composition trace.
+pub fn deep_composition_denominators<A: AirConfig>(
+    air: &Air<A>,
+    query_positions: &[usize],
+    execution_trace_ood_evals_map: &BTreeMap<(usize, isize), A::Fq>,
+    z: A::Fq,
 ) -> Vec<A::Fq> {
     let trace_domain = air.trace_domain();
     let g = trace_domain.group_gen();
     let g_inv = trace_domain.group_gen_inv();
-    let z_n = z.pow([air.ce_blowup_factor() as u64]);
-    let lde_domain = air.lde_domain();
-    let lde_domain_size = lde_domain.size();
-    let xs = query_positions
-        .iter()
-        .map(|pos| lde_domain.element(bit_reverse_index(lde_domain_size, *pos)))
-        .collect::<Vec<A::Fp>>();
+    let z_n = z.pow([air.num_composition_trace_columns() as u64]);
+    let ood_points = execution_trace_ood_evals_map
+        .keys()
+        .map(|(_, offset)| {
+            let shift = if *offset >= 0 { g } else { g_inv }.pow([offset.unsigned_abs() as u64]);
+            z * shift
+        })
+        .chain([z_n])
+        .collect::<Vec<A::Fq>>();
+    query_points(air, query_positions)
+        .into_iter()
+        .flat_map(|x| ood_points.iter().map(move |&point| A::Fq::from(x) - point))
+        .collect()
+}
 
-    let mut evals = vec![A::Fq::zero(); query_positions.len()];
+/// Evaluates the DEEP composition polynomial at each query position given the
+/// inverses of [`deep_composition_denominators`]
+#[allow(clippy::too_many_arguments)]
+pub fn deep_composition_evaluations_with_inverses<A: AirConfig>(
+    air: &Air<A>,
+    query_positions: &[usize],
+    composition_coeffs: &DeepCompositionCoeffs<A::Fq>,
+    base_trace_rows: &[&[A::Fp]],
+    extension_trace_rows: &[&[A::Fq]],
+    composition_trace_rows: &[&[A::Fq]],
+    execution_trace_ood_evals_map: &BTreeMap<(usize, isize), A::Fq>,
+    composition_trace_ood_evals: &[A::Fq],
+    inverse_denominators: &[A::Fq],
+) -> Vec<A::Fq> {
+    let num_execution_trace_denominators = execution_trace_ood_evals_map.len();
+    query_points(air, query_positions)
+        .into_iter()
+        .zip(inverse_denominators.chunks(num_execution_trace_denominators + 1))
+        .enumerate()
+        .map(|(i, (x, inverses))| {
+            let extension_trace_row = extension_trace_rows.get(i).copied().unwrap_or_default();
+            deep_composition_evaluation::<A>(
+                x,
+                composition_coeffs,
+                base_trace_rows[i],
+                extension_trace_row,
+                composition_trace_rows[i],
+                execution_trace_ood_evals_map,
+                composition_trace_ood_evals,
+                inverses,
+            )
+        })
+        .collect()
+}
 
+/// Evaluates the DEEP composition polynomial at the point `x` of a single
+/// query given its rows and the inverses of its
+/// [`deep_composition_denominators`]
+#[allow(clippy::too_many_arguments)]
+pub fn deep_composition_evaluation<A: AirConfig>(
+    x: A::Fp,
+    composition_coeffs: &DeepCompositionCoeffs<A::Fq>,
+    base_trace_row: &[A::Fp],
+    extension_trace_row: &[A::Fq],
+    composition_trace_row: &[A::Fq],
+    execution_trace_ood_evals_map: &BTreeMap<(usize, isize), A::Fq>,
+    composition_trace_ood_evals: &[A::Fq],
+    inverses: &[A::Fq],
+) -> A::Fq {
     let num_columns = A::NUM_BASE_COLUMNS + A::NUM_EXTENSION_COLUMNS;
     let base_column_range = 0..A::NUM_BASE_COLUMNS;
     let extension_column_range = A::NUM_BASE_COLUMNS..num_columns;
+    let num_execution_trace_denominators = execution_trace_ood_evals_map.len();
+    let (degree_alpha, degree_beta) = composition_coeffs.degree;
+    let mut eval = A::Fq::zero();
 
-    for (i, (&x, eval)) in xs.iter().zip(&mut evals).enumerate() {
-        // execution trace
-        for (j, ((column, offset), ood_eval)) in execution_trace_ood_evals_map.iter().enumerate() {
-            let trace_value = if base_column_range.contains(column) {
-                A::Fq::from(base_trace_rows[i][*column])
-            } else if extension_column_range.contains(column) {
-                extension_trace_rows[i][column - A::NUM_BASE_COLUMNS]
-            } else {
-                panic!("column {column} does not exist");
-            };
-
-            let alpha = composition_coeffs.execution_trace[j];
-            let shift = if *offset >= 0 { g } else { g_inv }.pow([offset.unsigned_abs() as u64]);
-            *eval += alpha * (trace_value - ood_eval) / (A::Fq::from(x) - z * shift);
-        }
+    // execution trace
+    for (j, ((column, _), ood_eval)) in execution_trace_ood_evals_map.iter().enumerate() {
+        let trace_value = if base_column_range.contains(column) {
+            A::Fq::from(base_trace_row[*column])
+        } else if extension_column_range.contains(column) {
+            extension_trace_row[column - A::NUM_BASE_COLUMNS]
+        } else {
+            panic!("column {column} does not exist");
+        };
 
-        // composition trace
-        for (j, value) in composition_trace_rows[i].iter().enumerate() {
-            let alpha = composition_coeffs.composition_trace[j];
-            let ood_eval = composition_trace_ood_evals[j];
-            *eval += alpha * (*value - ood_eval) / (A::Fq::from(x) - z_n);
-        }
+        let alpha = composition_coeffs.execution_trace[j];
+        eval += alpha * (trace_value - ood_eval) * inverses[j];
     }
 
-    // adjust degree
-    let (alpha, beta) = composition_coeffs.degree;
-    for (x, eval) in xs.iter().zip(&mut evals) {
-        *eval *= alpha + beta * x;
+    // composition trace
+    let composition_inverse = inverses[num_execution_trace_denominators];
+    for (j, value) in composition_trace_row.iter().enumerate() {
+        let alpha = composition_coeffs.composition_trace[j];
+        let ood_eval = composition_trace_ood_evals[j];
+        eval += alpha * (*value - ood_eval) * composition_inverse;
     }
 
-    evals
+    // adjust degree
+    eval * (degree_alpha + degree_beta * x)
+}
+
+/// Returns the points of the LDE domain at each query position
+fn query_points<A: AirConfig>(air: &Air<A>, query_positions: &[usize]) -> Vec<A::Fp> {
+    let lde_domain = air.lde_domain();
+    let lde_domain_size = lde_domain.size();
+    query_positions
+        .iter()
+        .map(|pos| lde_domain.element(bit_reverse_index(lde_domain_size, *pos)))
+        .collect()
 }
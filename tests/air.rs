@@ -0,0 +1,118 @@
+#![feature(allocator_api)]
+mod common;
+
+use ark_ff::Field;
+use ark_ff::One;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use common::gen_trace;
+use common::FibAirConfig;
+use common::FibClaim;
+use common::FibTrace;
+use common::OPTIONS;
+use common::TRACE_LEN;
+use ministark::air::AirConfig;
+use ministark::air::ColumnType;
+use ministark::air::ColumnTypeChecks;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::hints::Hints;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::utils::GpuAllocator;
+use ministark::Air;
+use ministark::Matrix;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+/// Fibonacci with a third column holding the fourth power of the first
+struct FibPow4AirConfig;
+
+impl AirConfig for FibPow4AirConfig {
+    const NUM_BASE_COLUMNS: usize = 3;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = Fp;
+
+    fn gen_hints(trace_len: usize, claim: &Fp, challenges: &Challenges<Fp>) -> Hints<Fp> {
+        FibAirConfig::gen_hints(trace_len, claim, challenges)
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        let mut constraints = FibAirConfig::constraints(trace_len);
+        constraints.push(Constraint::when_transition(
+            trace_len,
+            2.curr() - 0.curr().pow(4),
+        ));
+        constraints
+    }
+}
+
+#[test]
+fn column_types_check_value_ranges() {
+    assert!(ColumnType::Bit.contains(&Fp::one()));
+    assert!(!ColumnType::Bit.contains(&Fp::from(2u8)));
+    assert!(ColumnType::Bit.contains(&Fp::from(0u8)));
+    assert!(ColumnType::Byte.contains(&Fp::from(255u8)));
+    assert!(!ColumnType::Byte.contains(&Fp::from(256u16)));
+    assert!(ColumnType::U32.contains(&Fp::from(u32::MAX)));
+    assert!(!ColumnType::U32.contains(&-Fp::one()));
+    assert!(ColumnType::Felt.contains(&-Fp::one()));
+}
+
+#[test]
+fn byte_and_u32_columns_are_range_checked_by_auxiliary_columns() {
+    use AlgebraicItem::*;
+    let n = 8;
+    let column_types = vec![ColumnType::Byte, ColumnType::U32];
+    let checks = ColumnTypeChecks::new::<Fp>(column_types.clone(), 2 + 8 + 32).unwrap();
+    let rows = (0..n)
+        .map(|i| vec![Fp::from(255 - i), Fp::from(u64::from(u32::MAX) - i)])
+        .collect();
+    let mut trace = Matrix::from_rows(rows);
+    checks.fill_auxiliary_columns(&mut trace);
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n as usize).unwrap();
+    let is_satisfied = |trace: &Matrix<Fp>| {
+        checks
+            .constraints::<Fp, Fp>(n as usize)
+            .iter()
+            .all(|constraint| {
+                trace_domain.elements().enumerate().all(|(row, x)| {
+                    let result = constraint.check(&mut |leaf| match leaf {
+                        X => FieldVariant::Fp(x),
+                        &Constant(v) => v,
+                        &Trace(i, 0) => FieldVariant::Fp(trace.0[i][row]),
+                        _ => unreachable!(),
+                    });
+                    result.is_some()
+                })
+            })
+    };
+
+    assert_eq!(42, trace.num_cols());
+    assert!(is_satisfied(&trace));
+    // a byte column can't hold a wider value even if its bits are changed
+    trace.0[0][0] = Fp::from(256u16);
+    trace.0[2 + 7][0] = Fp::from(2u8);
+    assert!(!is_satisfied(&trace));
+    assert!(ColumnTypeChecks::new::<Fp>(column_types, 2 + 8).is_err());
+}
+
+#[test]
+fn composition_columns_follow_the_constraint_degree() {
+    let (claim, mut trace) = gen_trace();
+    let pow4 = trace.0[0].iter().map(|v| v.pow([4])).collect::<Vec<Fp>>();
+    trace.0.push(pow4.to_vec_in(GpuAllocator));
+    let claim = FibClaim::<FibTrace, FibPow4AirConfig>::new(claim.0);
+    let air = Air::<FibPow4AirConfig>::new(TRACE_LEN, claim.0, OPTIONS);
+
+    // the composition polynomial has degree `3 * (TRACE_LEN - 1)` so it's split
+    // into 3 columns even though it's evaluated over a 4x blowup
+    assert_eq!(3 * (TRACE_LEN - 1), air.composition_degree());
+    assert_eq!(3, air.num_composition_trace_columns());
+    assert_eq!(4, air.ce_blowup_factor());
+    let proof = pollster::block_on(claim.prove(OPTIONS, FibTrace(trace))).unwrap();
+    assert_eq!(3, proof.composition_trace_ood_evals.len());
+    assert!(claim.verify(proof, 0).is_ok());
+}
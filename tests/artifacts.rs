@@ -0,0 +1,26 @@
+#![feature(allocator_api)]
+mod common;
+
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use common::gen_trace;
+use common::FibTrace;
+use common::OPTIONS;
+use common::TRACE_LEN;
+use ministark::prover::default_prove_with_artifacts;
+
+#[test]
+fn artifacts_match_proof() {
+    let (claim, trace) = gen_trace();
+
+    let (proof, artifacts) =
+        default_prove_with_artifacts(&claim, OPTIONS, FibTrace(trace.clone())).unwrap();
+
+    let trace_domain = Radix2EvaluationDomain::new(TRACE_LEN).unwrap();
+    assert_eq!(trace.0, artifacts.base_trace_polys.evaluate(trace_domain).0);
+    assert_eq!(proof.fri_proof.layers.len(), artifacts.fri_layers.len());
+    assert_eq!(
+        proof.fri_proof.remainder_coeffs,
+        artifacts.fri_remainder_coeffs
+    );
+}
@@ -0,0 +1,31 @@
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ministark::cache::domain_elements;
+use ministark::cache::get_or_insert_with;
+use ministark::cache::CacheKey;
+use ministark::cache::FileSystemCache;
+use ministark::cache::MemoryCache;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+#[test]
+fn cached_values_are_not_recomputed() {
+    let cache = MemoryCache::new();
+    let key = CacheKey::new::<Fp>("test", 8, [0; 32]);
+
+    let first = get_or_insert_with(&cache, &key, || vec![Fp::from(1u8), Fp::from(2u8)]);
+    let second = get_or_insert_with(&cache, &key, || -> Vec<Fp> { unreachable!() });
+
+    assert_eq!(first, second);
+}
+
+#[test]
+fn file_system_cache_persists_domain_elements() {
+    let dir = std::env::temp_dir().join("ministark-cache-test");
+    let domain = Radix2EvaluationDomain::<Fp>::new(16).unwrap();
+
+    let elements = domain_elements(&FileSystemCache::new(&dir), domain);
+    let cached = domain_elements(&FileSystemCache::new(&dir), domain);
+
+    assert_eq!(domain.elements().collect::<Vec<Fp>>(), elements);
+    assert_eq!(elements, cached);
+}
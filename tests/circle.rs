@@ -0,0 +1,56 @@
+#![cfg(feature = "experimental")]
+use ark_ff::One;
+use ark_ff::UniformRand;
+use ark_ff::Zero;
+use ministark::circle::check_circle_constraint;
+use ministark::circle::CircleDomain;
+use ministark::circle::CircleError;
+use ministark::circle::FftDomain;
+use ministark::circle::M31;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+
+#[test]
+fn circle_fft_extends_low_degree_polynomials() {
+    let mut rng = ark_std::test_rng();
+    let domain = CircleDomain::<M31>::new(4);
+    let lde_domain = CircleDomain::<M31>::new(6);
+    let evals = (0..16).map(|_| M31::rand(&mut rng)).collect::<Vec<M31>>();
+
+    let coeffs = domain.interpolate(evals.clone());
+    let lde_coeffs = lde_domain.interpolate(lde_domain.evaluate(coeffs.clone()));
+
+    assert_eq!(domain.evaluate(coeffs.clone()), evals);
+    assert_eq!(lde_coeffs[..16], coeffs);
+    assert!(lde_coeffs[16..].iter().all(M31::is_zero));
+}
+
+#[test]
+fn circle_domain_vanishing_polynomial_is_zero_on_domain() {
+    let domain = CircleDomain::<M31>::new(5);
+    let lde_domain = CircleDomain::<M31>::new(7);
+
+    assert!(domain
+        .elements()
+        .into_iter()
+        .all(|p| domain.vanishing_eval(p).is_zero()));
+    assert!(!lde_domain
+        .elements()
+        .into_iter()
+        .any(|p| domain.vanishing_eval(p).is_zero()));
+}
+
+#[test]
+fn circle_constraints_cannot_use_domain_point() {
+    use AlgebraicItem::*;
+    let one = Constant(M31::one());
+    let transition = Constraint::<M31>::new(1.curr() - 0.next() * 0.curr());
+    let boundary = Constraint::new((0.curr() - one) / (X - one));
+
+    assert_eq!(check_circle_constraint(&transition), Ok(()));
+    assert_eq!(
+        check_circle_constraint(&boundary),
+        Err(CircleError::DomainPoint)
+    );
+}
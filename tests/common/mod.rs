@@ -0,0 +1,177 @@
+//! Fibonacci fixture shared by the integration tests
+#![allow(dead_code)]
+use ark_ff::One;
+use ark_ff::Zero;
+use ark_serialize::CanonicalSerialize;
+use ministark::air::AirConfig;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::hints::Hints;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::utils::GpuAllocator;
+use ministark::utils::SerdeOutput;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::Proof;
+use ministark::ProofOptions;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use sha2::Sha256;
+use std::marker::PhantomData;
+
+pub const TRACE_LEN: usize = 64;
+pub const OPTIONS: ProofOptions = ProofOptions::new(16, 4, 0, 2, 4);
+
+pub struct FibTrace(pub Matrix<Fp>);
+
+impl Trace for FibTrace {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn base_columns(&self) -> Option<&Matrix<Self::Fp>> {
+        Some(&self.0)
+    }
+}
+
+/// Fibonacci trace with an extension column holding the running sum of the
+/// first column scaled by a challenge
+pub struct FibSumTrace(pub Matrix<Fp>);
+
+impl Trace for FibSumTrace {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn base_columns(&self) -> Option<&Matrix<Self::Fp>> {
+        Some(&self.0)
+    }
+
+    fn build_extension_columns(&self, challenges: &Challenges<Fp>) -> Option<Matrix<Fp>> {
+        let challenge = challenges.as_slice()[0];
+        let mut column = Vec::with_capacity_in(self.0.num_rows(), GpuAllocator);
+        let mut sum = Fp::zero();
+        for &value in &self.0 .0[0] {
+            sum += challenge * value;
+            column.push(sum);
+        }
+        Some(Matrix::new(vec![column]))
+    }
+}
+
+pub struct FibAirConfig;
+
+impl AirConfig for FibAirConfig {
+    const NUM_BASE_COLUMNS: usize = 2;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = Fp;
+
+    fn gen_hints(_trace_len: usize, claim: &Fp, _: &Challenges<Fp>) -> Hints<Fp> {
+        Hints::new(vec![(0, *claim)])
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        let one = AlgebraicItem::Constant(FieldVariant::Fp(Fp::one()));
+        let boundary = [0.curr() - one, 1.curr() - one]
+            .into_iter()
+            .map(Constraint::when_first_row);
+        let transition = [0.next() - 1.curr(), 1.next() - 0.curr() - 1.curr()]
+            .into_iter()
+            .map(|expr| Constraint::when_transition(trace_len, expr));
+        let terminal = [1.curr() - AlgebraicItem::Hint(0)]
+            .into_iter()
+            .map(|expr| Constraint::when_last_row(trace_len, expr));
+        boundary.chain(transition).chain(terminal).collect()
+    }
+}
+
+/// Claim that the last Fibonacci number of the trace is `.0`. Generic over
+/// the trace and AIR so tests can prove Fibonacci with other trace layouts.
+pub struct FibSumAirConfig;
+
+impl AirConfig for FibSumAirConfig {
+    const NUM_BASE_COLUMNS: usize = 2;
+    const NUM_EXTENSION_COLUMNS: usize = 1;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = Fp;
+
+    fn gen_hints(trace_len: usize, claim: &Fp, challenges: &Challenges<Fp>) -> Hints<Fp> {
+        FibAirConfig::gen_hints(trace_len, claim, challenges)
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        let challenge = AlgebraicItem::Challenge(0);
+        let mut constraints = FibAirConfig::constraints(trace_len);
+        constraints.push(Constraint::when_first_row(2.curr() - 0.curr() * challenge));
+        constraints.push(Constraint::when_transition(
+            trace_len,
+            2.next() - 2.curr() - 0.next() * challenge,
+        ));
+        constraints
+    }
+}
+
+pub struct FibClaim<T = FibTrace, A = FibAirConfig>(pub Fp, PhantomData<(T, A)>);
+
+impl<T, A> FibClaim<T, A> {
+    pub const fn new(claim: Fp) -> Self {
+        Self(claim, PhantomData)
+    }
+}
+
+impl<T, A> Stark for FibClaim<T, A>
+where
+    T: Trace<Fp = Fp, Fq = Fp>,
+    A: AirConfig<Fp = Fp, Fq = Fp, PublicInputs = Fp>,
+{
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = A;
+    type Digest = SerdeOutput<Sha256>;
+    type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+    type Witness = T;
+    type Trace = T;
+
+    fn get_public_inputs(&self) -> Fp {
+        self.0
+    }
+
+    fn generate_trace(&self, witness: T) -> T {
+        witness
+    }
+
+    fn gen_public_coin(&self, air: &Air<A>) -> Self::PublicCoin {
+        let mut seed = Vec::new();
+        air.public_inputs().serialize_compressed(&mut seed).unwrap();
+        air.trace_len().serialize_compressed(&mut seed).unwrap();
+        air.options().serialize_compressed(&mut seed).unwrap();
+        PublicCoinImpl::new(Sha256HashFn::hash_chunks([&*seed]))
+    }
+}
+
+pub fn gen_trace() -> (FibClaim, Matrix<Fp>) {
+    let mut a = Vec::with_capacity_in(TRACE_LEN, GpuAllocator);
+    let mut b = Vec::with_capacity_in(TRACE_LEN, GpuAllocator);
+    let (mut x, mut y) = (Fp::one(), Fp::one());
+    for _ in 0..TRACE_LEN {
+        a.push(x);
+        b.push(y);
+        (x, y) = (y, x + y);
+    }
+    let claim = FibClaim::new(*b.last().unwrap());
+    (claim, Matrix::new(vec![a, b]))
+}
+
+pub fn gen_proof() -> (FibClaim, Proof<FibClaim>) {
+    let (claim, trace) = gen_trace();
+    let proof = pollster::block_on(claim.prove(OPTIONS, FibTrace(trace))).unwrap();
+    (claim, proof)
+}
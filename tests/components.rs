@@ -0,0 +1,302 @@
+#![feature(allocator_api)]
+use ark_ff::One;
+use ark_ff::UniformRand;
+use ark_ff::Zero;
+use ministark::arguments::ArgumentChallenges;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::expression::Expr;
+use ministark::gadgets::ByteDecomposition;
+use ministark::gadgets::Endianness;
+use ministark::memory::MemoryAccess;
+use ministark::memory::MemoryColumns;
+use ministark::memory::RwMemory;
+use ministark::rom::FetchColumns;
+use ministark::rom::ProgramRom;
+use ministark::rom::RomColumns;
+use ministark::stack::Stack;
+use ministark::stack::StackColumns;
+use ministark::stack::StackOp;
+use ministark::utils::FieldVariant;
+use ministark::utils::GpuAllocator;
+use ministark::utils::GpuVec;
+use ministark::Matrix;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+const NUM_ROWS: usize = 8;
+
+fn memory() -> RwMemory {
+    let accesses = MemoryColumns {
+        address: 0,
+        clk: 1,
+        value: 2,
+        is_write: 3,
+    };
+    let sorted = MemoryColumns {
+        address: 5,
+        clk: 6,
+        value: 7,
+        is_write: 8,
+    };
+    let challenges = ArgumentChallenges {
+        alpha: 0,
+        weights: vec![1, 2, 3, 4],
+    };
+    let clock_jump = ByteDecomposition::new(10, 11, 2, Endianness::Little);
+    RwMemory::new(accesses, sorted, 9, challenges, [13, 14], clock_jump).skip_accesses_when(4)
+}
+
+fn access(address: u64, clk: u64, value: u64, is_write: bool) -> MemoryAccess {
+    MemoryAccess {
+        address,
+        clk,
+        value,
+        is_write,
+    }
+}
+
+/// Generates the base trace of the accesses in execution order followed by
+/// the sorted memory table
+fn gen_base_trace(memory: &RwMemory, accesses: &[MemoryAccess]) -> Matrix<Fp> {
+    let column = |f: &dyn Fn(&MemoryAccess) -> Fp| {
+        let mut column: GpuVec<Fp> = Vec::with_capacity_in(NUM_ROWS, GpuAllocator);
+        column.extend(accesses.iter().map(f));
+        column.resize(NUM_ROWS, Fp::zero());
+        column
+    };
+    let mut access_dummy = column(&|_| Fp::zero());
+    access_dummy[accesses.len()..].fill(Fp::from(1u8));
+    let witness = memory.gen_sorted_table::<Fp>(accesses, NUM_ROWS);
+    let mut columns = vec![
+        column(&|a| Fp::from(a.address)),
+        column(&|a| Fp::from(a.clk)),
+        column(&|a| Fp::from(a.value)),
+        column(&|a| Fp::from(a.is_write)),
+        access_dummy,
+    ];
+    columns.extend(witness.sorted);
+    columns.push(witness.dummy);
+    columns.push(witness.clock_jump);
+    columns.extend(witness.clock_jump_bytes);
+    Matrix::new(columns)
+}
+
+/// Constraints of a component grouped by the rows they apply to
+struct ComponentConstraints {
+    boundary: Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>>,
+    row: Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>>,
+    transition: Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>>,
+    terminal: Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>>,
+}
+
+fn constraints_hold(
+    constraints: ComponentConstraints,
+    base: &Matrix<Fp>,
+    extension: &Matrix<Fp>,
+    challenges: &Challenges<Fp>,
+) -> bool {
+    let num_rows = base.num_rows();
+    let num_base_columns = base.num_cols();
+    let holds_on_rows = |constraints: Vec<Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>>,
+                         mut rows: core::ops::Range<usize>| {
+        rows.all(|row| {
+            constraints.iter().all(|constraint| {
+                constraint
+                    .eval(&mut |leaf| match *leaf {
+                        AlgebraicItem::Constant(v) => v,
+                        AlgebraicItem::Challenge(i) => FieldVariant::Fp(challenges[i]),
+                        AlgebraicItem::Trace(col, offset) => {
+                            let pos = (row as isize + offset).rem_euclid(num_rows as isize);
+                            let pos = pos as usize;
+                            FieldVariant::Fp(if col < num_base_columns {
+                                base.0[col][pos]
+                            } else {
+                                extension.0[col - num_base_columns][pos]
+                            })
+                        }
+                        _ => unreachable!(),
+                    })
+                    .is_zero()
+            })
+        })
+    };
+
+    holds_on_rows(constraints.boundary, 0..1)
+        && holds_on_rows(constraints.row, 0..num_rows)
+        && holds_on_rows(constraints.transition, 0..num_rows - 1)
+        && holds_on_rows(constraints.terminal, num_rows - 1..num_rows)
+}
+
+fn gen_challenges(num_challenges: usize) -> Challenges<Fp> {
+    let mut rng = ark_std::test_rng();
+    Challenges::new((0..num_challenges).map(|_| Fp::rand(&mut rng)).collect())
+}
+
+fn memory_constraints_hold(memory: &RwMemory, base: &Matrix<Fp>) -> bool {
+    let challenges = gen_challenges(5);
+    let extension = memory.gen_extension_columns(base, &challenges);
+    let constraints = ComponentConstraints {
+        boundary: memory.boundary_constraints(),
+        row: memory.row_constraints(),
+        transition: memory.transition_constraints(),
+        terminal: memory.terminal_constraints(),
+    };
+    constraints_hold(constraints, base, &extension, &challenges)
+}
+
+#[test]
+fn consistent_accesses_satisfy_constraints() {
+    let memory = memory();
+    let accesses = [
+        access(0, 0, 5, true),
+        access(1, 1, 0, false),
+        access(0, 2, 5, false),
+        access(1, 3, 7, true),
+        access(1, 9, 7, false),
+    ];
+
+    let base = gen_base_trace(&memory, &accesses);
+
+    assert!(memory_constraints_hold(&memory, &base));
+}
+
+#[test]
+fn read_of_unwritten_value_is_rejected() {
+    let memory = memory();
+    let accesses = [
+        access(0, 0, 5, true),
+        access(0, 1, 6, false),
+        access(1, 2, 0, false),
+    ];
+
+    let base = gen_base_trace(&memory, &accesses);
+
+    assert!(!memory_constraints_hold(&memory, &base));
+}
+
+#[test]
+fn stack_operations_satisfy_constraints() {
+    let columns = StackColumns {
+        clk: 0,
+        sp: 1,
+        sp_inverse: 2,
+        is_push: 3,
+        is_pop: 4,
+        value: 5,
+        address: 6,
+        no_access: 7,
+    };
+    let sorted = MemoryColumns {
+        address: 8,
+        clk: 9,
+        value: 10,
+        is_write: 11,
+    };
+    let challenges = ArgumentChallenges {
+        alpha: 0,
+        weights: vec![1, 2, 3, 4],
+    };
+    let clock_jump = ByteDecomposition::new(13, 14, 1, Endianness::Little);
+    let stack = Stack::new(columns, sorted, 12, challenges, [15, 16], clock_jump);
+    let ops = [
+        StackOp::Push(3),
+        StackOp::Push(4),
+        StackOp::None,
+        StackOp::Pop,
+        StackOp::Push(5),
+        StackOp::Pop,
+        StackOp::Pop,
+    ];
+
+    let witness = stack.gen_columns::<Fp>(&ops, NUM_ROWS);
+    let mut base_columns = witness.columns.to_vec();
+    base_columns.extend(witness.memory.sorted);
+    base_columns.push(witness.memory.dummy);
+    base_columns.push(witness.memory.clock_jump);
+    base_columns.extend(witness.memory.clock_jump_bytes);
+    let base = Matrix::new(base_columns);
+    let challenges = gen_challenges(5);
+    let extension = stack.gen_extension_columns(&base, &challenges);
+    let constraints = ComponentConstraints {
+        boundary: stack.boundary_constraints(),
+        row: stack.row_constraints(),
+        transition: stack.transition_constraints(),
+        terminal: stack.terminal_constraints(),
+    };
+
+    assert!(constraints_hold(
+        constraints,
+        &base,
+        &extension,
+        &challenges
+    ));
+}
+
+#[test]
+fn program_fetches_satisfy_constraints() {
+    let fetch = FetchColumns {
+        ip: 0,
+        instruction: vec![1],
+    };
+    let table = RomColumns {
+        ip: 3,
+        instruction: vec![4],
+        is_program: 5,
+        no_fetch: 6,
+    };
+    let fetch_challenges = ArgumentChallenges {
+        alpha: 0,
+        weights: vec![1, 2],
+    };
+    let program_challenges = ArgumentChallenges {
+        alpha: 3,
+        weights: vec![4, 5],
+    };
+    let rom = ProgramRom::new(
+        fetch,
+        table,
+        fetch_challenges,
+        program_challenges,
+        [7, 8],
+        9,
+    )
+    .skip_fetch_when(2);
+    let program = [10u8, 20, 30].map(|v| vec![Fp::from(v)]);
+    let fetched_ips = [0, 1, 2, 1, 2];
+
+    let witness = rom.gen_table(&program, &fetched_ips, NUM_ROWS + 8);
+    let fetch_column = |f: &dyn Fn(usize) -> Fp, padding: Fp| {
+        let mut column: GpuVec<Fp> = Vec::with_capacity_in(NUM_ROWS + 8, GpuAllocator);
+        column.extend(fetched_ips.iter().map(|&ip| f(ip)));
+        column.resize(NUM_ROWS + 8, padding);
+        column
+    };
+    let mut base_columns = vec![
+        fetch_column(&|ip| Fp::from(ip as u64), Fp::zero()),
+        fetch_column(&|ip| program[ip][0], Fp::zero()),
+        fetch_column(&|_| Fp::zero(), Fp::one()),
+        witness.ip,
+    ];
+    base_columns.extend(witness.instruction);
+    base_columns.push(witness.is_program);
+    base_columns.push(witness.no_fetch);
+    let base = Matrix::new(base_columns);
+    let challenges = gen_challenges(6);
+    let extension = rom.gen_extension_columns(&base, &challenges);
+    let program_evaluation = rom.program_evaluation(&challenges, &program);
+    let constraints = ComponentConstraints {
+        boundary: rom.boundary_constraints(),
+        row: rom.row_constraints(),
+        transition: rom.transition_constraints(),
+        terminal: rom.terminal_constraints(
+            AlgebraicItem::Constant(FieldVariant::Fq(program_evaluation)).into(),
+        ),
+    };
+
+    assert!(constraints_hold(
+        constraints,
+        &base,
+        &extension,
+        &challenges
+    ));
+}
@@ -10,11 +10,34 @@ use ark_poly::EvaluationDomain;
 use ark_poly::Polynomial;
 use ark_poly::Radix2EvaluationDomain;
 use ark_std::rand::seq::SliceRandom;
+use ministark::air::public_sequence_polynomial;
+use ministark::arguments::ArgumentChallenges;
+use ministark::arguments::EvaluationArgument;
+use ministark::arguments::PermutationArgument;
+use ministark::challenges::Challenges;
+use ministark::constraints::public_sequence_constraint;
 use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Assertion;
+use ministark::constraints::CompositionConstraint;
+use ministark::constraints::CompositionItem;
 use ministark::constraints::Constraint;
 use ministark::constraints::ExecutionTraceColumn;
+use ministark::constraints::Hint;
 use ministark::constraints::VerifierChallenge;
+use ministark::constraints::VirtualColumn;
+use ministark::degree_lowering::lower_degree;
+use ministark::eval_cpu::hoist_row_independent;
 use ministark::expression::Expr;
+use ministark::gadgets::is_zero_witness;
+use ministark::gadgets::ByteDecomposition;
+use ministark::gadgets::Endianness;
+use ministark::gadgets::IsZero;
+use ministark::gadgets::U32Arithmetic;
+use ministark::gadgets::U32Operation;
+use ministark::table::AlgebraicTable;
+use ministark::table::ColumnPadding;
+use ministark::table::Padding;
+use ministark::table::TraceSegments;
 use ministark::utils;
 use ministark::utils::tests::gen_binary_valued_matrix;
 use ministark::utils::tests::gen_fib_matrix;
@@ -60,6 +83,493 @@ fn constraint_degree() {
     assert_eq!(expected_degree, actual_degree);
 }
 
+#[test]
+fn virtual_column_offsets_are_shifted() {
+    use AlgebraicItem::*;
+    let sum = VirtualColumn::<Fp>::new("sum", 0.curr() + 1.offset(-1));
+    let constraint = Constraint::new(sum.next() - Constant(Fp::one()));
+
+    let arguments = constraint.trace_arguments().into_iter().collect::<Vec<_>>();
+
+    assert_eq!(vec![(0, 1), (1, 0)], arguments);
+}
+
+#[test]
+fn degree_lowering_preserves_satisfiability() {
+    use AlgebraicItem::*;
+    let n = 8;
+    let max_blowup_factor = 4;
+    let mut rng = ark_std::test_rng();
+    let one = FieldVariant::Fp(Fp::one());
+    let constraint = (0.curr().pow(4) * 1.curr().pow(4) - 2.curr()) / (X.pow(n) - Constant(one));
+    let lowering =
+        lower_degree::<Fp, Fp>(vec![Constraint::new(constraint)], 3, n, max_blowup_factor);
+    let col0 = (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+    let col1 = (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+    let col2 = col0
+        .iter()
+        .zip(&col1)
+        .map(|(a, b)| a.pow([4]) * b.pow([4]))
+        .collect::<Vec<Fp>>();
+    let mut trace = Matrix::new(vec![
+        col0.to_vec_in(GpuAllocator),
+        col1.to_vec_in(GpuAllocator),
+        col2.to_vec_in(GpuAllocator),
+    ]);
+
+    lowering.fill_auxiliary_columns(&mut trace);
+
+    assert_eq!(2, lowering.num_auxiliary_columns());
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    for constraint in lowering.constraints() {
+        assert!(constraint.blowup_factor(n) <= max_blowup_factor);
+        for (row, x) in trace_domain.elements().enumerate() {
+            let result = constraint.check(&mut |leaf| match leaf {
+                X => FieldVariant::Fp(x),
+                &Constant(v) => v,
+                &Trace(i, 0) => FieldVariant::Fp(trace.0[i][row]),
+                _ => unreachable!(),
+            });
+            assert!(result.is_some(), "constraint fails on row {row}");
+        }
+    }
+}
+
+#[test]
+fn degree_lowering_shifts_extension_columns() {
+    use AlgebraicItem::*;
+    let n = 8;
+    let max_blowup_factor = 4;
+    let mut rng = ark_std::test_rng();
+    let one = FieldVariant::Fp(Fp::one());
+    let vanishing_poly = X.pow(n) - Constant(one);
+    // column 2 is an extension column
+    let base = (0.curr().pow(4) * 1.curr().pow(4) - Constant(one)) / vanishing_poly.clone();
+    let extension = (2.curr() - 0.curr() * 1.curr()) / vanishing_poly;
+    let constraints = vec![Constraint::new(base), Constraint::new(extension)];
+    let lowering = lower_degree::<Fp, Fp>(constraints, 2, n, max_blowup_factor);
+    let col0 = (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+    let col1 = col0
+        .iter()
+        .map(|v| v.inverse().unwrap())
+        .collect::<Vec<Fp>>();
+    let mut trace = Matrix::new(vec![
+        col0.to_vec_in(GpuAllocator),
+        col1.to_vec_in(GpuAllocator),
+    ]);
+
+    lowering.fill_auxiliary_columns(&mut trace);
+    let product = col0
+        .iter()
+        .zip(&col1)
+        .map(|(a, b)| *a * b)
+        .collect::<Vec<Fp>>();
+    trace.append(Matrix::new(vec![product.to_vec_in(GpuAllocator)]));
+
+    let num_auxiliary_columns = lowering.num_auxiliary_columns();
+    assert_ne!(0, num_auxiliary_columns);
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    for constraint in lowering.constraints() {
+        assert!(constraint.blowup_factor(n) <= max_blowup_factor);
+        for (row, x) in trace_domain.elements().enumerate() {
+            let result = constraint.check(&mut |leaf| match leaf {
+                X => FieldVariant::Fp(x),
+                &Constant(v) => v,
+                &Trace(i, 0) => FieldVariant::Fp(trace.0[i][row]),
+                _ => unreachable!(),
+            });
+            assert!(result.is_some(), "constraint fails on row {row}");
+        }
+    }
+    let extension_column = 2 + num_auxiliary_columns;
+    let mut arguments = lowering
+        .constraints()
+        .iter()
+        .flat_map(|c| c.trace_arguments());
+    assert!(arguments.any(|(i, _)| i == extension_column));
+}
+
+#[test]
+fn boundary_constraint_on_arbitrary_rows() {
+    use AlgebraicItem::*;
+    let n = 8;
+    let mut rng = ark_std::test_rng();
+    let value = Fp::rand(&mut rng);
+    let mut col = (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+    col[2] = value;
+    col[n - 1] = value;
+    let expr = 0.curr() - Constant(FieldVariant::Fp(value));
+    let constraint = Constraint::<FieldVariant<Fp, Fp>>::when_rows(n, [2, -1], expr);
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let check_row = |col: &[Fp], row: usize| {
+        constraint
+            .check(&mut |leaf| match leaf {
+                X => FieldVariant::Fp(trace_domain.element(row)),
+                &Constant(v) => v,
+                &Trace(0, 0) => FieldVariant::Fp(col[row]),
+                _ => unreachable!(),
+            })
+            .is_some()
+    };
+
+    assert!((0..n).all(|row| check_row(&col, row)));
+    col[n - 1] += Fp::one();
+    assert!(!check_row(&col, n - 1));
+}
+
+#[test]
+fn previous_row_constraint_excludes_first_row() {
+    use AlgebraicItem::*;
+    let n = 8;
+    // the first row would wrap around to the last row without the exclusion
+    let mut col = (0..n).map(|i| Fp::from(i as u64)).collect::<Vec<Fp>>();
+    let one = Constant(FieldVariant::Fp(Fp::one()));
+    let expr = 0.curr() - 0.prev() - one;
+    let constraint = Constraint::<FieldVariant<Fp, Fp>>::when_not_first_row(n, expr);
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let check_row = |col: &[Fp], row: usize| {
+        constraint
+            .check(&mut |leaf| match leaf {
+                X => FieldVariant::Fp(trace_domain.element(row)),
+                &Constant(v) => v,
+                &Trace(0, offset) => {
+                    let pos = (row as isize + offset).rem_euclid(n as isize) as usize;
+                    FieldVariant::Fp(col[pos])
+                }
+                _ => unreachable!(),
+            })
+            .is_some()
+    };
+
+    assert!((0..n).all(|row| check_row(&col, row)));
+    col[3] += Fp::one();
+    assert!(!check_row(&col, 3));
+    assert!(!check_row(&col, 4));
+}
+
+#[test]
+fn assertion_checks_terminal_against_hint() {
+    use AlgebraicItem::*;
+    let n = 8;
+    let mut rng = ark_std::test_rng();
+    let (challenge, terminal) = (Fp::rand(&mut rng), Fp::rand(&mut rng));
+    let mut col = (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+    col[n - 1] = terminal * challenge;
+    let assertion = Assertion::<FieldVariant<Fp, Fp>>::last_row(0, 0.hint() * 0.challenge());
+    let constraint = assertion.constraint(n);
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let check_row = |col: &[Fp], row: usize| {
+        constraint
+            .check(&mut |leaf| match leaf {
+                X => FieldVariant::Fp(trace_domain.element(row)),
+                &Constant(v) => v,
+                &Hint(0) => FieldVariant::Fp(terminal),
+                &Challenge(0) => FieldVariant::Fp(challenge),
+                &Trace(0, 0) => FieldVariant::Fp(col[row]),
+                _ => unreachable!(),
+            })
+            .is_some()
+    };
+
+    assert!(assertion.is_row_independent());
+    assert!(!Assertion::<FieldVariant<Fp, Fp>>::first_row(0, 1.curr()).is_row_independent());
+    assert!((0..n).all(|row| check_row(&col, row)));
+    col[n - 1] += Fp::one();
+    assert!(!check_row(&col, n - 1));
+}
+
+#[test]
+fn is_zero_gadget_flags_zero_values() {
+    use AlgebraicItem::*;
+    let values = [Fp::zero(), Fp::from(5u8), Fp::zero(), Fp::from(9u8)];
+    let inverses = is_zero_witness(&values);
+    let gadget = IsZero::<FieldVariant<Fp, Fp>>::new(0.curr(), 1);
+    let eval_row = |expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>, row: usize| {
+        expr.eval(&mut |leaf| match leaf {
+            &Constant(v) => v,
+            &Trace(0, 0) => FieldVariant::Fp(values[row]),
+            &Trace(1, 0) => FieldVariant::Fp(inverses[row]),
+            _ => unreachable!(),
+        })
+    };
+
+    for (row, value) in values.iter().enumerate() {
+        let expected = FieldVariant::Fp(Fp::from(value.is_zero()));
+        assert_eq!(expected, eval_row(&gadget.is_zero, row));
+        for constraint in &gadget.constraints {
+            assert!(eval_row(constraint, row).is_zero());
+        }
+    }
+}
+
+#[test]
+fn byte_decomposition_recomposes_value() {
+    use AlgebraicItem::*;
+    let values = [Fp::from(0x0102u32), Fp::from(0xff00u32)];
+    let decomposition = ByteDecomposition::new(0, 1, 2, Endianness::Big);
+    let bytes = decomposition.witness(&values);
+    let constraints = decomposition.constraints::<Fp, Fp>();
+
+    assert_eq!([Fp::from(1u8), Fp::from(0xffu8)], bytes[0][..]);
+    assert_eq!([Fp::from(2u8), Fp::zero()], bytes[1][..]);
+    for (row, value) in values.iter().enumerate() {
+        for constraint in &constraints {
+            let eval = constraint.eval(&mut |leaf| match leaf {
+                &Constant(v) => v,
+                &Trace(0, 0) => FieldVariant::Fp(*value),
+                &Trace(col, 0) => FieldVariant::Fp(bytes[col - 1][row]),
+                _ => unreachable!(),
+            });
+            assert!(eval.is_zero());
+        }
+    }
+}
+
+#[test]
+fn u32_addition_wraps_with_carry() {
+    use AlgebraicItem::*;
+    let a = [u32::MAX, 7];
+    let b = [2, 8];
+    let gadget = U32Arithmetic {
+        operation: U32Operation::Add,
+        a: 0,
+        b: 1,
+        c: 2,
+        carry: 3,
+        first_byte_column: 4,
+    };
+    let witness = gadget.witness::<Fp>(&a, &b);
+    let constraints = gadget.constraints::<Fp, Fp>();
+
+    assert_eq!([Fp::one(), Fp::from(15u8)], witness.c[..]);
+    assert_eq!([Fp::one(), Fp::zero()], witness.carry[..]);
+    for row in 0..a.len() {
+        let columns = [
+            Fp::from(a[row]),
+            Fp::from(b[row]),
+            witness.c[row],
+            witness.carry[row],
+        ];
+        for constraint in &constraints {
+            let eval = constraint.eval(&mut |leaf| match leaf {
+                &Constant(v) => v,
+                &Trace(col @ 0..=3, 0) => FieldVariant::Fp(columns[col]),
+                &Trace(col, 0) => FieldVariant::Fp(witness.bytes[col - 4][row]),
+                _ => unreachable!(),
+            });
+            assert!(eval.is_zero());
+        }
+    }
+}
+
+#[test]
+fn padded_table_satisfies_constraints() {
+    use AlgebraicItem::*;
+    let n = 8;
+    let one = Constant(FieldVariant::Fp(Fp::one()));
+    // column 0 is a cycle counter and column 1 flags padding rows
+    let mut table = AlgebraicTable::<Fp, Fp>::new(
+        "counter",
+        Padding::NextRow(|row| vec![row[0] + Fp::one(), Fp::one()]),
+    );
+    table
+        .add_boundary_constraints([0.curr(), 1.curr()])
+        .add_transition_constraints([0.next() - 0.curr() - one, 1.curr() * (1.curr() - one)])
+        .add_terminal_constraints([1.curr() - one]);
+    let rows = (0..3u64).map(|i| vec![Fp::from(i), Fp::zero()]).collect();
+
+    let trace = table.gen_columns(rows, n);
+
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    for constraint in table.constraints(n) {
+        for (row, x) in trace_domain.elements().enumerate() {
+            let result = constraint.check(&mut |leaf| match leaf {
+                X => FieldVariant::Fp(x),
+                &Constant(v) => v,
+                &Trace(i, j) => {
+                    FieldVariant::Fp(trace.0[i][(row + usize::try_from(j).unwrap()) % n])
+                }
+                _ => unreachable!(),
+            });
+            assert!(result.is_some(), "constraint fails on row {row}");
+        }
+    }
+}
+
+#[test]
+fn column_padding_applies_each_columns_rule() {
+    use ColumnPadding::*;
+    static PADDING: [ColumnPadding<Fp>; 5] =
+        [Increment, Repeat, Zero, One, Custom(|row| row[0] + row[1])];
+    let table = AlgebraicTable::<Fp, Fp>::new("memory", Padding::Columns(&PADDING));
+    let row = |values: [u8; 5]| values.map(Fp::from).to_vec();
+    let mut rows = vec![row([3, 5, 7, 0, 0])];
+
+    table.pad(&mut rows, 3);
+
+    assert_eq!(rows[1], row([4, 5, 0, 1, 8]));
+    assert_eq!(rows[2], row([5, 5, 0, 1, 9]));
+}
+
+#[test]
+fn short_table_satisfies_constraints_on_subgroup() {
+    use AlgebraicItem::*;
+    let n = 16;
+    let height = 4;
+    let one = Constant(FieldVariant::Fp(Fp::one()));
+    let mut table =
+        AlgebraicTable::<Fp, Fp>::new("counter", Padding::NextRow(|row| vec![row[0] + Fp::one()]))
+            .with_height(height);
+    table
+        .add_boundary_constraints([0.curr() - one])
+        .add_transition_constraints([0.next() - 0.curr() - one]);
+    let rows = vec![vec![Fp::one()]];
+
+    let trace = table.gen_columns(rows, n);
+
+    // table rows are spread out over every `n / height` rows of the trace
+    for i in 0..height {
+        assert_eq!(trace.0[0][i * n / height], Fp::from(i as u64 + 1));
+    }
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    for constraint in table.constraints(n) {
+        for (row, x) in trace_domain.elements().enumerate() {
+            let result = constraint.check(&mut |leaf| match leaf {
+                X => FieldVariant::Fp(x),
+                &Constant(v) => v,
+                &Trace(i, j) => {
+                    FieldVariant::Fp(trace.0[i][(row + usize::try_from(j).unwrap()) % n])
+                }
+                _ => unreachable!(),
+            });
+            assert!(result.is_some(), "constraint fails on row {row}");
+        }
+    }
+}
+
+#[test]
+fn short_table_segment_interpolates_over_its_subgroup() {
+    let n = 16;
+    let table =
+        AlgebraicTable::<Fp, Fp>::new("counter", Padding::NextRow(|row| vec![row[0] + Fp::one()]))
+            .with_height(4);
+    let rows = vec![vec![Fp::one()]];
+    let long_columns = Matrix::from_rows((0..n).map(|i| vec![Fp::from(i as u64)]).collect());
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+
+    let segments = TraceSegments::new(vec![
+        table.gen_segment(rows.clone(), n),
+        long_columns.clone(),
+    ]);
+    let extended = Matrix::join(vec![table.gen_columns(rows, n), long_columns]);
+
+    assert_eq!(4, segments.0[0].num_rows());
+    assert_eq!(n, segments.num_rows());
+    assert_eq!(extended.0[0], segments.column(0));
+    assert_eq!(
+        extended.interpolate(trace_domain).0,
+        segments.interpolate(trace_domain).0
+    );
+}
+
+#[test]
+fn permutation_argument_accepts_permuted_rows() {
+    use AlgebraicItem::*;
+    let n = 8;
+    let num_dummy_rows = 2;
+    let mut rng = ark_std::test_rng();
+    let mut lhs = (0..n - num_dummy_rows)
+        .map(|_| vec![Fp::rand(&mut rng), Fp::rand(&mut rng), Fp::zero()])
+        .collect::<Vec<Vec<Fp>>>();
+    let mut rhs = lhs.clone();
+    rhs.shuffle(&mut rng);
+    // dummy rows hold junk values and are flagged by the last column
+    for side in [&mut lhs, &mut rhs] {
+        for _ in 0..num_dummy_rows {
+            side.push(vec![Fp::rand(&mut rng), Fp::rand(&mut rng), Fp::one()]);
+        }
+    }
+    let rows = lhs
+        .into_iter()
+        .zip(rhs)
+        .map(|(l, r)| [l, r].concat())
+        .collect();
+    let base = Matrix::from_rows(rows);
+    let challenges = Challenges::new((0..3).map(|_| Fp::rand(&mut rng)).collect());
+    let argument_challenges = ArgumentChallenges {
+        alpha: 0,
+        weights: vec![1, 2],
+    };
+    let argument = PermutationArgument::new(vec![0, 1], vec![3, 4], argument_challenges, [6, 7])
+        .skip_lhs_when(2)
+        .skip_rhs_when(5);
+
+    let extension = argument.gen_extension_columns(&base, &challenges);
+
+    let trace = Matrix::join(vec![base, extension]);
+    let eval_row = |expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>, row: usize| {
+        expr.eval(&mut |leaf| match leaf {
+            &Constant(v) => v,
+            &Challenge(i) => FieldVariant::Fp(challenges[i]),
+            &Trace(i, j) => FieldVariant::Fp(trace.0[i][(row + usize::try_from(j).unwrap()) % n]),
+            _ => unreachable!(),
+        })
+    };
+    for constraint in argument.boundary_constraints() {
+        assert!(eval_row(&constraint, 0).is_zero());
+    }
+    for constraint in argument.transition_constraints() {
+        assert!((0..n - 1).all(|row| eval_row(&constraint, row).is_zero()));
+    }
+    for constraint in argument.terminal_constraints() {
+        assert!(eval_row(&constraint, n - 1).is_zero());
+    }
+}
+
+#[test]
+fn evaluation_argument_absorbs_selected_rows() {
+    use AlgebraicItem::*;
+    let n = 8;
+    let mut rng = ark_std::test_rng();
+    // column 0 holds values and column 1 selects the rows to absorb
+    let rows = (0..n)
+        .map(|i| vec![Fp::rand(&mut rng), Fp::from(u64::from(i % 3 != 0))])
+        .collect::<Vec<Vec<Fp>>>();
+    let selected_rows = rows
+        .iter()
+        .filter(|row| row[1].is_one())
+        .map(|row| &row[..1]);
+    let base = Matrix::from_rows(rows.clone());
+    let challenges = Challenges::new((0..2).map(|_| Fp::rand(&mut rng)).collect());
+    let argument_challenges = ArgumentChallenges {
+        alpha: 0,
+        weights: vec![1],
+    };
+    let argument = EvaluationArgument::new(vec![0], argument_challenges, 2).with_selector(1);
+    let terminal = argument.compute_terminal(&challenges, selected_rows);
+
+    let extension = argument.gen_extension_columns(&base, &challenges);
+
+    let trace = Matrix::join(vec![base, extension]);
+    let eval_row = |expr: &Expr<AlgebraicItem<FieldVariant<Fp, Fp>>>, row: usize| {
+        expr.eval(&mut |leaf| match leaf {
+            &Constant(v) => v,
+            &Challenge(i) => FieldVariant::Fp(challenges[i]),
+            &Trace(i, j) => FieldVariant::Fp(trace.0[i][(row + usize::try_from(j).unwrap()) % n]),
+            _ => unreachable!(),
+        })
+    };
+    for constraint in argument.boundary_constraints() {
+        assert!(eval_row(&constraint, 0).is_zero());
+    }
+    for constraint in argument.transition_constraints() {
+        assert!((0..n - 1).all(|row| eval_row(&constraint, row).is_zero()));
+    }
+    for constraint in argument.terminal_constraints(Constant(FieldVariant::Fp(terminal)).into()) {
+        assert!(eval_row(&constraint, n - 1).is_zero());
+    }
+}
+
 #[test]
 fn constraint_with_challenges() {
     // TODO: hints
@@ -73,7 +583,7 @@ fn constraint_with_challenges() {
             X => unreachable!(),
             &Constant(v) => FieldVariant::Fp(v),
             &Hint(_) => unreachable!(),
-            &Periodic(_) => todo!(),
+            &Periodic(_) | &PublicPolynomial(_) => todo!(),
             &Challenge(i) => FieldVariant::Fp(challenges[i]),
             &Trace(i, j) => {
                 assert_eq!(0, j);
@@ -83,6 +593,30 @@ fn constraint_with_challenges() {
         .is_zero());
 }
 
+#[test]
+fn row_independent_subexpressions_are_hoisted() {
+    use AlgebraicItem::*;
+    let two = Constant(FieldVariant::Fp(Fp::from(2u8)));
+    let constraint: Expr<AlgebraicItem<FieldVariant<Fp, Fp>>> =
+        (0.challenge() * 1.challenge() + two) * 0.curr() - 0.hint().pow(3);
+    let composition =
+        CompositionConstraint::new(constraint.map_leaves(&mut |&leaf| CompositionItem::Item(leaf)));
+    let challenges = [Fp::from(3u8), Fp::from(5u8)];
+    let hints = [Fp::from(7u8)];
+
+    let hoisted = hoist_row_independent(&constraint, &challenges, &hints);
+
+    // the challenge product and the hint cubed
+    assert_eq!(3, composition.row_independent_ops().num_muls);
+    let value = hoisted.eval(&mut |leaf| match *leaf {
+        Constant(v) => v,
+        Trace(0, 0) => FieldVariant::Fp(Fp::from(11u8)),
+        _ => unreachable!("{leaf:?} should be hoisted"),
+    });
+    let expected = Fp::from(17u16 * 11) - Fp::from(7u16.pow(3));
+    assert_eq!(FieldVariant::Fp(expected), value);
+}
+
 #[test]
 fn symbolic_evaluation_with_challenges() {
     let n = 2048;
@@ -143,7 +677,7 @@ fn constraint_multiplication() {
             X => one,
             Challenge(_) => unreachable!(),
             Hint(_) => unreachable!(),
-            Periodic(_) => todo!(),
+            Periodic(_) | PublicPolynomial(_) => todo!(),
             &Constant(v) => v,
             &Trace(i, j) => {
                 assert_eq!(0, i, "for value {val}");
@@ -170,6 +704,41 @@ fn constraint_multiplication() {
     assert!(!between_0_and_10.eval(&mut f(twelve)).is_zero());
 }
 
+#[test]
+fn public_sequence_constraint_is_satisfied_on_subgroup() {
+    use AlgebraicItem::*;
+    let n = 64;
+    let num_values = 8;
+    let step = n / num_values;
+    let mut rng = ark_std::test_rng();
+    let values = (0..num_values)
+        .map(|_| Fp::rand(&mut rng))
+        .collect::<Vec<Fp>>();
+    let public_poly = DensePolynomial::from_coefficients_vec(public_sequence_polynomial(&values));
+    let mut column = (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+    for (i, &value) in values.iter().enumerate() {
+        column[i * step] = value;
+    }
+    let constraint = public_sequence_constraint::<FieldVariant<Fp, Fp>>(0, 0, num_values);
+    let trace_domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let check_row = |column: &[Fp], row: usize| {
+        let x = trace_domain.element(row);
+        constraint
+            .check(&mut |leaf| match leaf {
+                X => FieldVariant::Fp(x),
+                &Constant(v) => v,
+                &Trace(0, 0) => FieldVariant::Fp(column[row]),
+                &PublicPolynomial(0) => FieldVariant::Fp(public_poly.evaluate(&x)),
+                _ => unreachable!(),
+            })
+            .is_some()
+    };
+
+    assert!((0..n).all(|row| check_row(&column, row)));
+    column[step] += Fp::one();
+    assert!(!check_row(&column, step));
+}
+
 #[test]
 fn evaluate_fibonacci_constraint() {
     let n = 2048;
@@ -371,6 +940,7 @@ fn evaluate_symbolic<Fp: GpuFftField + FftField, Fq: StarkExtensionOf<Fp>>(
             &Hint(i) => FieldVariant::Fq(hints[i]),
             &Challenge(i) => FieldVariant::Fq(challenges[i]),
             &Periodic(_col) => todo!(),
+            &PublicPolynomial(_) => todo!(),
             &Trace(col_idx, offset) => {
                 let pos = (i as isize + blowup_factor * offset).rem_euclid(n as isize) as usize;
                 let column = &lde_matrix[col_idx];
@@ -0,0 +1,111 @@
+#![feature(allocator_api)]
+mod common;
+
+use ark_serialize::CanonicalSerialize;
+use common::gen_trace;
+use common::FibTrace;
+use common::OPTIONS;
+use ministark::context::CancellationToken;
+use ministark::context::ProverContext;
+use ministark::prover::ProvingError;
+use ministark::reference::differential_verify;
+use ministark::stark::Stark;
+use ministark::ProofOptions;
+
+#[test]
+fn prover_respects_memory_budget() {
+    let (claim, trace) = gen_trace();
+    let context = ProverContext::new().with_memory_budget(1 << 10);
+
+    let result = pollster::block_on(claim.prove_with_context(&context, OPTIONS, FibTrace(trace)));
+
+    assert!(matches!(
+        result,
+        Err(ProvingError::OutOfMemoryBudget { limit: 1024, .. })
+    ));
+    assert_eq!(0, context.memory_budget().unwrap().used());
+}
+
+#[test]
+fn cancelled_proofs_are_not_generated() {
+    let (claim, trace) = gen_trace();
+    let cancellation_token = CancellationToken::new();
+    cancellation_token.cancel();
+
+    let proof = claim.prove_cancellable(OPTIONS, FibTrace(trace), &cancellation_token);
+    let result = pollster::block_on(proof);
+
+    assert!(matches!(result, Err(ProvingError::Cancelled)));
+}
+
+#[test]
+fn prove_uses_the_current_context() {
+    let (claim, trace) = gen_trace();
+    let context = ProverContext::new().with_memory_budget(1 << 10);
+
+    let result = context.scope(|| pollster::block_on(claim.prove(OPTIONS, FibTrace(trace))));
+
+    assert!(matches!(
+        result,
+        Err(ProvingError::OutOfMemoryBudget { limit: 1024, .. })
+    ));
+}
+
+#[test]
+fn context_proofs_are_unchanged_by_recycled_buffers() {
+    let (claim, trace) = gen_trace();
+    let context = ProverContext::new();
+
+    let proof1 =
+        pollster::block_on(claim.prove_with_context(&context, OPTIONS, FibTrace(trace.clone())));
+    let proof2 = pollster::block_on(claim.prove_with_context(&context, OPTIONS, FibTrace(trace)));
+
+    let mut bytes1 = Vec::new();
+    let mut bytes2 = Vec::new();
+    proof1.unwrap().serialize_compressed(&mut bytes1).unwrap();
+    proof2.unwrap().serialize_compressed(&mut bytes2).unwrap();
+    assert_eq!(bytes1, bytes2);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn proofs_do_not_depend_on_thread_count() {
+    let prove_with_threads = |num_threads| {
+        let (claim, trace) = gen_trace();
+        let context = ProverContext::new().with_num_threads(num_threads).unwrap();
+        let proof = claim.prove_with_context(&context, OPTIONS, FibTrace(trace));
+        let proof = pollster::block_on(proof);
+        let mut bytes = Vec::new();
+        proof.unwrap().serialize_compressed(&mut bytes).unwrap();
+        bytes
+    };
+
+    assert_eq!(prove_with_threads(1), prove_with_threads(4));
+}
+
+/// Without the `gpu` feature the shard of each device is evaluated on the host
+#[cfg(not(feature = "gpu"))]
+#[test]
+fn sharded_proofs_match_single_device_proofs() {
+    let prove_on_devices = |num_devices| {
+        let (claim, trace) = gen_trace();
+        let context = ProverContext::new().with_devices(0..num_devices);
+        let proof = claim.prove_with_context(&context, OPTIONS, FibTrace(trace));
+        let proof = pollster::block_on(proof);
+        let mut bytes = Vec::new();
+        proof.unwrap().serialize_compressed(&mut bytes).unwrap();
+        bytes
+    };
+
+    assert_eq!(prove_on_devices(1), prove_on_devices(4));
+}
+
+#[test]
+fn small_proofs_verify() {
+    let (claim, trace) = gen_trace();
+    let context = ProverContext::low_latency();
+    let proof = claim.prove_with_context(&context, ProofOptions::small(), FibTrace(trace));
+    let proof = pollster::block_on(proof).unwrap();
+
+    assert!(differential_verify(&claim, proof, 1).is_ok());
+}
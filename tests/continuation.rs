@@ -0,0 +1,167 @@
+#![feature(allocator_api)]
+mod common;
+
+use ark_ff::One;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use common::FibTrace;
+use ministark::air::AirConfig;
+use ministark::challenges::Challenges;
+use ministark::constraints::AlgebraicItem;
+use ministark::constraints::Constraint;
+use ministark::constraints::ExecutionTraceColumn;
+use ministark::continuation::verify_chain;
+use ministark::continuation::ChainVerificationError;
+use ministark::continuation::Continuation;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::hints::Hints;
+use ministark::impl_public_inputs;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::public_inputs::public_coin_seed;
+use ministark::random::PublicCoinImpl;
+use ministark::stark::Stark;
+use ministark::utils::FieldVariant;
+use ministark::utils::GpuAllocator;
+use ministark::utils::SerdeOutput;
+use ministark::Air;
+use ministark::Matrix;
+use ministark::Proof;
+use ministark::ProofOptions;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use sha2::Sha256;
+
+const CHUNK_LEN: usize = 32;
+const OPTIONS: ProofOptions = ProofOptions::new(16, 4, 0, 2, 4);
+
+/// Fibonacci chunk starting and ending in the given rows
+#[derive(CanonicalSerialize, CanonicalDeserialize, Clone, Copy)]
+struct FibChunk {
+    initial: (Fp, Fp),
+    last: (Fp, Fp),
+}
+
+impl_public_inputs!(FibChunk { initial, last });
+
+impl Continuation for FibChunk {
+    type State = (Fp, Fp);
+
+    fn initial_state(&self) -> (Fp, Fp) {
+        self.initial
+    }
+
+    fn final_state(&self) -> (Fp, Fp) {
+        self.last
+    }
+}
+
+struct FibAirConfig;
+
+impl AirConfig for FibAirConfig {
+    const NUM_BASE_COLUMNS: usize = 2;
+    type Fp = Fp;
+    type Fq = Fp;
+    type PublicInputs = FibChunk;
+
+    fn gen_hints(_trace_len: usize, chunk: &FibChunk, _: &Challenges<Fp>) -> Hints<Fp> {
+        let FibChunk { initial, last } = *chunk;
+        Hints::new(vec![
+            (0, initial.0),
+            (1, initial.1),
+            (2, last.0),
+            (3, last.1),
+        ])
+    }
+
+    fn constraints(trace_len: usize) -> Vec<Constraint<FieldVariant<Fp, Fp>>> {
+        let boundary = [
+            0.curr() - AlgebraicItem::Hint(0),
+            1.curr() - AlgebraicItem::Hint(1),
+        ]
+        .into_iter()
+        .map(Constraint::when_first_row);
+        let transition = [0.next() - 1.curr(), 1.next() - 0.curr() - 1.curr()]
+            .into_iter()
+            .map(|expr| Constraint::when_transition(trace_len, expr));
+        let terminal = [
+            0.curr() - AlgebraicItem::Hint(2),
+            1.curr() - AlgebraicItem::Hint(3),
+        ]
+        .into_iter()
+        .map(|expr| Constraint::when_last_row(trace_len, expr));
+        boundary.chain(transition).chain(terminal).collect()
+    }
+}
+
+impl Stark for FibChunk {
+    type Fp = Fp;
+    type Fq = Fp;
+    type AirConfig = FibAirConfig;
+    type Digest = SerdeOutput<Sha256>;
+    type PublicCoin = PublicCoinImpl<Fp, Sha256HashFn>;
+    type MerkleTree = MatrixMerkleTreeImpl<Sha256HashFn>;
+    type Witness = FibTrace;
+    type Trace = FibTrace;
+
+    fn get_public_inputs(&self) -> FibChunk {
+        *self
+    }
+
+    fn generate_trace(&self, witness: FibTrace) -> FibTrace {
+        witness
+    }
+
+    fn gen_public_coin(&self, air: &Air<FibAirConfig>) -> Self::PublicCoin {
+        PublicCoinImpl::new(Sha256HashFn::hash_chunks([&*public_coin_seed(air)]))
+    }
+}
+
+/// Proves consecutive chunks of the fibonacci sequence. Each chunk starts in
+/// the last row of the previous chunk.
+fn gen_chain(num_chunks: usize) -> Vec<(FibChunk, Proof<FibChunk>)> {
+    let mut state = (Fp::one(), Fp::one());
+    (0..num_chunks)
+        .map(|_| {
+            let initial = state;
+            let mut a = Vec::with_capacity_in(CHUNK_LEN, GpuAllocator);
+            let mut b = Vec::with_capacity_in(CHUNK_LEN, GpuAllocator);
+            for _ in 0..CHUNK_LEN {
+                a.push(state.0);
+                b.push(state.1);
+                state = (state.1, state.0 + state.1);
+            }
+            state = (*a.last().unwrap(), *b.last().unwrap());
+            let chunk = FibChunk {
+                initial,
+                last: state,
+            };
+            let trace = FibTrace(Matrix::new(vec![a, b]));
+            let proof = pollster::block_on(chunk.prove(OPTIONS, trace)).unwrap();
+            (chunk, proof)
+        })
+        .collect()
+}
+
+#[test]
+fn chain_of_consecutive_chunks_is_valid() {
+    let chain = gen_chain(3);
+
+    let chain = chain.iter().map(|(c, p)| (c, p.clone())).collect();
+
+    assert_eq!(3, verify_chain(chain, 1).unwrap().len());
+}
+
+#[test]
+fn chain_with_skipped_chunk_is_invalid() {
+    let chain = gen_chain(3);
+
+    let chain = [&chain[0], &chain[2]]
+        .into_iter()
+        .map(|(c, p)| (c, p.clone()))
+        .collect();
+
+    assert!(matches!(
+        verify_chain(chain, 1),
+        Err(ChainVerificationError::BrokenLink { index: 0 })
+    ));
+}
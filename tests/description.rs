@@ -0,0 +1,46 @@
+#![feature(allocator_api)]
+mod common;
+
+use ark_ff::One;
+use common::FibAirConfig;
+use common::OPTIONS;
+use common::TRACE_LEN;
+use ministark::description::DescriptionError;
+use ministark::description::ExprDescription;
+use ministark::Air;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+#[test]
+fn exported_description_matches_air() {
+    let air = Air::<FibAirConfig>::new(TRACE_LEN, Fp::one(), OPTIONS);
+
+    let description = air.export_description();
+
+    assert_eq!(2, description.num_base_columns);
+    assert_eq!(air.constraints().len(), description.constraints.len());
+    assert!(matches!(
+        &description.constraints[0].expression,
+        ExprDescription::Div { lhs, .. } if matches!(**lhs, ExprDescription::Add { .. })
+    ));
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&description).unwrap();
+        assert_eq!(description, serde_json::from_str(&json).unwrap());
+    }
+}
+
+#[test]
+fn imported_description_matches_air() {
+    let air = Air::<FibAirConfig>::new(TRACE_LEN, Fp::one(), OPTIONS);
+    let mut description = air.export_description();
+
+    let imported = Air::<FibAirConfig>::from_description(&description, Fp::one(), OPTIONS).unwrap();
+
+    assert_eq!(air.id(), imported.id());
+    assert_eq!(description, imported.export_description());
+    description.constraints.pop();
+    assert!(matches!(
+        Air::<FibAirConfig>::from_description(&description, Fp::one(), OPTIONS),
+        Err(DescriptionError::AirIdMismatch { .. })
+    ));
+}
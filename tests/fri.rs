@@ -0,0 +1,161 @@
+#![feature(allocator_api)]
+use ark_ff::FftField;
+use ark_ff::UniformRand;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ministark::context::Device;
+use ministark::fri;
+use ministark::fri::FriOptions;
+use ministark::fri::FriProof;
+use ministark::fri::FriProver;
+use ministark::fri::FriVerifier;
+use ministark::fri::PublicCoinChannel;
+use ministark::fri::VerificationError;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::utils::GpuAllocator;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use ministark_gpu::utils::bit_reverse;
+
+type Tree = MatrixMerkleTreeImpl<Sha256HashFn>;
+type Proof = FriProof<Fp, <Sha256HashFn as HashFn>::Digest, Tree>;
+
+const MAX_POLY_DEGREE: usize = 63;
+const OPTIONS: FriOptions = FriOptions::new(4, 4, 8);
+
+fn public_coin() -> PublicCoinImpl<Fp, Sha256HashFn> {
+    PublicCoinImpl::new(Sha256HashFn::hash_chunks([b"fri".as_slice()]))
+}
+
+/// Returns bit-reversed evaluations of a random low degree polynomial over
+/// the LDE domain
+fn gen_evaluations(rng: &mut impl ark_std::rand::Rng) -> Vec<Fp> {
+    let coeffs = (0..=MAX_POLY_DEGREE)
+        .map(|_| Fp::rand(rng))
+        .collect::<Vec<Fp>>();
+    let domain_size = (MAX_POLY_DEGREE + 1) * OPTIONS.blowup_factor();
+    let domain = Radix2EvaluationDomain::new_coset(domain_size, Fp::GENERATOR).unwrap();
+    let mut evaluations = domain.fft(&coeffs);
+    bit_reverse(&mut evaluations);
+    evaluations
+}
+
+/// Proves a random low degree polynomial. Returns the query positions, the
+/// polynomial's evaluations at those positions and the proof.
+fn prove() -> (Vec<usize>, Vec<Fp>, Proof) {
+    let mut rng = ark_std::test_rng();
+    let evaluations = gen_evaluations(&mut rng);
+    let domain_size = evaluations.len();
+
+    let mut public_coin = public_coin();
+    let mut prover = FriProver::<Fp, _, Tree>::new(OPTIONS);
+    let mut channel = PublicCoinChannel::new(&mut public_coin);
+    prover.build_layers(&mut channel, evaluations.to_vec_in(GpuAllocator));
+    let positions = public_coin
+        .draw_queries(8, domain_size)
+        .into_iter()
+        .collect::<Vec<usize>>();
+    let queried = positions.iter().map(|&p| evaluations[p]).collect();
+    (positions, queried, prover.into_proof(&positions))
+}
+
+fn verify(proof: Proof, positions: &[usize], evaluations: &[Fp]) -> Result<(), VerificationError> {
+    let mut public_coin = public_coin();
+    fri::verify(
+        OPTIONS,
+        &mut public_coin,
+        proof,
+        MAX_POLY_DEGREE,
+        positions,
+        evaluations,
+    )
+}
+
+#[test]
+fn standalone_verifier_accepts_low_degree_polynomial() {
+    let (positions, queried, proof) = prove();
+
+    assert_eq!(Ok(()), verify(proof, &positions, &queried));
+}
+
+#[test]
+fn standalone_verifier_rejects_invalid_proofs() {
+    let (positions, queried, proof) = prove();
+    let mut wrong_evaluations = queried.clone();
+    wrong_evaluations[0] += Fp::from(1u8);
+    let mut missing_layer = proof.clone();
+    missing_layer.layers.pop();
+
+    assert!(verify(proof, &positions, &wrong_evaluations).is_err());
+    assert_eq!(
+        Err(VerificationError::LayerCountMismatch {
+            expected: 2,
+            actual: 1,
+        }),
+        verify(missing_layer, &positions, &queried)
+    );
+}
+
+#[test]
+fn batched_layers_round_trip() {
+    let mut rng = ark_std::test_rng();
+    let evaluations = [gen_evaluations(&mut rng), gen_evaluations(&mut rng)];
+    let domain_size = evaluations[0].len();
+    let mut public_coin = public_coin();
+    let batching_coeff = public_coin.draw();
+    let mut prover = FriProver::<Fp, _, Tree>::new(OPTIONS);
+    let mut channel = PublicCoinChannel::new(&mut public_coin);
+    let gpu_evaluations = evaluations.map(|evals| evals.to_vec_in(GpuAllocator));
+    prover.build_batched_layers(&mut channel, &gpu_evaluations, batching_coeff);
+    let positions = public_coin
+        .draw_queries(8, domain_size)
+        .into_iter()
+        .collect::<Vec<usize>>();
+    let queried = evaluations
+        .iter()
+        .map(|evals| positions.iter().map(|&p| evals[p]).collect())
+        .collect::<Vec<Vec<Fp>>>();
+    let proof = prover.into_proof(&positions);
+    let verify = |queried: &[Vec<Fp>]| {
+        let mut public_coin = public_coin();
+        let batching_coeff = public_coin.draw();
+        let proof = proof.clone();
+        let verifier = FriVerifier::new(&mut public_coin, OPTIONS, proof, MAX_POLY_DEGREE)?;
+        verifier.verify_batched(&positions, queried, batching_coeff)
+    };
+    let mut swapped = queried.clone();
+    swapped.reverse();
+
+    assert_eq!(Ok(()), verify(&queried));
+    assert!(verify(&swapped).is_err());
+}
+
+/// Without the `gpu` feature the shard of each device is folded on the host
+#[cfg(not(feature = "gpu"))]
+#[test]
+fn sharded_layers_match_single_device_layers() {
+    let mut rng = ark_std::test_rng();
+    let evaluations = gen_evaluations(&mut rng);
+    let build_layers = |devices: &[Device]| {
+        let mut public_coin = public_coin();
+        let mut prover = FriProver::<Fp, _, Tree>::new(OPTIONS).with_devices(devices);
+        let mut channel = PublicCoinChannel::new(&mut public_coin);
+        prover.build_layers(&mut channel, evaluations.to_vec_in(GpuAllocator));
+        prover
+    };
+    let devices = (0..4).map(Device::new).collect::<Vec<Device>>();
+
+    let single_device = build_layers(&devices[..1]);
+    let sharded = build_layers(&devices);
+
+    let single_device_layers = single_device.layer_evaluations();
+    let sharded_layers = sharded.layer_evaluations();
+    assert_eq!(single_device_layers.len(), sharded_layers.len());
+    for (expected, actual) in single_device_layers.iter().zip(&sharded_layers) {
+        assert_eq!(expected.0, actual.0);
+    }
+    assert_eq!(single_device.remainder_coeffs(), sharded.remainder_coeffs());
+}
@@ -0,0 +1,62 @@
+#![feature(allocator_api)]
+mod common;
+
+use ark_serialize::CanonicalSerialize;
+use common::gen_trace;
+use common::FibClaim;
+use common::FibTrace;
+use common::OPTIONS;
+use common::TRACE_LEN;
+use ministark::channel::ProverChannel;
+use ministark::fri;
+use ministark::interactive::InteractiveProver;
+use ministark::random::PublicCoin;
+use ministark::random::SubCoin;
+use ministark::reference::differential_verify;
+use ministark::stark::Stark;
+
+#[test]
+fn interactive_run_matches_proof() {
+    let (claim, trace) = gen_trace();
+    let proof = pollster::block_on(claim.prove(OPTIONS, FibTrace(trace.clone()))).unwrap();
+    let air = claim.air(TRACE_LEN, OPTIONS).unwrap();
+    let mut channel = ProverChannel::<FibClaim>::new(&air, claim.gen_public_coin(&air));
+    let mut prover = InteractiveProver::<FibClaim>::new(&air, FibTrace(trace));
+
+    // supply the verifier's randomness from the same transcript as the prover
+    channel.commit_base_trace(prover.commit_base_trace());
+    let num_challenges = air.num_challenges();
+    let challenges =
+        channel.draw_challenges(SubCoin::TraceExtension, "air challenges", num_challenges);
+    if let Some(commitment) = prover.commit_extension_trace(challenges) {
+        channel.commit_extension_trace(commitment);
+    }
+    let num_coeffs = air.num_composition_constraint_coeffs();
+    let composition_coeffs =
+        channel.draw_challenges(SubCoin::Composition, "composition coefficients", num_coeffs);
+    channel.commit_composition_trace(prover.commit_composition_trace(composition_coeffs));
+    let z = channel.get_ood_point();
+    let (execution_trace_oods, composition_trace_oods) = prover.evaluate_ood(z);
+    channel.send_ood_evals(execution_trace_oods, composition_trace_oods);
+    let deep_coeffs = claim.gen_deep_coeffs(&mut channel.public_coin.fork(SubCoin::Deep), &air);
+    let (_, remainder_coeffs) = prover
+        .commit_fri_layers(deep_coeffs, |commitment| {
+            fri::ProverChannel::commit_fri_layer(&mut channel, commitment.clone());
+            fri::ProverChannel::draw_fri_alpha(&mut channel)
+        })
+        .unwrap();
+    fri::ProverChannel::commit_remainder(&mut channel, &remainder_coeffs);
+    channel.grind_fri_commitments();
+    let query_positions = channel.get_fri_query_positions();
+    let (queries, fri_proof) = prover.open(&query_positions);
+    let interactive_proof = channel.build_proof(queries, fri_proof);
+
+    let mut bytes = Vec::new();
+    let mut interactive_bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    interactive_proof
+        .serialize_compressed(&mut interactive_bytes)
+        .unwrap();
+    assert_eq!(bytes, interactive_bytes);
+    assert!(differential_verify(&claim, interactive_proof, 1).is_ok());
+}
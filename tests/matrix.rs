@@ -0,0 +1,25 @@
+#![feature(allocator_api)]
+use ark_ff::UniformRand;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ministark::utils::GpuAllocator;
+use ministark::Matrix;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+#[test]
+fn coset_evaluation_matches_lde() {
+    let n = 64;
+    let mut rng = ark_std::test_rng();
+    let polys = Matrix::new(
+        (0..3)
+            .map(|_| (0..n).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>())
+            .map(|col| col.to_vec_in(GpuAllocator))
+            .collect(),
+    );
+    let offset = Fp::from(7u64);
+    let lde_domain = Radix2EvaluationDomain::<Fp>::new_coset(n * 8, offset).unwrap();
+
+    let expected = polys.bit_reversed_evaluate(lde_domain);
+
+    assert_eq!(expected.0, polys.bit_reversed_coset_evaluate(lde_domain).0);
+}
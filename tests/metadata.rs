@@ -0,0 +1,44 @@
+#![feature(allocator_api)]
+mod common;
+
+use common::gen_trace;
+use common::FibTrace;
+use common::OPTIONS;
+use ministark::debug::assert_golden;
+use ministark::hash::Sha256HashFn;
+use ministark::prover::default_prove_with_metadata;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::random::SubCoin;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+#[test]
+fn transcript_matches_golden_file() {
+    let (claim, trace) = gen_trace();
+
+    let (_, metadata) = default_prove_with_metadata(&claim, OPTIONS, FibTrace(trace)).unwrap();
+
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/golden/fib_transcript.txt"
+    );
+    assert_golden(path, &metadata.to_string());
+}
+
+#[test]
+fn challenges_are_drawn_from_sub_coins() {
+    let (claim, trace) = gen_trace();
+    let public_coin = PublicCoinImpl::<Fp, Sha256HashFn>::new(Default::default());
+
+    let (_, metadata) = default_prove_with_metadata(&claim, OPTIONS, FibTrace(trace)).unwrap();
+
+    for sub_coin in SubCoin::ALL {
+        assert!(metadata.sub_coin_challenges(sub_coin).next().is_some());
+    }
+    let ood_point = metadata.sub_coin_challenges(SubCoin::Deep).next().unwrap();
+    assert_eq!("ood point", ood_point.label);
+    assert_ne!(
+        public_coin.fork(SubCoin::Composition).draw(),
+        public_coin.fork(SubCoin::Deep).draw()
+    );
+}
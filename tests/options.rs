@@ -0,0 +1,72 @@
+#![feature(allocator_api)]
+mod common;
+
+use ark_serialize::CanonicalSerialize;
+use common::gen_proof;
+use common::gen_trace;
+use common::FibAirConfig;
+use common::FibClaim;
+use common::FibTrace;
+use common::OPTIONS;
+use common::TRACE_LEN;
+use ministark::prover::Throughput;
+use ministark::reference::differential_verify;
+use ministark::stark::Stark;
+use ministark::verifier::VerifierCost;
+use ministark::Air;
+use ministark::ProofSizeError;
+
+#[test]
+fn compact_proofs_are_smaller_and_verify() {
+    let (claim, trace) = gen_trace();
+    let options = OPTIONS.with_compact(true);
+    let (_, proof) = gen_proof();
+
+    let compact_proof = pollster::block_on(claim.prove(options, FibTrace(trace))).unwrap();
+
+    assert!(compact_proof.compressed_size() < proof.compressed_size());
+    assert!(differential_verify(&claim, compact_proof, 1).is_ok());
+}
+
+#[test]
+fn cost_estimate_matches_proof_shape() {
+    let (claim, proof) = gen_proof();
+
+    let estimate = claim.estimate_cost(TRACE_LEN, OPTIONS).unwrap();
+    let throughput = Throughput::measure::<FibClaim>(10);
+
+    assert_eq!(
+        proof.composition_trace_ood_evals.len(),
+        estimate.num_composition_columns
+    );
+    assert_eq!(proof.fri_proof.layers.len(), estimate.num_fri_layers);
+    assert!(throughput.butterflies_per_second > 0.0);
+    assert!(throughput.hashes_per_second > 0.0);
+}
+
+#[test]
+fn tuned_options_meet_proof_size_target() {
+    let (claim, trace) = gen_trace();
+    let air = Air::<FibAirConfig>::new(TRACE_LEN, claim.0, OPTIONS);
+    let fastest_options = OPTIONS
+        .target_max_proof_size::<FibClaim>(&air, usize::MAX, 40)
+        .unwrap();
+    let max_proof_size = VerifierCost::estimate::<FibClaim>(&air, fastest_options).proof_bytes - 1;
+
+    let options = OPTIONS
+        .target_max_proof_size::<FibClaim>(&air, max_proof_size, 40)
+        .unwrap();
+    let proof = pollster::block_on(claim.prove(options, FibTrace(trace))).unwrap();
+
+    assert!(options.grinding_factor > fastest_options.grinding_factor);
+    assert!(proof.compressed_size() <= max_proof_size);
+    assert!(differential_verify(&claim, proof, 40).is_ok());
+    assert!(matches!(
+        OPTIONS.target_max_proof_size::<FibClaim>(&air, 1000, 40),
+        Err(ProofSizeError::ProofTooLarge { .. })
+    ));
+    assert!(matches!(
+        OPTIONS.target_max_proof_size::<FibClaim>(&air, usize::MAX, 60),
+        Err(ProofSizeError::InsufficientSecurity { .. })
+    ));
+}
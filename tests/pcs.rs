@@ -0,0 +1,68 @@
+#![feature(allocator_api)]
+use ark_ff::UniformRand;
+use ministark::fri::FriOptions;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::pcs::FriPcs;
+use ministark::pcs::PcsError;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::utils::GpuAllocator;
+use ministark::Matrix;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+const NUM_ROWS: usize = 32;
+
+type Pcs = FriPcs<Fp, <Sha256HashFn as HashFn>::Digest, MatrixMerkleTreeImpl<Sha256HashFn>>;
+
+fn public_coin() -> PublicCoinImpl<Fp, Sha256HashFn> {
+    PublicCoinImpl::new(Sha256HashFn::hash_chunks([b"pcs".as_slice()]))
+}
+
+#[test]
+fn opens_committed_polynomials_at_points() {
+    let mut rng = ark_std::test_rng();
+    let constant = vec![Fp::from(5u8); NUM_ROWS];
+    let random = (0..NUM_ROWS)
+        .map(|_| Fp::rand(&mut rng))
+        .collect::<Vec<Fp>>();
+    let evaluations = Matrix::new(vec![
+        constant.to_vec_in(GpuAllocator),
+        random.to_vec_in(GpuAllocator),
+    ]);
+    let points = [Fp::rand(&mut rng), Fp::rand(&mut rng)];
+    let pcs = Pcs::new(FriOptions::new(4, 2, 4), 20);
+
+    let (commitment, data) = pcs.commit(evaluations);
+    let opening = pcs.open(&data, &points, &mut public_coin());
+    let mut wrong_opening = opening.clone();
+    wrong_opening.values[1][1] += Fp::from(1u8);
+    let verify = |opening| pcs.verify(&commitment, NUM_ROWS, &points, opening, &mut public_coin());
+
+    assert!(opening.values.iter().all(|v| v[0] == Fp::from(5u8)));
+    verify(opening).unwrap();
+    assert!(verify(wrong_opening).is_err());
+}
+
+#[test]
+fn rejects_invalid_num_rows() {
+    let mut rng = ark_std::test_rng();
+    let evaluations = Matrix::new(vec![vec![Fp::from(5u8); NUM_ROWS].to_vec_in(GpuAllocator)]);
+    let points = [Fp::rand(&mut rng)];
+    let pcs = Pcs::new(FriOptions::new(4, 2, 4), 20);
+
+    let (commitment, data) = pcs.commit(evaluations);
+    let opening = pcs.open(&data, &points, &mut public_coin());
+
+    for num_rows in [0, NUM_ROWS - 1] {
+        let result = pcs.verify(
+            &commitment,
+            num_rows,
+            &points,
+            opening.clone(),
+            &mut public_coin(),
+        );
+        assert!(matches!(result, Err(PcsError::InvalidNumPolyRows { .. })));
+    }
+}
@@ -0,0 +1,43 @@
+#![cfg(feature = "serde")]
+use ministark::profile::ProofProfile;
+use ministark::ProofOptions;
+
+fn profile() -> ProofProfile {
+    ProofProfile {
+        options: ProofOptions::new(32, 8, 16, 4, 16),
+        base_field: "Goldilocks".into(),
+        extension_field: "Goldilocks^3".into(),
+        merkle_tree_hash: "sha256".into(),
+        public_coin_hash: "sha256".into(),
+    }
+}
+
+#[test]
+fn profile_roundtrips_through_json() {
+    let profile = profile();
+
+    let json = serde_json::to_string(&profile).unwrap();
+
+    assert_eq!(profile, serde_json::from_str(&json).unwrap());
+}
+
+#[test]
+fn profile_loads_from_toml() {
+    let config = r#"
+        base_field = "Goldilocks"
+        extension_field = "Goldilocks^3"
+        merkle_tree_hash = "sha256"
+        public_coin_hash = "sha256"
+
+        [options]
+        num_queries = 32
+        lde_blowup_factor = 8
+        grinding_factor = 16
+        fri_folding_factor = 4
+        fri_max_remainder_coeffs = 16
+    "#;
+
+    let loaded: ProofProfile = toml::from_str(config).unwrap();
+
+    assert_eq!(profile(), loaded);
+}
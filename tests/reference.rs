@@ -0,0 +1,75 @@
+#![feature(allocator_api)]
+mod common;
+
+use ark_ff::One;
+use common::gen_proof;
+use common::gen_trace;
+use common::FibAirConfig;
+use common::FibClaim;
+use common::OPTIONS;
+use common::TRACE_LEN;
+use ministark::challenges::Challenges;
+use ministark::debug::constraint_violations;
+use ministark::debug::explain_verification;
+use ministark::reference::differential_verify;
+use ministark::Air;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+#[test]
+fn verifiers_accept_valid_proof() {
+    let (claim, proof) = gen_proof();
+
+    assert!(differential_verify(&claim, proof, 1).is_ok());
+}
+
+#[test]
+fn verifiers_reject_invalid_ood_evaluation() {
+    let (claim, mut proof) = gen_proof();
+    proof.execution_trace_ood_evals[0] += Fp::one();
+
+    assert!(differential_verify(&claim, proof, 1).is_err());
+}
+
+#[test]
+fn verifiers_reject_invalid_trace_query() {
+    let (claim, mut proof) = gen_proof();
+    proof.trace_queries.openings[0].base_trace_row[0] += Fp::one();
+
+    assert!(differential_verify(&claim, proof, 1).is_err());
+}
+
+#[test]
+fn verifiers_reject_wrong_claim() {
+    let (claim, proof) = gen_proof();
+    let wrong_claim = <FibClaim>::new(claim.0 + Fp::one());
+
+    assert!(differential_verify(&wrong_claim, proof, 1).is_err());
+}
+
+#[test]
+fn explain_reports_merkle_root_mismatch() {
+    let (claim, mut proof) = gen_proof();
+    proof.trace_queries.openings[0].base_trace_row[0] += Fp::one();
+
+    let report = explain_verification(&claim, proof, 1);
+
+    assert!(report.error.is_some());
+    let check = report.merkle_roots.last().unwrap();
+    assert_eq!(check.label, "base trace");
+    assert!(!check.is_valid());
+    assert!(check.actual.is_some());
+}
+
+#[test]
+fn violations_are_reported_on_broken_rows() {
+    let (claim, mut trace) = gen_trace();
+    trace.0[0][10] += Fp::one();
+    let air = Air::<FibAirConfig>::new(TRACE_LEN, claim.0, OPTIONS);
+    let challenges = Challenges::new(Vec::new());
+    let hints = air.gen_hints(&challenges);
+
+    let violations = constraint_violations(&air, &challenges, &hints, &trace, None);
+
+    let expected: [&[usize]; 5] = [&[], &[], &[9], &[10], &[]];
+    assert_eq!(expected.to_vec(), violations);
+}
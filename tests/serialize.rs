@@ -0,0 +1,53 @@
+use ark_ff::UniformRand;
+use ministark::hash::BigEndianLeafEncoder;
+use ministark::hash::ElementHashFn;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::serialize::from_bytes;
+use ministark::serialize::to_bytes_be;
+use ministark::serialize::to_bytes_le;
+use ministark::serialize::ByteOrder;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use ministark_gpu::fields::p18446744069414584321::ark::Fq3;
+
+#[test]
+fn big_endian_bytes_reverse_each_coefficient() {
+    let element = Fp::from(0x0102_0304_0506_0708u64);
+
+    assert_eq!([8, 7, 6, 5, 4, 3, 2, 1], *to_bytes_le(&element));
+    assert_eq!([1, 2, 3, 4, 5, 6, 7, 8], *to_bytes_be(&element));
+}
+
+#[test]
+fn extension_field_bytes_round_trip() {
+    let mut rng = ark_std::test_rng();
+    let element = Fq3::rand(&mut rng);
+
+    let le = to_bytes_le(&element);
+    let be = to_bytes_be(&element);
+
+    assert_eq!(element, from_bytes(&le, ByteOrder::Little).unwrap());
+    assert_eq!(element, from_bytes(&be, ByteOrder::Big).unwrap());
+    assert!(from_bytes::<Fq3>(&le[1..], ByteOrder::Little).is_err());
+}
+
+#[test]
+fn public_coin_absorbs_elements_in_its_byte_order() {
+    let element = Fp::from(0x0102_0304_0506_0708u64);
+    let seed = Sha256HashFn::hash([]);
+    let mut le_coin = PublicCoinImpl::<Fp, Sha256HashFn>::new(seed.clone());
+    let mut be_coin = PublicCoinImpl::<Fp, Sha256HashFn, BigEndianLeafEncoder>::new(seed.clone());
+
+    le_coin.reseed_with_field_elements(&[element]);
+    be_coin.reseed_with_field_elements(&[element]);
+
+    let be_digest = Sha256HashFn::hash_elements_in_order([element], ByteOrder::Big);
+    assert_eq!(Sha256HashFn::merge(&seed, &be_digest), be_coin.seed);
+    assert_ne!(le_coin.seed, be_coin.seed);
+    assert_eq!(
+        ByteOrder::Big,
+        <PublicCoinImpl<Fp, Sha256HashFn, BigEndianLeafEncoder>>::byte_order()
+    );
+}
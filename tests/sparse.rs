@@ -0,0 +1,85 @@
+mod common;
+
+use ark_ff::One;
+use ark_ff::Zero;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use common::gen_trace;
+use common::FibClaim;
+use common::OPTIONS;
+use ministark::reference::differential_verify;
+use ministark::sparse::RleColumn;
+use ministark::sparse::RleMatrix;
+use ministark::stark::Stark;
+use ministark::Matrix;
+use ministark::Trace;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use std::borrow::Cow;
+
+/// Fibonacci trace that only stores run-length encoded columns
+struct RleFibTrace(RleMatrix<Fp>);
+
+impl Trace for RleFibTrace {
+    type Fp = Fp;
+    type Fq = Fp;
+
+    fn len(&self) -> usize {
+        self.0.num_rows()
+    }
+
+    fn num_base_columns(&self) -> usize {
+        self.0.num_cols()
+    }
+
+    fn base_columns(&self) -> Option<&Matrix<Fp>> {
+        None
+    }
+
+    fn base_column(&self, column: usize) -> Cow<'_, [Fp]> {
+        Cow::Owned(self.0 .0[column].expand().to_vec())
+    }
+
+    fn interpolate_base_columns(&self, domain: Radix2EvaluationDomain<Fp>) -> Matrix<Fp> {
+        self.0.interpolate(domain)
+    }
+}
+
+#[test]
+fn rle_column_merges_runs() {
+    let mut column = [Fp::one(), Fp::one(), Fp::zero()]
+        .into_iter()
+        .collect::<RleColumn<Fp>>();
+    column.resize(64, Fp::zero());
+
+    assert_eq!(64, column.len());
+    assert_eq!(&[(Fp::one(), 2), (Fp::zero(), 62)], column.runs());
+    assert_eq!(Some(Fp::zero()), column.get(2));
+    assert_eq!(None, column.get(64));
+}
+
+#[test]
+fn rle_interpolation_matches_dense_interpolation() {
+    let n = 64;
+    let domain = Radix2EvaluationDomain::<Fp>::new(n).unwrap();
+    let mut counter = (0..10u64).map(Fp::from).collect::<RleColumn<Fp>>();
+    counter.resize(n, Fp::from(9u64));
+    let mut constant = RleColumn::new();
+    constant.push_run(Fp::from(7u64), n);
+    let sparse = RleMatrix::new(vec![counter, constant]);
+
+    let expected = sparse.expand().interpolate(domain);
+
+    assert_eq!(expected.0, sparse.interpolate(domain).0);
+}
+
+#[test]
+fn rle_trace_proof_verifies() {
+    let (claim, trace) = gen_trace();
+    let columns = trace.iter().map(|column| column.iter().copied().collect());
+    let trace = RleFibTrace(RleMatrix::new(columns.collect()));
+    let claim = FibClaim::<RleFibTrace>::new(claim.0);
+
+    let proof = pollster::block_on(claim.prove(OPTIONS, trace)).unwrap();
+
+    assert!(differential_verify(&claim, proof, 1).is_ok());
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "experimental")]
+use ark_ff::One;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::random::PublicCoin;
+use ministark::random::PublicCoinImpl;
+use ministark::sumcheck::prove_zerocheck;
+use ministark::sumcheck::verify_zerocheck;
+use ministark::sumcheck::SumcheckError;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+const NUM_VARS: usize = 4;
+
+fn public_coin() -> PublicCoinImpl<Fp, Sha256HashFn> {
+    PublicCoinImpl::new(Sha256HashFn::hash_chunks([b"zerocheck".as_slice()]))
+}
+
+// column 0 is boolean and column 1 is the complement of column 0
+fn constraint(row: &[Fp]) -> Fp {
+    row[0] * (row[0] - Fp::one()) + row[0] + row[1] - Fp::one()
+}
+
+fn columns() -> Vec<Vec<Fp>> {
+    let bits = (0..1u64 << NUM_VARS).map(|i| Fp::from(i.count_ones() % 2));
+    let complements = bits.clone().map(|bit| Fp::one() - bit);
+    vec![bits.collect(), complements.collect()]
+}
+
+#[test]
+fn zerocheck_accepts_satisfied_constraint() {
+    let proof = prove_zerocheck(columns(), 2, constraint, &mut public_coin());
+
+    let point = verify_zerocheck(&proof, NUM_VARS, 2, constraint, &mut public_coin());
+
+    assert_eq!(point.unwrap().len(), NUM_VARS);
+}
+
+#[test]
+fn zerocheck_rejects_violated_constraint() {
+    let mut columns = columns();
+    columns[1][5] = Fp::one() - columns[1][5];
+    let proof = prove_zerocheck(columns, 2, constraint, &mut public_coin());
+
+    let result = verify_zerocheck(&proof, NUM_VARS, 2, constraint, &mut public_coin());
+
+    assert!(matches!(
+        result,
+        Err(SumcheckError::RoundSumMismatch { round: 0 })
+    ));
+}
@@ -0,0 +1,88 @@
+#![feature(allocator_api)]
+use ark_ff::UniformRand;
+use ministark::challenges::Challenges;
+use ministark::trace::ExtensionColumn;
+use ministark::trace::ExtensionColumnError;
+use ministark::trace::ExtensionColumnGraph;
+use ministark::utils::GpuAllocator;
+use ministark::utils::GpuVec;
+use ministark::Matrix;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+const NUM_ROWS: usize = 8;
+
+type Column = ExtensionColumn<'static, Fp, Fp>;
+
+fn column(extension_columns: impl IntoIterator<Item = usize>) -> Column {
+    Column::new(|_| GpuVec::new_in(GpuAllocator)).with_extension_columns(extension_columns)
+}
+
+#[test]
+fn independent_extension_columns_share_a_level() {
+    let mut rng = ark_std::test_rng();
+    let base_columns = Matrix::new(
+        (0..2)
+            .map(|_| {
+                let column = (0..NUM_ROWS).map(|_| Fp::rand(&mut rng));
+                column.collect::<Vec<Fp>>().to_vec_in(GpuAllocator)
+            })
+            .collect(),
+    );
+    let challenges = Challenges::new(vec![Fp::rand(&mut rng), Fp::rand(&mut rng)]);
+    let scaled = Column::new(|inputs| {
+        let alpha = inputs.challenge(0);
+        let values = inputs.base_column(0).iter().map(|&v| v * alpha);
+        values.collect::<Vec<Fp>>().to_vec_in(GpuAllocator)
+    })
+    .with_base_columns([0])
+    .with_challenges([0]);
+    let sum = Column::new(|inputs| {
+        let scaled = inputs.extension_column(0);
+        let base = inputs.base_column(1);
+        let values = scaled.iter().zip(base).map(|(&a, &b)| a + b);
+        values.collect::<Vec<Fp>>().to_vec_in(GpuAllocator)
+    })
+    .with_base_columns([1])
+    .with_extension_columns([0]);
+    let other = Column::new(|inputs| {
+        let beta = inputs.challenge(1);
+        let values = inputs.base_column(1).iter().map(|&v| v * beta);
+        values.collect::<Vec<Fp>>().to_vec_in(GpuAllocator)
+    })
+    .with_base_columns([1])
+    .with_challenges([1]);
+
+    let graph = ExtensionColumnGraph::new(vec![scaled, sum, other]).unwrap();
+    let extension_columns = graph.build(&base_columns, &challenges);
+
+    assert_eq!([vec![0, 2], vec![1]], graph.levels());
+    for row in 0..NUM_ROWS {
+        let scaled = base_columns[0][row] * challenges[0];
+        assert_eq!(scaled, extension_columns[0][row]);
+        assert_eq!(scaled + base_columns[1][row], extension_columns[1][row]);
+        assert_eq!(
+            base_columns[1][row] * challenges[1],
+            extension_columns[2][row]
+        );
+    }
+}
+
+#[test]
+fn invalid_dependencies_are_rejected() {
+    let unknown = ExtensionColumnGraph::new(vec![column([]), column([2])]);
+    let cyclic = ExtensionColumnGraph::new(vec![column([]), column([2]), column([1])]);
+
+    assert_eq!(
+        Some(ExtensionColumnError::UnknownDependency {
+            column: 1,
+            dependency: 2,
+        }),
+        unknown.err()
+    );
+    assert_eq!(
+        Some(ExtensionColumnError::CyclicDependencies {
+            columns: vec![1, 2],
+        }),
+        cyclic.err()
+    );
+}
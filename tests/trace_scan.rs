@@ -0,0 +1,55 @@
+#![feature(allocator_api)]
+mod common;
+
+use ark_ff::BigInt;
+use ark_ff::One;
+use ark_ff::Zero;
+use common::gen_trace;
+use common::FibTrace;
+use common::OPTIONS;
+use common::TRACE_LEN;
+use core::marker::PhantomData;
+use ministark::context::ProverContext;
+use ministark::debug::scan_trace;
+use ministark::debug::TraceWarning;
+use ministark::prover::ProvingError;
+use ministark::reference::differential_verify;
+use ministark::stark::Stark;
+use ministark::utils::GpuAllocator;
+use ministark::Matrix;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+#[test]
+fn trace_scan_reports_constant_columns() {
+    let (claim, trace) = gen_trace();
+    let zeros = vec![Fp::zero(); TRACE_LEN].to_vec_in(GpuAllocator);
+    let ones = vec![Fp::one(); TRACE_LEN].to_vec_in(GpuAllocator);
+    let suspicious_trace = Matrix::new(vec![trace.0[0].clone(), zeros, ones]);
+    let context = ProverContext::new().with_trace_scan(true);
+
+    let warnings = scan_trace(&suspicious_trace);
+    let proof = pollster::block_on(claim.prove_with_context(&context, OPTIONS, FibTrace(trace)));
+
+    assert_eq!(
+        vec![
+            TraceWarning::ZeroColumn { column: 1 },
+            TraceWarning::ConstantColumn { column: 2 }
+        ],
+        warnings
+    );
+    assert!(differential_verify(&claim, proof.unwrap(), 1).is_ok());
+}
+
+#[test]
+fn trace_scan_rejects_non_canonical_values() {
+    let (claim, mut trace) = gen_trace();
+    trace.0[1][5] = ark_ff::Fp(BigInt([u64::MAX]), PhantomData);
+    let context = ProverContext::new().with_trace_scan(true);
+
+    let result = pollster::block_on(claim.prove_with_context(&context, OPTIONS, FibTrace(trace)));
+
+    assert!(matches!(
+        result,
+        Err(ProvingError::NonCanonicalTraceValue { column: 1, row: 5 })
+    ));
+}
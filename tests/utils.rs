@@ -0,0 +1,55 @@
+use ark_ff::Field;
+use ark_ff::One;
+use ark_ff::UniformRand;
+use ark_ff::Zero;
+use ministark::utils::barycentric_evaluate;
+use ministark::utils::barycentric_weights;
+use ministark::utils::batch_inverse;
+use ministark::utils::horner_evaluate;
+use ministark::utils::horner_evaluate_many;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+#[test]
+fn batch_inverse_matches_inverse() {
+    let mut rng = ark_std::test_rng();
+    let mut values = (0..37).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+    values[0] = Fp::zero();
+    values[9] = Fp::zero();
+    let expected = values
+        .iter()
+        .map(|v| v.inverse().unwrap_or_else(Fp::zero))
+        .collect::<Vec<Fp>>();
+
+    batch_inverse(&mut values);
+
+    assert_eq!(expected, values);
+}
+
+#[test]
+fn horner_evaluate_many_matches_horner_evaluate() {
+    let mut rng = ark_std::test_rng();
+    let coeffs = (0..16).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+    let points = (0..5).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+    let expected = points
+        .iter()
+        .map(|point| horner_evaluate(&coeffs, point))
+        .collect::<Vec<Fp>>();
+
+    assert_eq!(expected, horner_evaluate_many(&coeffs, &points));
+}
+
+#[test]
+fn barycentric_evaluate_matches_horner_evaluate() {
+    let mut rng = ark_std::test_rng();
+    let coeffs = (0..8).map(|_| Fp::rand(&mut rng)).collect::<Vec<Fp>>();
+    let xs = (0..8).map(|i| Fp::from(3u64).pow([i])).collect::<Vec<Fp>>();
+    let ys = horner_evaluate_many(&coeffs, &xs);
+    let weights = barycentric_weights(&xs);
+    let z = Fp::rand(&mut rng);
+
+    assert_eq!(
+        horner_evaluate(&coeffs, &z),
+        barycentric_evaluate(&xs, &weights, &ys, z)
+    );
+    assert_eq!(ys[0], barycentric_evaluate(&xs, &weights, &ys, Fp::one()));
+}
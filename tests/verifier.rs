@@ -0,0 +1,201 @@
+#![feature(allocator_api)]
+mod common;
+
+use ark_ff::One;
+use ark_serialize::CanonicalDeserialize;
+use ark_serialize::CanonicalSerialize;
+use ark_serialize::Compress;
+use ark_serialize::SerializationError;
+use ark_serialize::Validate;
+use common::gen_proof;
+use common::gen_trace;
+use common::FibAirConfig;
+use common::FibClaim;
+use common::FibSumAirConfig;
+use common::FibSumTrace;
+use common::OPTIONS;
+use common::TRACE_LEN;
+use ministark::air::AirVersion;
+use ministark::reference::differential_verify;
+use ministark::stark::Stark;
+use ministark::trace::LazyQueries;
+use ministark::trace::TraceOpenings;
+use ministark::verifier::verify_batch;
+use ministark::verifier::verify_batch_from_reader;
+use ministark::verifier::BatchVerificationError;
+use ministark::verifier::VerificationError;
+use ministark::verifier::VerifierCost;
+use ministark::Air;
+use ministark::Proof;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+
+#[test]
+fn batch_verification_reports_invalid_proof() {
+    let (claim, proof) = gen_proof();
+    let mut invalid_proof = proof.clone();
+    invalid_proof.execution_trace_ood_evals[0] += Fp::one();
+
+    let valid_batch = vec![(&claim, proof.clone()), (&claim, proof.clone())];
+    let invalid_batch = vec![(&claim, proof), (&claim, invalid_proof)];
+
+    assert_eq!(2, verify_batch(valid_batch, 1).unwrap().len());
+    assert!(matches!(
+        verify_batch(invalid_batch, 1),
+        Err(BatchVerificationError::InvalidProof { index: 1, .. })
+    ));
+}
+
+#[test]
+fn batch_verification_reports_invalid_merkle_proof() {
+    let (claim, proof) = gen_proof();
+    let mut invalid_proof = proof.clone();
+    let queries = &mut invalid_proof.trace_queries;
+    queries.base_trace_proof = queries.composition_trace_proof.clone();
+
+    let invalid_batch = vec![(&claim, invalid_proof), (&claim, proof)];
+
+    assert!(matches!(
+        verify_batch(invalid_batch, 1),
+        Err(BatchVerificationError::InvalidProof { index: 0, .. })
+    ));
+}
+
+#[test]
+fn verifier_cost_bounds_proof_size() {
+    let (claim, proof) = gen_proof();
+    let air = Air::<FibAirConfig>::new(TRACE_LEN, claim.0, OPTIONS);
+
+    let cost = VerifierCost::estimate::<FibClaim>(&air, OPTIONS);
+
+    assert!(proof.compressed_size() <= cost.proof_bytes);
+    assert!(cost.num_merkle_hashes > 0);
+}
+
+#[test]
+fn openings_must_match_the_trace_shape() {
+    let (claim, proof) = gen_proof();
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    let proof = Proof::<FibClaim>::deserialize_compressed(&*bytes).unwrap();
+    let mut invalid_proof = proof.clone();
+    invalid_proof.trace_queries.openings[0].base_trace_row.pop();
+
+    assert!(invalid_proof.serialize_compressed(Vec::new()).is_err());
+    assert!(differential_verify(&claim, invalid_proof, 1).is_err());
+    assert!(differential_verify(&claim, proof, 1).is_ok());
+}
+
+#[test]
+fn verifiers_reject_missing_extension_trace_commitment() {
+    let (claim, trace) = gen_trace();
+    let claim = FibClaim::<FibSumTrace, FibSumAirConfig>::new(claim.0);
+    let mut proof = pollster::block_on(claim.prove(OPTIONS, FibSumTrace(trace))).unwrap();
+    assert!(differential_verify(&claim, proof.clone(), 1).is_ok());
+
+    proof.extension_trace_commitment = None;
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+
+    assert!(matches!(
+        claim.verify(proof.clone(), 1),
+        Err(VerificationError::ExtensionTraceCommitmentMismatch { .. })
+    ));
+    assert!(matches!(
+        claim.verify_from_reader(bytes.as_slice(), Compress::Yes, 1),
+        Err(VerificationError::ExtensionTraceCommitmentMismatch { .. })
+    ));
+    assert!(differential_verify(&claim, proof, 1).is_err());
+}
+
+#[test]
+fn lazy_queries_resolve_serialized_openings() {
+    let (_, proof) = gen_proof();
+    let queries = proof.trace_queries;
+    let mut bytes = Vec::new();
+    queries.serialize_compressed(&mut bytes).unwrap();
+
+    let lazy = LazyQueries::<FibClaim, _>::new(&*bytes, Compress::Yes, Validate::Yes).unwrap();
+    assert_eq!(queries.openings.len(), lazy.num_openings());
+    let mut openings = Vec::new();
+    let resolved = lazy.resolve(|opening| {
+        openings.push(opening);
+        Ok::<_, SerializationError>(())
+    });
+    assert!(resolved.is_ok());
+    assert_eq!(queries.openings, openings);
+
+    let truncated = &bytes[..bytes.len() / 2];
+    let truncated = LazyQueries::<FibClaim, _>::new(truncated, Compress::Yes, Validate::Yes);
+    assert!(truncated
+        .unwrap()
+        .resolve(|_| Ok::<_, SerializationError>(()))
+        .is_err());
+}
+
+#[test]
+fn reader_verifier_reads_proofs_one_at_a_time() {
+    let (claim, proof) = gen_proof();
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).unwrap();
+    proof.serialize_compressed(&mut bytes).unwrap();
+
+    assert!(claim
+        .verify_from_reader(bytes.as_slice(), Compress::Yes, 1)
+        .is_ok());
+    assert_eq!(
+        2,
+        verify_batch_from_reader(&claim, bytes.as_slice(), Compress::Yes, 2, 1)
+            .unwrap()
+            .len()
+    );
+    assert!(matches!(
+        claim.verify_from_reader(&bytes[..bytes.len() / 4], Compress::Yes, 1),
+        Err(VerificationError::MalformedProof { .. })
+    ));
+}
+
+#[test]
+fn reader_verifier_checks_ood_evals_before_reading_openings() {
+    let (claim, mut proof) = gen_proof();
+    proof.composition_trace_ood_evals[0] += Fp::one();
+    let mut bytes = Vec::new();
+    proof.header.serialize_compressed(&mut bytes).unwrap();
+    proof.trace_len.serialize_compressed(&mut bytes).unwrap();
+    proof
+        .base_trace_commitment
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    proof
+        .extension_trace_commitment
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    proof
+        .composition_trace_commitment
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    proof
+        .execution_trace_ood_evals
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+    proof
+        .composition_trace_ood_evals
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+
+    // the rest of the proof is never read
+    assert!(matches!(
+        claim.verify_from_reader(bytes.as_slice(), Compress::Yes, 1),
+        Err(VerificationError::InconsistentOodConstraintEvaluations)
+    ));
+}
+
+#[test]
+fn verifiers_check_air_version_compatibility() {
+    let (claim, mut proof) = gen_proof();
+    let mut incompatible_proof = proof.clone();
+    proof.header.air_version = AirVersion::new(0, 0, 1);
+    incompatible_proof.header.air_version = AirVersion::new(0, 1, 0);
+
+    assert!(differential_verify(&claim, proof, 1).is_ok());
+    assert!(differential_verify(&claim, incompatible_proof, 1).is_err());
+}
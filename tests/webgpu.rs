@@ -0,0 +1,82 @@
+#![cfg(feature = "webgpu")]
+#![feature(allocator_api)]
+use ark_ff::UniformRand;
+use ark_poly::EvaluationDomain;
+use ark_poly::Radix2EvaluationDomain;
+use ministark::hash::CanonicalLeafEncoder;
+use ministark::hash::Digest;
+use ministark::hash::HashFn;
+use ministark::hash::Sha256HashFn;
+use ministark::merkle::build_merkle_nodes;
+use ministark::merkle::hash_rows;
+use ministark::merkle::HashedLeafConfig;
+use ministark::merkle::MatrixMerkleTree;
+use ministark::merkle::MatrixMerkleTreeImpl;
+use ministark::merkle::MerkleTree;
+use ministark::utils::GpuAllocator;
+use ministark::Matrix;
+use ministark_gpu::fields::p18446744069414584321::ark::Fp;
+use ministark_gpu::webgpu::WebGpuContext;
+
+/// Returns `None` on machines without a WebGPU adapter
+fn context() -> Option<WebGpuContext> {
+    pollster::block_on(WebGpuContext::new())
+}
+
+#[test]
+fn fft_matches_cpu() {
+    let Some(context) = context() else {
+        return;
+    };
+    let mut rng = ark_std::test_rng();
+    let domain = Radix2EvaluationDomain::<Fp>::new(1024).unwrap();
+    let coeffs = (0..domain.size())
+        .map(|_| Fp::rand(&mut rng))
+        .collect::<Vec<Fp>>();
+    let mut evals = coeffs.clone();
+
+    pollster::block_on(context.fft(&mut evals, domain));
+
+    assert_eq!(domain.fft(&coeffs), evals);
+}
+
+#[test]
+fn merkle_nodes_match_cpu() {
+    let Some(context) = context() else {
+        return;
+    };
+    let leaves = (0..256u64)
+        .map(|i| Sha256HashFn::hash(i.to_be_bytes()))
+        .collect::<Vec<_>>();
+    let leaf_bytes = leaves
+        .iter()
+        .map(Digest::as_bytes)
+        .collect::<Vec<[u8; 32]>>();
+
+    let nodes = context.sha256_merkle_nodes_blocking(&leaf_bytes);
+
+    let expected = build_merkle_nodes::<HashedLeafConfig<Sha256HashFn>>(&leaves);
+    assert_eq!(expected.len(), nodes.len());
+    for (i, (expected, node)) in expected.iter().zip(&nodes).enumerate().skip(1) {
+        assert_eq!(expected.as_bytes(), *node, "node {i} is different");
+    }
+}
+
+#[test]
+fn installed_context_builds_trace_trees() {
+    let Some(context) = context() else {
+        return;
+    };
+    assert!(context.install().is_ok());
+    let mut rng = ark_std::test_rng();
+    let column = (0..1 << 13)
+        .map(|_| Fp::rand(&mut rng))
+        .collect::<Vec<Fp>>();
+    let matrix = Matrix::new(vec![column.to_vec_in(GpuAllocator)]);
+
+    let tree = MatrixMerkleTreeImpl::<Sha256HashFn>::from_matrix(&matrix);
+
+    let row_hashes = hash_rows::<Fp, Sha256HashFn, CanonicalLeafEncoder>(&matrix);
+    let nodes = build_merkle_nodes::<HashedLeafConfig<Sha256HashFn>>(&row_hashes);
+    assert_eq!(nodes[1], tree.root());
+}